@@ -0,0 +1,7 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Only generate the gRPC server when the `grpc` feature is enabled,
+    // so default builds don't need protoc
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/events.proto")?;
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use std::cmp::Ordering;
+
+use ethers::types::U256;
+use serde_json::Value;
+
+use crate::{
+    config::{AlertCondition, AlertOp, AlertRule},
+    core::resources::sink::DecodedEvent,
+};
+
+impl AlertRule {
+    /// Returns whether the rule fires for an event.
+    pub fn matches(&self, event: &DecodedEvent) -> bool {
+        if let Some(contract) = &self.contract {
+            let matches = event.contract == *contract
+                || event.contract.rsplit(':').next() == Some(contract.as_str())
+                || event.address.eq_ignore_ascii_case(contract);
+            if !matches {
+                return false;
+            }
+        }
+        if let Some(name) = &self.event {
+            if event.event != *name {
+                return false;
+            }
+        }
+        self.conditions
+            .iter()
+            .all(|condition| condition.holds(&event.data))
+    }
+}
+
+impl AlertCondition {
+    /// Returns whether the condition holds for the decoded event fields.
+    ///
+    /// Missing fields never satisfy a condition.
+    pub fn holds(&self, data: &Value) -> bool {
+        let actual = match data.get(&self.field) {
+            Some(Value::String(s)) => s.clone(),
+            Some(value) => value.to_string(),
+            None => return false,
+        };
+        let ordering = compare(&actual, &self.value);
+        match self.op {
+            AlertOp::Eq => ordering == Ordering::Equal,
+            AlertOp::Ne => ordering != Ordering::Equal,
+            AlertOp::Gt => ordering == Ordering::Greater,
+            AlertOp::Gte => ordering != Ordering::Less,
+            AlertOp::Lt => ordering == Ordering::Less,
+            AlertOp::Lte => ordering != Ordering::Greater,
+        }
+    }
+}
+
+/// Compares two values numerically when both are integers, and
+/// as case-insensitive strings otherwise, so addresses match
+/// regardless of checksum casing.
+fn compare(actual: &str, expected: &str) -> Ordering {
+    match (U256::from_dec_str(actual), U256::from_dec_str(expected)) {
+        (Ok(actual), Ok(expected)) => actual.cmp(&expected),
+        _ => actual.to_lowercase().cmp(&expected.to_lowercase()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn can_match_rules() {
+        let event = DecodedEvent {
+            block_number: 17_000_000,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 0,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            event: "Trade".to_owned(),
+            signature: "Trade(address,uint256)".to_owned(),
+            schema: None,
+            data: json!({
+                "amountIn": "150000000000000000",
+                "senderAddress": "0xe09b0e8b5370528ab4f84c278b229c670ad91f4f"
+            }),
+            raw: None,
+            ..Default::default()
+        };
+        let rule = |value| serde_json::from_value::<AlertRule>(value).unwrap();
+
+        assert!(rule(json!({})).matches(&event));
+        assert!(rule(json!({
+            "contract": "UniswapV2Router02",
+            "event": "Trade",
+            "conditions": [
+                { "field": "amountIn", "op": ">=", "value": "100000000000000000" },
+                { "field": "senderAddress", "op": "==", "value": "0xE09B0E8B5370528AB4F84C278B229C670AD91F4F" }
+            ]
+        }))
+        .matches(&event));
+        assert!(!rule(json!({
+            "conditions": [{ "field": "amountIn", "op": ">", "value": "150000000000000000" }]
+        }))
+        .matches(&event));
+        assert!(!rule(json!({
+            "conditions": [{ "field": "missing", "op": "!=", "value": "0" }]
+        }))
+        .matches(&event));
+        assert!(!rule(json!({ "event": "Swap" })).matches(&event));
+    }
+}
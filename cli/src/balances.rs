@@ -0,0 +1,346 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Mutex,
+};
+
+use ethers::{
+    providers::{JsonRpcClient, Middleware, Provider, ProviderError},
+    types::{
+        transaction::eip2718::TypedTransaction, BlockId, Bytes, Log, TransactionReceipt,
+        TransactionRequest, H160, H256, U256,
+    },
+    utils::keccak256,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::core::resources::sink::BalanceDiff;
+
+/// The selector of `balanceOf(address)`
+const BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+/// Records the ETH and ERC-20 balances of the accounts a transaction
+/// involves, so decoded events carry the value that moved with them.
+///
+/// ETH balances come from the `prestateTracer` of the transaction, in
+/// diff mode, so they are the balances right before and after it even
+/// if other transactions in the block touch the same accounts. Token
+/// balances are read at the end of the previous block and moved by the
+/// `Transfer` logs of the block, up to and including the transaction's.
+pub struct BalanceTracker {
+    /// The ERC-20 tokens to track, in addition to ETH
+    tokens: Vec<H160>,
+
+    /// The diffs of the last transaction, since a transaction can emit
+    /// the event several times
+    last: Mutex<Option<(H256, Vec<BalanceDiff>)>>,
+}
+
+impl BalanceTracker {
+    pub fn new(tokens: Vec<H160>) -> Self {
+        Self {
+            tokens,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Returns the balance changes of the accounts involved in the
+    /// transaction of the receipt.
+    pub async fn diffs<P: JsonRpcClient>(
+        &self,
+        provider: &Provider<P>,
+        receipt: &TransactionReceipt,
+    ) -> Result<Vec<BalanceDiff>, ProviderError> {
+        let last = self.last.lock().unwrap().clone();
+        if let Some((hash, diffs)) = last {
+            if hash == receipt.transaction_hash {
+                return Ok(diffs);
+            }
+        }
+        let Some(block_number) = receipt.block_number.map(|n| n.as_u64()) else {
+            return Ok(Vec::new());
+        };
+        let state: StateDiff = provider
+            .request(
+                "debug_traceTransaction",
+                (
+                    receipt.transaction_hash,
+                    json!({ "tracer": "prestateTracer", "tracerConfig": { "diffMode": true } }),
+                ),
+            )
+            .await?;
+        // The transfers of the transactions before this one in the block
+        let earlier_logs = match receipt.transaction_index.as_u64() {
+            index if index > 0 && !self.tokens.is_empty() => provider
+                .get_block_receipts(block_number)
+                .await?
+                .into_iter()
+                .filter(|r| r.transaction_index.as_u64() < index)
+                .flat_map(|r| r.logs)
+                .collect(),
+            _ => Vec::new(),
+        };
+        let previous_block = BlockId::from(block_number.saturating_sub(1));
+
+        let mut diffs = Vec::new();
+        for account in participants(receipt, &self.tokens) {
+            if let Some((eth_before, eth_after)) = state.eth_balances(account) {
+                diffs.extend(balance_diff(account, None, eth_before, eth_after));
+            }
+            for token in &self.tokens {
+                let balance = balance_of(provider, *token, account, previous_block).await?;
+                let token_before = apply_transfers(balance, &earlier_logs, *token, account);
+                let token_after = apply_transfers(token_before, &receipt.logs, *token, account);
+                diffs.extend(balance_diff(
+                    account,
+                    Some(*token),
+                    token_before,
+                    token_after,
+                ));
+            }
+        }
+        *self.last.lock().unwrap() = Some((receipt.transaction_hash, diffs.clone()));
+        Ok(diffs)
+    }
+}
+
+/// The accounts a transaction changed, as returned by the
+/// `prestateTracer` in diff mode. Only the fields that changed are in
+/// `post`.
+#[derive(Debug, Default, Deserialize)]
+pub struct StateDiff {
+    #[serde(default)]
+    pre: BTreeMap<H160, AccountBalance>,
+    #[serde(default)]
+    post: BTreeMap<H160, AccountBalance>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AccountBalance {
+    balance: Option<U256>,
+}
+
+impl StateDiff {
+    /// Returns the ETH balance of an account before and after the
+    /// transaction, or `None` if the transaction didn't touch it.
+    pub fn eth_balances(&self, account: H160) -> Option<(U256, U256)> {
+        let before = self.pre.get(&account).and_then(|a| a.balance);
+        let after = self.post.get(&account).and_then(|a| a.balance);
+        if before.is_none() && after.is_none() {
+            return None;
+        }
+        // Accounts the transaction created are only in `post`
+        let before = before.unwrap_or_default();
+        Some((before, after.unwrap_or(before)))
+    }
+}
+
+/// Returns the accounts a transaction involves: its sender, its
+/// recipient or the contract it created, and the senders and recipients
+/// of the transfers of the tracked tokens.
+pub fn participants(receipt: &TransactionReceipt, tokens: &[H160]) -> BTreeSet<H160> {
+    let transfer = H256(keccak256("Transfer(address,address,uint256)"));
+    let mut accounts = BTreeSet::new();
+    accounts.insert(receipt.from);
+    accounts.extend(receipt.to);
+    accounts.extend(receipt.contract_address);
+    for log in &receipt.logs {
+        if !tokens.contains(&log.address) || log.topics.first() != Some(&transfer) {
+            continue;
+        }
+        accounts.extend(log.topics.iter().skip(1).take(2).map(|t| H160::from(*t)));
+    }
+    accounts.remove(&H160::zero());
+    accounts
+}
+
+/// Moves a token balance of an account by the token's `Transfer` logs.
+pub fn apply_transfers(balance: U256, logs: &[Log], token: H160, account: H160) -> U256 {
+    let transfer = H256(keccak256("Transfer(address,address,uint256)"));
+    let account = H256::from(account);
+    logs.iter()
+        .filter(|log| log.address == token && log.topics.first() == Some(&transfer))
+        .filter(|log| log.topics.len() == 3 && log.data.len() == 32)
+        .fold(balance, |balance, log| {
+            let amount = U256::from_big_endian(&log.data);
+            let mut balance = balance;
+            if log.topics[1] == account {
+                balance = balance.saturating_sub(amount);
+            }
+            if log.topics[2] == account {
+                balance = balance.saturating_add(amount);
+            }
+            balance
+        })
+}
+
+/// Returns the diff of a balance, or `None` if it didn't change.
+/// `token` is `None` for ETH.
+pub fn balance_diff(
+    address: H160,
+    token: Option<H160>,
+    before: U256,
+    after: U256,
+) -> Option<BalanceDiff> {
+    if before == after {
+        return None;
+    }
+    let change = if after > before {
+        (after - before).to_string()
+    } else {
+        format!("-{}", before - after)
+    };
+    Some(BalanceDiff {
+        address: format!("{:?}", address),
+        token: token
+            .map(|token| format!("{:?}", token))
+            .unwrap_or_else(|| "ETH".to_owned()),
+        before: before.to_string(),
+        after: after.to_string(),
+        change,
+    })
+}
+
+/// Reads the ERC-20 balance of an account at a block.
+async fn balance_of<M: Middleware>(
+    provider: &M,
+    token: H160,
+    account: H160,
+    block: BlockId,
+) -> Result<U256, M::Error> {
+    let data = [&BALANCE_OF[..], H256::from(account).as_bytes()].concat();
+    let call: TypedTransaction = TransactionRequest::new()
+        .to(token)
+        .data(Bytes::from(data))
+        .into();
+    let output = provider.call(&call, Some(block)).await?;
+    // Tokens that don't implement balanceOf count as a zero balance
+    Ok(output
+        .get(..32)
+        .map(U256::from_big_endian)
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_find_participants() {
+        let token = H160::repeat_byte(0xaa);
+        let other_token = H160::repeat_byte(0xbb);
+        let transfer = |address, from: H160, to: H160| Log {
+            address,
+            topics: vec![
+                H256(keccak256("Transfer(address,address,uint256)")),
+                H256::from(from),
+                H256::from(to),
+            ],
+            ..Default::default()
+        };
+        let receipt = TransactionReceipt {
+            from: H160::repeat_byte(1),
+            to: Some(H160::repeat_byte(2)),
+            logs: vec![
+                transfer(token, H160::repeat_byte(2), H160::repeat_byte(3)),
+                transfer(token, H160::zero(), H160::repeat_byte(4)),
+                transfer(other_token, H160::repeat_byte(5), H160::repeat_byte(6)),
+            ],
+            ..Default::default()
+        };
+
+        let accounts = participants(&receipt, &[token]);
+        assert_eq!(
+            accounts.into_iter().collect::<Vec<_>>(),
+            vec![
+                H160::repeat_byte(1),
+                H160::repeat_byte(2),
+                H160::repeat_byte(3),
+                H160::repeat_byte(4),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_diff_balances() {
+        let address = H160::repeat_byte(1);
+        let token = H160::repeat_byte(0xaa);
+        assert_eq!(
+            balance_diff(address, None, U256::from(100), U256::from(100)),
+            None
+        );
+
+        let diff = balance_diff(address, None, U256::from(100), U256::from(40)).unwrap();
+        assert_eq!(diff.token, "ETH");
+        assert_eq!(diff.change, "-60");
+
+        let diff = balance_diff(address, Some(token), U256::zero(), U256::MAX).unwrap();
+        assert_eq!(diff.token, format!("{:?}", token));
+        assert_eq!(diff.change, U256::MAX.to_string());
+    }
+
+    #[test]
+    fn can_read_eth_balances_from_a_state_diff() {
+        let sender = H160::repeat_byte(1);
+        let recipient = H160::repeat_byte(2);
+        let created = H160::repeat_byte(3);
+        let untouched = H160::repeat_byte(4);
+        let state: StateDiff = serde_json::from_value(json!({
+            "pre": {
+                format!("{:?}", sender): { "balance": "0x64", "nonce": 1 },
+                format!("{:?}", recipient): { "balance": "0x0a", "storage": {} },
+            },
+            "post": {
+                format!("{:?}", sender): { "balance": "0x28", "nonce": 2 },
+                format!("{:?}", created): { "balance": "0x05" },
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            state.eth_balances(sender),
+            Some((U256::from(100), U256::from(40)))
+        );
+        // Only the nonce or storage changed
+        assert_eq!(
+            state.eth_balances(recipient),
+            Some((U256::from(10), U256::from(10)))
+        );
+        assert_eq!(
+            state.eth_balances(created),
+            Some((U256::zero(), U256::from(5)))
+        );
+        assert_eq!(state.eth_balances(untouched), None);
+    }
+
+    #[test]
+    fn can_apply_transfers() {
+        let token = H160::repeat_byte(0xaa);
+        let account = H160::repeat_byte(1);
+        let other = H160::repeat_byte(2);
+        let transfer = |address, from: H160, to: H160, amount: u64| Log {
+            address,
+            topics: vec![
+                H256(keccak256("Transfer(address,address,uint256)")),
+                H256::from(from),
+                H256::from(to),
+            ],
+            data: Bytes::from(H256::from_low_u64_be(amount).as_bytes().to_vec()),
+            ..Default::default()
+        };
+        // Two transfers from the same account in a block are applied in order
+        let logs = vec![
+            transfer(token, account, other, 30),
+            transfer(token, other, account, 5),
+            transfer(H160::repeat_byte(0xbb), account, other, 1_000),
+        ];
+        let before = apply_transfers(U256::from(100), &logs[..1], token, account);
+        let after = apply_transfers(before, &logs[1..], token, account);
+        assert_eq!(before, U256::from(70));
+        assert_eq!(after, U256::from(75));
+        assert_eq!(
+            apply_transfers(U256::zero(), &logs, token, other),
+            U256::from(25)
+        );
+    }
+}
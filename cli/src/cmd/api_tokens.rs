@@ -0,0 +1,39 @@
+use clap::Args;
+
+use crate::server::auth::ApiAuth;
+
+/// The bearer tokens the API servers of the long-running commands
+/// require, so a shared shadow fork isn't open to anyone on the network.
+///
+/// Prefer the environment variables, since flags show up in the
+/// process list.
+#[derive(Args, Clone, Debug, Default)]
+pub struct ApiTokens {
+    /// Accept this bearer token for reading: subscribing to and querying
+    /// events, listing contracts and the replay status
+    #[clap(
+        long = "api-read-token",
+        env = "SHADOW_API_READ_TOKEN",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    pub read_tokens: Vec<String>,
+
+    /// Accept this bearer token for every request, including reloading
+    /// contracts and replaying transactions on the fork
+    #[clap(
+        long = "api-admin-token",
+        env = "SHADOW_API_ADMIN_TOKEN",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    pub admin_tokens: Vec<String>,
+}
+
+impl ApiTokens {
+    /// Returns the authentication of the API servers. Without tokens,
+    /// every request is allowed.
+    pub fn auth(&self) -> ApiAuth {
+        ApiAuth::new(&self.read_tokens, &self.admin_tokens)
+    }
+}
@@ -0,0 +1,146 @@
+use clap::{Args, ValueEnum};
+use ethers::providers::Middleware;
+
+pub use crate::core::actions::attest::AttestError;
+use crate::{
+    config::Config,
+    core::{
+        actions::deploy::{DeployError, MetadataMode},
+        resources::shadow::ShadowResource,
+    },
+    providers,
+    resources::{
+        credentials,
+        explorer::Explorer,
+        fork_backend::{Backend, BackendForkRunner},
+        http::shared_client,
+    },
+};
+
+use super::paths::Paths;
+
+#[derive(Args)]
+pub struct Attest {
+    /// The address of the shadow contract to attest
+    pub address: String,
+
+    /// Upstream HTTP RPC endpoints, in order of preference.
+    ///
+    /// The first endpoint is used for the temporary fork.
+    /// Defaults to the ETH_RPC_URL environment variable.
+    #[clap(long = "rpc-url", env = "SHADOW_RPC_URL", value_delimiter = ',')]
+    pub rpc_urls: Vec<String>,
+
+    /// Number of upstream endpoints that must agree on each block header.
+    #[clap(long, default_value_t = 1)]
+    pub quorum: usize,
+
+    /// The engine the temporary fork runs on. revm reads upstream state
+    /// on demand, so it starts much faster than an anvil node.
+    #[clap(long, value_enum, default_value_t = Backend::Anvil)]
+    pub backend: Backend,
+
+    /// The Etherscan API key.
+    ///
+    /// If not set, the key is read from the OS keychain or the encrypted
+    /// credentials file (see `shadow auth set etherscan`).
+    #[clap(long, env = "ETHERSCAN_API_KEY", hide_env_values = true)]
+    pub etherscan_api_key: Option<String>,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Checks that a shadow contract's stored bytecode can be reproduced
+/// from the current sources.
+///
+/// The command uses the [`crate::core::actions::Attest`] action
+/// under the hood, using the local file-based artifact store,
+/// and the local file-based shadow store.
+impl Attest {
+    pub async fn run(&self) -> Result<(), AttestError> {
+        let rpc_urls = if self.rpc_urls.is_empty() {
+            vec![providers::rpc_url_from_env("ETH_RPC_URL")
+                .map_err(|e| AttestError::CustomError(e.to_string()))?]
+        } else {
+            self.rpc_urls.clone()
+        };
+        let http_rpc_url = rpc_urls[0].clone();
+
+        // Build the provider
+        let config = Config::load().map_err(|e| AttestError::CustomError(e.to_string()))?;
+        let provider = providers::connect_http(&rpc_urls, self.quorum, shared_client(&config.http))
+            .map_err(|e| AttestError::CustomError(e.to_string()))?;
+
+        // Build the resources
+        let artifacts_resource = self.paths.artifacts_resource(&config);
+        let chain_id = provider
+            .get_chainid()
+            .await
+            .map_err(DeployError::ProviderError)?
+            .as_u64();
+        let etherscan_api_key =
+            credentials::resolve(credentials::ETHERSCAN, self.etherscan_api_key.clone())
+                .map_err(|e| AttestError::CustomError(e.to_string()))?;
+        let etherscan_resource = Explorer::from_config(
+            config.explorer_for(chain_id),
+            &config.http,
+            etherscan_api_key,
+        )
+        .map_err(|e| AttestError::CustomError(e.to_string()))?;
+        let shadow_resource = self.paths.shadow_resource(&config);
+
+        // Get the stored shadow contract
+        let shadow_contract = shadow_resource
+            .get_by_address(&self.address.to_lowercase())
+            .await
+            .map_err(|e| AttestError::CustomError(e.to_string()))?;
+        let metadata = shadow_contract
+            .attestation
+            .as_ref()
+            .and_then(|attestation| MetadataMode::from_str(&attestation.metadata, true).ok())
+            .unwrap_or_default();
+
+        // Build the action
+        let attest = crate::core::actions::Attest {
+            deploy: crate::core::actions::Deploy {
+                file_name: shadow_contract.file_name.clone(),
+                contract_name: shadow_contract.contract_name.clone(),
+                address: shadow_contract.address.clone(),
+                provider,
+                artifacts_resource,
+                etherscan_resource,
+                shadow_resource,
+                fork_runner: BackendForkRunner::new(
+                    self.backend,
+                    http_rpc_url,
+                    providers::connect_http(&rpc_urls, self.quorum, shared_client(&config.http))
+                        .map_err(|e| AttestError::CustomError(e.to_string()))?,
+                ),
+                tags: Vec::new(),
+                enforce_size_limit: false,
+                metadata,
+                init_calls: Vec::new(),
+                smoke_test: None,
+            },
+            shadow_contract,
+        };
+
+        // Run the action
+        let report = attest.run().await?;
+        if report.is_reproducible() {
+            println!("{} is reproducible from the current sources", self.address);
+            return Ok(());
+        }
+        for mismatch in &report.mismatches {
+            println!(
+                "{}: recorded {}, current {}",
+                mismatch.field, mismatch.recorded, mismatch.current
+            );
+        }
+        Err(AttestError::CustomError(format!(
+            "{} is not reproducible from the current sources",
+            self.address
+        )))
+    }
+}
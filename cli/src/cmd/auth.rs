@@ -0,0 +1,115 @@
+use clap::{Args, Subcommand, ValueEnum};
+use thiserror::Error;
+
+use crate::{
+    core::resources::credentials::CredentialsResource,
+    resources::credentials::{self, EncryptedFileCredentialStore, PASSPHRASE_ENV},
+};
+
+#[derive(Args)]
+pub struct Auth {
+    #[command(subcommand)]
+    pub command: AuthCommand,
+}
+
+#[derive(Subcommand)]
+pub enum AuthCommand {
+    /// Store a credential
+    Set(AuthSet),
+}
+
+#[derive(Args)]
+pub struct AuthSet {
+    /// The credential to store
+    #[clap(value_enum)]
+    pub name: Credential,
+
+    /// The name of the secret, referenced as `credential:<key>` in
+    /// shadow.config.json
+    #[clap(long, required_if_eq("name", "secret"))]
+    pub key: Option<String>,
+
+    /// Where to store the credential
+    #[clap(long, value_enum, default_value_t = CredentialStore::File)]
+    pub store: CredentialStore,
+}
+
+/// The credentials the CLI knows about
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Credential {
+    /// The Etherscan API key
+    Etherscan,
+    /// The private key of the signer of post-deploy transactions
+    Signer,
+    /// A secret of shadow.config.json, e.g. a notifier's webhook URL
+    Secret,
+}
+
+/// The available credential stores
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CredentialStore {
+    /// The encrypted `~/.shadow/credentials` file
+    File,
+    /// The OS keychain (requires the `keychain` feature)
+    Keychain,
+}
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+}
+
+/// Manages the credentials used by the CLI.
+impl Auth {
+    pub async fn run(&self) -> Result<(), AuthError> {
+        match &self.command {
+            AuthCommand::Set(set) => set.run(),
+        }
+    }
+}
+
+impl AuthSet {
+    pub fn run(&self) -> Result<(), AuthError> {
+        let (name, kind) = match self.name {
+            Credential::Etherscan => (credentials::ETHERSCAN, "API key"),
+            Credential::Signer => (credentials::SIGNER, "private key"),
+            Credential::Secret => (self.key.as_deref().unwrap_or_default(), "secret"),
+        };
+        let value = rpassword::prompt_password(format!("Enter the {} {}: ", name, kind))
+            .map_err(|e| AuthError::CustomError(e.to_string()))?;
+
+        let store = self.build_store()?;
+        store
+            .set(name, value.trim())
+            .map_err(|e| AuthError::CustomError(e.to_string()))?;
+        println!("Stored the {} {}", name, kind);
+
+        Ok(())
+    }
+
+    fn build_store(&self) -> Result<Box<dyn CredentialsResource>, AuthError> {
+        match self.store {
+            CredentialStore::File => {
+                let path = EncryptedFileCredentialStore::default_path().ok_or_else(|| {
+                    AuthError::CustomError("Could not find the home directory".to_owned())
+                })?;
+                let passphrase = match std::env::var(PASSPHRASE_ENV) {
+                    Ok(passphrase) => passphrase,
+                    Err(_) => rpassword::prompt_password("Enter the credentials passphrase: ")
+                        .map_err(|e| AuthError::CustomError(e.to_string()))?,
+                };
+                Ok(Box::new(EncryptedFileCredentialStore::new(
+                    path, passphrase,
+                )))
+            }
+            #[cfg(feature = "keychain")]
+            CredentialStore::Keychain => Ok(Box::new(credentials::KeychainCredentialStore::new())),
+            #[cfg(not(feature = "keychain"))]
+            CredentialStore::Keychain => Err(AuthError::CustomError(
+                "The CLI was built without the `keychain` feature".to_owned(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::Args;
+
+pub use crate::core::actions::backup::BackupError;
+use crate::{
+    config::Config,
+    core::resources::backup::BackupStore,
+    resources::{backup::LocalBackupStore, shadow::SHADOW_FILE, sinks::jsonl::rotated_files},
+};
+
+use super::{cancel, paths::Paths};
+
+#[derive(Args)]
+pub struct Backup {
+    /// Where to store the backups: a directory, or an s3://bucket/prefix
+    /// or gs://bucket/prefix URL with the archive feature
+    #[clap(long, env = "SHADOW_BACKUP_TO")]
+    pub to: String,
+
+    /// The JSONL event archive to back up, with its rotated files
+    #[clap(long, env = "SHADOW_ARCHIVE", value_delimiter = ',')]
+    pub archive: Vec<PathBuf>,
+
+    /// Back up every this many seconds, until stopped
+    #[clap(long, env = "SHADOW_BACKUP_EVERY")]
+    pub every: Option<u64>,
+
+    /// Keep at most this many backups, deleting the oldest
+    #[clap(
+        long,
+        env = "SHADOW_BACKUP_RETAIN",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub retain: Option<usize>,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Backs up `shadow.json` and the event archive.
+///
+/// The command uses the [`crate::core::actions::Backup`] action
+/// under the hood, storing the backups in a local directory or a bucket.
+impl Backup {
+    pub async fn run(&self) -> Result<(), BackupError> {
+        // Build the action
+        let config = Config::load().map_err(|e| BackupError::CustomError(e.to_string()))?;
+        let mut backup = crate::core::actions::Backup {
+            store: open_store(&self.to)?,
+            shadow_file: self.paths.store_path(&config).join(SHADOW_FILE),
+            archive_files: Vec::new(),
+            retain: self.retain,
+        };
+
+        // Run the action, once or on a schedule
+        let cancel = cancel::on_ctrl_c();
+        loop {
+            // Rotated archive files come and go between backups
+            let result = match self.archive_files() {
+                Ok(archive_files) => {
+                    backup.archive_files = archive_files;
+                    backup.run().await
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(report) => {
+                    println!(
+                        "Backed up {} files to {} in {}",
+                        report.manifest.files.len(),
+                        report.name,
+                        self.to
+                    );
+                    for name in report.pruned {
+                        println!("Deleted {}", name);
+                    }
+                }
+                // A scheduled backup tries again at the next interval
+                Err(e) if self.every.is_some() => log::warn!("Error backing up: {}", e),
+                Err(e) => return Err(e),
+            }
+
+            let Some(every) = self.every else {
+                return Ok(());
+            };
+            tokio::select! {
+                _ = cancel.cancelled() => return Ok(()),
+                _ = tokio::time::sleep(Duration::from_secs(every)) => {}
+            }
+        }
+    }
+
+    /// Returns the archive files, with their rotated files.
+    fn archive_files(&self) -> Result<Vec<PathBuf>, BackupError> {
+        let mut files = Vec::new();
+        for archive in &self.archive {
+            files.extend(rotated_files(archive).map_err(|e| {
+                BackupError::CustomError(format!("Error listing {}: {}", archive.display(), e))
+            })?);
+            if archive.exists() {
+                files.push(archive.clone());
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Opens the backup store at a directory or bucket URL.
+pub fn open_store(location: &str) -> Result<Box<dyn BackupStore>, BackupError> {
+    if location.starts_with("s3://") || location.starts_with("gs://") {
+        #[cfg(feature = "archive")]
+        return crate::resources::backup::ObjectStoreBackup::new(location)
+            .map(|store| Box::new(store) as Box<dyn BackupStore>)
+            .map_err(|e| BackupError::CustomError(format!("Error opening {}: {}", location, e)));
+        #[cfg(not(feature = "archive"))]
+        return Err(BackupError::CustomError(
+            "shadow was built without the archive feature".to_owned(),
+        ));
+    }
+    Ok(Box::new(LocalBackupStore::new(location)))
+}
@@ -0,0 +1,87 @@
+use clap::Args;
+
+pub use crate::core::actions::fork::ForkError;
+use crate::core::actions::fork::{FeeMode, ReplayOptions};
+use crate::{config::Config, providers};
+
+use super::{cancel, paths::Paths};
+
+#[derive(Args)]
+pub struct Bench {
+    /// The number of recent blocks to replay
+    #[clap(long, default_value_t = 10)]
+    pub blocks: u64,
+
+    /// Whether to replay all transactions from mainnet, instead of only
+    /// those sent to shadow contracts
+    #[clap(short, long)]
+    pub all_txs: bool,
+
+    /// Upstream websocket RPC endpoints, in order of preference.
+    ///
+    /// Defaults to the WS_RPC_URL environment variable.
+    #[clap(long = "ws-rpc-url", env = "SHADOW_WS_RPC_URL", value_delimiter = ',')]
+    pub ws_rpc_urls: Vec<String>,
+
+    /// How transaction fees are handled during replay
+    #[clap(long, value_enum, default_value_t = FeeMode::Mainnet)]
+    pub fee_mode: FeeMode,
+
+    /// Only load the shadow contracts with any of these tags
+    #[clap(long = "tag")]
+    pub tags: Vec<String>,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Measures the replay throughput of a shadow fork.
+///
+/// This command uses the [`crate::core::actions::Fork`] action
+/// under the hood, replaying a recent block range instead of
+/// following new blocks.
+impl Bench {
+    pub async fn run(&self) -> Result<(), ForkError> {
+        let http_rpc_url =
+            providers::rpc_url_from_env("ETH_RPC_URL").map_err(ForkError::ProviderError)?;
+
+        // Build the provider
+        let ws_rpc_urls = if self.ws_rpc_urls.is_empty() {
+            vec![providers::rpc_url_from_env("WS_RPC_URL").map_err(ForkError::ProviderError)?]
+        } else {
+            self.ws_rpc_urls.clone()
+        };
+        let provider = providers::connect_ws(&ws_rpc_urls, 1)
+            .await
+            .map_err(ForkError::ProviderError)?;
+
+        // Build the resources
+        let config = Config::load().map_err(|e| ForkError::CustomError(e.to_string()))?;
+        let shadow_resource = self.paths.shadow_resource(&config);
+
+        // Build the action
+        let mut fork = crate::core::actions::Fork::new(
+            provider,
+            shadow_resource,
+            http_rpc_url,
+            ReplayOptions {
+                all_txs: self.all_txs,
+                fee_mode: self.fee_mode,
+                rpc_timeout: config.timeouts.rpc(),
+                ..Default::default()
+            },
+        )
+        .await?;
+        fork.retain_tagged(&self.tags);
+        fork.cancel = cancel::on_ctrl_c();
+
+        // Run the action
+        let report = fork.bench(self.blocks).await?;
+        println!("{}", report);
+        for hint in report.hints() {
+            println!("Hint: {}", hint);
+        }
+
+        Ok(())
+    }
+}
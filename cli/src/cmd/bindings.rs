@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::config::Config;
+pub use crate::core::actions::bindings::BindingsError;
+use crate::core::actions::bindings::Language;
+
+use super::paths::Paths;
+
+#[derive(Args)]
+pub struct Bindings {
+    /// The directory to write the bindings module to, e.g. src/shadow
+    #[clap(long, short, default_value = "bindings")]
+    pub output: PathBuf,
+
+    /// The language to generate the bindings in
+    #[clap(long, value_enum, default_value_t = Language::Rust)]
+    pub language: Language,
+
+    /// The RPC URL of the shadow fork the bindings connect to
+    #[clap(long, default_value = "http://localhost:8545")]
+    pub rpc_url: String,
+
+    /// Only generate bindings for the shadow contracts with any of these tags
+    #[clap(long = "tag")]
+    pub tags: Vec<String>,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Generates a Rust or TypeScript module with typed bindings for the
+/// shadow contracts, pointed at their shadowed addresses on the shadow
+/// fork.
+///
+/// The command uses the [`crate::core::actions::Bindings`] action
+/// under the hood.
+impl Bindings {
+    pub async fn run(&self) -> Result<(), BindingsError> {
+        // Build the resources
+        let config = Config::load().map_err(|e| BindingsError::CustomError(e.to_string()))?;
+        let artifacts_resource = self.paths.artifacts_resource(&config);
+        let shadow_resource = self.paths.shadow_resource(&config);
+
+        // Build the action
+        let bindings = crate::core::actions::Bindings {
+            tags: self.tags.clone(),
+            fork_url: self.rpc_url.clone(),
+            language: self.language,
+            artifacts_resource,
+            shadow_resource,
+        };
+
+        // Run the action
+        let files = bindings.run().await?;
+        std::fs::create_dir_all(&self.output).map_err(|e| {
+            BindingsError::CustomError(format!("Error creating {}: {}", self.output.display(), e))
+        })?;
+        for file in &files {
+            let path = self.output.join(&file.path);
+            std::fs::write(&path, &file.contents).map_err(|e| {
+                BindingsError::CustomError(format!("Error writing {}: {}", path.display(), e))
+            })?;
+            println!("Wrote {}", path.display());
+        }
+
+        Ok(())
+    }
+}
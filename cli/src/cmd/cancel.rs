@@ -0,0 +1,16 @@
+use tokio_util::sync::CancellationToken;
+
+/// Returns a token that is cancelled when Ctrl-C is pressed, so long
+/// running actions can stop between network calls instead of being
+/// killed mid-operation.
+pub fn on_ctrl_c() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            log::info!("Received Ctrl-C, stopping");
+            token.cancel();
+        }
+    });
+    cancel
+}
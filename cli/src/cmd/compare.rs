@@ -0,0 +1,153 @@
+use clap::Args;
+use ethers::providers::{JsonRpcClient, Middleware, PubsubClient};
+
+pub use crate::core::actions::fork::ForkError;
+use crate::core::actions::fork::{CompareReport, FeeMode, ReplayOptions};
+use crate::resources::shadow::LocalShadowStore;
+use crate::{config::Config, providers};
+
+use super::{cancel, paths::Paths};
+
+#[derive(Args)]
+pub struct Compare {
+    /// The directory containing the shadow.json of the candidate shadow
+    /// contracts, compared against the current store
+    #[clap(long)]
+    pub candidate_store: String,
+
+    /// The first block to replay [default: --blocks before the latest]
+    #[clap(long)]
+    pub from_block: Option<u64>,
+
+    /// The last block to replay [default: the latest block]
+    #[clap(long)]
+    pub to_block: Option<u64>,
+
+    /// The number of recent blocks to replay, without --from-block
+    #[clap(long, default_value_t = 10)]
+    pub blocks: u64,
+
+    /// Whether to replay all transactions from mainnet, instead of only
+    /// those sent to shadow contracts
+    #[clap(short, long)]
+    pub all_txs: bool,
+
+    /// Upstream websocket RPC endpoints, in order of preference.
+    ///
+    /// Defaults to the WS_RPC_URL environment variable.
+    #[clap(long = "ws-rpc-url", env = "SHADOW_WS_RPC_URL", value_delimiter = ',')]
+    pub ws_rpc_urls: Vec<String>,
+
+    /// How transaction fees are handled during replay
+    #[clap(long, value_enum, default_value_t = FeeMode::Mainnet)]
+    pub fee_mode: FeeMode,
+
+    /// Exit with an error if the shadow events differ
+    #[clap(long)]
+    pub fail_on_diff: bool,
+
+    /// Only load the shadow contracts with any of these tags
+    #[clap(long = "tag")]
+    pub tags: Vec<String>,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Replays the same blocks against the current and the candidate shadow
+/// contracts, in two forks running side by side, and diffs the shadow
+/// events they emit.
+///
+/// This command uses the [`crate::core::actions::Fork`] action under
+/// the hood, once per version of the shadow contracts.
+impl Compare {
+    pub async fn run(&self) -> Result<(), ForkError> {
+        let http_rpc_url =
+            providers::rpc_url_from_env("ETH_RPC_URL").map_err(ForkError::ProviderError)?;
+        let ws_rpc_urls = if self.ws_rpc_urls.is_empty() {
+            vec![providers::rpc_url_from_env("WS_RPC_URL").map_err(ForkError::ProviderError)?]
+        } else {
+            self.ws_rpc_urls.clone()
+        };
+
+        // Build the resources
+        let config = Config::load().map_err(|e| ForkError::CustomError(e.to_string()))?;
+        let artifacts_resource = self.paths.artifacts_resource(&config);
+        let cancel = cancel::on_ctrl_c();
+
+        // Build the actions
+        let mut forks = Vec::new();
+        for shadow_resource in [
+            self.paths.shadow_resource(&config),
+            LocalShadowStore::new(self.candidate_store.clone()),
+        ] {
+            let provider = providers::connect_ws(&ws_rpc_urls, 1)
+                .await
+                .map_err(ForkError::ProviderError)?;
+            let mut fork = crate::core::actions::Fork::new(
+                provider,
+                shadow_resource,
+                http_rpc_url.clone(),
+                ReplayOptions {
+                    all_txs: self.all_txs,
+                    fee_mode: self.fee_mode,
+                    rpc_timeout: config.timeouts.rpc(),
+                    // Both forks run at once, so neither can take anvil's
+                    // default port
+                    port: Some(0),
+                    ..Default::default()
+                },
+            )
+            .await?;
+            fork.retain_tagged(&self.tags);
+            fork.cancel = cancel.clone();
+            // Events are named by topic0 when the ABIs can't be loaded
+            if let Err(e) = fork.load_abis(&artifacts_resource) {
+                log::warn!("Error loading ABIs to name shadow events: {}", e);
+            }
+            forks.push(fork);
+        }
+        let (first, last) = self.block_range(&forks[0]).await?;
+
+        // Run the actions
+        println!("Comparing blocks {} to {}", first, last);
+        let (baseline, candidate) = tokio::try_join!(
+            forks[0].replay_range(first, last),
+            forks[1].replay_range(first, last)
+        )?;
+        let report = CompareReport::new(&baseline, &candidate);
+        for diff in &report.diffs {
+            println!("{}", diff);
+        }
+        println!("{}", report);
+
+        if self.fail_on_diff && !report.is_identical() {
+            return Err(ForkError::CustomError(format!(
+                "Shadow events differ in {} transactions",
+                report.diffs.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the first and last block to replay.
+    async fn block_range<P: JsonRpcClient + PubsubClient>(
+        &self,
+        fork: &crate::core::actions::Fork<P>,
+    ) -> Result<(u64, u64), ForkError> {
+        let last = match self.to_block {
+            Some(last) => last,
+            None => fork.provider.get_block_number().await?.as_u64(),
+        };
+        let first = self
+            .from_block
+            .unwrap_or_else(|| last.saturating_sub(self.blocks.saturating_sub(1)));
+        if first > last {
+            return Err(ForkError::CustomError(format!(
+                "--from-block {} is after the last block {}",
+                first, last
+            )));
+        }
+        Ok((first, last))
+    }
+}
@@ -1,12 +1,25 @@
-use std::env;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
 use clap::Args;
+use ethers::providers::Middleware;
 
-pub use crate::core::actions::deploy::DeployError;
-use crate::resources::{
-    artifacts::LocalArtifactStore, etherscan::Etherscan, shadow::LocalShadowStore,
+use crate::core::actions::deploy::InitManifest;
+pub use crate::core::actions::deploy::{DeployError, MetadataMode};
+use crate::{
+    config::Config,
+    providers,
+    resources::{
+        credentials,
+        explorer::Explorer,
+        fork_backend::{Backend, BackendForkRunner},
+        http::shared_client,
+    },
 };
-use ethers::providers::{Http, Provider};
+
+use super::paths::Paths;
 
 #[derive(Args)]
 pub struct Deploy {
@@ -15,8 +28,71 @@ pub struct Deploy {
     /// Can either be in the form ContractFile.sol (if the filename and contract name are the same), or ContractFile.sol:ContractName.
     pub contract: String,
 
-    /// The address of the shadow contract to deploy
-    pub address: String,
+    /// The addresses of the shadow contract to deploy
+    ///
+    /// The same contract can be shadowed at many addresses. Each instance
+    /// replays its own creation transaction, so immutables stay per-instance.
+    #[clap(required_unless_present = "addresses_file")]
+    pub addresses: Vec<String>,
+
+    /// A file with more addresses to deploy the shadow contract to,
+    /// one per line. Blank lines and lines starting with `#` are ignored.
+    #[clap(long)]
+    pub addresses_file: Option<PathBuf>,
+
+    /// Upstream HTTP RPC endpoints, in order of preference.
+    ///
+    /// If an endpoint fails, the deploy fails over to the next one.
+    /// The first endpoint is used for the temporary fork.
+    /// Defaults to the ETH_RPC_URL environment variable.
+    #[clap(long = "rpc-url", env = "SHADOW_RPC_URL", value_delimiter = ',')]
+    pub rpc_urls: Vec<String>,
+
+    /// Number of upstream endpoints that must agree on each block header.
+    #[clap(long, default_value_t = 1)]
+    pub quorum: usize,
+
+    /// The Etherscan API key.
+    ///
+    /// If not set, the key is read from the OS keychain or the encrypted
+    /// credentials file (see `shadow auth set etherscan`).
+    #[clap(long, env = "ETHERSCAN_API_KEY", hide_env_values = true)]
+    pub etherscan_api_key: Option<String>,
+
+    /// Tags to add to the shadow contract, e.g. defi
+    #[clap(long = "tag")]
+    pub tags: Vec<String>,
+
+    /// The engine the temporary fork runs on. revm reads upstream state
+    /// on demand, so it starts much faster than an anvil node.
+    #[clap(long, value_enum, default_value_t = Backend::Anvil)]
+    pub backend: Backend,
+
+    /// Fail if the shadow runtime code exceeds the 24KB EIP-170 limit,
+    /// which the shadow fork doesn't enforce
+    #[clap(long)]
+    pub enforce_size_limit: bool,
+
+    /// What to do with the compiler metadata hash at the end of the
+    /// runtime bytecode. Strip or zero it so identical sources always
+    /// produce identical stored bytecode.
+    #[clap(long, value_enum, default_value_t = MetadataMode::Keep)]
+    pub metadata: MetadataMode,
+
+    /// A JSON file of calls to make on the temporary fork after the
+    /// shadow contract is created, for contracts whose constructors
+    /// don't fully configure them
+    #[clap(long)]
+    pub init_calls: Option<PathBuf>,
+
+    /// Replay this historical transaction against the shadow contract on
+    /// a temporary fork, and print the shadow events it emits, before
+    /// storing the contract
+    #[clap(long)]
+    pub smoke_test: Option<String>,
+
+    #[clap(flatten)]
+    pub paths: Paths,
 }
 
 /// Deploys a shadow contract to a local fork.
@@ -26,45 +102,120 @@ pub struct Deploy {
 /// and the local file-based shadow store.
 impl Deploy {
     pub async fn run(&self) -> Result<(), DeployError> {
-        let http_rpc_url = env!("ETH_RPC_URL", "Please set an ETH_RPC_URL").to_owned();
+        let rpc_urls = if self.rpc_urls.is_empty() {
+            vec![providers::rpc_url_from_env("ETH_RPC_URL").map_err(DeployError::ProviderError)?]
+        } else {
+            self.rpc_urls.clone()
+        };
+        let http_rpc_url = rpc_urls[0].clone();
 
         // Parse the contract string
         let (file_name, contract_name) = parse_contract_string(&self.contract);
 
         // Build the provider
+        let config = Config::load().map_err(|e| DeployError::CustomError(e.to_string()))?;
         let provider =
-            Provider::<Http>::try_from(&http_rpc_url).expect("Please set a valid ETH_RPC_URL");
+            providers::connect_http(&rpc_urls, self.quorum, shared_client(&config.http))?;
 
         // Build the resources
-        let artifacts_resource = LocalArtifactStore::new("contracts/out".to_owned());
-        let etherscan_resource = Etherscan::new(String::from(env!(
-            "ETHERSCAN_API_KEY",
-            "Please set an ETHERSCAN_API_KEY"
-        )));
-        let shadow_resource = LocalShadowStore::new(
-            env::current_dir()
-                .unwrap()
-                .as_path()
-                .to_str()
-                .unwrap()
-                .to_owned(),
-        );
-
-        let deploy = crate::core::actions::Deploy {
-            file_name,
-            contract_name,
-            address: self.address.clone(),
-            provider,
-            artifacts_resource,
-            etherscan_resource,
-            shadow_resource,
-            http_rpc_url,
+        let chain_id = provider.get_chainid().await?.as_u64();
+        let etherscan_api_key =
+            credentials::resolve(credentials::ETHERSCAN, self.etherscan_api_key.clone())
+                .map_err(|e| DeployError::CustomError(e.to_string()))?;
+        let addresses = self.addresses()?;
+        let init_calls = match &self.init_calls {
+            Some(path) => {
+                InitManifest::load(path)
+                    .map_err(DeployError::CustomError)?
+                    .calls
+            }
+            None => Vec::new(),
         };
 
-        deploy.run().await?;
+        // Deploy every instance, and report the ones that failed at the end
+        let mut failed = Vec::new();
+        for address in addresses {
+            let etherscan_resource = Explorer::from_config(
+                config.explorer_for(chain_id),
+                &config.http,
+                etherscan_api_key.clone(),
+            )
+            .map_err(|e| DeployError::CustomError(e.to_string()))?;
+            let deploy = crate::core::actions::Deploy {
+                file_name: file_name.clone(),
+                contract_name: contract_name.clone(),
+                address: address.clone(),
+                provider: providers::connect_http(
+                    &rpc_urls,
+                    self.quorum,
+                    shared_client(&config.http),
+                )?,
+                artifacts_resource: self.paths.artifacts_resource(&config),
+                etherscan_resource,
+                shadow_resource: self.paths.shadow_resource(&config),
+                fork_runner: BackendForkRunner::new(
+                    self.backend,
+                    http_rpc_url.clone(),
+                    providers::connect_http(&rpc_urls, self.quorum, shared_client(&config.http))?,
+                ),
+                tags: self.tags.clone(),
+                enforce_size_limit: self.enforce_size_limit,
+                metadata: self.metadata,
+                init_calls: init_calls.clone(),
+                smoke_test: self.smoke_test.clone(),
+            };
+
+            match deploy.run().await {
+                Ok(report) => {
+                    println!("{}: {}", address, report.code_size);
+                    if let Some(smoke_test) = report.smoke_test {
+                        println!("{}: {}", address, smoke_test);
+                    }
+                }
+                Err(e) => {
+                    println!("{}: {}", address, e);
+                    failed.push(address);
+                }
+            }
+        }
+
+        if !failed.is_empty() {
+            return Err(DeployError::CustomError(format!(
+                "Failed to deploy {} instance(s): {}",
+                failed.len(),
+                failed.join(", ")
+            )));
+        }
 
         Ok(())
     }
+
+    /// Returns the addresses given on the command line, followed by
+    /// the ones in the addresses file, without duplicates.
+    fn addresses(&self) -> Result<Vec<String>, DeployError> {
+        let mut addresses = self.addresses.clone();
+        if let Some(path) = &self.addresses_file {
+            addresses.extend(read_address_list(path).map_err(|e| {
+                DeployError::CustomError(format!(
+                    "Error reading addresses file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?);
+        }
+        Ok(unique_addresses(addresses))
+    }
+}
+
+/// Removes repeated addresses, keeping the first occurrence of each.
+/// Addresses are compared case-insensitively, so a checksummed and a
+/// lowercase address are the same.
+fn unique_addresses(addresses: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    addresses
+        .into_iter()
+        .filter(|address| seen.insert(address.to_lowercase()))
+        .collect()
 }
 
 /// Parses the contract string into a file name and contract name.
@@ -85,6 +236,24 @@ pub fn parse_contract_string(contract: &str) -> (String, String) {
     (file_name, contract_name)
 }
 
+/// Reads a list of addresses, one per line.
+///
+/// Surrounding whitespace is trimmed, and blank lines and lines starting
+/// with `#` are skipped.
+pub fn read_address_list(path: &Path) -> Result<Vec<String>, std::io::Error> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(parse_address_list(&contents))
+}
+
+fn parse_address_list(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_owned)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -99,4 +268,33 @@ mod tests {
         assert_eq!(file_name, String::from("UniswapV2Router02.sol"));
         assert_eq!(contract_name, String::from("UniswapV2Router02"));
     }
+
+    #[test]
+    fn can_parse_address_list() {
+        let contents = "# pools\n0x8ad599c3a0ff1de082011efddc58f1908eb6e6d8\n\n  0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640  \n";
+        assert_eq!(
+            super::parse_address_list(contents),
+            vec![
+                "0x8ad599c3a0ff1de082011efddc58f1908eb6e6d8".to_owned(),
+                "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn removes_repeated_addresses() {
+        let addresses = vec![
+            "0x8ad599c3a0ff1de082011efddc58f1908eb6e6d8".to_owned(),
+            "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640".to_owned(),
+            "0x8AD599C3A0FF1DE082011EFDDC58F1908EB6E6D8".to_owned(),
+            "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640".to_owned(),
+        ];
+        assert_eq!(
+            super::unique_addresses(addresses),
+            vec![
+                "0x8ad599c3a0ff1de082011efddc58f1908eb6e6d8".to_owned(),
+                "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640".to_owned(),
+            ]
+        );
+    }
 }
@@ -0,0 +1,213 @@
+use std::str::FromStr;
+
+use clap::Args;
+
+pub use crate::core::actions::discover::DiscoverError;
+use crate::{
+    config::Config,
+    core::resources::{artifacts::ArtifactsResource, shadow::ShadowResource},
+    providers,
+    resources::{fork_backend::Backend, http::shared_client},
+};
+
+use super::{
+    deploy::{parse_contract_string, Deploy, MetadataMode},
+    paths::Paths,
+};
+
+#[derive(Args)]
+pub struct Discover {
+    /// The shadow contract to register the children as
+    ///
+    /// Can either be in the form ContractFile.sol (if the filename and contract name are the same), or ContractFile.sol:ContractName.
+    pub contract: String,
+
+    /// The address of the factory that created the children
+    #[clap(long)]
+    pub factory: String,
+
+    /// The factory event emitted for each child, by name or signature,
+    /// e.g. PoolCreated
+    #[clap(long)]
+    pub event: String,
+
+    /// The event parameter holding the child address. Defaults to the
+    /// last address parameter of the event.
+    #[clap(long)]
+    pub param: Option<String>,
+
+    /// The factory's contract, used to look up the event ABI.
+    /// Defaults to the factory's entry in shadow.json.
+    #[clap(long)]
+    pub factory_contract: Option<String>,
+
+    /// The first block to scan, e.g. the factory's deployment block
+    #[clap(long, default_value_t = 0)]
+    pub from_block: u64,
+
+    /// The last block to scan. Defaults to the latest block.
+    #[clap(long)]
+    pub to_block: Option<u64>,
+
+    /// How many blocks each eth_getLogs query covers
+    #[clap(long, default_value_t = 10_000)]
+    pub chunk_size: u64,
+
+    /// Only print the children, without deploying them
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Upstream HTTP RPC endpoints, in order of preference.
+    ///
+    /// Defaults to the ETH_RPC_URL environment variable.
+    #[clap(long = "rpc-url", env = "SHADOW_RPC_URL", value_delimiter = ',')]
+    pub rpc_urls: Vec<String>,
+
+    /// Number of upstream endpoints that must agree on each block header.
+    #[clap(long, default_value_t = 1)]
+    pub quorum: usize,
+
+    /// The engine the temporary deploy forks run on
+    #[clap(long, value_enum, default_value_t = Backend::Anvil)]
+    pub backend: Backend,
+
+    /// The Etherscan API key, used to deploy the children.
+    #[clap(long, env = "ETHERSCAN_API_KEY", hide_env_values = true)]
+    pub etherscan_api_key: Option<String>,
+
+    /// Tags to add to the children, e.g. defi
+    #[clap(long = "tag")]
+    pub tags: Vec<String>,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Finds the children a factory created and deploys them as
+/// instances of a shadow contract.
+///
+/// The command uses the [`crate::core::actions::Discover`] action
+/// to scan the factory's events, then deploys every new child like
+/// `shadow deploy` does.
+impl Discover {
+    pub async fn run(&self) -> Result<(), DiscoverError> {
+        let rpc_urls = if self.rpc_urls.is_empty() {
+            vec![providers::rpc_url_from_env("ETH_RPC_URL").map_err(DiscoverError::ProviderError)?]
+        } else {
+            self.rpc_urls.clone()
+        };
+        let factory = ethers::types::H160::from_str(&self.factory)
+            .map_err(|e| DiscoverError::CustomError(format!("Invalid factory address: {}", e)))?;
+
+        // Build the provider
+        let config = Config::load().map_err(|e| DiscoverError::CustomError(e.to_string()))?;
+        let provider =
+            providers::connect_http(&rpc_urls, self.quorum, shared_client(&config.http))?;
+
+        // Build the resources
+        let artifacts_resource = self.paths.artifacts_resource(&config);
+        let shadow_resource = self.paths.shadow_resource(&config);
+
+        // Get the factory event
+        let (factory_file, factory_contract) = match &self.factory_contract {
+            Some(contract) => parse_contract_string(contract),
+            None => {
+                let address = format!("0x{}", hex::encode(factory.as_bytes()));
+                let shadow_contract =
+                    shadow_resource
+                        .get_by_address(&address)
+                        .await
+                        .map_err(|_| {
+                            DiscoverError::CustomError(format!(
+                                "Factory {} is not in shadow.json, please set --factory-contract",
+                                address
+                            ))
+                        })?;
+                (shadow_contract.file_name, shadow_contract.contract_name)
+            }
+        };
+        let artifact = artifacts_resource
+            .get_artifact(&factory_file, &factory_contract)
+            .map_err(|e| DiscoverError::CustomError(format!("Error getting artifact: {}", e)))?;
+        let event = artifact
+            .abi
+            .events
+            .values()
+            .flatten()
+            .find(|e| e.name == self.event || e.signature() == self.event)
+            .cloned()
+            .ok_or_else(|| {
+                DiscoverError::CustomError(format!(
+                    "Event not found in {}:{}: {}",
+                    factory_file, factory_contract, self.event
+                ))
+            })?;
+
+        // Build the action
+        let discover = crate::core::actions::Discover {
+            provider,
+            factory,
+            event,
+            param: self.param.clone(),
+            from_block: self.from_block,
+            to_block: self.to_block,
+            chunk_size: self.chunk_size,
+            rpc_timeout: config.timeouts.rpc(),
+        };
+
+        // Run the action
+        let children = discover.run().await?;
+
+        // Skip the children that are already shadowed
+        let (file_name, contract_name) = parse_contract_string(&self.contract);
+        let known: Vec<String> = shadow_resource
+            .list_by_name(&file_name, &contract_name)
+            .await
+            .map_err(|e| DiscoverError::CustomError(e.to_string()))?
+            .into_iter()
+            .map(|c| c.address.to_lowercase())
+            .collect();
+        let new: Vec<String> = children
+            .iter()
+            .filter(|child| !known.contains(child))
+            .cloned()
+            .collect();
+        println!(
+            "Found {} children of {}, {} not yet shadowed",
+            children.len(),
+            self.factory,
+            new.len()
+        );
+
+        if self.dry_run {
+            for child in &new {
+                println!("{}", child);
+            }
+            return Ok(());
+        }
+        if new.is_empty() {
+            return Ok(());
+        }
+
+        // Deploy the new children
+        let deploy = Deploy {
+            contract: self.contract.clone(),
+            addresses: new,
+            addresses_file: None,
+            rpc_urls,
+            quorum: self.quorum,
+            backend: self.backend,
+            etherscan_api_key: self.etherscan_api_key.clone(),
+            tags: self.tags.clone(),
+            enforce_size_limit: false,
+            metadata: MetadataMode::Keep,
+            init_calls: None,
+            smoke_test: None,
+            paths: self.paths.clone(),
+        };
+        deploy
+            .run()
+            .await
+            .map_err(|e| DiscoverError::CustomError(e.to_string()))
+    }
+}
@@ -0,0 +1,94 @@
+use std::{io::Write, process::Command};
+
+use clap::Args;
+use thiserror::Error;
+
+#[derive(Args)]
+pub struct Explorer {
+    /// The RPC URL of the running shadow fork, as seen from the browser
+    #[clap(long, default_value = "http://localhost:8545")]
+    pub rpc_url: String,
+
+    /// The local port to serve the explorer on
+    #[clap(long, default_value_t = 5100)]
+    pub port: u16,
+
+    /// Print the docker-compose config instead of starting the explorer
+    #[clap(long)]
+    pub print: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum ExplorerError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Error running docker
+    #[error("DockerError: {0}")]
+    DockerError(#[from] std::io::Error),
+}
+
+/// Starts a local Otterscan block explorer against the running shadow fork.
+///
+/// Otterscan is a static web app: the browser talks directly to the
+/// fork's RPC URL, so the fork must be reachable from the browser and
+/// support the `ots_*` RPC namespace.
+///
+/// The explorer is started with `docker compose`. Use `--print` to
+/// get the docker-compose config and run it yourself.
+impl Explorer {
+    pub async fn run(&self) -> Result<(), ExplorerError> {
+        let compose = docker_compose_config(&self.rpc_url, self.port);
+        if self.print {
+            println!("{}", compose);
+            return Ok(());
+        }
+
+        // Write the config to a temporary file and start the explorer
+        let mut file = tempfile::Builder::new()
+            .prefix("shadow-explorer")
+            .suffix(".yml")
+            .tempfile()?;
+        file.write_all(compose.as_bytes())?;
+
+        println!("Starting Otterscan on http://localhost:{}", self.port);
+        let status = Command::new("docker")
+            .args(["compose", "-f"])
+            .arg(file.path())
+            .args(["up", "--remove-orphans"])
+            .status()?;
+        if !status.success() {
+            return Err(ExplorerError::CustomError(format!(
+                "docker compose exited with {}",
+                status
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds the docker-compose config for an Otterscan instance
+/// pointed at the shadow fork.
+fn docker_compose_config(rpc_url: &str, port: u16) -> String {
+    format!(
+        r#"services:
+  otterscan:
+    image: otterscan/otterscan:latest
+    ports:
+      - "{port}:80"
+    environment:
+      ERIGON_URL: "{rpc_url}"
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn can_build_docker_compose_config() {
+        let config = super::docker_compose_config("http://localhost:8545", 5100);
+        assert!(config.contains(r#""5100:80""#));
+        assert!(config.contains(r#"ERIGON_URL: "http://localhost:8545""#));
+    }
+}
@@ -0,0 +1,63 @@
+use std::fs;
+
+use clap::Args;
+
+use crate::config::Config;
+pub use crate::core::actions::bundle::BundleError;
+
+use super::paths::Paths;
+
+#[derive(Args)]
+pub struct Export {
+    /// The file to write the bundle to
+    #[clap(short, long, default_value = "bundle.json")]
+    pub output: String,
+
+    /// Include the ABI of each shadow contract in the bundle
+    #[clap(long)]
+    pub with_abi: bool,
+
+    /// Include the source hashes of each shadow contract in the bundle
+    #[clap(long)]
+    pub with_source_hashes: bool,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Exports the shadow contracts to a bundle file.
+///
+/// The command uses the [`crate::core::actions::Export`] action
+/// under the hood, using the local file-based artifact store,
+/// and the local file-based shadow store.
+impl Export {
+    pub async fn run(&self) -> Result<(), BundleError> {
+        // Build the resources
+        let config = Config::load().map_err(|e| BundleError::CustomError(e.to_string()))?;
+        let artifacts_resource = self.paths.artifacts_resource(&config);
+        let shadow_resource = self.paths.shadow_resource(&config);
+
+        // Build the action
+        let export = crate::core::actions::Export {
+            artifacts_resource,
+            shadow_resource,
+            include_abi: self.with_abi,
+            include_source_hashes: self.with_source_hashes,
+        };
+
+        // Run the action
+        let bundle = export.run().await?;
+
+        // Write the bundle
+        let contents = serde_json::to_string_pretty(&bundle)
+            .map_err(|e| BundleError::CustomError(e.to_string()))?;
+        fs::write(&self.output, contents).map_err(|e| BundleError::CustomError(e.to_string()))?;
+        println!(
+            "Exported {} shadow contracts to {}",
+            bundle.contracts.len(),
+            self.output
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use clap::{Args, ValueEnum};
+
+pub use crate::core::actions::export_events::ExportEventsError;
+use crate::{
+    config::Config,
+    core::{
+        actions::export_events::Checkpoint,
+        resources::{artifacts::ArtifactsResource, export::ExportWriter, shadow::ShadowResource},
+    },
+    providers,
+    resources::{export::JsonlExport, http::shared_client},
+};
+
+use super::{deploy::parse_contract_string, paths::Paths};
+
+/// The file format events are exported to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// A JSON object per line, like the `events --archive` file
+    Jsonl,
+    /// A directory of Parquet files, requires the `parquet` feature
+    Parquet,
+}
+
+impl ExportFormat {
+    /// The name of the format, as saved in the checkpoint
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ExportEvents {
+    /// The shadow contract to export the events of
+    ///
+    /// Can either be in the form ContractFile.sol (if the filename and contract name are the same), or ContractFile.sol:ContractName.
+    #[clap(long)]
+    pub contract: String,
+
+    /// The event to export, by name or signature, e.g. Swap
+    #[clap(long)]
+    pub event: String,
+
+    /// The first block to export
+    #[clap(long, visible_alias = "from", default_value_t = 0)]
+    pub from_block: u64,
+
+    /// The last block to export. Defaults to the latest block.
+    #[clap(long, visible_alias = "to")]
+    pub to_block: Option<u64>,
+
+    /// The format of the export
+    #[clap(long, value_enum, default_value_t = ExportFormat::Jsonl)]
+    pub output: ExportFormat,
+
+    /// The file, or directory for Parquet, to export to
+    /// [default: <Contract>-<Event>.jsonl, or <Contract>-<Event> for Parquet]
+    #[clap(long)]
+    pub output_path: Option<PathBuf>,
+
+    /// The most blocks each eth_getLogs query covers. Queries the
+    /// provider rejects are split until they succeed.
+    #[clap(long, default_value_t = 10_000)]
+    pub chunk_size: u64,
+
+    /// The most RPC requests sent per second
+    #[clap(long)]
+    pub max_requests_per_second: Option<f64>,
+
+    /// Don't fetch the timestamp of the blocks with events
+    #[clap(long)]
+    pub no_timestamps: bool,
+
+    /// The file progress is saved to, to resume an interrupted export
+    /// [default: <output path>.checkpoint.json]
+    #[clap(long)]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Start over, ignoring the checkpoint of a previous export
+    #[clap(long)]
+    pub restart: bool,
+
+    /// The HTTP RPC endpoints of the shadow fork, in order of preference
+    #[clap(
+        long = "rpc-url",
+        default_value = "http://localhost:8545",
+        value_delimiter = ','
+    )]
+    pub rpc_urls: Vec<String>,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Exports the historical events of a shadow contract to a file.
+///
+/// The command uses the [`crate::core::actions::ExportEvents`] action
+/// under the hood, using the local file-based artifact store,
+/// and the local file-based shadow store.
+impl ExportEvents {
+    pub async fn run(&self) -> Result<(), ExportEventsError> {
+        // Parse the contract string
+        let (file_name, contract_name) = parse_contract_string(&self.contract);
+
+        // Build the resources
+        let config = Config::load().map_err(|e| ExportEventsError::CustomError(e.to_string()))?;
+        let artifacts_resource = self.paths.artifacts_resource(&config);
+        let shadow_resource = self.paths.shadow_resource(&config);
+        let provider = providers::connect_http(&self.rpc_urls, 1, shared_client(&config.http))?;
+
+        // Get the event and the addresses of the shadow contract
+        let artifact = artifacts_resource
+            .get_artifact(&file_name, &contract_name)
+            .map_err(|e| {
+                ExportEventsError::CustomError(format!("Error getting artifact: {}", e))
+            })?;
+        let event = artifact
+            .abi
+            .events
+            .values()
+            .flatten()
+            .find(|e| e.name == self.event || e.signature() == self.event)
+            .cloned()
+            .ok_or_else(|| {
+                ExportEventsError::CustomError(format!(
+                    "Event not found in {}:{}: {}",
+                    file_name, contract_name, self.event
+                ))
+            })?;
+        let addresses = shadow_resource
+            .list_by_name(&file_name, &contract_name)
+            .await
+            .map_err(|e| ExportEventsError::CustomError(e.to_string()))?
+            .iter()
+            .map(|c| {
+                ethers::types::H160::from_str(&c.address).map_err(|e| {
+                    ExportEventsError::CustomError(format!("Invalid address {}: {}", c.address, e))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if addresses.is_empty() {
+            return Err(ExportEventsError::CustomError(format!(
+                "{}:{} is not in shadow.json",
+                file_name, contract_name
+            )));
+        }
+
+        // Resume from the checkpoint of the same export, if any
+        let contract = format!("{}:{}", file_name, contract_name);
+        let output_path = self.output_path.clone().unwrap_or_else(|| {
+            let name = format!("{}-{}", contract_name, event.name);
+            match self.output {
+                ExportFormat::Jsonl => PathBuf::from(format!("{}.jsonl", name)),
+                ExportFormat::Parquet => PathBuf::from(name),
+            }
+        });
+        let checkpoint_path = self
+            .checkpoint
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{}.checkpoint.json", output_path.display())));
+        let resume = if self.restart {
+            None
+        } else {
+            Checkpoint::load(&checkpoint_path).map_err(ExportEventsError::CustomError)?
+        };
+        if let Some(checkpoint) = &resume {
+            if !checkpoint.matches(
+                &contract,
+                &event.signature(),
+                self.output.name(),
+                &addresses,
+                self.from_block,
+            ) {
+                return Err(ExportEventsError::CustomError(format!(
+                    "Checkpoint {} is for a different export ({} {} as {} from block {}), use --restart to start over",
+                    checkpoint_path.display(),
+                    checkpoint.contract,
+                    checkpoint.signature,
+                    checkpoint.format,
+                    checkpoint.from_block
+                )));
+            }
+            println!(
+                "Resuming the export from block {} ({} events exported)",
+                checkpoint.next_block, checkpoint.events
+            );
+        }
+
+        // Build the writer
+        let io_error = |e: std::io::Error| {
+            ExportEventsError::CustomError(format!(
+                "Error opening {}: {}",
+                output_path.display(),
+                e
+            ))
+        };
+        let writer: Box<dyn ExportWriter> = match self.output {
+            ExportFormat::Jsonl => Box::new(
+                match &resume {
+                    Some(checkpoint) => JsonlExport::resume(&output_path, checkpoint.position),
+                    None => JsonlExport::create(&output_path),
+                }
+                .map_err(io_error)?,
+            ),
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => Box::new(
+                crate::resources::export::ParquetExport::create(&output_path, &event)
+                    .map_err(io_error)?,
+            ),
+            #[cfg(not(feature = "parquet"))]
+            ExportFormat::Parquet => {
+                return Err(ExportEventsError::CustomError(
+                    "shadow was built without the parquet feature".to_owned(),
+                ))
+            }
+        };
+
+        // Build the action
+        let mut export = crate::core::actions::ExportEvents {
+            provider,
+            addresses,
+            contract,
+            event,
+            format: self.output.name().to_owned(),
+            from_block: self.from_block,
+            to_block: self.to_block,
+            chunk_size: self.chunk_size,
+            max_requests_per_second: self.max_requests_per_second,
+            timestamps: !self.no_timestamps,
+            checkpoint: Some(checkpoint_path.clone()),
+            writer,
+            rpc_timeout: config.timeouts.rpc(),
+        };
+
+        // Run the action
+        let report = export.run(resume).await?;
+
+        // The export is complete, so it won't be resumed
+        if let Err(e) = fs::remove_file(&checkpoint_path) {
+            log::warn!(
+                "Error removing checkpoint {}: {}",
+                checkpoint_path.display(),
+                e
+            );
+        }
+        println!(
+            "Exported {} events from blocks {}..={} to {}",
+            report.events,
+            report.from_block,
+            report.to_block,
+            output_path.display()
+        );
+
+        Ok(())
+    }
+}
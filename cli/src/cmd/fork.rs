@@ -1,10 +1,25 @@
-use std::env;
+use std::{net::SocketAddr, path::PathBuf, str::FromStr, sync::Arc};
 
 use clap::Args;
+use ethers::providers::{JsonRpcClient, Provider, PubsubClient};
 
 pub use crate::core::actions::fork::ForkError;
-use crate::resources::shadow::LocalShadowStore;
-use ethers::providers::{Provider, Ws};
+use crate::core::actions::fork::{
+    CatchUpStrategy, ChildCode, FeeMode, PinnedContract, PolledView, PostDeploy,
+    PostDeployTransaction, ReplayMode, ReplayOptions, Scenario, ScenarioRunner, SelectorRewrite,
+    TrackedFactory, TransactionRewrite, DEFAULT_GAS_HEADROOM, DEFAULT_RECEIPT_RETRIES,
+};
+use crate::providers::{
+    fork_proxy::{self, ForkTape},
+    Cassette, CassetteRecorder, ChainFixture, PlaybackClient, RecordingClient,
+};
+use crate::resources::{shadow::LocalShadowStore, signer, sinks::JsonlSink, stores};
+use crate::{config::Config, core::resources::artifacts::ArtifactsResource, providers};
+
+use super::{
+    api_tokens::ApiTokens, cancel, deploy::parse_contract_string, healthcheck::Healthcheck,
+    paths::Paths,
+};
 
 #[derive(Args)]
 pub struct Fork {
@@ -15,8 +30,244 @@ pub struct Fork {
     /// (i.e. running it on the same machine as your node). Otherwise,
     /// the block processing will be very slow (3-4 minutes per
     /// block), and you'll quickly run out of RPC compute units.
-    #[clap(short, long)]
+    /// If you run a reth node, `shadow-reth` replays every transaction
+    /// from the node's database instead.
+    #[clap(short, long, env = "SHADOW_ALL_TXS")]
     pub all_txs: Option<bool>,
+
+    /// Upstream websocket RPC endpoints, in order of preference.
+    ///
+    /// If an endpoint fails, the fork fails over to the next one.
+    /// Defaults to the WS_RPC_URL environment variable.
+    #[clap(long = "ws-rpc-url", env = "SHADOW_WS_RPC_URL", value_delimiter = ',')]
+    pub ws_rpc_urls: Vec<String>,
+
+    /// Number of upstream endpoints that must agree on each block header.
+    #[clap(long, default_value_t = 1, env = "SHADOW_QUORUM")]
+    pub quorum: usize,
+
+    /// How transaction fees are handled during replay.
+    ///
+    /// `mainnet` reproduces the original block's base fee, so shadow
+    /// logic that reads `block.basefee` or `tx.gasprice` sees real
+    /// values. `zero` replays every block with a zero base fee.
+    #[clap(long, value_enum, default_value_t = FeeMode::Mainnet, env = "SHADOW_FEE_MODE")]
+    pub fee_mode: FeeMode,
+
+    /// Mine empty blocks for skipped mainnet blocks, so the fork's
+    /// block numbers match mainnet exactly.
+    #[clap(long, env = "SHADOW_MIRROR_BLOCK_NUMBERS")]
+    pub mirror_block_numbers: bool,
+
+    /// Whether fork blocks use the mainnet block timestamps. Defaults to true.
+    ///
+    /// When disabled, anvil assigns timestamps from the local clock.
+    #[clap(
+        long,
+        default_value_t = true,
+        action = clap::ArgAction::Set,
+        env = "SHADOW_MIRROR_TIMESTAMPS"
+    )]
+    pub mirror_timestamps: bool,
+
+    /// Copy each mainnet block's fee recipient (`block.coinbase`), gas
+    /// limit (`block.gaslimit`) and mix hash (`block.prevrandao`) onto the
+    /// fork.
+    #[clap(long, env = "SHADOW_MIRROR_BLOCK_ENV")]
+    pub mirror_block_env: bool,
+
+    /// Print the call tree of each replayed transaction, with calls
+    /// to shadow contracts decoded using the artifact ABIs.
+    #[clap(long, env = "SHADOW_TRACE")]
+    pub trace: bool,
+
+    /// How many times receipts that aren't available upstream yet are
+    /// fetched again, before their transactions are skipped
+    #[clap(long, default_value_t = DEFAULT_RECEIPT_RETRIES, env = "SHADOW_RECEIPT_RETRIES")]
+    pub receipt_retries: u32,
+
+    /// How many blocks the fork may fall behind the chain head before
+    /// the catch-up strategy is applied. Unbounded by default.
+    #[clap(long, env = "SHADOW_MAX_LAG_BLOCKS")]
+    pub max_lag_blocks: Option<u64>,
+
+    /// How blocks are replayed while the fork lags more than
+    /// --max-lag-blocks behind the chain head.
+    ///
+    /// `replay` keeps replaying every block. `skip-blocks` skips blocks
+    /// without a transaction to a shadow contract entirely.
+    /// `logs-prefilter` only replays the transactions that emitted a
+    /// log from a shadow contract, found with `eth_getLogs`. With
+    /// `--all-txs`, it skips blocks like `skip-blocks`.
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = CatchUpStrategy::Replay,
+        requires = "max_lag_blocks",
+        env = "SHADOW_CATCH_UP"
+    )]
+    pub catch_up: CatchUpStrategy,
+
+    /// Serve the `shadow_*` control RPC methods on this address,
+    /// e.g. 127.0.0.1:8547
+    #[clap(long, env = "SHADOW_CONTROL_ADDR")]
+    pub control_addr: Option<SocketAddr>,
+
+    #[clap(flatten)]
+    pub api_tokens: ApiTokens,
+
+    /// Shadow the contracts a shadowed factory creates while replaying,
+    /// as `<factory>=<File.sol:Contract>`, e.g.
+    /// 0x1f98431c8ad98523631ae4a59f267346ea31f984=UniswapV3Pool.sol:UniswapV3Pool
+    ///
+    /// Children are registered in shadow.json with the given artifact.
+    #[clap(
+        long = "track-factory",
+        env = "SHADOW_TRACK_FACTORY",
+        value_delimiter = ','
+    )]
+    pub track_factories: Vec<String>,
+
+    /// Which code the children of tracked factories run.
+    ///
+    /// `factory` keeps the code the shadowed factory deployed.
+    /// `artifact` replaces it with the child artifact's deployed
+    /// bytecode, without immutables.
+    #[clap(long, value_enum, default_value_t = ChildCode::Factory, env = "SHADOW_CHILD_CODE")]
+    pub child_code: ChildCode,
+
+    /// The chain id of the fork. Defaults to the upstream chain's id.
+    ///
+    /// Mainnet transactions are signed for the upstream chain id, so
+    /// with another chain id they are replayed unsigned from their
+    /// impersonated senders instead of as raw transactions.
+    #[clap(long, env = "SHADOW_CHAIN_ID")]
+    pub chain_id: Option<u64>,
+
+    /// How replayed transactions are sent to the fork.
+    ///
+    /// `raw` sends the signed mainnet transactions. `impersonated`
+    /// rebuilds them unsigned and sends them from their impersonated
+    /// senders, so their gas limit and calldata can be changed.
+    #[clap(long, value_enum, default_value_t = ReplayMode::Raw, env = "SHADOW_REPLAY_MODE")]
+    pub replay_mode: ReplayMode,
+
+    /// Multiply the gas limit of impersonated replays by this factor,
+    /// for shadow code that uses more gas than the original.
+    /// Must be at least 1.
+    #[clap(
+        long,
+        default_value_t = DEFAULT_GAS_HEADROOM,
+        value_parser = parse_gas_multiplier,
+        env = "SHADOW_GAS_MULTIPLIER"
+    )]
+    pub gas_multiplier: f64,
+
+    /// Whether `--gas-multiplier` was set, on the command line or in the
+    /// environment, rather than left to its default
+    #[clap(skip)]
+    pub gas_multiplier_set: bool,
+
+    /// Replace a function selector in the calldata of impersonated
+    /// replays, as `[<address>:]<selector>=<selector>`, e.g.
+    /// 0xa9059cbb=0x12345678. Can be repeated.
+    #[clap(
+        long = "rewrite-selector",
+        env = "SHADOW_REWRITE_SELECTOR",
+        value_delimiter = ','
+    )]
+    pub rewrite_selectors: Vec<String>,
+
+    /// Print a summary of each replayed block: its timestamp, the shadow
+    /// transactions replayed, the shadow events emitted by name and the
+    /// gas used
+    #[clap(long, env = "SHADOW_BLOCK_SUMMARIES")]
+    pub block_summaries: bool,
+
+    /// Compare the receipt of each replayed transaction to its mainnet
+    /// receipt, and report the transactions whose status, gas used or
+    /// logs diverged. Costs an extra receipt fetch per replayed transaction.
+    #[clap(long, env = "SHADOW_DETERMINISM_REPORT")]
+    pub determinism_report: bool,
+
+    /// Before replaying a transaction that follows skipped ones in its
+    /// block, copy the balances, nonces and storage it read from the
+    /// mainnet state, so it sees the approvals or oracle updates of the
+    /// skipped transactions. Requires debug_traceBlockByNumber upstream.
+    #[clap(long, env = "SHADOW_PRESTAGE_STATE")]
+    pub prestage_state: bool,
+
+    /// Write the block summaries to this JSONL file
+    #[clap(long, env = "SHADOW_SUMMARY_ARCHIVE")]
+    pub summary_archive: Option<String>,
+
+    /// Write the block summaries to this event store, e.g.
+    /// sqlite://summaries.db
+    #[clap(long, env = "SHADOW_SUMMARY_STORE")]
+    pub summary_store: Option<String>,
+
+    /// Write the results of the config's view calls to this JSONL file
+    #[clap(long, env = "SHADOW_VIEW_ARCHIVE")]
+    pub view_archive: Option<String>,
+
+    /// Write the results of the config's view calls to this event store,
+    /// e.g. sqlite://views.db
+    #[clap(long, env = "SHADOW_VIEW_STORE")]
+    pub view_store: Option<String>,
+
+    /// Mix the synthetic transactions of this scenario file into the
+    /// replayed transactions, sent at block offsets or when a shadow
+    /// contract emits an event
+    #[clap(long, env = "SHADOW_SCENARIO")]
+    pub scenario: Option<String>,
+
+    /// Record every upstream RPC response the session consumes to this
+    /// gzipped cassette file, to play the session back with --playback
+    #[clap(long, conflicts_with = "playback", env = "SHADOW_RECORD")]
+    pub record: Option<String>,
+
+    /// Re-run a session recorded with --record offline, answering every
+    /// RPC request from the cassette file
+    #[clap(long, env = "SHADOW_PLAYBACK")]
+    pub playback: Option<String>,
+
+    /// Capture the session as a chain fixture in this directory: the
+    /// blocks and the state anvil reads, with the shadow contracts. Use
+    /// with --max-blocks to keep the fixture small.
+    #[clap(
+        long,
+        conflicts_with_all = ["record", "playback", "fixture"],
+        env = "SHADOW_RECORD_FIXTURE"
+    )]
+    pub record_fixture: Option<String>,
+
+    /// Replay a chain fixture captured with --record-fixture offline,
+    /// with the shadow contracts it was captured with
+    #[clap(long, conflicts_with_all = ["record", "playback"], env = "SHADOW_FIXTURE")]
+    pub fixture: Option<String>,
+
+    /// Stop after replaying this many blocks, e.g. to run the fork as a
+    /// one-shot job
+    #[clap(long, visible_alias = "exit-after-blocks", env = "SHADOW_MAX_BLOCKS")]
+    pub max_blocks: Option<u64>,
+
+    /// Only load the shadow contracts with any of these tags
+    #[clap(long = "tag", env = "SHADOW_TAG", value_delimiter = ',')]
+    pub tags: Vec<String>,
+
+    /// The private key of the signer of the `postDeploy` transactions.
+    ///
+    /// If not set, the signer is loaded from the keystore in
+    /// shadow.config.json, the OS keychain or the encrypted credentials
+    /// file (see `shadow auth set signer`).
+    #[clap(long, env = "SHADOW_PRIVATE_KEY", hide_env_values = true)]
+    pub private_key: Option<String>,
+
+    #[clap(flatten)]
+    pub healthcheck: Healthcheck,
+
+    #[clap(flatten)]
+    pub paths: Paths,
 }
 
 /// Starts a local shadow fork using Anvil.
@@ -25,36 +276,329 @@ pub struct Fork {
 /// under the hood, using the local file-based shadow store.
 impl Fork {
     pub async fn run(&self) -> Result<(), ForkError> {
-        let http_rpc_url = env!("ETH_RPC_URL", "Please set an ETH_RPC_URL").to_owned();
-
-        // Build the provider
-        let provider =
-            Provider::<Ws>::connect(env!("WS_RPC_URL", "Please set an WS_RPC_URL").to_owned())
+        // Build the provider, from a cassette when playing back
+        let playback = match &self.fixture {
+            Some(fixture) => Some(
+                ChainFixture::open(fixture)
+                    .map_err(|e| ForkError::CustomError(e.to_string()))?
+                    .session_path(),
+            ),
+            None => self.playback.as_ref().map(PathBuf::from),
+        };
+        if let Some(playback) = playback {
+            let cassette =
+                Arc::new(Cassette::load(playback).map_err(|e| {
+                    ForkError::CustomError(format!("Error loading cassette: {}", e))
+                })?);
+            let fork_url = fork_proxy::serve(ForkTape::Playback(cassette.clone()))
                 .await
-                .map_err(ForkError::ProviderError)?;
+                .map_err(|e| ForkError::CustomError(format!("Error serving cassette: {}", e)))?;
+            let provider = Provider::new(PlaybackClient::new(cassette));
+            return self.run_with(provider, fork_url).await;
+        }
+        let http_rpc_url =
+            providers::rpc_url_from_env("ETH_RPC_URL").map_err(ForkError::ProviderError)?;
+        let ws_rpc_urls = if self.ws_rpc_urls.is_empty() {
+            vec![providers::rpc_url_from_env("WS_RPC_URL").map_err(ForkError::ProviderError)?]
+        } else {
+            self.ws_rpc_urls.clone()
+        };
+        let client = providers::failover_ws(&ws_rpc_urls, self.quorum)
+            .await
+            .map_err(ForkError::ProviderError)?;
+        let recorder = match (&self.record, &self.record_fixture) {
+            (Some(record), _) => CassetteRecorder::create(record),
+            (None, Some(fixture)) => {
+                let config = Config::load().map_err(|e| ForkError::CustomError(e.to_string()))?;
+                ChainFixture::create(fixture, self.paths.shadow_resource(&config).file_path())
+            }
+            (None, None) => return self.run_with(Provider::new(client), http_rpc_url).await,
+        };
+        let recorder = Arc::new(
+            recorder
+                .map_err(|e| ForkError::CustomError(format!("Error creating cassette: {}", e)))?,
+        );
+        let fork_url = fork_proxy::serve(ForkTape::Record {
+            upstream: http_rpc_url,
+            recorder: recorder.clone(),
+        })
+        .await
+        .map_err(|e| ForkError::CustomError(format!("Error serving cassette: {}", e)))?;
+        let provider = Provider::new(RecordingClient::new(client, recorder.clone()));
+        self.run_with(provider, fork_url).await?;
+        recorder
+            .finish()
+            .map_err(|e| ForkError::CustomError(format!("Error writing cassette: {}", e)))
+    }
 
+    /// Runs the fork against the given provider, with anvil forking from
+    /// the given HTTP RPC url.
+    async fn run_with<P: JsonRpcClient + PubsubClient + 'static>(
+        &self,
+        provider: Provider<P>,
+        http_rpc_url: String,
+    ) -> Result<(), ForkError> {
         // Build the resources
-        let shadow_resource = LocalShadowStore::new(
-            env::current_dir()
-                .unwrap()
-                .as_path()
-                .to_str()
-                .unwrap()
-                .to_owned(),
-        );
+        let config = Config::load().map_err(|e| ForkError::CustomError(e.to_string()))?;
+        let shadow_resource = match &self.fixture {
+            Some(fixture) => LocalShadowStore::new(fixture.clone()),
+            None => self.paths.shadow_resource(&config),
+        };
 
         // Build the action
-        let fork = crate::core::actions::Fork::new(
+        let mut fork = crate::core::actions::Fork::new(
             provider,
             shadow_resource,
             http_rpc_url,
-            self.all_txs.unwrap_or(false),
+            ReplayOptions {
+                all_txs: self.all_txs.unwrap_or(false),
+                fee_mode: self.fee_mode,
+                mirror_block_numbers: self.mirror_block_numbers,
+                mirror_timestamps: self.mirror_timestamps,
+                mirror_block_env: self.mirror_block_env,
+                trace: self.trace,
+                rpc_timeout: config.timeouts.rpc(),
+                receipt_retries: self.receipt_retries,
+                receipt_retry_delay: ReplayOptions::default().receipt_retry_delay,
+                max_lag_blocks: self.max_lag_blocks,
+                catch_up: self.catch_up,
+                block_summaries: self.block_summaries,
+                chain_id: self.chain_id,
+                replay_mode: self.replay_mode,
+                gas_multiplier: self.gas_multiplier,
+                storage_caching: self.record.is_none()
+                    && self.playback.is_none()
+                    && self.record_fixture.is_none()
+                    && self.fixture.is_none(),
+                port: None,
+                determinism_report: self.determinism_report,
+                prestage_state: self.prestage_state,
+            },
         )
         .await?;
+        if self.replay_mode == ReplayMode::Raw
+            && (self.gas_multiplier_set
+                || !self.rewrite_selectors.is_empty()
+                || !config.replay_rewrites.is_empty())
+        {
+            log::warn!(
+                "--gas-multiplier, --rewrite-selector and replay rewrites only apply with \
+                 --replay-mode impersonated, or to transactions replayed for another \
+                 --chain-id"
+            );
+        }
+        for argument in &self.rewrite_selectors {
+            let rewrite = SelectorRewrite::parse(argument).map_err(ForkError::CustomError)?;
+            fork.replay_hooks.push(Box::new(rewrite));
+        }
+        for rewrite in &config.replay_rewrites {
+            let rewrite = TransactionRewrite::try_from(rewrite)
+                .map_err(|e| ForkError::CustomError(format!("Invalid replay rewrite: {}", e)))?;
+            fork.replay_hooks.push(Box::new(rewrite));
+        }
+        if let Some(scenario) = &self.scenario {
+            let scenario = Scenario::load(scenario).map_err(ForkError::CustomError)?;
+            fork.scenario = Some(ScenarioRunner::new(scenario));
+        }
+        fork.post_deploy = self.post_deploy(&config)?;
+        fork.pins = config
+            .pins
+            .iter()
+            .map(|pin| {
+                PinnedContract::try_from(pin)
+                    .map_err(|e| ForkError::CustomError(format!("Invalid pin: {}", e)))
+            })
+            .collect::<Result<_, _>>()?;
+        fork.views = config
+            .view_calls
+            .iter()
+            .map(|call| {
+                PolledView::try_from(call)
+                    .map_err(|e| ForkError::CustomError(format!("Invalid view call: {}", e)))
+            })
+            .collect::<Result<_, _>>()?;
+        if !fork.views.is_empty() && self.view_archive.is_none() && self.view_store.is_none() {
+            log::warn!(
+                "viewCalls are configured but not polled: pass --view-archive or --view-store \
+                 to write their results"
+            );
+            fork.views.clear();
+        }
+        fork.max_blocks = self.max_blocks;
+        fork.health = self.healthcheck.start().map_err(ForkError::CustomError)?;
+        if let Some(health) = &fork.health {
+            health.report_metrics(fork.metrics.clone());
+        }
+        fork.control_addr = self.control_addr;
+        fork.control_auth = self.api_tokens.auth();
+        fork.cancel = cancel::on_ctrl_c();
+        fork.retain_tagged(&self.tags);
+        if self.trace {
+            fork.load_abis(&self.paths.artifacts_resource(&config))?;
+        } else if self.block_summaries
+            || self.summary_archive.is_some()
+            || self.summary_store.is_some()
+            || self.scenario.is_some()
+            || !fork.views.is_empty()
+        {
+            // Events are named by topic0, and view results kept as raw
+            // return data, when the ABIs can't be loaded
+            if let Err(e) = fork.load_abis(&self.paths.artifacts_resource(&config)) {
+                log::warn!("Error loading ABIs to name shadow events: {}", e);
+            }
+        }
+        if let Some(archive) = &self.summary_archive {
+            let sink = JsonlSink::new(archive.clone()).map_err(|e| {
+                ForkError::CustomError(format!("Error opening summary archive: {}", e))
+            })?;
+            fork.summary_sinks.push(Box::new(sink));
+        }
+        if let Some(store) = &self.summary_store {
+            let store = stores::open(store).await.map_err(|e| {
+                ForkError::CustomError(format!("Error opening summary store: {}", e))
+            })?;
+            fork.summary_sinks.push(Box::new(store));
+        }
+        if let Some(archive) = &self.view_archive {
+            let sink = JsonlSink::new(archive.clone()).map_err(|e| {
+                ForkError::CustomError(format!("Error opening view archive: {}", e))
+            })?;
+            fork.view_sinks.push(Box::new(sink));
+        }
+        if let Some(store) = &self.view_store {
+            let store = stores::open(store)
+                .await
+                .map_err(|e| ForkError::CustomError(format!("Error opening view store: {}", e)))?;
+            fork.view_sinks.push(Box::new(store));
+        }
+        for factory in &self.track_factories {
+            fork.factories
+                .push(self.tracked_factory(factory, &self.paths.artifacts_resource(&config))?);
+        }
 
         // Run the action
         fork.run().await?;
 
         Ok(())
     }
+
+    /// Builds the post-deploy transactions of the config, and loads
+    /// their signer.
+    fn post_deploy(&self, config: &Config) -> Result<Option<PostDeploy>, ForkError> {
+        if config.post_deploy.is_empty() {
+            return Ok(None);
+        }
+        let transactions = config
+            .post_deploy
+            .iter()
+            .map(|call| {
+                PostDeployTransaction::try_from(call).map_err(|e| {
+                    ForkError::CustomError(format!("Invalid post-deploy transaction: {}", e))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let signer = signer::load(self.private_key.clone(), &config.signer)
+            .map_err(|e| ForkError::CustomError(e.to_string()))?
+            .ok_or_else(|| {
+                ForkError::CustomError(
+                    "Post-deploy transactions need a signer: set --private-key, a keystore in \
+                     shadow.config.json or `shadow auth set signer`"
+                        .to_owned(),
+                )
+            })?;
+        Ok(Some(PostDeploy {
+            signer,
+            transactions,
+        }))
+    }
+
+    /// Builds a tracked factory from a `<factory>=<File.sol:Contract>` argument.
+    fn tracked_factory<A: ArtifactsResource>(
+        &self,
+        argument: &str,
+        artifacts_resource: &A,
+    ) -> Result<TrackedFactory, ForkError> {
+        let (factory, child) = parse_tracked_factory(argument)?;
+        let (file_name, contract_name) = parse_contract_string(child);
+        let artifact = artifacts_resource
+            .get_artifact(&file_name, &contract_name)
+            .map_err(|e| ForkError::CustomError(format!("Error getting artifact: {}", e)))?;
+        let runtime_bytecode = match self.child_code {
+            ChildCode::Factory => None,
+            ChildCode::Artifact => Some(hex::encode(artifact.deployed_bytecode.ok_or_else(
+                || {
+                    ForkError::CustomError(format!(
+                        "Artifact {}:{} has no deployed bytecode",
+                        file_name, contract_name
+                    ))
+                },
+            )?)),
+        };
+        Ok(TrackedFactory {
+            factory,
+            file_name,
+            contract_name,
+            abi: artifact.abi,
+            runtime_bytecode,
+        })
+    }
+}
+
+/// Parses a `<factory>=<File.sol:Contract>` argument into the factory
+/// address and the child contract.
+fn parse_tracked_factory(argument: &str) -> Result<(ethers::types::H160, &str), ForkError> {
+    let (factory, child) = argument.split_once('=').ok_or_else(|| {
+        ForkError::CustomError(format!(
+            "Expected <factory>=<File.sol:Contract>, got {}",
+            argument
+        ))
+    })?;
+    let factory = ethers::types::H160::from_str(factory.trim()).map_err(|e| {
+        ForkError::CustomError(format!("Invalid factory address {}: {}", factory, e))
+    })?;
+    Ok((factory, child.trim()))
+}
+
+/// Parses a --gas-multiplier value, which can't lower the gas limit of
+/// replays.
+fn parse_gas_multiplier(value: &str) -> Result<f64, String> {
+    let multiplier = f64::from_str(value.trim())
+        .map_err(|e| format!("Invalid gas multiplier {}: {}", value, e))?;
+    if !multiplier.is_finite() || multiplier < 1.0 {
+        return Err(format!(
+            "Expected a gas multiplier of at least 1, got {}",
+            value
+        ));
+    }
+    Ok(multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    #[test]
+    fn can_parse_tracked_factory() {
+        let (factory, child) = super::parse_tracked_factory(
+            "0x1f98431c8ad98523631ae4a59f267346ea31f984=UniswapV3Pool.sol:UniswapV3Pool",
+        )
+        .unwrap();
+        assert_eq!(
+            factory,
+            ethers::types::H160::from_str("0x1f98431c8ad98523631ae4a59f267346ea31f984").unwrap()
+        );
+        assert_eq!(child, "UniswapV3Pool.sol:UniswapV3Pool");
+
+        assert!(super::parse_tracked_factory("UniswapV3Pool.sol").is_err());
+        assert!(super::parse_tracked_factory("0x1234=UniswapV3Pool.sol").is_err());
+    }
+
+    #[test]
+    fn can_parse_gas_multiplier() {
+        assert_eq!(super::parse_gas_multiplier("1.5").unwrap(), 1.5);
+        assert_eq!(super::parse_gas_multiplier("1").unwrap(), 1.0);
+        for invalid in ["0", "0.5", "-2", "NaN", "inf", "abc"] {
+            assert!(super::parse_gas_multiplier(invalid).is_err(), "{}", invalid);
+        }
+    }
 }
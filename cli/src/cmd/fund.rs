@@ -0,0 +1,48 @@
+use clap::Args;
+use ethers::{
+    providers::{Http, Provider},
+    types::Address,
+    utils::{format_ether, parse_ether},
+};
+
+pub use crate::core::actions::cheats::CheatsError;
+
+#[derive(Args)]
+pub struct Fund {
+    /// The address to fund
+    pub address: Address,
+
+    /// The amount of ETH to add to the balance, e.g. 1.5
+    pub amount: String,
+
+    /// The RPC URL of the running shadow fork
+    #[clap(long, default_value = "http://localhost:8545")]
+    pub rpc_url: String,
+}
+
+/// Adds ETH to the balance of an address on the running shadow fork.
+///
+/// The command uses the [`crate::core::actions::Cheats`] action
+/// under the hood.
+impl Fund {
+    pub async fn run(&self) -> Result<(), CheatsError> {
+        let amount = parse_ether(&self.amount)
+            .map_err(|e| CheatsError::CustomError(format!("Invalid amount: {}", e)))?;
+
+        // Build the provider
+        let provider = Provider::<Http>::try_from(self.rpc_url.as_str())
+            .map_err(|e| CheatsError::CustomError(e.to_string()))?;
+
+        // Run the action
+        let cheats = crate::core::actions::Cheats { provider };
+        let balance = cheats.fund(self.address, amount).await?;
+        println!(
+            "Funded {:?} with {} ETH, balance is now {} ETH",
+            self.address,
+            self.amount,
+            format_ether(balance)
+        );
+
+        Ok(())
+    }
+}
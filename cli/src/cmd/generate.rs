@@ -0,0 +1,62 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+
+use crate::core::actions::generate::parse_spec;
+pub use crate::core::actions::generate::GenerateError;
+
+#[derive(Args)]
+pub struct Generate {
+    /// The Solidity source to instrument, e.g. a verified source
+    /// copied into contracts/src
+    pub source: PathBuf,
+
+    /// A spec file with instrumentation rules, one per line, e.g.
+    /// `after Router.swap emit Swapped(amountIn, msg.sender)`
+    #[clap(long)]
+    pub spec: Option<PathBuf>,
+
+    /// Where to write the instrumented source. Defaults to stdout.
+    #[clap(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+/// Instruments a Solidity source at its annotated functions, and
+/// with the rules of a spec file.
+///
+/// Functions are annotated with a NatSpec tag listing the patterns
+/// to inject, e.g. `/// @custom:shadow emit gas`.
+///
+/// The command uses the [`crate::core::actions::Generate`] action
+/// under the hood.
+impl Generate {
+    pub async fn run(&self) -> Result<(), GenerateError> {
+        let source = read(&self.source)?;
+        let rules = match &self.spec {
+            Some(spec) => parse_spec(&read(spec)?)?,
+            None => Vec::new(),
+        };
+
+        // Build the action
+        let generate = crate::core::actions::Generate { source, rules };
+
+        // Run the action
+        let instrumented = generate.run()?;
+        match &self.output {
+            Some(output) => {
+                std::fs::write(output, instrumented).map_err(|e| {
+                    GenerateError::CustomError(format!("Error writing {}: {}", output.display(), e))
+                })?;
+                println!("Wrote {}", output.display());
+            }
+            None => print!("{}", instrumented),
+        }
+
+        Ok(())
+    }
+}
+
+fn read(path: &Path) -> Result<String, GenerateError> {
+    std::fs::read_to_string(path)
+        .map_err(|e| GenerateError::CustomError(format!("Error reading {}: {}", path.display(), e)))
+}
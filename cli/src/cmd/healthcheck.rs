@@ -0,0 +1,48 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use clap::Args;
+
+use crate::server::health::{self, Health, DEFAULT_MAX_STALL};
+
+/// The health check endpoints of the long-running commands, for
+/// running them under a container orchestrator.
+#[derive(Args, Clone, Debug, Default)]
+pub struct Healthcheck {
+    /// Serve `GET /healthz` (liveness) and `GET /readyz` (readiness) on
+    /// this port, on all interfaces
+    #[clap(long, env = "SHADOW_HEALTHCHECK_PORT")]
+    pub healthcheck_port: Option<u16>,
+
+    /// Report the daemon as not live once it hasn't processed a block
+    /// for this many seconds [default: 120]
+    #[clap(
+        long,
+        env = "SHADOW_HEALTHCHECK_MAX_STALL",
+        requires = "healthcheck_port"
+    )]
+    pub healthcheck_max_stall: Option<u64>,
+}
+
+impl Healthcheck {
+    /// Starts serving the health check endpoints, if a port is set, and
+    /// returns the health the daemon reports its progress to.
+    ///
+    /// Fails if the port can't be bound, so the daemon doesn't run
+    /// without the probes its orchestrator expects.
+    pub fn start(&self) -> Result<Option<Health>, String> {
+        let Some(port) = self.healthcheck_port else {
+            return Ok(None);
+        };
+        let health = Health::new(
+            self.healthcheck_max_stall
+                .map_or(DEFAULT_MAX_STALL, Duration::from_secs),
+        );
+        let addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, port));
+        health::serve(addr, health.clone())
+            .map_err(|e| format!("Error serving health checks on port {}: {}", port, e))?;
+        Ok(Some(health))
+    }
+}
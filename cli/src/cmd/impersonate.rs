@@ -0,0 +1,46 @@
+use clap::Args;
+use ethers::{
+    providers::{Http, Provider},
+    types::Address,
+};
+
+pub use crate::core::actions::cheats::CheatsError;
+
+#[derive(Args)]
+pub struct Impersonate {
+    /// The address to impersonate
+    pub address: Address,
+
+    /// Stop impersonating the address
+    #[clap(long)]
+    pub stop: bool,
+
+    /// The RPC URL of the running shadow fork
+    #[clap(long, default_value = "http://localhost:8545")]
+    pub rpc_url: String,
+}
+
+/// Impersonates an address on the running shadow fork, so
+/// transactions can be sent from it without its private key.
+///
+/// The command uses the [`crate::core::actions::Cheats`] action
+/// under the hood.
+impl Impersonate {
+    pub async fn run(&self) -> Result<(), CheatsError> {
+        // Build the provider
+        let provider = Provider::<Http>::try_from(self.rpc_url.as_str())
+            .map_err(|e| CheatsError::CustomError(e.to_string()))?;
+
+        // Run the action
+        let cheats = crate::core::actions::Cheats { provider };
+        if self.stop {
+            cheats.stop_impersonating(self.address).await?;
+            println!("Stopped impersonating {:?}", self.address);
+        } else {
+            cheats.impersonate(self.address).await?;
+            println!("Impersonating {:?}", self.address);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use std::fs;
+
+use clap::Args;
+
+pub use crate::core::actions::bundle::BundleError;
+use crate::{config::Config, core::actions::bundle::ShadowBundle};
+
+use super::paths::Paths;
+
+#[derive(Args)]
+pub struct Import {
+    /// The bundle file to import
+    pub bundle: String,
+
+    /// Merge the bundle into the shadow store. Without it, the bundle
+    /// replaces the shadow store.
+    #[clap(long)]
+    pub merge: bool,
+
+    /// Overwrite shadow contracts that conflict with the bundle when
+    /// merging
+    #[clap(long)]
+    pub overwrite: bool,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Imports shadow contracts from a bundle file.
+///
+/// The command uses the [`crate::core::actions::Import`] action
+/// under the hood, using the local file-based artifact store,
+/// and the local file-based shadow store.
+impl Import {
+    pub async fn run(&self) -> Result<(), BundleError> {
+        // Read the bundle
+        let contents = fs::read_to_string(&self.bundle)
+            .map_err(|e| BundleError::CustomError(e.to_string()))?;
+        let bundle: ShadowBundle = serde_json::from_str(&contents)
+            .map_err(|e| BundleError::CustomError(format!("Invalid bundle: {}", e)))?;
+        let count = bundle.contracts.len();
+
+        // Build the resources
+        let config = Config::load().map_err(|e| BundleError::CustomError(e.to_string()))?;
+        let artifacts_resource = self.paths.artifacts_resource(&config);
+        let shadow_resource = self.paths.shadow_resource(&config);
+
+        // Build the action
+        let import = crate::core::actions::Import {
+            bundle,
+            artifacts_resource,
+            shadow_resource,
+            merge: self.merge,
+            overwrite: self.overwrite,
+        };
+
+        // Run the action
+        import.run().await?;
+        println!("Imported {} shadow contracts from {}", count, self.bundle);
+
+        Ok(())
+    }
+}
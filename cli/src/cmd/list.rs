@@ -0,0 +1,51 @@
+use clap::Args;
+
+use crate::config::Config;
+pub use crate::core::actions::list::ListError;
+
+use super::paths::Paths;
+
+#[derive(Args)]
+pub struct List {
+    /// Only list the shadow contracts with any of these tags
+    #[clap(long = "tag")]
+    pub tags: Vec<String>,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Lists the shadow contracts.
+///
+/// The command uses the [`crate::core::actions::List`] action
+/// under the hood, using the local file-based shadow store.
+impl List {
+    pub async fn run(&self) -> Result<(), ListError> {
+        // Build the resources
+        let config = Config::load().map_err(|e| ListError::CustomError(e.to_string()))?;
+        let shadow_resource = self.paths.shadow_resource(&config);
+
+        // Build the action
+        let list = crate::core::actions::List {
+            tags: self.tags.clone(),
+            shadow_resource,
+        };
+
+        // Run the action
+        for contract in list.run().await? {
+            let mut line = format!(
+                "{}  {}:{}",
+                contract.address, contract.file_name, contract.contract_name
+            );
+            if !contract.tags.is_empty() {
+                line.push_str(&format!("  [{}]", contract.tags.join(", ")));
+            }
+            if !contract.enabled {
+                line.push_str("  (disabled)");
+            }
+            println!("{}", line);
+        }
+
+        Ok(())
+    }
+}
@@ -1,3 +1,33 @@
+pub mod api_tokens;
+pub mod attest;
+pub mod auth;
+pub mod backup;
+pub mod bench;
+pub mod bindings;
+pub mod cancel;
+pub mod compare;
 pub mod deploy;
+pub mod discover;
 pub mod events;
+pub mod explorer;
+pub mod export;
+pub mod export_events;
 pub mod fork;
+pub mod fund;
+pub mod generate;
+pub mod healthcheck;
+pub mod impersonate;
+pub mod import;
+pub mod list;
+pub mod patch;
+pub mod paths;
+pub mod redrive;
+pub mod restore;
+pub mod self_update;
+pub mod serve;
+pub mod stats;
+pub mod storage;
+pub mod tag;
+pub mod toggle;
+pub mod validate;
+pub mod verify;
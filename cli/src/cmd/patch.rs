@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use ethers::providers::{Http, Provider};
+
+pub use crate::core::actions::patch::PatchError;
+use crate::{config::Config, core::resources::shadow::ShadowResource};
+
+use super::paths::Paths;
+
+#[derive(Args)]
+pub struct Patch {
+    /// The address of the shadow contract to patch
+    pub address: String,
+
+    /// The patch file, with one `<variable> = <value>` per line,
+    /// e.g. `config.fee = 30` or `admins[0xab...] = true`
+    pub file: PathBuf,
+
+    /// The RPC URL of the running shadow fork
+    #[clap(long, default_value = "http://localhost:8545")]
+    pub rpc_url: String,
+
+    /// Print the slots that would be written, without writing them
+    #[clap(long)]
+    pub dry_run: bool,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Sets state variables of a shadow contract on the running shadow fork.
+///
+/// The command uses the [`crate::core::actions::Patch`] action
+/// under the hood, using the local file-based artifact store,
+/// and the local file-based shadow store.
+impl Patch {
+    pub async fn run(&self) -> Result<(), PatchError> {
+        let contents = std::fs::read_to_string(&self.file).map_err(|e| {
+            PatchError::CustomError(format!(
+                "Error reading patch file {}: {}",
+                self.file.display(),
+                e
+            ))
+        })?;
+        let patches = crate::core::actions::patch::parse_patch_file(&contents)?;
+
+        // Build the provider
+        let provider = Provider::<Http>::try_from(self.rpc_url.as_str())
+            .map_err(|e| PatchError::CustomError(e.to_string()))?;
+
+        // Build the resources
+        let config = Config::load().map_err(|e| PatchError::CustomError(e.to_string()))?;
+        let artifacts_resource = self.paths.artifacts_resource(&config);
+        let shadow_resource = self.paths.shadow_resource(&config);
+        let shadow_contract = shadow_resource
+            .get_by_address(&self.address.to_lowercase())
+            .await
+            .map_err(|e| PatchError::CustomError(e.to_string()))?;
+
+        // Build the action
+        let patch = crate::core::actions::Patch {
+            provider,
+            artifacts_resource,
+            shadow_contract,
+            patches,
+            dry_run: self.dry_run,
+        };
+
+        // Run the action
+        let patched = patch.run().await?;
+        for slot in &patched {
+            println!(
+                "{}: slot {:?} {:?} -> {:?}",
+                slot.path, slot.slot, slot.previous, slot.value
+            );
+        }
+        if !self.dry_run {
+            println!("Patched {} slots of {}", patched.len(), self.address);
+        }
+
+        Ok(())
+    }
+}
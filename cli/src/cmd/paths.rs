@@ -0,0 +1,191 @@
+use std::{
+    env,
+    path::{Component, Path, PathBuf, Prefix},
+};
+
+use clap::Args;
+
+use crate::{
+    config::{Config, DEFAULT_ARTIFACTS_DIR},
+    resources::{artifacts::LocalArtifactStore, foundry::FoundryProject, shadow::LocalShadowStore},
+};
+
+/// The artifacts and shadow store locations, shared by the commands
+/// that read them.
+///
+/// Flags take precedence over the `paths` section of the config file.
+/// Otherwise, the artifacts directory is read from the `foundry.toml`
+/// of the enclosing Foundry project, so the CLI works from any of its
+/// subdirectories.
+#[derive(Args, Clone, Debug, Default)]
+pub struct Paths {
+    /// The directory containing the compiled contract artifacts [default: contracts/out]
+    #[clap(long, env = "SHADOW_ARTIFACTS_DIR")]
+    pub artifacts_dir: Option<PathBuf>,
+
+    /// The directory containing shadow.json [default: the current directory]
+    #[clap(long, env = "SHADOW_STORE_PATH")]
+    pub store_path: Option<PathBuf>,
+}
+
+impl Paths {
+    /// Returns the artifacts directory.
+    pub fn artifacts_dir(&self, config: &Config, project: Option<&FoundryProject>) -> PathBuf {
+        self.artifacts_dir
+            .clone()
+            .or_else(|| config.paths.artifacts_dir.clone())
+            .or_else(|| project.map(|project| project.out.clone()))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_ARTIFACTS_DIR))
+    }
+
+    /// Returns the directory containing `shadow.json`.
+    pub fn store_path(&self, config: &Config) -> PathBuf {
+        self.store_path
+            .clone()
+            .or_else(|| config.paths.store_path.clone())
+            .unwrap_or_else(current_dir)
+    }
+
+    /// Builds the local file-based artifact store.
+    ///
+    /// Sources are resolved relative to the enclosing Foundry project,
+    /// or the current directory if there is none.
+    pub fn artifacts_resource(&self, config: &Config) -> LocalArtifactStore {
+        let project = discover_project();
+        let artifacts_dir = self.artifacts_dir(config, project.as_ref());
+        match project {
+            Some(project) => {
+                log::debug!(
+                    "Using Foundry project at {} (src: {}, out: {})",
+                    project.root.display(),
+                    project.src.display(),
+                    project.out.display()
+                );
+                LocalArtifactStore::with_root(artifacts_dir, project.root)
+            }
+            None => LocalArtifactStore::new(artifacts_dir),
+        }
+    }
+
+    /// Builds the local file-based shadow store.
+    pub fn shadow_resource(&self, config: &Config) -> LocalShadowStore {
+        LocalShadowStore::new(self.store_path(config))
+    }
+}
+
+/// Discovers the Foundry project enclosing the current directory.
+pub fn discover_project() -> Option<FoundryProject> {
+    match FoundryProject::discover(&current_dir()) {
+        Ok(project) => project,
+        Err(e) => {
+            log::warn!("Ignoring foundry.toml: {}", e);
+            None
+        }
+    }
+}
+
+/// Returns the current directory, or `.` if it can't be read, e.g.
+/// because it was deleted.
+///
+/// On Windows, the current directory may be a verbatim `\\?\C:\...`
+/// path, which doesn't accept `/` separators and which other tools
+/// don't print. It is returned as a regular `C:\...` path instead.
+pub fn current_dir() -> PathBuf {
+    match env::current_dir() {
+        Ok(dir) => strip_verbatim(&dir),
+        Err(e) => {
+            log::warn!("Error reading the current directory: {}", e);
+            PathBuf::from(".")
+        }
+    }
+}
+
+/// Turns a verbatim disk path, e.g. `\\?\C:\project`, into a regular
+/// one, e.g. `C:\project`. Other paths are returned as is.
+fn strip_verbatim(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::VerbatimDisk(disk) => {
+                let mut stripped = PathBuf::from(format!("{}:\\", disk as char));
+                stripped.extend(components.filter(|c| !matches!(c, Component::RootDir)));
+                stripped
+            }
+            _ => path.to_path_buf(),
+        },
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PathsConfig;
+
+    #[test]
+    fn can_resolve_paths() {
+        let config = Config {
+            paths: PathsConfig {
+                artifacts_dir: Some("packages/contracts/out".into()),
+                store_path: None,
+            },
+            ..Default::default()
+        };
+        let paths = Paths {
+            artifacts_dir: None,
+            store_path: Some("/tmp/shadow".into()),
+        };
+        assert_eq!(
+            paths.artifacts_dir(&config, None),
+            Path::new("packages/contracts/out")
+        );
+        assert_eq!(paths.store_path(&config), Path::new("/tmp/shadow"));
+
+        let paths = Paths {
+            artifacts_dir: Some("out".into()),
+            store_path: None,
+        };
+        assert_eq!(paths.artifacts_dir(&config, None), Path::new("out"));
+        assert_eq!(
+            paths.artifacts_dir(&Config::default(), None),
+            Path::new(DEFAULT_ARTIFACTS_DIR)
+        );
+
+        let project = FoundryProject {
+            root: "/project".into(),
+            src: "/project/src".into(),
+            out: "/project/out".into(),
+        };
+        assert_eq!(
+            paths.artifacts_dir(&Config::default(), Some(&project)),
+            Path::new("out")
+        );
+        let paths = Paths::default();
+        assert_eq!(
+            paths.artifacts_dir(&Config::default(), Some(&project)),
+            Path::new("/project/out")
+        );
+    }
+
+    #[test]
+    fn keeps_regular_paths() {
+        assert_eq!(
+            strip_verbatim(Path::new("/project/contracts")),
+            Path::new("/project/contracts")
+        );
+        assert_eq!(strip_verbatim(Path::new("out")), Path::new("out"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn strips_verbatim_disk_prefix() {
+        assert_eq!(
+            strip_verbatim(Path::new(r"\\?\C:\project\contracts")),
+            Path::new(r"C:\project\contracts")
+        );
+        assert_eq!(
+            strip_verbatim(Path::new(r"\\?\UNC\server\share")),
+            Path::new(r"\\?\UNC\server\share")
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::config::Config;
+pub use crate::core::actions::redrive::RedriveError;
+use crate::core::resources::sink::EventSink;
+use crate::resources::dead_letters::{DeadLetterFile, DEFAULT_DEAD_LETTERS_PATH};
+use crate::resources::stores;
+
+use super::paths::Paths;
+
+#[derive(Args)]
+pub struct Redrive {
+    /// The dead-letter file written by `shadow events --dead-letters`
+    #[clap(long, default_value = DEFAULT_DEAD_LETTERS_PATH)]
+    pub dead_letters: String,
+
+    /// Store the redriven events in this event store: the path of a
+    /// JSONL archive, `sqlite://<path>`, or a `postgres://` URL
+    #[clap(long)]
+    pub archive: String,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Re-processes the logs in the dead-letter queue.
+///
+/// The command uses the [`crate::core::actions::Redrive`] action
+/// under the hood, using the local file-based artifact store and
+/// the dead-letter file.
+impl Redrive {
+    pub async fn run(&self) -> Result<(), RedriveError> {
+        // Build the resources
+        let config = Config::load().map_err(|e| RedriveError::CustomError(e.to_string()))?;
+        let artifacts_resource = self.paths.artifacts_resource(&config);
+        let dead_letters = DeadLetterFile::new(PathBuf::from(&self.dead_letters));
+        let store = stores::open(&self.archive)
+            .await
+            .map_err(|e| RedriveError::CustomError(format!("Error opening archive: {}", e)))?;
+        let sinks: Vec<Box<dyn EventSink>> = vec![Box::new(store)];
+
+        // Build the action
+        let redrive = crate::core::actions::Redrive {
+            dead_letters,
+            artifacts_resource,
+            sinks,
+        };
+
+        // Run the action
+        let report = redrive.run().await?;
+        for event in &report.redriven {
+            println!("=> Transaction: {}", event.transaction_hash);
+            println!("{}", serde_json::to_string(&event.data).unwrap_or_default());
+        }
+        for letter in &report.failed {
+            println!(
+                "Failed again: {} log {} ({})",
+                letter.transaction_hash, letter.raw.log_index, letter.error
+            );
+        }
+        println!(
+            "Redrove {} dead letters, {} left in {}",
+            report.redriven.len(),
+            report.failed.len(),
+            self.dead_letters
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use clap::Args;
+
+pub use crate::core::actions::backup::BackupError;
+use crate::{config::Config, core::actions::backup::list_backups, resources::shadow::SHADOW_FILE};
+
+use super::{backup::open_store, paths::Paths};
+
+#[derive(Args)]
+pub struct Restore {
+    /// The backup to restore [default: the latest]
+    pub backup: Option<String>,
+
+    /// Where the backups are stored: a directory, or an s3://bucket/prefix
+    /// or gs://bucket/prefix URL with the archive feature
+    #[clap(long, env = "SHADOW_BACKUP_FROM")]
+    pub from: String,
+
+    /// The directory to restore the event archive files to
+    #[clap(long, default_value = ".")]
+    pub archive_dir: PathBuf,
+
+    /// Overwrite existing files
+    #[clap(long)]
+    pub force: bool,
+
+    /// List the backups instead of restoring one
+    #[clap(long)]
+    pub list: bool,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Restores `shadow.json` and the event archive from a backup.
+///
+/// The command uses the [`crate::core::actions::Restore`] action
+/// under the hood, reading the backups from a local directory or a bucket.
+impl Restore {
+    pub async fn run(&self) -> Result<(), BackupError> {
+        let store = open_store(&self.from)?;
+        if self.list {
+            for name in list_backups(store.as_ref()).await? {
+                println!("{}", name);
+            }
+            return Ok(());
+        }
+
+        // Build the action
+        let config = Config::load().map_err(|e| BackupError::CustomError(e.to_string()))?;
+        let restore = crate::core::actions::Restore {
+            store,
+            name: self.backup.clone(),
+            shadow_file: self.paths.store_path(&config).join(SHADOW_FILE),
+            archive_dir: self.archive_dir.clone(),
+            force: self.force,
+        };
+
+        // Run the action
+        let (name, manifest) = restore.run().await?;
+        println!("Restored {} files from {}", manifest.files.len(), name);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,387 @@
+use std::{
+    env, fs,
+    io::{Read, Write},
+    path::Path,
+};
+
+use clap::Args;
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{config::Config, resources::http::shared_client};
+
+/// The GitHub API endpoint listing the CLI's releases
+const RELEASES_URL: &str = "https://api.github.com/repos/shadow-hq/shadow-starter-pack/releases";
+
+/// The release asset listing the SHA-256 checksums of the binaries,
+/// in `sha256sum` format
+const CHECKSUMS_ASSET: &str = "checksums.txt";
+
+/// The release asset holding the minisign signature of the checksums
+const SIGNATURE_ASSET: &str = "checksums.txt.minisig";
+
+/// The minisign public key release checksums are signed with, embedded
+/// by the release workflow. Builds from source don't have one.
+const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("SHADOW_RELEASE_PUBLIC_KEY");
+
+#[derive(Error, Debug)]
+pub enum SelfUpdateError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+}
+
+#[derive(Args)]
+pub struct SelfUpdate {
+    /// The release to install, e.g. v0.2.0 [default: the latest release]
+    #[clap(long)]
+    pub version: Option<String>,
+
+    /// Only check whether a newer release is available
+    #[clap(long)]
+    pub check: bool,
+
+    /// Reinstall the release even if it isn't newer than this binary
+    #[clap(long)]
+    pub force: bool,
+}
+
+/// A GitHub release
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+/// A file attached to a GitHub release
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Result<&Asset, SelfUpdateError> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| {
+                SelfUpdateError::CustomError(format!(
+                    "Release {} has no {} asset",
+                    self.tag_name, name
+                ))
+            })
+    }
+}
+
+/// Replaces the running binary with the one of a GitHub release.
+///
+/// Releases attach a gzipped binary per target, named
+/// `shadow-<target>.gz`, a `checksums.txt` file, and its minisign
+/// signature. The checksums are checked against the release public key
+/// embedded in this binary, and the download against its checksum,
+/// before the binary is replaced.
+impl SelfUpdate {
+    pub async fn run(&self) -> Result<(), SelfUpdateError> {
+        let config = Config::load().map_err(|e| SelfUpdateError::CustomError(e.to_string()))?;
+        let client = shared_client(&config.http);
+
+        let release = self.fetch_release(&client).await?;
+        let current = env!("CARGO_PKG_VERSION");
+        let newer = is_newer(&release.tag_name, current)?;
+        if self.check {
+            if newer {
+                println!(
+                    "shadow {} is available (installed: {}), run `shadow self-update` to install it",
+                    release.tag_name, current
+                );
+            } else {
+                println!("shadow {} is up to date", current);
+            }
+            return Ok(());
+        }
+        if !newer && !self.force {
+            println!(
+                "shadow {} is up to date (latest: {}), use --force to reinstall",
+                current, release.tag_name
+            );
+            return Ok(());
+        }
+
+        let target = current_target().ok_or_else(|| {
+            SelfUpdateError::CustomError(format!(
+                "No release binary for {} {}, please build from source",
+                env::consts::OS,
+                env::consts::ARCH
+            ))
+        })?;
+        let public_key = RELEASE_PUBLIC_KEY.ok_or_else(|| {
+            SelfUpdateError::CustomError(
+                "This binary was built from source without a release public key, please update \
+                 it with `cargo install --path cli`"
+                    .to_owned(),
+            )
+        })?;
+        let asset_name = format!("shadow-{}.gz", target);
+        let checksums = download(&client, release.asset(CHECKSUMS_ASSET)?).await?;
+        let signature = download(&client, release.asset(SIGNATURE_ASSET)?).await?;
+        verify_signature(public_key, &checksums, &String::from_utf8_lossy(&signature))?;
+        let checksums = String::from_utf8(checksums).map_err(|e| {
+            SelfUpdateError::CustomError(format!("Invalid {}: {}", CHECKSUMS_ASSET, e))
+        })?;
+        let expected = find_checksum(&checksums, &asset_name).ok_or_else(|| {
+            SelfUpdateError::CustomError(format!(
+                "{} has no checksum for {}",
+                CHECKSUMS_ASSET, asset_name
+            ))
+        })?;
+
+        println!("Downloading {} {}...", asset_name, release.tag_name);
+        let archive = download(&client, release.asset(&asset_name)?).await?;
+        verify_checksum(&archive, &expected)?;
+        let mut binary = Vec::new();
+        GzDecoder::new(archive.as_slice())
+            .read_to_end(&mut binary)
+            .map_err(|e| {
+                SelfUpdateError::CustomError(format!("Error decompressing {}: {}", asset_name, e))
+            })?;
+
+        let exe = env::current_exe().map_err(|e| {
+            SelfUpdateError::CustomError(format!("Error locating the shadow binary: {}", e))
+        })?;
+        replace_binary(&exe, &binary)?;
+        println!(
+            "Updated shadow {} to {} at {}",
+            current,
+            release.tag_name,
+            exe.display()
+        );
+
+        Ok(())
+    }
+
+    /// Fetches the requested release, or the latest one.
+    async fn fetch_release(&self, client: &reqwest::Client) -> Result<Release, SelfUpdateError> {
+        let url = match &self.version {
+            Some(version) => format!("{}/tags/{}", RELEASES_URL, tag(version)),
+            None => format!("{}/latest", RELEASES_URL),
+        };
+        client
+            .get(&url)
+            .header(reqwest::header::USER_AGENT, "shadow-cli")
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| SelfUpdateError::CustomError(format!("Error fetching release: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| SelfUpdateError::CustomError(format!("Invalid release: {}", e)))
+    }
+}
+
+/// Downloads a release asset.
+async fn download(client: &reqwest::Client, asset: &Asset) -> Result<Vec<u8>, SelfUpdateError> {
+    let bytes = client
+        .get(&asset.browser_download_url)
+        .header(reqwest::header::USER_AGENT, "shadow-cli")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| {
+            SelfUpdateError::CustomError(format!("Error downloading {}: {}", asset.name, e))
+        })?
+        .bytes()
+        .await
+        .map_err(|e| {
+            SelfUpdateError::CustomError(format!("Error downloading {}: {}", asset.name, e))
+        })?;
+    Ok(bytes.to_vec())
+}
+
+/// Returns the release tag of a version, e.g. `v0.2.0` for `0.2.0`.
+fn tag(version: &str) -> String {
+    if version.starts_with('v') {
+        version.to_owned()
+    } else {
+        format!("v{}", version)
+    }
+}
+
+/// Returns whether a release tag is a newer version than the current one.
+fn is_newer(tag: &str, current: &str) -> Result<bool, SelfUpdateError> {
+    let parse = |version: &str| {
+        semver::Version::parse(version.trim_start_matches('v')).map_err(|e| {
+            SelfUpdateError::CustomError(format!("Invalid version {}: {}", version, e))
+        })
+    };
+    Ok(parse(tag)? > parse(current)?)
+}
+
+/// Returns the target triple of the release binary for this platform.
+fn current_target() -> Option<&'static str> {
+    target(env::consts::OS, env::consts::ARCH)
+}
+
+/// Returns the target triple of the release binary for a platform, if
+/// releases have one.
+fn target(os: &str, arch: &str) -> Option<&'static str> {
+    match (os, arch) {
+        ("linux", "x86_64") => Some("x86_64-unknown-linux-gnu"),
+        ("macos", "x86_64") => Some("x86_64-apple-darwin"),
+        ("macos", "aarch64") => Some("aarch64-apple-darwin"),
+        ("windows", "x86_64") => Some("x86_64-pc-windows-msvc"),
+        _ => None,
+    }
+}
+
+/// Finds the checksum of a file in `sha256sum` output.
+fn find_checksum(checksums: &str, file_name: &str) -> Option<String> {
+    checksums.lines().find_map(|line| {
+        let (checksum, name) = line.trim().split_once(char::is_whitespace)?;
+        // sha256sum marks files read in binary mode with a `*`
+        (name.trim_start().trim_start_matches('*') == file_name).then(|| checksum.to_lowercase())
+    })
+}
+
+/// Checks that the SHA-256 checksum of a download matches the expected
+/// hex checksum.
+fn verify_checksum(bytes: &[u8], expected: &str) -> Result<(), SelfUpdateError> {
+    let actual = hex::encode(Sha256::digest(bytes));
+    if actual != expected {
+        return Err(SelfUpdateError::CustomError(format!(
+            "Checksum mismatch: expected {}, got {}",
+            expected, actual
+        )));
+    }
+    Ok(())
+}
+
+/// Checks the minisign signature of the release checksums against the
+/// release public key.
+fn verify_signature(
+    public_key: &str,
+    checksums: &[u8],
+    signature: &str,
+) -> Result<(), SelfUpdateError> {
+    let public_key = minisign_verify::PublicKey::from_base64(public_key.trim())
+        .map_err(|e| SelfUpdateError::CustomError(format!("Invalid release public key: {}", e)))?;
+    let signature = minisign_verify::Signature::decode(signature)
+        .map_err(|e| SelfUpdateError::CustomError(format!("Invalid {}: {}", SIGNATURE_ASSET, e)))?;
+    public_key
+        .verify(checksums, &signature, false)
+        .map_err(|e| {
+            SelfUpdateError::CustomError(format!(
+                "{} isn't signed by the release key: {}",
+                CHECKSUMS_ASSET, e
+            ))
+        })
+}
+
+/// Replaces a binary, writing the new one next to it first so the
+/// replacement is a rename on the same filesystem.
+///
+/// Windows doesn't allow replacing a running binary, but allows renaming
+/// it, so the old binary is moved aside to `<name>.old` first.
+fn replace_binary(exe: &Path, binary: &[u8]) -> Result<(), SelfUpdateError> {
+    let dir = exe.parent().ok_or_else(|| {
+        SelfUpdateError::CustomError(format!("Invalid binary path {}", exe.display()))
+    })?;
+    let write_error = |e: std::io::Error| {
+        SelfUpdateError::CustomError(format!(
+            "Error writing the new binary to {}: {}",
+            dir.display(),
+            e
+        ))
+    };
+    let mut file = tempfile::NamedTempFile::new_in(dir).map_err(write_error)?;
+    file.write_all(binary).map_err(write_error)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.as_file()
+            .set_permissions(fs::Permissions::from_mode(0o755))
+            .map_err(write_error)?;
+    }
+    if cfg!(windows) {
+        let old = exe.with_extension("old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).map_err(|e| {
+            SelfUpdateError::CustomError(format!("Error moving {} aside: {}", exe.display(), e))
+        })?;
+    }
+    file.persist(exe).map_err(|e| {
+        SelfUpdateError::CustomError(format!("Error replacing {}: {}", exe.display(), e))
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_compare_versions() {
+        assert!(is_newer("v0.2.0", "0.1.0").unwrap());
+        assert!(is_newer("0.1.1", "0.1.0").unwrap());
+        assert!(!is_newer("v0.1.0", "0.1.0").unwrap());
+        assert!(!is_newer("v0.1.0-rc.1", "0.1.0").unwrap());
+        assert!(is_newer("latest", "0.1.0").is_err());
+        assert_eq!(tag("0.2.0"), "v0.2.0");
+        assert_eq!(tag("v0.2.0"), "v0.2.0");
+    }
+
+    #[test]
+    fn can_map_platforms_to_targets() {
+        assert_eq!(target("linux", "x86_64"), Some("x86_64-unknown-linux-gnu"));
+        assert_eq!(target("macos", "aarch64"), Some("aarch64-apple-darwin"));
+        assert_eq!(target("freebsd", "x86_64"), None);
+    }
+
+    #[test]
+    fn can_verify_checksums() {
+        let bytes = b"shadow";
+        let checksum = hex::encode(Sha256::digest(bytes));
+        let checksums = format!(
+            "{}  shadow-x86_64-unknown-linux-gnu.gz\n{} *shadow-aarch64-apple-darwin.gz\n",
+            "ab".repeat(32),
+            checksum.to_uppercase()
+        );
+        assert_eq!(
+            find_checksum(&checksums, "shadow-aarch64-apple-darwin.gz"),
+            Some(checksum.clone())
+        );
+        assert_eq!(
+            find_checksum(&checksums, "shadow-x86_64-pc-windows-msvc.gz"),
+            None
+        );
+        assert!(verify_checksum(bytes, &checksum).is_ok());
+        assert!(verify_checksum(b"tampered", &checksum).is_err());
+    }
+
+    #[test]
+    fn can_verify_signatures() {
+        // A test key pair, not the release key
+        let public_key = "RWRFLnG+uf2UlyRWzcon6BS4/8x8K1nZAPZdgdcG9S/6EBBoRkixJ4fM";
+        let checksums = b"7b2ba6e4f1b0ba44e2b1d8b82d74dd5e9d3f20f7a08d4e3f6b6c2e1e8b1b8c3d  \
+                          shadow-x86_64-unknown-linux-gnu.gz\n";
+        let signature = "untrusted comment: signature from minisign secret key\n\
+            RURFLnG+uf2Ul1uPCrknU/p467bVj+/M1oaKtLbNAzkm9kUzVZlYcP3VIll/nAzpnf2FCcp7NtF/Kvv3rtGc4ngApqdZQ2RjFQQ=\n\
+            trusted comment: timestamp:1700000000\tfile:checksums.txt\thashed\n\
+            M2t4DqikkWVtGLYRFp/c2skja9tgTm244r7CSN7qhI/KEcvbjs4cCPbdkPK/OmWCgqEHnrHxw9jvaHLFp+WADA==\n";
+        assert!(verify_signature(public_key, checksums, signature).is_ok());
+        assert!(verify_signature(public_key, b"tampered", signature).is_err());
+        assert!(verify_signature(public_key, checksums, "not a signature").is_err());
+    }
+
+    #[test]
+    fn can_replace_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("shadow");
+        fs::write(&exe, b"old").unwrap();
+        replace_binary(&exe, b"new").unwrap();
+        assert_eq!(fs::read(&exe).unwrap(), b"new");
+    }
+}
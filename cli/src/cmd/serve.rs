@@ -0,0 +1,120 @@
+use std::net::SocketAddr;
+
+use clap::Args;
+use thiserror::Error;
+
+use crate::{
+    cmd::{
+        api_tokens::ApiTokens,
+        events::{build_events, warn_schema_drift, EventsError},
+        paths::Paths,
+    },
+    resources::{sinks::BroadcastSink, stores},
+};
+
+#[derive(Args)]
+pub struct Serve {
+    /// The shadow contract to serve events for.
+    ///
+    /// Can either be in the form ContractFile.sol (if the filename and contract name are the same), or ContractFile.sol:ContractName.
+    pub contract: String,
+
+    /// The event signature to serve.
+    pub event_signature: String,
+
+    /// Store decoded events in this event store, which range queries are answered from.
+    ///
+    /// Either the path of a JSONL archive, `sqlite://<path>`, a
+    /// `postgres://` URL, or `memory://`.
+    #[clap(long)]
+    pub archive: Option<String>,
+
+    /// Include the raw log (address, topics, data, block and log index)
+    /// with each served event
+    #[clap(long)]
+    pub raw: bool,
+
+    /// Push events to websocket clients on this address, e.g. 127.0.0.1:8546
+    #[clap(long)]
+    pub ws: Option<SocketAddr>,
+
+    /// Serve the gRPC API on this address, e.g. 127.0.0.1:50051
+    #[cfg(feature = "grpc")]
+    #[clap(long)]
+    pub grpc: Option<SocketAddr>,
+
+    #[clap(flatten)]
+    pub api_tokens: ApiTokens,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum ServeError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Error related to listening to events
+    #[error("EventsError: {0}")]
+    EventsError(#[from] EventsError),
+}
+
+/// Listens to events from a shadow contract on a local fork,
+/// and serves them to clients over the enabled APIs.
+impl Serve {
+    pub async fn run(&self) -> Result<(), ServeError> {
+        // Build the action
+        let mut events =
+            build_events(&self.contract, &self.event_signature, &[], &self.paths).await?;
+        events.set_include_raw(self.raw);
+        let broadcast = BroadcastSink::new(1024);
+        events.add_sink(broadcast.clone());
+        let store = match &self.archive {
+            Some(archive) => {
+                let store = stores::open(archive).await.map_err(|e| {
+                    ServeError::CustomError(format!("Error opening archive: {}", e))
+                })?;
+                warn_schema_drift(&events, &*store).await?;
+                events.add_sink(store.clone());
+                Some(store)
+            }
+            None => None,
+        };
+
+        // Start the servers
+        let auth = self.api_tokens.auth();
+        let mut servers = tokio::task::JoinSet::new();
+        if let Some(addr) = self.ws {
+            let server = crate::server::ws::WsServer::new(broadcast.clone(), auth.clone());
+            servers.spawn(async move { server.serve(addr).await.map_err(|e| e.to_string()) });
+        }
+        #[cfg(feature = "grpc")]
+        if let Some(addr) = self.grpc {
+            let service = crate::server::grpc::EventsService::new(
+                broadcast.clone(),
+                store.clone(),
+                auth.clone(),
+            );
+            servers.spawn(async move { service.serve(addr).await.map_err(|e| e.to_string()) });
+        }
+        if servers.is_empty() {
+            return Err(ServeError::CustomError(
+                "No server enabled, pass --ws or --grpc".to_owned(),
+            ));
+        }
+
+        // Run the action until it or any server stops
+        tokio::select! {
+            result = events.run() => result?,
+            Some(result) = servers.join_next() => {
+                result
+                    .map_err(|e| ServeError::CustomError(e.to_string()))?
+                    .map_err(ServeError::CustomError)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use clap::Args;
+
+pub use crate::core::actions::stats::StatsError;
+use crate::core::resources::event_store::EventQuery;
+use crate::resources::stores;
+
+#[derive(Args)]
+pub struct Stats {
+    /// The event store written by `shadow events`.
+    ///
+    /// Either the path of a JSONL archive, `sqlite://<path>`, or a
+    /// `postgres://` URL.
+    #[clap(long, default_value = "events.jsonl")]
+    pub archive: String,
+
+    /// Only include events from this block onwards
+    #[clap(long)]
+    pub from_block: Option<u64>,
+
+    /// Only include events up to and including this block
+    #[clap(long)]
+    pub to_block: Option<u64>,
+
+    /// Only include events with this name, e.g. Transfer
+    #[clap(long)]
+    pub event: Option<String>,
+
+    /// Rank the most frequent values of this decoded field, e.g. to
+    /// find the top addresses
+    #[clap(long)]
+    pub top_by: Option<String>,
+
+    /// The number of top values to show
+    #[clap(long, default_value_t = 10)]
+    pub top: usize,
+
+    /// Show the min, max and average of this decoded numeric field
+    #[clap(long)]
+    pub field: Option<String>,
+}
+
+/// Prints statistics over the local event archive.
+///
+/// The command uses the [`crate::core::actions::Stats`] action
+/// under the hood, reading events from an event store.
+impl Stats {
+    pub async fn run(&self) -> Result<(), StatsError> {
+        // Read the events from the store
+        let store = stores::open(&self.archive)
+            .await
+            .map_err(StatsError::ArchiveError)?;
+        let query = EventQuery {
+            from_block: self.from_block,
+            to_block: self.to_block,
+            event: self.event.clone(),
+        };
+        let events = store
+            .query(&query)
+            .await
+            .map_err(StatsError::ArchiveError)?;
+
+        // Build the action
+        let stats = crate::core::actions::Stats {
+            events,
+            from_block: self.from_block,
+            to_block: self.to_block,
+            event: self.event.clone(),
+            top_by: self.top_by.clone(),
+            top: self.top,
+            field: self.field.clone(),
+        };
+
+        // Run the action
+        let report = stats.run()?;
+
+        println!("Events: {}", report.total);
+        for (event, hours) in &report.per_hour {
+            println!("\n{} per hour (UTC):", event);
+            for (hour, count) in hours {
+                println!("  {}  {}", hour, count);
+            }
+        }
+        if let Some(top_by) = &self.top_by {
+            println!("\nTop {} by {}:", self.top, top_by);
+            for (value, count) in &report.top_values {
+                println!("  {}  {}", value, count);
+            }
+        }
+        if let (Some(field), Some(summary)) = (&self.field, &report.summary) {
+            println!("\n{} over {} events:", field, summary.count);
+            println!("  min  {}", summary.min);
+            println!("  max  {}", summary.max);
+            println!("  avg  {}", summary.avg);
+        }
+
+        Ok(())
+    }
+}
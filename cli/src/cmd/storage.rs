@@ -0,0 +1,83 @@
+use clap::Args;
+use ethers::{
+    providers::{Http, Provider},
+    types::BlockId,
+};
+
+pub use crate::core::actions::storage::StorageError;
+use crate::{config::Config, core::resources::shadow::ShadowResource, providers};
+
+use super::paths::Paths;
+
+#[derive(Args)]
+pub struct Storage {
+    /// The address of the shadow contract
+    pub address: String,
+
+    /// The state variable to read, with struct members and mapping keys
+    /// or array indexes, e.g. `config.fee` or `balances[0xab...]`
+    pub path: String,
+
+    /// The RPC URL to read from. Defaults to the running shadow fork.
+    #[clap(long, default_value = "http://localhost:8545")]
+    pub rpc_url: String,
+
+    /// Read from mainnet instead of the shadow fork, using the
+    /// ETH_RPC_URL environment variable
+    #[clap(long, conflicts_with = "rpc_url")]
+    pub mainnet: bool,
+
+    /// The block to read at. Defaults to the latest block.
+    #[clap(long)]
+    pub block: Option<u64>,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Reads a state variable of a shadow contract, from the running
+/// shadow fork or from mainnet.
+///
+/// The command uses the [`crate::core::actions::Storage`] action
+/// under the hood, using the local file-based artifact store,
+/// and the local file-based shadow store.
+impl Storage {
+    pub async fn run(&self) -> Result<(), StorageError> {
+        // Build the provider
+        let rpc_url = if self.mainnet {
+            providers::rpc_url_from_env("ETH_RPC_URL")?
+        } else {
+            self.rpc_url.clone()
+        };
+        let provider = Provider::<Http>::try_from(rpc_url.as_str())
+            .map_err(|e| StorageError::CustomError(e.to_string()))?;
+
+        // Build the resources
+        let config = Config::load().map_err(|e| StorageError::CustomError(e.to_string()))?;
+        let artifacts_resource = self.paths.artifacts_resource(&config);
+        let shadow_resource = self.paths.shadow_resource(&config);
+        let shadow_contract = shadow_resource
+            .get_by_address(&self.address.to_lowercase())
+            .await
+            .map_err(|e| StorageError::CustomError(e.to_string()))?;
+
+        // Build the action
+        let storage = crate::core::actions::Storage {
+            provider,
+            artifacts_resource,
+            shadow_contract,
+            path: self.path.clone(),
+            block: self.block.map(BlockId::from),
+        };
+
+        // Run the action
+        let value = storage.run().await?;
+        println!("{} ({}) = {}", self.path, value.type_label, value.value);
+        println!(
+            "slot {:?}, offset {}, raw {:?}",
+            value.slot, value.offset, value.raw
+        );
+
+        Ok(())
+    }
+}
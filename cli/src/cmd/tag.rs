@@ -0,0 +1,49 @@
+use clap::Args;
+
+use crate::config::Config;
+pub use crate::core::actions::tag::TagError;
+
+use super::paths::Paths;
+
+#[derive(Args)]
+pub struct Tag {
+    /// The address of the shadow contract
+    pub address: String,
+
+    /// The tags to add, e.g. defi nft
+    #[clap(required = true)]
+    pub tags: Vec<String>,
+
+    /// Remove the tags instead of adding them
+    #[clap(long)]
+    pub remove: bool,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Adds or removes tags on a shadow contract.
+///
+/// The command uses the [`crate::core::actions::Tag`] action
+/// under the hood, using the local file-based shadow store.
+impl Tag {
+    pub async fn run(&self) -> Result<(), TagError> {
+        // Build the resources
+        let config = Config::load().map_err(|e| TagError::CustomError(e.to_string()))?;
+        let shadow_resource = self.paths.shadow_resource(&config);
+
+        // Build the action
+        let tag = crate::core::actions::Tag {
+            address: self.address.clone(),
+            tags: self.tags.clone(),
+            remove: self.remove,
+            shadow_resource,
+        };
+
+        // Run the action
+        let contract = tag.run().await?;
+        println!("{} tags: [{}]", self.address, contract.tags.join(", "));
+
+        Ok(())
+    }
+}
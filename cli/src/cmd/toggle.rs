@@ -0,0 +1,45 @@
+use clap::Args;
+
+use crate::config::Config;
+pub use crate::core::actions::toggle::ToggleError;
+
+use super::paths::Paths;
+
+#[derive(Args)]
+pub struct Toggle {
+    /// The address of the shadow contract
+    pub address: String,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Enables or disables a shadow contract.
+///
+/// The command uses the [`crate::core::actions::Toggle`] action
+/// under the hood, using the local file-based shadow store.
+/// Restart the shadow fork for the change to take effect.
+impl Toggle {
+    pub async fn run(&self, enabled: bool) -> Result<(), ToggleError> {
+        // Build the resources
+        let config = Config::load().map_err(|e| ToggleError::CustomError(e.to_string()))?;
+        let shadow_resource = self.paths.shadow_resource(&config);
+
+        // Build the action
+        let toggle = crate::core::actions::Toggle {
+            address: self.address.clone(),
+            enabled,
+            shadow_resource,
+        };
+
+        // Run the action
+        toggle.run().await?;
+        println!(
+            "{} {}",
+            if enabled { "Enabled" } else { "Disabled" },
+            self.address
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use thiserror::Error;
+
+use crate::{
+    config::Config,
+    resources::shadow::SHADOW_FILE,
+    validate::{validate_file, FileKind},
+};
+
+use super::paths::Paths;
+
+#[derive(Error, Debug)]
+pub enum ValidateError {
+    /// Some files have problems
+    #[error("{0} problems found")]
+    Invalid(usize),
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+}
+
+#[derive(Args)]
+pub struct Validate {
+    /// The config file [default: shadow.config.json, or SHADOW_CONFIG]
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+
+    /// Init calls files, as passed to `shadow deploy --init-calls`
+    #[clap(long)]
+    pub init_calls: Vec<PathBuf>,
+
+    /// Scenario files, as passed to `shadow fork --scenario`
+    #[clap(long)]
+    pub scenario: Vec<PathBuf>,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Checks the config file, init calls, scenarios and `shadow.json`,
+/// printing every problem with its line and column.
+///
+/// Missing config and shadow store files are skipped, since the other
+/// commands fall back to defaults for them.
+impl Validate {
+    pub async fn run(&self) -> Result<(), ValidateError> {
+        let config_path = self.config.clone().unwrap_or_else(Config::path);
+        let config = if config_path.exists() {
+            Config::load_from(&config_path).unwrap_or_default()
+        } else {
+            println!("{}: not found, using the defaults", config_path.display());
+            Config::default()
+        };
+        let shadow_file = self.paths.store_path(&config).join(SHADOW_FILE);
+
+        let mut files = Vec::new();
+        if config_path.exists() {
+            files.push((FileKind::Config, config_path));
+        }
+        files.extend(
+            self.init_calls
+                .iter()
+                .map(|path| (FileKind::InitCalls, path.clone())),
+        );
+        files.extend(
+            self.scenario
+                .iter()
+                .map(|path| (FileKind::Scenario, path.clone())),
+        );
+        if shadow_file.exists() {
+            files.push((FileKind::ShadowStore, shadow_file));
+        } else {
+            println!("{}: not found, skipped", shadow_file.display());
+        }
+
+        let mut problems = 0;
+        for (kind, path) in files {
+            let diagnostics = validate_file(kind, &path);
+            if diagnostics.is_empty() {
+                println!("{}: valid {}", path.display(), kind);
+            }
+            for diagnostic in &diagnostics {
+                eprintln!("{}", diagnostic);
+            }
+            problems += diagnostics.len();
+        }
+
+        match problems {
+            0 => Ok(()),
+            problems => Err(ValidateError::Invalid(problems)),
+        }
+    }
+}
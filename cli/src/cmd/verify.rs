@@ -0,0 +1,97 @@
+use clap::Args;
+
+pub use crate::core::actions::verify::VerifyError;
+use crate::{
+    config::{Config, VerifierKind},
+    core::resources::verifier::{VerificationRequest, VerifierResource},
+    resources::{
+        http::client_with_auth,
+        verifier::{EtherscanVerifier, SourcifyVerifier},
+    },
+};
+
+use super::{deploy::parse_contract_string, paths::Paths};
+
+#[derive(Args)]
+pub struct Verify {
+    /// The shadow contract to verify
+    ///
+    /// Can either be in the form ContractFile.sol (if the filename and contract name are the same), or ContractFile.sol:ContractName.
+    pub contract: String,
+
+    /// The ABI-encoded constructor arguments, hex encoded
+    #[clap(long, default_value = "")]
+    pub constructor_args: String,
+
+    #[clap(flatten)]
+    pub paths: Paths,
+}
+
+/// Submits the source of a shadow contract to the verifier set in config.
+///
+/// The command uses the [`crate::core::actions::Verify`] action
+/// under the hood, using the local file-based artifact store,
+/// and the local file-based shadow store.
+impl Verify {
+    pub async fn run(&self) -> Result<(), VerifyError> {
+        // Parse the contract string
+        let (file_name, contract_name) = parse_contract_string(&self.contract);
+
+        // Build the resources
+        let config = Config::load().map_err(|e| VerifyError::CustomError(e.to_string()))?;
+        let verifier_config = config.verifier.ok_or_else(|| {
+            VerifyError::CustomError("Please configure a verifier in shadow.config.json".to_owned())
+        })?;
+        let client = client_with_auth(&config.http, verifier_config.auth_header.as_deref())
+            .map_err(|e| VerifyError::CustomError(e.to_string()))?;
+        let verifier_resource = match verifier_config.kind {
+            VerifierKind::Etherscan => ConfiguredVerifier::Etherscan(EtherscanVerifier::new(
+                verifier_config.url,
+                verifier_config.api_key.unwrap_or_default(),
+                client,
+            )),
+            VerifierKind::Sourcify => ConfiguredVerifier::Sourcify(SourcifyVerifier::new(
+                verifier_config.url,
+                verifier_config.chain_id,
+                client,
+            )),
+        };
+        let artifacts_resource = self.paths.artifacts_resource(&config);
+        let shadow_resource = self.paths.shadow_resource(&config);
+
+        // Build the action
+        let verify = crate::core::actions::Verify {
+            file_name,
+            contract_name,
+            constructor_arguments: self.constructor_args.clone(),
+            artifacts_resource,
+            shadow_resource,
+            verifier_resource,
+        };
+
+        // Run the action
+        let message = verify.run().await?;
+        println!("Submitted {} for verification: {}", self.contract, message);
+
+        Ok(())
+    }
+}
+
+/// The verifier selected in config
+enum ConfiguredVerifier {
+    Etherscan(EtherscanVerifier),
+    Sourcify(SourcifyVerifier),
+}
+
+#[async_trait::async_trait]
+impl VerifierResource for ConfiguredVerifier {
+    async fn verify(
+        &self,
+        request: &VerificationRequest,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        match self {
+            ConfiguredVerifier::Etherscan(verifier) => verifier.verify(request).await,
+            ConfiguredVerifier::Sourcify(verifier) => verifier.verify(request).await,
+        }
+    }
+}
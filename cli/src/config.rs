@@ -0,0 +1,512 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The default location of the config file, relative to the current directory
+pub const DEFAULT_CONFIG_PATH: &str = "shadow.config.json";
+
+/// The environment variable that overrides the config file location
+pub const CONFIG_PATH_ENV: &str = "SHADOW_CONFIG";
+
+/// The default artifacts directory, relative to the current directory
+pub const DEFAULT_ARTIFACTS_DIR: &str = "contracts/out";
+
+/// The CLI configuration.
+///
+/// The configuration is read from `shadow.config.json` in the current
+/// directory (or the file set in `SHADOW_CONFIG`). Every field is
+/// optional, so a missing file is equivalent to an empty one.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct Config {
+    /// The contract metadata explorer
+    pub explorer: ExplorerConfig,
+    /// Per-chain overrides of the contract metadata explorer, keyed by chain id
+    pub explorers: BTreeMap<u64, ExplorerConfig>,
+    /// The source verifier used by `shadow verify`
+    pub verifier: Option<VerifierConfig>,
+    /// The notifiers that matching events are posted to
+    pub notifiers: Vec<NotifierConfig>,
+    /// Message templates keyed by event signature, used for console
+    /// output and notifiers, e.g. `{from|short} sent {value|amount:18}`
+    pub templates: BTreeMap<String, String>,
+    /// Overrides of the artifacts and shadow store locations
+    pub paths: PathsConfig,
+    /// Sampling rules applied to every notifier, so noisy events
+    /// don't drown the alert channels
+    pub sampling: Vec<SamplingRule>,
+    /// Timeouts and connection pooling of the HTTP clients
+    pub http: HttpConfig,
+    /// Timeouts of the upstream RPC calls
+    pub timeouts: TimeoutConfig,
+    /// Rules joining shadow events with other logs of their transaction
+    /// into composite records
+    pub joins: Vec<JoinRule>,
+    /// Changes made to matching transactions before they are replayed
+    /// from their impersonated senders
+    pub replay_rewrites: Vec<ReplayRewrite>,
+    /// Where the signer of the post-deploy transactions is loaded from
+    pub signer: SignerConfig,
+    /// Transactions the signer sends once the shadow fork has deployed
+    /// the shadow contracts, e.g. to call shadow-only admin setters
+    pub post_deploy: Vec<PostDeployCall>,
+    /// Contracts whose storage on the shadow fork is refreshed from
+    /// mainnet after every block, e.g. price oracles
+    pub pins: Vec<StatePin>,
+    /// View functions called on the shadow fork after replayed blocks, on
+    /// a schedule, whose results are written to the sinks as events
+    pub view_calls: Vec<ViewCall>,
+}
+
+/// Bounds how long a single upstream call may take, so a hung
+/// endpoint fails the call instead of stalling the command.
+///
+/// HTTP requests to explorers and sinks are bounded by [`HttpConfig`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+    /// The timeout of an RPC call, in seconds
+    pub rpc: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self { rpc: 30 }
+    }
+}
+
+impl TimeoutConfig {
+    /// Returns the RPC call timeout.
+    pub fn rpc(&self) -> Duration {
+        Duration::from_secs(self.rpc)
+    }
+}
+
+/// Configures the HTTP client shared by the RPC provider, the
+/// explorers and the sinks.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// The timeout of a whole request, in seconds
+    pub timeout: u64,
+    /// The timeout of establishing a connection, in seconds
+    pub connect_timeout: u64,
+    /// How long idle connections are kept open for reuse, in seconds
+    pub pool_idle_timeout: u64,
+    /// The maximum number of idle connections kept per host
+    pub pool_max_idle_per_host: Option<usize>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout: 30,
+            connect_timeout: 10,
+            pool_idle_timeout: 90,
+            pool_max_idle_per_host: None,
+        }
+    }
+}
+
+/// Overrides the directory layout the CLI expects.
+///
+/// Relative paths are resolved against the current directory.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct PathsConfig {
+    /// The directory containing the compiled contract artifacts,
+    /// defaults to `contracts/out`
+    pub artifacts_dir: Option<PathBuf>,
+    /// The directory containing `shadow.json`, defaults to the
+    /// current directory
+    pub store_path: Option<PathBuf>,
+}
+
+/// Configures the explorer API used to fetch contract metadata.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct ExplorerConfig {
+    /// The kind of explorer API
+    pub kind: ExplorerKind,
+    /// The base URL of the explorer API.
+    ///
+    /// For Etherscan-compatible APIs, the query parameters are appended
+    /// to this URL, so it may point at Etherscan, Blockscout's Etherscan
+    /// compatible API, or an internal mirror. For Blockscout's REST API,
+    /// this is the root URL of the explorer.
+    pub api_url: String,
+    /// An extra header sent with every request, in the form `Name: value`
+    pub auth_header: Option<String>,
+}
+
+/// The supported explorer APIs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExplorerKind {
+    /// An Etherscan-compatible API
+    #[default]
+    Etherscan,
+    /// Blockscout's REST API
+    Blockscout,
+}
+
+impl Default for ExplorerConfig {
+    fn default() -> Self {
+        Self {
+            kind: ExplorerKind::Etherscan,
+            api_url: "https://api.etherscan.io/api".to_owned(),
+            auth_header: None,
+        }
+    }
+}
+
+/// Configures the source verifier that shadow contracts are submitted to.
+///
+/// This is usually a block explorer running against the shadow fork.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct VerifierConfig {
+    /// The kind of verification API
+    pub kind: VerifierKind,
+    /// The URL of the verification API
+    pub url: String,
+    /// The API key, for Etherscan-compatible verifiers
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// An extra header sent with every request, in the form `Name: value`
+    #[serde(default)]
+    pub auth_header: Option<String>,
+    /// The chain id of the shadow fork, for Sourcify
+    #[serde(default = "default_chain_id")]
+    pub chain_id: u64,
+}
+
+/// The supported verification APIs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VerifierKind {
+    /// An Etherscan-compatible API (Etherscan, Blockscout)
+    Etherscan,
+    /// A Sourcify server (used by Otterscan)
+    Sourcify,
+}
+
+/// Configures a notifier that posts a message for every event
+/// matching its alert rule.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NotifierConfig {
+    /// Where the message is posted
+    pub kind: NotifierKind,
+    /// The webhook URL, for Discord and Slack. Read from an environment
+    /// variable as `env:<name>`, or from the credentials store as
+    /// `credential:<key>`.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// The bot token, for Telegram. Read from an environment variable
+    /// as `env:<name>`, or from the credentials store as
+    /// `credential:<key>`.
+    #[serde(default)]
+    pub bot_token: Option<String>,
+    /// The chat id, for Telegram
+    #[serde(default)]
+    pub chat_id: Option<String>,
+    /// The message template, e.g. `{senderAddress} swapped {amountIn}`.
+    ///
+    /// Defaults to the template set for the event's signature.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// The events the notifier posts for
+    #[serde(default)]
+    pub rule: AlertRule,
+    /// Sampling rules for this notifier, taking precedence over the
+    /// top-level rules for the same signature
+    #[serde(default)]
+    pub sampling: Vec<SamplingRule>,
+}
+
+/// The supported notifiers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NotifierKind {
+    /// A Discord webhook
+    Discord,
+    /// A Slack incoming webhook
+    Slack,
+    /// A Telegram bot chat
+    Telegram,
+}
+
+/// Selects the events an alert fires for.
+///
+/// Unset fields match all events, and every condition must hold.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct AlertRule {
+    /// The contract name, `File.sol:Contract` or address to match
+    pub contract: Option<String>,
+    /// The event name to match
+    pub event: Option<String>,
+    /// Conditions on the decoded event fields
+    pub conditions: Vec<AlertCondition>,
+}
+
+/// Thins out the events with a given signature before they reach a sink.
+///
+/// When both limits are set, the 1 in N sampling applies first.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SamplingRule {
+    /// The event signature, e.g. `Transfer(address,address,uint256)`
+    pub signature: String,
+    /// Only keep 1 in every N events
+    #[serde(default)]
+    pub every: Option<u64>,
+    /// Keep at most this many events per minute
+    #[serde(default)]
+    pub max_per_minute: Option<u64>,
+}
+
+/// Joins a shadow event with another log of the same transaction, e.g.
+/// a `PriceObserved` with the DEX `Swap` of the same pool.
+///
+/// Each match produces a composite record, written to the sinks after
+/// the shadow event.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct JoinRule {
+    /// The name of the composite record, e.g. `ObservedSwap`
+    pub name: String,
+    /// The shadow event to join, matched like an alert rule
+    pub event: AlertRule,
+    /// The other log to join it with, matched like an alert rule. Its
+    /// ABI must be in the artifacts directory or an `--abi-dir`.
+    pub with: AlertRule,
+    /// The fields that must be equal in both events
+    #[serde(default)]
+    pub on: Vec<JoinKey>,
+}
+
+/// Changes a replayed transaction for a "what-if" experiment, e.g. a
+/// swap with another fee setting.
+///
+/// Only applies to transactions replayed from their impersonated
+/// senders, since signed transactions can't be changed.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct ReplayRewrite {
+    /// Only transactions to this address, if set
+    pub to: Option<String>,
+    /// Only calls to this function selector, if set
+    pub selector: Option<String>,
+    /// The recipient to send the transaction to instead
+    pub set_to: Option<String>,
+    /// The value to send instead, in wei
+    pub set_value: Option<String>,
+    /// The function selector to call instead
+    pub set_selector: Option<String>,
+    /// ABI words of the calldata to replace, keyed by their index after
+    /// the selector, as decimal numbers or 32 bytes of hex
+    pub set_words: BTreeMap<usize, String>,
+}
+
+/// Where the signer of the post-deploy transactions is loaded from.
+///
+/// Without a keystore, the private key is read from `SHADOW_PRIVATE_KEY`
+/// or the `signer` credential (see `shadow auth set signer`).
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct SignerConfig {
+    /// An encrypted JSON keystore, unlocked with the password in
+    /// `SHADOW_KEYSTORE_PASSWORD`, or prompted for
+    pub keystore: Option<String>,
+}
+
+/// A transaction sent from the signer to the shadow fork once the
+/// shadow contracts are deployed, e.g. `setFeeRecipient(address)`.
+///
+/// The calldata is either encoded from `signature` and `args`, or given
+/// as is in `data`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct PostDeployCall {
+    /// A name to refer to the transaction in logs
+    pub name: Option<String>,
+    /// The contract to call
+    pub to: ethers::types::H160,
+    /// The function to call, e.g. `setFee(uint24)`
+    pub signature: Option<String>,
+    /// The arguments of the function, e.g. `["500"]`
+    pub args: Vec<String>,
+    /// The calldata, as hex, instead of `signature` and `args`
+    pub data: Option<String>,
+    /// The value sent, as a hex quantity, like the `value` of init calls
+    pub value: ethers::types::U256,
+    /// The gas limit
+    pub gas: Option<u64>,
+}
+
+/// Keeps a contract's storage on the shadow fork in sync with mainnet,
+/// e.g. a price oracle or a sequencer uptime feed whose update
+/// transactions aren't replayed.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct StatePin {
+    /// A name to refer to the pin in logs
+    pub name: Option<String>,
+    /// The contract to pin
+    pub address: String,
+    /// The storage slots to refresh, as decimal numbers or 32 bytes of
+    /// hex. When empty, every slot mainnet changed in the block is
+    /// refreshed, which needs `debug_traceBlockByNumber` upstream.
+    pub slots: Vec<String>,
+}
+
+/// Polls a view function of a shadow contract on a schedule of blocks or
+/// minutes, e.g. `getShadowMetrics()`, for instrumentation that keeps its
+/// state in storage instead of emitting events, like TWAPs or utilization.
+///
+/// The results are decoded with the contract's ABI and written to the
+/// sinks as a synthetic event named after the call.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct ViewCall {
+    /// The name of the synthetic event, defaults to the function name
+    pub name: Option<String>,
+    /// The contract to call
+    pub to: String,
+    /// The function to call, e.g. `getShadowMetrics()`
+    pub signature: String,
+    /// The arguments of the function, e.g. `["0x..."]`
+    pub args: Vec<String>,
+    /// Only call the function every this many blocks
+    pub every: Option<u64>,
+    /// Only call the function every this many minutes, measured with the
+    /// block timestamps. Can't be combined with `every`.
+    pub every_minutes: Option<u64>,
+}
+
+/// A pair of fields a [`JoinRule`] matches on.
+///
+/// Besides the decoded fields, `address` is the address of the
+/// contract that emitted the log.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct JoinKey {
+    /// The field of the shadow event
+    pub event: String,
+    /// The field of the other log
+    pub with: String,
+}
+
+/// A condition on a decoded event field, e.g. `amountIn >= 1000`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AlertCondition {
+    /// The decoded field
+    pub field: String,
+    /// The comparison
+    pub op: AlertOp,
+    /// The value to compare against
+    pub value: String,
+}
+
+/// The supported comparisons
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlertOp {
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Gte,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Lte,
+}
+
+fn default_chain_id() -> u64 {
+    1
+}
+
+impl Config {
+    /// Returns the explorer to use for the given chain.
+    pub fn explorer_for(&self, chain_id: u64) -> &ExplorerConfig {
+        self.explorers.get(&chain_id).unwrap_or(&self.explorer)
+    }
+
+    /// Returns the location of the config file, `shadow.config.json`
+    /// unless `SHADOW_CONFIG` is set.
+    pub fn path() -> PathBuf {
+        std::env::var_os(CONFIG_PATH_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH))
+    }
+
+    /// Loads the config from the default location.
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_from(Self::path())
+    }
+
+    /// Loads the config from a file, falling back to the defaults
+    /// if the file does not exist.
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)?;
+        let config = serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_partial_config() {
+        let config: Config =
+            serde_json::from_str(r#"{ "explorer": { "authHeader": "X-API-Key: secret" } }"#)
+                .unwrap();
+        assert_eq!(config.explorer.api_url, "https://api.etherscan.io/api");
+        assert_eq!(
+            config.explorer.auth_header,
+            Some("X-API-Key: secret".to_owned())
+        );
+
+        let config = Config::load_from("does-not-exist.json").unwrap();
+        assert_eq!(config.explorer.api_url, "https://api.etherscan.io/api");
+        assert_eq!(config.timeouts.rpc(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn can_select_explorer_per_chain() {
+        let config: Config = serde_json::from_str(
+            r#"{ "explorers": { "100": { "kind": "blockscout", "apiUrl": "https://gnosis.blockscout.com" } } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.explorer_for(1).kind, ExplorerKind::Etherscan);
+        assert_eq!(config.explorer_for(100).kind, ExplorerKind::Blockscout);
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let err = serde_json::from_str::<Config>(r#"{ "notifier": [] }"#).unwrap_err();
+        assert!(err.to_string().starts_with("unknown field `notifier`"));
+
+        let err = serde_json::from_str::<Config>(r#"{ "http": { "timout": 5 } }"#).unwrap_err();
+        assert!(err.to_string().starts_with("unknown field `timout`"));
+        assert_eq!(err.line(), 1);
+    }
+}
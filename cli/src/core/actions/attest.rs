@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use ethers::{providers::JsonRpcClient, utils::keccak256};
+use thiserror::Error;
+
+use crate::core::{
+    actions::deploy::{source_tree_hash, Deploy, DeployError},
+    resources::{
+        artifacts::ArtifactsResource,
+        etherscan::EtherscanResource,
+        fork_runner::ForkRunner,
+        shadow::{Attestation, ShadowContract, ShadowResource},
+    },
+};
+
+/// Checks that a shadow contract's stored runtime bytecode can be
+/// reproduced from the current sources.
+///
+/// This action is used by the `attest` command. It compares the build
+/// inputs recorded at deploy time with the current artifact, checks
+/// that the artifact was compiled from the sources on disk, and
+/// rebuilds the runtime bytecode on a temporary fork.
+pub struct Attest<
+    E: EtherscanResource,
+    A: ArtifactsResource,
+    S: ShadowResource,
+    P: JsonRpcClient,
+    R: ForkRunner,
+> {
+    /// The stored shadow contract
+    pub shadow_contract: ShadowContract,
+
+    /// The deploy action used to rebuild the runtime bytecode, set up
+    /// with the recorded metadata mode
+    pub deploy: Deploy<E, A, S, P, R>,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum AttestError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Error rebuilding the shadow contract
+    #[error("DeployError: {0}")]
+    DeployError(#[from] DeployError),
+}
+
+/// A build input that differs between deploy time and now.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The name of the build input
+    pub field: String,
+    /// The value recorded at deploy time
+    pub recorded: String,
+    /// The current value
+    pub current: String,
+}
+
+/// The result of an attestation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AttestReport {
+    /// The build inputs that changed since the deployment
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl AttestReport {
+    /// Returns whether the stored bytecode was reproduced.
+    pub fn is_reproducible(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl<
+        E: EtherscanResource,
+        A: ArtifactsResource,
+        S: ShadowResource,
+        P: JsonRpcClient,
+        R: ForkRunner,
+    > Attest<E, A, S, P, R>
+{
+    pub async fn run(&self) -> Result<AttestReport, AttestError> {
+        let recorded = self.shadow_contract.attestation.as_ref().ok_or_else(|| {
+            AttestError::CustomError(format!(
+                "{} has no attestation, redeploy it to record one",
+                self.shadow_contract.address
+            ))
+        })?;
+
+        // Compare the build inputs
+        let current = self.deploy.attestation()?;
+        let mut mismatches = compare(recorded, &current);
+
+        // Check that the artifact was compiled from the sources on disk
+        let artifact_hashes = self
+            .deploy
+            .artifacts_resource
+            .get_source_hashes(&self.deploy.file_name, &self.deploy.contract_name)
+            .map_err(DeployError::ArtifactError)?;
+        let sources = self
+            .deploy
+            .artifacts_resource
+            .get_sources(&self.deploy.file_name, &self.deploy.contract_name)
+            .map_err(DeployError::ArtifactError)?;
+        let disk_hashes: BTreeMap<String, String> = sources
+            .iter()
+            .map(|(path, contents)| {
+                (
+                    path.clone(),
+                    format!("0x{}", hex::encode(keccak256(contents.as_bytes()))),
+                )
+            })
+            .collect();
+        let (artifact_tree, disk_tree) = (
+            source_tree_hash(&artifact_hashes),
+            source_tree_hash(&disk_hashes),
+        );
+        if artifact_tree != disk_tree {
+            mismatches.push(Mismatch {
+                field: "sources on disk (run `forge build`)".to_owned(),
+                recorded: artifact_tree,
+                current: disk_tree,
+            });
+        }
+
+        // Rebuild the runtime bytecode
+        let rebuilt = self.deploy.build().await?;
+        if rebuilt != self.shadow_contract.runtime_bytecode {
+            mismatches.push(Mismatch {
+                field: "runtimeBytecode".to_owned(),
+                recorded: bytecode_hash(&self.shadow_contract.runtime_bytecode),
+                current: bytecode_hash(&rebuilt),
+            });
+        }
+
+        Ok(AttestReport { mismatches })
+    }
+}
+
+/// Compares the recorded build inputs with the current ones.
+fn compare(recorded: &Attestation, current: &Attestation) -> Vec<Mismatch> {
+    let fields = [
+        (
+            "compilerVersion",
+            recorded.compiler_version.clone(),
+            current.compiler_version.clone(),
+        ),
+        (
+            "optimizerEnabled",
+            recorded.optimizer_enabled.to_string(),
+            current.optimizer_enabled.to_string(),
+        ),
+        (
+            "optimizerRuns",
+            format!("{:?}", recorded.optimizer_runs),
+            format!("{:?}", current.optimizer_runs),
+        ),
+        (
+            "evmVersion",
+            format!("{:?}", recorded.evm_version),
+            format!("{:?}", current.evm_version),
+        ),
+        (
+            "viaIR",
+            recorded.via_ir.to_string(),
+            current.via_ir.to_string(),
+        ),
+        (
+            "sourceTreeHash",
+            recorded.source_tree_hash.clone(),
+            current.source_tree_hash.clone(),
+        ),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, recorded, current)| recorded != current)
+        .map(|(field, recorded, current)| Mismatch {
+            field: field.to_owned(),
+            recorded,
+            current,
+        })
+        .collect()
+}
+
+/// Returns the keccak256 hash of hex-encoded bytecode, for display.
+fn bytecode_hash(bytecode: &str) -> String {
+    match hex::decode(bytecode) {
+        Ok(bytes) => format!("0x{}", hex::encode(keccak256(bytes))),
+        Err(_) => "invalid bytecode".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attestation() -> Attestation {
+        Attestation {
+            compiler_version: "0.6.6+commit.6c089d02".to_owned(),
+            optimizer_enabled: true,
+            optimizer_runs: Some(200),
+            evm_version: None,
+            via_ir: false,
+            source_tree_hash: "0x01".to_owned(),
+            metadata: "keep".to_owned(),
+        }
+    }
+
+    #[test]
+    fn can_compare_attestations() {
+        assert!(compare(&attestation(), &attestation()).is_empty());
+
+        let current = Attestation {
+            optimizer_runs: Some(1_000_000),
+            source_tree_hash: "0x02".to_owned(),
+            ..attestation()
+        };
+        let mismatches = compare(&attestation(), &current);
+        assert_eq!(
+            mismatches
+                .iter()
+                .map(|mismatch| mismatch.field.as_str())
+                .collect::<Vec<_>>(),
+            vec!["optimizerRuns", "sourceTreeHash"]
+        );
+        assert_eq!(mismatches[0].recorded, "Some(200)");
+        assert_eq!(mismatches[0].current, "Some(1000000)");
+    }
+}
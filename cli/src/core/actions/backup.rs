@@ -0,0 +1,524 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::core::resources::backup::BackupStore;
+
+/// The current version of the backup format
+pub const BACKUP_VERSION: u32 = 1;
+
+/// The name of the manifest in each backup. A backup is complete once
+/// its manifest is written.
+const MANIFEST: &str = "manifest.json";
+
+/// Lists the files in a backup, written after all of them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupManifest {
+    /// The version of the backup format
+    pub version: u32,
+    /// The unix timestamp of the backup
+    pub created_at: u64,
+    /// The backed up files
+    pub files: Vec<BackupFile>,
+}
+
+/// A file in a backup, stored gzipped under `<backup>/<name>.gz`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupFile {
+    /// The file name
+    pub name: String,
+    /// What the file is
+    pub kind: BackupFileKind,
+    /// The size of the file, in bytes
+    pub size: u64,
+    /// The hex SHA-256 hash of the file
+    pub sha256: String,
+}
+
+/// What a backed up file is, which decides where it is restored to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackupFileKind {
+    /// The `shadow.json` shadow store
+    Store,
+    /// A file of the event archive
+    Archive,
+}
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+}
+
+/// The outcome of a backup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackupReport {
+    /// The name of the backup
+    pub name: String,
+    /// The manifest of the backup
+    pub manifest: BackupManifest,
+    /// The older backups deleted to keep the retention limit
+    pub pruned: Vec<String>,
+}
+
+/// Backs up the shadow store and the event archive.
+///
+/// This action is used by the `backup` command.
+///
+/// Each backup is named `backup-<unix millis>`, and its manifest is
+/// written last, so an interrupted backup is never restored.
+pub struct Backup {
+    /// Where the backups are stored
+    pub store: Box<dyn BackupStore>,
+
+    /// The path of `shadow.json`
+    pub shadow_file: PathBuf,
+
+    /// The event archive files, including rotated ones
+    pub archive_files: Vec<PathBuf>,
+
+    /// How many backups to keep, deleting the oldest, if set
+    pub retain: Option<usize>,
+}
+
+impl Backup {
+    pub async fn run(&self) -> Result<BackupReport, BackupError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let name = format!("backup-{}", now.as_millis());
+
+        // Upload the files
+        let files = std::iter::once((&self.shadow_file, BackupFileKind::Store)).chain(
+            self.archive_files
+                .iter()
+                .map(|path| (path, BackupFileKind::Archive)),
+        );
+        let mut manifest = BackupManifest {
+            version: BACKUP_VERSION,
+            created_at: now.as_secs(),
+            files: Vec::new(),
+        };
+        let mut names = HashSet::new();
+        for (path, kind) in files {
+            let file_name = file_name(path)?;
+            if !names.insert(file_name.clone()) {
+                return Err(BackupError::CustomError(format!(
+                    "Two files to back up are named {}",
+                    file_name
+                )));
+            }
+            let contents = fs::read(path).map_err(|e| {
+                BackupError::CustomError(format!("Error reading {}: {}", path.display(), e))
+            })?;
+            self.store
+                .put(&format!("{}/{}.gz", name, file_name), gzip(&contents)?)
+                .await
+                .map_err(|e| {
+                    BackupError::CustomError(format!("Error uploading {}: {}", file_name, e))
+                })?;
+            manifest.files.push(BackupFile {
+                name: file_name,
+                kind,
+                size: contents.len() as u64,
+                sha256: hex::encode(Sha256::digest(&contents)),
+            });
+        }
+
+        // Complete the backup
+        let contents = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| BackupError::CustomError(e.to_string()))?;
+        self.store
+            .put(&format!("{}/{}", name, MANIFEST), contents)
+            .await
+            .map_err(|e| BackupError::CustomError(format!("Error uploading manifest: {}", e)))?;
+
+        // Delete the oldest backups
+        let mut pruned = Vec::new();
+        if let Some(retain) = self.retain {
+            let backups = list_backups(self.store.as_ref()).await?;
+            let excess = backups.len().saturating_sub(retain);
+            for backup in &backups[..excess] {
+                delete_backup(self.store.as_ref(), backup).await?;
+                pruned.push(backup.clone());
+            }
+        }
+
+        Ok(BackupReport {
+            name,
+            manifest,
+            pruned,
+        })
+    }
+}
+
+/// Restores the shadow store and the event archive from a backup.
+///
+/// This action is used by the `restore` command.
+///
+/// Every file is downloaded and checked against its hash before any is
+/// written, and files are written through a temporary file, so a failed
+/// restore leaves the existing files untouched.
+pub struct Restore {
+    /// Where the backups are stored
+    pub store: Box<dyn BackupStore>,
+
+    /// The backup to restore, the latest if not set
+    pub name: Option<String>,
+
+    /// The path `shadow.json` is restored to
+    pub shadow_file: PathBuf,
+
+    /// The directory the event archive files are restored to
+    pub archive_dir: PathBuf,
+
+    /// Whether to overwrite existing files
+    pub force: bool,
+}
+
+impl Restore {
+    /// Restores the backup, and returns its name and manifest.
+    pub async fn run(&self) -> Result<(String, BackupManifest), BackupError> {
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => list_backups(self.store.as_ref())
+                .await?
+                .pop()
+                .ok_or_else(|| BackupError::CustomError("There are no backups".to_owned()))?,
+        };
+        let manifest = self
+            .store
+            .get(&format!("{}/{}", name, MANIFEST))
+            .await
+            .map_err(|e| {
+                BackupError::CustomError(format!("Error reading backup {}: {}", name, e))
+            })?;
+        let manifest: BackupManifest = serde_json::from_slice(&manifest)
+            .map_err(|e| BackupError::CustomError(format!("Invalid backup {}: {}", name, e)))?;
+        if manifest.version > BACKUP_VERSION {
+            return Err(BackupError::CustomError(format!(
+                "Backup {} has version {}, upgrade shadow to restore it",
+                name, manifest.version
+            )));
+        }
+
+        // Download and check every file first
+        let mut restored = Vec::new();
+        for file in &manifest.files {
+            // A manifest could otherwise restore files outside the archive
+            // directory, e.g. with `../../.ssh/authorized_keys`
+            if !is_plain_file_name(&file.name) {
+                return Err(BackupError::CustomError(format!(
+                    "Backup {} has an invalid file name {}",
+                    name, file.name
+                )));
+            }
+            let target = match file.kind {
+                BackupFileKind::Store => self.shadow_file.clone(),
+                BackupFileKind::Archive => self.archive_dir.join(&file.name),
+            };
+            if target.exists() && !self.force {
+                return Err(BackupError::CustomError(format!(
+                    "{} already exists, use --force to overwrite it",
+                    target.display()
+                )));
+            }
+            let contents = self
+                .store
+                .get(&format!("{}/{}.gz", name, file.name))
+                .await
+                .map_err(|e| {
+                    BackupError::CustomError(format!("Error downloading {}: {}", file.name, e))
+                })?;
+            let contents = gunzip(&contents)?;
+            if hex::encode(Sha256::digest(&contents)) != file.sha256 {
+                return Err(BackupError::CustomError(format!(
+                    "{} in backup {} is corrupted",
+                    file.name, name
+                )));
+            }
+            restored.push((target, contents));
+        }
+
+        // Write the files
+        for (target, contents) in restored {
+            write_atomic(&target, &contents).map_err(|e| {
+                BackupError::CustomError(format!("Error writing {}: {}", target.display(), e))
+            })?;
+        }
+
+        Ok((name, manifest))
+    }
+}
+
+/// Lists the complete backups in a store, oldest first.
+pub async fn list_backups(store: &dyn BackupStore) -> Result<Vec<String>, BackupError> {
+    let keys = store
+        .list()
+        .await
+        .map_err(|e| BackupError::CustomError(format!("Error listing backups: {}", e)))?;
+    let mut backups = keys
+        .iter()
+        .filter_map(|key| key.strip_suffix(&format!("/{}", MANIFEST)))
+        .filter(|name| name.starts_with("backup-"))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    backups.sort();
+    Ok(backups)
+}
+
+/// Deletes a backup, its manifest first so it's never restored partially.
+async fn delete_backup(store: &dyn BackupStore, name: &str) -> Result<(), BackupError> {
+    let error = |e: Box<dyn std::error::Error + Send + Sync>| {
+        BackupError::CustomError(format!("Error deleting backup {}: {}", name, e))
+    };
+    let manifest = store
+        .get(&format!("{}/{}", name, MANIFEST))
+        .await
+        .map_err(error)?;
+    let manifest: BackupManifest = serde_json::from_slice(&manifest)
+        .map_err(|e| BackupError::CustomError(format!("Invalid backup {}: {}", name, e)))?;
+    store
+        .delete(&format!("{}/{}", name, MANIFEST))
+        .await
+        .map_err(error)?;
+    for file in &manifest.files {
+        store
+            .delete(&format!("{}/{}.gz", name, file.name))
+            .await
+            .map_err(error)?;
+    }
+    Ok(())
+}
+
+fn file_name(path: &Path) -> Result<String, BackupError> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| BackupError::CustomError(format!("Invalid file {}", path.display())))
+}
+
+/// Returns whether a name is a single file name, without directories.
+fn is_plain_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(file_name)), None) if file_name.to_str() == Some(name)
+    )
+}
+
+fn gzip(contents: &[u8]) -> Result<Vec<u8>, BackupError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(contents)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| BackupError::CustomError(e.to_string()))
+}
+
+fn gunzip(contents: &[u8]) -> Result<Vec<u8>, BackupError> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(contents)
+        .read_to_end(&mut decoded)
+        .map_err(|e| BackupError::CustomError(e.to_string()))?;
+    Ok(decoded)
+}
+
+/// Writes a file through a temporary file in the same directory.
+fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    file.write_all(contents)?;
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Mutex};
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    /// A backup store in memory, shared between the actions of a test
+    #[derive(Clone, Default)]
+    struct MemoryStore(std::sync::Arc<Mutex<BTreeMap<String, Vec<u8>>>>);
+
+    #[async_trait]
+    impl BackupStore for MemoryStore {
+        async fn put(
+            &self,
+            key: &str,
+            data: Vec<u8>,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.0.lock().unwrap().insert(key.to_owned(), data);
+            Ok(())
+        }
+
+        async fn get(
+            &self,
+            key: &str,
+        ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(self
+                .0
+                .lock()
+                .unwrap()
+                .get(key)
+                .cloned()
+                .ok_or("Not found")?)
+        }
+
+        async fn list(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(self.0.lock().unwrap().keys().cloned().collect())
+        }
+
+        async fn delete(&self, key: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.0.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn can_backup_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let shadow_file = dir.path().join("shadow.json");
+        let archive = dir.path().join("events.jsonl");
+        fs::write(&shadow_file, r#"{"version":2,"contracts":[]}"#).unwrap();
+        fs::write(&archive, "{\"event\":\"Transfer\"}\n").unwrap();
+
+        let store = MemoryStore::default();
+        let backup = Backup {
+            store: Box::new(store.clone()),
+            shadow_file: shadow_file.clone(),
+            archive_files: vec![archive.clone()],
+            retain: Some(1),
+        };
+        let first = backup.run().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        let second = backup.run().await.unwrap();
+        assert_eq!(second.pruned, vec![first.name]);
+        assert_eq!(second.manifest.files.len(), 2);
+        assert_eq!(
+            list_backups(&store).await.unwrap(),
+            vec![second.name.clone()]
+        );
+        assert_eq!(store.0.lock().unwrap().len(), 3);
+
+        // Existing files are only overwritten with force
+        fs::write(&shadow_file, "corrupted").unwrap();
+        let mut restore = Restore {
+            store: Box::new(store.clone()),
+            name: None,
+            shadow_file: shadow_file.clone(),
+            archive_dir: dir.path().join("restored"),
+            force: false,
+        };
+        assert!(restore.run().await.is_err());
+        restore.force = true;
+        let (name, _) = restore.run().await.unwrap();
+        assert_eq!(name, second.name);
+        assert_eq!(
+            fs::read_to_string(&shadow_file).unwrap(),
+            r#"{"version":2,"contracts":[]}"#
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("restored").join("events.jsonl")).unwrap(),
+            "{\"event\":\"Transfer\"}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_corrupted_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let shadow_file = dir.path().join("shadow.json");
+        fs::write(&shadow_file, "{}").unwrap();
+        let store = MemoryStore::default();
+        let report = Backup {
+            store: Box::new(store.clone()),
+            shadow_file: shadow_file.clone(),
+            archive_files: Vec::new(),
+            retain: None,
+        }
+        .run()
+        .await
+        .unwrap();
+        store
+            .put(
+                &format!("{}/shadow.json.gz", report.name),
+                gzip(b"[]").unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let restore = Restore {
+            store: Box::new(store),
+            name: Some(report.name),
+            shadow_file,
+            archive_dir: dir.path().to_owned(),
+            force: true,
+        };
+        assert!(restore.run().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_file_names_with_directories() {
+        assert!(is_plain_file_name("events.jsonl"));
+        assert!(!is_plain_file_name("../events.jsonl"));
+        assert!(!is_plain_file_name("logs/events.jsonl"));
+        assert!(!is_plain_file_name("/etc/passwd"));
+        assert!(!is_plain_file_name(".."));
+        assert!(!is_plain_file_name("./events.jsonl"));
+        assert!(!is_plain_file_name(""));
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = MemoryStore::default();
+        let contents = b"{}";
+        let manifest = BackupManifest {
+            version: BACKUP_VERSION,
+            created_at: 0,
+            files: vec![BackupFile {
+                name: "../escaped.jsonl".to_owned(),
+                kind: BackupFileKind::Archive,
+                size: contents.len() as u64,
+                sha256: hex::encode(Sha256::digest(contents)),
+            }],
+        };
+        store
+            .put("backup-1/../escaped.jsonl.gz", gzip(contents).unwrap())
+            .await
+            .unwrap();
+        store
+            .put(
+                "backup-1/manifest.json",
+                serde_json::to_vec(&manifest).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let restore = Restore {
+            store: Box::new(store),
+            name: None,
+            shadow_file: dir.path().join("shadow.json"),
+            archive_dir: dir.path().join("archive"),
+            force: true,
+        };
+        assert!(restore.run().await.is_err());
+        assert!(!dir.path().join("escaped.jsonl").exists());
+    }
+}
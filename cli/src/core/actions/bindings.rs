@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+
+use alloy_json_abi::JsonAbi;
+use clap::ValueEnum;
+use ethers::contract::Abigen;
+use thiserror::Error;
+
+use crate::core::resources::{
+    artifacts::ArtifactsResource,
+    shadow::{ShadowContract, ShadowResource},
+};
+
+mod typescript;
+
+/// Generates bindings for the shadow contracts, pointed at the shadow
+/// fork, so analysis tools, bots and frontends get typed access to
+/// shadow-only functions and events.
+///
+/// This action is used by the `bindings` command.
+pub struct Bindings<A: ArtifactsResource, S: ShadowResource> {
+    /// Only generate bindings for the shadow contracts with any of these tags
+    pub tags: Vec<String>,
+
+    /// The URL of the shadow fork the bindings connect to
+    pub fork_url: String,
+
+    /// The language to generate the bindings in
+    pub language: Language,
+
+    /// The Artifacts resource
+    pub artifacts_resource: A,
+
+    /// The Shadow resource
+    pub shadow_resource: S,
+}
+
+/// The language of the generated bindings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Language {
+    /// A Rust module with ethers' abigen bindings
+    #[default]
+    Rust,
+    /// A TypeScript module with the ABIs, event types and a viem client
+    Typescript,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum BindingsError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+}
+
+/// A generated source file, relative to the bindings module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BindingFile {
+    /// The file name, e.g. `uniswap_v2_pair.rs` or `index.ts`
+    pub path: String,
+    /// The source
+    pub contents: String,
+}
+
+impl<A: ArtifactsResource, S: ShadowResource> Bindings<A, S> {
+    /// Returns the files of the bindings module.
+    ///
+    /// In Rust, this is a file per shadow contract and a `mod.rs` with
+    /// their shadowed addresses and a fork provider. In TypeScript, a
+    /// single `index.ts`.
+    pub async fn run(&self) -> Result<Vec<BindingFile>, BindingsError> {
+        let shadow_contracts = self
+            .shadow_resource
+            .list()
+            .await
+            .map_err(|e| BindingsError::CustomError(e.to_string()))?;
+
+        // Group the instances of each contract
+        let mut contracts: BTreeMap<String, Vec<ShadowContract>> = BTreeMap::new();
+        for contract in shadow_contracts
+            .into_iter()
+            .filter(|c| c.has_any_tag(&self.tags))
+        {
+            let instances = contracts.entry(contract.contract_name.clone()).or_default();
+            if let Some(other) = instances.first() {
+                if other.file_name != contract.file_name {
+                    return Err(BindingsError::CustomError(format!(
+                        "{}:{} and {}:{} would have the same bindings",
+                        other.file_name,
+                        other.contract_name,
+                        contract.file_name,
+                        contract.contract_name
+                    )));
+                }
+            }
+            instances.push(contract);
+        }
+
+        let mut abis = BTreeMap::new();
+        for (contract_name, instances) in contracts {
+            let file_name = &instances[0].file_name;
+            let artifact = self
+                .artifacts_resource
+                .get_artifact(file_name, &contract_name)
+                .map_err(|e| {
+                    BindingsError::CustomError(format!(
+                        "Error getting artifact {}:{}: {}",
+                        file_name, contract_name, e
+                    ))
+                })?;
+            abis.insert(contract_name, (instances, artifact.abi));
+        }
+
+        let files = match self.language {
+            Language::Rust => {
+                let mut files = Vec::new();
+                for (contract_name, (instances, abi)) in &abis {
+                    files.push(BindingFile {
+                        path: format!("{}.rs", module_name(contract_name)),
+                        contents: rust_bindings(&instances[0].file_name, contract_name, abi)?,
+                    });
+                }
+                files.push(BindingFile {
+                    path: "mod.rs".to_owned(),
+                    contents: module(&self.fork_url, &abis),
+                });
+                files
+            }
+            Language::Typescript => vec![BindingFile {
+                path: "index.ts".to_owned(),
+                contents: typescript::module(&self.fork_url, &abis),
+            }],
+        };
+
+        Ok(files)
+    }
+}
+
+/// Generates the abigen bindings of a contract.
+fn rust_bindings(
+    file_name: &str,
+    contract_name: &str,
+    abi: &JsonAbi,
+) -> Result<String, BindingsError> {
+    let abi = serde_json::to_string(abi).map_err(|e| BindingsError::CustomError(e.to_string()))?;
+    let bindings = Abigen::new(contract_name, abi)
+        .and_then(|abigen| abigen.generate())
+        .map_err(|e| {
+            BindingsError::CustomError(format!(
+                "Error generating bindings for {}:{}: {}",
+                file_name, contract_name, e
+            ))
+        })?;
+    let mut contents = Vec::new();
+    bindings
+        .write(&mut contents)
+        .map_err(|e| BindingsError::CustomError(e.to_string()))?;
+    Ok(String::from_utf8_lossy(&contents).into_owned())
+}
+
+/// Returns the `mod.rs` of the bindings: the contract modules, the
+/// addresses each contract is shadowed at, and constructors pointing
+/// the bindings at the shadow fork.
+fn module(fork_url: &str, contracts: &BTreeMap<String, (Vec<ShadowContract>, JsonAbi)>) -> String {
+    let mut source = String::from(
+        "//! Bindings for the shadow contracts, generated by `shadow bindings`.\n\
+         \n\
+         #![allow(dead_code)]\n\
+         \n\
+         use ethers::providers::{Http, Provider};\n\
+         \n",
+    );
+    for contract_name in contracts.keys() {
+        source.push_str(&format!("pub mod {};\n", module_name(contract_name)));
+    }
+    source.push_str(&format!(
+        "\n\
+         /// The URL of the shadow fork\n\
+         pub const FORK_URL: &str = {:?};\n\
+         \n\
+         /// Connects to the shadow fork.\n\
+         pub fn provider() -> Provider<Http> {{\n\
+         \x20   Provider::<Http>::try_from(FORK_URL).expect(\"invalid FORK_URL\")\n\
+         }}\n",
+        fork_url
+    ));
+    for (contract_name, (instances, _)) in contracts {
+        let module = module_name(contract_name);
+        let addresses = instances
+            .iter()
+            .map(|instance| format!("{:?}", instance.address))
+            .collect::<Vec<_>>()
+            .join(", ");
+        source.push_str(&format!(
+            "\n\
+             /// The addresses `{file}:{name}` is shadowed at\n\
+             pub const {constant}: &[&str] = &[{addresses}];\n\
+             \n\
+             /// Returns `{file}:{name}` at one of its shadowed addresses, on the shadow fork.\n\
+             pub fn {module}(address: &str) -> {module}::{name}<Provider<Http>> {{\n\
+             \x20   let address = address.parse::<ethers::types::Address>().expect(\"invalid address\");\n\
+             \x20   {module}::{name}::new(address, std::sync::Arc::new(provider()))\n\
+             }}\n",
+            file = instances[0].file_name,
+            name = contract_name,
+            constant = module.to_uppercase(),
+            addresses = addresses,
+            module = module,
+        ));
+    }
+    source
+}
+
+/// Returns the snake case module name of a contract, as abigen names it,
+/// e.g. `uniswap_v2_pair` for `UniswapV2Pair`.
+fn module_name(contract_name: &str) -> String {
+    let chars = contract_name.chars().collect::<Vec<_>>();
+    let mut name = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).map_or(false, |next| next.is_lowercase());
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lower)
+            {
+                name.push('_');
+            }
+        }
+        name.extend(c.to_lowercase());
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_name_modules() {
+        assert_eq!(module_name("UniswapV2Pair"), "uniswap_v2_pair");
+        assert_eq!(module_name("ERC20"), "erc20");
+        assert_eq!(module_name("WETH9"), "weth9");
+        assert_eq!(module_name("ERC20Token"), "erc20_token");
+    }
+
+    #[test]
+    fn can_generate_module() {
+        let pair = |address: &str| ShadowContract {
+            file_name: "UniswapV2Pair.sol".to_owned(),
+            contract_name: "UniswapV2Pair".to_owned(),
+            address: address.to_owned(),
+            runtime_bytecode: String::new(),
+            enabled: true,
+            tags: Vec::new(),
+            attestation: None,
+        };
+        let contracts =
+            BTreeMap::from([("UniswapV2Pair".to_owned(), vec![pair("0x01"), pair("0x02")])]);
+
+        let source = module("http://localhost:8545", &contracts);
+        assert!(source.contains("pub mod uniswap_v2_pair;\n"));
+        assert!(source.contains("pub const FORK_URL: &str = \"http://localhost:8545\";\n"));
+        assert!(source.contains("pub const UNISWAP_V2_PAIR: &[&str] = &[\"0x01\", \"0x02\"];\n"));
+        assert!(source.contains(
+            "pub fn uniswap_v2_pair(address: &str) -> uniswap_v2_pair::UniswapV2Pair<Provider<Http>> {\n"
+        ));
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+
+use alloy_json_abi::{JsonAbi, Param};
+
+use crate::core::resources::shadow::ShadowContract;
+
+/// Returns the `index.ts` of the TypeScript bindings: a viem client for
+/// the shadow fork and, per contract, its ABI, shadowed addresses, event
+/// names and event argument types.
+pub fn module(
+    fork_url: &str,
+    contracts: &BTreeMap<String, (Vec<ShadowContract>, JsonAbi)>,
+) -> String {
+    let mut source = format!(
+        "// Bindings for the shadow contracts, generated by `shadow bindings --language typescript`.\n\
+         \n\
+         import {{ createPublicClient, getContract, http, type Address, type Hex }} from \"viem\";\n\
+         \n\
+         /** The URL of the shadow fork */\n\
+         export const FORK_URL = {:?};\n\
+         \n\
+         /** A client connected to the shadow fork */\n\
+         export const client = createPublicClient({{ transport: http(FORK_URL) }});\n",
+        fork_url
+    );
+    for (contract_name, (instances, abi)) in contracts {
+        source.push('\n');
+        source.push_str(&contract(contract_name, instances, abi));
+    }
+    source
+}
+
+/// Returns the declarations of a single contract.
+fn contract(contract_name: &str, instances: &[ShadowContract], abi: &JsonAbi) -> String {
+    let name = camel_case(contract_name);
+    let label = format!("{}:{}", instances[0].file_name, contract_name);
+    let addresses = instances
+        .iter()
+        .map(|instance| format!("{:?}", instance.address))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let abi_json = serde_json::to_string(abi).unwrap_or_else(|_| "[]".to_owned());
+
+    let mut source = format!(
+        "/** The ABI of `{label}` */\n\
+         export const {name}Abi = {abi_json} as const;\n\
+         \n\
+         /** The addresses `{label}` is shadowed at */\n\
+         export const {name}Addresses = [{addresses}] as const satisfies readonly Address[];\n\
+         \n",
+    );
+
+    let event_names = if abi.events.is_empty() {
+        "never".to_owned()
+    } else {
+        abi.events
+            .keys()
+            .map(|event_name| format!("{:?}", event_name))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    };
+    source.push_str(&format!(
+        "/** The events of `{label}` */\n\
+         export type {contract_name}EventName = {event_names};\n\
+         \n\
+         /** The arguments of each event of `{label}` */\n\
+         export type {contract_name}Events = {{\n",
+    ));
+    for (event_name, overloads) in &abi.events {
+        let args = overloads
+            .iter()
+            .map(|event| {
+                let fields = event
+                    .inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(index, input)| {
+                        field(&input.name, index, &ts_type(&input.ty, &input.components))
+                    })
+                    .collect::<Vec<_>>();
+                object(&fields)
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        source.push_str(&format!("  {}: {};\n", event_name, args));
+    }
+    source.push_str(&format!(
+        "}};\n\
+         \n\
+         /** Returns `{label}` at one of its shadowed addresses, on the shadow fork */\n\
+         export function {name}(address: Address = {name}Addresses[0]) {{\n\
+         \x20 return getContract({{ address, abi: {name}Abi, client }});\n\
+         }}\n",
+    ));
+    source
+}
+
+/// Returns the TypeScript type viem decodes a Solidity type to.
+fn ts_type(ty: &str, components: &[Param]) -> String {
+    if let Some(element) = ty.strip_suffix(']').and_then(|ty| ty.rsplit_once('[')) {
+        let element = ts_type(element.0, components);
+        return if element.starts_with("readonly") {
+            format!("readonly ({})[]", element)
+        } else {
+            format!("readonly {}[]", element)
+        };
+    }
+    match ty {
+        "address" => "Address".to_owned(),
+        "bool" => "boolean".to_owned(),
+        "string" => "string".to_owned(),
+        "tuple" => {
+            let fields = components
+                .iter()
+                .enumerate()
+                .map(|(index, component)| {
+                    field(
+                        &component.name,
+                        index,
+                        &ts_type(&component.ty, &component.components),
+                    )
+                })
+                .collect::<Vec<_>>();
+            object(&fields)
+        }
+        ty if ty.starts_with("bytes") => "Hex".to_owned(),
+        ty if ty.starts_with("uint") || ty.starts_with("int") => {
+            // viem decodes integers that fit in a JS number to numbers
+            let bits = ty
+                .trim_start_matches('u')
+                .trim_start_matches("int")
+                .parse::<u16>()
+                .unwrap_or(256);
+            if bits <= 48 {
+                "number".to_owned()
+            } else {
+                "bigint".to_owned()
+            }
+        }
+        _ => "unknown".to_owned(),
+    }
+}
+
+/// Returns an object field, naming unnamed parameters by position.
+fn field(name: &str, index: usize, ty: &str) -> String {
+    if name.is_empty() {
+        format!("arg{}: {}", index, ty)
+    } else {
+        format!("{}: {}", name, ty)
+    }
+}
+
+fn object(fields: &[String]) -> String {
+    if fields.is_empty() {
+        "{}".to_owned()
+    } else {
+        format!("{{ {} }}", fields.join("; "))
+    }
+}
+
+/// Returns the camel case name of a contract, e.g. `uniswapV2Pair` for
+/// `UniswapV2Pair` and `wethVault` for `WETHVault`.
+fn camel_case(contract_name: &str) -> String {
+    let chars = contract_name.chars().collect::<Vec<_>>();
+    let upper = chars.iter().take_while(|c| c.is_uppercase()).count();
+    // Keep the first letter of the next word in an acronym prefix
+    let lower = if upper > 1 && chars.get(upper).map_or(false, |c| c.is_lowercase()) {
+        upper - 1
+    } else {
+        upper.max(1)
+    };
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            if i < lower {
+                c.to_ascii_lowercase()
+            } else {
+                *c
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_name_contracts() {
+        assert_eq!(camel_case("UniswapV2Pair"), "uniswapV2Pair");
+        assert_eq!(camel_case("WETHVault"), "wethVault");
+        assert_eq!(camel_case("ERC20"), "erc20");
+        assert_eq!(camel_case("ERC20Token"), "erc20Token");
+    }
+
+    #[test]
+    fn can_map_solidity_types() {
+        let components = vec![
+            Param {
+                name: "owner".to_owned(),
+                ty: "address".to_owned(),
+                components: vec![],
+                internal_type: None,
+            },
+            Param {
+                name: "".to_owned(),
+                ty: "uint32".to_owned(),
+                components: vec![],
+                internal_type: None,
+            },
+        ];
+        assert_eq!(ts_type("uint256", &[]), "bigint");
+        assert_eq!(ts_type("int24", &[]), "number");
+        assert_eq!(ts_type("bytes32", &[]), "Hex");
+        assert_eq!(ts_type("bool[]", &[]), "readonly boolean[]");
+        assert_eq!(ts_type("uint8[2][]", &[]), "readonly (readonly number[])[]");
+        assert_eq!(
+            ts_type("tuple[]", &components),
+            "readonly { owner: Address; arg1: number }[]"
+        );
+    }
+
+    #[test]
+    fn can_generate_contract() {
+        let abi: JsonAbi = serde_json::from_str(
+            r#"[{"type":"event","name":"Sync","anonymous":false,"inputs":[
+                {"name":"reserve0","type":"uint112","indexed":false},
+                {"name":"reserve1","type":"uint112","indexed":false}]}]"#,
+        )
+        .unwrap();
+        let instance = ShadowContract {
+            file_name: "UniswapV2Pair.sol".to_owned(),
+            contract_name: "UniswapV2Pair".to_owned(),
+            address: "0x01".to_owned(),
+            runtime_bytecode: String::new(),
+            enabled: true,
+            tags: Vec::new(),
+            attestation: None,
+        };
+
+        let source = contract("UniswapV2Pair", &[instance], &abi);
+        assert!(source.contains("export const uniswapV2PairAbi = [{"));
+        assert!(source.contains(
+            "export const uniswapV2PairAddresses = [\"0x01\"] as const satisfies readonly Address[];\n"
+        ));
+        assert!(source.contains("export type UniswapV2PairEventName = \"Sync\";\n"));
+        assert!(source.contains("  Sync: { reserve0: bigint; reserve1: bigint };\n"));
+        assert!(source.contains(
+            "export function uniswapV2Pair(address: Address = uniswapV2PairAddresses[0]) {\n"
+        ));
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::core::resources::{
+    artifacts::ArtifactsResource,
+    shadow::{ShadowContract, ShadowResource},
+};
+
+/// The current version of the bundle format
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// A portable package of shadow contracts that can be shared
+/// between teammates.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowBundle {
+    /// The version of the bundle format
+    pub version: u32,
+    /// The bundled shadow contracts
+    pub contracts: Vec<BundledContract>,
+}
+
+/// A shadow contract, optionally packaged with its ABI and source hashes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledContract {
+    /// The shadow contract
+    #[serde(flatten)]
+    pub contract: ShadowContract,
+    /// The ABI of the shadow contract
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abi: Option<alloy_json_abi::JsonAbi>,
+    /// The keccak256 hashes of the sources, keyed by source path
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub source_hashes: BTreeMap<String, String>,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum BundleError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Address collisions between the bundle and the shadow store
+    #[error("ConflictError: conflicting shadow contracts at {}", .0.join(", "))]
+    ConflictError(Vec<String>),
+}
+
+/// Packages the shadow contracts in a shadow store into a bundle.
+///
+/// This action is used by the `export` command.
+pub struct Export<A: ArtifactsResource, S: ShadowResource> {
+    /// The Artifacts resource
+    pub artifacts_resource: A,
+
+    /// The Shadow resource
+    pub shadow_resource: S,
+
+    /// Whether to include the contract ABIs
+    pub include_abi: bool,
+
+    /// Whether to include the source hashes
+    pub include_source_hashes: bool,
+}
+
+impl<A: ArtifactsResource, S: ShadowResource> Export<A, S> {
+    pub async fn run(&self) -> Result<ShadowBundle, BundleError> {
+        let contracts = self
+            .shadow_resource
+            .list()
+            .await
+            .map_err(|e| BundleError::CustomError(e.to_string()))?;
+
+        let mut bundled = Vec::new();
+        for contract in contracts {
+            let abi = if self.include_abi {
+                let artifact = self
+                    .artifacts_resource
+                    .get_artifact(&contract.file_name, &contract.contract_name)
+                    .map_err(|e| {
+                        BundleError::CustomError(format!("Error getting artifact: {}", e))
+                    })?;
+                Some(artifact.abi)
+            } else {
+                None
+            };
+            let source_hashes = if self.include_source_hashes {
+                self.artifacts_resource
+                    .get_source_hashes(&contract.file_name, &contract.contract_name)
+                    .map_err(|e| {
+                        BundleError::CustomError(format!("Error getting source hashes: {}", e))
+                    })?
+            } else {
+                BTreeMap::new()
+            };
+            bundled.push(BundledContract {
+                contract,
+                abi,
+                source_hashes,
+            });
+        }
+
+        Ok(ShadowBundle {
+            version: BUNDLE_VERSION,
+            contracts: bundled,
+        })
+    }
+}
+
+/// Imports a bundle of shadow contracts into a shadow store.
+///
+/// This action is used by the `import` command.
+///
+/// Without `merge`, the bundle replaces the shadow store, and contracts
+/// missing from the bundle are removed. With `merge`, the bundle is
+/// added to the existing contracts, and a bundled contract whose address
+/// is already registered with a different contract is a conflict:
+/// nothing is imported unless `overwrite` is set.
+///
+/// A bundle that shadows the same address twice is rejected. Bundled
+/// ABIs and source hashes are checked against the local artifacts.
+pub struct Import<A: ArtifactsResource, S: ShadowResource> {
+    /// The bundle to import
+    pub bundle: ShadowBundle,
+
+    /// The Artifacts resource, used to compare ABIs and source hashes
+    pub artifacts_resource: A,
+
+    /// The Shadow resource
+    pub shadow_resource: S,
+
+    /// Whether to merge into the shadow store instead of replacing it
+    pub merge: bool,
+
+    /// Whether to overwrite conflicting contracts
+    pub overwrite: bool,
+}
+
+impl<A: ArtifactsResource, S: ShadowResource> Import<A, S> {
+    pub async fn run(&self) -> Result<(), BundleError> {
+        if self.bundle.version > BUNDLE_VERSION {
+            return Err(BundleError::CustomError(format!(
+                "Unsupported bundle version: {}",
+                self.bundle.version
+            )));
+        }
+
+        let duplicates = duplicates(&self.bundle);
+        if !duplicates.is_empty() {
+            return Err(BundleError::CustomError(format!(
+                "Bundle shadows the same address more than once: {}",
+                duplicates.join(", ")
+            )));
+        }
+
+        let existing = self
+            .shadow_resource
+            .list()
+            .await
+            .map_err(|e| BundleError::CustomError(e.to_string()))?;
+
+        let contracts = if self.merge {
+            // Detect address collisions
+            let conflicts = conflicts(&existing, &self.bundle);
+            if !conflicts.is_empty() && !self.overwrite {
+                return Err(BundleError::ConflictError(conflicts));
+            }
+            let mut contracts = existing;
+            for bundled in &self.bundle.contracts {
+                let address = bundled.contract.address.to_lowercase();
+                match contracts
+                    .iter()
+                    .position(|c| c.address.to_lowercase() == address)
+                {
+                    Some(index) => contracts[index] = bundled.contract.clone(),
+                    None => contracts.push(bundled.contract.clone()),
+                }
+            }
+            contracts
+        } else {
+            // Replace the store, dropping the contracts the bundle doesn't have
+            self.bundle
+                .contracts
+                .iter()
+                .map(|bundled| bundled.contract.clone())
+                .collect()
+        };
+
+        for bundled in &self.bundle.contracts {
+            self.warn_on_artifact_mismatch(bundled);
+        }
+
+        // Write the store once, so a failed import leaves it untouched
+        self.shadow_resource
+            .replace(contracts)
+            .await
+            .map_err(|e| BundleError::CustomError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Warns when the local artifact has a different ABI, or was built
+    /// from different sources, than the bundled contract.
+    fn warn_on_artifact_mismatch(&self, bundled: &BundledContract) {
+        if let Some(abi) = &bundled.abi {
+            let local = self
+                .artifacts_resource
+                .get_artifact(&bundled.contract.file_name, &bundled.contract.contract_name);
+            match local {
+                Ok(local) if local.abi != *abi => log::warn!(
+                    "Local ABI for {}:{} differs from the bundle",
+                    bundled.contract.file_name,
+                    bundled.contract.contract_name
+                ),
+                Ok(_) => {}
+                Err(_) => log::warn!(
+                    "No local artifact for {}:{}, cannot compare ABIs",
+                    bundled.contract.file_name,
+                    bundled.contract.contract_name
+                ),
+            }
+        }
+        if bundled.source_hashes.is_empty() {
+            return;
+        }
+        let local = self
+            .artifacts_resource
+            .get_source_hashes(&bundled.contract.file_name, &bundled.contract.contract_name);
+        match local {
+            Ok(local) if local != bundled.source_hashes => log::warn!(
+                "Local sources for {}:{} differ from the bundle",
+                bundled.contract.file_name,
+                bundled.contract.contract_name
+            ),
+            Ok(_) => {}
+            Err(_) => log::warn!(
+                "No local artifact for {}:{}, cannot compare source hashes",
+                bundled.contract.file_name,
+                bundled.contract.contract_name
+            ),
+        }
+    }
+}
+
+/// Returns the addresses shadowed more than once in `bundle`.
+fn duplicates(bundle: &ShadowBundle) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let mut duplicates = Vec::new();
+    for bundled in &bundle.contracts {
+        let address = bundled.contract.address.to_lowercase();
+        if !seen.insert(address.clone()) && !duplicates.contains(&address) {
+            duplicates.push(address);
+        }
+    }
+    duplicates
+}
+
+/// Returns the addresses registered in `existing` with a different
+/// contract than in `bundle`.
+fn conflicts(existing: &[ShadowContract], bundle: &ShadowBundle) -> Vec<String> {
+    bundle
+        .contracts
+        .iter()
+        .filter(|bundled| {
+            let address = bundled.contract.address.to_lowercase();
+            existing
+                .iter()
+                .any(|c| c.address.to_lowercase() == address && *c != bundled.contract)
+        })
+        .map(|bundled| bundled.contract.address.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        resources::{artifacts::LocalArtifactStore, shadow::LocalShadowStore},
+        test_fixture,
+    };
+
+    fn shadow_store_with_fixture() -> (tempfile::TempDir, LocalShadowStore) {
+        let temp_dir = tempdir().unwrap();
+        let file_path_buf = temp_dir.path().join("shadow.json");
+        File::create(file_path_buf.as_path()).unwrap();
+        fs::copy(
+            test_fixture!("resources", "shadow.json"),
+            file_path_buf.as_path(),
+        )
+        .unwrap();
+        let store = LocalShadowStore::new(temp_dir.path());
+        (temp_dir, store)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_export() {
+        let export = Export {
+            artifacts_resource: LocalArtifactStore::new(test_fixture!("resources", "")),
+            shadow_resource: LocalShadowStore::new(test_fixture!("resources", "")),
+            include_abi: false,
+            include_source_hashes: false,
+        };
+        let bundle = export.run().await.unwrap();
+        assert_eq!(bundle.version, BUNDLE_VERSION);
+        assert_eq!(bundle.contracts.len(), 2);
+        assert!(bundle.contracts[0].abi.is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_detect_conflicts() {
+        let (_temp_dir, shadow_resource) = shadow_store_with_fixture();
+        let contract = ShadowContract {
+            file_name: "UniswapV2Router02.sol".to_string(),
+            contract_name: "UniswapV2Router02".to_string(),
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_string(),
+            runtime_bytecode: "UniswapV2Router02_otherruntimebytecode".to_string(),
+            enabled: true,
+            tags: Vec::new(),
+            attestation: None,
+        };
+        let bundle = ShadowBundle {
+            version: BUNDLE_VERSION,
+            contracts: vec![BundledContract {
+                contract,
+                abi: None,
+                source_hashes: BTreeMap::new(),
+            }],
+        };
+
+        let import = Import {
+            bundle: bundle.clone(),
+            artifacts_resource: LocalArtifactStore::new(test_fixture!("resources", "")),
+            shadow_resource,
+            merge: true,
+            overwrite: false,
+        };
+        let result = import.run().await;
+        assert!(matches!(result, Err(BundleError::ConflictError(addresses))
+            if addresses == vec!["0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned()]));
+
+        let import = Import {
+            overwrite: true,
+            ..import
+        };
+        import.run().await.unwrap();
+        let contract = import
+            .shadow_resource
+            .get_by_address("0x7a250d5630b4cf539739df2c5dacb4c659f2488d")
+            .await
+            .unwrap();
+        assert_eq!(
+            contract.runtime_bytecode,
+            "UniswapV2Router02_otherruntimebytecode"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_replace_store_without_merge() {
+        let (_temp_dir, shadow_resource) = shadow_store_with_fixture();
+        let contract = ShadowContract {
+            file_name: "UniswapV2Router02.sol".to_string(),
+            contract_name: "UniswapV2Router02".to_string(),
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_string(),
+            runtime_bytecode: "UniswapV2Router02_otherruntimebytecode".to_string(),
+            ..Default::default()
+        };
+        let bundled = BundledContract {
+            contract: contract.clone(),
+            abi: None,
+            source_hashes: BTreeMap::new(),
+        };
+        let import = Import {
+            bundle: ShadowBundle {
+                version: BUNDLE_VERSION,
+                contracts: vec![bundled.clone(), bundled],
+            },
+            artifacts_resource: LocalArtifactStore::new(test_fixture!("resources", "")),
+            shadow_resource,
+            merge: false,
+            overwrite: false,
+        };
+        match import.run().await {
+            Err(BundleError::CustomError(message)) => assert!(message.contains(&contract.address)),
+            result => panic!("expected a duplicate address error, got {:?}", result),
+        }
+
+        let import = Import {
+            bundle: ShadowBundle {
+                version: BUNDLE_VERSION,
+                contracts: vec![BundledContract {
+                    contract: contract.clone(),
+                    abi: None,
+                    source_hashes: BTreeMap::new(),
+                }],
+            },
+            ..import
+        };
+        import.run().await.unwrap();
+        assert_eq!(import.shadow_resource.list().await.unwrap(), vec![contract]);
+    }
+
+    #[test]
+    fn conflicts_ignore_address_case() {
+        let existing = ShadowContract {
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_string(),
+            runtime_bytecode: "UniswapV2Router02_runtimebytecode".to_string(),
+            ..Default::default()
+        };
+        let contract = ShadowContract {
+            address: "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D".to_string(),
+            runtime_bytecode: "UniswapV2Router02_otherruntimebytecode".to_string(),
+            ..Default::default()
+        };
+        let bundle = ShadowBundle {
+            version: BUNDLE_VERSION,
+            contracts: vec![BundledContract {
+                contract: contract.clone(),
+                abi: None,
+                source_hashes: BTreeMap::new(),
+            }],
+        };
+        assert_eq!(conflicts(&[existing], &bundle), vec![contract.address]);
+    }
+}
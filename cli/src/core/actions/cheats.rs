@@ -0,0 +1,54 @@
+use ethers::{
+    providers::{JsonRpcClient, Middleware, Provider, ProviderError},
+    types::{Address, U256},
+};
+use thiserror::Error;
+
+/// Wraps anvil's cheat RPC methods on a running shadow fork.
+///
+/// This action is used by the `impersonate` and `fund` commands.
+pub struct Cheats<P: JsonRpcClient> {
+    /// The provider of the running shadow fork
+    pub provider: Provider<P>,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum CheatsError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Provider error
+    #[error("ProviderError: {0}")]
+    ProviderError(#[from] ProviderError),
+}
+
+impl<P: JsonRpcClient> Cheats<P> {
+    /// Lets transactions be sent from an address without its private key.
+    pub async fn impersonate(&self, address: Address) -> Result<(), CheatsError> {
+        self.provider
+            .request::<_, ()>("anvil_impersonateAccount", [address])
+            .await?;
+        Ok(())
+    }
+
+    /// Stops impersonating an address.
+    pub async fn stop_impersonating(&self, address: Address) -> Result<(), CheatsError> {
+        self.provider
+            .request::<_, ()>("anvil_stopImpersonatingAccount", [address])
+            .await?;
+        Ok(())
+    }
+
+    /// Adds `amount` wei to the balance of an address, returning the new balance.
+    pub async fn fund(&self, address: Address, amount: U256) -> Result<U256, CheatsError> {
+        let balance = self.provider.get_balance(address, None).await?;
+        let balance = balance.checked_add(amount).ok_or_else(|| {
+            CheatsError::CustomError("The new balance overflows a uint256".to_owned())
+        })?;
+        self.provider
+            .request::<_, ()>("anvil_setBalance", (address, balance))
+            .await?;
+        Ok(balance)
+    }
+}
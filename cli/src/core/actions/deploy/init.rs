@@ -0,0 +1,123 @@
+use std::{fs, path::Path};
+
+use ethers::types::{Bytes, H160, U256};
+use serde::Deserialize;
+
+use crate::decode::encode_function_call;
+
+/// Calls made on the temporary deploy fork once the shadow contract is
+/// created, and before its runtime bytecode is read, for contracts whose
+/// constructors don't fully configure them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct InitManifest {
+    /// The calls, in order
+    pub calls: Vec<InitCall>,
+}
+
+/// A call made on the temporary deploy fork from an impersonated sender.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct InitCall {
+    /// A name to refer to the call in errors
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// The sender. Defaults to the creator of the original contract.
+    #[serde(default)]
+    pub from: Option<H160>,
+
+    /// The contract to call. Defaults to the shadow contract, which
+    /// the fork creates at another address than the original.
+    #[serde(default)]
+    pub to: Option<H160>,
+
+    /// The function to call, e.g. `initialize(address,uint24)`
+    pub signature: String,
+
+    /// The arguments of the function, e.g. `["0x...", "500"]`
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// The value sent, as a hex quantity
+    #[serde(default)]
+    pub value: U256,
+}
+
+impl InitCall {
+    /// Returns the name of the call, or its position in the manifest.
+    pub fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("init call {}", index))
+    }
+
+    /// Encodes the calldata from the signature and arguments.
+    pub fn calldata(&self) -> Result<Bytes, String> {
+        encode_function_call(&self.signature, &self.args)
+    }
+}
+
+impl InitManifest {
+    /// Loads the init calls from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading init calls {}: {}", path.display(), e))?;
+        Self::from_json(&json)
+    }
+
+    /// Parses the init calls, checking that every call can be encoded.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let manifest: Self =
+            serde_json::from_str(json).map_err(|e| format!("Invalid init calls: {}", e))?;
+        for (index, call) in manifest.calls.iter().enumerate() {
+            call.calldata()
+                .map_err(|e| format!("Invalid {}: {}", call.label(index), e))?;
+        }
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_init_calls() {
+        let manifest = InitManifest::from_json(
+            r#"{
+                "calls": [
+                    {
+                        "name": "set-fee",
+                        "signature": "setFee(uint24)",
+                        "args": ["500"]
+                    },
+                    {
+                        "from": "0x00000000000000000000000000000000000000aa",
+                        "to": "0x00000000000000000000000000000000000000bb",
+                        "signature": "initialize()",
+                        "value": "0x1"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.calls[0].label(0), "set-fee");
+        assert_eq!(manifest.calls[0].from, None);
+        assert_eq!(manifest.calls[0].to, None);
+        let mut expected = ethers::utils::id("setFee(uint24)").to_vec();
+        expected.extend(ethers::abi::encode(&[ethers::abi::Token::Uint(500.into())]));
+        assert_eq!(manifest.calls[0].calldata().unwrap(), Bytes::from(expected));
+
+        assert_eq!(manifest.calls[1].label(1), "init call 1");
+        assert_eq!(manifest.calls[1].from, Some(H160::from_low_u64_be(0xaa)));
+        assert_eq!(manifest.calls[1].value, U256::one());
+
+        assert_eq!(
+            InitManifest::from_json(r#"{ "calls": [{ "signature": "setFee(uint24)" }] }"#)
+                .unwrap_err(),
+            "Invalid init call 0: setFee(uint24) takes 1 arguments, got 0"
+        );
+    }
+}
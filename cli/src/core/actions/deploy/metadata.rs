@@ -0,0 +1,92 @@
+use std::ops::Range;
+
+use clap::ValueEnum;
+
+/// Controls what happens to the CBOR metadata the compiler appends
+/// to the shadow runtime bytecode.
+///
+/// The metadata embeds a hash of the compiler metadata, so it changes
+/// with comments, file paths and compiler settings even when the
+/// executed code is identical.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum MetadataMode {
+    /// Store the bytecode as deployed.
+    #[default]
+    Keep,
+    /// Remove the metadata and its length suffix.
+    ///
+    /// This changes the code size, so contracts that read their own
+    /// code with `CODECOPY` past the executable code may behave
+    /// differently.
+    Strip,
+    /// Overwrite the metadata with zeros, keeping the code size.
+    Zero,
+}
+
+impl MetadataMode {
+    /// Applies the mode to runtime bytecode.
+    ///
+    /// Bytecode without recognizable metadata is returned unchanged.
+    pub fn apply(&self, code: &[u8]) -> Vec<u8> {
+        let Some(range) = metadata_range(code) else {
+            return code.to_vec();
+        };
+        match self {
+            MetadataMode::Keep => code.to_vec(),
+            MetadataMode::Strip => code[..range.start].to_vec(),
+            MetadataMode::Zero => {
+                let mut code = code.to_vec();
+                code[range.start..code.len() - 2].fill(0);
+                code
+            }
+        }
+    }
+}
+
+/// Returns the range of the CBOR metadata, including its two byte
+/// big-endian length suffix.
+fn metadata_range(code: &[u8]) -> Option<Range<usize>> {
+    if code.len() < 2 {
+        return None;
+    }
+    let length = u16::from_be_bytes([code[code.len() - 2], code[code.len() - 1]]) as usize;
+    let start = code.len().checked_sub(length + 2)?;
+    // Solidity and Vyper encode the metadata as a CBOR map
+    match code.get(start) {
+        Some(0xa0..=0xbf) if length > 0 => Some(start..code.len()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `PUSH1 0x80 PUSH1 0x40 MSTORE`, then `{"ipfs": 0x0102, "solc": 0.8.19}`
+    /// encoded as CBOR, then its length
+    fn code() -> Vec<u8> {
+        hex::decode("6080604052a2646970667342010264736f6c63430008130012").unwrap()
+    }
+
+    #[test]
+    fn can_find_metadata() {
+        assert_eq!(metadata_range(&code()), Some(5..25));
+        assert_eq!(metadata_range(&hex::decode("6080604052").unwrap()), None);
+        assert_eq!(metadata_range(&[]), None);
+    }
+
+    #[test]
+    fn can_apply_modes() {
+        let code = code();
+        assert_eq!(MetadataMode::Keep.apply(&code), code);
+        assert_eq!(
+            MetadataMode::Strip.apply(&code),
+            hex::decode("6080604052").unwrap()
+        );
+        let zeroed = MetadataMode::Zero.apply(&code);
+        assert_eq!(zeroed.len(), code.len());
+        assert_eq!(&zeroed[..5], &code[..5]);
+        assert!(zeroed[5..23].iter().all(|byte| *byte == 0));
+        assert_eq!(&zeroed[23..], &[0x00, 0x12]);
+    }
+}
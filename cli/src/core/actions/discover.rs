@@ -0,0 +1,214 @@
+use std::{collections::HashSet, time::Duration};
+
+use alloy_json_abi::Event;
+use ethers::{
+    providers::{JsonRpcClient, Middleware, Provider, ProviderError},
+    types::{Filter, H160, H256},
+};
+use thiserror::Error;
+
+use crate::{decode, providers::with_timeout};
+
+/// Finds the contracts a factory created, from the factory's
+/// historical creation events.
+///
+/// This action is used by the `discover` command.
+pub struct Discover<P: JsonRpcClient> {
+    /// The Ethereum provider
+    pub provider: Provider<P>,
+
+    /// The address of the factory
+    pub factory: H160,
+
+    /// The event the factory emits for each child, e.g. `PoolCreated`
+    pub event: Event,
+
+    /// The event parameter holding the child address. Defaults to the
+    /// last address parameter of the event.
+    pub param: Option<String>,
+
+    /// The first block to scan
+    pub from_block: u64,
+
+    /// The last block to scan. Defaults to the latest block.
+    pub to_block: Option<u64>,
+
+    /// How many blocks each `eth_getLogs` query covers
+    pub chunk_size: u64,
+
+    /// How long an RPC call may take before it is failed
+    pub rpc_timeout: Duration,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum DiscoverError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Provider error
+    #[error("ProviderError: {0}")]
+    ProviderError(#[from] ProviderError),
+}
+
+impl<P: JsonRpcClient> Discover<P> {
+    /// Returns the child addresses, in the order they were created.
+    pub async fn run(&self) -> Result<Vec<String>, DiscoverError> {
+        let param = child_param(&self.event, self.param.as_deref())?;
+        let to_block = match self.to_block {
+            Some(to_block) => to_block,
+            None => with_timeout(self.rpc_timeout, self.provider.get_block_number())
+                .await?
+                .as_u64(),
+        };
+
+        let mut children = Vec::new();
+        let mut seen = HashSet::new();
+        let mut from_block = self.from_block;
+        while from_block <= to_block {
+            let chunk_end = to_block.min(from_block.saturating_add(self.chunk_size.max(1) - 1));
+            let filter = Filter::new()
+                .address(self.factory)
+                .topic0(H256::from_slice(self.event.selector().as_slice()))
+                .from_block(from_block)
+                .to_block(chunk_end);
+            let logs = with_timeout(self.rpc_timeout, self.provider.get_logs(&filter)).await?;
+            for log in logs {
+                let decoded = decode::decode_log(&log, &self.event)
+                    .map_err(|e| DiscoverError::CustomError(e.to_string()))?;
+                let child = decoded
+                    .get(&param)
+                    .and_then(|value| value.as_str())
+                    .map(|child| child.to_lowercase())
+                    .ok_or_else(|| {
+                        DiscoverError::CustomError(format!(
+                            "Event parameter {} is missing from log {:?}",
+                            param, log.transaction_hash
+                        ))
+                    })?;
+                if seen.insert(child.clone()) {
+                    children.push(child);
+                }
+            }
+            from_block = chunk_end + 1;
+        }
+
+        Ok(children)
+    }
+}
+
+/// Returns the name of the event parameter holding the child address.
+///
+/// If no parameter is given, the last address parameter of the event
+/// is used, which is the child in the common factory events, e.g.
+/// `PoolCreated(token0, token1, fee, tickSpacing, pool)`.
+pub fn child_param(event: &Event, param: Option<&str>) -> Result<String, DiscoverError> {
+    match param {
+        Some(param) => event
+            .inputs
+            .iter()
+            .find(|input| input.name == param && input.ty == "address")
+            .map(|input| input.name.clone())
+            .ok_or_else(|| {
+                DiscoverError::CustomError(format!(
+                    "Event {} has no address parameter named {}",
+                    event.name, param
+                ))
+            }),
+        None => event
+            .inputs
+            .iter()
+            .rev()
+            .find(|input| input.ty == "address")
+            .map(|input| input.name.clone())
+            .ok_or_else(|| {
+                DiscoverError::CustomError(format!("Event {} has no address parameter", event.name))
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        abi::Token,
+        providers::MockProvider,
+        types::{Bytes, Log, I256},
+    };
+
+    use super::*;
+
+    fn pool_created() -> Event {
+        serde_json::from_str(
+            r#"{
+                "type": "event",
+                "name": "PoolCreated",
+                "inputs": [
+                    { "name": "token0", "type": "address", "internalType": "address", "indexed": true },
+                    { "name": "token1", "type": "address", "internalType": "address", "indexed": true },
+                    { "name": "fee", "type": "uint24", "internalType": "uint24", "indexed": true },
+                    { "name": "tickSpacing", "type": "int24", "internalType": "int24", "indexed": false },
+                    { "name": "pool", "type": "address", "internalType": "address", "indexed": false }
+                ],
+                "anonymous": false
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn can_find_child_param() {
+        let event = pool_created();
+        assert_eq!(child_param(&event, None).unwrap(), "pool");
+        assert_eq!(child_param(&event, Some("token0")).unwrap(), "token0");
+        assert!(child_param(&event, Some("fee")).is_err());
+        assert!(child_param(&event, Some("missing")).is_err());
+    }
+
+    fn pool_created_log(event: &Event, pool: H160) -> Log {
+        Log {
+            address: H160::repeat_byte(0xfa),
+            topics: vec![
+                H256::from_slice(event.selector().as_slice()),
+                H256::from(H160::repeat_byte(1)),
+                H256::from(H160::repeat_byte(2)),
+                H256::from_low_u64_be(3000),
+            ],
+            data: Bytes::from(ethers::abi::encode(&[
+                Token::Int(I256::from(60).into_raw()),
+                Token::Address(pool),
+            ])),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn scans_in_chunks() {
+        let event = pool_created();
+        let first = H160::repeat_byte(0xaa);
+        let second = H160::repeat_byte(0xbb);
+        let mock = MockProvider::new();
+        // Responses are popped in reverse order
+        mock.push(vec![
+            pool_created_log(&event, second),
+            pool_created_log(&event, first),
+        ])
+        .unwrap();
+        mock.push(vec![pool_created_log(&event, first)]).unwrap();
+
+        let discover = Discover {
+            provider: Provider::new(mock),
+            factory: H160::repeat_byte(0xfa),
+            event,
+            param: None,
+            from_block: 0,
+            to_block: Some(19),
+            chunk_size: 10,
+            rpc_timeout: Duration::from_secs(1),
+        };
+        let children = discover.run().await.unwrap();
+        assert_eq!(
+            children,
+            vec![format!("{:?}", first), format!("{:?}", second)]
+        );
+    }
+}
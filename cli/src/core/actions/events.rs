@@ -1,33 +1,201 @@
 use alloy_json_abi::Event;
 use ethers::{
     prelude::{providers::StreamExt, Provider},
-    providers::{JsonRpcClient, Middleware, ProviderError, PubsubClient},
+    providers::{JsonRpcClient, Middleware, ProviderError, PubsubClient, SubscriptionStream},
     types::Filter,
 };
-use std::{str::FromStr, sync::Arc};
+use futures_util::{
+    future::BoxFuture,
+    stream::{BoxStream, SelectAll},
+};
+use std::{
+    collections::HashSet,
+    future::Future,
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
 
 use crate::{
+    balances::BalanceTracker,
+    config::JoinRule,
     core::resources::{
         artifacts::ArtifactsResource,
+        dead_letter::{DeadLetter, DeadLetterResource, FailureStage},
+        event_store::{EventQuery, EventStore},
+        plugin::EventPlugin,
         shadow::{ShadowContract, ShadowResource},
+        signatures::SignaturesResource,
+        sink::{BalanceDiff, Correlation, DecodedEvent, EventSink, RawLog},
     },
-    decode,
+    decode::{self, encoding::Encoder, schema::describe_event, EventRegistry, SchemaDrift},
+    providers::{retry_with_backoff, with_timeout, DEFAULT_RPC_TIMEOUT},
+    render::{self, OutputFormat, Renderer},
+    server::health::Health,
 };
 
+/// How long to wait for more logs of a transaction before emitting its
+/// grouped events.
+const GROUP_IDLE: Duration = Duration::from_millis(500);
+
+/// Opens a new connection to the fork.
+type Reconnect<P> =
+    Box<dyn Fn() -> BoxFuture<'static, Result<Provider<P>, ProviderError>> + Send + Sync>;
+
+/// The log subscriptions of every shard, merged. A shard's subscription
+/// ending ends the stream with `None`.
+type LogStream<'a> = SelectAll<BoxStream<'a, Option<(usize, ethers::types::Log)>>>;
+
+/// The subscription to the fork's blocks.
+type BlockStream<'a, P> = SubscriptionStream<'a, P, ethers::types::Block<ethers::types::TxHash>>;
+
 /// Subscribes to events from a shadow contract on
 /// a local fork.
 ///
 /// This action is used by the `events` command.
 pub struct Events<P: JsonRpcClient> {
     /// The Ethereum provider
-    provider: Arc<Provider<P>>,
+    provider: RwLock<Arc<Provider<P>>>,
+
+    /// Extra connections the log subscriptions are spread over, along
+    /// with the provider's, when they are sharded.
+    shard_providers: RwLock<Vec<Arc<Provider<P>>>>,
+
+    /// Opens the connections again when a subscription ends, if set.
+    reconnect: Option<Reconnect<P>>,
+
+    /// The most addresses a single log subscription filters on, if
+    /// limited. The instances are sharded over several subscriptions.
+    max_addresses_per_filter: Option<usize>,
 
     /// The shadow contract to listen to events for.
     shadow_contract: ShadowContract,
 
+    /// The addresses of every instance of the shadow contract, which
+    /// share its ABI and are listened to as a group.
+    instances: RwLock<Vec<ethers::types::H160>>,
+
+    /// Only the instances with any of these tags are listened to
+    tags: Vec<String>,
+
     /// The event to listen to.
     event: Event,
+
+    /// The sinks decoded events are written to, in addition to the console,
+    /// with their own encoding if set.
+    sinks: Vec<(Box<dyn EventSink>, Option<Encoder>)>,
+
+    /// How values are encoded on the console and in the sinks without
+    /// their own encoding.
+    encoder: Encoder,
+
+    /// Renders events with a template for the console, if one is set.
+    renderer: Renderer,
+
+    /// How events are printed to the console.
+    format: OutputFormat,
+
+    /// The most recently seen block number and timestamp.
+    last_block: Mutex<Option<(u64, u64)>>,
+
+    /// Decodes the other logs of transactions that emit the event, if set.
+    context: Option<LogContext>,
+
+    /// The last transaction whose other logs were printed.
+    context_tx: Mutex<Option<ethers::types::H256>>,
+
+    /// Whether to include the raw log with each decoded event.
+    include_raw: bool,
+
+    /// Records the balance changes around each event's transaction, if set.
+    balances: Option<BalanceTracker>,
+
+    /// The last transaction an event was decoded from, and the ordinal
+    /// of that event within it.
+    last_transaction: Mutex<Option<(ethers::types::H256, u64)>>,
+
+    /// Whether to print and write the events of a transaction together.
+    group_by_transaction: bool,
+
+    /// The events of the current transaction, when grouping by transaction.
+    group: Mutex<Vec<(ethers::types::Log, DecodedEvent)>>,
+
+    /// Joins events with the other logs of their transaction, if set.
+    joins: Option<Joins>,
+
+    /// Post-process each decoded event before it is printed and written
+    /// to the sinks, in order.
+    plugins: Vec<Box<dyn EventPlugin>>,
+
+    /// Where logs that fail to decode or to be written are kept, if set.
+    dead_letters: Option<Box<dyn DeadLetterResource>>,
+
+    /// How long an RPC call may take before it is failed.
+    rpc_timeout: Duration,
+
+    /// Stops listening when cancelled.
+    cancel: CancellationToken,
+
+    /// Stops listening after this many blocks, if set
+    max_blocks: Option<u64>,
+
+    /// Reports the listener's progress to health checks, if set
+    health: Option<Health>,
+
+    /// The id of the event ABI events are decoded with.
+    schema: String,
+
+    /// Watches the artifacts for changes to the event ABI, if set.
+    abi_watch: Option<AbiWatch>,
+
+    /// Watches the Shadow store for new instances, if set.
+    instance_watch: Option<InstanceWatch>,
+}
+
+/// Periodically reloads the artifact of the shadow contract, to detect
+/// when the event ABI changes while events are being decoded.
+struct AbiWatch {
+    /// The Artifacts resource the artifact is reloaded from
+    artifacts_resource: Box<dyn ArtifactsResource + Send + Sync>,
+
+    /// How often the artifact is reloaded
+    interval: Duration,
+
+    /// The last schema a change was reported for
+    reported: Mutex<Option<String>>,
+}
+
+/// Periodically lists the instances of the shadow contract, to listen
+/// to the ones shadowed while events are being decoded, e.g. the
+/// children of a tracked factory.
+struct InstanceWatch {
+    /// The Shadow resource the instances are listed from
+    shadow_resource: Box<dyn ShadowResource + Send + Sync>,
+
+    /// How often the instances are listed
+    interval: Duration,
+}
+
+/// Joins events with the other logs of their transaction into composite
+/// records, which are printed and written to the sinks after the events.
+struct Joins {
+    /// The join rules
+    rules: Vec<JoinRule>,
+
+    /// Decodes the other logs of the transaction
+    registry: EventRegistry,
+}
+
+/// Decodes every log of the transactions that emit the event, so the
+/// context around each event is visible.
+pub struct LogContext {
+    /// The known event ABIs
+    pub registry: EventRegistry,
+
+    /// Names logs whose ABI isn't known, if set
+    pub signatures: Option<Box<dyn SignaturesResource + Send + Sync>>,
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -42,9 +210,17 @@ pub enum EventsError {
     /// Decoder error
     #[error("DecoderError: {0}")]
     DecoderError(#[from] Box<dyn std::error::Error>),
+    /// A decoded event that doesn't match the event's schema
+    #[error("SchemaError: {0}")]
+    SchemaError(String),
+    /// A plugin failed to process a decoded event
+    #[error("PluginError: {0}")]
+    PluginError(String),
 }
 
 impl<P: JsonRpcClient + PubsubClient> Events<P> {
+    /// Creates the action for every instance of the shadow contract with
+    /// any of the given tags, or every instance if no tags are given.
     pub async fn new<A: ArtifactsResource, S: ShadowResource>(
         file_name: String,
         contract_name: String,
@@ -52,16 +228,38 @@ impl<P: JsonRpcClient + PubsubClient> Events<P> {
         provider: Provider<P>,
         artifacts_resource: A,
         shadow_resource: S,
+        tags: &[String],
     ) -> Result<Self, EventsError> {
-        let provider = Arc::new(provider);
+        let provider = RwLock::new(Arc::new(provider));
 
-        // Get shadow contract
-        let shadow_contract = shadow_resource
-            .get_by_name(&file_name, &contract_name)
+        // Get every tagged instance of the shadow contract
+        let mut contracts = shadow_resource
+            .list_by_name(&file_name, &contract_name)
             .await
             .map_err(|e| {
                 EventsError::CustomError(format!("Error getting shadow contract: {}", e))
             })?;
+        contracts.retain(|contract| contract.has_any_tag(tags));
+        let instances = contracts
+            .iter()
+            .map(|contract| {
+                ethers::types::H160::from_str(&contract.address).map_err(|e| {
+                    EventsError::CustomError(format!(
+                        "Invalid shadow contract address {}: {}",
+                        contract.address, e
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let shadow_contract = contracts.into_iter().next().ok_or_else(|| {
+            EventsError::CustomError(match tags.is_empty() {
+                true => "Error getting shadow contract: Contract not found".to_owned(),
+                false => format!(
+                    "Error getting shadow contract: No instance tagged {}",
+                    tags.join(", ")
+                ),
+            })
+        })?;
 
         // Get the artifact
         let artifact = artifacts_resource
@@ -73,9 +271,36 @@ impl<P: JsonRpcClient + PubsubClient> Events<P> {
 
         match event {
             Some(event) => Ok(Self {
+                schema: decode::schema_id(&event),
+                abi_watch: None,
+                instance_watch: None,
                 provider,
+                shard_providers: RwLock::new(Vec::new()),
+                reconnect: None,
+                max_addresses_per_filter: None,
                 shadow_contract,
+                instances: RwLock::new(instances),
+                tags: tags.to_vec(),
                 event,
+                sinks: Vec::new(),
+                encoder: Encoder::default(),
+                renderer: Renderer::default(),
+                format: OutputFormat::default(),
+                last_block: Mutex::new(None),
+                context: None,
+                context_tx: Mutex::new(None),
+                include_raw: false,
+                balances: None,
+                last_transaction: Mutex::new(None),
+                group_by_transaction: false,
+                group: Mutex::new(Vec::new()),
+                joins: None,
+                plugins: Vec::new(),
+                dead_letters: None,
+                rpc_timeout: DEFAULT_RPC_TIMEOUT,
+                cancel: CancellationToken::new(),
+                max_blocks: None,
+                health: None,
             }),
             None => Err(EventsError::CustomError(format!(
                 "Event signature not found in contract's ABI: {}",
@@ -84,27 +309,576 @@ impl<P: JsonRpcClient + PubsubClient> Events<P> {
         }
     }
 
+    /// Adds a sink that decoded events are written to.
+    pub fn add_sink<K: EventSink + 'static>(&mut self, sink: K) {
+        self.sinks.push((Box::new(sink), None));
+    }
+
+    /// Adds a plugin that post-processes decoded events, after the
+    /// plugins already added.
+    pub fn add_plugin<K: EventPlugin + 'static>(&mut self, plugin: K) {
+        self.plugins.push(Box::new(plugin));
+    }
+
+    /// Adds a sink that decoded events are written to, with values
+    /// encoded for it rather than with the default encoding.
+    pub fn add_encoded_sink<K: EventSink + 'static>(&mut self, sink: K, encoder: Encoder) {
+        self.sinks.push((Box::new(sink), Some(encoder)));
+    }
+
+    /// Sets how values are encoded on the console and in the sinks
+    /// without their own encoding.
+    ///
+    /// Events are decoded, validated, joined and dead-lettered with the
+    /// decoder's encoding, and only encoded when printed or written.
+    pub fn set_encoder(&mut self, encoder: Encoder) {
+        self.encoder = encoder;
+    }
+
+    /// Returns the event being listened to.
+    pub fn event(&self) -> &Event {
+        &self.event
+    }
+
+    /// Returns the shadow contract being listened to.
+    ///
+    /// If the contract is shadowed at several addresses, this is the
+    /// first instance.
+    pub fn shadow_contract(&self) -> &ShadowContract {
+        &self.shadow_contract
+    }
+
+    /// Sets the renderer used to print events to the console.
+    pub fn set_renderer(&mut self, renderer: Renderer) {
+        self.renderer = renderer;
+    }
+
+    /// Sets how events are printed to the console.
+    pub fn set_format(&mut self, format: OutputFormat) {
+        self.format = format;
+    }
+
+    /// Decodes and prints the other logs of each transaction that
+    /// emits the event.
+    pub fn set_context(&mut self, context: LogContext) {
+        self.context = Some(context);
+    }
+
+    /// Includes the raw log with each decoded event, so consumers
+    /// can re-decode or verify it.
+    pub fn set_include_raw(&mut self, include_raw: bool) {
+        self.include_raw = include_raw;
+    }
+
+    /// Includes the ETH and ERC-20 balance changes of the accounts
+    /// involved in each event's transaction with the decoded event.
+    pub fn track_balances(&mut self, tokens: Vec<ethers::types::H160>) {
+        self.balances = Some(BalanceTracker::new(tokens));
+    }
+
+    /// Prints and writes the events of each transaction together, once
+    /// the transaction's logs stop arriving, instead of one by one.
+    pub fn set_group_by_transaction(&mut self, group_by_transaction: bool) {
+        self.group_by_transaction = group_by_transaction;
+    }
+
+    /// Joins each event with the other logs of its transaction that
+    /// match the rules, decoded with the registry.
+    pub fn set_joins(&mut self, rules: Vec<JoinRule>, registry: EventRegistry) {
+        self.joins = Some(Joins { rules, registry });
+    }
+
+    /// Keeps the logs that fail to decode or to be written to a sink
+    /// in a dead-letter queue, so they can be redriven later.
+    pub fn set_dead_letters<D: DeadLetterResource + 'static>(&mut self, dead_letters: D) {
+        self.dead_letters = Some(Box::new(dead_letters));
+    }
+
+    /// Sets how long an RPC call may take before it is failed.
+    pub fn set_rpc_timeout(&mut self, rpc_timeout: Duration) {
+        self.rpc_timeout = rpc_timeout;
+    }
+
+    /// Shards the log subscription into filters of at most
+    /// `max_addresses_per_filter` instances each, spread over the
+    /// provider's connection and the given ones, and merges their logs.
+    ///
+    /// This keeps contracts shadowed at hundreds of addresses under
+    /// provider-side filter size limits, and a busy shard from holding
+    /// up the others.
+    pub fn shard_subscriptions(
+        &mut self,
+        max_addresses_per_filter: usize,
+        providers: Vec<Provider<P>>,
+    ) {
+        self.max_addresses_per_filter = Some(max_addresses_per_filter.max(1));
+        self.shard_providers = RwLock::new(providers.into_iter().map(Arc::new).collect());
+    }
+
+    /// Opens new connections with `reconnect` when a log subscription
+    /// ends, e.g. because the fork restarted, retrying with exponential
+    /// backoff.
+    ///
+    /// The connections must not reconnect on their own: a subscription
+    /// silently resumed on a new connection misses the logs emitted
+    /// while it was down, which are only backfilled when it ends.
+    pub fn set_reconnect<F, Fut>(&mut self, reconnect: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Provider<P>, ProviderError>> + Send + 'static,
+    {
+        self.reconnect = Some(Box::new(move || Box::pin(reconnect())));
+    }
+
+    /// Stops listening to the event when the token is cancelled.
+    pub fn set_cancellation(&mut self, cancel: CancellationToken) {
+        self.cancel = cancel;
+    }
+
+    /// Stops listening to the event once this many blocks were mined on
+    /// the fork.
+    pub fn set_max_blocks(&mut self, max_blocks: u64) {
+        self.max_blocks = Some(max_blocks);
+    }
+
+    /// Reports the blocks mined on the fork to health checks, so a
+    /// listener whose fork stalled is reported as not live.
+    pub fn set_health(&mut self, health: Health) {
+        self.health = Some(health);
+    }
+
+    /// Reloads the artifact of the shadow contract at the given interval,
+    /// and warns when the event ABI no longer matches the one events are
+    /// decoded with.
+    pub fn watch_abi<A: ArtifactsResource + Send + Sync + 'static>(
+        &mut self,
+        artifacts_resource: A,
+        interval: Duration,
+    ) {
+        self.abi_watch = Some(AbiWatch {
+            artifacts_resource: Box::new(artifacts_resource),
+            interval,
+            reported: Mutex::new(None),
+        });
+    }
+
+    /// Lists the instances of the shadow contract at the given interval,
+    /// and subscribes to the logs of the new ones.
+    ///
+    /// The new instances' logs since the listener started are
+    /// backfilled, so events emitted right after they were shadowed
+    /// aren't missed.
+    pub fn watch_instances<S: ShadowResource + Send + Sync + 'static>(
+        &mut self,
+        shadow_resource: S,
+        interval: Duration,
+    ) {
+        self.instance_watch = Some(InstanceWatch {
+            shadow_resource: Box::new(shadow_resource),
+            interval,
+        });
+    }
+
+    /// Returns the id of the event ABI events are decoded with.
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+
+    /// Checks whether an event store holds events of this contract's
+    /// event that were decoded with an older ABI.
+    pub async fn check_schema_drift(
+        &self,
+        store: &dyn EventStore,
+    ) -> Result<Option<SchemaDrift>, EventsError> {
+        let query = EventQuery {
+            event: Some(self.event.name.clone()),
+            ..Default::default()
+        };
+        let archived = store
+            .query(&query)
+            .await
+            .map_err(|e| EventsError::CustomError(format!("Error reading archive: {}", e)))?;
+        Ok(SchemaDrift::find(
+            &archived,
+            &self.contract_id(),
+            &self.event.name,
+            &self.schema,
+        ))
+    }
+
+    /// Listens to the event until the subscription can't be re-established,
+    /// or the action is cancelled.
+    ///
+    /// The position of the last processed log is tracked, so if the
+    /// subscription drops, the logs emitted in the meantime are backfilled
+    /// with `eth_getLogs` before resuming the live stream. With
+    /// [`Events::set_reconnect`], the connections are opened again first,
+    /// with exponential backoff.
+    ///
+    /// When the subscription is sharded, each shard tracks its own
+    /// position, and any shard dropping resubscribes them all.
+    ///
+    /// With a block limit or health checks, the fork's blocks are
+    /// subscribed to as well, since logs don't arrive for every block.
     pub async fn run(&self) -> Result<(), EventsError> {
-        // Build logs filter
-        let logs_filter = self.build_logs_filter();
+        // Build the logs filters, one per shard
+        let mut logs_filters = self.build_logs_filters(&self.instances.read().unwrap());
+        if logs_filters.len() > 1 {
+            log::info!(
+                "Sharding the subscription to {} addresses into {} filters",
+                self.instances.read().unwrap().len(),
+                logs_filters.len()
+            );
+        }
+
+        if self.format == OutputFormat::Table {
+            println!("{}", render::table_header());
+        }
 
-        // Subscribe to log
-        let mut stream = self.provider.subscribe_logs(&logs_filter).await?;
-        while let Some(log) = stream.next().await {
-            let result = self.on_log(log);
-            if let Err(e) = result {
-                log::warn!("Error processing log: {}", e);
+        // The block number and log index of the last processed log, per shard
+        let mut last_seen: Vec<Option<(u64, u64)>> = vec![None; logs_filters.len()];
+        // The block the first subscription started at
+        let mut since = None;
+        let track_blocks = self.max_blocks.is_some() || self.health.is_some();
+        let mut blocks_seen = 0;
+        let mut abi_check = tokio::time::interval(
+            self.abi_watch
+                .as_ref()
+                .map_or(Duration::from_secs(60), |watch| watch.interval),
+        );
+        let mut instance_check = tokio::time::interval(
+            self.instance_watch
+                .as_ref()
+                .map_or(Duration::from_secs(60), |watch| watch.interval),
+        );
+        while !self.cancel.is_cancelled() {
+            let connections = self.connections();
+            let subscribed = self
+                .subscribe(
+                    &connections,
+                    &logs_filters,
+                    track_blocks,
+                    &mut last_seen,
+                    &mut since,
+                )
+                .await;
+            let (mut stream, mut blocks) = match subscribed {
+                Ok(subscribed) => subscribed,
+                Err(e) if self.reconnect.is_some() => {
+                    log::warn!("Error resubscribing to logs: {}", e);
+                    if !self.reconnect().await {
+                        break;
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if let Some(health) = &self.health {
+                health.set_ready();
+            }
+
+            // Grouped events are emitted once their transaction's logs
+            // stop arriving
+            let idle = tokio::time::sleep(GROUP_IDLE);
+            tokio::pin!(idle);
+            let mut added = false;
+            loop {
+                tokio::select! {
+                    _ = self.cancel.cancelled() => {
+                        log::info!("Stopping the event listener");
+                        self.flush_group().await;
+                        self.flush_sinks().await;
+                        return Ok(());
+                    }
+                    log = stream.next() => match log {
+                        Some(Some((shard, log))) => {
+                            self.process_log(log, &mut last_seen[shard]).await;
+                            idle.as_mut().reset(tokio::time::Instant::now() + GROUP_IDLE);
+                        }
+                        _ => break,
+                    },
+                    _ = &mut idle, if !self.group.lock().unwrap().is_empty() => {
+                        self.flush_group().await
+                    }
+                    _ = abi_check.tick(), if self.abi_watch.is_some() => self.check_abi(),
+                    _ = instance_check.tick(), if self.instance_watch.is_some() => {
+                        // New instances get shards of their own, whose
+                        // logs are backfilled when resubscribing
+                        let instances = self.new_instances().await;
+                        if !instances.is_empty() {
+                            log::info!(
+                                "Listening to {} new instances of {}",
+                                instances.len(),
+                                self.contract_id()
+                            );
+                            logs_filters.extend(self.build_logs_filters(&instances));
+                            last_seen.resize(logs_filters.len(), None);
+                            added = true;
+                            break;
+                        }
+                    }
+                    Some(block) = async { blocks.as_mut()?.next().await }, if blocks.is_some() => {
+                        let block_number = block.number.map(|n| n.as_u64()).unwrap_or_default();
+                        if let Some(health) = &self.health {
+                            health.record_block(block_number);
+                        }
+                        blocks_seen += 1;
+                        if self.max_blocks.map_or(false, |max| blocks_seen >= max) {
+                            log::info!("Stopping the event listener after {} blocks", blocks_seen);
+                            self.flush_group().await;
+                            self.flush_sinks().await;
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+            self.flush_group().await;
+            drop((stream, blocks));
+            if added {
+                continue;
+            }
+            log::warn!("Log subscription dropped, resubscribing");
+            if self.reconnect.is_some() && !self.reconnect().await {
+                break;
             }
         }
+        self.flush_sinks().await;
 
         Ok(())
     }
 
-    fn build_logs_filter(&self) -> Filter {
+    /// Subscribes to the logs of every shard, and to blocks if
+    /// `track_blocks` is set, then backfills the logs each shard missed
+    /// since its last processed log, or since the first subscription
+    /// started if it has none.
+    ///
+    /// Subscribing before backfilling ensures no log falls in between.
+    async fn subscribe<'a>(
+        &self,
+        connections: &'a [Arc<Provider<P>>],
+        logs_filters: &[Filter],
+        track_blocks: bool,
+        last_seen: &mut [Option<(u64, u64)>],
+        since: &mut Option<u64>,
+    ) -> Result<(LogStream<'a>, Option<BlockStream<'a, P>>), EventsError> {
+        let mut shards = Vec::new();
+        for (shard, logs_filter) in logs_filters.iter().enumerate() {
+            let provider = &connections[shard % connections.len()];
+            let stream =
+                with_timeout(self.rpc_timeout, provider.subscribe_logs(logs_filter)).await?;
+            shards.push(
+                stream
+                    .map(move |log| Some((shard, log)))
+                    .chain(futures_util::stream::once(async { None }))
+                    .boxed(),
+            );
+        }
+        let blocks = if track_blocks {
+            Some(with_timeout(self.rpc_timeout, connections[0].subscribe_blocks()).await?)
+        } else {
+            None
+        };
+
+        // Backfill the logs missed while the subscription was down
+        let resume_from = *since;
+        if since.is_none() {
+            let block_number =
+                with_timeout(self.rpc_timeout, connections[0].get_block_number()).await?;
+            *since = Some(block_number.as_u64());
+        }
+        for (shard, logs_filter) in logs_filters.iter().enumerate() {
+            let Some(block_number) = last_seen[shard].map(|(block, _)| block).or(resume_from)
+            else {
+                continue;
+            };
+            let backfill_filter = logs_filter.clone().from_block(block_number);
+            let logs =
+                with_timeout(self.rpc_timeout, connections[0].get_logs(&backfill_filter)).await?;
+            log::info!(
+                "Backfilling {} logs from block {}",
+                logs.len(),
+                block_number
+            );
+            for log in logs {
+                self.process_log(log, &mut last_seen[shard]).await;
+            }
+        }
+        self.flush_group().await;
+
+        Ok((futures_util::stream::select_all(shards), blocks))
+    }
+
+    /// Replaces every connection with a new one, retrying with
+    /// exponential backoff.
+    ///
+    /// Returns `false` if cancelled first.
+    async fn reconnect(&self) -> bool {
+        let Some(reconnect) = &self.reconnect else {
+            return true;
+        };
+        let shards = self.shard_providers.read().unwrap().len();
+        let connections = retry_with_backoff("reconnecting to the fork", &self.cancel, || async {
+            let mut connections = Vec::new();
+            for _ in 0..=shards {
+                connections.push(Arc::new(reconnect().await?));
+            }
+            Ok(connections)
+        })
+        .await;
+        let Some(mut connections) = connections else {
+            return false;
+        };
+        *self.provider.write().unwrap() = connections.remove(0);
+        *self.shard_providers.write().unwrap() = connections;
+        log::info!("Reconnected to the fork");
+        true
+    }
+
+    /// Reloads the artifact and warns, once per change, when the event
+    /// ABI differs from the one events are decoded with.
+    ///
+    /// Events keep being decoded with the ABI loaded at startup, which
+    /// matches the deployed shadow contract.
+    fn check_abi(&self) {
+        let Some(watch) = &self.abi_watch else {
+            return;
+        };
+        let artifact = match watch.artifacts_resource.get_artifact(
+            &self.shadow_contract.file_name,
+            &self.shadow_contract.contract_name,
+        ) {
+            Ok(artifact) => artifact,
+            Err(e) => {
+                log::debug!("Error reloading artifact: {}", e);
+                return;
+            }
+        };
+
+        // The signature itself may have changed, so match the event by name
+        let events = artifact.abi.events.get(&self.event.name);
+        let current = events.and_then(|events| {
+            events
+                .iter()
+                .find(|e| e.signature() == self.event.signature())
+                .or_else(|| events.first())
+        });
+        let (schema, description) = match current {
+            Some(event) => (decode::schema_id(event), describe_event(event)),
+            None => ("none".to_owned(), "removed".to_owned()),
+        };
+
+        let mut reported = watch.reported.lock().unwrap();
+        let already_reported = reported.as_deref() == Some(schema.as_str());
+        if schema == self.schema || already_reported {
+            return;
+        }
+        log::warn!(
+            "The ABI of {} changed in the artifacts (schema {} -> {}: {}). \
+             Events are still decoded with schema {}, redeploy the shadow contract \
+             and restart to decode them with the new ABI.",
+            self.event.name,
+            self.schema,
+            schema,
+            description,
+            self.schema
+        );
+        *reported = Some(schema);
+    }
+
+    /// Lists the instances of the shadow contract, and returns the ones
+    /// that aren't listened to yet, adding them to the instances.
+    async fn new_instances(&self) -> Vec<ethers::types::H160> {
+        let Some(watch) = &self.instance_watch else {
+            return Vec::new();
+        };
+        let contracts = match watch
+            .shadow_resource
+            .list_by_name(
+                &self.shadow_contract.file_name,
+                &self.shadow_contract.contract_name,
+            )
+            .await
+        {
+            Ok(contracts) => contracts,
+            Err(e) => {
+                log::debug!("Error listing shadow contracts: {}", e);
+                return Vec::new();
+            }
+        };
+        let mut instances = self.instances.write().unwrap();
+        let mut known: HashSet<_> = instances.iter().copied().collect();
+        let mut added = Vec::new();
+        for contract in contracts {
+            if !contract.has_any_tag(&self.tags) {
+                continue;
+            }
+            match ethers::types::H160::from_str(&contract.address) {
+                Ok(address) if known.insert(address) => {
+                    instances.push(address);
+                    added.push(address);
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!(
+                    "Invalid shadow contract address {}: {}",
+                    contract.address,
+                    e
+                ),
+            }
+        }
+        added
+    }
+
+    /// Returns the contract the event belongs to, in the form `File.sol:Contract`.
+    fn contract_id(&self) -> String {
+        format!(
+            "{}:{}",
+            self.shadow_contract.file_name, self.shadow_contract.contract_name
+        )
+    }
+
+    /// Processes a log, skipping logs at or before the last processed one.
+    async fn process_log(&self, log: ethers::types::Log, last_seen: &mut Option<(u64, u64)>) {
+        let position = (
+            log.block_number.map(|n| n.as_u64()).unwrap_or_default(),
+            log.log_index.map(|i| i.as_u64()).unwrap_or_default(),
+        );
+        if matches!(last_seen, Some(last) if position <= *last) {
+            return;
+        }
+        if let Err(e) = self.on_log(log).await {
+            log::warn!("Error processing log: {}", e);
+        }
+        *last_seen = Some(position);
+    }
+
+    /// Returns the current connection to the fork.
+    fn provider(&self) -> Arc<Provider<P>> {
+        self.provider.read().unwrap().clone()
+    }
+
+    /// Returns every connection the shards subscribe on, round robin,
+    /// starting with the provider's.
+    fn connections(&self) -> Vec<Arc<Provider<P>>> {
+        let mut connections = vec![self.provider()];
+        connections.extend(self.shard_providers.read().unwrap().iter().cloned());
+        connections
+    }
+
+    /// Returns the logs filters of the shards of the given instances, at
+    /// most `max_addresses_per_filter` instances each.
+    fn build_logs_filters(&self, instances: &[ethers::types::H160]) -> Vec<Filter> {
+        shard_addresses(instances, self.max_addresses_per_filter)
+            .iter()
+            .map(|addresses| self.build_logs_filter(addresses))
+            .collect()
+    }
+
+    fn build_logs_filter(&self, addresses: &[ethers::types::H160]) -> Filter {
         Filter {
-            address: Some(ethers::types::ValueOrArray::Value(
-                ethers::types::H160::from_str(self.shadow_contract.address.as_str()).unwrap(),
-            )),
+            address: Some(match addresses {
+                [address] => ethers::types::ValueOrArray::Value(*address),
+                addresses => ethers::types::ValueOrArray::Array(addresses.to_vec()),
+            }),
             topics: [
                 Some(ethers::types::ValueOrArray::Value(Some(
                     ethers::types::H256::from_slice(self.event.selector().as_slice()),
@@ -117,20 +891,466 @@ impl<P: JsonRpcClient + PubsubClient> Events<P> {
         }
     }
 
-    fn on_log(&self, log: ethers::types::Log) -> Result<(), EventsError> {
-        let decoded = decode::decode_log(&log, &self.event)?;
-        let pretty = colored_json::to_colored_json_auto(&decoded).map_err(|e| {
-            EventsError::CustomError(format!("Error serializing decoded event to JSON: {}", e))
-        })?;
+    async fn on_log(&self, log: ethers::types::Log) -> Result<(), EventsError> {
+        let decoded = match decode::decode_log(&log, &self.event) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                self.dead_letter(&log, FailureStage::Decode, e.to_string(), None);
+                return Err(EventsError::DecoderError(e));
+            }
+        };
         let tx_hash = format!("0x{}", hex::encode(log.transaction_hash.unwrap()));
-        println!("=> Transaction: {}", tx_hash);
-        println!("{}", pretty);
+        if let Some(errors) = decode::decode_errors(&decoded) {
+            log::warn!(
+                "Partially decoded {} in {}: {}",
+                self.event.name,
+                tx_hash,
+                serde_json::Value::Object(errors.clone())
+            );
+        }
+        let block_number = log.block_number.map(|n| n.as_u64()).unwrap_or_default();
+        // The block timestamp costs an extra request, so it's only fetched
+        // for sinks and the table format
+        let block_timestamp = if self.sinks.is_empty() && self.format != OutputFormat::Table {
+            None
+        } else {
+            self.block_timestamp(block_number).await
+        };
+        let event = DecodedEvent {
+            block_number,
+            block_timestamp,
+            transaction_hash: tx_hash,
+            log_index: log.log_index.map(|i| i.as_u64()).unwrap_or_default(),
+            address: format!("{:?}", log.address),
+            contract: self.contract_id(),
+            event: self.event.name.clone(),
+            signature: self.event.signature(),
+            schema: Some(self.schema.clone()),
+            data: decoded,
+            raw: self.include_raw.then(|| RawLog::from(&log)),
+            balance_diffs: match &self.balances {
+                Some(balances) => self
+                    .balance_diffs(&log, balances)
+                    .await
+                    .unwrap_or_else(|e| {
+                        log::warn!("Error reading balances: {}", e);
+                        Vec::new()
+                    }),
+                None => Vec::new(),
+            },
+            correlation: Some(self.correlation(&log)),
+        };
+
+        // Keep events that don't match the schema out of the sinks, so
+        // a decoder regression can't corrupt downstream data
+        let violations = decode::schema::validate(&self.event, &event.data);
+        if !violations.is_empty() {
+            let diff = violations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("; ");
+            self.dead_letter(&log, FailureStage::Schema, diff.clone(), Some(&event));
+            return Err(EventsError::SchemaError(diff));
+        }
+
+        let Some(event) = self.apply_plugins(&log, event)? else {
+            return Ok(());
+        };
+
+        if !self.group_by_transaction {
+            return self.emit(vec![(log, event)]).await;
+        }
+        // Emit the previous transaction's events once a new transaction starts
+        let previous = {
+            let mut group = self.group.lock().unwrap();
+            let same_transaction = group.first().map_or(true, |(first, _)| {
+                first.transaction_hash == log.transaction_hash
+            });
+            let previous = if same_transaction {
+                Vec::new()
+            } else {
+                std::mem::take(&mut *group)
+            };
+            group.push((log, event));
+            previous
+        };
+        self.emit(previous).await
+    }
+
+    /// Runs the plugins over a decoded event, returning `None` when one
+    /// of them drops it.
+    fn apply_plugins(
+        &self,
+        log: &ethers::types::Log,
+        mut event: DecodedEvent,
+    ) -> Result<Option<DecodedEvent>, EventsError> {
+        for plugin in &self.plugins {
+            match plugin.process(&event) {
+                Ok(Some(processed)) => event = processed,
+                Ok(None) => {
+                    log::debug!(
+                        "Plugin {} dropped {} in {}",
+                        plugin.name(),
+                        event.event,
+                        event.transaction_hash
+                    );
+                    return Ok(None);
+                }
+                Err(e) => {
+                    let error = format!("Plugin {}: {}", plugin.name(), e);
+                    self.dead_letter(log, FailureStage::Plugin, error.clone(), Some(&event));
+                    return Err(EventsError::PluginError(error));
+                }
+            }
+        }
+        Ok(Some(event))
+    }
+
+    /// Emits the events grouped for the current transaction, if any.
+    async fn flush_group(&self) {
+        let group = std::mem::take(&mut *self.group.lock().unwrap());
+        if let Err(e) = self.emit(group).await {
+            log::warn!("Error processing log: {}", e);
+        }
+    }
+
+    /// Writes out the events the sinks buffered, before the listener
+    /// stops.
+    async fn flush_sinks(&self) {
+        for (sink, _) in &self.sinks {
+            if let Err(e) = sink.flush().await {
+                log::warn!("Error flushing sink: {}", e);
+            }
+        }
+    }
+
+    /// Prints the events of a transaction and writes them to the sinks.
+    ///
+    /// When events are grouped by transaction, this is every event of
+    /// the transaction, otherwise a single event.
+    async fn emit(
+        &self,
+        group: Vec<(ethers::types::Log, DecodedEvent)>,
+    ) -> Result<(), EventsError> {
+        let Some((first_log, first)) = group.first() else {
+            return Ok(());
+        };
+
+        // Print the events, as table rows or with their template if there is one
+        let printed = group
+            .iter()
+            .map(|(_, event)| self.encoder.encode_event(&self.event, event))
+            .collect::<Vec<_>>();
+        if self.format == OutputFormat::Table {
+            for event in &printed {
+                println!("{}", render::render_row(event, &self.renderer));
+            }
+        } else {
+            if self.group_by_transaction {
+                println!(
+                    "=> Transaction: {} ({} events)",
+                    first.transaction_hash,
+                    group.len()
+                );
+            } else {
+                println!("=> Transaction: {}", first.transaction_hash);
+            }
+            for event in &printed {
+                if self.group_by_transaction {
+                    let ordinal = event.correlation.as_ref().map_or(0, |c| c.ordinal);
+                    println!("-> #{} {} (log {})", ordinal, event.event, event.log_index);
+                }
+                match self.renderer.render(event) {
+                    Some(message) => println!("{}", message),
+                    None => {
+                        let pretty =
+                            colored_json::to_colored_json_auto(&event.data).map_err(|e| {
+                                EventsError::CustomError(format!(
+                                    "Error serializing decoded event to JSON: {}",
+                                    e
+                                ))
+                            })?;
+                        println!("{}", pretty);
+                    }
+                }
+                for diff in &event.balance_diffs {
+                    println!(
+                        "   ~ {} {}: {} -> {} ({})",
+                        diff.address, diff.token, diff.before, diff.after, diff.change
+                    );
+                }
+            }
+        }
+
+        // Print the other logs of the transaction
+        if let Some(context) = &self.context {
+            if let Err(e) = self.print_context(first_log, context).await {
+                log::warn!("Error decoding transaction logs: {}", e);
+            }
+        }
+
+        // Join the events with the other logs of the transaction
+        let joined = match &self.joins {
+            Some(joins) => self
+                .join(first_log, &group, joins)
+                .await
+                .unwrap_or_else(|e| {
+                    log::warn!("Error joining transaction logs: {}", e);
+                    Vec::new()
+                }),
+            None => Vec::new(),
+        };
+        for event in &joined {
+            if self.format == OutputFormat::Table {
+                println!("{}", render::render_row(event, &self.renderer));
+            } else {
+                println!("=> Joined: {}", event.event);
+                println!("{}", serde_json::to_string(&event.data).unwrap_or_default());
+            }
+        }
+
+        // Write the events to the sinks, in order, followed by the joined records
+        let events = group.iter().map(|(log, event)| (log, event));
+        let joined = joined.iter().map(|event| (first_log, event));
+        for (log, event) in events.chain(joined) {
+            for (sink, encoder) in &self.sinks {
+                let encoded = encoder
+                    .as_ref()
+                    .unwrap_or(&self.encoder)
+                    .encode_event(&self.event, event);
+                if let Err(e) = sink.write(&encoded).await {
+                    log::warn!("Error writing event to sink: {}", e);
+                    self.dead_letter(log, FailureStage::Sink, e.to_string(), Some(event));
+                }
+            }
+        }
         Ok(())
     }
+
+    /// Returns the composite records of the join rules matching the
+    /// events and the other logs of their transaction.
+    async fn join(
+        &self,
+        log: &ethers::types::Log,
+        group: &[(ethers::types::Log, DecodedEvent)],
+        joins: &Joins,
+    ) -> Result<Vec<DecodedEvent>, EventsError> {
+        let Some(tx_hash) = log.transaction_hash else {
+            return Ok(Vec::new());
+        };
+        let receipt = with_timeout(
+            self.rpc_timeout,
+            self.provider().get_transaction_receipt(tx_hash),
+        )
+        .await?
+        .ok_or_else(|| EventsError::CustomError("Transaction receipt not found".to_owned()))?;
+
+        let mut joined = Vec::new();
+        for other in &receipt.logs {
+            let Some(decoded) = joins.registry.decode(other) else {
+                continue;
+            };
+            for (log, event) in group {
+                if other.log_index == log.log_index {
+                    continue;
+                }
+                joined.extend(
+                    joins
+                        .rules
+                        .iter()
+                        .filter_map(|rule| rule.join(event, other, &decoded)),
+                );
+            }
+        }
+        Ok(joined)
+    }
+
+    /// Returns the correlation of a log's event, counting the events
+    /// already decoded from its transaction.
+    ///
+    /// Logs arrive in order, so the events of a transaction are consecutive.
+    fn correlation(&self, log: &ethers::types::Log) -> Correlation {
+        correlate(&mut self.last_transaction.lock().unwrap(), log)
+    }
+
+    /// Adds a log that failed to be processed to the dead-letter queue, if set.
+    fn dead_letter(
+        &self,
+        log: &ethers::types::Log,
+        stage: FailureStage,
+        error: String,
+        event: Option<&DecodedEvent>,
+    ) {
+        let Some(dead_letters) = &self.dead_letters else {
+            return;
+        };
+        let letter = DeadLetter {
+            stage,
+            error,
+            failed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            contract: self.contract_id(),
+            signature: self.event.signature(),
+            transaction_hash: log
+                .transaction_hash
+                .map(|hash| format!("{:?}", hash))
+                .unwrap_or_default(),
+            raw: RawLog::from(log),
+            event: event.cloned(),
+        };
+        if let Err(e) = dead_letters.push(&letter) {
+            log::error!("Error writing dead letter: {}", e);
+        }
+    }
+
+    /// Prints every other log of the log's transaction, once per transaction.
+    ///
+    /// Logs are decoded with the known ABIs, falling back to the
+    /// signature database and decoding them without parameter names.
+    async fn print_context(
+        &self,
+        log: &ethers::types::Log,
+        context: &LogContext,
+    ) -> Result<(), EventsError> {
+        let Some(tx_hash) = log.transaction_hash else {
+            return Ok(());
+        };
+        {
+            let mut context_tx = self.context_tx.lock().unwrap();
+            if *context_tx == Some(tx_hash) {
+                return Ok(());
+            }
+            *context_tx = Some(tx_hash);
+        }
+        let receipt = with_timeout(
+            self.rpc_timeout,
+            self.provider().get_transaction_receipt(tx_hash),
+        )
+        .await?
+        .ok_or_else(|| EventsError::CustomError("Transaction receipt not found".to_owned()))?;
+
+        let selector = ethers::types::H256::from_slice(self.event.selector().as_slice());
+        for other in receipt.logs {
+            // The shadow contract's events are printed by the main stream
+            if other.address == log.address && other.topics.first() == Some(&selector) {
+                continue;
+            }
+            let index = other.log_index.map(|i| i.as_u64()).unwrap_or_default();
+            if let Some(decoded) = context.registry.decode(&other) {
+                println!(
+                    "   -> [{}] {:?} {}.{}",
+                    index, other.address, decoded.contract, decoded.signature
+                );
+                println!(
+                    "{}",
+                    serde_json::to_string(&decoded.data).unwrap_or_default()
+                );
+                continue;
+            }
+            let signatures = match (&context.signatures, other.topics.first()) {
+                (Some(signatures), Some(topic0)) => signatures
+                    .lookup_event(&format!("{:?}", topic0))
+                    .await
+                    .unwrap_or_default(),
+                _ => vec![],
+            };
+
+            // Several signatures can share a topic0, so use the first
+            // one the log fits
+            let decoded = signatures.iter().find_map(|signature| {
+                decode::decode_unnamed(&other, signature).map(|data| (signature, data))
+            });
+            if let Some((signature, data)) = decoded {
+                println!(
+                    "   -> [{}] {:?} {} (unknown ABI)",
+                    index, other.address, signature
+                );
+                println!("{}", serde_json::to_string(&data).unwrap_or_default());
+                continue;
+            }
+            println!(
+                "   -> [{}] {:?} {} (unknown ABI)",
+                index,
+                other.address,
+                signatures
+                    .first()
+                    .map(String::as_str)
+                    .unwrap_or("unknown event")
+            );
+            println!(
+                "{}",
+                serde_json::json!({ "topics": other.topics, "data": other.data })
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the balance changes around the log's transaction.
+    async fn balance_diffs(
+        &self,
+        log: &ethers::types::Log,
+        balances: &BalanceTracker,
+    ) -> Result<Vec<BalanceDiff>, EventsError> {
+        let Some(tx_hash) = log.transaction_hash else {
+            return Ok(Vec::new());
+        };
+        let receipt = with_timeout(
+            self.rpc_timeout,
+            self.provider().get_transaction_receipt(tx_hash),
+        )
+        .await?
+        .ok_or_else(|| EventsError::CustomError("Transaction receipt not found".to_owned()))?;
+        Ok(with_timeout(
+            self.rpc_timeout,
+            balances.diffs(&*self.provider(), &receipt),
+        )
+        .await?)
+    }
+
+    /// Returns the timestamp of a block, caching the most recent block.
+    async fn block_timestamp(&self, block_number: u64) -> Option<u64> {
+        if let Some((number, timestamp)) = *self.last_block.lock().unwrap() {
+            if number == block_number {
+                return Some(timestamp);
+            }
+        }
+        let block = with_timeout(self.rpc_timeout, self.provider().get_block(block_number))
+            .await
+            .ok()
+            .flatten()?;
+        let timestamp = block.timestamp.as_u64();
+        *self.last_block.lock().unwrap() = Some((block_number, timestamp));
+        Some(timestamp)
+    }
+}
+
+/// Returns the correlation of a log's event, given the last transaction
+/// an event was decoded from and that event's ordinal.
+fn correlate(
+    last: &mut Option<(ethers::types::H256, u64)>,
+    log: &ethers::types::Log,
+) -> Correlation {
+    let tx_hash = log.transaction_hash.unwrap_or_default();
+    let ordinal = match *last {
+        Some((hash, ordinal)) if hash == tx_hash => ordinal + 1,
+        _ => 0,
+    };
+    *last = Some((tx_hash, ordinal));
+    Correlation {
+        transaction_hash: format!("{:?}", tx_hash),
+        transaction_index: log
+            .transaction_index
+            .map(|i| i.as_u64())
+            .unwrap_or_default(),
+        ordinal,
+    }
 }
 
 // Get the event from the contract's ABI
-fn get_event(
+pub(crate) fn get_event(
     event_signature: &str,
     contract_object: &alloy_json_abi::ContractObject,
 ) -> Option<Event> {
@@ -142,3 +1362,60 @@ fn get_event(
         .find(|e| e.signature() == event_signature)
         .cloned()
 }
+
+/// Splits the addresses into shards of at most `max` addresses, or a
+/// single shard if unlimited.
+fn shard_addresses(
+    addresses: &[ethers::types::H160],
+    max: Option<usize>,
+) -> Vec<Vec<ethers::types::H160>> {
+    match max {
+        Some(max) if addresses.len() > max => {
+            addresses.chunks(max.max(1)).map(<[_]>::to_vec).collect()
+        }
+        _ => vec![addresses.to_vec()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{H160, H256};
+
+    use super::*;
+
+    #[test]
+    fn can_shard_addresses() {
+        let addresses = (1..=5).map(H160::repeat_byte).collect::<Vec<_>>();
+        assert_eq!(shard_addresses(&addresses, None), vec![addresses.clone()]);
+        assert_eq!(
+            shard_addresses(&addresses, Some(2)),
+            vec![
+                addresses[0..2].to_vec(),
+                addresses[2..4].to_vec(),
+                addresses[4..].to_vec()
+            ]
+        );
+        assert_eq!(shard_addresses(&addresses, Some(5)), vec![addresses]);
+    }
+
+    #[test]
+    fn can_correlate_events_of_a_transaction() {
+        let log = |tx_hash: u8, tx_index: u64| ethers::types::Log {
+            transaction_hash: Some(H256::repeat_byte(tx_hash)),
+            transaction_index: Some(tx_index.into()),
+            ..Default::default()
+        };
+        let mut last = None;
+        let correlations = [log(1, 4), log(1, 4), log(1, 4), log(2, 7), log(1, 9)]
+            .iter()
+            .map(|log| correlate(&mut last, log))
+            .map(|c| (c.transaction_index, c.ordinal))
+            .collect::<Vec<_>>();
+        // The ordinal counts the events of each transaction from 0
+        assert_eq!(correlations, vec![(4, 0), (4, 1), (4, 2), (7, 0), (9, 0)]);
+        assert_eq!(
+            correlate(&mut last, &log(3, 0)).transaction_hash,
+            format!("{:?}", H256::repeat_byte(3))
+        );
+    }
+}
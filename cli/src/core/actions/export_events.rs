@@ -0,0 +1,548 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use alloy_json_abi::Event;
+use ethers::{
+    providers::{JsonRpcClient, Middleware, Provider, ProviderError},
+    types::{Filter, Log, H160, H256},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    core::resources::{
+        export::ExportWriter,
+        sink::{Correlation, DecodedEvent},
+    },
+    decode,
+    providers::with_timeout,
+};
+
+/// How many times a single-block `eth_getLogs` query is retried before
+/// the export fails
+const MAX_RETRIES: u32 = 5;
+
+/// The buffered events are flushed once there are this many
+pub const FLUSH_EVENTS: usize = 100_000;
+
+/// The buffered events are flushed at least this often
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Exports the historical events of a shadow contract over a block
+/// range, paginating `eth_getLogs`.
+///
+/// Queries the provider rejects, e.g. because they return too many
+/// logs, are split in half and retried, and the chunk size grows back
+/// after successful queries. Progress is saved to a checkpoint file
+/// every time the writer is flushed, so an interrupted export resumes
+/// where it left off.
+///
+/// This action is used by the `export-events` command.
+pub struct ExportEvents<P: JsonRpcClient> {
+    /// The Ethereum provider
+    pub provider: Provider<P>,
+
+    /// The addresses of the shadow contract
+    pub addresses: Vec<H160>,
+
+    /// The contract the event belongs to, in the form `File.sol:Contract`
+    pub contract: String,
+
+    /// The event to export
+    pub event: Event,
+
+    /// The name of the format the events are written in, saved in the
+    /// checkpoint
+    pub format: String,
+
+    /// The first block to export
+    pub from_block: u64,
+
+    /// The last block to export. Defaults to the latest block.
+    pub to_block: Option<u64>,
+
+    /// The most blocks an `eth_getLogs` query covers
+    pub chunk_size: u64,
+
+    /// The most RPC requests sent per second, if limited
+    pub max_requests_per_second: Option<f64>,
+
+    /// Whether to fetch the timestamp of each block with events
+    pub timestamps: bool,
+
+    /// The file progress is saved to, if any
+    pub checkpoint: Option<PathBuf>,
+
+    /// Where the events are written
+    pub writer: Box<dyn ExportWriter>,
+
+    /// How long an RPC call may take before it is failed
+    pub rpc_timeout: Duration,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum ExportEventsError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Provider error
+    #[error("ProviderError: {0}")]
+    ProviderError(#[from] ProviderError),
+}
+
+/// The progress of an export, saved so it can be resumed.
+///
+/// Besides the progress, the checkpoint records what is exported, so an
+/// export with other options isn't resumed from it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    /// The contract the event belongs to, in the form `File.sol:Contract`
+    pub contract: String,
+    /// The signature of the exported event
+    pub signature: String,
+    /// The name of the format the events are written in
+    #[serde(default)]
+    pub format: String,
+    /// The addresses the events are exported from
+    #[serde(default)]
+    pub addresses: Vec<H160>,
+    /// The first block of the export
+    #[serde(default)]
+    pub from_block: u64,
+    /// The last block of the export
+    pub to_block: u64,
+    /// The first block that wasn't exported yet
+    pub next_block: u64,
+    /// The number of events exported so far
+    pub events: u64,
+    /// The position the writer is reopened at
+    pub position: u64,
+}
+
+impl Checkpoint {
+    /// Loads a checkpoint, if the file exists.
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>, String> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading checkpoint {}: {}", path.display(), e))?;
+        serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| format!("Invalid checkpoint {}: {}", path.display(), e))
+    }
+
+    /// Saves the checkpoint, replacing the file atomically so an
+    /// interruption can't leave a partial checkpoint behind.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let tmp = PathBuf::from(format!("{}.tmp", path.display()));
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&tmp, json)
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| format!("Error writing checkpoint {}: {}", path.display(), e))
+    }
+
+    /// Checks that the checkpoint belongs to the same export: the same
+    /// event of the same addresses, written in the same format from the
+    /// same block.
+    pub fn matches(
+        &self,
+        contract: &str,
+        signature: &str,
+        format: &str,
+        addresses: &[H160],
+        from_block: u64,
+    ) -> bool {
+        let mut saved = self.addresses.clone();
+        saved.sort();
+        let mut addresses = addresses.to_vec();
+        addresses.sort();
+        self.contract == contract
+            && self.signature == signature
+            && self.format == format
+            && saved == addresses
+            && self.from_block == from_block
+    }
+}
+
+/// The outcome of an export
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    /// The first block exported by this run
+    pub from_block: u64,
+    /// The last block exported
+    pub to_block: u64,
+    /// The number of events exported, including previous runs
+    pub events: u64,
+}
+
+/// Spaces out RPC requests to stay under a maximum rate.
+struct RateLimiter {
+    interval: Option<Duration>,
+    last: Option<Instant>,
+}
+
+impl RateLimiter {
+    fn new(max_requests_per_second: Option<f64>) -> Self {
+        Self {
+            interval: max_requests_per_second
+                .filter(|rate| *rate > 0.0)
+                .map(|rate| Duration::from_secs_f64(1.0 / rate)),
+            last: None,
+        }
+    }
+
+    /// Waits until the next request may be sent.
+    async fn wait(&mut self) {
+        if let (Some(interval), Some(last)) = (self.interval, self.last) {
+            let elapsed = last.elapsed();
+            if elapsed < interval {
+                tokio::time::sleep(interval - elapsed).await;
+            }
+        }
+        self.last = Some(Instant::now());
+    }
+}
+
+impl<P: JsonRpcClient> ExportEvents<P> {
+    /// Exports the events, starting from the checkpoint if there is one.
+    pub async fn run(
+        &mut self,
+        resume: Option<Checkpoint>,
+    ) -> Result<ExportReport, ExportEventsError> {
+        let mut limiter = RateLimiter::new(self.max_requests_per_second);
+        let to_block = match (&resume, self.to_block) {
+            (Some(checkpoint), _) => checkpoint.to_block,
+            (None, Some(to_block)) => to_block,
+            (None, None) => {
+                limiter.wait().await;
+                with_timeout(self.rpc_timeout, self.provider.get_block_number())
+                    .await?
+                    .as_u64()
+            }
+        };
+        let mut checkpoint = resume.unwrap_or_else(|| Checkpoint {
+            contract: self.contract.clone(),
+            signature: self.event.signature(),
+            format: self.format.clone(),
+            addresses: self.addresses.clone(),
+            from_block: self.from_block,
+            to_block,
+            next_block: self.from_block,
+            events: 0,
+            position: 0,
+        });
+        let start_block = checkpoint.next_block;
+
+        let max_chunk = self.chunk_size.max(1);
+        let mut chunk = max_chunk;
+        let mut retries = 0;
+        let mut from_block = checkpoint.next_block;
+        let mut buffered = 0;
+        let mut exported = checkpoint.events;
+        let mut last_flush = Instant::now();
+        while from_block <= to_block {
+            let chunk_end = to_block.min(from_block.saturating_add(chunk - 1));
+            let filter = Filter::new()
+                .address(self.addresses.clone())
+                .topic0(H256::from_slice(self.event.selector().as_slice()))
+                .from_block(from_block)
+                .to_block(chunk_end);
+            limiter.wait().await;
+            let logs = match with_timeout(self.rpc_timeout, self.provider.get_logs(&filter)).await {
+                Ok(logs) => logs,
+                Err(e) if chunk > 1 => {
+                    chunk = shrink_chunk(chunk);
+                    log::debug!(
+                        "eth_getLogs failed for blocks {}..={}, retrying with {} blocks: {}",
+                        from_block,
+                        chunk_end,
+                        chunk,
+                        e
+                    );
+                    continue;
+                }
+                Err(e) if retries < MAX_RETRIES => {
+                    retries += 1;
+                    log::warn!(
+                        "eth_getLogs failed for block {}, retrying: {}",
+                        from_block,
+                        e
+                    );
+                    tokio::time::sleep(Duration::from_secs(1 << retries)).await;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            retries = 0;
+
+            let events = self.decode_logs(&logs, &mut limiter).await?;
+            self.writer
+                .write(from_block, chunk_end, &events)
+                .map_err(|e| {
+                    ExportEventsError::CustomError(format!("Error writing events: {}", e))
+                })?;
+            buffered += events.len();
+            exported += events.len() as u64;
+            from_block = chunk_end + 1;
+            chunk = grow_chunk(chunk, max_chunk);
+            eprint!(
+                "\rExported blocks {}..={} of {} ({:.1}%), {} events",
+                start_block,
+                chunk_end,
+                to_block,
+                progress(start_block, chunk_end, to_block),
+                exported
+            );
+
+            // Save progress once enough events or time have accumulated
+            if buffered >= FLUSH_EVENTS
+                || last_flush.elapsed() >= FLUSH_INTERVAL
+                || from_block > to_block
+            {
+                checkpoint.position = self.writer.flush().map_err(|e| {
+                    ExportEventsError::CustomError(format!("Error flushing events: {}", e))
+                })?;
+                checkpoint.next_block = from_block;
+                checkpoint.events = exported;
+                if let Some(path) = &self.checkpoint {
+                    checkpoint
+                        .save(path)
+                        .map_err(ExportEventsError::CustomError)?;
+                }
+                buffered = 0;
+                last_flush = Instant::now();
+            }
+        }
+        eprintln!();
+
+        Ok(ExportReport {
+            from_block: start_block,
+            to_block,
+            events: exported,
+        })
+    }
+
+    /// Decodes the logs of a chunk, fetching the timestamps of their blocks.
+    async fn decode_logs(
+        &self,
+        logs: &[Log],
+        limiter: &mut RateLimiter,
+    ) -> Result<Vec<DecodedEvent>, ExportEventsError> {
+        let mut timestamps = HashMap::new();
+        let mut ordinals: HashMap<Option<H256>, u64> = HashMap::new();
+        let mut events = Vec::with_capacity(logs.len());
+        for log in logs {
+            let block_number = log.block_number.map(|n| n.as_u64()).unwrap_or_default();
+            let data = decode::decode_log(log, &self.event).map_err(|e| {
+                ExportEventsError::CustomError(format!(
+                    "Error decoding log {:?} in block {}: {}",
+                    log.transaction_hash, block_number, e
+                ))
+            })?;
+            let block_timestamp = match (self.timestamps, timestamps.get(&block_number)) {
+                (false, _) => None,
+                (true, Some(timestamp)) => Some(*timestamp),
+                (true, None) => {
+                    limiter.wait().await;
+                    let timestamp =
+                        with_timeout(self.rpc_timeout, self.provider.get_block(block_number))
+                            .await?
+                            .map(|block| block.timestamp.as_u64());
+                    if let Some(timestamp) = timestamp {
+                        timestamps.insert(block_number, timestamp);
+                    }
+                    timestamp
+                }
+            };
+            let ordinal = ordinals.entry(log.transaction_hash).or_default();
+            let transaction_hash = log
+                .transaction_hash
+                .map(|hash| format!("{:?}", hash))
+                .unwrap_or_default();
+            events.push(DecodedEvent {
+                block_number,
+                block_timestamp,
+                transaction_hash: transaction_hash.clone(),
+                log_index: log.log_index.map(|i| i.as_u64()).unwrap_or_default(),
+                address: format!("{:?}", log.address),
+                contract: self.contract.clone(),
+                event: self.event.name.clone(),
+                signature: self.event.signature(),
+                schema: Some(decode::schema_id(&self.event)),
+                data,
+                raw: None,
+                correlation: Some(Correlation {
+                    transaction_hash,
+                    transaction_index: log
+                        .transaction_index
+                        .map(|i| i.as_u64())
+                        .unwrap_or_default(),
+                    ordinal: *ordinal,
+                }),
+                ..Default::default()
+            });
+            *ordinal += 1;
+        }
+        Ok(events)
+    }
+}
+
+/// Halves the chunk size after a failed query.
+fn shrink_chunk(chunk: u64) -> u64 {
+    (chunk / 2).max(1)
+}
+
+/// Doubles the chunk size after a successful query, up to the maximum.
+fn grow_chunk(chunk: u64, max_chunk: u64) -> u64 {
+    chunk.saturating_mul(2).min(max_chunk)
+}
+
+/// Returns the percentage of the export done, from its first block.
+fn progress(start_block: u64, current: u64, to_block: u64) -> f64 {
+    if to_block <= start_block {
+        return 100.0;
+    }
+    (current.saturating_sub(start_block) + 1) as f64 * 100.0 / (to_block - start_block + 1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use ethers::providers::{JsonRpcError, MockProvider, MockResponse};
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct FakeWriter {
+        ranges: Arc<Mutex<Vec<(u64, u64)>>>,
+        flushes: Arc<Mutex<u64>>,
+    }
+
+    impl ExportWriter for FakeWriter {
+        fn write(
+            &mut self,
+            from_block: u64,
+            to_block: u64,
+            _events: &[DecodedEvent],
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            self.ranges.lock().unwrap().push((from_block, to_block));
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<u64, Box<dyn std::error::Error>> {
+            let mut flushes = self.flushes.lock().unwrap();
+            *flushes += 1;
+            Ok(*flushes)
+        }
+    }
+
+    fn transfer() -> Event {
+        serde_json::from_str(
+            r#"{
+                "type": "event",
+                "name": "Transfer",
+                "inputs": [
+                    { "name": "from", "type": "address", "internalType": "address", "indexed": true },
+                    { "name": "to", "type": "address", "internalType": "address", "indexed": true },
+                    { "name": "value", "type": "uint256", "internalType": "uint256", "indexed": false }
+                ],
+                "anonymous": false
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn can_size_chunks() {
+        assert_eq!(shrink_chunk(10_000), 5_000);
+        assert_eq!(shrink_chunk(1), 1);
+        assert_eq!(grow_chunk(5_000, 10_000), 10_000);
+        assert_eq!(grow_chunk(4_000, 10_000), 8_000);
+        assert_eq!(progress(100, 149, 199), 50.0);
+        assert_eq!(progress(100, 100, 100), 100.0);
+    }
+
+    #[test]
+    fn can_save_and_load_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.checkpoint.json");
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+
+        let checkpoint = Checkpoint {
+            contract: "Token.sol:Token".to_owned(),
+            signature: "Transfer(address,address,uint256)".to_owned(),
+            format: "jsonl".to_owned(),
+            addresses: vec![H160::repeat_byte(1), H160::repeat_byte(2)],
+            from_block: 100,
+            to_block: 200,
+            next_block: 150,
+            events: 42,
+            position: 1024,
+        };
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap().unwrap();
+        assert_eq!(loaded, checkpoint);
+        let transfer = "Transfer(address,address,uint256)";
+        let addresses = [H160::repeat_byte(2), H160::repeat_byte(1)];
+        assert!(loaded.matches("Token.sol:Token", transfer, "jsonl", &addresses, 100));
+        assert!(!loaded.matches(
+            "Token.sol:Token",
+            "Approval(address,address,uint256)",
+            "jsonl",
+            &addresses,
+            100
+        ));
+        assert!(!loaded.matches("Token.sol:Token", transfer, "parquet", &addresses, 100));
+        assert!(!loaded.matches("Token.sol:Token", transfer, "jsonl", &addresses[..1], 100));
+        assert!(!loaded.matches("Token.sol:Token", transfer, "jsonl", &addresses, 0));
+    }
+
+    #[tokio::test]
+    async fn splits_rejected_queries() {
+        let mock = MockProvider::new();
+        // Responses are popped in reverse order
+        mock.push::<Vec<Log>, _>(Vec::new()).unwrap();
+        mock.push::<Vec<Log>, _>(Vec::new()).unwrap();
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32005,
+            message: "query returned more than 10000 results".to_owned(),
+            data: Some(json!(null)),
+        }));
+
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint = dir.path().join("export.checkpoint.json");
+        let writer = FakeWriter::default();
+        let mut export = ExportEvents {
+            provider: Provider::new(mock),
+            addresses: vec![H160::zero()],
+            contract: "Token.sol:Token".to_owned(),
+            event: transfer(),
+            format: "jsonl".to_owned(),
+            from_block: 0,
+            to_block: Some(9),
+            chunk_size: 10,
+            max_requests_per_second: None,
+            timestamps: false,
+            checkpoint: Some(checkpoint.clone()),
+            writer: Box::new(writer.clone()),
+            rpc_timeout: Duration::from_secs(1),
+        };
+        let report = export.run(None).await.unwrap();
+        assert_eq!(*writer.ranges.lock().unwrap(), vec![(0, 4), (5, 9)]);
+        assert_eq!(report.to_block, 9);
+        let saved = Checkpoint::load(&checkpoint).unwrap().unwrap();
+        assert_eq!(saved.next_block, 10);
+        assert_eq!(saved.position, *writer.flushes.lock().unwrap());
+    }
+}
@@ -3,18 +3,76 @@ use anvil::{
     eth::{error::BlockchainError, EthApi},
     NodeHandle,
 };
+use anvil_core::eth::transaction::EthTransactionRequest;
 use clap::Parser;
 use ethers::{
     prelude::{providers::StreamExt, Provider},
     providers::{JsonRpcClient, Middleware, ProviderError, PubsubClient},
-    types::{Transaction, TransactionReceipt},
+    signers::Signer,
+    types::{transaction::eip2718::TypedTransaction, Filter, Transaction, TransactionReceipt},
 };
-use tokio::task::JoinSet;
+use futures_util::FutureExt;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
-use std::{collections::HashMap, str::FromStr, sync::Arc};
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet, VecDeque},
+    net::SocketAddr,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Instant,
+};
 use thiserror::Error;
 
-use crate::core::resources::shadow::{ShadowContract, ShadowResource};
+use crate::{
+    core::resources::{
+        artifacts::ArtifactsResource,
+        shadow::{ShadowContract, ShadowResource},
+        sink::EventSink,
+    },
+    providers::{retry_with_backoff, with_timeout},
+    server::{auth::ApiAuth, health::Health},
+};
+
+mod bench;
+mod compare;
+pub mod control;
+mod determinism;
+mod factory;
+mod options;
+mod pins;
+mod post_deploy;
+mod prefilter;
+mod prestage;
+mod receipts;
+mod report;
+mod rewrite;
+mod scenario;
+mod summary;
+mod trace;
+mod views;
+
+pub use bench::BenchReport;
+pub use compare::{CompareReport, EventDiff};
+use control::{ControlMessage, ControlReply, ControlRequest};
+pub use determinism::{Divergence, DivergenceKind};
+pub use factory::{ChildCode, TrackedFactory};
+pub use options::{
+    CatchUpStrategy, FeeMode, ReplayMode, ReplayOptions, DEFAULT_GAS_HEADROOM,
+    DEFAULT_RECEIPT_RETRIES,
+};
+pub use pins::PinnedContract;
+pub use post_deploy::{PostDeploy, PostDeployTransaction, DEFAULT_POST_DEPLOY_GAS};
+pub use report::{BlockReplayReport, FailedTransaction, ReplayMetrics, ShadowLog};
+pub use rewrite::{ReplayCall, ReplayHook, SelectorRewrite, TransactionRewrite};
+pub use scenario::{EventTrigger, Scenario, ScenarioRunner, SyntheticTransaction};
+pub use summary::BlockSummary;
+pub use views::{PolledView, ViewSchedule};
 
 /// Starts a local shadow fork using Anvil.
 ///
@@ -38,13 +96,78 @@ pub struct Fork<P: JsonRpcClient + 'static> {
     pub provider: Arc<Provider<P>>,
 
     // The shadow contracts to use on the fork
-    pub shadow_contracts: Vec<ShadowContract>,
+    pub shadow_contracts: RwLock<Vec<ShadowContract>>,
+
+    /// The Shadow resource, used to reload shadow contracts
+    pub shadow_resource: Box<dyn ShadowResource + Send + Sync>,
 
     /// The HTTP RPC URL to use for the anvil fork
     pub http_rpc_url: String,
 
-    /// Whether to replay all transactions from mainnet
-    pub all_txs: bool,
+    /// Options controlling how blocks are replayed
+    pub options: ReplayOptions,
+
+    /// Cumulative replay statistics
+    pub metrics: Arc<Mutex<ReplayMetrics>>,
+
+    /// The ABIs of the shadow contracts, used to decode call traces
+    pub abis: RwLock<HashMap<ethers::types::H160, alloy_json_abi::JsonAbi>>,
+
+    /// The shadowed factories whose children are shadowed as they are created
+    pub factories: Vec<TrackedFactory>,
+
+    /// The address to serve the `shadow_*` control RPC on, if any
+    pub control_addr: Option<SocketAddr>,
+
+    /// The tokens the control RPC requires, if any
+    pub control_auth: ApiAuth,
+
+    /// Stops the block replay when cancelled
+    pub cancel: CancellationToken,
+
+    /// The sinks each block's summary is written to
+    pub summary_sinks: Vec<Box<dyn EventSink>>,
+
+    /// Change the transactions replayed from their impersonated senders
+    pub replay_hooks: Vec<Box<dyn ReplayHook>>,
+
+    /// Synthetic transactions to mix into the replayed transactions
+    pub scenario: Option<ScenarioRunner>,
+
+    /// Transactions sent from a local signer once the shadow contracts
+    /// are deployed, if any
+    pub post_deploy: Option<PostDeploy>,
+
+    /// Whether the post-deploy transactions were sent
+    post_deploy_sent: AtomicBool,
+
+    /// Contracts whose storage is refreshed from mainnet after every block
+    pub pins: Vec<PinnedContract>,
+
+    /// View functions called on the fork after replayed blocks, on a schedule
+    pub views: Vec<PolledView>,
+
+    /// The sinks the results of the view functions are written to
+    pub view_sinks: Vec<Box<dyn EventSink>>,
+
+    /// Stops the block replay after this many blocks, if set
+    pub max_blocks: Option<u64>,
+
+    /// Reports the replay's progress to health checks, if set
+    pub health: Option<Health>,
+}
+
+/// The reason a transaction is, or is not, replayed on the fork.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReplayDecision {
+    /// The transaction should be replayed
+    Replay,
+    /// The transaction does not touch a shadowed contract
+    SkipNotShadowed,
+    /// The transaction failed on mainnet
+    SkipFailedOnMainnet,
+    /// The transaction's mainnet receipt never became available
+    SkipMissingReceipt,
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -62,11 +185,11 @@ pub enum ForkError {
 }
 
 impl<P: JsonRpcClient + PubsubClient> Fork<P> {
-    pub async fn new<S: ShadowResource>(
+    pub async fn new<S: ShadowResource + Send + Sync + 'static>(
         provider: Provider<P>,
         shadow_resource: S,
         http_rpc_url: String,
-        all_txs: bool,
+        options: ReplayOptions,
     ) -> Result<Self, ForkError> {
         let provider = Arc::new(provider);
         let shadow_contracts = shadow_resource
@@ -76,34 +199,482 @@ impl<P: JsonRpcClient + PubsubClient> Fork<P> {
 
         Ok(Self {
             provider,
-            shadow_contracts,
+            shadow_contracts: RwLock::new(shadow_contracts),
+            shadow_resource: Box::new(shadow_resource),
             http_rpc_url,
-            all_txs,
+            options,
+            metrics: Arc::new(Mutex::new(ReplayMetrics::default())),
+            abis: RwLock::new(HashMap::new()),
+            factories: Vec::new(),
+            control_addr: None,
+            control_auth: ApiAuth::default(),
+            cancel: CancellationToken::new(),
+            summary_sinks: Vec::new(),
+            replay_hooks: Vec::new(),
+            scenario: None,
+            post_deploy: None,
+            post_deploy_sent: AtomicBool::new(false),
+            pins: Vec::new(),
+            views: Vec::new(),
+            view_sinks: Vec::new(),
+            max_blocks: None,
+            health: None,
         })
     }
 
+    /// Only keeps the shadow contracts with any of the given tags.
+    pub fn retain_tagged(&mut self, tags: &[String]) {
+        self.shadow_contracts
+            .get_mut()
+            .unwrap()
+            .retain(|c| c.has_any_tag(tags));
+    }
+
+    /// Loads the ABIs of the shadow contracts from the artifacts store,
+    /// so call traces to shadow contracts can be decoded.
+    pub fn load_abis<A: ArtifactsResource>(
+        &mut self,
+        artifacts_resource: &A,
+    ) -> Result<(), ForkError> {
+        for shadow_contract in self.shadow_contracts.read().unwrap().iter() {
+            let artifact = artifacts_resource
+                .get_artifact(&shadow_contract.file_name, &shadow_contract.contract_name)
+                .map_err(|e| ForkError::CustomError(format!("Error getting artifact: {}", e)))?;
+            self.abis.get_mut().unwrap().insert(
+                ethers::types::H160::from_str(shadow_contract.address.as_str()).unwrap(),
+                artifact.abi,
+            );
+        }
+        Ok(())
+    }
+
     pub async fn run(&self) -> Result<(), ForkError> {
         // Start the anvil fork
-        let (api, _) = self.start_anvil().await?;
+        let (api, _) = self.start_anvil(None).await?;
 
-        // Override the shadow contracts
+        // Override the shadow contracts, which the post-deploy
+        // transactions initialize in the first replayed block
         self.override_contracts(&api).await?;
 
+        // Start the control server
+        let (control_sender, mut control_receiver) = mpsc::channel::<ControlMessage>(16);
+        if let Some(addr) = self.control_addr {
+            let auth = self.control_auth.clone();
+            tokio::spawn(async move {
+                if let Err(e) = control::serve(addr, control_sender, auth).await {
+                    log::error!("Control server stopped: {}", e);
+                }
+            });
+        }
+
         // Start the block replay
-        let mut stream = self.provider.subscribe_blocks().await?;
-        while let Some(block) = stream.next().await {
-            let result = self.replay_block(&api, block.number.unwrap());
-            if let Err(e) = result.await {
-                log::warn!("Error replaying block: {}", e);
+        let timeout = self.options.rpc_timeout;
+        let mut stream = with_timeout(timeout, self.provider.subscribe_blocks()).await?;
+        let mut pending = VecDeque::new();
+        let mut last_header = None;
+        let mut resumed_after = None;
+        let mut replayed = 0;
+        let mut queued_replays = Vec::new();
+        if let Some(health) = &self.health {
+            health.set_ready();
+        }
+        loop {
+            // Queue the headers that arrived while the last block was replayed
+            while let Some(Some(block)) = stream.next().now_or_never() {
+                let number = block.number.unwrap().as_u64();
+                queue_header(&mut pending, &mut resumed_after, number);
+                last_header = Some(number);
+            }
+            while let Ok(message) = control_receiver.try_recv() {
+                self.handle_control(&api, message, &mut queued_replays)
+                    .await;
+            }
+
+            let Some(block_number) = pending.pop_front() else {
+                // Wait for the next block, serving control requests meanwhile
+                tokio::select! {
+                    _ = self.cancel.cancelled() => {
+                        log::info!("Stopping the block replay");
+                        break;
+                    }
+                    block = stream.next() => match block {
+                        Some(block) => {
+                            let number = block.number.unwrap().as_u64();
+                            queue_header(&mut pending, &mut resumed_after, number);
+                            last_header = Some(number);
+                        }
+                        None => {
+                            // The endpoint dropped the subscription. With
+                            // several endpoints, `eth_subscribe` fails over
+                            // to the next one if this one is gone.
+                            log::warn!("Block subscription dropped, resubscribing");
+                            let resubscribed = retry_with_backoff(
+                                "resubscribing to blocks",
+                                &self.cancel,
+                                || with_timeout(timeout, self.provider.subscribe_blocks()),
+                            )
+                            .await;
+                            let Some(resubscribed) = resubscribed else {
+                                log::info!("Stopping the block replay");
+                                break;
+                            };
+                            stream = resubscribed;
+                            resumed_after = last_header;
+                        }
+                    },
+                    Some(message) = control_receiver.recv() => {
+                        self.handle_control(&api, message, &mut queued_replays)
+                            .await;
+                    }
+                }
+                continue;
+            };
+
+            // The lag is measured against the newest header seen
+            let lag = pending
+                .back()
+                .map_or(0, |head| head.saturating_sub(block_number));
+            let strategy = self.options.catch_up_strategy(lag);
+            let result = tokio::select! {
+                result = self.replay_block(&api, block_number.into(), strategy) => result,
+                _ = self.cancel.cancelled() => {
+                    log::info!("Stopping the block replay");
+                    break;
+                }
+            };
+            match result {
+                Ok(mut report) => {
+                    report.lag = lag;
+                    report.log();
+                    self.metrics.lock().unwrap().record(&report);
+                    self.emit_summary(&report).await;
+                    if !report.skipped_block {
+                        self.poll_views(&api, &report).await;
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Error replaying block: {}", e);
+                    self.metrics.lock().unwrap().record_block_failure();
+                }
+            }
+            self.answer_queued_replays(&api, &mut queued_replays).await;
+            if let Some(health) = &self.health {
+                health.record_block(block_number);
+            }
+            replayed += 1;
+            if self
+                .max_blocks
+                .map_or(false, |max_blocks| replayed >= max_blocks)
+            {
+                log::info!("Stopping the block replay after {} blocks", replayed);
+                break;
             }
         }
 
         Ok(())
     }
 
+    /// Prints the summary of a replayed block and writes it to the
+    /// summary sinks, if enabled.
+    async fn emit_summary(&self, report: &BlockReplayReport) {
+        if !self.options.block_summaries && self.summary_sinks.is_empty() {
+            return;
+        }
+        let summary = BlockSummary::from(report);
+        if self.options.block_summaries {
+            println!("{}", summary);
+        }
+        let event = summary.to_event();
+        for sink in &self.summary_sinks {
+            if let Err(e) = sink.write(&event).await {
+                log::warn!("Error writing block summary to sink: {}", e);
+            }
+        }
+    }
+
+    /// Calls the view functions due after a replayed block and writes
+    /// their decoded results to the view sinks.
+    ///
+    /// A failed call is logged and doesn't stop the replay.
+    async fn poll_views(&self, api: &EthApi, report: &BlockReplayReport) {
+        if self.view_sinks.is_empty() {
+            return;
+        }
+        for (index, view) in self.views.iter().enumerate() {
+            if !view.due(report.block_number, report.timestamp) {
+                continue;
+            }
+            let request = EthTransactionRequest {
+                to: Some(view.to),
+                data: Some(view.calldata.clone()),
+                ..Default::default()
+            };
+            let output = match api.call(request, None, None).await {
+                Ok(output) => output,
+                Err(e) => {
+                    log::warn!("Error calling view {}: {}", view.name, e);
+                    continue;
+                }
+            };
+            let data = view.decode(self.abis.read().unwrap().get(&view.to), &output);
+            let event = view.to_event(index, report.block_number, report.timestamp, data);
+            for sink in &self.view_sinks {
+                if let Err(e) = sink.write(&event).await {
+                    log::warn!("Error writing view {} to sink: {}", view.name, e);
+                }
+            }
+        }
+    }
+
+    /// Replays the last `blocks` mainnet blocks on a fork started just
+    /// before them, measuring the replay throughput.
+    pub async fn bench(&self, blocks: u64) -> Result<BenchReport, ForkError> {
+        let timeout = self.options.rpc_timeout;
+        let latest = with_timeout(timeout, self.provider.get_block_number())
+            .await?
+            .as_u64();
+        let first = latest.saturating_sub(blocks.saturating_sub(1));
+
+        // Start the anvil fork
+        let (api, _) = self.start_anvil(Some(first.saturating_sub(1))).await?;
+
+        // Override the shadow contracts
+        self.override_contracts(&api).await?;
+
+        // Replay the blocks
+        let mut bench = BenchReport::default();
+        let started = Instant::now();
+        for block_number in first..=latest {
+            let result = tokio::select! {
+                result = self.replay_block(&api, block_number.into(), CatchUpStrategy::Replay) => result,
+                _ = self.cancel.cancelled() => {
+                    log::info!("Stopping the benchmark at block {}", block_number);
+                    break;
+                }
+            };
+            match result {
+                Ok(report) => {
+                    report.log();
+                    bench.record(&report);
+                }
+                Err(e) => {
+                    log::warn!("Error replaying block: {}", e);
+                    bench.record_block_failure();
+                }
+            }
+        }
+        bench.elapsed = started.elapsed();
+
+        Ok(bench)
+    }
+
+    /// Replays the mainnet blocks `first..=last` on a fork started just
+    /// before them, returning the report of each block.
+    ///
+    /// Unlike the live replay, a block that can't be replayed fails the
+    /// whole range, since the reports are meant to be complete.
+    pub async fn replay_range(
+        &self,
+        first: u64,
+        last: u64,
+    ) -> Result<Vec<BlockReplayReport>, ForkError> {
+        // Start the anvil fork
+        let (api, _) = self.start_anvil(Some(first.saturating_sub(1))).await?;
+
+        // Override the shadow contracts
+        self.override_contracts(&api).await?;
+
+        // Replay the blocks
+        let mut reports = Vec::new();
+        for block_number in first..=last {
+            let report = tokio::select! {
+                result = self.replay_block(&api, block_number.into(), CatchUpStrategy::Replay) => result,
+                _ = self.cancel.cancelled() => {
+                    return Err(ForkError::CustomError(format!(
+                        "Stopped at block {}",
+                        block_number
+                    )));
+                }
+            }
+            .map_err(|e| {
+                ForkError::CustomError(format!("Error replaying block {}: {}", block_number, e))
+            })?;
+            report.log();
+            reports.push(report);
+        }
+
+        Ok(reports)
+    }
+
+    /// Handles a `shadow_*` control request between block replays.
+    ///
+    /// Transactions to replay are only sent to the fork: mining them in a
+    /// block of their own would shift the fork's block numbers off
+    /// mainnet's, so they are mined at the start of the next replayed
+    /// block, and answered by [`Self::answer_queued_replays`].
+    async fn handle_control(
+        &self,
+        api: &EthApi,
+        (request, reply): ControlMessage,
+        queued_replays: &mut Vec<(ethers::types::H256, ControlReply)>,
+    ) {
+        let result = match request {
+            ControlRequest::ReplayTx(tx_hash) => match self.queue_replay(api, tx_hash).await {
+                Ok(hash) => {
+                    queued_replays.push((hash, reply));
+                    return;
+                }
+                Err(e) => Err(e),
+            },
+            request => self.control_result(api, request).await,
+        };
+        let _ = reply.send(result.map_err(|e| e.to_string()));
+    }
+
+    /// Sends a mainnet transaction to the fork on request, without
+    /// mining it.
+    async fn queue_replay(
+        &self,
+        api: &EthApi,
+        tx_hash: ethers::types::H256,
+    ) -> Result<ethers::types::H256, ForkError> {
+        let tx = with_timeout(
+            self.options.rpc_timeout,
+            self.provider.get_transaction(tx_hash),
+        )
+        .await?
+        .ok_or_else(|| ForkError::CustomError("Transaction not found".to_owned()))?;
+        api.anvil_set_balance(tx.from, self.sender_balance(&tx))
+            .await
+            .map_err(ForkError::BlockchainError)?;
+        self.send_replay(api, &tx)
+            .await
+            .map_err(ForkError::BlockchainError)
+    }
+
+    /// Answers the `shadow_replayTx` requests whose transactions were
+    /// mined, keeping the others queued.
+    async fn answer_queued_replays(
+        &self,
+        api: &EthApi,
+        queued_replays: &mut Vec<(ethers::types::H256, ControlReply)>,
+    ) {
+        for (hash, reply) in std::mem::take(queued_replays) {
+            let receipt = match api.transaction_receipt(hash).await {
+                Ok(Some(receipt)) => receipt,
+                Ok(None) => {
+                    queued_replays.push((hash, reply));
+                    continue;
+                }
+                Err(e) => {
+                    let _ = reply.send(Err(e.to_string()));
+                    continue;
+                }
+            };
+            let error = match receipt.status.map(|s| s.as_u64()) {
+                Some(1) => None,
+                _ => Some(self.revert_reason(api, hash).await),
+            };
+            let _ = reply.send(Ok(json!({
+                "transactionHash": hash,
+                "blockNumber": receipt.block_number,
+                "success": error.is_none(),
+                "error": error,
+            })));
+        }
+    }
+
+    /// Returns the result of a control request answered right away.
+    async fn control_result(
+        &self,
+        api: &EthApi,
+        request: ControlRequest,
+    ) -> Result<Value, ForkError> {
+        match request {
+            ControlRequest::ListContracts => {
+                let contracts: Vec<Value> = self
+                    .shadow_contracts
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .map(|c| {
+                        json!({
+                            "fileName": c.file_name,
+                            "contractName": c.contract_name,
+                            "address": c.address,
+                            "enabled": c.enabled,
+                        })
+                    })
+                    .collect();
+                Ok(json!(contracts))
+            }
+            ControlRequest::ReloadContract(address) => {
+                let address = format!("0x{}", hex::encode(address.as_bytes()));
+                let shadow_contract = self
+                    .shadow_resource
+                    .get_by_address(&address)
+                    .await
+                    .map_err(|e| ForkError::CustomError(e.to_string()))?;
+                if shadow_contract.enabled {
+                    self.set_code(api, &shadow_contract).await?;
+                }
+                let mut shadow_contracts = self.shadow_contracts.write().unwrap();
+                shadow_contracts.retain(|c| c.address != shadow_contract.address);
+                shadow_contracts.push(shadow_contract);
+                Ok(json!(true))
+            }
+            ControlRequest::ReplayTx(_) => Err(ForkError::CustomError(
+                "Transactions are replayed with the next block".to_owned(),
+            )),
+            ControlRequest::Status => {
+                let block_number = api
+                    .block_number()
+                    .map_err(ForkError::BlockchainError)?
+                    .as_u64();
+                let metrics = self.metrics.lock().unwrap().clone();
+                Ok(json!({
+                    "blockNumber": block_number,
+                    "shadowContracts": self.shadow_contracts.read().unwrap().len(),
+                    "metrics": metrics,
+                }))
+            }
+            ControlRequest::Impersonate(address) => {
+                api.anvil_impersonate_account(address)
+                    .await
+                    .map_err(ForkError::BlockchainError)?;
+                Ok(json!(true))
+            }
+            ControlRequest::StopImpersonating(address) => {
+                api.anvil_stop_impersonating_account(address)
+                    .await
+                    .map_err(ForkError::BlockchainError)?;
+                Ok(json!(true))
+            }
+            ControlRequest::Fund(address, amount) => {
+                let balance = api
+                    .balance(address, None)
+                    .await
+                    .map_err(ForkError::BlockchainError)?
+                    .checked_add(amount)
+                    .ok_or_else(|| {
+                        ForkError::CustomError("The new balance overflows a uint256".to_owned())
+                    })?;
+                api.anvil_set_balance(address, balance)
+                    .await
+                    .map_err(ForkError::BlockchainError)?;
+                Ok(json!(balance))
+            }
+        }
+    }
+
     /// Starts an anvil fork, which is used as a local shadow fork.
-    async fn start_anvil(&self) -> Result<(EthApi, NodeHandle), ForkError> {
-        let anvil_args = anvil_args(self.http_rpc_url.as_str());
+    ///
+    /// The fork starts at the latest block, unless a block number is given.
+    async fn start_anvil(
+        &self,
+        fork_block_number: Option<u64>,
+    ) -> Result<(EthApi, NodeHandle), ForkError> {
+        let anvil_args = anvil_args(self.http_rpc_url.as_str(), fork_block_number, &self.options);
         let (api, node_handle) = anvil::spawn(anvil_args.into_node_config()).await;
         Ok((api, node_handle))
     }
@@ -111,32 +682,148 @@ impl<P: JsonRpcClient + PubsubClient> Fork<P> {
     /// Overrides the shadow contract bytecode on the anvil fork.
     async fn override_contracts(&self, api: &EthApi) -> Result<(), ForkError> {
         // Override the contracts
-        for shadow_contract in &self.shadow_contracts {
-            api.anvil_set_code(
-                ethers::types::H160::from_str(shadow_contract.address.as_str()).unwrap(),
-                ethers::types::Bytes::from(
-                    hex::decode(shadow_contract.runtime_bytecode.as_str()).unwrap(),
-                ),
-            )
+        let shadow_contracts = self.shadow_contracts.read().unwrap().clone();
+        for shadow_contract in shadow_contracts.iter().filter(|c| c.enabled) {
+            self.set_code(api, shadow_contract).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends the post-deploy transactions from their signer, the first
+    /// time it is called, returning their labels and hashes.
+    ///
+    /// They are mined at the start of the first replayed block, rather
+    /// than in a block of their own, so the fork's block numbers keep
+    /// mirroring mainnet's. They pay the block's base fee.
+    async fn send_post_deploy(
+        &self,
+        api: &EthApi,
+        base_fee: ethers::types::U256,
+    ) -> Result<Vec<(String, ethers::types::H256)>, ForkError> {
+        let Some(post_deploy) = &self.post_deploy else {
+            return Ok(Vec::new());
+        };
+        if self.post_deploy_sent.swap(true, Ordering::SeqCst) {
+            return Ok(Vec::new());
+        }
+        let signer = &post_deploy.signer;
+        let chain_id = match self.options.chain_id {
+            Some(chain_id) => chain_id,
+            None => with_timeout(self.options.rpc_timeout, self.provider.get_chainid())
+                .await?
+                .as_u64(),
+        };
+        let mut nonce = api
+            .transaction_count(signer.address(), None)
             .await
-            .map_err(|e| ForkError::CustomError(e.to_string()))?;
+            .map_err(ForkError::BlockchainError)?;
+
+        // Fund the signer for the value it sends and the fees
+        let cost =
+            post_deploy
+                .transactions
+                .iter()
+                .fold(ethers::types::U256::zero(), |total, tx| {
+                    let gas = ethers::types::U256::from(tx.gas.unwrap_or(DEFAULT_POST_DEPLOY_GAS));
+                    total
+                        .saturating_add(tx.value)
+                        .saturating_add(gas.saturating_mul(base_fee))
+                });
+        api.anvil_set_balance(signer.address(), default_balance().saturating_add(cost))
+            .await
+            .map_err(ForkError::BlockchainError)?;
+
+        let mut sent = Vec::new();
+        for (index, tx) in post_deploy.transactions.iter().enumerate() {
+            let request: TypedTransaction = ethers::types::TransactionRequest::new()
+                .from(signer.address())
+                .to(tx.to)
+                .value(tx.value)
+                .data(tx.data.clone())
+                .gas(tx.gas.unwrap_or(DEFAULT_POST_DEPLOY_GAS))
+                .gas_price(base_fee)
+                .nonce(nonce)
+                .chain_id(chain_id)
+                .into();
+            let signature = signer
+                .sign_transaction_sync(&request)
+                .map_err(|e| ForkError::CustomError(e.to_string()))?;
+            let hash = api
+                .send_raw_transaction(request.rlp_signed(&signature))
+                .await
+                .map_err(ForkError::BlockchainError)?;
+            sent.push((tx.label(index), hash));
+            nonce += 1.into();
         }
+        Ok(sent)
+    }
 
+    /// Checks that the post-deploy transactions succeeded once mined.
+    ///
+    /// Fails if any of them reverted, since the shadow contracts would
+    /// run uninitialized.
+    async fn check_post_deploy(
+        &self,
+        api: &EthApi,
+        sent: Vec<(String, ethers::types::H256)>,
+    ) -> Result<(), ForkError> {
+        for (label, hash) in sent {
+            let status = api
+                .transaction_receipt(hash)
+                .await
+                .map_err(ForkError::BlockchainError)?
+                .and_then(|r| r.status)
+                .map(|s| s.as_u64());
+            if status != Some(1) {
+                return Err(ForkError::CustomError(format!(
+                    "Post-deploy {} failed: {}",
+                    label,
+                    self.revert_reason(api, hash).await
+                )));
+            }
+            log::info!("Sent post-deploy {} in {:?}", label, hash);
+        }
         Ok(())
     }
 
+    /// Sets the bytecode of a shadow contract on the anvil fork.
+    async fn set_code(
+        &self,
+        api: &EthApi,
+        shadow_contract: &ShadowContract,
+    ) -> Result<(), ForkError> {
+        api.anvil_set_code(
+            ethers::types::H160::from_str(shadow_contract.address.as_str()).unwrap(),
+            ethers::types::Bytes::from(
+                hex::decode(shadow_contract.runtime_bytecode.as_str()).unwrap(),
+            ),
+        )
+        .await
+        .map_err(|e| ForkError::CustomError(e.to_string()))
+    }
+
     /// Replays a block on the anvil fork.
+    ///
+    /// Returns a report describing which transactions were replayed,
+    /// which were skipped, and which failed on the fork. While catching
+    /// up with the chain head, the block may be skipped entirely.
+    #[tracing::instrument(skip_all, fields(block = %block_number))]
     async fn replay_block(
         &self,
         api: &EthApi,
         block_number: ethers::types::U64,
-    ) -> Result<(), ForkError> {
+        strategy: CatchUpStrategy,
+    ) -> Result<BlockReplayReport, ForkError> {
         // Get the block with transactions
-        let block = self
-            .provider
-            .get_block_with_txs(block_number)
-            .await
-            .map_err(ForkError::ProviderError)?;
+        let started = Instant::now();
+        let block = with_timeout(
+            self.options.rpc_timeout,
+            self.provider.get_block_with_txs(block_number),
+        )
+        .instrument(tracing::info_span!("fetch_block"))
+        .await
+        .map_err(ForkError::ProviderError)?;
 
         if block.is_none() {
             return Err(ForkError::CustomError(format!(
@@ -145,120 +832,951 @@ impl<P: JsonRpcClient + PubsubClient> Fork<P> {
             )));
         }
 
-        // Fetch the receipts
-        let block = block.unwrap();
-        let receipts = self.fetch_receipts(&block.transactions).await?;
+        // Narrow down the transactions while catching up with the chain head
+        let mut block = block.unwrap();
+        let mut report = BlockReplayReport::new(block_number.as_u64());
+        report.timestamp = block.timestamp.as_u64();
+        let total = block.transactions.len();
+        let logged = match strategy {
+            CatchUpStrategy::LogsPrefilter => {
+                Some(self.shadow_log_transactions(block_number).await?)
+            }
+            _ => None,
+        };
+        prefilter::catch_up(
+            strategy,
+            &mut block.transactions,
+            |tx| self.is_sent_to_shadow(tx),
+            logged.as_ref(),
+        );
+        if strategy != CatchUpStrategy::Replay && block.transactions.is_empty() {
+            report.skipped_not_shadowed = total as u64;
+            report.skipped_block = true;
+            return Ok(report);
+        }
+        report.skipped_not_shadowed = (total - block.transactions.len()) as u64;
+
+        // Find out which transactions succeeded on mainnet
+        let fetched_block = Instant::now();
+        let (succeeded, fetched) = self
+            .fetch_succeeded(block_number, &block.transactions, logged)
+            .await?;
+        let missing = fetched.missing.iter().copied().collect::<HashSet<_>>();
+        let fetched_receipts = Instant::now();
+
+        // Fill any gap between the fork and mainnet block numbers
+        if self.options.mirror_block_numbers {
+            self.align_block_number(api, block_number.as_u64(), block.timestamp.as_u64())
+                .await?;
+        }
 
         // Set up the block
-        if let Some(base_fee) = block.base_fee_per_gas {
-            api.anvil_set_next_block_base_fee_per_gas(base_fee)
-                .await
+        let base_fee = match self.options.fee_mode {
+            FeeMode::Zero => ethers::types::U256::zero(),
+            FeeMode::Mainnet => block.base_fee_per_gas.unwrap_or_default(),
+        };
+        api.anvil_set_next_block_base_fee_per_gas(base_fee)
+            .await
+            .map_err(ForkError::BlockchainError)?;
+        if self.options.mirror_timestamps {
+            api.evm_set_next_block_timestamp(block.timestamp.as_u64())
                 .map_err(ForkError::BlockchainError)?;
         }
-        api.evm_set_next_block_timestamp(block.timestamp.as_u64())
-            .map_err(ForkError::BlockchainError)?;
+        if self.options.mirror_block_env {
+            self.set_block_env(api, &block).await?;
+        }
+
+        // Replay the transactions
+        report.timings.fetch_block = fetched_block - started;
+        report.timings.fetch_receipts = fetched_receipts - fetched_block;
+        self.execute_transactions(
+            api,
+            block.transactions,
+            base_fee,
+            &succeeded,
+            &missing,
+            &mut report,
+        )
+        .await?;
+        report.timings.execution = fetched_receipts.elapsed();
+
+        // Keep the pinned contracts in sync for the next block
+        self.refresh_pins(api, block_number).await;
+
+        Ok(report)
+    }
+
+    /// Sends the transactions to replay to the fork, mines them, and
+    /// records their outcome in the report.
+    #[tracing::instrument(skip_all, fields(transactions = transactions.len()))]
+    async fn execute_transactions(
+        &self,
+        api: &EthApi,
+        transactions: Vec<Transaction>,
+        base_fee: ethers::types::U256,
+        succeeded: &HashSet<ethers::types::H256>,
+        missing: &HashSet<ethers::types::H256>,
+        report: &mut BlockReplayReport,
+    ) -> Result<(), ForkError> {
+        // Initialize the shadow contracts in the first replayed block
+        let post_deploy = self.send_post_deploy(api, base_fee).await?;
+
+        // Send the synthetic transactions due in this block next
+        let mut sent = Vec::new();
+        let mut synthetic = HashMap::new();
+        let mut synthetic_senders = HashMap::new();
+        if let Some(scenario) = &self.scenario {
+            for (index, tx) in scenario.due(report.block_number) {
+                let label = tx.label(index);
+                // Keep the sender's balance to restore it once mined
+                if let Entry::Vacant(entry) = synthetic_senders.entry(tx.from) {
+                    let balance = api
+                        .balance(tx.from, None)
+                        .await
+                        .map_err(ForkError::BlockchainError)?;
+                    entry.insert((balance, 0));
+                }
+                match self.send_synthetic(api, &tx).await {
+                    Ok(hash) => {
+                        sent.push((hash, hash, None));
+                        synthetic.insert(hash, (label, tx.from));
+                    }
+                    Err(e) => {
+                        report.synthetic_failed += 1;
+                        log::warn!("Error sending synthetic {}: {}", label, e);
+                    }
+                }
+            }
+        }
 
         // Send the transactions
-        for tx in block.transactions {
-            if self.should_replay(&tx, &receipts) {
-                // Give the wallet extra ETH for the transaction before sending it
-                api.anvil_set_balance(tx.from, ethers::types::U256::from("100000000000000000000"))
-                    .await
-                    .map_err(ForkError::BlockchainError)?;
-                api.send_raw_transaction(tx.rlp())
-                    .await
-                    .map_err(ForkError::BlockchainError)?;
+        let decisions = transactions
+            .iter()
+            .map(|tx| self.should_replay(tx, succeeded, missing))
+            .collect::<Vec<_>>();
+        let staged = if self.options.prestage_state {
+            prestage::staged(&decisions)
+        } else {
+            Vec::new()
+        };
+        let mut prestates = None;
+        for (index, (tx, decision)) in transactions.into_iter().zip(decisions).enumerate() {
+            match decision {
+                ReplayDecision::SkipNotShadowed => report.skipped_not_shadowed += 1,
+                ReplayDecision::SkipFailedOnMainnet => report.skipped_failed_on_mainnet += 1,
+                ReplayDecision::SkipMissingReceipt => report.skipped_missing_receipt += 1,
+                ReplayDecision::Replay => {
+                    // Catch up with the state skipped transactions changed,
+                    // tracing the block once for all of them
+                    if staged.contains(&index) {
+                        if prestates.is_none() {
+                            prestates = Some(self.trace_block_prestates(report.block_number).await);
+                        }
+                        self.prestage(api, &tx, index, prestates.as_ref().unwrap(), report)
+                            .await;
+                    }
+                    // Give the wallet extra ETH for the transaction before sending it
+                    api.anvil_set_balance(tx.from, self.sender_balance(&tx))
+                        .await
+                        .map_err(ForkError::BlockchainError)?;
+                    match self.send_replay(api, &tx).await {
+                        Ok(hash) => sent.push((hash, tx.hash, self.headroom_limit(&tx))),
+                        Err(e) => report.record_failure(tx.hash, e.to_string()),
+                    }
+                }
             }
         }
 
         // Mine the block
         api.evm_mine(None)
+            .instrument(tracing::info_span!("mine"))
             .await
             .map_err(ForkError::BlockchainError)?;
+        self.check_post_deploy(api, post_deploy).await?;
+
+        // Fetch the mainnet receipts to compare the replays to, other
+        // than the ones fetched to tell which transactions succeeded
+        let mainnet_receipts = if self.options.determinism_report {
+            let mut mainnet_receipts = fetched.receipts;
+            let origins = sent
+                .iter()
+                .filter(|(hash, _, _)| !synthetic.contains_key(hash))
+                .map(|(_, origin, _)| *origin)
+                .filter(|origin| !mainnet_receipts.contains_key(origin))
+                .collect::<Vec<_>>();
+            mainnet_receipts.extend(self.fetch_receipts_by_hash(&origins).await?.receipts);
+            mainnet_receipts
+        } else {
+            HashMap::new()
+        };
+
+        // Check the outcome of the replayed transactions
+        for (hash, origin, headroom_limit) in sent {
+            let receipt = api
+                .transaction_receipt(hash)
+                .await
+                .map_err(ForkError::BlockchainError)?;
+            let gas_used = receipt.as_ref().and_then(|r| r.gas_used);
+            if matches!((gas_used, headroom_limit), (Some(used), Some(limit)) if used > limit) {
+                report.headroom_needed += 1;
+            }
+            if let Some(receipt) = &receipt {
+                self.record_events(origin, receipt, report);
+                if let Some(mainnet) = mainnet_receipts.get(&origin) {
+                    self.check_determinism(origin, mainnet, receipt, report);
+                }
+            }
+            let status = receipt.as_ref().and_then(|r| r.status).map(|s| s.as_u64());
+            if let Some((label, from)) = synthetic.get(&hash) {
+                if receipt.is_some() {
+                    if let Some((_, mined)) = synthetic_senders.get_mut(from) {
+                        *mined += 1;
+                    }
+                }
+                if status == Some(1) {
+                    report.synthetic += 1;
+                } else {
+                    report.synthetic_failed += 1;
+                    log::warn!(
+                        "Synthetic {} failed on the fork: {}",
+                        label,
+                        self.revert_reason(api, hash).await
+                    );
+                }
+            } else {
+                // Only mainnet transactions trigger synthetic ones, so
+                // triggers can't loop
+                if let (Some(scenario), Some(receipt)) = (&self.scenario, &receipt) {
+                    for (log, event) in self.shadow_events(receipt) {
+                        scenario.observe(log.address, &event);
+                    }
+                }
+                // Failures are reported by their mainnet hash, which
+                // differs from the fork's when the sender is impersonated
+                match status {
+                    Some(1) => report.replayed += 1,
+                    Some(_) => report.record_failure(origin, self.revert_reason(api, hash).await),
+                    None => report.record_failure(origin, "Transaction was not mined".to_owned()),
+                }
+            }
+            if status == Some(1) && !self.factories.is_empty() {
+                self.track_children(api, hash).await;
+            }
+            if self.options.trace {
+                self.print_trace(api, hash).await;
+            }
+        }
+        self.restore_synthetic_senders(api, synthetic_senders).await;
 
         Ok(())
     }
 
-    /// Fetches the receipts for a list of transactions in parallel
-    async fn fetch_receipts(
+    /// Restores the balances the synthetic transactions' senders had
+    /// before they were funded, and takes back the nonces the mined
+    /// synthetic transactions used, so the senders' mainnet transactions
+    /// replay with their mainnet nonces.
+    ///
+    /// Errors are logged, so a sender never stops the replay.
+    async fn restore_synthetic_senders(
         &self,
-        transactions: &[Transaction],
-    ) -> Result<HashMap<ethers::types::H256, TransactionReceipt>, ForkError> {
-        let mut receipt_map = HashMap::new();
-
-        let mut join_set = JoinSet::new();
-
-        // Spawn a task for each transaction receipt fetch
-        for tx in transactions.iter() {
-            let tx_hash = tx.hash;
-            let provider = self.provider.clone();
-            join_set.spawn(async move {
-                let receipt = provider.get_transaction_receipt(tx_hash).await?;
-                Ok::<Option<TransactionReceipt>, ProviderError>(receipt)
-            });
+        api: &EthApi,
+        senders: HashMap<ethers::types::H160, (ethers::types::U256, u64)>,
+    ) {
+        for (address, (balance, mined)) in senders {
+            let restored = async {
+                api.anvil_set_balance(address, balance).await?;
+                let nonce = api.transaction_count(address, None).await?;
+                api.anvil_set_nonce(address, nonce.saturating_sub(mined.into()))
+                    .await
+            };
+            if let Err(e) = restored.await {
+                log::warn!("Error restoring synthetic sender {:?}: {}", address, e);
+            }
         }
+    }
 
-        while let Some(result) = join_set.join_next().await {
-            let receipt = result
-                .map_err(|e| ForkError::CustomError(e.to_string()))?
-                .map_err(|e| {
-                    ForkError::CustomError(format!("Error getting transaction receipt: {}", e))
-                })?;
+    /// Sends a mainnet transaction to the fork.
+    ///
+    /// The signed transaction is sent as is, unless the replay mode is
+    /// impersonated or it was signed for another chain id than the fork's.
+    /// It is then rebuilt unsigned, with its gas limit scaled and the
+    /// replay hooks applied, and sent from its impersonated sender with
+    /// the fork's nonce.
+    async fn send_replay(
+        &self,
+        api: &EthApi,
+        tx: &Transaction,
+    ) -> Result<ethers::types::H256, BlockchainError> {
+        if self.options.replays_signed(tx) {
+            return api.send_raw_transaction(tx.rlp()).await;
+        }
+        let (gas_price, max_fee_per_gas, max_priority_fee_per_gas) = match self.options.fee_mode {
+            FeeMode::Zero => (None, None, None),
+            FeeMode::Mainnet if tx.max_fee_per_gas.is_some() => {
+                (None, tx.max_fee_per_gas, tx.max_priority_fee_per_gas)
+            }
+            FeeMode::Mainnet => (tx.gas_price, None, None),
+        };
+        let mut call = ReplayCall::from(tx);
+        for hook in &self.replay_hooks {
+            hook.apply(tx, &mut call);
+        }
+        if call.value > tx.value {
+            // The sender was only funded for the original value
+            api.anvil_set_balance(
+                tx.from,
+                self.sender_balance(tx)
+                    .saturating_add(call.value - tx.value),
+            )
+            .await?;
+        }
+        let request = EthTransactionRequest {
+            from: Some(tx.from),
+            to: call.to,
+            value: Some(call.value),
+            gas: Some(self.replay_gas(tx)),
+            data: Some(call.data),
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            access_list: tx.access_list.clone().map(|list| list.0),
+            ..Default::default()
+        };
+        send_impersonated(api, tx.from, request).await
+    }
 
-            match receipt {
-                Some(receipt) => {
-                    receipt_map.insert(receipt.transaction_hash, receipt);
+    /// Sends a synthetic transaction from its impersonated sender.
+    ///
+    /// The sender is funded for it, and restored by
+    /// [`Fork::restore_synthetic_senders`] once the block is mined.
+    async fn send_synthetic(
+        &self,
+        api: &EthApi,
+        tx: &SyntheticTransaction,
+    ) -> Result<ethers::types::H256, BlockchainError> {
+        api.anvil_set_balance(tx.from, default_balance().saturating_add(tx.value))
+            .await?;
+        let request = EthTransactionRequest {
+            from: Some(tx.from),
+            to: tx.to,
+            value: Some(tx.value),
+            gas: tx.gas.map(Into::into),
+            data: Some(tx.data.clone()),
+            ..Default::default()
+        };
+        send_impersonated(api, tx.from, request).await
+    }
+
+    /// Adds the gas used by a replayed transaction, and the events its
+    /// shadow contracts emitted, to the report.
+    ///
+    /// `origin` is the hash of the mainnet transaction that was replayed,
+    /// which differs from the receipt's for impersonated replays.
+    fn record_events(
+        &self,
+        origin: ethers::types::H256,
+        receipt: &TransactionReceipt,
+        report: &mut BlockReplayReport,
+    ) {
+        report.gas_used += receipt.gas_used.unwrap_or_default().as_u64();
+        for (log, name) in self.shadow_events(receipt) {
+            *report.events.entry(name.clone()).or_default() += 1;
+            report.shadow_logs.push(ShadowLog {
+                transaction_hash: origin,
+                address: log.address,
+                event: name,
+                topics: log.topics.clone(),
+                data: log.data.clone(),
+            });
+        }
+    }
+
+    /// Copies the storage of the pinned contracts at the end of a mainnet
+    /// block onto the fork.
+    ///
+    /// Pins with slots read them with `eth_getStorageAt`. The others copy
+    /// every slot the block changed, traced with `debug_traceBlockByNumber`,
+    /// so their changes in blocks skipped while catching up are missed.
+    /// Errors are logged, so a pin never stops the replay.
+    async fn refresh_pins(&self, api: &EthApi, block_number: ethers::types::U64) {
+        if self.pins.is_empty() {
+            return;
+        }
+        let mut diffs = None;
+        for pin in &self.pins {
+            let slots = if pin.slots.is_empty() {
+                if diffs.is_none() {
+                    diffs = Some(self.trace_block_diffs(block_number).await);
+                }
+                match diffs.as_ref().unwrap() {
+                    Ok(diffs) => pins::changed_slots(diffs, pin.address),
+                    Err(e) => {
+                        log::warn!("Error refreshing pin {}: {}", pin.label(), e);
+                        continue;
+                    }
+                }
+            } else {
+                let mut slots = BTreeMap::new();
+                for slot in &pin.slots {
+                    let value = with_timeout(
+                        self.options.rpc_timeout,
+                        self.provider
+                            .get_storage_at(pin.address, *slot, Some(block_number.into())),
+                    )
+                    .await;
+                    match value {
+                        Ok(value) => {
+                            slots.insert(*slot, value);
+                        }
+                        Err(e) => log::warn!("Error refreshing pin {}: {}", pin.label(), e),
+                    }
+                }
+                slots
+            };
+            for (slot, value) in &slots {
+                let slot = ethers::types::U256::from(slot.as_bytes());
+                if let Err(e) = api.anvil_set_storage_at(pin.address, slot, *value).await {
+                    log::warn!("Error refreshing pin {}: {}", pin.label(), e);
                 }
+            }
+            log::debug!("Refreshed {} slots of pin {}", slots.len(), pin.label());
+        }
+    }
+
+    /// Returns the state changes of each transaction in a mainnet block.
+    async fn trace_block_diffs(
+        &self,
+        block_number: ethers::types::U64,
+    ) -> Result<Vec<pins::TransactionDiff>, ProviderError> {
+        with_timeout(
+            self.options.rpc_timeout,
+            self.provider.request(
+                "debug_traceBlockByNumber",
+                (
+                    block_number,
+                    json!({ "tracer": "prestateTracer", "tracerConfig": { "diffMode": true } }),
+                ),
+            ),
+        )
+        .await
+    }
+
+    /// Returns the state each transaction in a mainnet block read.
+    async fn trace_block_prestates(
+        &self,
+        block_number: u64,
+    ) -> Result<Vec<prestage::TransactionPreState>, ProviderError> {
+        with_timeout(
+            self.options.rpc_timeout,
+            self.provider.request(
+                "debug_traceBlockByNumber",
+                (
+                    ethers::types::U64::from(block_number),
+                    json!({ "tracer": "prestateTracer" }),
+                ),
+            ),
+        )
+        .await
+    }
+
+    /// Copies the mainnet state a transaction read onto the fork before
+    /// it is replayed.
+    ///
+    /// This is best effort: the state is traced with the `prestateTracer`
+    /// of `debug_traceBlockByNumber`, which not every provider supports,
+    /// and the transaction is replayed as is when it can't be.
+    async fn prestage(
+        &self,
+        api: &EthApi,
+        tx: &Transaction,
+        index: usize,
+        prestates: &Result<Vec<prestage::TransactionPreState>, ProviderError>,
+        report: &mut BlockReplayReport,
+    ) {
+        let state = match prestates {
+            Ok(prestates) => match prestates.get(index) {
+                Some(prestate) => &prestate.result,
                 None => {
-                    return Err(ForkError::CustomError("Receipt not found.".to_string()));
+                    log::warn!("Missing the prestate of {:?}", tx.hash);
+                    return;
                 }
+            },
+            Err(e) => {
+                log::warn!("Error fetching the prestate of {:?}: {}", tx.hash, e);
+                return;
+            }
+        };
+        let is_shadowed = |address: &ethers::types::H160| {
+            self.is_shadowed(&format!("0x{}", hex::encode(address.as_bytes())))
+        };
+        match prestage::apply(api, state, is_shadowed).await {
+            Ok(slots) => {
+                report.prestaged += 1;
+                log::debug!(
+                    "Pre-staged {} accounts and {} storage slots for {:?}",
+                    state.len(),
+                    slots,
+                    tx.hash
+                );
             }
+            Err(e) => log::warn!("Error pre-staging the state of {:?}: {}", tx.hash, e),
         }
+    }
 
-        Ok(receipt_map)
+    /// Compares the fork receipt of a replayed transaction to its mainnet
+    /// receipt, adding the divergences to the report.
+    fn check_determinism(
+        &self,
+        origin: ethers::types::H256,
+        mainnet: &TransactionReceipt,
+        fork: &TransactionReceipt,
+        report: &mut BlockReplayReport,
+    ) {
+        report.determinism_checked += 1;
+        let divergences = determinism::compare_receipts(mainnet, fork, |address| {
+            self.is_shadowed(&format!("{:?}", address))
+        });
+        report
+            .divergences
+            .extend(divergences.into_iter().map(|kind| Divergence {
+                tx_hash: origin,
+                kind,
+            }));
+    }
+
+    /// Returns each log shadow contracts emitted in a replayed
+    /// transaction, with its event name.
+    ///
+    /// Events are named with the loaded ABIs, or by topic0 otherwise.
+    fn shadow_events<'a>(
+        &self,
+        receipt: &'a TransactionReceipt,
+    ) -> Vec<(&'a ethers::types::Log, String)> {
+        let abis = self.abis.read().unwrap();
+        let mut events = Vec::new();
+        for log in &receipt.logs {
+            if !self.is_shadowed(&format!("{:?}", log.address)) {
+                continue;
+            }
+            let Some(topic0) = log.topics.first() else {
+                continue;
+            };
+            let name = abis
+                .get(&log.address)
+                .and_then(|abi| {
+                    abi.events()
+                        .find(|event| event.selector().as_slice() == topic0.as_bytes())
+                })
+                .map(|event| event.name.clone())
+                .unwrap_or_else(|| format!("{:?}", topic0));
+            events.push((log, name));
+        }
+        events
+    }
+
+    /// Copies the mainnet block environment onto the next fork block.
+    ///
+    /// The fee recipient (`block.coinbase`) and gas limit (`block.gaslimit`)
+    /// are set through anvil's setters. Anvil has no setter for
+    /// `block.prevrandao`, so the block's mix hash is written directly into
+    /// the backend's environment, which the next mined block inherits.
+    async fn set_block_env(
+        &self,
+        api: &EthApi,
+        block: &ethers::types::Block<Transaction>,
+    ) -> Result<(), ForkError> {
+        if let Some(miner) = block.author {
+            api.anvil_set_coinbase(miner)
+                .await
+                .map_err(ForkError::BlockchainError)?;
+        }
+        api.evm_set_block_gas_limit(block.gas_limit)
+            .map_err(ForkError::BlockchainError)?;
+        if let Some(mix_hash) = block.mix_hash {
+            api.backend.env().write().block.prevrandao = Some(mix_hash.0.into());
+        }
+        Ok(())
+    }
+
+    /// Mines empty blocks until the next fork block has the same
+    /// number as the mainnet block about to be replayed.
+    ///
+    /// When timestamps are mirrored, the empty blocks get timestamps
+    /// one second apart leading up to the mainnet block's timestamp.
+    async fn align_block_number(
+        &self,
+        api: &EthApi,
+        block_number: u64,
+        timestamp: u64,
+    ) -> Result<(), ForkError> {
+        let fork_block_number = api
+            .block_number()
+            .map_err(ForkError::BlockchainError)?
+            .as_u64();
+
+        if fork_block_number >= block_number {
+            log::warn!(
+                "Fork is at block {} and cannot mirror mainnet block {}",
+                fork_block_number,
+                block_number
+            );
+            return Ok(());
+        }
+
+        let gap = block_number - fork_block_number - 1;
+        for i in 0..gap {
+            if self.options.mirror_timestamps {
+                if let Err(e) = api.evm_set_next_block_timestamp(timestamp.saturating_sub(gap - i))
+                {
+                    log::debug!("Could not set timestamp of empty block: {}", e);
+                }
+            }
+            api.evm_mine(None)
+                .await
+                .map_err(ForkError::BlockchainError)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the balance to give a sender before replaying its transaction.
+    ///
+    /// In [`FeeMode::Mainnet`] the sender pays real fees on the fork, so the
+    /// balance must cover the worst-case fee on top of the transferred value.
+    fn sender_balance(&self, tx: &Transaction) -> ethers::types::U256 {
+        match self.options.fee_mode {
+            FeeMode::Zero => default_balance(),
+            FeeMode::Mainnet => {
+                let max_fee = tx.max_fee_per_gas.or(tx.gas_price).unwrap_or_default();
+                let required = self
+                    .replay_gas(tx)
+                    .saturating_mul(max_fee)
+                    .saturating_add(tx.value);
+                default_balance().saturating_add(required)
+            }
+        }
+    }
+
+    /// Returns the gas limit a transaction is replayed with.
+    ///
+    /// Signed transactions keep their own gas limit, and others are
+    /// scaled by the gas multiplier.
+    fn replay_gas(&self, tx: &Transaction) -> ethers::types::U256 {
+        if self.options.replays_signed(tx) || self.options.gas_multiplier == 1.0 {
+            return tx.gas;
+        }
+        let scaled = tx.gas.as_u128() as f64 * self.options.gas_multiplier;
+        ethers::types::U256::from(scaled as u128)
+    }
+
+    /// Returns the original gas limit of a transaction replayed with gas
+    /// headroom, so the replay can tell whether the headroom was needed.
+    fn headroom_limit(&self, tx: &Transaction) -> Option<ethers::types::U256> {
+        (self.replay_gas(tx) > tx.gas).then_some(tx.gas)
+    }
+
+    /// Prints the decoded call tree of a replayed transaction.
+    async fn print_trace(&self, api: &EthApi, tx_hash: ethers::types::H256) {
+        match api.trace_transaction(tx_hash).await {
+            Ok(traces) => println!(
+                "=> Trace: 0x{}\n{}",
+                hex::encode(tx_hash.as_bytes()),
+                trace::render_call_tree(&traces, &self.abis.read().unwrap())
+            ),
+            Err(e) => log::warn!("Error tracing transaction: {}", e),
+        }
+    }
+
+    /// Shadows the contracts tracked factories created in a replayed
+    /// transaction.
+    ///
+    /// Each child is registered in the Shadow store with the factory's
+    /// child artifact, so its transactions are replayed from the next
+    /// block on and a running `shadow events` starts decoding its events.
+    /// Errors are logged, so a child that can't be shadowed doesn't stop
+    /// the replay of the block.
+    async fn track_children(&self, api: &EthApi, tx_hash: ethers::types::H256) {
+        let enabled: Vec<ethers::types::H160> = self
+            .factories
+            .iter()
+            .map(|f| f.factory)
+            .filter(|factory| self.is_shadowed(&format!("0x{}", hex::encode(factory.as_bytes()))))
+            .collect();
+        if enabled.is_empty() {
+            return;
+        }
+        let traces = match api.trace_transaction(tx_hash).await {
+            Ok(traces) => traces,
+            Err(e) => {
+                log::warn!("Error tracing transaction: {}", e);
+                return;
+            }
+        };
+
+        for child in factory::find_children(&traces, &enabled) {
+            let tracked = self
+                .factories
+                .iter()
+                .find(|f| f.factory == child.factory)
+                .unwrap();
+            let address = format!("0x{}", hex::encode(child.address.as_bytes()));
+            let tags = self
+                .shadow_contracts
+                .read()
+                .unwrap()
+                .iter()
+                .find(|c| c.address == format!("0x{}", hex::encode(child.factory.as_bytes())))
+                .map(|c| c.tags.clone())
+                .unwrap_or_default();
+            let shadow_contract = ShadowContract {
+                file_name: tracked.file_name.clone(),
+                contract_name: tracked.contract_name.clone(),
+                address: address.clone(),
+                runtime_bytecode: tracked
+                    .runtime_bytecode
+                    .clone()
+                    .unwrap_or_else(|| hex::encode(&child.code)),
+                enabled: true,
+                tags,
+                attestation: None,
+            };
+            if tracked.runtime_bytecode.is_some() {
+                if let Err(e) = self.set_code(api, &shadow_contract).await {
+                    log::warn!("Error shadowing {}: {}", address, e);
+                    continue;
+                }
+            }
+            if let Err(e) = self.shadow_resource.upsert(shadow_contract.clone()).await {
+                log::warn!("Error saving shadow contract {}: {}", address, e);
+            }
+            println!(
+                "Shadowing {}:{} at {}, created by factory 0x{}",
+                tracked.file_name,
+                tracked.contract_name,
+                address,
+                hex::encode(child.factory.as_bytes())
+            );
+            self.abis
+                .write()
+                .unwrap()
+                .insert(child.address, tracked.abi.clone());
+            let mut shadow_contracts = self.shadow_contracts.write().unwrap();
+            shadow_contracts.retain(|c| c.address != address);
+            shadow_contracts.push(shadow_contract);
+        }
+    }
+
+    /// Returns a best-effort revert reason for a transaction that failed on the fork.
+    ///
+    /// The reason is taken from the top-level call trace of the transaction.
+    async fn revert_reason(&self, api: &EthApi, tx_hash: ethers::types::H256) -> String {
+        match api.trace_transaction(tx_hash).await {
+            Ok(traces) => traces
+                .into_iter()
+                .find(|trace| trace.trace_address.is_empty())
+                .and_then(|trace| trace.error)
+                .unwrap_or_else(|| "execution reverted".to_owned()),
+            Err(e) => format!("execution reverted ({})", e),
+        }
+    }
+
+    /// Returns the hashes of the transactions that succeeded on mainnet,
+    /// among those that may be replayed, and the receipts fetched to
+    /// tell, with the transactions whose receipt never became available.
+    ///
+    /// Rather than fetching every receipt of the block, a single
+    /// `eth_getLogs` query for the shadow contracts prefilters it: only
+    /// transactions sent to a shadow contract may be replayed, and those
+    /// that emitted a shadow contract log must have succeeded. Only the
+    /// receipts of the remaining ones are fetched. The logs are queried
+    /// unless they are given.
+    ///
+    /// When all transactions are replayed, their outcome on mainnet
+    /// doesn't matter, so nothing is fetched.
+    async fn fetch_succeeded(
+        &self,
+        block_number: ethers::types::U64,
+        transactions: &[Transaction],
+        logged: Option<HashSet<ethers::types::H256>>,
+    ) -> Result<(HashSet<ethers::types::H256>, receipts::FetchedReceipts), ForkError> {
+        if self.options.all_txs {
+            return Ok(Default::default());
+        }
+
+        let candidates = transactions
+            .iter()
+            .filter(|tx| self.is_sent_to_shadow(tx))
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return Ok(Default::default());
+        }
+
+        let logged = match logged {
+            Some(logged) => logged,
+            None => self.shadow_log_transactions(block_number).await?,
+        };
+        let (mut succeeded, unknown) = prefilter::split_by_logs(candidates, &logged);
+        log::debug!(
+            "Fetching {} of {} receipts for block {}",
+            unknown.len(),
+            transactions.len(),
+            block_number
+        );
+
+        let fetched = self.fetch_receipts(&unknown).await?;
+        succeeded.extend(
+            fetched
+                .receipts
+                .values()
+                .filter(|receipt| receipt.status.map(|s| s.as_u64()) == Some(1))
+                .map(|receipt| receipt.transaction_hash),
+        );
+        // Only the transactions without a receipt are skipped, not the block
+        for tx_hash in &fetched.missing {
+            log::warn!(
+                "Receipt of {:?} in block {} is not available, skipping the transaction",
+                tx_hash,
+                block_number
+            );
+        }
+        Ok((succeeded, fetched))
+    }
+
+    /// Fetches the receipts for a list of transactions in parallel,
+    /// retrying those that aren't available yet
+    #[tracing::instrument(skip_all, fields(transactions = transactions.len()))]
+    async fn fetch_receipts(
+        &self,
+        transactions: &[Transaction],
+    ) -> Result<receipts::FetchedReceipts, ForkError> {
+        let hashes = transactions.iter().map(|tx| tx.hash).collect::<Vec<_>>();
+        self.fetch_receipts_by_hash(&hashes).await
+    }
+
+    /// Fetches the receipts for a list of transaction hashes in parallel,
+    /// retrying those that aren't available yet
+    async fn fetch_receipts_by_hash(
+        &self,
+        hashes: &[ethers::types::H256],
+    ) -> Result<receipts::FetchedReceipts, ForkError> {
+        receipts::fetch_receipts(
+            hashes,
+            self.options.receipt_retries,
+            self.options.receipt_retry_delay,
+            |tx_hash| {
+                let provider = self.provider.clone();
+                let timeout = self.options.rpc_timeout;
+                async move {
+                    with_timeout(timeout, provider.get_transaction_receipt(tx_hash)).await
+                }
+            },
+        )
+        .await
+    }
+
+    /// Returns the hashes of the transactions in a block that emitted a
+    /// log from an enabled shadow contract.
+    async fn shadow_log_transactions(
+        &self,
+        block_number: ethers::types::U64,
+    ) -> Result<HashSet<ethers::types::H256>, ForkError> {
+        let addresses = self
+            .shadow_contracts
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|c| c.enabled)
+            .map(|c| ethers::types::H160::from_str(c.address.as_str()).unwrap())
+            .collect::<Vec<_>>();
+        if addresses.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let filter = Filter::new().select(block_number).address(addresses);
+        let logs = with_timeout(self.options.rpc_timeout, self.provider.get_logs(&filter)).await?;
+        Ok(logs
+            .into_iter()
+            .filter_map(|log| log.transaction_hash)
+            .collect())
+    }
+
+    /// Returns whether a transaction is sent to an enabled shadow contract.
+    fn is_sent_to_shadow(&self, tx: &Transaction) -> bool {
+        tx.to
+            .map(|to| self.is_shadowed(format!("0x{}", hex::encode(to.as_bytes())).as_str()))
+            .unwrap_or(false)
     }
 
     fn should_replay(
         &self,
         tx: &Transaction,
-        receipts: &HashMap<ethers::types::H256, TransactionReceipt>,
-    ) -> bool {
-        if self.all_txs {
-            return true;
+        succeeded: &HashSet<ethers::types::H256>,
+        missing: &HashSet<ethers::types::H256>,
+    ) -> ReplayDecision {
+        if self.options.all_txs {
+            return ReplayDecision::Replay;
         }
 
         // If the transaction is not to a shadowed contract, don't replay it
-        let is_shadowed = tx
-            .to
-            .map(|to| self.is_shadowed(format!("0x{}", hex::encode(to.as_bytes())).as_str()))
-            .unwrap_or(false);
+        let is_shadowed = self.is_sent_to_shadow(tx);
 
         // If the transaction is not successful, don't replay it
-        let is_success = receipts
-            .get(&tx.hash)
-            .map(|receipt| {
-                receipt
-                    .status
-                    .map(|status| status.as_u64() == 1)
-                    .unwrap_or(false)
-            })
-            .unwrap_or(false);
+        let is_success = succeeded.contains(&tx.hash);
 
-        is_shadowed && is_success
+        if !is_shadowed {
+            ReplayDecision::SkipNotShadowed
+        } else if missing.contains(&tx.hash) {
+            ReplayDecision::SkipMissingReceipt
+        } else if !is_success {
+            ReplayDecision::SkipFailedOnMainnet
+        } else {
+            ReplayDecision::Replay
+        }
     }
 
     fn is_shadowed(&self, address: &str) -> bool {
-        self.shadow_contracts.iter().any(|c| c.address == address)
+        self.shadow_contracts
+            .read()
+            .unwrap()
+            .iter()
+            .any(|c| c.enabled && c.address == address)
+    }
+}
+
+/// Sends a transaction from its impersonated sender.
+///
+/// The impersonation is always stopped. An error stopping it is logged,
+/// so it doesn't hide the outcome of the transaction.
+async fn send_impersonated(
+    api: &EthApi,
+    from: ethers::types::H160,
+    request: EthTransactionRequest,
+) -> Result<ethers::types::H256, BlockchainError> {
+    api.anvil_impersonate_account(from).await?;
+    let result = api.send_transaction(request).await;
+    if let Err(e) = api.anvil_stop_impersonating_account(from).await {
+        log::warn!("Error stopping impersonating {:?}: {}", from, e);
     }
+    result
+}
+
+/// The balance senders are given on top of what they spend, 100 ETH.
+fn default_balance() -> ethers::types::U256 {
+    ethers::utils::parse_ether(100).unwrap()
 }
 
-fn anvil_args(http_rpc_url: &str) -> NodeArgs {
-    NodeArgs::parse_from([
+fn anvil_args(
+    http_rpc_url: &str,
+    fork_block_number: Option<u64>,
+    options: &ReplayOptions,
+) -> NodeArgs {
+    let code_size_limit = usize::MAX.to_string();
+    let mut args = vec![
         "anvil",
         "--fork-url",
         http_rpc_url,
         "--code-size-limit",
-        usize::MAX.to_string().as_str(),
+        code_size_limit.as_str(),
         "--base-fee",
         "0",
         "--gas-price",
@@ -268,5 +1786,42 @@ fn anvil_args(http_rpc_url: &str) -> NodeArgs {
         "--no-rate-limit",
         "--hardfork",
         "latest",
-    ])
+        // Mine transactions in the order they are sent, like mainnet's
+        // block order, with the post-deploy and synthetic ones first
+        "--order",
+        "fifo",
+    ];
+    let fork_block_number = fork_block_number.map(|n| n.to_string());
+    if let Some(fork_block_number) = &fork_block_number {
+        args.extend(["--fork-block-number", fork_block_number.as_str()]);
+    }
+    let chain_id = options.chain_id.map(|id| id.to_string());
+    if let Some(chain_id) = &chain_id {
+        args.extend(["--chain-id", chain_id.as_str()]);
+    }
+    let port = options.port.map(|port| port.to_string());
+    if let Some(port) = &port {
+        args.extend(["--port", port.as_str()]);
+    }
+    if !options.storage_caching {
+        args.push("--no-storage-caching");
+    }
+    NodeArgs::parse_from(args)
+}
+
+/// Queues a block header for replay. The first header after the block
+/// subscription was re-established also queues the blocks it missed,
+/// since the last header seen before it dropped.
+fn queue_header(pending: &mut VecDeque<u64>, resumed_after: &mut Option<u64>, number: u64) {
+    if let Some(last) = resumed_after.take() {
+        if number > last + 1 {
+            log::info!(
+                "Queueing blocks {}..{} missed while resubscribing",
+                last + 1,
+                number
+            );
+        }
+        pending.extend(last + 1..number);
+    }
+    pending.push_back(number);
 }
@@ -0,0 +1,148 @@
+use std::{fmt, time::Duration};
+
+use super::report::BlockReplayReport;
+
+/// Replay throughput measured over a range of blocks, with the time
+/// spent in each phase of the replay.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BenchReport {
+    /// Number of blocks replayed
+    pub blocks: u64,
+    /// Number of blocks that could not be replayed at all
+    pub blocks_failed: u64,
+    /// Number of transactions replayed successfully on the fork
+    pub replayed: u64,
+    /// Number of transactions that succeeded on mainnet but failed on the fork
+    pub failed_on_fork: u64,
+    /// The total wall-clock time of the replay
+    pub elapsed: Duration,
+    /// The total time spent fetching blocks
+    pub fetch_block: Duration,
+    /// The total time spent fetching receipts
+    pub fetch_receipts: Duration,
+    /// The slowest receipts fetch of a single block
+    pub max_fetch_receipts: Duration,
+    /// The total time spent executing transactions and mining on anvil
+    pub execution: Duration,
+}
+
+impl BenchReport {
+    /// Adds a replayed block to the report.
+    pub fn record(&mut self, report: &BlockReplayReport) {
+        self.blocks += 1;
+        self.replayed += report.replayed;
+        self.failed_on_fork += report.failed_on_fork.len() as u64;
+        self.fetch_block += report.timings.fetch_block;
+        self.fetch_receipts += report.timings.fetch_receipts;
+        self.max_fetch_receipts = self.max_fetch_receipts.max(report.timings.fetch_receipts);
+        self.execution += report.timings.execution;
+    }
+
+    /// Records a block that could not be replayed.
+    pub fn record_block_failure(&mut self) {
+        self.blocks += 1;
+        self.blocks_failed += 1;
+    }
+
+    /// Returns the number of blocks replayed per second.
+    pub fn blocks_per_sec(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.blocks as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Returns tuning hints for the phases that dominate the replay.
+    pub fn hints(&self) -> Vec<&'static str> {
+        let mut hints = Vec::new();
+        let total = self.fetch_block + self.fetch_receipts + self.execution;
+        if total.is_zero() {
+            return hints;
+        }
+        let share = |phase: Duration| phase.as_secs_f64() / total.as_secs_f64();
+        if share(self.fetch_receipts) > 0.5 {
+            hints.push(
+                "Receipts fetching dominates: use an RPC closer to the fork, or one \
+                 that supports batched block receipts",
+            );
+        }
+        if share(self.fetch_block) > 0.5 {
+            hints.push("Block fetching dominates: use an RPC closer to the fork");
+        }
+        if share(self.execution) > 0.5 {
+            hints.push(
+                "Execution dominates: replay fewer transactions (e.g. without --all-txs), \
+                 or warm anvil's fork cache by benchmarking twice",
+            );
+        }
+        hints
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let per_block = |phase: Duration| {
+            let replayed = self.blocks - self.blocks_failed;
+            if replayed == 0 {
+                Duration::ZERO
+            } else {
+                phase / replayed as u32
+            }
+        };
+        writeln!(
+            f,
+            "Replayed {} blocks in {:.2?} ({:.2} blocks/sec)",
+            self.blocks,
+            self.elapsed,
+            self.blocks_per_sec()
+        )?;
+        writeln!(
+            f,
+            "Transactions: {} replayed, {} failed on fork, {} blocks failed",
+            self.replayed, self.failed_on_fork, self.blocks_failed
+        )?;
+        writeln!(f, "Per block:")?;
+        writeln!(
+            f,
+            "  fetch block     {:>10.2?}",
+            per_block(self.fetch_block)
+        )?;
+        writeln!(
+            f,
+            "  fetch receipts  {:>10.2?}  (max {:.2?})",
+            per_block(self.fetch_receipts),
+            self.max_fetch_receipts
+        )?;
+        write!(f, "  execution       {:>10.2?}", per_block(self.execution))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_aggregate_bench_reports() {
+        let mut bench = BenchReport::default();
+        for (receipts, execution) in [(300, 50), (500, 70)] {
+            let mut report = BlockReplayReport::new(17_000_000);
+            report.replayed = 2;
+            report.timings.fetch_block = Duration::from_millis(20);
+            report.timings.fetch_receipts = Duration::from_millis(receipts);
+            report.timings.execution = Duration::from_millis(execution);
+            bench.record(&report);
+        }
+        bench.record_block_failure();
+        bench.elapsed = Duration::from_secs(2);
+
+        assert_eq!(bench.blocks, 3);
+        assert_eq!(bench.replayed, 4);
+        assert_eq!(bench.blocks_per_sec(), 1.5);
+        assert_eq!(bench.max_fetch_receipts, Duration::from_millis(500));
+        assert_eq!(bench.hints().len(), 1);
+        assert!(bench.hints()[0].starts_with("Receipts fetching dominates"));
+        assert!(bench
+            .to_string()
+            .contains("fetch receipts    400.00ms  (max 500.00ms)"));
+    }
+}
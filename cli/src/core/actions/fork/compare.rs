@@ -0,0 +1,196 @@
+use std::{collections::BTreeMap, fmt};
+
+use ethers::types::H256;
+
+use super::report::{BlockReplayReport, ShadowLog};
+
+/// The shadow events of a transaction that differ between the baseline
+/// and the candidate shadow contracts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventDiff {
+    /// The mainnet block number
+    pub block_number: u64,
+    /// The hash of the mainnet transaction
+    pub transaction_hash: H256,
+    /// The logs only the baseline emitted
+    pub baseline_only: Vec<ShadowLog>,
+    /// The logs only the candidate emitted
+    pub candidate_only: Vec<ShadowLog>,
+}
+
+/// The outcome of replaying the same blocks against two versions of the
+/// shadow contracts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompareReport {
+    /// Number of blocks compared
+    pub blocks: u64,
+    /// Number of shadow events the baseline emitted
+    pub baseline_events: u64,
+    /// Number of shadow events the candidate emitted
+    pub candidate_events: u64,
+    /// Number of transactions that failed on the baseline fork
+    pub baseline_failed: u64,
+    /// Number of transactions that failed on the candidate fork
+    pub candidate_failed: u64,
+    /// The transactions whose shadow events differ
+    pub diffs: Vec<EventDiff>,
+}
+
+impl CompareReport {
+    /// Diffs the shadow events of the same blocks replayed against the
+    /// baseline and the candidate.
+    ///
+    /// Events are compared per transaction, by contract, topics and
+    /// data, so events named differently by the two ABIs still match.
+    pub fn new(baseline: &[BlockReplayReport], candidate: &[BlockReplayReport]) -> Self {
+        let mut report = CompareReport {
+            blocks: baseline.len().max(candidate.len()) as u64,
+            ..Default::default()
+        };
+        let mut transactions: BTreeMap<(u64, H256), (Vec<ShadowLog>, Vec<ShadowLog>)> =
+            BTreeMap::new();
+        for block in baseline {
+            report.baseline_events += block.shadow_logs.len() as u64;
+            report.baseline_failed += block.failed_on_fork.len() as u64;
+            for log in &block.shadow_logs {
+                transactions
+                    .entry((block.block_number, log.transaction_hash))
+                    .or_default()
+                    .0
+                    .push(log.clone());
+            }
+        }
+        for block in candidate {
+            report.candidate_events += block.shadow_logs.len() as u64;
+            report.candidate_failed += block.failed_on_fork.len() as u64;
+            for log in &block.shadow_logs {
+                transactions
+                    .entry((block.block_number, log.transaction_hash))
+                    .or_default()
+                    .1
+                    .push(log.clone());
+            }
+        }
+
+        for ((block_number, transaction_hash), (baseline_logs, mut candidate_only)) in transactions
+        {
+            let mut baseline_only = Vec::new();
+            for log in baseline_logs {
+                match candidate_only.iter().position(|other| log.same_as(other)) {
+                    Some(index) => {
+                        candidate_only.remove(index);
+                    }
+                    None => baseline_only.push(log),
+                }
+            }
+            if !baseline_only.is_empty() || !candidate_only.is_empty() {
+                report.diffs.push(EventDiff {
+                    block_number,
+                    transaction_hash,
+                    baseline_only,
+                    candidate_only,
+                });
+            }
+        }
+        report
+    }
+
+    /// Returns whether both versions emitted the same events.
+    pub fn is_identical(&self) -> bool {
+        self.diffs.is_empty()
+    }
+}
+
+impl fmt::Display for EventDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "block={} tx={:?}",
+            self.block_number, self.transaction_hash
+        )?;
+        for log in &self.baseline_only {
+            write!(f, "\n  - {}", log)?;
+        }
+        for log in &self.candidate_only {
+            write!(f, "\n  + {}", log)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CompareReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "blocks={} baseline_events={} candidate_events={} baseline_failed={} candidate_failed={} differing_txs={}",
+            self.blocks,
+            self.baseline_events,
+            self.candidate_events,
+            self.baseline_failed,
+            self.candidate_failed,
+            self.diffs.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Bytes, H160};
+
+    use super::*;
+
+    fn log(tx: u8, event: &str, data: u8) -> ShadowLog {
+        ShadowLog {
+            transaction_hash: H256::repeat_byte(tx),
+            address: H160::repeat_byte(0xaa),
+            event: event.to_owned(),
+            topics: vec![H256::repeat_byte(0x01)],
+            data: Bytes::from(vec![data]),
+        }
+    }
+
+    fn block(block_number: u64, shadow_logs: Vec<ShadowLog>) -> BlockReplayReport {
+        BlockReplayReport {
+            block_number,
+            shadow_logs,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_diff_shadow_events() {
+        let baseline = vec![
+            block(1, vec![log(1, "Swap", 1), log(1, "Swap", 2)]),
+            block(2, vec![log(2, "Swap", 3)]),
+        ];
+        let candidate = vec![
+            // Names don't matter, only the log contents
+            block(1, vec![log(1, "0x0101", 2), log(1, "Swap", 1)]),
+            block(2, vec![log(2, "Swap", 4), log(3, "Swap", 5)]),
+        ];
+
+        let report = CompareReport::new(&baseline, &candidate);
+        assert_eq!(
+            report.diffs,
+            vec![
+                EventDiff {
+                    block_number: 2,
+                    transaction_hash: H256::repeat_byte(2),
+                    baseline_only: vec![log(2, "Swap", 3)],
+                    candidate_only: vec![log(2, "Swap", 4)],
+                },
+                EventDiff {
+                    block_number: 2,
+                    transaction_hash: H256::repeat_byte(3),
+                    baseline_only: vec![],
+                    candidate_only: vec![log(3, "Swap", 5)],
+                },
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "blocks=2 baseline_events=3 candidate_events=4 baseline_failed=0 candidate_failed=0 differing_txs=2"
+        );
+        assert!(CompareReport::new(&baseline, &baseline).is_identical());
+    }
+}
@@ -0,0 +1,304 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use ethers::types::{H160, H256, U256};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::server::auth::{ApiAuth, AuthFailure, Scope};
+
+/// A request to control the running fork, sent by the control server
+/// to the replay loop.
+///
+/// Requests are handled by the replay loop between blocks, so they
+/// never race with a block replay.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControlRequest {
+    /// `shadow_listContracts`: lists the shadow contracts on the fork
+    ListContracts,
+    /// `shadow_reloadContract`: re-reads a shadow contract from the store
+    /// and redeploys its bytecode
+    ReloadContract(H160),
+    /// `shadow_replayTx`: replays a mainnet transaction on the fork, at
+    /// the start of the next replayed block
+    ReplayTx(H256),
+    /// `shadow_status`: returns the replay metrics
+    Status,
+    /// `shadow_impersonate`: lets transactions be sent to the fork from
+    /// an address without its private key
+    Impersonate(H160),
+    /// `shadow_stopImpersonating`: stops impersonating an address
+    StopImpersonating(H160),
+    /// `shadow_fund`: adds wei to the balance of an address
+    Fund(H160, U256),
+}
+
+/// The channel the result of a control request is sent back on.
+pub type ControlReply = oneshot::Sender<Result<Value, String>>;
+
+/// A control request with the channel its result is sent back on.
+pub type ControlMessage = (ControlRequest, ControlReply);
+
+/// A JSON-RPC request
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Vec<Value>,
+}
+
+/// The JSON-RPC error code for unknown methods
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The JSON-RPC error code for invalid parameters
+const INVALID_PARAMS: i64 = -32602;
+/// The JSON-RPC error code for requests that failed on the fork
+const SERVER_ERROR: i64 = -32000;
+/// The JSON-RPC error code for requests without a valid token
+const UNAUTHORIZED: i64 = -32001;
+
+impl ControlRequest {
+    /// Parses a `shadow_*` JSON-RPC method and its parameters.
+    fn parse(method: &str, params: &[Value]) -> Result<Self, (i64, String)> {
+        let param = |i: usize| {
+            params
+                .get(i)
+                .and_then(Value::as_str)
+                .ok_or((INVALID_PARAMS, format!("Missing parameter {}", i)))
+        };
+        let address = |i: usize| {
+            param(i)?
+                .parse::<H160>()
+                .map_err(|e| (INVALID_PARAMS, format!("Invalid address: {}", e)))
+        };
+        match method {
+            "shadow_listContracts" => Ok(ControlRequest::ListContracts),
+            "shadow_reloadContract" => address(0).map(ControlRequest::ReloadContract),
+            "shadow_replayTx" => param(0)?
+                .parse()
+                .map(ControlRequest::ReplayTx)
+                .map_err(|e| (INVALID_PARAMS, format!("Invalid transaction hash: {}", e))),
+            "shadow_status" => Ok(ControlRequest::Status),
+            "shadow_impersonate" => address(0).map(ControlRequest::Impersonate),
+            "shadow_stopImpersonating" => address(0).map(ControlRequest::StopImpersonating),
+            "shadow_fund" => {
+                let amount = U256::from_str_radix(param(1)?.trim_start_matches("0x"), 16)
+                    .map_err(|e| (INVALID_PARAMS, format!("Invalid amount: {}", e)))?;
+                Ok(ControlRequest::Fund(address(0)?, amount))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        }
+    }
+
+    /// Returns the scope a token needs for the request: reading is
+    /// allowed with a read token, changing the fork needs an admin one.
+    pub fn scope(&self) -> Scope {
+        match self {
+            ControlRequest::ListContracts | ControlRequest::Status => Scope::Read,
+            ControlRequest::ReloadContract(_)
+            | ControlRequest::ReplayTx(_)
+            | ControlRequest::Impersonate(_)
+            | ControlRequest::StopImpersonating(_)
+            | ControlRequest::Fund(_, _) => Scope::Admin,
+        }
+    }
+}
+
+/// Serves the `shadow_*` JSON-RPC methods over HTTP, forwarding each
+/// request to the replay loop.
+///
+/// With authentication enabled, requests carry a token in the
+/// `Authorization: Bearer <token>` header, with the scope of the method.
+pub async fn serve(
+    addr: SocketAddr,
+    sender: mpsc::Sender<ControlMessage>,
+    auth: ApiAuth,
+) -> Result<(), hyper::Error> {
+    log::info!("Serving shadow_* RPC on {}", addr);
+    auth.warn_if_open("control", addr);
+    let make_service = make_service_fn(move |_| {
+        let sender = sender.clone();
+        let auth = auth.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle(request, sender.clone(), auth.clone())
+            }))
+        }
+    });
+    Server::bind(&addr).serve(make_service).await
+}
+
+async fn handle(
+    request: Request<Body>,
+    sender: mpsc::Sender<ControlMessage>,
+    auth: ApiAuth,
+) -> Result<Response<Body>, Infallible> {
+    if request.method() != Method::POST {
+        return Ok(Response::builder().status(405).body(Body::empty()).unwrap());
+    }
+    let header = request
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    // Unknown tokens are rejected before the request is parsed
+    if let Err(failure) = auth.authorize_header(header.as_deref(), Scope::Read) {
+        return Ok(auth_response(Value::Null, failure));
+    }
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(e) => {
+            return Ok(rpc_response(
+                Value::Null,
+                Err((SERVER_ERROR, e.to_string())),
+            ))
+        }
+    };
+    let request: RpcRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return Ok(rpc_response(Value::Null, Err((-32700, e.to_string())))),
+    };
+
+    let result = match ControlRequest::parse(&request.method, &request.params) {
+        Ok(control_request) => {
+            if let Err(failure) = auth.authorize_header(header.as_deref(), control_request.scope())
+            {
+                return Ok(auth_response(request.id, failure));
+            }
+            dispatch(control_request, &sender)
+                .await
+                .map_err(|e| (SERVER_ERROR, e))
+        }
+        Err(e) => Err(e),
+    };
+    Ok(rpc_response(request.id, result))
+}
+
+/// Builds the response to a request rejected by authentication, with
+/// the HTTP status of the failure.
+fn auth_response(id: Value, failure: AuthFailure) -> Response<Body> {
+    let mut response = rpc_response(id, Err((UNAUTHORIZED, failure.to_string())));
+    *response.status_mut() = hyper::StatusCode::from_u16(failure.status()).unwrap();
+    response
+}
+
+/// Sends a request to the replay loop and waits for its result.
+async fn dispatch(
+    request: ControlRequest,
+    sender: &mpsc::Sender<ControlMessage>,
+) -> Result<Value, String> {
+    let (reply, result) = oneshot::channel();
+    sender
+        .send((request, reply))
+        .await
+        .map_err(|_| "The fork is shutting down".to_owned())?;
+    result
+        .await
+        .map_err(|_| "The fork dropped the request".to_owned())?
+}
+
+fn rpc_response(id: Value, result: Result<Value, (i64, String)>) -> Response<Body> {
+    let body = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message }
+        }),
+    };
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_requests() {
+        assert_eq!(
+            ControlRequest::parse("shadow_listContracts", &[]),
+            Ok(ControlRequest::ListContracts)
+        );
+        assert_eq!(
+            ControlRequest::parse(
+                "shadow_reloadContract",
+                &[json!("0x7a250d5630b4cf539739df2c5dacb4c659f2488d")]
+            ),
+            Ok(ControlRequest::ReloadContract(
+                "0x7a250d5630b4cf539739df2c5dacb4c659f2488d"
+                    .parse()
+                    .unwrap()
+            ))
+        );
+        assert_eq!(
+            ControlRequest::parse(
+                "shadow_fund",
+                &[
+                    json!("0x7a250d5630b4cf539739df2c5dacb4c659f2488d"),
+                    json!("0xde0b6b3a7640000")
+                ]
+            ),
+            Ok(ControlRequest::Fund(
+                "0x7a250d5630b4cf539739df2c5dacb4c659f2488d"
+                    .parse()
+                    .unwrap(),
+                U256::exp10(18)
+            ))
+        );
+        assert_eq!(
+            ControlRequest::parse("shadow_replayTx", &[]).unwrap_err().0,
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            ControlRequest::parse("shadow_impersonate", &[json!("0x01")])
+                .unwrap_err()
+                .0,
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            ControlRequest::parse("shadow_unknown", &[]).unwrap_err().0,
+            METHOD_NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn admin_scope_is_required_to_change_the_fork() {
+        assert_eq!(ControlRequest::Status.scope(), Scope::Read);
+        assert_eq!(ControlRequest::ListContracts.scope(), Scope::Read);
+        assert_eq!(ControlRequest::ReplayTx(H256::zero()).scope(), Scope::Admin);
+        assert_eq!(
+            ControlRequest::ReloadContract(H160::zero()).scope(),
+            Scope::Admin
+        );
+        assert_eq!(
+            ControlRequest::Impersonate(H160::zero()).scope(),
+            Scope::Admin
+        );
+        assert_eq!(
+            ControlRequest::Fund(H160::zero(), U256::one()).scope(),
+            Scope::Admin
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_dispatch_to_replay_loop() {
+        let (sender, mut receiver) = mpsc::channel::<ControlMessage>(1);
+        tokio::spawn(async move {
+            while let Some((request, reply)) = receiver.recv().await {
+                let _ = reply.send(Ok(json!(format!("{:?}", request))));
+            }
+        });
+        assert_eq!(
+            dispatch(ControlRequest::Status, &sender).await,
+            Ok(json!("Status"))
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+
+use ethers::types::{Log, TransactionReceipt, H160, H256};
+
+/// A way a replayed transaction behaved differently on the fork than
+/// on mainnet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// The transaction succeeded on one chain and reverted on the other
+    Status { mainnet: u64, fork: u64 },
+    /// The transaction used less gas on the fork. Shadow contracts only
+    /// add code, so this means it took a shorter execution path
+    GasUsed { mainnet: u64, fork: u64 },
+    /// The logs differ, ignoring the events only shadow contracts emit
+    Logs {
+        mainnet: usize,
+        fork: usize,
+        /// The index of the first log that differs
+        first_mismatch: usize,
+    },
+}
+
+impl fmt::Display for DivergenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DivergenceKind::Status { mainnet, fork } => {
+                write!(f, "status mainnet={} fork={}", mainnet, fork)
+            }
+            DivergenceKind::GasUsed { mainnet, fork } => {
+                write!(f, "gas_used mainnet={} fork={}", mainnet, fork)
+            }
+            DivergenceKind::Logs {
+                mainnet,
+                fork,
+                first_mismatch,
+            } => write!(
+                f,
+                "logs mainnet={} fork={} first_mismatch={}",
+                mainnet, fork, first_mismatch
+            ),
+        }
+    }
+}
+
+/// A replayed transaction whose fork receipt doesn't match its mainnet
+/// receipt.
+///
+/// Divergences usually mean the fork state differs from mainnet where
+/// the transaction reads it, e.g. because a skipped transaction earlier
+/// in the block changed a balance or a price the replayed one depends on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The hash of the mainnet transaction
+    pub tx_hash: H256,
+    /// How the fork receipt differs
+    pub kind: DivergenceKind,
+}
+
+/// Compares the fork receipt of a replayed transaction to its mainnet
+/// receipt.
+///
+/// Logs are compared by address and topics. Logs on the fork from a
+/// shadowed address whose event the original contract didn't emit in
+/// the same transaction are shadow-only events, and are ignored.
+pub fn compare_receipts(
+    mainnet: &TransactionReceipt,
+    fork: &TransactionReceipt,
+    is_shadowed: impl Fn(&H160) -> bool,
+) -> Vec<DivergenceKind> {
+    let mut divergences = Vec::new();
+
+    let mainnet_status = mainnet.status.unwrap_or_default().as_u64();
+    let fork_status = fork.status.unwrap_or_default().as_u64();
+    if mainnet_status != fork_status {
+        divergences.push(DivergenceKind::Status {
+            mainnet: mainnet_status,
+            fork: fork_status,
+        });
+    }
+
+    let mainnet_gas = mainnet.gas_used.unwrap_or_default().as_u64();
+    let fork_gas = fork.gas_used.unwrap_or_default().as_u64();
+    if fork_gas < mainnet_gas {
+        divergences.push(DivergenceKind::GasUsed {
+            mainnet: mainnet_gas,
+            fork: fork_gas,
+        });
+    }
+
+    let emitted_on_mainnet = |log: &Log| {
+        mainnet
+            .logs
+            .iter()
+            .any(|other| other.address == log.address && other.topics.first() == log.topics.first())
+    };
+    let fork_logs = fork
+        .logs
+        .iter()
+        .filter(|log| !is_shadowed(&log.address) || emitted_on_mainnet(log))
+        .collect::<Vec<_>>();
+    let first_mismatch = mainnet
+        .logs
+        .iter()
+        .zip(&fork_logs)
+        .position(|(a, b)| a.address != b.address || a.topics != b.topics)
+        .or_else(|| {
+            (mainnet.logs.len() != fork_logs.len())
+                .then_some(mainnet.logs.len().min(fork_logs.len()))
+        });
+    if let Some(first_mismatch) = first_mismatch {
+        divergences.push(DivergenceKind::Logs {
+            mainnet: mainnet.logs.len(),
+            fork: fork_logs.len(),
+            first_mismatch,
+        });
+    }
+
+    divergences
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::U64;
+
+    use super::*;
+
+    fn log(address: H160, topic0: u64) -> Log {
+        Log {
+            address,
+            topics: vec![H256::from_low_u64_be(topic0)],
+            ..Default::default()
+        }
+    }
+
+    fn receipt(status: u64, gas_used: u64, logs: Vec<Log>) -> TransactionReceipt {
+        TransactionReceipt {
+            status: Some(U64::from(status)),
+            gas_used: Some(gas_used.into()),
+            logs,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ignores_shadow_only_events_and_extra_gas() {
+        let shadowed = H160::from_low_u64_be(1);
+        let other = H160::from_low_u64_be(2);
+        let mainnet = receipt(1, 50_000, vec![log(shadowed, 10), log(other, 20)]);
+        let fork = receipt(
+            1,
+            60_000,
+            vec![log(shadowed, 10), log(shadowed, 11), log(other, 20)],
+        );
+
+        assert!(compare_receipts(&mainnet, &fork, |a| *a == shadowed).is_empty());
+    }
+
+    #[test]
+    fn flags_divergent_receipts() {
+        let shadowed = H160::from_low_u64_be(1);
+        let other = H160::from_low_u64_be(2);
+        let mainnet = receipt(1, 50_000, vec![log(shadowed, 10), log(other, 20)]);
+        let fork = receipt(0, 21_000, vec![log(shadowed, 10)]);
+
+        assert_eq!(
+            compare_receipts(&mainnet, &fork, |a| *a == shadowed),
+            vec![
+                DivergenceKind::Status {
+                    mainnet: 1,
+                    fork: 0
+                },
+                DivergenceKind::GasUsed {
+                    mainnet: 50_000,
+                    fork: 21_000
+                },
+                DivergenceKind::Logs {
+                    mainnet: 2,
+                    fork: 1,
+                    first_mismatch: 1
+                },
+            ]
+        );
+    }
+}
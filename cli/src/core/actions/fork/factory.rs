@@ -0,0 +1,154 @@
+use alloy_json_abi::JsonAbi;
+use clap::ValueEnum;
+use ethers::types::{Action, Bytes, Res, Trace, H160};
+
+/// A shadowed factory whose children are shadowed as they are created
+/// on the fork.
+#[derive(Clone, Debug)]
+pub struct TrackedFactory {
+    /// The address of the factory
+    pub factory: H160,
+
+    /// The file name of the child contract's artifact
+    pub file_name: String,
+
+    /// The name of the child contract
+    pub contract_name: String,
+
+    /// The ABI of the child contract, used to decode call traces
+    pub abi: JsonAbi,
+
+    /// The runtime bytecode children are given, if any. Otherwise
+    /// children keep the code the shadowed factory deployed for them.
+    pub runtime_bytecode: Option<String>,
+}
+
+/// Controls which code children of a tracked factory run on the fork.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ChildCode {
+    /// Keep the code the shadowed factory deployed.
+    ///
+    /// If the shadow factory embeds the shadow child's creation code,
+    /// children are shadowed with their constructor and immutables
+    /// intact.
+    #[default]
+    Factory,
+    /// Replace the code with the child artifact's deployed bytecode.
+    ///
+    /// Immutables are not filled in, so this is only suitable for
+    /// children without immutables.
+    Artifact,
+}
+
+/// A contract created by a tracked factory in a replayed transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CreatedChild {
+    /// The factory that created the child
+    pub factory: H160,
+
+    /// The address of the child
+    pub address: H160,
+
+    /// The runtime code the factory deployed
+    pub code: Bytes,
+}
+
+/// Returns the contracts the given factories created, in the order of
+/// the call traces. Creations that failed are ignored.
+pub fn find_children(traces: &[Trace], factories: &[H160]) -> Vec<CreatedChild> {
+    traces
+        .iter()
+        .filter(|trace| trace.error.is_none())
+        .filter_map(|trace| match (&trace.action, &trace.result) {
+            (Action::Create(create), Some(Res::Create(result)))
+                if factories.contains(&create.from) =>
+            {
+                Some(CreatedChild {
+                    factory: create.from,
+                    address: result.address,
+                    code: result.code.clone(),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn can_find_children() {
+        let factory = H160::from_str("0x1f98431c8ad98523631ae4a59f267346ea31f984").unwrap();
+        let traces: Vec<Trace> = serde_json::from_str(
+            r#"[
+            {
+                "action": {
+                    "callType": "call",
+                    "from": "0x73ede13ab9c28bc4302e94c1d1e7f755988a9158",
+                    "to": "0x1f98431c8ad98523631ae4a59f267346ea31f984",
+                    "gas": "0x0",
+                    "input": "0xa1671295",
+                    "value": "0x0"
+                },
+                "result": { "gasUsed": "0x0", "output": "0x" },
+                "subtraces": 2,
+                "traceAddress": [],
+                "blockNumber": 0,
+                "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "type": "call"
+            },
+            {
+                "action": {
+                    "from": "0x1f98431c8ad98523631ae4a59f267346ea31f984",
+                    "gas": "0x0",
+                    "init": "0x6080",
+                    "value": "0x0"
+                },
+                "result": {
+                    "gasUsed": "0x0",
+                    "code": "0x6001",
+                    "address": "0x8ad599c3a0ff1de082011efddc58f1908eb6e6d8"
+                },
+                "subtraces": 0,
+                "traceAddress": [0],
+                "blockNumber": 0,
+                "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "type": "create"
+            },
+            {
+                "action": {
+                    "from": "0x73ede13ab9c28bc4302e94c1d1e7f755988a9158",
+                    "gas": "0x0",
+                    "init": "0x6080",
+                    "value": "0x0"
+                },
+                "result": {
+                    "gasUsed": "0x0",
+                    "code": "0x6002",
+                    "address": "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640"
+                },
+                "subtraces": 0,
+                "traceAddress": [1],
+                "blockNumber": 0,
+                "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "type": "create"
+            }
+        ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_children(&traces, &[factory]),
+            vec![CreatedChild {
+                factory,
+                address: H160::from_str("0x8ad599c3a0ff1de082011efddc58f1908eb6e6d8").unwrap(),
+                code: Bytes::from(vec![0x60, 0x01]),
+            }]
+        );
+        assert!(find_children(&traces, &[]).is_empty());
+    }
+}
@@ -0,0 +1,275 @@
+use std::time::Duration;
+
+use clap::ValueEnum;
+use ethers::types::{Transaction, U256};
+
+use crate::providers::DEFAULT_RPC_TIMEOUT;
+
+/// The default factor the gas limit of impersonated replays is raised
+/// by, since shadow contracts add storage writes and logs
+pub const DEFAULT_GAS_HEADROOM: f64 = 1.5;
+
+/// The default number of times a receipt that isn't available yet is
+/// fetched again
+pub const DEFAULT_RECEIPT_RETRIES: u32 = 3;
+
+/// Options that control how mainnet blocks are replayed on the fork.
+#[derive(Clone, Debug)]
+pub struct ReplayOptions {
+    /// Whether to replay all transactions from mainnet
+    pub all_txs: bool,
+
+    /// How transaction fees are handled during replay
+    pub fee_mode: FeeMode,
+
+    /// Whether to mine empty blocks so fork block numbers match mainnet
+    pub mirror_block_numbers: bool,
+
+    /// Whether to give fork blocks the same timestamps as mainnet
+    pub mirror_timestamps: bool,
+
+    /// Whether to copy the mainnet block's fee recipient and gas limit
+    pub mirror_block_env: bool,
+
+    /// Whether to print the decoded call tree of each replayed transaction
+    pub trace: bool,
+
+    /// How long an upstream RPC call may take before it is failed
+    pub rpc_timeout: Duration,
+
+    /// How many times receipts that aren't available yet, or fail to
+    /// fetch, are fetched again before their transactions are skipped
+    pub receipt_retries: u32,
+
+    /// How long to wait before the first retry of missing receipts,
+    /// growing by as much with each retry
+    pub receipt_retry_delay: Duration,
+
+    /// How many blocks the fork may fall behind the chain head before
+    /// the catch-up strategy is applied, if any
+    pub max_lag_blocks: Option<u64>,
+
+    /// How blocks are replayed while the fork lags too far behind
+    pub catch_up: CatchUpStrategy,
+
+    /// Whether to print a summary of each replayed block
+    pub block_summaries: bool,
+
+    /// The chain id of the fork, if it differs from the upstream chain's
+    pub chain_id: Option<u64>,
+
+    /// How transactions are sent to the fork
+    pub replay_mode: ReplayMode,
+
+    /// The factor the gas limit of transactions replayed from their
+    /// impersonated senders is multiplied by, as headroom for the extra
+    /// gas shadow contracts use
+    pub gas_multiplier: f64,
+
+    /// Whether anvil caches the forked state on disk. Disabled while
+    /// recording or playing back a cassette, so every state read goes
+    /// through it
+    pub storage_caching: bool,
+
+    /// The port anvil serves the fork on, if not anvil's default 8545.
+    /// Port 0 picks a free port, e.g. to run several forks at once
+    pub port: Option<u16>,
+
+    /// Whether to compare the fork receipt of each replayed transaction
+    /// to its mainnet receipt, and report the divergences per block
+    pub determinism_report: bool,
+
+    /// Whether to copy the mainnet state a transaction read onto the
+    /// fork before replaying it, when transactions before it in the
+    /// block were skipped
+    pub prestage_state: bool,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            all_txs: false,
+            fee_mode: FeeMode::default(),
+            mirror_block_numbers: false,
+            mirror_timestamps: true,
+            mirror_block_env: false,
+            trace: false,
+            rpc_timeout: DEFAULT_RPC_TIMEOUT,
+            receipt_retries: DEFAULT_RECEIPT_RETRIES,
+            receipt_retry_delay: Duration::from_millis(500),
+            max_lag_blocks: None,
+            catch_up: CatchUpStrategy::default(),
+            block_summaries: false,
+            chain_id: None,
+            replay_mode: ReplayMode::default(),
+            gas_multiplier: DEFAULT_GAS_HEADROOM,
+            storage_caching: true,
+            port: None,
+            determinism_report: false,
+            prestage_state: false,
+        }
+    }
+}
+
+impl ReplayOptions {
+    /// Returns whether a transaction can be replayed as signed.
+    ///
+    /// In [`ReplayMode::Impersonated`], no transaction is.
+    ///
+    /// Signatures commit to the chain id (EIP-155), so when the fork's
+    /// chain id is overridden, transactions signed for another chain
+    /// are replayed unsigned from their impersonated sender instead.
+    /// Legacy transactions without a chain id replay on any chain.
+    pub fn replays_signed(&self, tx: &Transaction) -> bool {
+        if self.replay_mode == ReplayMode::Impersonated {
+            return false;
+        }
+        match (self.chain_id, tx.chain_id) {
+            (Some(chain_id), Some(tx_chain_id)) => U256::from(chain_id) == tx_chain_id,
+            _ => true,
+        }
+    }
+
+    /// Returns how a block is replayed, given how many blocks the fork
+    /// lags behind the chain head.
+    ///
+    /// With `all_txs`, [`CatchUpStrategy::LogsPrefilter`] falls back to
+    /// [`CatchUpStrategy::SkipBlocks`], so the blocks that are replayed
+    /// keep all their transactions.
+    pub fn catch_up_strategy(&self, lag: u64) -> CatchUpStrategy {
+        match self.max_lag_blocks {
+            Some(max_lag_blocks) if lag > max_lag_blocks => match self.catch_up {
+                CatchUpStrategy::LogsPrefilter if self.all_txs => CatchUpStrategy::SkipBlocks,
+                strategy => strategy,
+            },
+            _ => CatchUpStrategy::Replay,
+        }
+    }
+}
+
+/// Controls how the fork handles the base fee and gas prices
+/// of replayed transactions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum FeeMode {
+    /// Replay every block with a zero base fee.
+    ///
+    /// This is the cheapest mode: senders only need to cover the
+    /// transferred value, but `block.basefee` and `tx.gasprice`
+    /// will not match mainnet.
+    Zero,
+    /// Reproduce the original block's base fee.
+    ///
+    /// Replayed transactions keep their signed fee fields, so
+    /// `block.basefee` and `tx.gasprice` observe the same values as
+    /// on mainnet. Senders are funded to cover the maximum fee.
+    ///
+    /// Access lists (EIP-2930) are preserved as part of the raw
+    /// transaction. Blob fees (EIP-4844) are not supported by the
+    /// embedded anvil version.
+    #[default]
+    Mainnet,
+}
+
+/// Controls how replayed transactions are sent to the fork.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ReplayMode {
+    /// Send the signed mainnet transaction as raw RLP.
+    ///
+    /// The transaction is replayed exactly, but can't be modified
+    /// without invalidating its signature.
+    #[default]
+    Raw,
+    /// Rebuild the transaction unsigned and send it from its
+    /// impersonated sender.
+    ///
+    /// The gas limit can be raised for shadow code that uses more gas,
+    /// and the calldata rewritten. The sender's nonce on the fork is
+    /// used, and the transaction gets a different hash than on mainnet.
+    Impersonated,
+}
+
+/// Controls how blocks are replayed while the fork lags more than
+/// `max_lag_blocks` behind the chain head.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum CatchUpStrategy {
+    /// Keep replaying every block as usual, the lag is unbounded.
+    #[default]
+    Replay,
+    /// Skip blocks without any transaction to a shadowed contract
+    /// entirely, without fetching their receipts or mining them.
+    SkipBlocks,
+    /// Only replay the transactions that emitted a log from a shadowed
+    /// contract, found with a single `eth_getLogs` query per block.
+    ///
+    /// Transactions to shadowed contracts that emit no logs on mainnet
+    /// are not replayed while catching up.
+    LogsPrefilter,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_decide_signed_replays() {
+        let tx = Transaction {
+            chain_id: Some(U256::from(1)),
+            ..Default::default()
+        };
+        let legacy = Transaction::default();
+
+        let upstream = ReplayOptions::default();
+        assert!(upstream.replays_signed(&tx));
+
+        let overridden = ReplayOptions {
+            chain_id: Some(31337),
+            ..Default::default()
+        };
+        assert!(!overridden.replays_signed(&tx));
+        assert!(overridden.replays_signed(&legacy));
+
+        let same = ReplayOptions {
+            chain_id: Some(1),
+            ..Default::default()
+        };
+        assert!(same.replays_signed(&tx));
+
+        let impersonated = ReplayOptions {
+            replay_mode: ReplayMode::Impersonated,
+            ..Default::default()
+        };
+        assert!(!impersonated.replays_signed(&legacy));
+    }
+
+    #[test]
+    fn can_pick_catch_up_strategies() {
+        let unbounded = ReplayOptions {
+            catch_up: CatchUpStrategy::SkipBlocks,
+            ..Default::default()
+        };
+        assert_eq!(unbounded.catch_up_strategy(1_000), CatchUpStrategy::Replay);
+
+        let bounded = ReplayOptions {
+            max_lag_blocks: Some(5),
+            catch_up: CatchUpStrategy::LogsPrefilter,
+            ..Default::default()
+        };
+        assert_eq!(bounded.catch_up_strategy(5), CatchUpStrategy::Replay);
+        assert_eq!(bounded.catch_up_strategy(6), CatchUpStrategy::LogsPrefilter);
+
+        // Prefiltering would drop the transactions --all-txs replays
+        let all_txs = ReplayOptions {
+            all_txs: true,
+            ..bounded
+        };
+        assert_eq!(all_txs.catch_up_strategy(6), CatchUpStrategy::SkipBlocks);
+        let skip_blocks = ReplayOptions {
+            catch_up: CatchUpStrategy::SkipBlocks,
+            ..all_txs
+        };
+        assert_eq!(
+            skip_blocks.catch_up_strategy(6),
+            CatchUpStrategy::SkipBlocks
+        );
+    }
+}
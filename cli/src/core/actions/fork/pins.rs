@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use ethers::types::{H160, H256};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    prestage::PreState,
+    rewrite::{parse_address, parse_word},
+};
+use crate::config::StatePin;
+
+/// A contract whose storage on the fork is refreshed from mainnet after
+/// every replayed block, e.g. a price oracle whose update transactions
+/// aren't replayed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PinnedContract {
+    /// A name to refer to the pin in logs
+    pub name: Option<String>,
+    /// The pinned contract
+    pub address: H160,
+    /// The storage slots refreshed with `eth_getStorageAt`. When empty,
+    /// every slot mainnet changed in the block is refreshed instead.
+    pub slots: Vec<H256>,
+}
+
+impl PinnedContract {
+    /// Returns the name of the pin, or its address.
+    pub fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("{:?}", self.address))
+    }
+}
+
+impl TryFrom<&StatePin> for PinnedContract {
+    type Error = String;
+
+    fn try_from(pin: &StatePin) -> Result<Self, String> {
+        Ok(Self {
+            name: pin.name.clone(),
+            address: parse_address(&pin.address)?,
+            slots: pin
+                .slots
+                .iter()
+                .map(|slot| parse_word(slot).map(H256))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// The state changes of a transaction, as returned by the
+/// `prestateTracer` of `debug_traceBlockByNumber` in diff mode.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(super) struct TransactionDiff {
+    pub result: StateDiff,
+}
+
+/// The accounts a transaction changed, before and after it ran.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(super) struct StateDiff {
+    #[serde(default)]
+    pub pre: PreState,
+    #[serde(default)]
+    pub post: PreState,
+}
+
+/// Returns the final value of every storage slot of a contract that the
+/// transactions of a block changed.
+///
+/// Slots cleared by a transaction are left out of its post state, so
+/// they are set back to zero.
+pub(super) fn changed_slots(diffs: &[TransactionDiff], address: H160) -> BTreeMap<H256, H256> {
+    let mut slots = BTreeMap::new();
+    for diff in diffs {
+        if let Some(pre) = diff.result.pre.get(&address) {
+            for slot in pre.storage.keys() {
+                slots.insert(*slot, H256::zero());
+            }
+        }
+        if let Some(post) = diff.result.post.get(&address) {
+            slots.extend(post.storage.clone());
+        }
+    }
+    slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_pins() {
+        let pin = StatePin {
+            name: Some("ETH/USD".to_owned()),
+            address: "0x5f4ec3df9cbd43714fe2740f5e3616155c5b8419".to_owned(),
+            slots: vec!["2".to_owned()],
+        };
+        let pinned = PinnedContract::try_from(&pin).unwrap();
+        assert_eq!(pinned.slots, vec![H256::from_low_u64_be(2)]);
+        assert_eq!(pinned.label(), "ETH/USD");
+
+        let pin = StatePin {
+            address: "oracle".to_owned(),
+            ..pin
+        };
+        assert!(PinnedContract::try_from(&pin).is_err());
+    }
+
+    #[test]
+    fn can_collect_changed_slots() {
+        let oracle = "0x5f4ec3df9cbd43714fe2740f5e3616155c5b8419";
+        let diffs: Vec<TransactionDiff> = serde_json::from_str(&format!(
+            r#"[
+                {{ "txHash": "0x01", "result": {{
+                    "pre": {{ "{oracle}": {{ "storage": {{
+                        "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000005",
+                        "0x0000000000000000000000000000000000000000000000000000000000000002": "0x0000000000000000000000000000000000000000000000000000000000000007"
+                    }} }} }},
+                    "post": {{ "{oracle}": {{ "storage": {{
+                        "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000006"
+                    }} }} }}
+                }} }},
+                {{ "txHash": "0x02", "result": {{
+                    "pre": {{}},
+                    "post": {{ "{oracle}": {{ "storage": {{
+                        "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000008"
+                    }} }} }}
+                }} }}
+            ]"#
+        ))
+        .unwrap();
+
+        let slots = changed_slots(&diffs, oracle.parse().unwrap());
+        assert_eq!(
+            slots,
+            BTreeMap::from([
+                (H256::from_low_u64_be(1), H256::from_low_u64_be(8)),
+                (H256::from_low_u64_be(2), H256::zero()),
+            ])
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use ethers::{
+    signers::LocalWallet,
+    types::{Bytes, H160, U256},
+};
+
+use crate::{config::PostDeployCall, decode::encode_function_call};
+
+/// The gas limit of post-deploy transactions without one. The fork has
+/// no block gas limit, and gas is free.
+pub const DEFAULT_POST_DEPLOY_GAS: u64 = 10_000_000;
+
+/// Transactions a local signer sends once the shadow contracts are
+/// deployed on the fork, e.g. to call shadow-only admin setters.
+pub struct PostDeploy {
+    /// The signer the transactions are sent from
+    pub signer: LocalWallet,
+    /// The transactions, in order
+    pub transactions: Vec<PostDeployTransaction>,
+}
+
+/// A transaction sent by the post-deploy signer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PostDeployTransaction {
+    /// A name to refer to the transaction in logs
+    pub name: Option<String>,
+    /// The recipient
+    pub to: H160,
+    /// The value sent
+    pub value: U256,
+    /// The calldata
+    pub data: Bytes,
+    /// The gas limit, [`DEFAULT_POST_DEPLOY_GAS`] if not set
+    pub gas: Option<u64>,
+}
+
+impl PostDeployTransaction {
+    /// Returns the name of the transaction, or its position in the
+    /// post-deploy section.
+    pub fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("transaction {}", index))
+    }
+}
+
+impl TryFrom<&PostDeployCall> for PostDeployTransaction {
+    type Error = String;
+
+    fn try_from(call: &PostDeployCall) -> Result<Self, String> {
+        let data = match (&call.signature, &call.data) {
+            (Some(signature), None) => encode_function_call(signature, &call.args)?,
+            (None, Some(data)) => hex::decode(data.trim().trim_start_matches("0x"))
+                .map_err(|e| format!("Invalid data {}: {}", data, e))?
+                .into(),
+            (None, None) => Bytes::new(),
+            (Some(_), Some(_)) => {
+                return Err("Set either a signature and args, or data".to_owned());
+            }
+        };
+        Ok(Self {
+            name: call.name.clone(),
+            to: call.to,
+            value: call.value,
+            data,
+            gas: call.gas,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_encode_post_deploy_calls() {
+        let call = PostDeployCall {
+            name: Some("set fee".to_owned()),
+            to: "0x1f98431c8ad98523631ae4a59f267346ea31f984"
+                .parse()
+                .unwrap(),
+            signature: Some("setFee(uint24,address)".to_owned()),
+            args: vec![
+                "500".to_owned(),
+                "0x00000000000000000000000000000000000000ff".to_owned(),
+            ],
+            ..Default::default()
+        };
+        let tx = PostDeployTransaction::try_from(&call).unwrap();
+        let mut expected = ethers::utils::id("setFee(uint24,address)").to_vec();
+        expected.extend(ethers::abi::encode(&[
+            ethers::abi::Token::Uint(500.into()),
+            ethers::abi::Token::Address(H160::from_low_u64_be(0xff)),
+        ]));
+        assert_eq!(tx.data, Bytes::from(expected));
+        assert_eq!(tx.label(0), "set fee");
+
+        let call: PostDeployCall = serde_json::from_str(
+            r#"{
+                "to": "0x1f98431c8ad98523631ae4a59f267346ea31f984",
+                "data": "0x",
+                "value": "0xde0b6b3a7640000"
+            }"#,
+        )
+        .unwrap();
+        let tx = PostDeployTransaction::try_from(&call).unwrap();
+        assert_eq!(tx.value, ethers::utils::parse_ether(1).unwrap());
+
+        let call = PostDeployCall {
+            to: "0x1f98431c8ad98523631ae4a59f267346ea31f984"
+                .parse()
+                .unwrap(),
+            signature: Some("setFee(uint24)".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            PostDeployTransaction::try_from(&call).unwrap_err(),
+            "setFee(uint24) takes 1 arguments, got 0"
+        );
+    }
+}
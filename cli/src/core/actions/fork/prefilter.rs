@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+
+use ethers::types::{Transaction, H256};
+
+use super::CatchUpStrategy;
+
+/// Narrows down the transactions of a block replayed while the fork
+/// catches up with the chain head.
+///
+/// `logged` holds the transactions that emitted a log from a shadow
+/// contract, which [`CatchUpStrategy::LogsPrefilter`] keeps.
+pub(super) fn catch_up(
+    strategy: CatchUpStrategy,
+    transactions: &mut Vec<Transaction>,
+    is_sent_to_shadow: impl Fn(&Transaction) -> bool,
+    logged: Option<&HashSet<H256>>,
+) {
+    match (strategy, logged) {
+        (CatchUpStrategy::SkipBlocks, _) => {
+            if !transactions.iter().any(is_sent_to_shadow) {
+                transactions.clear();
+            }
+        }
+        (CatchUpStrategy::LogsPrefilter, Some(logged)) => {
+            transactions.retain(|tx| logged.contains(&tx.hash));
+        }
+        _ => {}
+    }
+}
+
+/// Splits the transactions that may be replayed by whether they are known
+/// to have succeeded on mainnet.
+///
+/// Logs are discarded when a transaction reverts, so a transaction that
+/// emitted a log from a shadow contract must have succeeded. The receipts
+/// of the other transactions are still needed to tell.
+pub(super) fn split_by_logs<'a>(
+    candidates: impl IntoIterator<Item = &'a Transaction>,
+    logged: &HashSet<H256>,
+) -> (HashSet<H256>, Vec<Transaction>) {
+    let mut succeeded = HashSet::new();
+    let mut unknown = Vec::new();
+    for tx in candidates {
+        if logged.contains(&tx.hash) {
+            succeeded.insert(tx.hash);
+        } else {
+            unknown.push(tx.clone());
+        }
+    }
+    (succeeded, unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::H160;
+
+    use super::*;
+
+    #[test]
+    fn can_narrow_blocks_while_catching_up() {
+        let shadow = H160::repeat_byte(1);
+        let txs = (1..=3)
+            .map(|i| Transaction {
+                hash: H256::from_low_u64_be(i),
+                to: Some(H160::repeat_byte(i as u8)),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let is_sent_to_shadow = |tx: &Transaction| tx.to == Some(shadow);
+        let logged = HashSet::from([H256::from_low_u64_be(3)]);
+        let narrow = |strategy, txs: &[Transaction]| {
+            let mut txs = txs.to_vec();
+            catch_up(strategy, &mut txs, is_sent_to_shadow, Some(&logged));
+            txs.iter().map(|tx| tx.hash).collect::<Vec<_>>()
+        };
+
+        let all = txs.iter().map(|tx| tx.hash).collect::<Vec<_>>();
+        assert_eq!(narrow(CatchUpStrategy::Replay, &txs), all);
+        // The block has a transaction to a shadow contract, so it's kept whole
+        assert_eq!(narrow(CatchUpStrategy::SkipBlocks, &txs), all);
+        assert!(narrow(CatchUpStrategy::SkipBlocks, &txs[1..]).is_empty());
+        assert_eq!(
+            narrow(CatchUpStrategy::LogsPrefilter, &txs),
+            vec![H256::from_low_u64_be(3)]
+        );
+    }
+
+    #[test]
+    fn can_split_by_logs() {
+        let txs = (1..=3)
+            .map(|i| Transaction {
+                hash: H256::from_low_u64_be(i),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let logged = HashSet::from([H256::from_low_u64_be(2), H256::from_low_u64_be(4)]);
+
+        let (succeeded, unknown) = split_by_logs(&txs, &logged);
+        assert_eq!(succeeded, HashSet::from([H256::from_low_u64_be(2)]));
+        assert_eq!(
+            unknown.iter().map(|tx| tx.hash).collect::<Vec<_>>(),
+            vec![H256::from_low_u64_be(1), H256::from_low_u64_be(3)]
+        );
+    }
+}
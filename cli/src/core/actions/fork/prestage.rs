@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use anvil::eth::{error::BlockchainError, EthApi};
+use ethers::types::{H160, H256, U256};
+use futures_util::future::try_join_all;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use super::ReplayDecision;
+
+/// The state a mainnet transaction read, as returned by the
+/// `prestateTracer`, keyed by account.
+pub(super) type PreState = BTreeMap<H160, AccountState>;
+
+/// The state a transaction of a mainnet block read, as returned by the
+/// `prestateTracer` of `debug_traceBlockByNumber`, in block order.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(super) struct TransactionPreState {
+    pub result: PreState,
+}
+
+/// The state of an account before a mainnet transaction ran.
+///
+/// The account's code isn't kept: the fork must keep running the
+/// shadow contracts' code.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct AccountState {
+    pub balance: Option<U256>,
+    #[serde(default, deserialize_with = "deserialize_nonce")]
+    pub nonce: Option<U256>,
+    #[serde(default)]
+    pub storage: BTreeMap<H256, H256>,
+}
+
+/// Nonces are numbers in geth's output, and hex strings in some other
+/// clients'.
+fn deserialize_nonce<'de, D>(deserializer: D) -> Result<Option<U256>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(nonce)) => nonce
+            .as_u64()
+            .map(|nonce| Some(U256::from(nonce)))
+            .ok_or_else(|| serde::de::Error::custom(format!("Invalid nonce: {}", nonce))),
+        Some(value) => serde_json::from_value(value)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Returns the indexes of the transactions of a block whose state is
+/// pre-staged: the replayed ones that follow a skipped transaction.
+pub(super) fn staged(decisions: &[ReplayDecision]) -> Vec<usize> {
+    let mut skipped = false;
+    let mut staged = Vec::new();
+    for (index, decision) in decisions.iter().enumerate() {
+        if *decision == ReplayDecision::Replay {
+            if skipped {
+                staged.push(index);
+            }
+        } else {
+            skipped = true;
+        }
+    }
+    staged
+}
+
+/// Copies the mainnet state a transaction read onto the fork.
+///
+/// The balances, nonces and storage slots in the prestate are the
+/// values mainnet had right before the transaction, so they include
+/// the changes of the transactions before it in the block that weren't
+/// replayed, e.g. approvals or oracle updates.
+///
+/// The storage of shadowed contracts is left alone, as the shadow code
+/// writes slots mainnet never did. The slots of an account are written
+/// together.
+///
+/// Returns the number of storage slots written.
+pub(super) async fn apply(
+    api: &EthApi,
+    state: &PreState,
+    is_shadowed: impl Fn(&H160) -> bool,
+) -> Result<usize, BlockchainError> {
+    let mut slots = 0;
+    for (address, account) in state {
+        if let Some(balance) = account.balance {
+            api.anvil_set_balance(*address, balance).await?;
+        }
+        if let Some(nonce) = account.nonce {
+            api.anvil_set_nonce(*address, nonce).await?;
+        }
+        if is_shadowed(address) {
+            continue;
+        }
+        try_join_all(account.storage.iter().map(|(slot, value)| {
+            api.anvil_set_storage_at(*address, U256::from(slot.as_bytes()), *value)
+        }))
+        .await?;
+        slots += account.storage.len();
+    }
+    Ok(slots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_prestate() {
+        let state: PreState = serde_json::from_str(
+            r#"{
+                "0x35a9f94af726f07b5162df7e828cc9dc8439e7d0": {
+                    "balance": "0x7a48734599f7284",
+                    "nonce": 1133
+                },
+                "0xc8ba32cab1757528daf49033e3673fae77dcf05d": {
+                    "balance": "0x0",
+                    "code": "0x6080",
+                    "nonce": "0x1",
+                    "storage": {
+                        "0x0000000000000000000000000000000000000000000000000000000000000018": "0x00000000000000000000000000000000000000000000000000000000000003e8"
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let sender: H160 = "0x35a9f94af726f07b5162df7e828cc9dc8439e7d0"
+            .parse()
+            .unwrap();
+        let sender = &state[&sender];
+        assert_eq!(sender.nonce, Some(U256::from(1133)));
+        assert!(sender.storage.is_empty());
+        let contract: H160 = "0xc8ba32cab1757528daf49033e3673fae77dcf05d"
+            .parse()
+            .unwrap();
+        let contract = &state[&contract];
+        assert_eq!(contract.nonce, Some(U256::one()));
+        assert_eq!(
+            contract.storage[&H256::from_low_u64_be(0x18)],
+            H256::from_low_u64_be(1000)
+        );
+    }
+
+    #[test]
+    fn stages_transactions_after_skipped_ones() {
+        use ReplayDecision::*;
+
+        assert!(staged(&[Replay, Replay]).is_empty());
+        assert_eq!(
+            staged(&[Replay, SkipNotShadowed, Replay, SkipFailedOnMainnet, Replay]),
+            vec![2, 4]
+        );
+        assert_eq!(staged(&[SkipMissingReceipt, Replay, Replay]), vec![1, 2]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_apply_prestate() {
+        let (api, _handle) = anvil::spawn(anvil::NodeConfig::test()).await;
+        let token = H160::repeat_byte(1);
+        let shadowed = H160::repeat_byte(2);
+        let slot = H256::from_low_u64_be(3);
+        let account = AccountState {
+            balance: Some(U256::from(1000)),
+            nonce: Some(U256::from(7)),
+            storage: BTreeMap::from([(slot, H256::from_low_u64_be(42))]),
+        };
+        let state = PreState::from([(token, account.clone()), (shadowed, account)]);
+
+        let slots = apply(&api, &state, |address| *address == shadowed)
+            .await
+            .unwrap();
+
+        assert_eq!(slots, 1);
+        let slot = U256::from(slot.as_bytes());
+        assert_eq!(
+            api.storage_at(token, slot, None).await.unwrap(),
+            H256::from_low_u64_be(42)
+        );
+        assert_eq!(
+            api.storage_at(shadowed, slot, None).await.unwrap(),
+            H256::zero()
+        );
+        assert_eq!(api.balance(shadowed, None).await.unwrap(), U256::from(1000));
+        assert_eq!(
+            api.transaction_count(token, None).await.unwrap(),
+            U256::from(7)
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::{collections::HashMap, future::Future, time::Duration};
+
+use ethers::{
+    providers::ProviderError,
+    types::{TransactionReceipt, H256},
+};
+use tokio::task::JoinSet;
+
+use super::ForkError;
+
+/// The receipts fetched for a block's transactions.
+#[derive(Debug, Default)]
+pub(super) struct FetchedReceipts {
+    /// The receipts found, by transaction hash
+    pub receipts: HashMap<H256, TransactionReceipt>,
+    /// The transactions whose receipt was still not available after
+    /// every retry, e.g. while the upstream node indexes the block
+    pub missing: Vec<H256>,
+}
+
+/// Fetches the receipts of the given transactions in parallel.
+///
+/// Receipts that aren't available yet, and fetches that fail, are
+/// retried up to `retries` times, waiting `delay` longer before each
+/// retry. Some providers answer with a "not found" error rather than
+/// `null` right after a block is published, which counts as not
+/// available yet. Receipts still not available after every retry are
+/// returned as missing, along with the others.
+/// A fetch that still fails, or a task that panics, is fatal: the
+/// outstanding fetches are cancelled before the error is returned.
+pub(super) async fn fetch_receipts<F, Fut>(
+    hashes: &[H256],
+    retries: u32,
+    delay: Duration,
+    fetch: F,
+) -> Result<FetchedReceipts, ForkError>
+where
+    F: Fn(H256) -> Fut,
+    Fut: Future<Output = Result<Option<TransactionReceipt>, ProviderError>> + Send + 'static,
+{
+    let mut fetched = FetchedReceipts::default();
+    let mut pending = hashes.to_vec();
+    for attempt in 0..=retries {
+        if attempt > 0 {
+            log::debug!(
+                "Retrying {} receipts, attempt {} of {}",
+                pending.len(),
+                attempt,
+                retries
+            );
+            tokio::time::sleep(delay * attempt).await;
+        }
+
+        // Spawn a task for each transaction receipt fetch
+        let mut join_set = JoinSet::new();
+        for tx_hash in pending.drain(..) {
+            let receipt = fetch(tx_hash);
+            join_set.spawn(async move { (tx_hash, receipt.await) });
+        }
+
+        let mut error = None;
+        while let Some(result) = join_set.join_next().await {
+            let (tx_hash, receipt) = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    join_set.shutdown().await;
+                    return Err(ForkError::CustomError(format!(
+                        "Error getting transaction receipt: {}",
+                        e
+                    )));
+                }
+            };
+            match receipt {
+                Ok(Some(receipt)) => {
+                    fetched.receipts.insert(tx_hash, receipt);
+                }
+                Ok(None) => pending.push(tx_hash),
+                Err(e) if is_not_found(&e) => pending.push(tx_hash),
+                Err(e) => {
+                    // The last attempt's errors are fatal
+                    if attempt == retries {
+                        join_set.shutdown().await;
+                        return Err(ForkError::CustomError(format!(
+                            "Error getting transaction receipt {:?}: {}",
+                            tx_hash, e
+                        )));
+                    }
+                    pending.push(tx_hash);
+                    error = Some(e);
+                }
+            }
+        }
+        if let Some(e) = error {
+            log::warn!("Error getting transaction receipts, retrying: {}", e);
+        }
+        if pending.is_empty() {
+            break;
+        }
+    }
+    fetched.missing = pending;
+    Ok(fetched)
+}
+
+/// Returns whether a provider error means the receipt isn't known yet.
+fn is_not_found(error: &ProviderError) -> bool {
+    error.to_string().to_lowercase().contains("not found")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn receipt(tx_hash: H256) -> TransactionReceipt {
+        TransactionReceipt {
+            transaction_hash: tx_hash,
+            ..Default::default()
+        }
+    }
+
+    /// Fetches receipts that become available after `unavailable`
+    /// attempts, failing the attempts listed in `failing`.
+    fn upstream(
+        unavailable: HashMap<H256, u32>,
+        failing: HashMap<H256, u32>,
+    ) -> impl Fn(H256) -> futures_util::future::Ready<Result<Option<TransactionReceipt>, ProviderError>>
+    {
+        let attempts = Arc::new(Mutex::new(HashMap::<H256, u32>::new()));
+        move |tx_hash| {
+            let attempt = {
+                let mut attempts = attempts.lock().unwrap();
+                let attempt = attempts.entry(tx_hash).or_default();
+                *attempt += 1;
+                *attempt
+            };
+            let result = if failing.get(&tx_hash).map_or(false, |n| attempt <= *n) {
+                Err(ProviderError::CustomError("connection reset".to_owned()))
+            } else if unavailable.get(&tx_hash).map_or(false, |n| attempt <= *n) {
+                // Providers answer either null or an error
+                if attempt % 2 == 0 {
+                    Ok(None)
+                } else {
+                    Err(ProviderError::CustomError("receipt not found".to_owned()))
+                }
+            } else {
+                Ok(Some(receipt(tx_hash)))
+            };
+            futures_util::future::ready(result)
+        }
+    }
+
+    #[tokio::test]
+    async fn can_retry_missing_receipts() {
+        let (found, late, pending, flaky) = (
+            H256::repeat_byte(1),
+            H256::repeat_byte(2),
+            H256::repeat_byte(3),
+            H256::repeat_byte(4),
+        );
+        let fetch = upstream(
+            HashMap::from([(late, 2), (pending, u32::MAX)]),
+            HashMap::from([(flaky, 1)]),
+        );
+
+        let fetched = fetch_receipts(&[found, late, pending, flaky], 2, Duration::ZERO, fetch)
+            .await
+            .unwrap();
+        let mut receipts = fetched.receipts.keys().copied().collect::<Vec<_>>();
+        receipts.sort();
+        assert_eq!(receipts, vec![found, late, flaky]);
+        assert_eq!(fetched.missing, vec![pending]);
+    }
+
+    #[tokio::test]
+    async fn fails_on_persistent_errors() {
+        let failing = H256::repeat_byte(1);
+        let fetch = upstream(HashMap::new(), HashMap::from([(failing, u32::MAX)]));
+
+        let result =
+            fetch_receipts(&[failing, H256::repeat_byte(2)], 1, Duration::ZERO, fetch).await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,479 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use ethers::types::{Bytes, H160, H256};
+use serde::Serialize;
+
+use super::determinism::Divergence;
+
+/// Describes a single transaction that failed when it was
+/// replayed on the shadow fork.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FailedTransaction {
+    /// The hash of the mainnet transaction
+    pub tx_hash: H256,
+    /// The reason the transaction failed on the fork
+    pub reason: String,
+}
+
+/// A log emitted by a shadow contract in a replayed transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShadowLog {
+    /// The hash of the mainnet transaction that was replayed
+    pub transaction_hash: H256,
+    /// The shadow contract that emitted the log
+    pub address: H160,
+    /// The event name, or its topic0 if the ABI isn't loaded
+    pub event: String,
+    /// The log topics
+    pub topics: Vec<H256>,
+    /// The log data
+    pub data: Bytes,
+}
+
+impl ShadowLog {
+    /// Returns whether two logs are the same, regardless of how their
+    /// events are named.
+    pub fn same_as(&self, other: &ShadowLog) -> bool {
+        self.address == other.address && self.topics == other.topics && self.data == other.data
+    }
+}
+
+impl fmt::Display for ShadowLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let topics = self
+            .topics
+            .iter()
+            .map(|topic| format!("{:?}", topic))
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            "{} address={:?} topics=[{}] data={}",
+            self.event,
+            self.address,
+            topics.join(","),
+            self.data
+        )
+    }
+}
+
+/// Per-block statistics collected while replaying a block.
+///
+/// A block can be partially replayed: some transactions may be
+/// skipped on purpose, and others may fail on the fork even though
+/// they succeeded on mainnet. This report makes those partial
+/// failures visible instead of hiding them behind a single warning.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockReplayReport {
+    /// The mainnet block number
+    pub block_number: u64,
+    /// The mainnet block timestamp
+    pub timestamp: u64,
+    /// Number of transactions replayed successfully on the fork
+    pub replayed: u64,
+    /// Number of transactions skipped because they don't touch a shadowed contract
+    pub skipped_not_shadowed: u64,
+    /// Number of transactions skipped because they failed on mainnet
+    pub skipped_failed_on_mainnet: u64,
+    /// Number of transactions skipped because their mainnet receipt
+    /// never became available
+    pub skipped_missing_receipt: u64,
+    /// Transactions that succeeded on mainnet but failed on the fork
+    pub failed_on_fork: Vec<FailedTransaction>,
+    /// How long each phase of the replay took
+    pub timings: ReplayTimings,
+    /// How many blocks the fork was behind the chain head
+    pub lag: u64,
+    /// Whether the block was skipped entirely to catch up with the chain head
+    pub skipped_block: bool,
+    /// The gas used by the replayed transactions
+    pub gas_used: u64,
+    /// Number of events emitted by shadow contracts, keyed by event name
+    pub events: BTreeMap<String, u64>,
+    /// Number of transactions that used more gas than their original
+    /// limit, and only went through thanks to the gas headroom
+    pub headroom_needed: u64,
+    /// Number of synthetic transactions from the scenario that succeeded
+    pub synthetic: u64,
+    /// Number of synthetic transactions that failed or couldn't be sent
+    pub synthetic_failed: u64,
+    /// The logs shadow contracts emitted, in replay order
+    pub shadow_logs: Vec<ShadowLog>,
+    /// Number of replayed transactions whose fork receipt was compared
+    /// to their mainnet receipt
+    pub determinism_checked: u64,
+    /// The differences between the fork and mainnet receipts
+    pub divergences: Vec<Divergence>,
+    /// Number of transactions replayed after copying the mainnet state
+    /// they read onto the fork
+    pub prestaged: u64,
+}
+
+/// How long each phase of a block replay took.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReplayTimings {
+    /// Fetching the block with its transactions
+    pub fetch_block: Duration,
+    /// Fetching the transaction receipts
+    pub fetch_receipts: Duration,
+    /// Executing the transactions and mining the block on anvil
+    pub execution: Duration,
+}
+
+impl BlockReplayReport {
+    pub fn new(block_number: u64) -> Self {
+        Self {
+            block_number,
+            ..Default::default()
+        }
+    }
+
+    /// Records a transaction that failed on the fork.
+    pub fn record_failure(&mut self, tx_hash: H256, reason: String) {
+        self.failed_on_fork
+            .push(FailedTransaction { tx_hash, reason });
+    }
+
+    /// Logs the report as structured log lines.
+    ///
+    /// A summary line is always emitted; each failed transaction
+    /// gets its own warning line with the revert reason.
+    pub fn log(&self) {
+        log::info!(target: "shadow::replay", "{}", self);
+        for failure in &self.failed_on_fork {
+            log::warn!(
+                target: "shadow::replay",
+                "block={} tx=0x{} failed_on_fork reason={:?}",
+                self.block_number,
+                hex::encode(failure.tx_hash.as_bytes()),
+                failure.reason
+            );
+        }
+        if self.determinism_checked > 0 {
+            log::info!(
+                target: "shadow::replay",
+                "block={} determinism checked={} divergent={}",
+                self.block_number,
+                self.determinism_checked,
+                self.divergent_transactions()
+            );
+        }
+        for divergence in &self.divergences {
+            log::warn!(
+                target: "shadow::replay",
+                "block={} tx=0x{} diverged {}",
+                self.block_number,
+                hex::encode(divergence.tx_hash.as_bytes()),
+                divergence.kind
+            );
+        }
+    }
+
+    /// Returns the number of transactions with at least one divergence.
+    pub fn divergent_transactions(&self) -> u64 {
+        let mut tx_hashes = self
+            .divergences
+            .iter()
+            .map(|divergence| divergence.tx_hash)
+            .collect::<Vec<_>>();
+        tx_hashes.dedup();
+        tx_hashes.len() as u64
+    }
+}
+
+impl fmt::Display for BlockReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "block={} replayed={} skipped_not_shadowed={} skipped_failed_on_mainnet={} failed_on_fork={}",
+            self.block_number,
+            self.replayed,
+            self.skipped_not_shadowed,
+            self.skipped_failed_on_mainnet,
+            self.failed_on_fork.len()
+        )?;
+        if self.lag > 0 {
+            write!(f, " lag={}", self.lag)?;
+        }
+        if self.skipped_block {
+            write!(f, " skipped_block")?;
+        }
+        if self.skipped_missing_receipt > 0 {
+            write!(
+                f,
+                " skipped_missing_receipt={}",
+                self.skipped_missing_receipt
+            )?;
+        }
+        if self.headroom_needed > 0 {
+            write!(f, " headroom_needed={}", self.headroom_needed)?;
+        }
+        if self.prestaged > 0 {
+            write!(f, " prestaged={}", self.prestaged)?;
+        }
+        if !self.divergences.is_empty() {
+            write!(f, " divergent={}", self.divergent_transactions())?;
+        }
+        if self.synthetic > 0 || self.synthetic_failed > 0 {
+            write!(
+                f,
+                " synthetic={} synthetic_failed={}",
+                self.synthetic, self.synthetic_failed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Cumulative replay statistics over the lifetime of a fork.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayMetrics {
+    /// Number of blocks processed, including blocks that errored
+    pub blocks: u64,
+    /// Number of blocks that could not be replayed at all
+    pub blocks_failed: u64,
+    /// Number of blocks skipped entirely to catch up with the chain head
+    pub blocks_skipped: u64,
+    /// Number of transactions replayed successfully on the fork
+    pub replayed: u64,
+    /// Number of transactions skipped because they don't touch a shadowed contract
+    pub skipped_not_shadowed: u64,
+    /// Number of transactions skipped because they failed on mainnet
+    pub skipped_failed_on_mainnet: u64,
+    /// Number of transactions skipped because their receipt never became available
+    pub skipped_missing_receipt: u64,
+    /// Number of transactions that succeeded on mainnet but failed on the fork
+    pub failed_on_fork: u64,
+    /// Number of transactions that needed the gas headroom
+    pub headroom_needed: u64,
+    /// Number of synthetic transactions that succeeded
+    pub synthetic: u64,
+    /// Number of synthetic transactions that failed
+    pub synthetic_failed: u64,
+    /// Number of replayed transactions whose fork receipt differed from
+    /// their mainnet receipt
+    pub divergent: u64,
+    /// Number of transactions replayed after copying the mainnet state
+    /// they read onto the fork
+    pub prestaged: u64,
+}
+
+impl ReplayMetrics {
+    /// Adds a block report to the cumulative metrics.
+    pub fn record(&mut self, report: &BlockReplayReport) {
+        self.blocks += 1;
+        if report.skipped_block {
+            self.blocks_skipped += 1;
+        }
+        self.replayed += report.replayed;
+        self.skipped_not_shadowed += report.skipped_not_shadowed;
+        self.skipped_failed_on_mainnet += report.skipped_failed_on_mainnet;
+        self.skipped_missing_receipt += report.skipped_missing_receipt;
+        self.failed_on_fork += report.failed_on_fork.len() as u64;
+        self.headroom_needed += report.headroom_needed;
+        self.synthetic += report.synthetic;
+        self.synthetic_failed += report.synthetic_failed;
+        self.divergent += report.divergent_transactions();
+        self.prestaged += report.prestaged;
+    }
+
+    /// Records a block that could not be replayed.
+    pub fn record_block_failure(&mut self) {
+        self.blocks += 1;
+        self.blocks_failed += 1;
+    }
+
+    /// Renders the metrics as counters in the Prometheus text format.
+    pub fn to_prometheus(&self) -> String {
+        let counters = [
+            (
+                "blocks",
+                "Blocks processed, including blocks that errored",
+                self.blocks,
+            ),
+            (
+                "blocks_failed",
+                "Blocks that could not be replayed",
+                self.blocks_failed,
+            ),
+            (
+                "blocks_skipped",
+                "Blocks skipped to catch up with the chain head",
+                self.blocks_skipped,
+            ),
+            (
+                "transactions_replayed",
+                "Transactions replayed on the fork",
+                self.replayed,
+            ),
+            (
+                "transactions_skipped_not_shadowed",
+                "Transactions that don't touch a shadowed contract",
+                self.skipped_not_shadowed,
+            ),
+            (
+                "transactions_skipped_failed_on_mainnet",
+                "Transactions that failed on mainnet",
+                self.skipped_failed_on_mainnet,
+            ),
+            (
+                "transactions_skipped_missing_receipt",
+                "Transactions whose receipt never became available",
+                self.skipped_missing_receipt,
+            ),
+            (
+                "transactions_failed_on_fork",
+                "Transactions that succeeded on mainnet but failed on the fork",
+                self.failed_on_fork,
+            ),
+            (
+                "transactions_headroom_needed",
+                "Transactions that needed the gas headroom",
+                self.headroom_needed,
+            ),
+            (
+                "synthetic_transactions",
+                "Synthetic transactions that succeeded",
+                self.synthetic,
+            ),
+            (
+                "synthetic_transactions_failed",
+                "Synthetic transactions that failed",
+                self.synthetic_failed,
+            ),
+            (
+                "transactions_divergent",
+                "Transactions whose fork receipt differed from mainnet",
+                self.divergent,
+            ),
+            (
+                "transactions_prestaged",
+                "Transactions replayed after copying the mainnet state they read",
+                self.prestaged,
+            ),
+        ];
+        let mut metrics = String::new();
+        for (name, help, value) in counters {
+            metrics.push_str(&format!(
+                "# HELP shadow_replay_{name}_total {help}\n\
+                 # TYPE shadow_replay_{name}_total counter\n\
+                 shadow_replay_{name}_total {value}\n"
+            ));
+        }
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_aggregate_reports() {
+        let mut report = BlockReplayReport::new(17_000_000);
+        report.replayed = 2;
+        report.skipped_not_shadowed = 100;
+        report.skipped_failed_on_mainnet = 3;
+        report.record_failure(H256::zero(), "execution reverted".to_owned());
+
+        let mut metrics = ReplayMetrics::default();
+        metrics.record(&report);
+        metrics.record(&BlockReplayReport::new(17_000_001));
+        metrics.record_block_failure();
+
+        assert_eq!(
+            metrics,
+            ReplayMetrics {
+                blocks: 3,
+                blocks_failed: 1,
+                blocks_skipped: 0,
+                replayed: 2,
+                skipped_not_shadowed: 100,
+                skipped_failed_on_mainnet: 3,
+                skipped_missing_receipt: 0,
+                failed_on_fork: 1,
+                headroom_needed: 0,
+                synthetic: 0,
+                synthetic_failed: 0,
+                divergent: 0,
+                prestaged: 0,
+            }
+        );
+        assert_eq!(
+            report.to_string(),
+            "block=17000000 replayed=2 skipped_not_shadowed=100 skipped_failed_on_mainnet=3 failed_on_fork=1"
+        );
+
+        let prometheus = metrics.to_prometheus();
+        assert!(prometheus.contains("# TYPE shadow_replay_blocks_total counter\n"));
+        assert!(prometheus.contains("\nshadow_replay_blocks_total 3\n"));
+        assert!(prometheus.contains("\nshadow_replay_transactions_failed_on_fork_total 1\n"));
+    }
+
+    #[test]
+    fn can_report_skipped_blocks() {
+        let mut report = BlockReplayReport::new(17_000_000);
+        report.skipped_not_shadowed = 150;
+        report.lag = 12;
+        report.skipped_block = true;
+
+        let mut metrics = ReplayMetrics::default();
+        metrics.record(&report);
+
+        assert_eq!(metrics.blocks_skipped, 1);
+        assert_eq!(
+            report.to_string(),
+            "block=17000000 replayed=0 skipped_not_shadowed=150 skipped_failed_on_mainnet=0 failed_on_fork=0 lag=12 skipped_block"
+        );
+    }
+
+    #[test]
+    fn can_report_missing_receipts() {
+        let mut report = BlockReplayReport::new(17_000_000);
+        report.replayed = 1;
+        report.skipped_missing_receipt = 2;
+
+        let mut metrics = ReplayMetrics::default();
+        metrics.record(&report);
+
+        assert_eq!(metrics.skipped_missing_receipt, 2);
+        assert_eq!(
+            report.to_string(),
+            "block=17000000 replayed=1 skipped_not_shadowed=0 skipped_failed_on_mainnet=0 failed_on_fork=0 skipped_missing_receipt=2"
+        );
+    }
+
+    #[test]
+    fn can_report_gas_headroom() {
+        let mut report = BlockReplayReport::new(17_000_000);
+        report.replayed = 3;
+        report.headroom_needed = 2;
+
+        let mut metrics = ReplayMetrics::default();
+        metrics.record(&report);
+        metrics.record(&report);
+
+        assert_eq!(metrics.headroom_needed, 4);
+        assert_eq!(
+            report.to_string(),
+            "block=17000000 replayed=3 skipped_not_shadowed=0 skipped_failed_on_mainnet=0 failed_on_fork=0 headroom_needed=2"
+        );
+    }
+
+    #[test]
+    fn can_report_synthetic_transactions() {
+        let mut report = BlockReplayReport::new(17_000_000);
+        report.replayed = 1;
+        report.synthetic = 2;
+        report.synthetic_failed = 1;
+
+        let mut metrics = ReplayMetrics::default();
+        metrics.record(&report);
+
+        assert_eq!((metrics.synthetic, metrics.synthetic_failed), (2, 1));
+        assert_eq!(
+            report.to_string(),
+            "block=17000000 replayed=1 skipped_not_shadowed=0 skipped_failed_on_mainnet=0 failed_on_fork=0 synthetic=2 synthetic_failed=1"
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+
+use ethers::types::{Bytes, Transaction, H160, U256};
+
+use crate::config::ReplayRewrite;
+
+/// The parts of a transaction a [`ReplayHook`] can change before it is
+/// replayed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayCall {
+    /// The recipient, or `None` for contract creations
+    pub to: Option<H160>,
+    /// The value sent
+    pub value: U256,
+    /// The calldata
+    pub data: Bytes,
+}
+
+impl From<&Transaction> for ReplayCall {
+    fn from(tx: &Transaction) -> Self {
+        Self {
+            to: tx.to,
+            value: tx.value,
+            data: tx.input.clone(),
+        }
+    }
+}
+
+/// Changes transactions replayed from their impersonated senders, for
+/// "what-if" experiments on real historical flow, e.g. routing calls to
+/// a shadow-only function or trying other fee settings.
+///
+/// Hooks run in order, each on the output of the previous one. The
+/// original transaction is passed along to match on.
+pub trait ReplayHook: Send + Sync {
+    /// Changes the call before it is replayed.
+    fn apply(&self, tx: &Transaction, call: &mut ReplayCall);
+}
+
+/// Replaces the function selector of calls, keeping the arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectorRewrite {
+    /// Only rewrites calls to this contract, if set
+    pub address: Option<H160>,
+    /// The selector to replace
+    pub from: [u8; 4],
+    /// The selector to call instead
+    pub to: [u8; 4],
+}
+
+impl ReplayHook for SelectorRewrite {
+    fn apply(&self, tx: &Transaction, call: &mut ReplayCall) {
+        if self.address.is_some() && tx.to != self.address {
+            return;
+        }
+        if call.data.get(..4) != Some(&self.from[..]) {
+            return;
+        }
+        call.data = [&self.to[..], &call.data[4..]].concat().into();
+    }
+}
+
+impl SelectorRewrite {
+    /// Parses a `[<address>:]<selector>=<selector>` argument, e.g.
+    /// `0xa9059cbb=0x12345678`.
+    pub fn parse(argument: &str) -> Result<Self, String> {
+        let (call, to) = argument.split_once('=').ok_or_else(|| {
+            format!(
+                "Expected [<address>:]<selector>=<selector>, got {}",
+                argument
+            )
+        })?;
+        let (address, from) = match call.split_once(':') {
+            Some((address, from)) => (Some(parse_address(address)?), from),
+            None => (None, call),
+        };
+        Ok(Self {
+            address,
+            from: parse_selector(from)?,
+            to: parse_selector(to)?,
+        })
+    }
+}
+
+/// A [`ReplayRewrite`] from the config, with its values parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionRewrite {
+    to: Option<H160>,
+    selector: Option<[u8; 4]>,
+    set_to: Option<H160>,
+    set_value: Option<U256>,
+    set_selector: Option<[u8; 4]>,
+    set_words: BTreeMap<usize, [u8; 32]>,
+}
+
+impl TryFrom<&ReplayRewrite> for TransactionRewrite {
+    type Error = String;
+
+    fn try_from(rewrite: &ReplayRewrite) -> Result<Self, String> {
+        let set_words = rewrite
+            .set_words
+            .iter()
+            .map(|(index, value)| Ok((*index, parse_word(value)?)))
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            to: rewrite.to.as_deref().map(parse_address).transpose()?,
+            selector: rewrite
+                .selector
+                .as_deref()
+                .map(parse_selector)
+                .transpose()?,
+            set_to: rewrite.set_to.as_deref().map(parse_address).transpose()?,
+            set_value: rewrite
+                .set_value
+                .as_deref()
+                .map(|value| {
+                    U256::from_dec_str(value).map_err(|e| format!("Invalid value {}: {}", value, e))
+                })
+                .transpose()?,
+            set_selector: rewrite
+                .set_selector
+                .as_deref()
+                .map(parse_selector)
+                .transpose()?,
+            set_words,
+        })
+    }
+}
+
+impl ReplayHook for TransactionRewrite {
+    fn apply(&self, tx: &Transaction, call: &mut ReplayCall) {
+        if self.to.is_some() && tx.to != self.to {
+            return;
+        }
+        if let Some(selector) = &self.selector {
+            if tx.input.get(..4) != Some(&selector[..]) {
+                return;
+            }
+        }
+        if let Some(to) = self.set_to {
+            call.to = Some(to);
+        }
+        if let Some(value) = self.set_value {
+            call.value = value;
+        }
+        let mut data = call.data.to_vec();
+        if let Some(selector) = &self.set_selector {
+            if data.len() < 4 {
+                data.resize(4, 0);
+            }
+            data[..4].copy_from_slice(selector);
+        }
+        for (index, word) in &self.set_words {
+            // Words follow the selector, and the calldata is padded if
+            // it's too short
+            let start = 4 + index * 32;
+            if data.len() < start + 32 {
+                data.resize(start + 32, 0);
+            }
+            data[start..start + 32].copy_from_slice(word);
+        }
+        call.data = data.into();
+    }
+}
+
+pub(super) fn parse_address(address: &str) -> Result<H160, String> {
+    let address = address.trim();
+    address
+        .parse::<H160>()
+        .map_err(|e| format!("Invalid address {}: {}", address, e))
+}
+
+fn parse_selector(selector: &str) -> Result<[u8; 4], String> {
+    let selector = selector.trim();
+    hex::decode(selector.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| format!("Invalid selector {}", selector))
+}
+
+/// Parses an ABI word, from a decimal number or 32 bytes of hex.
+pub(super) fn parse_word(value: &str) -> Result<[u8; 32], String> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix("0x") {
+        return hex::decode(hex)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| format!("Invalid word {}: expected 32 bytes", value));
+    }
+    let number = U256::from_dec_str(value).map_err(|e| format!("Invalid word {}: {}", value, e))?;
+    let mut word = [0; 32];
+    number.to_big_endian(&mut word);
+    Ok(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_rewrite_selectors() {
+        let token = H160::repeat_byte(0xaa);
+        let rewrite =
+            SelectorRewrite::parse(&format!("{:?}:0xa9059cbb=0x12345678", token)).unwrap();
+        assert_eq!(
+            rewrite,
+            SelectorRewrite {
+                address: Some(token),
+                from: [0xa9, 0x05, 0x9c, 0xbb],
+                to: [0x12, 0x34, 0x56, 0x78],
+            }
+        );
+
+        let tx = Transaction {
+            to: Some(token),
+            input: Bytes::from(vec![0xa9, 0x05, 0x9c, 0xbb, 0x01, 0x02]),
+            ..Default::default()
+        };
+        let mut call = ReplayCall::from(&tx);
+        rewrite.apply(&tx, &mut call);
+        assert_eq!(
+            call.data,
+            Bytes::from(vec![0x12, 0x34, 0x56, 0x78, 0x01, 0x02])
+        );
+
+        // Other contracts are left alone
+        let other = Transaction {
+            to: Some(H160::repeat_byte(0xbb)),
+            ..tx.clone()
+        };
+        let mut call = ReplayCall::from(&other);
+        rewrite.apply(&other, &mut call);
+        assert_eq!(call, ReplayCall::from(&other));
+
+        assert!(SelectorRewrite::parse("0xa9059cbb").is_err());
+        assert!(SelectorRewrite::parse("0xa9059c=0x12345678").is_err());
+    }
+
+    #[test]
+    fn can_rewrite_transactions() {
+        let router = H160::repeat_byte(0xaa);
+        let rewrite = TransactionRewrite::try_from(&ReplayRewrite {
+            to: Some(format!("{:?}", router)),
+            selector: Some("0x38ed1739".to_owned()),
+            set_to: Some(format!("{:?}", H160::repeat_byte(0xbb))),
+            set_value: Some("1000".to_owned()),
+            set_selector: None,
+            set_words: BTreeMap::from([(1, "30".to_owned())]),
+        })
+        .unwrap();
+
+        let tx = Transaction {
+            to: Some(router),
+            input: Bytes::from([&[0x38, 0xed, 0x17, 0x39][..], &[1; 64][..]].concat()),
+            ..Default::default()
+        };
+        let mut call = ReplayCall::from(&tx);
+        rewrite.apply(&tx, &mut call);
+        assert_eq!(call.to, Some(H160::repeat_byte(0xbb)));
+        assert_eq!(call.value, U256::from(1000));
+        assert_eq!(call.data[..36], tx.input[..36]);
+        assert_eq!(U256::from_big_endian(&call.data[36..68]), U256::from(30));
+
+        // Other functions are left alone
+        let other = Transaction {
+            input: Bytes::from(vec![0x01, 0x02, 0x03, 0x04]),
+            ..tx
+        };
+        let mut call = ReplayCall::from(&other);
+        rewrite.apply(&other, &mut call);
+        assert_eq!(call, ReplayCall::from(&other));
+
+        assert!(TransactionRewrite::try_from(&ReplayRewrite {
+            set_words: BTreeMap::from([(0, "0x1234".to_owned())]),
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use std::{collections::HashSet, fs, path::Path, sync::Mutex};
+
+use ethers::types::{Bytes, H160, U256};
+use serde::Deserialize;
+
+/// Synthetic transactions to mix into the replayed mainnet traffic, to
+/// see how the shadow contracts behave under hypothetical actions.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Scenario {
+    /// The scheduled transactions
+    pub transactions: Vec<SyntheticTransaction>,
+}
+
+/// A synthetic transaction, sent from its impersonated sender either at
+/// a block offset or when a shadow contract emits an event.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SyntheticTransaction {
+    /// A name to refer to the transaction in logs
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Send the transaction this many blocks after the first replayed
+    /// block, ahead of that block's mainnet transactions
+    #[serde(default)]
+    pub at_block: Option<u64>,
+
+    /// Send the transaction in the block after a replayed mainnet
+    /// transaction emits this shadow event
+    #[serde(default)]
+    pub on_event: Option<EventTrigger>,
+
+    /// Whether an event triggered transaction is sent every time the
+    /// event is emitted, rather than once
+    #[serde(default)]
+    pub repeat: bool,
+
+    /// The sender
+    pub from: H160,
+
+    /// The recipient, or `None` for contract creations
+    #[serde(default)]
+    pub to: Option<H160>,
+
+    /// The value sent, as a hex quantity
+    #[serde(default)]
+    pub value: U256,
+
+    /// The calldata
+    #[serde(default)]
+    pub data: Bytes,
+
+    /// The gas limit. Estimated by the fork if not set.
+    #[serde(default)]
+    pub gas: Option<u64>,
+}
+
+/// A shadow event that triggers a synthetic transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct EventTrigger {
+    /// The event name, or its topic0 if the ABI isn't loaded
+    pub event: String,
+
+    /// Only events of this contract, if set
+    #[serde(default)]
+    pub address: Option<H160>,
+}
+
+impl SyntheticTransaction {
+    /// Returns the name of the transaction, or its position in the
+    /// scenario.
+    pub fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("transaction {}", index))
+    }
+}
+
+impl Scenario {
+    /// Loads a scenario from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading scenario {}: {}", path.display(), e))?;
+        Self::from_json(&json)
+    }
+
+    /// Parses a scenario, checking that every transaction has exactly
+    /// one trigger.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let scenario: Self =
+            serde_json::from_str(json).map_err(|e| format!("Invalid scenario: {}", e))?;
+        for (index, tx) in scenario.transactions.iter().enumerate() {
+            if tx.at_block.is_some() == tx.on_event.is_some() {
+                return Err(format!(
+                    "Scenario {} needs either atBlock or onEvent",
+                    tx.label(index)
+                ));
+            }
+        }
+        Ok(scenario)
+    }
+}
+
+/// Schedules the transactions of a scenario as blocks are replayed.
+pub struct ScenarioRunner {
+    scenario: Scenario,
+    state: Mutex<ScenarioState>,
+}
+
+#[derive(Default)]
+struct ScenarioState {
+    /// The first replayed block, which block offsets count from
+    start: Option<u64>,
+    /// The transactions that were sent and won't be sent again
+    fired: HashSet<usize>,
+    /// The transactions triggered by events, waiting for the next block
+    triggered: Vec<usize>,
+}
+
+impl ScenarioRunner {
+    pub fn new(scenario: Scenario) -> Self {
+        Self {
+            scenario,
+            state: Mutex::new(ScenarioState::default()),
+        }
+    }
+
+    /// Returns the transactions to send in a block, with their index in
+    /// the scenario.
+    ///
+    /// These are the transactions triggered in earlier blocks, and
+    /// those scheduled at or before the block's offset. A transaction
+    /// scheduled in a block that was skipped is sent in the next
+    /// replayed block.
+    pub fn due(&self, block_number: u64) -> Vec<(usize, SyntheticTransaction)> {
+        let mut state = self.state.lock().unwrap();
+        let start = *state.start.get_or_insert(block_number);
+        let offset = block_number.saturating_sub(start);
+
+        let mut due = std::mem::take(&mut state.triggered);
+        for (index, tx) in self.scenario.transactions.iter().enumerate() {
+            if matches!(tx.at_block, Some(at) if at <= offset) && state.fired.insert(index) {
+                due.push(index);
+            }
+        }
+        due.into_iter()
+            .map(|index| (index, self.scenario.transactions[index].clone()))
+            .collect()
+    }
+
+    /// Queues the transactions triggered by a shadow event for the next
+    /// block.
+    pub fn observe(&self, address: H160, event: &str) {
+        let mut state = self.state.lock().unwrap();
+        for (index, tx) in self.scenario.transactions.iter().enumerate() {
+            let Some(trigger) = &tx.on_event else {
+                continue;
+            };
+            if trigger.event != event || matches!(trigger.address, Some(a) if a != address) {
+                continue;
+            }
+            if tx.repeat || state.fired.insert(index) {
+                state.triggered.push(index);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_schedule_transactions() {
+        let pool = H160::repeat_byte(0xaa);
+        let scenario = Scenario::from_json(&format!(
+            r#"{{
+                "transactions": [
+                    {{ "name": "whale", "atBlock": 2, "from": "{from:?}", "to": "{pool:?}", "value": "0x10" }},
+                    {{ "name": "arb", "onEvent": {{ "event": "Swap", "address": "{pool:?}" }}, "from": "{from:?}" }},
+                    {{ "onEvent": {{ "event": "Sync" }}, "repeat": true, "from": "{from:?}" }}
+                ]
+            }}"#,
+            from = H160::repeat_byte(1),
+        ))
+        .unwrap();
+        assert_eq!(scenario.transactions[0].value, U256::from(16));
+
+        let runner = ScenarioRunner::new(scenario);
+        let due = |block| {
+            runner
+                .due(block)
+                .into_iter()
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(due(100), Vec::<usize>::new());
+
+        // Events of other contracts don't trigger
+        runner.observe(H160::repeat_byte(0xbb), "Swap");
+        runner.observe(pool, "Swap");
+        runner.observe(pool, "Swap");
+        runner.observe(pool, "Sync");
+        runner.observe(pool, "Sync");
+        assert_eq!(due(101), vec![1, 2, 2]);
+
+        // Block offsets count from the first replayed block, and only
+        // repeating transactions are triggered again
+        runner.observe(pool, "Swap");
+        runner.observe(pool, "Sync");
+        assert_eq!(due(103), vec![2, 0]);
+        assert_eq!(due(104), Vec::<usize>::new());
+
+        assert!(Scenario::from_json(&format!(
+            r#"{{ "transactions": [{{ "from": "{:?}" }}] }}"#,
+            H160::zero()
+        ))
+        .is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::Serialize;
+
+use crate::core::resources::sink::DecodedEvent;
+
+use super::BlockReplayReport;
+
+/// A compact record of a replayed block, emitted after each block as a
+/// heartbeat for monitoring the fork and the shadowed protocol.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockSummary {
+    /// The mainnet block number
+    pub block_number: u64,
+    /// The mainnet block timestamp
+    pub timestamp: u64,
+    /// Number of shadow transactions replayed successfully
+    pub shadow_transactions: u64,
+    /// Number of transactions that failed on the fork
+    pub failed_on_fork: u64,
+    /// Number of shadow events emitted, keyed by event name
+    pub events: BTreeMap<String, u64>,
+    /// The gas used by the replayed transactions
+    pub gas_used: u64,
+}
+
+impl From<&BlockReplayReport> for BlockSummary {
+    fn from(report: &BlockReplayReport) -> Self {
+        Self {
+            block_number: report.block_number,
+            timestamp: report.timestamp,
+            shadow_transactions: report.replayed,
+            failed_on_fork: report.failed_on_fork.len() as u64,
+            events: report.events.clone(),
+            gas_used: report.gas_used,
+        }
+    }
+}
+
+impl BlockSummary {
+    /// Describes the summary as a decoded `BlockSummary` event, so it can
+    /// be written to the event sinks.
+    ///
+    /// Its transaction hash is `block:<number>`, which keeps summaries
+    /// apart from events in stores keyed by transaction and log index.
+    pub fn to_event(&self) -> DecodedEvent {
+        DecodedEvent {
+            block_number: self.block_number,
+            block_timestamp: Some(self.timestamp),
+            transaction_hash: format!("block:{}", self.block_number),
+            log_index: 0,
+            address: format!("{:?}", ethers::types::H160::zero()),
+            contract: "shadow:fork".to_owned(),
+            event: "BlockSummary".to_owned(),
+            signature: "BlockSummary".to_owned(),
+            schema: None,
+            data: serde_json::to_value(self).unwrap_or_default(),
+            raw: None,
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for BlockSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "block={} timestamp={} shadow_txs={} failed_on_fork={} gas_used={} events=",
+            self.block_number,
+            self.timestamp,
+            self.shadow_transactions,
+            self.failed_on_fork,
+            self.gas_used
+        )?;
+        let events = self
+            .events
+            .iter()
+            .map(|(name, count)| format!("{}:{}", name, count))
+            .collect::<Vec<_>>();
+        write!(f, "{}", events.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn can_summarize_blocks() {
+        let mut report = BlockReplayReport::new(17_000_000);
+        report.timestamp = 1_681_338_455;
+        report.replayed = 2;
+        report.gas_used = 250_000;
+        report.events = BTreeMap::from([("Swap".to_owned(), 3), ("Trade".to_owned(), 1)]);
+
+        let summary = BlockSummary::from(&report);
+        assert_eq!(
+            summary.to_string(),
+            "block=17000000 timestamp=1681338455 shadow_txs=2 failed_on_fork=0 gas_used=250000 events=Swap:3,Trade:1"
+        );
+
+        let event = summary.to_event();
+        assert_eq!(event.transaction_hash, "block:17000000");
+        assert_eq!(
+            event.data,
+            json!({
+                "blockNumber": 17_000_000,
+                "timestamp": 1_681_338_455,
+                "shadowTransactions": 2,
+                "failedOnFork": 0,
+                "events": { "Swap": 3, "Trade": 1 },
+                "gasUsed": 250_000,
+            })
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::{collections::HashMap, fmt::Write};
+
+use alloy_json_abi::JsonAbi;
+use ethers::types::{Action, Res, Trace, H160};
+
+use crate::decode;
+
+/// Renders the call traces of a transaction as a readable call tree.
+///
+/// Calls to contracts with a known ABI (the shadow contracts) are
+/// decoded into function names with named arguments and return values.
+/// Other calls are rendered as the target address and raw selector.
+///
+/// Example:
+/// ```text
+/// 0x7a25…488d::swapExactTokensForTokens(amountIn=1000, …) → (amounts=[1000,998])
+///   0xc02a…6cc2::0x23b872dd
+///   0xb4e1…c9cc::0x022c0d9f
+/// ```
+pub fn render_call_tree(traces: &[Trace], abis: &HashMap<H160, JsonAbi>) -> String {
+    let mut tree = String::new();
+    for trace in traces {
+        let indent = "  ".repeat(trace.trace_address.len());
+        let line = match &trace.action {
+            Action::Call(call) => {
+                let output = match &trace.result {
+                    Some(Res::Call(result)) => Some(result.output.as_ref()),
+                    _ => None,
+                };
+                render_call(call.to, call.input.as_ref(), output, abis)
+            }
+            Action::Create(_) => "CREATE".to_owned(),
+            Action::Suicide(_) => "SELFDESTRUCT".to_owned(),
+            Action::Reward(_) => "REWARD".to_owned(),
+        };
+        let error = trace
+            .error
+            .as_ref()
+            .map(|e| format!(" [{}]", e))
+            .unwrap_or_default();
+        let _ = writeln!(tree, "{}{}{}", indent, line, error);
+    }
+    tree
+}
+
+fn render_call(
+    to: H160,
+    input: &[u8],
+    output: Option<&[u8]>,
+    abis: &HashMap<H160, JsonAbi>,
+) -> String {
+    let target = format!("0x{}", hex::encode(to.as_bytes()));
+    let decoded = abis
+        .get(&to)
+        .and_then(|abi| decode::decode_function_input(input, abi).ok().flatten());
+
+    match decoded {
+        Some((function, args)) => {
+            let returns = output
+                .and_then(|output| decode::decode_function_output(output, &function).ok())
+                .map(|value| format!(" → ({})", render_args(&value)))
+                .unwrap_or_default();
+            format!(
+                "{}::{}({}){}",
+                target,
+                function.name,
+                render_args(&args),
+                returns
+            )
+        }
+        None if input.len() >= 4 => format!("{}::0x{}", target, hex::encode(&input[..4])),
+        None => format!("{}::fallback", target),
+    }
+}
+
+/// Renders a decoded JSON object as `name=value` pairs.
+fn render_args(value: &serde_json::Value) -> String {
+    value
+        .as_object()
+        .map(|map| {
+            map.iter()
+                .map(|(name, value)| match value {
+                    serde_json::Value::String(s) => format!("{}={}", name, s),
+                    other => format!("{}={}", name, other),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn can_render_call_tree() {
+        let abi: JsonAbi = serde_json::from_str(
+            r#"[{
+                "type": "function",
+                "name": "transfer",
+                "inputs": [
+                    { "name": "to", "type": "address", "internalType": "address" },
+                    { "name": "value", "type": "uint256", "internalType": "uint256" }
+                ],
+                "outputs": [{ "name": "success", "type": "bool", "internalType": "bool" }],
+                "stateMutability": "nonpayable"
+            }]"#,
+        )
+        .unwrap();
+        let token = H160::from_str("0x6b175474e89094c44da98b954eedeac495271d0f").unwrap();
+        let abis = HashMap::from([(token, abi)]);
+
+        let traces: Vec<Trace> = serde_json::from_str(r#"[
+            {
+                "action": {
+                    "callType": "call",
+                    "from": "0x73ede13ab9c28bc4302e94c1d1e7f755988a9158",
+                    "to": "0x6b175474e89094c44da98b954eedeac495271d0f",
+                    "gas": "0x0",
+                    "input": "0xa9059cbb00000000000000000000000091364516d3cad16e1666261dbdbb39c881dbe9ee0000000000000000000000000000000000000000000000000000000000000045",
+                    "value": "0x0"
+                },
+                "result": {
+                    "gasUsed": "0x0",
+                    "output": "0x0000000000000000000000000000000000000000000000000000000000000001"
+                },
+                "subtraces": 1,
+                "traceAddress": [],
+                "blockNumber": 0,
+                "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "type": "call"
+            },
+            {
+                "action": {
+                    "callType": "call",
+                    "from": "0x6b175474e89094c44da98b954eedeac495271d0f",
+                    "to": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                    "gas": "0x0",
+                    "input": "0x23b872dd",
+                    "value": "0x0"
+                },
+                "subtraces": 0,
+                "traceAddress": [0],
+                "blockNumber": 0,
+                "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "type": "call",
+                "error": "Reverted"
+            }
+        ]"#).unwrap();
+
+        assert_eq!(
+            render_call_tree(&traces, &abis),
+            "0x6b175474e89094c44da98b954eedeac495271d0f::transfer(to=0x91364516d3cad16e1666261dbdbb39c881dbe9ee, value=69) → (success=true)\n  0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2::0x23b872dd [Reverted]\n"
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use std::sync::Mutex;
+
+use alloy_json_abi::JsonAbi;
+use ethers::types::{Bytes, H160};
+use serde_json::{json, Value};
+
+use super::rewrite::parse_address;
+use crate::{
+    config::ViewCall,
+    core::resources::sink::DecodedEvent,
+    decode::{self, encode_function_call},
+};
+
+/// The contract name of the events view calls are written as
+pub const VIEW_CONTRACT: &str = "shadow:view";
+
+/// When a view function is called
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewSchedule {
+    /// On block numbers divisible by this many blocks
+    Blocks(u64),
+    /// On the first block at least this many minutes after the last call,
+    /// by block timestamp
+    Minutes(u64),
+}
+
+/// A view function called on the fork after replayed blocks, whose
+/// results are written to the sinks as a synthetic event.
+#[derive(Debug)]
+pub struct PolledView {
+    /// The name of the synthetic event
+    pub name: String,
+    /// The contract to call
+    pub to: H160,
+    /// The function, e.g. `getShadowMetrics()`
+    pub signature: String,
+    /// The encoded call
+    pub calldata: Bytes,
+    /// When the function is called
+    pub schedule: ViewSchedule,
+    /// The timestamp of the block the function was last called after
+    last_called: Mutex<Option<u64>>,
+}
+
+impl TryFrom<&ViewCall> for PolledView {
+    type Error = String;
+
+    fn try_from(call: &ViewCall) -> Result<Self, String> {
+        let signature = call.signature.trim().to_owned();
+        let function = signature
+            .split_once('(')
+            .map(|(function, _)| function.to_owned())
+            .ok_or_else(|| format!("Invalid signature {}", signature))?;
+        let schedule = match (call.every, call.every_minutes) {
+            (Some(0), _) => return Err("every must be at least 1".to_owned()),
+            (_, Some(0)) => return Err("everyMinutes must be at least 1".to_owned()),
+            (Some(_), Some(_)) => return Err("every and everyMinutes can't be combined".to_owned()),
+            (_, Some(minutes)) => ViewSchedule::Minutes(minutes),
+            (every, None) => ViewSchedule::Blocks(every.unwrap_or(1)),
+        };
+        Ok(Self {
+            name: call.name.clone().unwrap_or(function),
+            to: parse_address(&call.to)?,
+            calldata: encode_function_call(&signature, &call.args)?,
+            signature,
+            schedule,
+            last_called: Mutex::new(None),
+        })
+    }
+}
+
+impl PolledView {
+    /// Returns whether the function is called after a block, recording
+    /// the call if it is.
+    pub fn due(&self, block_number: u64, timestamp: u64) -> bool {
+        let mut last_called = self.last_called.lock().unwrap();
+        let due = match self.schedule {
+            ViewSchedule::Blocks(every) => block_number % every == 0,
+            ViewSchedule::Minutes(minutes) => last_called.map_or(true, |last| {
+                timestamp >= last.saturating_add(minutes.saturating_mul(60))
+            }),
+        };
+        if due {
+            *last_called = Some(timestamp);
+        }
+        due
+    }
+
+    /// Decodes the return data with the contract's ABI, if loaded.
+    ///
+    /// Without the ABI, or a function matching the call, the raw return
+    /// data is kept under `returnData`.
+    pub fn decode(&self, abi: Option<&JsonAbi>, output: &[u8]) -> Value {
+        let function = abi.and_then(|abi| {
+            abi.functions()
+                .find(|function| function.selector().as_slice() == &self.calldata[..4])
+        });
+        let raw = || json!({ "returnData": Bytes::from(output.to_vec()) });
+        match function {
+            Some(function) => {
+                decode::decode_function_output(output, function).unwrap_or_else(|e| {
+                    log::warn!("Error decoding the results of view {}: {}", self.name, e);
+                    raw()
+                })
+            }
+            None => raw(),
+        }
+    }
+
+    /// Describes the results of a call after a block as a decoded event,
+    /// so they can be written to the event sinks.
+    ///
+    /// Like block summaries, its transaction hash is `block:<number>`,
+    /// and the log index is the position of the view in the config.
+    pub fn to_event(
+        &self,
+        index: usize,
+        block_number: u64,
+        timestamp: u64,
+        data: Value,
+    ) -> DecodedEvent {
+        DecodedEvent {
+            block_number,
+            block_timestamp: Some(timestamp),
+            transaction_hash: format!("block:{}", block_number),
+            log_index: index as u64,
+            address: format!("{:?}", self.to),
+            contract: VIEW_CONTRACT.to_owned(),
+            event: self.name.clone(),
+            signature: self.signature.clone(),
+            schema: None,
+            data,
+            raw: None,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_poll_views() {
+        let call = ViewCall {
+            to: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            signature: "getShadowMetrics()".to_owned(),
+            every: Some(10),
+            ..Default::default()
+        };
+        let view = PolledView::try_from(&call).unwrap();
+        assert_eq!(view.name, "getShadowMetrics");
+        assert!(view.due(17_000_000, 1_681_338_455));
+        assert!(!view.due(17_000_001, 1_681_338_467));
+
+        let abi: JsonAbi = serde_json::from_str(
+            r#"[{
+                "type": "function",
+                "name": "getShadowMetrics",
+                "stateMutability": "view",
+                "inputs": [],
+                "outputs": [
+                    { "name": "volume", "type": "uint256" },
+                    { "name": "trades", "type": "uint64" }
+                ]
+            }]"#,
+        )
+        .unwrap();
+        let output = ethabi::encode(&[
+            ethabi::Token::Uint(1000.into()),
+            ethabi::Token::Uint(3.into()),
+        ]);
+        assert_eq!(
+            view.decode(Some(&abi), &output),
+            json!({ "volume": "1000", "trades": "3" })
+        );
+        assert_eq!(view.decode(None, &[0xff]), json!({ "returnData": "0xff" }));
+
+        let event = view.to_event(2, 17_000_000, 1_681_338_455, json!({}));
+        assert_eq!(event.transaction_hash, "block:17000000");
+        assert_eq!(event.log_index, 2);
+        assert_eq!(event.contract, VIEW_CONTRACT);
+
+        let invalid = ViewCall {
+            every: Some(0),
+            ..call.clone()
+        };
+        assert!(PolledView::try_from(&invalid).is_err());
+        let invalid = ViewCall {
+            every_minutes: Some(5),
+            ..call.clone()
+        };
+        assert!(PolledView::try_from(&invalid).is_err());
+    }
+
+    #[test]
+    fn can_schedule_views_by_time() {
+        let call = ViewCall {
+            to: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            signature: "consult(uint32)".to_owned(),
+            args: vec!["1800".to_owned()],
+            every_minutes: Some(5),
+            ..Default::default()
+        };
+        let view = PolledView::try_from(&call).unwrap();
+        assert_eq!(view.schedule, ViewSchedule::Minutes(5));
+        // The first replayed block is always due
+        assert!(view.due(17_000_000, 1_000));
+        assert!(!view.due(17_000_001, 1_012));
+        assert!(!view.due(17_000_024, 1_288));
+        assert!(view.due(17_000_025, 1_300));
+        // Later calls count from the last one, not the first
+        assert!(!view.due(17_000_030, 1_500));
+        assert!(view.due(17_000_050, 1_612));
+
+        // A schedule too long to count in seconds is only due once
+        let view = PolledView::try_from(&ViewCall {
+            every_minutes: Some(u64::MAX),
+            ..call
+        })
+        .unwrap();
+        assert!(view.due(17_000_000, 1_000));
+        assert!(!view.due(17_000_001, u64::MAX - 1));
+    }
+}
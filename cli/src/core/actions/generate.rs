@@ -0,0 +1,940 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use thiserror::Error;
+
+mod spec;
+
+pub use spec::{parse_spec, Rule, When};
+
+/// The NatSpec tag that marks a function for instrumentation, e.g.
+/// `/// @custom:shadow emit gas`
+const ANNOTATION: &str = "@custom:shadow";
+
+/// The Solidity keywords and builtin symbols generated parameter names
+/// must not take, since they would fail to compile or shadow the builtin.
+const RESERVED: &[&str] = &[
+    "abi",
+    "addmod",
+    "after",
+    "alias",
+    "apply",
+    "auto",
+    "block",
+    "blockhash",
+    "byte",
+    "case",
+    "copyof",
+    "default",
+    "define",
+    "ecrecover",
+    "final",
+    "gasleft",
+    "immutable",
+    "implements",
+    "in",
+    "inline",
+    "keccak256",
+    "let",
+    "macro",
+    "match",
+    "msg",
+    "mulmod",
+    "mutable",
+    "now",
+    "null",
+    "of",
+    "override",
+    "partial",
+    "promise",
+    "reference",
+    "relocatable",
+    "ripemd160",
+    "sealed",
+    "selfdestruct",
+    "sha256",
+    "sizeof",
+    "static",
+    "super",
+    "supports",
+    "switch",
+    "this",
+    "tx",
+    "type",
+    "typedef",
+    "typeof",
+    "unchecked",
+    "virtual",
+];
+
+/// Injects instrumentation into a Solidity source, at the functions
+/// annotated with `/// @custom:shadow <patterns>`, and as declared by
+/// the rules of a spec file.
+///
+/// This action is used by the `generate` command.
+pub struct Generate {
+    /// The Solidity source to instrument
+    pub source: String,
+
+    /// The rules from a spec file, see [`parse_spec`]
+    pub rules: Vec<Rule>,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum GenerateError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+}
+
+/// An instrumentation pattern that can be injected into a function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Emit an event with the function's arguments when it is called
+    Emit,
+    /// Emit the gas used by each call of the function
+    Gas,
+}
+
+impl FromStr for Pattern {
+    type Err = GenerateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "emit" => Ok(Pattern::Emit),
+            "gas" => Ok(Pattern::Gas),
+            other => Err(GenerateError::CustomError(format!(
+                "Unknown instrumentation pattern {}, expected emit or gas",
+                other
+            ))),
+        }
+    }
+}
+
+/// An annotated function in the source.
+struct Function {
+    /// The function name
+    name: String,
+    /// The offset just after the closing parenthesis of the parameters
+    params_end: usize,
+    /// The offset just after the opening brace of the body
+    body_start: usize,
+    /// The indentation of the function declaration
+    indent: String,
+    /// The parameters, as `(type, name)`
+    params: Vec<(String, String)>,
+    /// The source of the parameter list, without parentheses
+    params_source: String,
+    /// The name of the enclosing contract
+    contract_name: String,
+    /// The offset just after the opening brace of the enclosing contract
+    contract_start: usize,
+    /// The indentation of the enclosing contract
+    contract_indent: String,
+}
+
+impl Generate {
+    /// Returns the instrumented source.
+    pub fn run(&self) -> Result<String, GenerateError> {
+        let source = self.source.as_str();
+        // Declarations are matched in the code only, not in comments or strings
+        let code = strip_comments(source);
+        let mut edits: Vec<(usize, String)> = Vec::new();
+        let mut declarations: BTreeMap<usize, (String, Vec<String>)> = BTreeMap::new();
+
+        let annotations = annotations(source)?;
+        if annotations.is_empty() && self.rules.is_empty() {
+            return Err(GenerateError::CustomError(format!(
+                "No functions are annotated with /// {}, and no rules are given",
+                ANNOTATION
+            )));
+        }
+
+        for (line, end, patterns) in annotations {
+            let function = parse_annotated(source, &code, end).map_err(|e| {
+                GenerateError::CustomError(format!("Annotation on line {}: {}", line, e))
+            })?;
+            let (_, contract) = declarations
+                .entry(function.contract_start)
+                .or_insert_with(|| (function.contract_indent.clone(), Vec::new()));
+            let body_indent = format!("{}    ", function.indent);
+
+            for pattern in patterns {
+                match pattern {
+                    Pattern::Emit => {
+                        let event = format!("Shadow{}", upper_first(&function.name));
+                        let params = function
+                            .params
+                            .iter()
+                            .map(|(ty, name)| format!("{} {}", ty, name))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let args = function
+                            .params
+                            .iter()
+                            .map(|(_, name)| name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        push_unique(contract, format!("event {}({});", event, params));
+                        edits.push((
+                            function.body_start,
+                            format!("\n{}emit {}({});", body_indent, event, args),
+                        ));
+                    }
+                    Pattern::Gas => {
+                        push_unique(
+                            contract,
+                            "event ShadowGasUsed(string name, uint256 gasUsed);".to_owned(),
+                        );
+                        push_unique(
+                            contract,
+                            [
+                                "modifier shadowGas(string memory name) {",
+                                "    uint256 gasStart = gasleft();",
+                                "    _;",
+                                "    emit ShadowGasUsed(name, gasStart - gasleft());",
+                                "}",
+                            ]
+                            .join("\n"),
+                        );
+                        edits.push((
+                            function.params_end,
+                            format!(" shadowGas(\"{}\")", function.name),
+                        ));
+                    }
+                }
+            }
+        }
+
+        for rule in &self.rules {
+            let functions = find_functions(&code, &rule.function)
+                .map_err(|e| GenerateError::CustomError(format!("Spec line {}: {}", rule.line, e)))?
+                .into_iter()
+                .filter(|f| {
+                    rule.contract
+                        .as_ref()
+                        .map_or(true, |c| *c == f.contract_name)
+                })
+                .collect::<Vec<_>>();
+            if functions.is_empty() {
+                return Err(GenerateError::CustomError(format!(
+                    "Spec line {}: function {} not found",
+                    rule.line, rule.function
+                )));
+            }
+
+            // Overloads get their own modifiers, which can't be overloaded
+            let overloaded: Vec<bool> = functions
+                .iter()
+                .map(|f| {
+                    functions
+                        .iter()
+                        .filter(|g| g.contract_start == f.contract_start)
+                        .count()
+                        > 1
+                })
+                .collect();
+
+            for (function, overloaded) in functions.into_iter().zip(overloaded) {
+                let args = resolve_args(rule, &function).map_err(|e| {
+                    GenerateError::CustomError(format!("Spec line {}: {}", rule.line, e))
+                })?;
+                let (_, contract) = declarations
+                    .entry(function.contract_start)
+                    .or_insert_with(|| (function.contract_indent.clone(), Vec::new()));
+                let params = args
+                    .iter()
+                    .map(|arg| format!("{} {}", arg.ty, arg.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let values = args
+                    .iter()
+                    .map(|arg| arg.value.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                push_unique(contract, format!("event {}({});", rule.event, params));
+
+                match rule.when {
+                    When::Before => edits.push((
+                        function.body_start,
+                        format!("\n{}    emit {}({});", function.indent, rule.event, values),
+                    )),
+                    When::After => {
+                        // The event is emitted by a modifier, so it also runs
+                        // after early returns. Parameters are passed to it.
+                        let mut modifier =
+                            format!("shadowAfter{}{}", upper_first(&function.name), rule.event);
+                        if overloaded {
+                            modifier.push_str(&format!("_{}", selector(&function)));
+                        }
+                        let passed: Vec<&Arg> = args.iter().filter(|arg| !arg.builtin).collect();
+                        // Parameters are passed to the modifier under their event name
+                        let emitted = args
+                            .iter()
+                            .map(|arg| if arg.builtin { &arg.value } else { &arg.name })
+                            .map(String::as_str)
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        push_unique(
+                            contract,
+                            format!(
+                                "modifier {}({}) {{\n    _;\n    emit {}({});\n}}",
+                                modifier,
+                                passed
+                                    .iter()
+                                    .map(|arg| format!("{} {}", arg.location_ty, arg.name))
+                                    .collect::<Vec<_>>()
+                                    .join(", "),
+                                rule.event,
+                                emitted
+                            ),
+                        );
+                        edits.push((
+                            function.params_end,
+                            format!(
+                                " {}({})",
+                                modifier,
+                                passed
+                                    .iter()
+                                    .map(|arg| arg.value.as_str())
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+
+        for (contract_start, (indent, items)) in declarations {
+            let indent = format!("{}    ", indent);
+            let mut block = format!("\n{}// Added by shadow generate", indent);
+            for item in items {
+                for line in item.lines() {
+                    block.push_str(&format!("\n{}{}", indent, line));
+                }
+            }
+            block.push('\n');
+            edits.push((contract_start, block));
+        }
+
+        // Apply the edits from the end, so earlier offsets stay valid.
+        // Edits at the same offset keep the order they were made in.
+        let mut edits: Vec<(usize, usize, String)> = edits
+            .into_iter()
+            .enumerate()
+            .map(|(index, (offset, text))| (offset, index, text))
+            .collect();
+        edits.sort_by(|a, b| (b.0, b.1).cmp(&(a.0, a.1)));
+        let mut instrumented = source.to_owned();
+        for (offset, _, text) in edits {
+            instrumented.insert_str(offset, &text);
+        }
+        Ok(instrumented)
+    }
+}
+
+/// Returns the annotations in the source, as the line number, the offset
+/// of the end of the annotation line, and the patterns.
+fn annotations(source: &str) -> Result<Vec<(usize, usize, Vec<Pattern>)>, GenerateError> {
+    let mut annotations = Vec::new();
+    let mut offset = 0;
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        offset += line.len();
+        let patterns = match line
+            .trim()
+            .strip_prefix("///")
+            .and_then(|comment| comment.trim_start().strip_prefix(ANNOTATION))
+        {
+            Some(patterns) => patterns,
+            None => continue,
+        };
+        let patterns = patterns
+            .split_whitespace()
+            .map(Pattern::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| GenerateError::CustomError(format!("Line {}: {}", index + 1, e)))?;
+        if patterns.is_empty() {
+            return Err(GenerateError::CustomError(format!(
+                "Line {}: expected at least one pattern, e.g. {} emit",
+                index + 1,
+                ANNOTATION
+            )));
+        }
+        annotations.push((index + 1, offset, patterns));
+    }
+    Ok(annotations)
+}
+
+/// Blanks out the comments and string literals of a source, keeping
+/// every other character at its offset.
+fn strip_comments(source: &str) -> String {
+    enum State {
+        Code,
+        LineComment,
+        BlockComment,
+        Literal(char),
+    }
+
+    fn blank(stripped: &mut String, c: char) {
+        match c {
+            '\n' => stripped.push('\n'),
+            c => stripped.extend(std::iter::repeat(' ').take(c.len_utf8())),
+        }
+    }
+
+    let mut stripped = String::with_capacity(source.len());
+    let mut state = State::Code;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match state {
+            State::Code => match (c, chars.peek()) {
+                ('/', Some('/')) => {
+                    state = State::LineComment;
+                    blank(&mut stripped, c);
+                }
+                ('/', Some('*')) => {
+                    state = State::BlockComment;
+                    chars.next();
+                    stripped.push_str("  ");
+                }
+                ('"' | '\'', _) => {
+                    state = State::Literal(c);
+                    stripped.push(c);
+                }
+                _ => stripped.push(c),
+            },
+            State::LineComment => {
+                if c == '\n' {
+                    state = State::Code;
+                }
+                blank(&mut stripped, c);
+            }
+            State::BlockComment => {
+                blank(&mut stripped, c);
+                if c == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    state = State::Code;
+                    stripped.push(' ');
+                }
+            }
+            State::Literal(quote) => {
+                if c == quote {
+                    state = State::Code;
+                    stripped.push(c);
+                    continue;
+                }
+                blank(&mut stripped, c);
+                if c == '\\' {
+                    if let Some(escaped) = chars.next() {
+                        blank(&mut stripped, escaped);
+                    }
+                }
+            }
+        }
+    }
+    stripped
+}
+
+/// Parses the function declared after the annotation ending at the
+/// given offset, in the source and the code stripped of comments.
+fn parse_annotated(source: &str, code: &str, from: usize) -> Result<Function, String> {
+    let start = from
+        + code[from..]
+            .find("function ")
+            .ok_or("no function follows it")?;
+    // Only comments may separate the annotation from the function
+    let between = &source[from..start];
+    let line_start = between.rfind('\n').map_or(from, |i| from + i + 1);
+    if source[from..line_start].lines().any(|line| {
+        let line = line.trim();
+        !(line.is_empty()
+            || line.starts_with("//")
+            || line.starts_with('*')
+            || line.starts_with("/*"))
+    }) || !source[line_start..start].trim().is_empty()
+    {
+        return Err("it is not directly followed by a function".to_owned());
+    }
+    parse_function(code, start)
+}
+
+/// Returns every function with the given name and a body in the code.
+fn find_functions(source: &str, name: &str) -> Result<Vec<Function>, String> {
+    let declaration = format!("function {}", name);
+    source
+        .match_indices(&declaration)
+        .map(|(start, _)| start)
+        .filter(|&start| {
+            let rest = source[start + declaration.len()..].trim_start();
+            // Skip other functions sharing the prefix, and declarations without a body
+            let end = match rest.starts_with('(').then(|| matching_paren(rest).ok()) {
+                Some(Some(end)) => end,
+                _ => return false,
+            };
+            rest[end..]
+                .find(|c| c == '{' || c == ';')
+                .map(|i| rest.as_bytes()[end + i])
+                == Some(b'{')
+        })
+        .map(|start| parse_function(source, start))
+        .collect()
+}
+
+/// Parses the function declared at the given offset.
+fn parse_function(source: &str, start: usize) -> Result<Function, String> {
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let indent: String = source[line_start..start]
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+
+    let name_start = start + "function ".len();
+    let name: String = source[name_start..]
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .collect();
+    let params_start = name_start
+        + source[name_start..]
+            .find('(')
+            .ok_or("the function has no parameter list")?;
+    let params_end = params_start + matching_paren(&source[params_start..])? + 1;
+    let body = source[params_end..]
+        .find(|c| c == '{' || c == ';')
+        .map(|i| params_end + i)
+        .ok_or("the function has no body")?;
+    if &source[body..=body] == ";" {
+        return Err(format!("function {} has no body", name));
+    }
+    let header = &source[params_end..body];
+    if header
+        .split_whitespace()
+        .any(|word| word == "view" || word == "pure")
+    {
+        return Err(format!(
+            "function {} is view or pure, so it can't emit events",
+            name
+        ));
+    }
+
+    let params = parse_params(&source[params_start + 1..params_end - 1])
+        .map_err(|e| format!("function {}: {}", name, e))?;
+    let (contract_name, contract_start, contract_indent) = enclosing_contract(source, start)?;
+
+    Ok(Function {
+        name,
+        params_end,
+        body_start: body + 1,
+        indent,
+        params,
+        params_source: source[params_start + 1..params_end - 1].to_owned(),
+        contract_name,
+        contract_start,
+        contract_indent,
+    })
+}
+
+/// Returns the offset of the parenthesis closing the one the input starts with.
+fn matching_paren(input: &str) -> Result<usize, String> {
+    let mut depth = 0;
+    for (index, c) in input.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err("the parameter list is not closed".to_owned())
+}
+
+/// Parses a parameter list into `(type, name)` pairs, without data locations.
+fn parse_params(params: &str) -> Result<Vec<(String, String)>, String> {
+    Ok(parse_params_with_location(params)?
+        .into_iter()
+        .map(|(ty, _, name)| (ty, name))
+        .collect())
+}
+
+/// Parses a parameter list into `(type, type with data location, name)`.
+fn parse_params_with_location(params: &str) -> Result<Vec<(String, String, String)>, String> {
+    params
+        .split(',')
+        .map(str::trim)
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let words: Vec<&str> = param.split_whitespace().collect();
+            match words.as_slice() {
+                [ty @ .., name] if !ty.is_empty() => {
+                    let bare: Vec<&str> = ty
+                        .iter()
+                        .copied()
+                        .filter(|w| !matches!(*w, "memory" | "calldata" | "storage" | "payable"))
+                        .collect();
+                    // Modifier parameters can't be calldata
+                    let located: Vec<&str> = ty
+                        .iter()
+                        .map(|w| if *w == "calldata" { "memory" } else { w })
+                        .collect();
+                    Ok((bare.join(" "), located.join(" "), (*name).to_owned()))
+                }
+                _ => Err(format!("parameter {} has no name", param)),
+            }
+        })
+        .collect()
+}
+
+/// Returns the name of the contract enclosing the given offset, the
+/// offset just after its opening brace, and its indentation.
+fn enclosing_contract(source: &str, offset: usize) -> Result<(String, usize, String), String> {
+    let (keyword, kind) = ["contract ", "interface ", "library "]
+        .iter()
+        .flat_map(|kind| {
+            source[..offset]
+                .match_indices(kind)
+                .filter(|&(i, _)| i == 0 || source[..i].ends_with(char::is_whitespace))
+        })
+        .max_by_key(|&(i, _)| i)
+        .ok_or("the function is not in a contract")?;
+    if kind != "contract " {
+        return Err("the function is not in a contract".to_owned());
+    }
+    let brace = keyword
+        + source[keyword..]
+            .find('{')
+            .ok_or("the contract has no body")?;
+    let line_start = source[..keyword].rfind('\n').map_or(0, |i| i + 1);
+    let indent: String = source[line_start..keyword]
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+    let name = source[keyword + "contract ".len()..]
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .collect();
+    Ok((name, brace + 1, indent))
+}
+
+/// An argument of a rule's event.
+struct Arg {
+    /// The event parameter type
+    ty: String,
+    /// The parameter type with its data location, for modifiers
+    location_ty: String,
+    /// The event parameter name
+    name: String,
+    /// The expression emitted
+    value: String,
+    /// Whether the value is a transaction or block value, rather than
+    /// a function parameter
+    builtin: bool,
+}
+
+/// Resolves the types of a rule's arguments, from the function's
+/// parameters and the builtin values.
+///
+/// Event parameter names are escaped with a trailing `_` when they are
+/// reserved, or when a builtin's name is taken by a function parameter.
+fn resolve_args(rule: &Rule, function: &Function) -> Result<Vec<Arg>, String> {
+    let params = parse_params_with_location(&function.params_source)?;
+    let param_names: Vec<String> = params.iter().map(|(_, _, name)| name.clone()).collect();
+    rule.args
+        .iter()
+        .map(|arg| {
+            if let Some((ty, location_ty, name)) = params.iter().find(|(_, _, name)| name == arg) {
+                return Ok(Arg {
+                    ty: ty.clone(),
+                    location_ty: location_ty.clone(),
+                    name: escape_name(name, &[]),
+                    value: arg.clone(),
+                    builtin: false,
+                });
+            }
+            spec::BUILTINS
+                .iter()
+                .find(|(value, _, _)| value == arg)
+                .map(|(value, ty, name)| Arg {
+                    ty: (*ty).to_owned(),
+                    location_ty: (*ty).to_owned(),
+                    name: escape_name(name, &param_names),
+                    value: (*value).to_owned(),
+                    builtin: true,
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "{} is neither a parameter of {} nor one of {}",
+                        arg,
+                        function.name,
+                        spec::BUILTINS
+                            .iter()
+                            .map(|(value, _, _)| *value)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Appends `_` to a name until it is neither reserved nor taken.
+fn escape_name(name: &str, taken: &[String]) -> String {
+    let mut name = name.to_owned();
+    while RESERVED.contains(&name.as_str()) || taken.contains(&name) {
+        name.push('_');
+    }
+    name
+}
+
+/// Returns the selector of a function, as hex without the `0x` prefix.
+fn selector(function: &Function) -> String {
+    let types = function
+        .params
+        .iter()
+        .map(|(ty, _)| canonical_type(ty))
+        .collect::<Vec<_>>()
+        .join(",");
+    hex::encode(ethers::utils::id(format!("{}({})", function.name, types)))
+}
+
+/// Returns the canonical name of a parameter type, as in signatures.
+fn canonical_type(ty: &str) -> String {
+    let (base, dimensions) = ty.split_at(ty.find('[').unwrap_or(ty.len()));
+    let base = match base {
+        "uint" => "uint256",
+        "int" => "int256",
+        "byte" => "bytes1",
+        base => base,
+    };
+    format!("{}{}", base, dimensions)
+}
+
+fn push_unique(items: &mut Vec<String>, item: String) {
+    if !items.contains(&item) {
+        items.push(item);
+    }
+}
+
+fn upper_first(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_instrument_functions() {
+        let source = r#"pragma solidity ^0.8.0;
+
+contract Vault {
+    uint256 public total;
+
+    /// @notice Deposits tokens
+    /// @custom:shadow emit gas
+    function deposit(address token, uint256[] memory amounts) external returns (uint256) {
+        total += amounts.length;
+        return total;
+    }
+
+    /// @custom:shadow gas
+    function withdraw() external {
+        total = 0;
+    }
+}
+"#;
+        let generate = Generate {
+            source: source.to_owned(),
+            rules: Vec::new(),
+        };
+        assert_eq!(
+            generate.run().unwrap(),
+            r#"pragma solidity ^0.8.0;
+
+contract Vault {
+    // Added by shadow generate
+    event ShadowDeposit(address token, uint256[] amounts);
+    event ShadowGasUsed(string name, uint256 gasUsed);
+    modifier shadowGas(string memory name) {
+        uint256 gasStart = gasleft();
+        _;
+        emit ShadowGasUsed(name, gasStart - gasleft());
+    }
+
+    uint256 public total;
+
+    /// @notice Deposits tokens
+    /// @custom:shadow emit gas
+    function deposit(address token, uint256[] memory amounts) shadowGas("deposit") external returns (uint256) {
+        emit ShadowDeposit(token, amounts);
+        total += amounts.length;
+        return total;
+    }
+
+    /// @custom:shadow gas
+    function withdraw() shadowGas("withdraw") external {
+        total = 0;
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn can_apply_rules() {
+        let source = r#"interface IRouter {
+    function swap(uint256 amountIn, address[] calldata path) external returns (uint256);
+}
+
+contract Router {
+    function swap(uint256 amountIn, address[] calldata path) external returns (uint256) {
+        return amountIn;
+    }
+}
+"#;
+        let generate = Generate {
+            source: source.to_owned(),
+            rules: parse_spec(
+                "after Router.swap emit Swapped(amountIn, path, msg.sender)\nbefore swap emit Swapping(block.number)",
+            )
+            .unwrap(),
+        };
+        assert_eq!(
+            generate.run().unwrap(),
+            r#"interface IRouter {
+    function swap(uint256 amountIn, address[] calldata path) external returns (uint256);
+}
+
+contract Router {
+    // Added by shadow generate
+    event Swapped(uint256 amountIn, address[] path, address sender);
+    modifier shadowAfterSwapSwapped(uint256 amountIn, address[] memory path) {
+        _;
+        emit Swapped(amountIn, path, msg.sender);
+    }
+    event Swapping(uint256 blockNumber);
+
+    function swap(uint256 amountIn, address[] calldata path) shadowAfterSwapSwapped(amountIn, path) external returns (uint256) {
+        emit Swapping(block.number);
+        return amountIn;
+    }
+}
+"#
+        );
+
+        for spec in [
+            "after swap emit Swapped(amountOut)",
+            "after Pair.swap emit Swapped()",
+            "after burn emit Burned()",
+        ] {
+            let generate = Generate {
+                source: source.to_owned(),
+                rules: parse_spec(spec).unwrap(),
+            };
+            assert!(generate.run().is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn ignores_comments_and_strings() {
+        let source = r#"contract Router {
+    // contract Fake {
+    string constant NAME = "contract Other { function swap(uint256 x) external {} }";
+    /* contract Hidden {
+       function swap(uint256 x) external {} */
+    function swap(uint256 amountIn) external {
+    }
+}
+"#;
+        let generate = Generate {
+            source: source.to_owned(),
+            rules: parse_spec("after swap emit Swapped(amountIn)").unwrap(),
+        };
+        let instrumented = generate.run().unwrap();
+        assert!(instrumented.starts_with("contract Router {\n    // Added by shadow generate\n"));
+        assert!(instrumented
+            .contains("function swap(uint256 amountIn) shadowAfterSwapSwapped(amountIn) external"));
+        assert_eq!(instrumented.matches("shadowAfterSwapSwapped").count(), 2);
+    }
+
+    #[test]
+    fn suffixes_overloads_with_their_selector() {
+        let source = r#"contract Router {
+    function swap(uint256 amountIn) external {
+    }
+
+    function swap(uint amountIn, address to) external {
+    }
+}
+"#;
+        let generate = Generate {
+            source: source.to_owned(),
+            rules: parse_spec("after swap emit Swapped(amountIn)").unwrap(),
+        };
+        let instrumented = generate.run().unwrap();
+        for signature in ["swap(uint256)", "swap(uint256,address)"] {
+            let modifier = format!(
+                "shadowAfterSwapSwapped_{}",
+                hex::encode(ethers::utils::id(signature))
+            );
+            assert!(
+                instrumented.contains(&format!("modifier {}(uint256 amountIn)", modifier)),
+                "{}",
+                instrumented
+            );
+            assert!(instrumented.contains(&format!(") {}(amountIn) external", modifier)));
+        }
+    }
+
+    #[test]
+    fn escapes_reserved_and_taken_names() {
+        let source = r#"contract Vault {
+    function deposit(address sender, uint256 tx) external {
+    }
+}
+"#;
+        let generate = Generate {
+            source: source.to_owned(),
+            rules: parse_spec(
+                "before deposit emit Deposited(sender, msg.sender)\nafter deposit emit Settled(tx, msg.sender)",
+            )
+            .unwrap(),
+        };
+        let instrumented = generate.run().unwrap();
+        for expected in [
+            "event Deposited(address sender, address sender_);",
+            "event Settled(uint256 tx_, address sender_);",
+            "modifier shadowAfterDepositSettled(uint256 tx_) {",
+            "emit Settled(tx_, msg.sender);",
+            "shadowAfterDepositSettled(tx) external {",
+            "emit Deposited(sender, msg.sender);",
+        ] {
+            assert!(instrumented.contains(expected), "{}", expected);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_annotations() {
+        for source in [
+            "contract A {\n    uint256 x;\n}\n",
+            "contract A {\n    /// @custom:shadow trace\n    function f() external {}\n}\n",
+            "contract A {\n    /// @custom:shadow emit\n    function f() external view returns (uint256) { return 1; }\n}\n",
+            "contract A {\n    /// @custom:shadow emit\n    uint256 x;\n    function f() external {}\n}\n",
+            "interface A {\n    /// @custom:shadow emit\n    function f(uint256) external;\n}\n",
+        ] {
+            let generate = Generate {
+                source: source.to_owned(),
+                rules: Vec::new(),
+            };
+            assert!(generate.run().is_err(), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use super::GenerateError;
+
+/// When a rule's event is emitted, relative to the function body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum When {
+    /// Before the function body runs
+    Before,
+    /// After the function body completed without reverting
+    After,
+}
+
+/// An instrumentation rule from a spec file, e.g.
+/// `after Router.swap emit Swapped(amountIn, msg.sender)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    /// The line of the spec file the rule is on
+    pub line: usize,
+    /// When the event is emitted
+    pub when: When,
+    /// The contract the function is in, if set. Otherwise the function
+    /// is instrumented in every contract.
+    pub contract: Option<String>,
+    /// The name of the function to instrument
+    pub function: String,
+    /// The name of the event to emit
+    pub event: String,
+    /// The event arguments, which are function parameters or
+    /// transaction and block values, e.g. `msg.sender`
+    pub args: Vec<String>,
+}
+
+/// The values other than function parameters that rules can emit,
+/// with their type and the name of the event parameter.
+pub const BUILTINS: &[(&str, &str, &str)] = &[
+    ("msg.sender", "address", "sender"),
+    ("msg.value", "uint256", "value"),
+    ("tx.origin", "address", "origin"),
+    ("block.number", "uint256", "blockNumber"),
+    ("block.timestamp", "uint256", "timestamp"),
+];
+
+/// Parses a spec file into rules.
+///
+/// Each line is a rule of the form
+/// `<before|after> [Contract.]function emit Event(arg, ...)`.
+/// Blank lines and lines starting with `#` are ignored.
+pub fn parse_spec(spec: &str) -> Result<Vec<Rule>, GenerateError> {
+    spec.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line, rule)| {
+            parse_rule(line, rule)
+                .map_err(|e| GenerateError::CustomError(format!("Spec line {}: {}", line, e)))
+        })
+        .collect()
+}
+
+fn parse_rule(line: usize, rule: &str) -> Result<Rule, String> {
+    let (when, rest) = split_word(rule);
+    let when = match when {
+        "before" => When::Before,
+        "after" => When::After,
+        _ => return Err("expected the rule to start with before or after".to_owned()),
+    };
+    let (target, rest) = split_word(rest);
+    if target.is_empty() {
+        return Err("expected a function".to_owned());
+    }
+    let (emit, event) = split_word(rest);
+    if emit != "emit" {
+        return Err("expected emit after the function".to_owned());
+    }
+
+    let (contract, function) = match target.split_once('.') {
+        Some((contract, function)) => (Some(contract.to_owned()), function.to_owned()),
+        None => (None, target.to_owned()),
+    };
+    if !is_identifier(&function) || !contract.as_deref().map_or(true, is_identifier) {
+        return Err(format!("invalid function {}", target));
+    }
+
+    let (name, args) = event
+        .strip_suffix(')')
+        .and_then(|event| event.split_once('('))
+        .ok_or_else(|| format!("expected an event like Name(arg, ...), got {}", event))?;
+    let name = name.trim();
+    if !is_identifier(name) {
+        return Err(format!("invalid event name {}", name));
+    }
+    let args: Vec<String> = args
+        .split(',')
+        .map(str::trim)
+        .filter(|arg| !arg.is_empty())
+        .map(str::to_owned)
+        .collect();
+
+    Ok(Rule {
+        line,
+        when,
+        contract,
+        function,
+        event: name.to_owned(),
+        args,
+    })
+}
+
+/// Splits off the first word, returning it and the trimmed rest.
+fn split_word(input: &str) -> (&str, &str) {
+    let input = input.trim_start();
+    match input.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (input, ""),
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_spec() {
+        let spec = "# Swaps\nafter Router.swap emit Swapped(amountIn, msg.sender)\n\nbefore deposit emit Depositing()\n";
+        assert_eq!(
+            parse_spec(spec).unwrap(),
+            vec![
+                Rule {
+                    line: 2,
+                    when: When::After,
+                    contract: Some("Router".to_owned()),
+                    function: "swap".to_owned(),
+                    event: "Swapped".to_owned(),
+                    args: vec!["amountIn".to_owned(), "msg.sender".to_owned()],
+                },
+                Rule {
+                    line: 4,
+                    when: When::Before,
+                    contract: None,
+                    function: "deposit".to_owned(),
+                    event: "Depositing".to_owned(),
+                    args: vec![],
+                },
+            ]
+        );
+
+        assert!(parse_spec("during swap emit Swapped()").is_err());
+        assert!(parse_spec("after swap Swapped()").is_err());
+        assert!(parse_spec("after swap emit Swapped").is_err());
+        assert!(parse_spec("after 1swap emit Swapped()").is_err());
+    }
+}
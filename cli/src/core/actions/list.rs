@@ -0,0 +1,36 @@
+use thiserror::Error;
+
+use crate::core::resources::shadow::{ShadowContract, ShadowResource};
+
+/// Lists the shadow contracts in the Shadow store.
+///
+/// This action is used by the `list` command.
+pub struct List<S: ShadowResource> {
+    /// Only list the shadow contracts with any of these tags
+    pub tags: Vec<String>,
+
+    /// The Shadow resource
+    pub shadow_resource: S,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum ListError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+}
+
+impl<S: ShadowResource> List<S> {
+    pub async fn run(&self) -> Result<Vec<ShadowContract>, ListError> {
+        let shadow_contracts = self
+            .shadow_resource
+            .list()
+            .await
+            .map_err(|e| ListError::CustomError(e.to_string()))?;
+        Ok(shadow_contracts
+            .into_iter()
+            .filter(|c| c.has_any_tag(&self.tags))
+            .collect())
+    }
+}
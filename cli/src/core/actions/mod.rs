@@ -1,7 +1,39 @@
+pub mod attest;
+pub mod backup;
+pub mod bindings;
+pub mod bundle;
+pub mod cheats;
 pub mod deploy;
+pub mod discover;
 pub mod events;
+pub mod export_events;
 pub mod fork;
+pub mod generate;
+pub mod list;
+pub mod patch;
+pub mod redrive;
+pub mod stats;
+pub mod storage;
+pub mod tag;
+pub mod toggle;
+pub mod verify;
 
+pub use attest::Attest;
+pub use backup::{Backup, Restore};
+pub use bindings::Bindings;
+pub use bundle::{Export, Import};
+pub use cheats::Cheats;
 pub use deploy::Deploy;
+pub use discover::Discover;
 pub use events::Events;
+pub use export_events::ExportEvents;
 pub use fork::Fork;
+pub use generate::Generate;
+pub use list::List;
+pub use patch::Patch;
+pub use redrive::Redrive;
+pub use stats::Stats;
+pub use storage::Storage;
+pub use tag::Tag;
+pub use toggle::Toggle;
+pub use verify::Verify;
@@ -0,0 +1,178 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ethers::{
+    providers::{JsonRpcClient, Middleware, Provider, ProviderError},
+    types::{Address, H256, U256},
+};
+use thiserror::Error;
+
+use crate::{
+    core::resources::{artifacts::ArtifactsResource, shadow::ShadowContract},
+    storage::{word, StorageLayout},
+};
+
+/// Sets state variables of a shadow contract on the running shadow fork,
+/// without redeploying it.
+///
+/// The slots are computed from the contract's storage layout, so only
+/// the values that should differ from mainnet need to be listed, e.g.
+/// a fee or an admin address.
+///
+/// This action is used by the `patch` command.
+pub struct Patch<A: ArtifactsResource, P: JsonRpcClient> {
+    /// The provider of the running shadow fork
+    pub provider: Provider<P>,
+    /// The Artifacts resource, used to get the storage layout
+    pub artifacts_resource: A,
+    /// The shadow contract to patch
+    pub shadow_contract: ShadowContract,
+    /// The values to set
+    pub patches: Vec<PatchEntry>,
+    /// Only compute the slots, without writing them
+    pub dry_run: bool,
+}
+
+/// A value to set, from a line of a patch file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchEntry {
+    /// The line of the patch file the value is on
+    pub line: usize,
+    /// The variable path, e.g. `config.fee` or `balances[0xab...]`
+    pub path: String,
+    /// The value, e.g. `30` or `"name"`
+    pub value: String,
+}
+
+/// A slot written by the patch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchedSlot {
+    /// The variable path the slot belongs to
+    pub path: String,
+    pub slot: H256,
+    /// The slot value before the patch
+    pub previous: H256,
+    /// The slot value after the patch
+    pub value: H256,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum PatchError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Provider error
+    #[error("ProviderError: {0}")]
+    ProviderError(#[from] ProviderError),
+}
+
+impl<A: ArtifactsResource, P: JsonRpcClient> Patch<A, P> {
+    pub async fn run(&self) -> Result<Vec<PatchedSlot>, PatchError> {
+        let layout = StorageLayout::from_artifacts(
+            &self.artifacts_resource,
+            &self.shadow_contract.file_name,
+            &self.shadow_contract.contract_name,
+        )
+        .map_err(PatchError::CustomError)?;
+        let address = Address::from_str(&self.shadow_contract.address)
+            .map_err(|e| PatchError::CustomError(format!("Invalid address: {}", e)))?;
+
+        // Compute every write first, so a bad line doesn't leave the
+        // contract half patched
+        let mut writes = Vec::new();
+        for patch in &self.patches {
+            let slot_writes = layout
+                .locate(&patch.path)
+                .and_then(|location| layout.encode(&location, &patch.value))
+                .map_err(|e| PatchError::CustomError(format!("Line {}: {}", patch.line, e)))?;
+            writes.extend(slot_writes.into_iter().map(|write| (patch, write)));
+        }
+
+        // Merge the writes into the current slot values. Packed variables
+        // share a slot, so later writes build on earlier ones.
+        let mut words: HashMap<U256, [u8; 32]> = HashMap::new();
+        let mut patched = Vec::new();
+        for (patch, write) in writes {
+            let previous = match words.get(&write.slot) {
+                Some(current) => *current,
+                None => {
+                    self.provider
+                        .get_storage_at(address, H256(word(write.slot)), None)
+                        .await?
+                        .0
+                }
+            };
+            let value = write.apply(previous);
+            words.insert(write.slot, value);
+            patched.push(PatchedSlot {
+                path: patch.path.clone(),
+                slot: H256(word(write.slot)),
+                previous: H256(previous),
+                value: H256(value),
+            });
+        }
+
+        if !self.dry_run {
+            for slot in &patched {
+                self.provider
+                    .request::<_, bool>("anvil_setStorageAt", (address, slot.slot, slot.value))
+                    .await?;
+            }
+        }
+        Ok(patched)
+    }
+}
+
+/// Parses a patch file into the values to set.
+///
+/// Each line is of the form `<variable path> = <value>`, e.g.
+/// `config.fee = 30`. Blank lines and lines starting with `#` are ignored.
+pub fn parse_patch_file(contents: &str) -> Result<Vec<PatchEntry>, PatchError> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line, entry)| {
+            let (path, value) = entry
+                .split_once('=')
+                .map(|(path, value)| (path.trim(), value.trim()))
+                .filter(|(path, value)| !path.is_empty() && !value.is_empty())
+                .ok_or_else(|| {
+                    PatchError::CustomError(format!("Line {}: expected <variable> = <value>", line))
+                })?;
+            Ok(PatchEntry {
+                line,
+                path: path.to_owned(),
+                value: value.to_owned(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_patch_file() {
+        let contents = "# Shadow config\nconfig.fee = 30\n\nname = \"a = b\"\n";
+        assert_eq!(
+            parse_patch_file(contents).unwrap(),
+            vec![
+                PatchEntry {
+                    line: 2,
+                    path: "config.fee".to_owned(),
+                    value: "30".to_owned(),
+                },
+                PatchEntry {
+                    line: 4,
+                    path: "name".to_owned(),
+                    value: "\"a = b\"".to_owned(),
+                },
+            ]
+        );
+        assert!(parse_patch_file("config.fee 30").is_err());
+        assert!(parse_patch_file("config.fee =").is_err());
+    }
+}
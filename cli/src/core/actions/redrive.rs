@@ -0,0 +1,285 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use thiserror::Error;
+
+use crate::{
+    core::{
+        actions::events::get_event,
+        resources::{
+            artifacts::ArtifactsResource,
+            dead_letter::{DeadLetter, DeadLetterResource, FailureStage},
+            sink::{DecodedEvent, EventSink},
+        },
+    },
+    decode,
+};
+
+/// Re-processes the logs in the dead-letter queue, e.g. after an
+/// ABI or sink fix.
+///
+/// Logs that failed to decode or didn't match their schema are decoded
+/// again with the current artifacts, and validated. Decoded events are
+/// written to the given sinks. Letters that fail again stay in the queue
+/// with their new error.
+///
+/// This action is used by the `redrive` command.
+pub struct Redrive<D: DeadLetterResource, A: ArtifactsResource> {
+    /// The dead-letter queue
+    pub dead_letters: D,
+
+    /// The Artifacts resource, used to decode the logs again
+    pub artifacts_resource: A,
+
+    /// The sinks redriven events are written to
+    pub sinks: Vec<Box<dyn EventSink>>,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum RedriveError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Error related to the dead-letter queue
+    #[error("DeadLetterError: {0}")]
+    DeadLetterError(Box<dyn std::error::Error>),
+}
+
+/// The outcome of a redrive.
+#[derive(Debug, Default)]
+pub struct RedriveReport {
+    /// The events that were processed successfully
+    pub redriven: Vec<DecodedEvent>,
+    /// The letters that failed again, which stay in the queue
+    pub failed: Vec<DeadLetter>,
+}
+
+impl<D: DeadLetterResource, A: ArtifactsResource> Redrive<D, A> {
+    pub async fn run(&self) -> Result<RedriveReport, RedriveError> {
+        // Without a sink, every letter would be dropped from the queue
+        if self.sinks.is_empty() {
+            return Err(RedriveError::CustomError(
+                "Redriving needs a sink to write the events to".to_owned(),
+            ));
+        }
+        let listed = self
+            .dead_letters
+            .list()
+            .map_err(RedriveError::DeadLetterError)?;
+
+        // Redrive each log once, with its latest letter
+        let mut letters: Vec<DeadLetter> = Vec::new();
+        let mut positions = HashMap::new();
+        for letter in &listed {
+            match positions.get(&letter.key()) {
+                Some(&position) => letters[position] = letter.clone(),
+                None => {
+                    positions.insert(letter.key(), letters.len());
+                    letters.push(letter.clone());
+                }
+            }
+        }
+
+        let mut report = RedriveReport::default();
+        for mut letter in letters {
+            match self.redrive(&letter).await {
+                Ok(event) => report.redriven.push(event),
+                Err(e) => {
+                    letter.error = e;
+                    letter.failed_at = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs();
+                    report.failed.push(letter);
+                }
+            }
+        }
+
+        self.dead_letters
+            .resolve(&listed, &report.failed)
+            .map_err(RedriveError::DeadLetterError)?;
+        Ok(report)
+    }
+
+    /// Processes a dead letter, returning the error if it fails again.
+    async fn redrive(&self, letter: &DeadLetter) -> Result<DecodedEvent, String> {
+        let event = match (&letter.stage, &letter.event) {
+            (FailureStage::Sink, Some(event)) => event.clone(),
+            _ => self.decode(letter)?,
+        };
+        for sink in &self.sinks {
+            sink.write(&event).await.map_err(|e| e.to_string())?;
+        }
+        Ok(event)
+    }
+
+    /// Decodes the raw log of a dead letter with the current artifacts.
+    fn decode(&self, letter: &DeadLetter) -> Result<DecodedEvent, String> {
+        let (file_name, contract_name) = letter
+            .contract
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid contract: {}", letter.contract))?;
+        let artifact = self
+            .artifacts_resource
+            .get_artifact(file_name, contract_name)
+            .map_err(|e| format!("Error getting artifact: {}", e))?;
+        let event = get_event(&letter.signature, &artifact).ok_or_else(|| {
+            format!(
+                "Event signature not found in contract's ABI: {}",
+                letter.signature
+            )
+        })?;
+
+        let log = ethers::types::Log::try_from(&letter.raw).map_err(|e| e.to_string())?;
+        let data = decode::decode_log(&log, &event).map_err(|e| e.to_string())?;
+        let violations = decode::schema::validate(&event, &data);
+        if !violations.is_empty() {
+            return Err(violations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("; "));
+        }
+        Ok(DecodedEvent {
+            block_number: letter.raw.block_number,
+            block_timestamp: None,
+            transaction_hash: letter.transaction_hash.clone(),
+            log_index: letter.raw.log_index,
+            address: letter.raw.address.clone(),
+            contract: letter.contract.clone(),
+            event: event.name.clone(),
+            signature: event.signature(),
+            schema: Some(decode::schema_id(&event)),
+            data,
+            raw: Some(letter.raw.clone()),
+            balance_diffs: letter
+                .event
+                .as_ref()
+                .map(|event| event.balance_diffs.clone())
+                .unwrap_or_default(),
+            correlation: letter
+                .event
+                .as_ref()
+                .and_then(|event| event.correlation.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use ethers::{types::Address, utils::keccak256};
+
+    use crate::test_fixture;
+    use crate::{core::resources::sink::RawLog, resources::artifacts::LocalArtifactStore};
+
+    use super::*;
+
+    const TRADE: &str = "Trade(string,address,address,address,uint256,uint256,address)";
+
+    #[derive(Default)]
+    struct MemoryDeadLetters(Mutex<Vec<DeadLetter>>);
+
+    impl DeadLetterResource for MemoryDeadLetters {
+        fn push(&self, letter: &DeadLetter) -> Result<(), Box<dyn std::error::Error>> {
+            self.0.lock().unwrap().push(letter.clone());
+            Ok(())
+        }
+
+        fn list(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error>> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        fn resolve(
+            &self,
+            redriven: &[DeadLetter],
+            failed: &[DeadLetter],
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            let mut letters = self.0.lock().unwrap();
+            letters.retain(|letter| !redriven.contains(letter));
+            letters.extend_from_slice(failed);
+            Ok(())
+        }
+    }
+
+    struct MemorySink(std::sync::Arc<Mutex<Vec<DecodedEvent>>>);
+
+    #[async_trait]
+    impl EventSink for MemorySink {
+        async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    fn letter(data: Vec<u8>) -> DeadLetter {
+        DeadLetter {
+            stage: FailureStage::Decode,
+            error: "Invalid data".to_owned(),
+            failed_at: 0,
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            signature: TRADE.to_owned(),
+            transaction_hash: "0x01".to_owned(),
+            raw: RawLog {
+                address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+                topics: vec![format!("0x{}", hex::encode(keccak256(TRADE)))],
+                data: format!("0x{}", hex::encode(data)),
+                block_number: 17_000_000,
+                log_index: 4,
+            },
+            event: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn can_redrive_dead_letters() {
+        let data = ethabi::encode(&[
+            ethabi::Token::String("uniswap-v2".to_owned()),
+            ethabi::Token::Address(Address::zero()),
+            ethabi::Token::Address(Address::zero()),
+            ethabi::Token::Address(Address::zero()),
+            ethabi::Token::Uint(42.into()),
+            ethabi::Token::Uint(7.into()),
+            ethabi::Token::Address(Address::zero()),
+        ]);
+        let dead_letters = MemoryDeadLetters::default();
+        // The same log was dead-lettered twice
+        dead_letters.push(&letter(data.clone())).unwrap();
+        dead_letters.push(&letter(data)).unwrap();
+        let mut undecodable = letter(vec![1, 2, 3]);
+        undecodable.raw.log_index = 5;
+        dead_letters.push(&undecodable).unwrap();
+
+        let written = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let redrive = Redrive {
+            dead_letters,
+            artifacts_resource: LocalArtifactStore::new(test_fixture!("resources", "")),
+            sinks: vec![Box::new(MemorySink(written.clone()))],
+        };
+        let report = redrive.run().await.unwrap();
+
+        assert_eq!(report.redriven.len(), 1);
+        assert_eq!(report.redriven[0].event, "Trade");
+        assert_eq!(report.redriven[0].data["amountIn"], "42");
+        assert_eq!(written.lock().unwrap().len(), 1);
+
+        // The letter that still can't be decoded stays in the queue
+        let remaining = redrive.dead_letters.list().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].raw.data, "0x010203");
+        assert_ne!(remaining[0].error, "Invalid data");
+
+        // Without a sink, nothing is redriven
+        let redrive = Redrive {
+            sinks: Vec::new(),
+            ..redrive
+        };
+        assert!(redrive.run().await.is_err());
+        assert_eq!(redrive.dead_letters.list().unwrap().len(), 1);
+    }
+}
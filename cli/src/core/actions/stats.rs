@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::core::resources::sink::{utc_date_hour, DecodedEvent};
+
+/// Aggregates decoded events from the local event archive.
+///
+/// This action is used by the `stats` command.
+pub struct Stats {
+    /// The events to aggregate
+    pub events: Vec<DecodedEvent>,
+
+    /// Only include events from this block onwards
+    pub from_block: Option<u64>,
+
+    /// Only include events up to and including this block
+    pub to_block: Option<u64>,
+
+    /// Only include events with this name
+    pub event: Option<String>,
+
+    /// The decoded field to rank values of
+    pub top_by: Option<String>,
+
+    /// The number of top values to report
+    pub top: usize,
+
+    /// The decoded numeric field to summarize
+    pub field: Option<String>,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum StatsError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Error related to the event archive
+    #[error("ArchiveError: {0}")]
+    ArchiveError(Box<dyn std::error::Error>),
+}
+
+/// The aggregated statistics over an event archive.
+#[derive(Debug, Default, PartialEq)]
+pub struct StatsReport {
+    /// The number of events matched
+    pub total: usize,
+    /// The number of events per hour (UTC), per event name
+    pub per_hour: BTreeMap<String, BTreeMap<String, usize>>,
+    /// The most frequent values of the `top_by` field, with their counts
+    pub top_values: Vec<(String, usize)>,
+    /// The summary of the numeric `field`
+    pub summary: Option<FieldSummary>,
+}
+
+/// The min, max and average of a numeric field.
+#[derive(Debug, PartialEq)]
+pub struct FieldSummary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+}
+
+impl Stats {
+    pub fn run(&self) -> Result<StatsReport, StatsError> {
+        let events: Vec<&DecodedEvent> = self
+            .events
+            .iter()
+            .filter(|event| self.matches(event))
+            .collect();
+
+        let mut report = StatsReport {
+            total: events.len(),
+            ..Default::default()
+        };
+
+        // Count the events per hour
+        for event in &events {
+            let hour = match event.block_timestamp {
+                Some(timestamp) => format_hour(timestamp),
+                None => "unknown".to_owned(),
+            };
+            *report
+                .per_hour
+                .entry(event.event.clone())
+                .or_default()
+                .entry(hour)
+                .or_default() += 1;
+        }
+
+        // Rank the values of the top-by field
+        if let Some(top_by) = &self.top_by {
+            let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+            for event in &events {
+                if let Some(value) = event.data.get(top_by) {
+                    *counts.entry(value_to_string(value)).or_default() += 1;
+                }
+            }
+            let mut top_values: Vec<(String, usize)> = counts.into_iter().collect();
+            top_values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            top_values.truncate(self.top);
+            report.top_values = top_values;
+        }
+
+        // Summarize the numeric field
+        if let Some(field) = &self.field {
+            let mut values = Vec::new();
+            for event in &events {
+                let Some(value) = event.data.get(field) else {
+                    continue;
+                };
+                let number = value_to_f64(value).ok_or_else(|| {
+                    StatsError::CustomError(format!(
+                        "Field {} is not numeric in transaction {}",
+                        field, event.transaction_hash
+                    ))
+                })?;
+                values.push(number);
+            }
+            if !values.is_empty() {
+                let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                let avg = values.iter().sum::<f64>() / values.len() as f64;
+                report.summary = Some(FieldSummary {
+                    count: values.len(),
+                    min,
+                    max,
+                    avg,
+                });
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn matches(&self, event: &DecodedEvent) -> bool {
+        if let Some(from_block) = self.from_block {
+            if event.block_number < from_block {
+                return false;
+            }
+        }
+        if let Some(to_block) = self.to_block {
+            if event.block_number > to_block {
+                return false;
+            }
+        }
+        match &self.event {
+            Some(name) => &event.event == name,
+            None => true,
+        }
+    }
+}
+
+/// Renders a decoded value as a plain string, without JSON quotes.
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Converts a decoded value to a float.
+///
+/// Integers are decoded as decimal strings, so both strings and
+/// JSON numbers are accepted.
+fn value_to_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+/// Formats a unix timestamp as the UTC hour it falls in,
+/// e.g. `2023-04-12 22:00`.
+fn format_hour(timestamp: u64) -> String {
+    let (year, month, day, hour) = utc_date_hour(timestamp);
+    format!("{:04}-{:02}-{:02} {:02}:00", year, month, day, hour)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(block_number: u64, timestamp: u64, name: &str, data: Value) -> DecodedEvent {
+        DecodedEvent {
+            block_number,
+            block_timestamp: Some(timestamp),
+            transaction_hash: format!("0x{:x}", block_number),
+            log_index: 0,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            event: name.to_owned(),
+            signature: format!("{}(address,uint256)", name),
+            schema: None,
+            data,
+            raw: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_format_hour() {
+        assert_eq!(format_hour(0), "1970-01-01 00:00");
+        assert_eq!(format_hour(1_681_338_455), "2023-04-12 22:00");
+    }
+
+    #[test]
+    fn can_aggregate_events() {
+        let stats = Stats {
+            events: vec![
+                event(
+                    1,
+                    1_681_338_455,
+                    "Trade",
+                    json!({ "trader": "0xa", "amount": "10" }),
+                ),
+                event(
+                    2,
+                    1_681_338_467,
+                    "Trade",
+                    json!({ "trader": "0xb", "amount": "30" }),
+                ),
+                event(
+                    3,
+                    1_681_342_055,
+                    "Trade",
+                    json!({ "trader": "0xa", "amount": "20" }),
+                ),
+                event(
+                    4,
+                    1_681_342_067,
+                    "Swap",
+                    json!({ "trader": "0xa", "amount": "99" }),
+                ),
+                event(
+                    9,
+                    1_681_342_079,
+                    "Trade",
+                    json!({ "trader": "0xc", "amount": "1" }),
+                ),
+            ],
+            from_block: Some(1),
+            to_block: Some(4),
+            event: Some("Trade".to_owned()),
+            top_by: Some("trader".to_owned()),
+            top: 1,
+            field: Some("amount".to_owned()),
+        };
+        let report = stats.run().unwrap();
+
+        assert_eq!(report.total, 3);
+        assert_eq!(report.per_hour["Trade"]["2023-04-12 22:00"], 2);
+        assert_eq!(report.per_hour["Trade"]["2023-04-12 23:00"], 1);
+        assert_eq!(report.top_values, vec![("0xa".to_owned(), 2)]);
+        assert_eq!(
+            report.summary,
+            Some(FieldSummary {
+                count: 3,
+                min: 10.0,
+                max: 30.0,
+                avg: 20.0
+            })
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use std::str::FromStr;
+
+use ethers::{
+    providers::{JsonRpcClient, Middleware, Provider, ProviderError},
+    types::{Address, BlockId, H256},
+};
+use thiserror::Error;
+
+use crate::{
+    core::resources::{artifacts::ArtifactsResource, shadow::ShadowContract},
+    storage::{word, Location, StorageLayout},
+};
+
+/// Reads a state variable of a shadow contract, using the storage
+/// layout of its artifact.
+///
+/// The value can be read from the shadow fork, or from mainnet to
+/// compare against it.
+///
+/// This action is used by the `storage` command.
+pub struct Storage<A: ArtifactsResource, P: JsonRpcClient> {
+    /// The provider to read the storage from
+    pub provider: Provider<P>,
+    /// The Artifacts resource, used to get the storage layout
+    pub artifacts_resource: A,
+    /// The shadow contract to read
+    pub shadow_contract: ShadowContract,
+    /// The variable path, e.g. `config.fee` or `balances[0xab...]`
+    pub path: String,
+    /// The block to read at, or the latest block
+    pub block: Option<BlockId>,
+}
+
+/// A state variable read from storage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageValue {
+    /// The Solidity type of the value
+    pub type_label: String,
+    pub slot: H256,
+    /// The byte offset in the slot, counted from the right
+    pub offset: usize,
+    /// The raw value of the slot
+    pub raw: H256,
+    /// The decoded value
+    pub value: String,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum StorageError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Provider error
+    #[error("ProviderError: {0}")]
+    ProviderError(#[from] ProviderError),
+}
+
+impl<A: ArtifactsResource, P: JsonRpcClient> Storage<A, P> {
+    pub async fn run(&self) -> Result<StorageValue, StorageError> {
+        let layout = StorageLayout::from_artifacts(
+            &self.artifacts_resource,
+            &self.shadow_contract.file_name,
+            &self.shadow_contract.contract_name,
+        )
+        .map_err(StorageError::CustomError)?;
+        let location = layout
+            .locate(&self.path)
+            .map_err(StorageError::CustomError)?;
+
+        // Read the slot, and the data slots of long strings and bytes
+        let first = self.read(&location.slot).await?;
+        let mut data = Vec::new();
+        for slot in layout
+            .data_slots(&location, first)
+            .map_err(StorageError::CustomError)?
+        {
+            data.push(self.read(&slot).await?);
+        }
+
+        let value = layout
+            .decode(&location, first, &data)
+            .map_err(StorageError::CustomError)?;
+        Ok(StorageValue {
+            type_label: self.type_label(&layout, &location),
+            slot: H256(word(location.slot)),
+            offset: location.offset,
+            raw: H256(first),
+            value,
+        })
+    }
+
+    async fn read(&self, slot: &ethers::types::U256) -> Result<[u8; 32], StorageError> {
+        let address = Address::from_str(&self.shadow_contract.address)
+            .map_err(|e| StorageError::CustomError(format!("Invalid address: {}", e)))?;
+        Ok(self
+            .provider
+            .get_storage_at(address, H256(word(*slot)), self.block)
+            .await?
+            .0)
+    }
+
+    fn type_label(&self, layout: &StorageLayout, location: &Location) -> String {
+        layout
+            .get_type(&location.type_id)
+            .map(|ty| ty.label.clone())
+            .unwrap_or_else(|_| location.type_id.clone())
+    }
+}
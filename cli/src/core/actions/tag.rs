@@ -0,0 +1,92 @@
+use thiserror::Error;
+
+use crate::core::resources::shadow::{ShadowContract, ShadowResource};
+
+/// Adds or removes tags on a shadow contract.
+///
+/// This action is used by the `tag` command.
+pub struct Tag<S: ShadowResource> {
+    /// The address of the shadow contract
+    pub address: String,
+
+    /// The tags to add or remove
+    pub tags: Vec<String>,
+
+    /// Whether to remove the tags instead of adding them
+    pub remove: bool,
+
+    /// The Shadow resource
+    pub shadow_resource: S,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum TagError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+}
+
+impl<S: ShadowResource> Tag<S> {
+    /// Updates the tags, returning the updated shadow contract.
+    pub async fn run(&self) -> Result<ShadowContract, TagError> {
+        let mut shadow_contract = self
+            .shadow_resource
+            .get_by_address(&self.address.to_lowercase())
+            .await
+            .map_err(|e| TagError::CustomError(format!("Error getting shadow contract: {}", e)))?;
+        if self.remove {
+            shadow_contract.tags.retain(|tag| !self.tags.contains(tag));
+        } else {
+            for tag in &self.tags {
+                if !shadow_contract.tags.contains(tag) {
+                    shadow_contract.tags.push(tag.clone());
+                }
+            }
+        }
+        self.shadow_resource
+            .upsert(shadow_contract.clone())
+            .await
+            .map_err(|e| TagError::CustomError(e.to_string()))?;
+        Ok(shadow_contract)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{core::actions::List, resources::shadow::LocalShadowStore, test_fixture};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_tag_and_list() {
+        let temp_dir = tempdir().unwrap();
+        fs::copy(
+            test_fixture!("resources", "shadow.json"),
+            temp_dir.path().join("shadow.json"),
+        )
+        .unwrap();
+        let path = temp_dir.path().to_str().unwrap().to_owned();
+        let tag = |tags: &[&str], remove| Tag {
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            remove,
+            shadow_resource: LocalShadowStore::new(path.clone()),
+        };
+
+        tag(&["defi", "experimental"], false).run().await.unwrap();
+        let contract = tag(&["experimental"], true).run().await.unwrap();
+        assert_eq!(contract.tags, vec!["defi".to_owned()]);
+
+        let list = List {
+            tags: vec!["defi".to_owned()],
+            shadow_resource: LocalShadowStore::new(path),
+        };
+        let contracts = list.run().await.unwrap();
+        assert_eq!(contracts.len(), 1);
+        assert_eq!(contracts[0].contract_name, "UniswapV2Router02");
+    }
+}
@@ -0,0 +1,81 @@
+use thiserror::Error;
+
+use crate::core::resources::shadow::ShadowResource;
+
+/// Enables or disables a shadow contract without removing it
+/// from the Shadow store.
+///
+/// Disabled contracts keep their mainnet bytecode on the shadow fork,
+/// and transactions to them are not replayed.
+///
+/// This action is used by the `enable` and `disable` commands.
+pub struct Toggle<S: ShadowResource> {
+    /// The address of the shadow contract
+    pub address: String,
+
+    /// Whether to enable or disable the shadow contract
+    pub enabled: bool,
+
+    /// The Shadow resource
+    pub shadow_resource: S,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum ToggleError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+}
+
+impl<S: ShadowResource> Toggle<S> {
+    pub async fn run(&self) -> Result<(), ToggleError> {
+        let mut shadow_contract = self
+            .shadow_resource
+            .get_by_address(&self.address.to_lowercase())
+            .await
+            .map_err(|e| {
+                ToggleError::CustomError(format!("Error getting shadow contract: {}", e))
+            })?;
+        shadow_contract.enabled = self.enabled;
+        self.shadow_resource
+            .upsert(shadow_contract)
+            .await
+            .map_err(|e| ToggleError::CustomError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{resources::shadow::LocalShadowStore, test_fixture};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_toggle() {
+        let temp_dir = tempdir().unwrap();
+        fs::copy(
+            test_fixture!("resources", "shadow.json"),
+            temp_dir.path().join("shadow.json"),
+        )
+        .unwrap();
+        let path = temp_dir.path().to_str().unwrap().to_owned();
+        let address = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
+
+        let toggle = Toggle {
+            address: address.to_uppercase().replace("0X", "0x"),
+            enabled: false,
+            shadow_resource: LocalShadowStore::new(path.clone()),
+        };
+        toggle.run().await.unwrap();
+
+        let shadow_resource = LocalShadowStore::new(path);
+        let contract = shadow_resource.get_by_address(address).await.unwrap();
+        assert!(!contract.enabled);
+        assert_eq!(shadow_resource.list().await.unwrap().len(), 2);
+    }
+}
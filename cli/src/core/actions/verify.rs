@@ -0,0 +1,102 @@
+use thiserror::Error;
+
+use crate::core::resources::{
+    artifacts::ArtifactsResource,
+    shadow::ShadowResource,
+    verifier::{VerificationRequest, VerifierResource},
+};
+
+/// Submits the source of a shadow contract to a source verifier,
+/// so a block explorer running against the shadow fork can show
+/// the verified shadow source.
+///
+/// This action is used by the `verify` command.
+pub struct Verify<A: ArtifactsResource, S: ShadowResource, V: VerifierResource> {
+    /// The name of the artifact file to use
+    pub file_name: String,
+
+    /// The name of the contract to verify
+    pub contract_name: String,
+
+    /// The ABI-encoded constructor arguments, hex encoded
+    pub constructor_arguments: String,
+
+    /// The Artifacts resource
+    pub artifacts_resource: A,
+
+    /// The Shadow resource
+    pub shadow_resource: S,
+
+    /// The Verifier resource
+    pub verifier_resource: V,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum VerifyError {
+    /// Catch-all error
+    #[error("CustomError: {0}")]
+    CustomError(String),
+    /// Error related to the artifacts store
+    #[error("ArtifactError: {0}")]
+    ArtifactError(Box<dyn std::error::Error>),
+    /// Error returned by the verifier
+    #[error("VerifierError: {0}")]
+    VerifierError(Box<dyn std::error::Error>),
+}
+
+impl<A: ArtifactsResource, S: ShadowResource, V: VerifierResource> Verify<A, S, V> {
+    /// Runs the verification, returning the verifier's response message.
+    pub async fn run(&self) -> Result<String, VerifyError> {
+        // Get the shadow contract
+        let shadow_contract = self
+            .shadow_resource
+            .get_by_name(&self.file_name, &self.contract_name)
+            .await
+            .map_err(|e| {
+                VerifyError::CustomError(format!("Error getting shadow contract: {}", e))
+            })?;
+
+        // Build the verification request
+        let request = self.build_request(shadow_contract.address)?;
+
+        // Submit the sources
+        self.verifier_resource
+            .verify(&request)
+            .await
+            .map_err(VerifyError::VerifierError)
+    }
+
+    fn build_request(&self, address: String) -> Result<VerificationRequest, VerifyError> {
+        let metadata = self
+            .artifacts_resource
+            .get_metadata(&self.file_name, &self.contract_name)
+            .map_err(VerifyError::ArtifactError)?;
+        let sources = self
+            .artifacts_resource
+            .get_sources(&self.file_name, &self.contract_name)
+            .map_err(VerifyError::ArtifactError)?;
+
+        // The compilation target maps the source path to the contract name
+        let source_path = metadata["settings"]["compilationTarget"]
+            .as_object()
+            .and_then(|target| target.keys().next().cloned())
+            .ok_or_else(|| {
+                VerifyError::CustomError(
+                    "Metadata does not contain a compilation target".to_owned(),
+                )
+            })?;
+
+        Ok(VerificationRequest {
+            address,
+            source_path,
+            contract_name: self.contract_name.clone(),
+            metadata,
+            sources,
+            constructor_arguments: self
+                .constructor_arguments
+                .trim_start_matches("0x")
+                .to_owned(),
+        })
+    }
+}
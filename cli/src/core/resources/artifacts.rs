@@ -1,3 +1,53 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+/// The position of a placeholder in the bytecode, in bytes
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct BytecodeOffset {
+    pub start: usize,
+    pub length: usize,
+}
+
+/// Library link references, keyed by source path and library name
+pub type LinkReferences = BTreeMap<String, BTreeMap<String, Vec<BytecodeOffset>>>;
+
+/// The compiler output of a contract that isn't in the flat artifact,
+/// read from the build-info files.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContractBuildInfo {
+    /// The source path the contract was compiled from
+    pub source_path: String,
+    /// The libraries the init code must be linked against
+    pub link_references: LinkReferences,
+    /// The libraries the runtime code must be linked against
+    pub deployed_link_references: LinkReferences,
+    /// The positions of immutables in the runtime code, keyed by AST id
+    pub immutable_references: BTreeMap<String, Vec<BytecodeOffset>>,
+    /// The storage layout, if the contract was compiled with it
+    pub storage_layout: Option<serde_json::Value>,
+}
+
+impl ContractBuildInfo {
+    /// Returns the libraries the contract links against, as `File.sol:Library`.
+    pub fn libraries(&self) -> Vec<String> {
+        let mut libraries: Vec<String> = self
+            .link_references
+            .iter()
+            .chain(&self.deployed_link_references)
+            .flat_map(|(path, libraries)| {
+                let file_name = path.rsplit('/').next().unwrap_or(path);
+                libraries
+                    .keys()
+                    .map(move |library| format!("{}:{}", file_name, library))
+            })
+            .collect();
+        libraries.sort();
+        libraries.dedup();
+        libraries
+    }
+}
+
 /// Defines the interface for interacting with an Artifacts store.
 ///
 /// The Artifacts resource is responsible for retrieving artifacts from
@@ -11,4 +61,77 @@ pub trait ArtifactsResource {
         file_name: &str,
         contract_name: &str,
     ) -> Result<alloy_json_abi::ContractObject, Box<dyn std::error::Error>>;
+
+    /// Get the keccak256 hashes of the sources the contract was compiled from,
+    /// keyed by source path
+    fn get_source_hashes(
+        &self,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>>;
+
+    /// Get the compiler metadata of the contract
+    fn get_metadata(
+        &self,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>>;
+
+    /// Get the contents of the sources the contract was compiled from,
+    /// keyed by source path
+    fn get_sources(
+        &self,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>>;
+
+    /// Get the compiler output of the contract from the build-info files.
+    ///
+    /// Returns `None` if the store has no build info, e.g. when the
+    /// project wasn't built with `forge build --build-info`.
+    fn get_build_info(
+        &self,
+        _file_name: &str,
+        _contract_name: &str,
+    ) -> Result<Option<ContractBuildInfo>, Box<dyn std::error::Error>> {
+        Ok(None)
+    }
+
+    /// Get the storage layout of the contract.
+    ///
+    /// Returns `None` if the contract wasn't compiled with its storage layout.
+    fn get_storage_layout(
+        &self,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<Option<serde_json::Value>, Box<dyn std::error::Error>> {
+        Ok(self
+            .get_build_info(file_name, contract_name)?
+            .and_then(|build_info| build_info.storage_layout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_list_libraries() {
+        let offsets = vec![BytecodeOffset {
+            start: 1024,
+            length: 20,
+        }];
+        let library = |name: &str| {
+            BTreeMap::from([(
+                "src/libraries/Math.sol".to_owned(),
+                BTreeMap::from([(name.to_owned(), offsets.clone())]),
+            )])
+        };
+        let build_info = ContractBuildInfo {
+            link_references: library("Math"),
+            deployed_link_references: library("Math"),
+            ..Default::default()
+        };
+        assert_eq!(build_info.libraries(), vec!["Math.sol:Math".to_owned()]);
+    }
 }
@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+
+/// Defines the interface for the place backups are stored.
+///
+/// The store may be a local directory or an object store bucket. Keys
+/// are `/`-separated paths relative to the root of the store.
+#[async_trait]
+pub trait BackupStore: Send + Sync {
+    /// Writes an object, replacing it if it exists
+    async fn put(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Reads an object
+    async fn get(&self, key: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Lists the keys of every object in the store
+    async fn list(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Deletes an object
+    async fn delete(&self, key: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
@@ -0,0 +1,15 @@
+/// Defines the interface for interacting with a Credentials store.
+///
+/// The Credentials resource is responsible for storing and retrieving
+/// secrets such as API keys, so they don't have to be embedded in the
+/// binary or kept in plain-text environment files.
+///
+/// The Credentials store may be an OS keychain, an encrypted file,
+/// or a remote secrets manager.
+pub trait CredentialsResource {
+    /// Get a credential by name, or `None` if it is not set
+    fn get(&self, name: &str) -> Result<Option<String>, Box<dyn std::error::Error>>;
+
+    /// Set a credential
+    fn set(&self, name: &str, value: &str) -> Result<(), Box<dyn std::error::Error>>;
+}
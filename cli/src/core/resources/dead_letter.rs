@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use super::sink::{DecodedEvent, RawLog};
+
+/// Where processing a log failed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FailureStage {
+    /// The log couldn't be decoded with the event ABI
+    Decode,
+    /// The decoded event didn't match the event's schema
+    Schema,
+    /// The decoded event couldn't be written to a sink
+    Sink,
+    /// A plugin failed to process the decoded event
+    Plugin,
+}
+
+/// A log that failed to be processed, kept so it can be redriven
+/// once the cause is fixed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetter {
+    /// Where processing failed
+    pub stage: FailureStage,
+    /// The error that caused the failure
+    pub error: String,
+    /// The unix timestamp of the failure
+    pub failed_at: u64,
+    /// The contract the event belongs to, in the form `File.sol:Contract`
+    pub contract: String,
+    /// The signature of the event, e.g. `Transfer(address,address,uint256)`
+    pub signature: String,
+    /// The hash of the transaction that emitted the log
+    pub transaction_hash: String,
+    /// The raw log
+    pub raw: RawLog,
+    /// The decoded event, for schema and sink failures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<DecodedEvent>,
+}
+
+impl DeadLetter {
+    /// Returns the transaction hash and log index of the letter's log.
+    ///
+    /// A log can be dead-lettered more than once, e.g. by a restarted
+    /// listener, so several letters can share a key.
+    pub fn key(&self) -> (String, u64) {
+        (self.transaction_hash.to_lowercase(), self.raw.log_index)
+    }
+}
+
+/// Defines the interface for the dead-letter queue.
+///
+/// The queue may be a file, a database table, or a message broker.
+pub trait DeadLetterResource: Send + Sync {
+    /// Appends a dead letter to the queue
+    fn push(&self, letter: &DeadLetter) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Lists the dead letters, oldest first
+    fn list(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error>>;
+
+    /// Removes the given letters, which were redriven, and appends the
+    /// letters of the logs that failed again.
+    ///
+    /// Letters pushed since the queue was listed are kept.
+    fn resolve(
+        &self,
+        redriven: &[DeadLetter],
+        failed: &[DeadLetter],
+    ) -> Result<(), Box<dyn std::error::Error>>;
+}
@@ -50,8 +50,14 @@ pub struct GetSourceCodeResponse {
 
 /// Represents a single result in the Etherscan API for the source code endpoint
 /// https://docs.etherscan.io/api-endpoints/contracts#get-contract-source-code-for-verified-contract-source-codes
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct SourceCodeResult {
     pub constructor_arguments: String,
+    #[serde(default)]
+    pub source_code: String,
+    #[serde(default)]
+    pub contract_name: String,
+    #[serde(default)]
+    pub compiler_version: String,
 }
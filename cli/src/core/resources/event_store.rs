@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::sink::{DecodedEvent, EventSink};
+
+/// Selects stored events by block range and event name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventQuery {
+    /// Only events from this block onwards
+    pub from_block: Option<u64>,
+    /// Only events up to and including this block
+    pub to_block: Option<u64>,
+    /// Only events with this name
+    pub event: Option<String>,
+}
+
+impl EventQuery {
+    /// Returns whether an event is selected by the query.
+    pub fn matches(&self, event: &DecodedEvent) -> bool {
+        if matches!(self.from_block, Some(from_block) if event.block_number < from_block) {
+            return false;
+        }
+        if matches!(self.to_block, Some(to_block) if event.block_number > to_block) {
+            return false;
+        }
+        match &self.event {
+            Some(name) => &event.event == name,
+            None => true,
+        }
+    }
+}
+
+/// Defines the interface for stores that decoded events are archived in
+/// and queried from.
+///
+/// A store may be a file, an embedded database, or a database server.
+#[async_trait]
+pub trait EventStore: Send + Sync {
+    /// Inserts a decoded event. An event at the position of a stored one
+    /// replaces it, so redriving events doesn't duplicate them.
+    async fn insert(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Returns the events matching the query, by block and log index
+    async fn query(
+        &self,
+        query: &EventQuery,
+    ) -> Result<Vec<DecodedEvent>, Box<dyn std::error::Error>>;
+
+    /// Returns all the stored events, by block and log index
+    async fn list(&self) -> Result<Vec<DecodedEvent>, Box<dyn std::error::Error>> {
+        self.query(&EventQuery::default()).await
+    }
+}
+
+/// A shared store doubles as a sink, so live events are archived in it.
+#[async_trait]
+impl EventSink for Arc<dyn EventStore> {
+    async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        self.insert(event).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn can_match_events() {
+        let event = DecodedEvent {
+            block_number: 10,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 0,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            event: "Trade".to_owned(),
+            signature: "Trade(address,uint256)".to_owned(),
+            schema: None,
+            data: json!({}),
+            raw: None,
+            ..Default::default()
+        };
+        assert!(EventQuery::default().matches(&event));
+        assert!(EventQuery {
+            from_block: Some(10),
+            to_block: Some(10),
+            event: Some("Trade".to_owned()),
+        }
+        .matches(&event));
+        assert!(!EventQuery {
+            to_block: Some(9),
+            ..Default::default()
+        }
+        .matches(&event));
+        assert!(!EventQuery {
+            event: Some("Swap".to_owned()),
+            ..Default::default()
+        }
+        .matches(&event));
+    }
+}
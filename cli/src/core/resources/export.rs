@@ -0,0 +1,19 @@
+use super::sink::DecodedEvent;
+
+/// Defines the interface for the files historical events are exported to.
+///
+/// Events are buffered as they are written, and only made durable when
+/// flushed, so an interrupted export can resume from its last flush.
+pub trait ExportWriter: Send {
+    /// Buffers the events of a block range, which follows the previous one.
+    fn write(
+        &mut self,
+        from_block: u64,
+        to_block: u64,
+        events: &[DecodedEvent],
+    ) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Durably writes the buffered events, and returns the position the
+    /// writer is reopened at to resume the export.
+    fn flush(&mut self) -> Result<u64, Box<dyn std::error::Error>>;
+}
@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use ethers::types::{Bytes, TransactionReceipt, H160, H256, U256};
+
+/// A transaction sent on a fork from an impersonated account.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ForkTransaction {
+    /// The sender, which must be impersonated first
+    pub from: H160,
+    /// The recipient, or `None` to create a contract
+    pub to: Option<H160>,
+    /// The value transferred, in wei
+    pub value: U256,
+    /// The gas limit
+    pub gas: U256,
+    /// The calldata, or the init code when creating a contract
+    pub data: Bytes,
+}
+
+/// Defines the interface for running short-lived forks of the upstream chain.
+///
+/// The Fork runner is responsible for starting a fork at a given block,
+/// and for executing transactions on it.
+///
+/// The fork may be an anvil node, an in-process EVM, or an in-memory fake.
+#[async_trait]
+pub trait ForkRunner: Send + Sync {
+    /// A running fork
+    type Fork: Send + Sync;
+
+    /// Starts a fork at the given block, or at the latest block.
+    async fn spawn(
+        &self,
+        block_number: Option<u64>,
+    ) -> Result<Self::Fork, Box<dyn std::error::Error>>;
+    /// Sets the balance of an account, in wei.
+    async fn set_balance(
+        &self,
+        fork: &Self::Fork,
+        address: H160,
+        balance: U256,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Replaces the runtime code of an account.
+    async fn set_code(
+        &self,
+        fork: &Self::Fork,
+        address: H160,
+        code: Bytes,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Lets transactions be sent from an account without its key.
+    async fn impersonate(
+        &self,
+        fork: &Self::Fork,
+        address: H160,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Sends a transaction, which is executed once a block is mined.
+    async fn send_transaction(
+        &self,
+        fork: &Self::Fork,
+        transaction: ForkTransaction,
+    ) -> Result<H256, Box<dyn std::error::Error>>;
+    /// Mines a block with the pending transactions.
+    async fn mine(&self, fork: &Self::Fork) -> Result<(), Box<dyn std::error::Error>>;
+    /// Returns the receipt of a mined transaction.
+    async fn transaction_receipt(
+        &self,
+        fork: &Self::Fork,
+        tx_hash: H256,
+    ) -> Result<Option<TransactionReceipt>, Box<dyn std::error::Error>>;
+    /// Returns the runtime code of an account.
+    async fn get_code(
+        &self,
+        fork: &Self::Fork,
+        address: H160,
+    ) -> Result<Bytes, Box<dyn std::error::Error>>;
+    /// Stops the fork.
+    async fn stop(&self, fork: Self::Fork);
+}
@@ -1,3 +1,13 @@
 pub mod artifacts;
+pub mod backup;
+pub mod credentials;
+pub mod dead_letter;
 pub mod etherscan;
+pub mod event_store;
+pub mod export;
+pub mod fork_runner;
+pub mod plugin;
 pub mod shadow;
+pub mod signatures;
+pub mod sink;
+pub mod verifier;
@@ -0,0 +1,18 @@
+use super::sink::DecodedEvent;
+
+/// Defines the interface for user-provided code that post-processes
+/// decoded events before they are printed and written to the sinks.
+///
+/// A plugin may enrich an event, e.g. with an off-chain label, transform
+/// its fields, or filter it out.
+pub trait EventPlugin: Send + Sync {
+    /// The name of the plugin, used in logs and errors
+    fn name(&self) -> &str;
+
+    /// Processes an event, returning the event to emit in its place, or
+    /// `None` to drop it
+    fn process(
+        &self,
+        event: &DecodedEvent,
+    ) -> Result<Option<DecodedEvent>, Box<dyn std::error::Error + Send + Sync>>;
+}
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
 /// Represents a shadow contract
-#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShadowContract {
     /// The file name of the shadow contract
@@ -14,6 +14,64 @@ pub struct ShadowContract {
     /// The runtime bytecode of the shadow contract.
     /// This is the bytecode that is stored on the shadow fork.
     pub runtime_bytecode: String,
+    /// Whether the shadow contract is used on the shadow fork.
+    /// Disabled contracts keep their mainnet bytecode.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Free-form tags used to group shadow contracts, e.g. `defi`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// How the runtime bytecode was built, recorded at deploy time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Attestation>,
+}
+
+/// The build inputs of a shadow contract's runtime bytecode, used by
+/// `shadow attest` to check that it can be reproduced.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attestation {
+    /// The full compiler version, e.g. `0.8.19+commit.7dd6d404`
+    pub compiler_version: String,
+    /// Whether the optimizer was enabled
+    pub optimizer_enabled: bool,
+    /// The number of optimizer runs
+    pub optimizer_runs: Option<u64>,
+    /// The target EVM version, if set
+    pub evm_version: Option<String>,
+    /// Whether the code was compiled via the IR pipeline
+    pub via_ir: bool,
+    /// The keccak256 hash over the paths and hashes of every source
+    pub source_tree_hash: String,
+    /// What was done with the compiler metadata, e.g. `strip`
+    pub metadata: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for ShadowContract {
+    fn default() -> Self {
+        Self {
+            file_name: String::new(),
+            contract_name: String::new(),
+            address: String::new(),
+            runtime_bytecode: String::new(),
+            enabled: true,
+            tags: Vec::new(),
+            attestation: None,
+        }
+    }
+}
+
+impl ShadowContract {
+    /// Returns whether the contract has any of the given tags.
+    ///
+    /// An empty tag filter matches every contract.
+    pub fn has_any_tag(&self, tags: &[String]) -> bool {
+        tags.is_empty() || tags.iter().any(|tag| self.tags.contains(tag))
+    }
 }
 
 /// Defines the interface for interacting with a Shadow store
@@ -34,9 +92,46 @@ pub trait ShadowResource {
         contract_name: &str,
     ) -> Result<ShadowContract, Box<dyn std::error::Error>>;
     async fn list(&self) -> Result<Vec<ShadowContract>, Box<dyn std::error::Error>>;
+    /// Lists every instance of a contract, i.e. every address the same
+    /// file and contract name is shadowed at.
+    async fn list_by_name(
+        &self,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<Vec<ShadowContract>, Box<dyn std::error::Error>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|contract| {
+                contract.file_name == file_name && contract.contract_name == contract_name
+            })
+            .collect())
+    }
     async fn upsert(
         &self,
         shadow_contract: ShadowContract,
     ) -> Result<(), Box<dyn std::error::Error>>;
     async fn remove(&self, address: &str) -> Result<(), Box<dyn std::error::Error>>;
+    /// Replaces every contract in the store with `contracts` in a single write.
+    async fn replace(
+        &self,
+        contracts: Vec<ShadowContract>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_filter_by_tag() {
+        let contract = ShadowContract {
+            tags: vec!["defi".to_owned()],
+            ..Default::default()
+        };
+        assert!(contract.has_any_tag(&[]));
+        assert!(contract.has_any_tag(&["nft".to_owned(), "defi".to_owned()]));
+        assert!(!contract.has_any_tag(&["nft".to_owned()]));
+    }
 }
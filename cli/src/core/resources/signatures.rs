@@ -0,0 +1,12 @@
+use async_trait::async_trait;
+
+/// Defines the interface for resolving selectors to text signatures.
+///
+/// The Signatures resource is used to name logs from contracts whose
+/// ABI isn't known.
+#[async_trait]
+pub trait SignaturesResource {
+    /// Returns the event signatures matching a topic0, e.g.
+    /// `Transfer(address,address,uint256)`, most likely first.
+    async fn lookup_event(&self, topic0: &str) -> Result<Vec<String>, Box<dyn std::error::Error>>;
+}
@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+
+pub use shadow_decode_core::{BalanceDiff, Correlation, DecodedEvent, RawLog};
+
+/// Defines the interface for sinks that decoded shadow events are written to.
+///
+/// A sink may be a file, a database, a message broker, or a notifier.
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// Write a decoded event to the sink
+    async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Write out the events the sink buffered, e.g. before shutting down.
+    /// Sinks that write every event right away have nothing to flush.
+    async fn flush(&self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+}
+
+/// Splits a unix timestamp into its UTC year, month, day and hour,
+/// for bucketing events by time.
+pub fn utc_date_hour(timestamp: u64) -> (i64, u32, u32, u32) {
+    let days = (timestamp / 86_400) as i64;
+    let hour = ((timestamp % 86_400) / 3_600) as u32;
+
+    // Convert days since the epoch to a civil date
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day, hour)
+}
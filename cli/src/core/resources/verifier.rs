@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+/// Everything a source verifier needs to verify a shadow contract.
+#[derive(Clone, Debug)]
+pub struct VerificationRequest {
+    /// The address of the shadow contract
+    pub address: String,
+    /// The path of the source file that contains the contract
+    pub source_path: String,
+    /// The name of the contract
+    pub contract_name: String,
+    /// The compiler metadata of the contract
+    pub metadata: Value,
+    /// The contents of the sources, keyed by source path
+    pub sources: BTreeMap<String, String>,
+    /// The ABI-encoded constructor arguments, hex encoded
+    pub constructor_arguments: String,
+}
+
+impl VerificationRequest {
+    /// The full compiler version, e.g. `v0.8.19+commit.7dd6d404`
+    pub fn compiler_version(&self) -> String {
+        format!(
+            "v{}",
+            self.metadata["compiler"]["version"]
+                .as_str()
+                .unwrap_or_default()
+        )
+    }
+
+    /// Builds the solc standard JSON input from the compiler metadata.
+    pub fn standard_json_input(&self) -> Value {
+        let mut settings = self.metadata["settings"].clone();
+        if let Some(settings) = settings.as_object_mut() {
+            settings.remove("compilationTarget");
+            // The metadata prefixes remappings with their (empty) context
+            if let Some(remappings) = settings.get_mut("remappings") {
+                *remappings = remappings
+                    .as_array()
+                    .map(|r| {
+                        r.iter()
+                            .filter_map(|r| r.as_str())
+                            .map(|r| Value::from(r.trim_start_matches(':')))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+            }
+            settings.insert(
+                "outputSelection".to_owned(),
+                json!({ "*": { "*": ["abi", "evm.bytecode", "evm.deployedBytecode", "metadata"] } }),
+            );
+        }
+        let sources = self
+            .sources
+            .iter()
+            .map(|(path, content)| (path.clone(), json!({ "content": content })))
+            .collect::<serde_json::Map<_, _>>();
+        json!({
+            "language": self.metadata["language"],
+            "sources": sources,
+            "settings": settings,
+        })
+    }
+}
+
+/// Defines the interface for submitting shadow contract sources
+/// to a source verifier, such as a block explorer running against
+/// the shadow fork.
+#[async_trait]
+pub trait VerifierResource {
+    /// Submits the sources for verification, returning the verifier's
+    /// response message (e.g. a submission id)
+    async fn verify(
+        &self,
+        request: &VerificationRequest,
+    ) -> Result<String, Box<dyn std::error::Error>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_build_standard_json_input() {
+        let request = VerificationRequest {
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            source_path: "src/Router.sol".to_owned(),
+            contract_name: "Router".to_owned(),
+            metadata: json!({
+                "compiler": { "version": "0.6.6+commit.6c089d02" },
+                "language": "Solidity",
+                "settings": {
+                    "remappings": [":forge-std/=lib/forge-std/src/"],
+                    "optimizer": { "enabled": true, "runs": 200 },
+                    "compilationTarget": { "src/Router.sol": "Router" },
+                    "libraries": {}
+                }
+            }),
+            sources: BTreeMap::from([(
+                "src/Router.sol".to_owned(),
+                "contract Router {}".to_owned(),
+            )]),
+            constructor_arguments: String::new(),
+        };
+
+        assert_eq!(request.compiler_version(), "v0.6.6+commit.6c089d02");
+        let input = request.standard_json_input();
+        assert_eq!(input["language"], "Solidity");
+        assert_eq!(
+            input["sources"]["src/Router.sol"]["content"],
+            "contract Router {}"
+        );
+        assert_eq!(
+            input["settings"]["remappings"][0],
+            "forge-std/=lib/forge-std/src/"
+        );
+        assert!(input["settings"].get("compilationTarget").is_none());
+        assert_eq!(input["settings"]["optimizer"]["runs"], 200);
+    }
+}
@@ -1,5 +0,0 @@
-pub mod event;
-mod param;
-mod token;
-
-pub use event::decode_log;
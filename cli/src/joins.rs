@@ -0,0 +1,183 @@
+use serde_json::{Map, Value};
+
+use crate::{config::JoinRule, core::resources::sink::DecodedEvent, decode::DecodedLog};
+
+impl JoinRule {
+    /// Joins a shadow event with another decoded log of its transaction,
+    /// returning the composite record if the rule matches both.
+    pub fn join(
+        &self,
+        event: &DecodedEvent,
+        log: &ethers::types::Log,
+        decoded: &DecodedLog,
+    ) -> Option<DecodedEvent> {
+        let other = other_event(event, log, decoded);
+        if !self.event.matches(event) || !self.with.matches(&other) {
+            return None;
+        }
+        let joined =
+            self.on.iter().all(
+                |key| match (field(event, &key.event), field(&other, &key.with)) {
+                    (Some(left), Some(right)) => left.eq_ignore_ascii_case(&right),
+                    _ => false,
+                },
+            );
+        if !joined {
+            return None;
+        }
+
+        // Both events are kept whole, keyed by name
+        let (left_key, right_key) = if event.event == other.event {
+            ("event".to_owned(), "with".to_owned())
+        } else {
+            (event.event.clone(), other.event.clone())
+        };
+        let mut data = Map::new();
+        data.insert(left_key, event.data.clone());
+        data.insert(right_key, with_address(&other));
+        Some(DecodedEvent {
+            block_number: event.block_number,
+            block_timestamp: event.block_timestamp,
+            transaction_hash: event.transaction_hash.clone(),
+            // Stores keep composite records apart from events, see
+            // `DecodedEvent::is_join`
+            log_index: other.log_index,
+            address: event.address.clone(),
+            contract: event.contract.clone(),
+            event: self.name.clone(),
+            signature: format!("{}+{}", event.signature, other.signature),
+            schema: None,
+            data: Value::Object(data),
+            raw: None,
+            correlation: event.correlation.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Describes the other log as an event of the same transaction, so it
+/// can be matched like the shadow event.
+fn other_event(
+    event: &DecodedEvent,
+    log: &ethers::types::Log,
+    decoded: &DecodedLog,
+) -> DecodedEvent {
+    DecodedEvent {
+        block_number: event.block_number,
+        block_timestamp: event.block_timestamp,
+        transaction_hash: event.transaction_hash.clone(),
+        log_index: log.log_index.map(|i| i.as_u64()).unwrap_or_default(),
+        address: format!("{:?}", log.address),
+        contract: decoded.contract.clone(),
+        event: decoded.event.clone(),
+        signature: decoded.signature.clone(),
+        schema: None,
+        data: decoded.data.clone(),
+        raw: None,
+        ..Default::default()
+    }
+}
+
+/// Returns a field of an event as a string, where `address` is the
+/// address of the emitting contract unless the event has such a field.
+fn field(event: &DecodedEvent, name: &str) -> Option<String> {
+    match event.data.get(name) {
+        Some(Value::String(s)) => Some(s.clone()),
+        Some(value) => Some(value.to_string()),
+        None if name == "address" => Some(event.address.clone()),
+        None => None,
+    }
+}
+
+/// Returns the decoded fields of the other log, with the address of the
+/// contract that emitted it.
+fn with_address(other: &DecodedEvent) -> Value {
+    let mut data = match &other.data {
+        Value::Object(data) => data.clone(),
+        _ => Map::new(),
+    };
+    data.entry("address")
+        .or_insert_with(|| Value::String(other.address.clone()));
+    Value::Object(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{H160, U256};
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::{AlertRule, JoinKey};
+
+    fn rule() -> JoinRule {
+        JoinRule {
+            name: "ObservedSwap".to_owned(),
+            event: AlertRule {
+                event: Some("PriceObserved".to_owned()),
+                ..Default::default()
+            },
+            with: AlertRule {
+                event: Some("Swap".to_owned()),
+                ..Default::default()
+            },
+            on: vec![JoinKey {
+                event: "pool".to_owned(),
+                with: "address".to_owned(),
+            }],
+        }
+    }
+
+    fn shadow_event(pool: &str) -> DecodedEvent {
+        DecodedEvent {
+            block_number: 17_000_000,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 2,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "Oracle.sol:Oracle".to_owned(),
+            event: "PriceObserved".to_owned(),
+            signature: "PriceObserved(address,uint256)".to_owned(),
+            schema: None,
+            data: json!({ "pool": pool, "price": "42" }),
+            raw: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_join_events() {
+        let pool = H160::repeat_byte(0xaa);
+        let log = ethers::types::Log {
+            address: pool,
+            log_index: Some(U256::from(5)),
+            ..Default::default()
+        };
+        let swap = DecodedLog {
+            contract: "UniswapV2Pair".to_owned(),
+            event: "Swap".to_owned(),
+            signature: "Swap(address,uint256)".to_owned(),
+            data: json!({ "amount": "7" }),
+        };
+
+        // Addresses match regardless of checksum casing
+        let checksummed = ethers::utils::to_checksum(&pool, None);
+        let joined = rule()
+            .join(&shadow_event(&checksummed), &log, &swap)
+            .unwrap();
+        assert_eq!(joined.event, "ObservedSwap");
+        assert_eq!(joined.log_index, 5);
+        assert_eq!(
+            joined.data,
+            json!({
+                "PriceObserved": { "pool": checksummed, "price": "42" },
+                "Swap": { "amount": "7", "address": format!("{:?}", pool) },
+            })
+        );
+
+        // Another pool doesn't join
+        let other_pool = format!("{:?}", H160::repeat_byte(0xbb));
+        assert!(rule()
+            .join(&shadow_event(&other_pool), &log, &swap)
+            .is_none());
+    }
+}
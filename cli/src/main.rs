@@ -1,12 +1,23 @@
+mod alerts;
+mod balances;
 mod cmd;
+mod config;
 mod core;
-mod decode;
+mod joins;
 #[macro_use]
 mod macros;
+mod profile;
+mod providers;
+mod render;
 mod resources;
-use std::fmt;
+mod server;
+mod storage;
+mod validate;
+use std::{fmt, path::PathBuf, time::Duration};
 
-use clap::{Parser, Subcommand};
+use clap::{parser::ValueSource, CommandFactory, FromArgMatches, Parser, Subcommand};
+// The decoder is shared with the Python bindings and the C ABI
+use shadow_decode_core as decode;
 use thiserror::Error;
 
 #[derive(Parser)]
@@ -15,27 +26,130 @@ use thiserror::Error;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// Write a Chrome trace of the replay and decoding spans to this
+    /// file, viewable in Perfetto
+    #[arg(long, global = true)]
+    profile: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Check that a shadow contract's bytecode can be reproduced from source
+    Attest(cmd::attest::Attest),
+    /// Manage credentials
+    Auth(cmd::auth::Auth),
+    /// Back up shadow.json and the event archive
+    Backup(cmd::backup::Backup),
+    /// Measure the block replay throughput of a shadow fork
+    Bench(cmd::bench::Bench),
+    /// Generate Rust bindings for the shadow contracts on the shadow fork
+    Bindings(cmd::bindings::Bindings),
+    /// Diff the shadow events of the current and candidate shadow contracts
+    Compare(cmd::compare::Compare),
     /// Deploy a shadow contract
     Deploy(cmd::deploy::Deploy),
+    /// Find the contracts a factory created and shadow them
+    Discover(cmd::discover::Discover),
     /// Start a local shadow fork
     Fork(cmd::fork::Fork),
+    /// Inject instrumentation into a Solidity source
+    Generate(cmd::generate::Generate),
+    /// List the shadow contracts
+    List(cmd::list::List),
+    /// Add or remove tags on a shadow contract
+    Tag(cmd::tag::Tag),
+    /// Enable a disabled shadow contract
+    Enable(cmd::toggle::Toggle),
+    /// Disable a shadow contract without removing it
+    Disable(cmd::toggle::Toggle),
     /// Listen to events from a shadow contract
     Events(cmd::events::Events),
+    /// Start a local block explorer against the shadow fork
+    Explorer(cmd::explorer::Explorer),
+    /// Export the shadow contracts to a bundle file
+    Export(cmd::export::Export),
+    /// Export the historical events of a shadow contract to a file
+    ExportEvents(cmd::export_events::ExportEvents),
+    /// Import shadow contracts from a bundle file
+    Import(cmd::import::Import),
+    /// Impersonate an address on the running shadow fork
+    Impersonate(cmd::impersonate::Impersonate),
+    /// Add ETH to an address on the running shadow fork
+    Fund(cmd::fund::Fund),
+    /// Set state variables of a shadow contract on the running shadow fork
+    Patch(cmd::patch::Patch),
+    /// Re-process the logs in the dead-letter queue
+    Redrive(cmd::redrive::Redrive),
+    /// Restore shadow.json and the event archive from a backup
+    Restore(cmd::restore::Restore),
+    /// Update the CLI to the latest GitHub release
+    SelfUpdate(cmd::self_update::SelfUpdate),
+    /// Serve events from a shadow contract to API clients
+    Serve(cmd::serve::Serve),
+    /// Print statistics over the local event archive
+    Stats(cmd::stats::Stats),
+    /// Read a state variable of a shadow contract
+    Storage(cmd::storage::Storage),
+    /// Check the config file, init calls, scenarios and shadow.json
+    Validate(cmd::validate::Validate),
+    /// Submit a shadow contract's source to a verifier
+    Verify(cmd::verify::Verify),
 }
 
 /// Represents an error that can occur while running the CLI tool
 #[derive(Error, Debug)]
 enum CliError {
+    /// Error related to the attest command
+    AttestError(cmd::attest::AttestError),
+    /// Error related to the auth command
+    AuthError(cmd::auth::AuthError),
+    /// Error related to the backup and restore commands
+    BackupError(cmd::backup::BackupError),
+    /// Error related to the bindings command
+    BindingsError(cmd::bindings::BindingsError),
     /// Error related to the deploy command
     DeployError(cmd::deploy::DeployError),
+    /// Error related to the discover command
+    DiscoverError(cmd::discover::DiscoverError),
     /// Error related to the fork command
     ForkError(cmd::fork::ForkError),
+    /// Error related to the generate command
+    GenerateError(cmd::generate::GenerateError),
     /// Error related to the events command
     EventsError(cmd::events::EventsError),
+    /// Error related to the explorer command
+    ExplorerError(cmd::explorer::ExplorerError),
+    /// Error related to the export and import commands
+    BundleError(cmd::export::BundleError),
+    /// Error related to the export-events command
+    ExportEventsError(cmd::export_events::ExportEventsError),
+    /// Error related to the impersonate and fund commands
+    CheatsError(cmd::impersonate::CheatsError),
+    /// Error related to the list command
+    ListError(cmd::list::ListError),
+    /// Error related to the patch command
+    PatchError(cmd::patch::PatchError),
+    /// Error starting the --profile trace
+    ProfileError(String),
+    /// Error related to the tag command
+    TagError(cmd::tag::TagError),
+    /// Error related to the enable and disable commands
+    ToggleError(cmd::toggle::ToggleError),
+    /// Error related to the redrive command
+    RedriveError(cmd::redrive::RedriveError),
+    /// Error related to the self-update command
+    SelfUpdateError(cmd::self_update::SelfUpdateError),
+    /// Error related to the serve command
+    ServeError(cmd::serve::ServeError),
+    /// Error related to the stats command
+    StatsError(cmd::stats::StatsError),
+    /// Error related to the storage command
+    StorageError(cmd::storage::StorageError),
+    /// Error related to the validate command
+    ValidateError(cmd::validate::ValidateError),
+    /// Error related to the verify command
+    VerifyError(cmd::verify::VerifyError),
     /// Error that should never occur
     Never,
 }
@@ -43,9 +157,31 @@ enum CliError {
 impl fmt::Display for CliError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            CliError::AttestError(err) => write!(f, "Attest error: {}", err),
+            CliError::AuthError(err) => write!(f, "Auth error: {}", err),
+            CliError::BackupError(err) => write!(f, "Backup error: {}", err),
+            CliError::BindingsError(err) => write!(f, "Bindings error: {}", err),
             CliError::DeployError(err) => write!(f, "Deploy error: {}", err),
+            CliError::DiscoverError(err) => write!(f, "Discover error: {}", err),
             CliError::ForkError(err) => write!(f, "Fork error: {}", err),
+            CliError::GenerateError(err) => write!(f, "Generate error: {}", err),
             CliError::EventsError(err) => write!(f, "Events error: {}", err),
+            CliError::ExplorerError(err) => write!(f, "Explorer error: {}", err),
+            CliError::BundleError(err) => write!(f, "Bundle error: {}", err),
+            CliError::ExportEventsError(err) => write!(f, "Export events error: {}", err),
+            CliError::CheatsError(err) => write!(f, "Cheats error: {}", err),
+            CliError::ListError(err) => write!(f, "List error: {}", err),
+            CliError::PatchError(err) => write!(f, "Patch error: {}", err),
+            CliError::ProfileError(err) => write!(f, "Profile error: {}", err),
+            CliError::TagError(err) => write!(f, "Tag error: {}", err),
+            CliError::ToggleError(err) => write!(f, "Toggle error: {}", err),
+            CliError::RedriveError(err) => write!(f, "Redrive error: {}", err),
+            CliError::SelfUpdateError(err) => write!(f, "Self-update error: {}", err),
+            CliError::ServeError(err) => write!(f, "Serve error: {}", err),
+            CliError::StatsError(err) => write!(f, "Stats error: {}", err),
+            CliError::StorageError(err) => write!(f, "Storage error: {}", err),
+            CliError::ValidateError(err) => write!(f, "Validate error: {}", err),
+            CliError::VerifyError(err) => write!(f, "Verify error: {}", err),
             CliError::Never => write!(
                 f,
                 "This error should never occur, please file a bug report to help@tryshadow.xyz."
@@ -54,23 +190,185 @@ impl fmt::Display for CliError {
     }
 }
 
+/// How long a command gets to stop after Ctrl-C when profiling, before
+/// the trace file is written and the CLI exits
+const PROFILE_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
 #[tokio::main]
 async fn main() -> Result<(), CliError> {
-    let cli = Cli::parse();
+    // The matches tell the flags that were set apart from their defaults
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let (Some(Commands::Fork(fork)), Some(("fork", fork_matches))) =
+        (&mut cli.command, matches.subcommand())
+    {
+        fork.gas_multiplier_set = !matches!(
+            fork_matches.value_source("gas_multiplier"),
+            None | Some(ValueSource::DefaultValue)
+        );
+    }
+    // Progress, warnings and errors go to stderr, keeping stdout for
+    // output. With --profile, the trace subscriber prints them instead.
+    let profile = match cli.profile.as_deref() {
+        Some(path) => {
+            Some(profile::start(path).map_err(|e| CliError::ProfileError(e.to_string()))?)
+        }
+        None => {
+            env_logger::Builder::from_env(
+                env_logger::Env::default().default_filter_or("warn,shadow=info"),
+            )
+            .init();
+            None
+        }
+    };
+
+    let run = run(&cli);
+    tokio::pin!(run);
+    let result = tokio::select! {
+        result = &mut run => result,
+        // Catching Ctrl-C keeps the process alive long enough to write
+        // the trace, commands that handle it themselves can still stop
+        Ok(()) = tokio::signal::ctrl_c(), if profile.is_some() => {
+            match tokio::time::timeout(PROFILE_SHUTDOWN_GRACE, &mut run).await {
+                Ok(result) => result,
+                Err(_) => {
+                    log::warn!("Interrupted by Ctrl-C");
+                    Ok(())
+                }
+            }
+        }
+    };
+    // Writes the trace file on every exit path
+    drop(profile);
+    result
+}
 
+/// Runs the selected command.
+async fn run(cli: &Cli) -> Result<(), CliError> {
     match &cli.command {
+        Some(Commands::Attest(attest)) => {
+            attest.run().await.map_err(CliError::AttestError)?;
+            Ok(())
+        }
+        Some(Commands::Auth(auth)) => {
+            auth.run().await.map_err(CliError::AuthError)?;
+            Ok(())
+        }
+        Some(Commands::Backup(backup)) => {
+            backup.run().await.map_err(CliError::BackupError)?;
+            Ok(())
+        }
+        Some(Commands::Bench(bench)) => {
+            bench.run().await.map_err(CliError::ForkError)?;
+            Ok(())
+        }
+        Some(Commands::Bindings(bindings)) => {
+            bindings.run().await.map_err(CliError::BindingsError)?;
+            Ok(())
+        }
+        Some(Commands::Compare(compare)) => {
+            compare.run().await.map_err(CliError::ForkError)?;
+            Ok(())
+        }
         Some(Commands::Deploy(deploy)) => {
             deploy.run().await.map_err(CliError::DeployError)?;
             Ok(())
         }
+        Some(Commands::Discover(discover)) => {
+            discover.run().await.map_err(CliError::DiscoverError)?;
+            Ok(())
+        }
         Some(Commands::Fork(fork)) => {
             fork.run().await.map_err(CliError::ForkError)?;
             Ok(())
         }
+        Some(Commands::Generate(generate)) => {
+            generate.run().await.map_err(CliError::GenerateError)?;
+            Ok(())
+        }
+        Some(Commands::List(list)) => {
+            list.run().await.map_err(CliError::ListError)?;
+            Ok(())
+        }
+        Some(Commands::Tag(tag)) => {
+            tag.run().await.map_err(CliError::TagError)?;
+            Ok(())
+        }
+        Some(Commands::Enable(toggle)) => {
+            toggle.run(true).await.map_err(CliError::ToggleError)?;
+            Ok(())
+        }
+        Some(Commands::Disable(toggle)) => {
+            toggle.run(false).await.map_err(CliError::ToggleError)?;
+            Ok(())
+        }
         Some(Commands::Events(events)) => {
             events.run().await.map_err(CliError::EventsError)?;
             Ok(())
         }
+        Some(Commands::Explorer(explorer)) => {
+            explorer.run().await.map_err(CliError::ExplorerError)?;
+            Ok(())
+        }
+        Some(Commands::Export(export)) => {
+            export.run().await.map_err(CliError::BundleError)?;
+            Ok(())
+        }
+        Some(Commands::ExportEvents(export_events)) => {
+            export_events
+                .run()
+                .await
+                .map_err(CliError::ExportEventsError)?;
+            Ok(())
+        }
+        Some(Commands::Import(import)) => {
+            import.run().await.map_err(CliError::BundleError)?;
+            Ok(())
+        }
+        Some(Commands::Impersonate(impersonate)) => {
+            impersonate.run().await.map_err(CliError::CheatsError)?;
+            Ok(())
+        }
+        Some(Commands::Fund(fund)) => {
+            fund.run().await.map_err(CliError::CheatsError)?;
+            Ok(())
+        }
+        Some(Commands::Patch(patch)) => {
+            patch.run().await.map_err(CliError::PatchError)?;
+            Ok(())
+        }
+        Some(Commands::Redrive(redrive)) => {
+            redrive.run().await.map_err(CliError::RedriveError)?;
+            Ok(())
+        }
+        Some(Commands::Restore(restore)) => {
+            restore.run().await.map_err(CliError::BackupError)?;
+            Ok(())
+        }
+        Some(Commands::SelfUpdate(self_update)) => {
+            self_update.run().await.map_err(CliError::SelfUpdateError)?;
+            Ok(())
+        }
+        Some(Commands::Serve(serve)) => {
+            serve.run().await.map_err(CliError::ServeError)?;
+            Ok(())
+        }
+        Some(Commands::Stats(stats)) => {
+            stats.run().await.map_err(CliError::StatsError)?;
+            Ok(())
+        }
+        Some(Commands::Storage(storage)) => {
+            storage.run().await.map_err(CliError::StorageError)?;
+            Ok(())
+        }
+        Some(Commands::Validate(validate)) => {
+            validate.run().await.map_err(CliError::ValidateError)?;
+            Ok(())
+        }
+        Some(Commands::Verify(verify)) => {
+            verify.run().await.map_err(CliError::VerifyError)?;
+            Ok(())
+        }
         None => Err(CliError::Never),
     }
 }
@@ -0,0 +1,36 @@
+use std::path::Path;
+
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    prelude::*,
+};
+
+/// Records the tracing spans of the replay pipeline (block replays,
+/// receipt fetching, decoding, and anvil's own spans) to a Chrome
+/// trace file, which can be opened in Perfetto or `chrome://tracing`.
+///
+/// Only one global logger can be installed, so the same subscriber
+/// also prints `log` records to stderr, at the default `RUST_LOG`
+/// levels, instead of env_logger.
+///
+/// The file is written when the returned guard is dropped. Fails if a
+/// global subscriber or logger is already installed.
+pub fn start(path: &Path) -> Result<FlushGuard, Box<dyn std::error::Error>> {
+    let (chrome_layer, guard) = ChromeLayerBuilder::new()
+        .file(path)
+        .include_args(true)
+        .build();
+    let stderr_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(
+            Targets::new()
+                .with_target("shadow", LevelFilter::INFO)
+                .with_default(LevelFilter::WARN),
+        );
+    tracing_subscriber::registry()
+        .with(chrome_layer.with_filter(LevelFilter::INFO))
+        .with(stderr_layer)
+        .try_init()?;
+    Ok(guard)
+}
@@ -0,0 +1,489 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use async_trait::async_trait;
+use ethers::{
+    providers::{JsonRpcClient, JsonRpcError, ProviderError, PubsubClient, RpcError},
+    types::U256,
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures_util::{Stream, StreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, value::RawValue, Value};
+use thiserror::Error;
+use tokio::sync::watch;
+
+/// Where a recorded request was sent from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Source {
+    /// The upstream provider blocks and receipts are read from
+    Provider,
+    /// Anvil, reading the forked state
+    Fork,
+}
+
+/// An entry of a cassette file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Entry {
+    /// A request and its response, either a result or a JSON-RPC error
+    #[serde(rename_all = "camelCase")]
+    Request {
+        source: Source,
+        method: String,
+        params: Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        result: Option<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<Value>,
+    },
+    /// A subscription notification, with the number of provider requests
+    /// answered before it arrived
+    #[serde(rename_all = "camelCase")]
+    Notification {
+        subscription: U256,
+        after: usize,
+        payload: Value,
+    },
+}
+
+/// Represents an error of the cassette clients
+#[derive(Error, Debug)]
+pub enum CassetteError {
+    /// The upstream provider failed
+    #[error("{0}")]
+    Provider(ProviderError),
+    /// A recorded JSON-RPC error
+    #[error("{0}")]
+    JsonRpc(JsonRpcError),
+    /// The session asked for a request that wasn't recorded
+    #[error("Request not in cassette: {0} {1}")]
+    NotRecorded(String, Value),
+    #[error("SerdeJson: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("Io: {0}")]
+    Io(#[from] io::Error),
+}
+
+impl RpcError for CassetteError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            CassetteError::Provider(e) => e.as_error_response(),
+            CassetteError::JsonRpc(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            CassetteError::Provider(e) => e.as_serde_error(),
+            CassetteError::SerdeJson(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<CassetteError> for ProviderError {
+    fn from(e: CassetteError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
+
+/// The notifications of a subscription, recorded or played back.
+pub type NotificationStream = Pin<Box<dyn Stream<Item = Box<RawValue>> + Send>>;
+
+/// Writes the RPC traffic of a fork session to a gzipped JSONL cassette.
+#[derive(Debug)]
+pub struct CassetteRecorder {
+    writer: Mutex<Option<GzEncoder<BufWriter<File>>>>,
+    /// The number of provider requests answered so far
+    answered: AtomicUsize,
+}
+
+impl CassetteRecorder {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self {
+            writer: Mutex::new(Some(GzEncoder::new(
+                BufWriter::new(file),
+                Compression::default(),
+            ))),
+            answered: AtomicUsize::new(0),
+        })
+    }
+
+    /// Records a request and its response, a result or a JSON-RPC
+    /// error. Transport failures aren't part of the session, and are
+    /// left out.
+    pub fn record_request(
+        &self,
+        source: Source,
+        method: &str,
+        params: Value,
+        response: Result<&Value, &JsonRpcError>,
+    ) {
+        let (result, error) = match response {
+            Ok(result) => (Some(result.clone()), None),
+            Err(e) => (
+                None,
+                Some(json!({ "code": e.code, "message": e.message, "data": e.data })),
+            ),
+        };
+        self.write(&Entry::Request {
+            source,
+            method: method.to_owned(),
+            params,
+            result,
+            error,
+        });
+        if source == Source::Provider {
+            self.answered.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Records a subscription notification.
+    pub fn record_notification(&self, subscription: U256, payload: &RawValue) {
+        let payload = serde_json::from_str(payload.get()).unwrap_or(Value::Null);
+        self.write(&Entry::Notification {
+            subscription,
+            after: self.answered.load(Ordering::SeqCst),
+            payload,
+        });
+    }
+
+    /// Completes the cassette file. Later entries are dropped.
+    pub fn finish(&self) -> io::Result<()> {
+        match self.writer.lock().unwrap().take() {
+            Some(writer) => writer.finish()?.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn write(&self, entry: &Entry) {
+        let mut writer = self.writer.lock().unwrap();
+        let Some(writer) = writer.as_mut() else {
+            return;
+        };
+        let result = serde_json::to_writer(&mut *writer, entry)
+            .map_err(io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"));
+        if let Err(e) = result {
+            log::warn!("Error writing to cassette: {}", e);
+        }
+    }
+}
+
+impl Drop for CassetteRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            log::warn!("Error finishing cassette: {}", e);
+        }
+    }
+}
+
+/// A recorded fork session, answering the same requests with the same
+/// responses.
+///
+/// Repeated requests are answered in the order they were recorded, and
+/// with the last response once they run out, e.g. for polling.
+#[derive(Debug)]
+pub struct Cassette {
+    responses: Mutex<HashMap<(Source, String), VecDeque<Result<Value, Value>>>>,
+    notifications: Mutex<HashMap<U256, Vec<(usize, Value)>>>,
+    /// The number of provider requests answered so far
+    answered: watch::Sender<usize>,
+}
+
+impl Cassette {
+    /// Loads a cassette written by a [`CassetteRecorder`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CassetteError> {
+        Ok(Self::from_entries(read_entries(path)?))
+    }
+
+    pub fn from_entries(entries: impl IntoIterator<Item = Entry>) -> Self {
+        let mut responses: HashMap<_, VecDeque<_>> = HashMap::new();
+        let mut notifications: HashMap<_, Vec<_>> = HashMap::new();
+        for entry in entries {
+            match entry {
+                Entry::Request {
+                    source,
+                    method,
+                    params,
+                    result,
+                    error,
+                } => {
+                    let response = match error {
+                        Some(error) => Err(error),
+                        None => Ok(result.unwrap_or(Value::Null)),
+                    };
+                    responses
+                        .entry((source, request_key(&method, &params)))
+                        .or_default()
+                        .push_back(response);
+                }
+                Entry::Notification {
+                    subscription,
+                    after,
+                    payload,
+                } => notifications
+                    .entry(subscription)
+                    .or_default()
+                    .push((after, payload)),
+            }
+        }
+        Self {
+            responses: Mutex::new(responses),
+            notifications: Mutex::new(notifications),
+            answered: watch::channel(0).0,
+        }
+    }
+
+    /// Answers a request with its recorded response.
+    pub fn respond(
+        &self,
+        source: Source,
+        method: &str,
+        params: &Value,
+    ) -> Result<Value, CassetteError> {
+        let response = {
+            let mut responses = self.responses.lock().unwrap();
+            let queue = responses
+                .get_mut(&(source, request_key(method, params)))
+                .ok_or_else(|| CassetteError::NotRecorded(method.to_owned(), params.clone()))?;
+            if queue.len() > 1 {
+                queue.pop_front().unwrap()
+            } else {
+                queue[0].clone()
+            }
+        };
+        if source == Source::Provider {
+            self.answered.send_modify(|answered| *answered += 1);
+        }
+        response.map_err(|error| match serde_json::from_value(error) {
+            Ok(error) => CassetteError::JsonRpc(error),
+            Err(e) => CassetteError::SerdeJson(e),
+        })
+    }
+
+    /// Plays back the notifications of a subscription.
+    ///
+    /// Each notification is held back until the provider requests
+    /// answered before it arrived are played back too, so the session
+    /// sees new blocks at the same points as when it was recorded.
+    pub fn notifications(&self, subscription: U256) -> NotificationStream {
+        let notifications = self
+            .notifications
+            .lock()
+            .unwrap()
+            .remove(&subscription)
+            .unwrap_or_default();
+        let answered = self.answered.subscribe();
+        Box::pin(futures_util::stream::unfold(
+            (notifications.into_iter(), answered),
+            |(mut notifications, mut answered)| async move {
+                let (after, payload) = notifications.next()?;
+                let _ = answered.wait_for(|answered| *answered >= after).await;
+                let payload = serde_json::value::to_raw_value(&payload).ok()?;
+                Some((payload, (notifications, answered)))
+            },
+        ))
+    }
+}
+
+/// Reads the entries of a cassette file, in the order they were recorded.
+pub fn read_entries(path: impl AsRef<Path>) -> Result<Vec<Entry>, CassetteError> {
+    let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            entries.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(entries)
+}
+
+fn request_key(method: &str, params: &Value) -> String {
+    format!("{} {}", method, params)
+}
+
+/// A JSON-RPC client recording the requests of another client, and the
+/// notifications of its subscriptions, to a cassette.
+#[derive(Debug)]
+pub struct RecordingClient<C> {
+    inner: C,
+    recorder: Arc<CassetteRecorder>,
+}
+
+impl<C> RecordingClient<C> {
+    pub fn new(inner: C, recorder: Arc<CassetteRecorder>) -> Self {
+        Self { inner, recorder }
+    }
+}
+
+#[async_trait]
+impl<C: JsonRpcClient> JsonRpcClient for RecordingClient<C> {
+    type Error = CassetteError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let recorded_params = serde_json::to_value(&params)?;
+        match self.inner.request::<_, Value>(method, params).await {
+            Ok(value) => {
+                self.recorder
+                    .record_request(Source::Provider, method, recorded_params, Ok(&value));
+                Ok(serde_json::from_value(value)?)
+            }
+            Err(e) => {
+                let e: ProviderError = e.into();
+                if let Some(error) = e.as_error_response() {
+                    self.recorder.record_request(
+                        Source::Provider,
+                        method,
+                        recorded_params,
+                        Err(error),
+                    );
+                }
+                Err(CassetteError::Provider(e))
+            }
+        }
+    }
+}
+
+impl<C> PubsubClient for RecordingClient<C>
+where
+    C: JsonRpcClient + PubsubClient,
+    C::NotificationStream: 'static,
+{
+    type NotificationStream = NotificationStream;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        let id = id.into();
+        let stream = self
+            .inner
+            .subscribe(id)
+            .map_err(|e| CassetteError::Provider(e.into()))?;
+        let recorder = self.recorder.clone();
+        Ok(Box::pin(stream.inspect(move |payload| {
+            recorder.record_notification(id, payload)
+        })))
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        self.inner
+            .unsubscribe(id)
+            .map_err(|e| CassetteError::Provider(e.into()))
+    }
+}
+
+/// A JSON-RPC client playing back a cassette, without any upstream
+/// endpoint.
+#[derive(Debug)]
+pub struct PlaybackClient {
+    cassette: Arc<Cassette>,
+}
+
+impl PlaybackClient {
+    pub fn new(cassette: Arc<Cassette>) -> Self {
+        Self { cassette }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for PlaybackClient {
+    type Error = CassetteError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(&params)?;
+        let value = self.cassette.respond(Source::Provider, method, &params)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl PubsubClient for PlaybackClient {
+    type NotificationStream = NotificationStream;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        Ok(self.cassette.notifications(id.into()))
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, _id: T) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::providers::MockProvider;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_record_and_play_back() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let recorder = Arc::new(CassetteRecorder::create(file.path()).unwrap());
+
+        let mock = MockProvider::new();
+        mock.push(U256::from(1)).unwrap();
+        let client = RecordingClient::new(mock, recorder.clone());
+        let chain_id: U256 = client.request("eth_chainId", ()).await.unwrap();
+        assert_eq!(chain_id, U256::from(1));
+        recorder.record_notification(
+            U256::from(7),
+            &RawValue::from_string(r#"{"number":"0x1"}"#.to_owned()).unwrap(),
+        );
+        recorder.record_request(
+            Source::Fork,
+            "eth_getCode",
+            json!(["0x00", "latest"]),
+            Err(&JsonRpcError {
+                code: -32000,
+                message: "header not found".to_owned(),
+                data: None,
+            }),
+        );
+        recorder.finish().unwrap();
+
+        let cassette = Arc::new(Cassette::load(file.path()).unwrap());
+        let client = PlaybackClient::new(cassette.clone());
+
+        // The notification is held back until eth_chainId is played back
+        let mut notifications = client.subscribe(U256::from(7)).unwrap();
+        assert!(futures_util::FutureExt::now_or_never(notifications.next()).is_none());
+        let chain_id: U256 = client.request("eth_chainId", ()).await.unwrap();
+        assert_eq!(chain_id, U256::from(1));
+        let notification = notifications.next().await.unwrap();
+        assert_eq!(notification.get(), r#"{"number":"0x1"}"#);
+        assert!(notifications.next().await.is_none());
+
+        let error = cassette
+            .respond(Source::Fork, "eth_getCode", &json!(["0x00", "latest"]))
+            .unwrap_err();
+        assert_eq!(
+            error.as_error_response().unwrap().message,
+            "header not found"
+        );
+        assert!(matches!(
+            client.request::<_, U256>("eth_blockNumber", ()).await,
+            Err(CassetteError::NotRecorded(..))
+        ));
+    }
+}
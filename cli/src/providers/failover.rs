@@ -0,0 +1,272 @@
+use std::{
+    fmt::Debug,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use async_trait::async_trait;
+use ethers::{
+    providers::{JsonRpcClient, JsonRpcError, ProviderError, PubsubClient, RpcError},
+    types::U256,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+/// RPC methods whose responses are checked against a quorum of
+/// providers when quorum checking is enabled.
+const QUORUM_METHODS: [&str; 2] = ["eth_getBlockByNumber", "eth_getBlockByHash"];
+
+/// A JSON-RPC client that fails over between multiple upstream endpoints.
+///
+/// Requests are sent to the currently active endpoint. When a request
+/// fails, the next endpoint becomes active and the request is retried,
+/// until every endpoint has been tried once. The active endpoint is
+/// sticky, so subscriptions are created on the same connection as the
+/// `eth_subscribe` request that preceded them.
+///
+/// Subscriptions that end, e.g. because their endpoint dropped, are
+/// re-established by the caller: the `eth_subscribe` request fails over
+/// like any other, so the new subscription is on a live endpoint.
+///
+/// When `quorum` is greater than one, block header requests are sent to
+/// `quorum` endpoints and their block hashes must agree.
+#[derive(Debug)]
+pub struct FailoverClient<C> {
+    clients: Vec<C>,
+    active: AtomicUsize,
+    quorum: usize,
+}
+
+/// Represents an error returned by the [`FailoverClient`]
+#[derive(Error, Debug)]
+pub enum FailoverError {
+    /// Every endpoint failed; contains the last error
+    #[error("All {0} providers failed, last error: {1}")]
+    AllProvidersFailed(usize, ProviderError),
+    /// A single endpoint failed, e.g. with a JSON-RPC error, or on a
+    /// subscription
+    #[error("ProviderError: {0}")]
+    ProviderError(ProviderError),
+    /// The endpoints disagreed on a block header
+    #[error("Quorum of {0} providers not reached for {1}")]
+    QuorumNotReached(usize, String),
+    /// The response could not be deserialized
+    #[error("SerdeJson: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+impl RpcError for FailoverError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            FailoverError::AllProvidersFailed(_, e) | FailoverError::ProviderError(e) => {
+                e.as_error_response()
+            }
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            FailoverError::SerdeJson(e) => Some(e),
+            FailoverError::AllProvidersFailed(_, e) | FailoverError::ProviderError(e) => {
+                e.as_serde_error()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<FailoverError> for ProviderError {
+    fn from(e: FailoverError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
+
+impl<C: JsonRpcClient> FailoverClient<C> {
+    /// Creates a new failover client.
+    ///
+    /// Panics if `clients` is empty.
+    pub fn new(clients: Vec<C>, quorum: usize) -> Self {
+        assert!(!clients.is_empty(), "At least one RPC endpoint is required");
+        let quorum = quorum.clamp(1, clients.len());
+        Self {
+            clients,
+            active: AtomicUsize::new(0),
+            quorum,
+        }
+    }
+
+    /// Returns the index of the currently active endpoint.
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Sends a request to the active endpoint, failing over on error.
+    async fn request_with_failover<T>(
+        &self,
+        method: &str,
+        params: &T,
+    ) -> Result<Value, FailoverError>
+    where
+        T: Debug + Serialize + Send + Sync,
+    {
+        let start = self.active();
+        let mut last_error = None;
+        for offset in 0..self.clients.len() {
+            let index = (start + offset) % self.clients.len();
+            match self.clients[index]
+                .request::<_, Value>(method, params)
+                .await
+            {
+                Ok(value) => {
+                    if index != start {
+                        log::warn!("Failed over to RPC endpoint #{}", index);
+                        self.active.store(index, Ordering::SeqCst);
+                    }
+                    return Ok(value);
+                }
+                Err(e) => {
+                    let e: ProviderError = e.into();
+                    // JSON-RPC errors (e.g. reverts) are not endpoint failures
+                    if e.as_error_response().is_some() {
+                        return Err(FailoverError::ProviderError(e));
+                    }
+                    log::warn!("RPC endpoint #{} failed on {}: {}", index, method, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(FailoverError::AllProvidersFailed(
+            self.clients.len(),
+            last_error.unwrap(),
+        ))
+    }
+
+    /// Sends a block header request to `quorum` endpoints and checks that
+    /// they agree on the block hash.
+    async fn request_with_quorum<T>(&self, method: &str, params: &T) -> Result<Value, FailoverError>
+    where
+        T: Debug + Serialize + Send + Sync,
+    {
+        let start = self.active();
+        let mut responses = Vec::new();
+        for offset in 0..self.clients.len() {
+            if responses.len() == self.quorum {
+                break;
+            }
+            let index = (start + offset) % self.clients.len();
+            match self.clients[index]
+                .request::<_, Value>(method, params)
+                .await
+            {
+                Ok(value) => responses.push(value),
+                Err(e) => {
+                    let e: ProviderError = e.into();
+                    log::warn!("RPC endpoint #{} failed on {}: {}", index, method, e);
+                }
+            }
+        }
+
+        if responses.len() < self.quorum {
+            return Err(FailoverError::QuorumNotReached(
+                self.quorum,
+                method.to_owned(),
+            ));
+        }
+        let hash = responses[0].get("hash").cloned();
+        if responses.iter().any(|r| r.get("hash").cloned() != hash) {
+            return Err(FailoverError::QuorumNotReached(
+                self.quorum,
+                method.to_owned(),
+            ));
+        }
+        Ok(responses.swap_remove(0))
+    }
+}
+
+#[async_trait]
+impl<C: JsonRpcClient> JsonRpcClient for FailoverClient<C> {
+    type Error = FailoverError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let value = if self.quorum > 1 && QUORUM_METHODS.contains(&method) {
+            self.request_with_quorum(method, &params).await?
+        } else {
+            self.request_with_failover(method, &params).await?
+        };
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl<C: JsonRpcClient + PubsubClient> PubsubClient for FailoverClient<C> {
+    type NotificationStream = C::NotificationStream;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        self.clients[self.active()]
+            .subscribe(id)
+            .map_err(|e| FailoverError::ProviderError(e.into()))
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        self.clients[self.active()]
+            .unsubscribe(id)
+            .map_err(|e| FailoverError::ProviderError(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::providers::{MockProvider, MockResponse};
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_fail_over() {
+        // A mock provider without responses fails every request
+        let failing = MockProvider::new();
+        let healthy = MockProvider::new();
+        healthy.push(ethers::types::U64::from(17_000_000)).unwrap();
+
+        let client = FailoverClient::new(vec![failing, healthy], 1);
+        let block_number: ethers::types::U64 = client.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block_number, ethers::types::U64::from(17_000_000));
+        assert_eq!(client.active(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn does_not_fail_over_on_json_rpc_errors() {
+        let reverting = MockProvider::new();
+        reverting.push_response(MockResponse::Error(JsonRpcError {
+            code: 3,
+            message: "execution reverted".to_owned(),
+            data: None,
+        }));
+        let healthy = MockProvider::new();
+        healthy.push(ethers::types::Bytes::default()).unwrap();
+
+        let client = FailoverClient::new(vec![reverting, healthy], 1);
+        let result: Result<Value, _> = client.request("eth_call", ()).await;
+        assert!(matches!(result, Err(FailoverError::ProviderError(_))));
+        assert!(result.unwrap_err().as_error_response().is_some());
+        assert_eq!(client.active(), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_detect_quorum_mismatch() {
+        let a = MockProvider::new();
+        a.push(json!({ "hash": "0x01" })).unwrap();
+        let b = MockProvider::new();
+        b.push(json!({ "hash": "0x02" })).unwrap();
+
+        let client = FailoverClient::new(vec![a, b], 2);
+        let result: Result<Value, _> = client
+            .request("eth_getBlockByNumber", ("latest", false))
+            .await;
+        assert!(matches!(result, Err(FailoverError::QuorumNotReached(2, _))));
+    }
+}
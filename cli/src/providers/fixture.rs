@@ -0,0 +1,337 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ethers::providers::Provider;
+use serde_json::Value;
+
+use super::{
+    cassette::{read_entries, Cassette, CassetteError, CassetteRecorder, Entry, Source},
+    fork_proxy::{self, ForkTape},
+    PlaybackClient,
+};
+use crate::{
+    core::actions::{
+        fork::{ForkError, ReplayOptions},
+        Fork,
+    },
+    resources::shadow::{LocalShadowStore, SHADOW_FILE},
+};
+
+/// The cassette of a fixture, in its directory
+pub const SESSION_FILE: &str = "session.jsonl.gz";
+
+/// A chain snapshot to run fork and events sessions against without
+/// network access.
+///
+/// A fixture is a directory holding a recorded session: the state anvil
+/// read from the fork block, the blocks, receipts and subscription
+/// notifications the session consumed, and the shadow contracts it ran
+/// with. Sessions played back from it stop once its blocks run out.
+#[derive(Clone, Debug)]
+pub struct ChainFixture {
+    dir: PathBuf,
+}
+
+impl ChainFixture {
+    /// Starts capturing a fixture into a directory, with the shadow
+    /// contracts of `shadow.json`.
+    ///
+    /// The returned recorder records the session, and must be finished
+    /// once it ends.
+    pub fn create(
+        dir: impl AsRef<Path>,
+        shadow_json: impl AsRef<Path>,
+    ) -> Result<CassetteRecorder, io::Error> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        fs::copy(shadow_json, dir.join(SHADOW_FILE))?;
+        CassetteRecorder::create(dir.join(SESSION_FILE))
+    }
+
+    /// Opens a fixture directory.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let dir = dir.as_ref().to_path_buf();
+        for file in [SESSION_FILE, SHADOW_FILE] {
+            if !dir.join(file).is_file() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Fixture {} has no {}", dir.display(), file),
+                ));
+            }
+        }
+        Ok(Self { dir })
+    }
+
+    /// Returns the fixture directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the path of the recorded session.
+    pub fn session_path(&self) -> PathBuf {
+        self.dir.join(SESSION_FILE)
+    }
+
+    /// Loads the recorded session.
+    pub fn cassette(&self) -> Result<Cassette, CassetteError> {
+        Cassette::load(self.session_path())
+    }
+
+    /// Returns the shadow store of the contracts the session ran with.
+    pub fn shadow_resource(&self) -> LocalShadowStore {
+        LocalShadowStore::new(&self.dir)
+    }
+
+    /// Returns the numbers of the blocks notified during the session,
+    /// in order.
+    pub fn blocks(&self) -> Result<Vec<u64>, CassetteError> {
+        Ok(read_entries(&self.session_path())?
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Notification { payload, .. } => payload
+                    .get("number")
+                    .and_then(Value::as_str)
+                    .and_then(|number| {
+                        u64::from_str_radix(number.trim_start_matches("0x"), 16).ok()
+                    }),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Returns the number of state reads anvil made from the fork block,
+    /// which make up the fixture's state snapshot.
+    pub fn state_reads(&self) -> Result<usize, CassetteError> {
+        Ok(read_entries(&self.session_path())?
+            .iter()
+            .filter(|entry| {
+                matches!(
+                    entry,
+                    Entry::Request {
+                        source: Source::Fork,
+                        ..
+                    }
+                )
+            })
+            .count())
+    }
+
+    /// Builds a provider playing back the session.
+    pub fn provider(&self) -> Result<Provider<PlaybackClient>, CassetteError> {
+        Ok(Provider::new(PlaybackClient::new(Arc::new(
+            self.cassette()?,
+        ))))
+    }
+
+    /// Builds a fork replaying the fixture's blocks offline, with anvil
+    /// forking from the recorded state.
+    pub async fn fork(&self, options: ReplayOptions) -> Result<Fork<PlaybackClient>, ForkError> {
+        let cassette = Arc::new(
+            self.cassette()
+                .map_err(|e| ForkError::CustomError(format!("Error loading fixture: {}", e)))?,
+        );
+        let fork_url = fork_proxy::serve(ForkTape::Playback(cassette.clone()))
+            .await
+            .map_err(|e| ForkError::CustomError(format!("Error serving fixture: {}", e)))?;
+        Fork::new(
+            Provider::new(PlaybackClient::new(cassette)),
+            self.shadow_resource(),
+            fork_url,
+            ReplayOptions {
+                storage_caching: false,
+                ..options
+            },
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use anvil::NodeConfig;
+    use anvil_core::eth::transaction::EthTransactionRequest;
+    use async_trait::async_trait;
+    use ethers::{
+        providers::{Middleware, Ws},
+        types::{H160, U256},
+    };
+    use serde_json::{json, value::RawValue};
+
+    use super::*;
+    use crate::{
+        core::{
+            actions::Events,
+            resources::sink::{DecodedEvent, EventSink},
+        },
+        providers::RecordingClient,
+        resources::artifacts::LocalArtifactStore,
+        test_fixture,
+    };
+
+    /// Collects the events written to it
+    #[derive(Clone, Default)]
+    struct MemorySink(Arc<Mutex<Vec<DecodedEvent>>>);
+
+    #[async_trait]
+    impl EventSink for MemorySink {
+        async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_capture_and_open_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture_dir = dir.path().join("fixture");
+        assert!(ChainFixture::open(&fixture_dir).is_err());
+
+        let recorder =
+            ChainFixture::create(&fixture_dir, test_fixture!("resources", "shadow.json")).unwrap();
+        recorder.record_request(
+            Source::Provider,
+            "eth_chainId",
+            Value::Null,
+            Ok(&json!("0x1")),
+        );
+        recorder.record_request(
+            Source::Fork,
+            "eth_getBalance",
+            json!(["0x0000000000000000000000000000000000000000", "0x10"]),
+            Ok(&json!("0x0")),
+        );
+        for number in ["0x11", "0x12"] {
+            recorder.record_notification(
+                U256::from(7),
+                &RawValue::from_string(json!({ "number": number }).to_string()).unwrap(),
+            );
+        }
+        recorder.finish().unwrap();
+
+        let fixture = ChainFixture::open(&fixture_dir).unwrap();
+        assert_eq!(fixture.blocks().unwrap(), vec![0x11, 0x12]);
+        assert_eq!(fixture.state_reads().unwrap(), 1);
+        let provider = fixture.provider().unwrap();
+        assert_eq!(provider.get_chainid().await.unwrap(), U256::from(1));
+        assert!(fs::read_to_string(fixture.dir().join(SHADOW_FILE)).is_ok());
+    }
+
+    /// The `trade` fixture holds a mainnet block with a single `Trade`
+    /// event from the router.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_listen_to_events_from_fixture() {
+        let fixture = ChainFixture::open(test_fixture!("providers", "trade")).unwrap();
+        assert_eq!(fixture.blocks().unwrap(), vec![17_723_714]);
+
+        let mut events = Events::new(
+            "UniswapV2Router02.sol".to_owned(),
+            "UniswapV2Router02".to_owned(),
+            "Trade(string,address,address,address,uint256,uint256,address)".to_owned(),
+            fixture.provider().unwrap(),
+            LocalArtifactStore::new(test_fixture!("resources", "")),
+            fixture.shadow_resource(),
+            &[],
+        )
+        .await
+        .unwrap();
+        let sink = MemorySink::default();
+        events.add_sink(sink.clone());
+        events.set_max_blocks(1);
+        events.run().await.unwrap();
+
+        let written = sink.0.lock().unwrap();
+        assert_eq!(written.len(), 1);
+        assert_eq!(written[0].block_number, 17_723_714);
+        assert_eq!(written[0].block_timestamp, Some(1_689_726_947));
+        assert_eq!(written[0].log_index, 7);
+        assert_eq!(written[0].data["platformName"], json!("uniswap-v2"));
+    }
+
+    /// Captures a fixture from a local chain, which stands in for
+    /// mainnet, and replays it once the chain is gone.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_replay_captured_fixture_offline() {
+        // A transaction to the shadow contract, which emits an empty log
+        // with the shadow code: PUSH1 0 PUSH1 0 LOG0 STOP
+        let (upstream, handle) = anvil::spawn(NodeConfig::test()).await;
+        let shadow_address = H160::repeat_byte(0x5a);
+        upstream
+            .send_transaction(EthTransactionRequest {
+                from: handle.dev_accounts().next(),
+                to: Some(shadow_address),
+                gas: Some(U256::from(100_000)),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let shadow_json = dir.path().join(SHADOW_FILE);
+        fs::write(
+            &shadow_json,
+            json!({
+                "version": 2,
+                "contracts": [{
+                    "fileName": "Logger.sol",
+                    "contractName": "Logger",
+                    "address": format!("{:?}", shadow_address),
+                    "runtimeBytecode": "60006000a000"
+                }]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let options = ReplayOptions {
+            storage_caching: false,
+            port: Some(0),
+            ..Default::default()
+        };
+
+        // Capture the replay of the block
+        let fixture_dir = dir.path().join("fixture");
+        let recorder = Arc::new(ChainFixture::create(&fixture_dir, &shadow_json).unwrap());
+        let fork_url = fork_proxy::serve(ForkTape::Record {
+            upstream: handle.http_endpoint(),
+            recorder: recorder.clone(),
+        })
+        .await
+        .unwrap();
+        let client = RecordingClient::new(
+            Ws::connect(handle.ws_endpoint()).await.unwrap(),
+            recorder.clone(),
+        );
+        let recorded = Fork::new(
+            Provider::new(client),
+            LocalShadowStore::new(&fixture_dir),
+            fork_url,
+            options.clone(),
+        )
+        .await
+        .unwrap()
+        .replay_range(1, 1)
+        .await
+        .unwrap();
+        recorder.finish().unwrap();
+        handle.node_service.abort();
+        assert_eq!(recorded[0].replayed, 1);
+        assert_eq!(recorded[0].shadow_logs.len(), 1);
+
+        // Replay it without the chain
+        let fixture = ChainFixture::open(&fixture_dir).unwrap();
+        assert!(fixture.state_reads().unwrap() > 0);
+        let replayed = fixture
+            .fork(options)
+            .await
+            .unwrap()
+            .replay_range(1, 1)
+            .await
+            .unwrap();
+        assert_eq!(replayed[0].replayed, 1);
+        assert_eq!(replayed[0].shadow_logs, recorded[0].shadow_logs);
+    }
+}
@@ -0,0 +1,139 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use ethers::providers::JsonRpcError;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server,
+};
+use serde_json::{json, Value};
+
+use super::cassette::{Cassette, CassetteError, CassetteRecorder, Source};
+
+/// What the fork proxy does with anvil's requests.
+#[derive(Clone, Debug)]
+pub enum ForkTape {
+    /// Forward the requests to the upstream HTTP endpoint, recording them
+    Record {
+        upstream: String,
+        recorder: Arc<CassetteRecorder>,
+    },
+    /// Answer the requests from a cassette
+    Playback(Arc<Cassette>),
+}
+
+/// Serves the JSON-RPC endpoint anvil forks from on a local port,
+/// returning its URL.
+///
+/// Anvil reads the forked state over HTTP by itself, so its requests
+/// go through this proxy to be recorded to, or played back from, a
+/// cassette.
+pub async fn serve(tape: ForkTape) -> Result<String, hyper::Error> {
+    let client = reqwest::Client::new();
+    let make_service = make_service_fn(move |_| {
+        let tape = tape.clone();
+        let client = client.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle(request, tape.clone(), client.clone())
+            }))
+        }
+    });
+    let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?.serve(make_service);
+    let url = format!("http://{}", server.local_addr());
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            log::error!("Fork proxy stopped: {}", e);
+        }
+    });
+    Ok(url)
+}
+
+async fn handle(
+    request: Request<Body>,
+    tape: ForkTape,
+    client: reqwest::Client,
+) -> Result<Response<Body>, Infallible> {
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(e) => return Ok(error_response(Value::Null, -32000, e.to_string())),
+    };
+    let request: Value = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return Ok(error_response(Value::Null, -32700, e.to_string())),
+    };
+    let response = match request {
+        Value::Array(batch) => {
+            let mut responses = Vec::new();
+            for request in batch {
+                responses.push(respond(request, &tape, &client).await);
+            }
+            Value::Array(responses)
+        }
+        request => respond(request, &tape, &client).await,
+    };
+    Ok(Response::new(Body::from(response.to_string())))
+}
+
+/// Answers a single JSON-RPC request.
+async fn respond(request: Value, tape: &ForkTape, client: &reqwest::Client) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    match tape {
+        ForkTape::Record { upstream, recorder } => {
+            let response = match forward(client, upstream, &request).await {
+                Ok(response) => response,
+                Err(e) => return rpc_error(id, -32000, e.to_string()),
+            };
+            if let Some(result) = response.get("result") {
+                recorder.record_request(Source::Fork, method, params, Ok(result));
+            } else if let Some(error) = response
+                .get("error")
+                .and_then(|e| serde_json::from_value::<JsonRpcError>(e.clone()).ok())
+            {
+                recorder.record_request(Source::Fork, method, params, Err(&error));
+            }
+            response
+        }
+        ForkTape::Playback(cassette) => match cassette.respond(Source::Fork, method, &params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(CassetteError::JsonRpc(error)) => {
+                json!({ "jsonrpc": "2.0", "id": id, "error": error_value(&error) })
+            }
+            Err(e) => {
+                log::warn!("Fork proxy: {}", e);
+                rpc_error(id, -32000, e.to_string())
+            }
+        },
+    }
+}
+
+/// Forwards a request to the upstream endpoint.
+async fn forward(
+    client: &reqwest::Client,
+    upstream: &str,
+    request: &Value,
+) -> Result<Value, reqwest::Error> {
+    client
+        .post(upstream)
+        .json(request)
+        .send()
+        .await?
+        .json()
+        .await
+}
+
+fn error_value(error: &JsonRpcError) -> Value {
+    json!({ "code": error.code, "message": error.message, "data": error.data })
+}
+
+fn rpc_error(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Response<Body> {
+    Response::new(Body::from(rpc_error(id, code, message).to_string()))
+}
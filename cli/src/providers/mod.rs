@@ -0,0 +1,199 @@
+pub mod cassette;
+pub mod failover;
+pub mod fixture;
+pub mod fork_proxy;
+
+pub use cassette::{Cassette, CassetteRecorder, PlaybackClient, RecordingClient};
+pub use failover::FailoverClient;
+pub use fixture::ChainFixture;
+
+use std::{future::Future, str::FromStr, time::Duration};
+
+use ethers::providers::{Http, Provider, ProviderError, Ws};
+use reqwest::Url;
+use tokio_util::sync::CancellationToken;
+
+/// Reads a default RPC url, e.g. `ETH_RPC_URL`, from the environment.
+///
+/// The url is read at runtime, so release binaries don't embed the RPC
+/// urls of the machine that built them.
+pub fn rpc_url_from_env(name: &str) -> Result<String, ProviderError> {
+    std::env::var(name).map_err(|_| ProviderError::CustomError(format!("Please set an {}", name)))
+}
+
+/// The first delay before retrying a failed resubscription, doubled
+/// after each failed attempt
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The longest delay between retries of a failed resubscription
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Checks that a quorum can be reached with the given number of endpoints.
+fn check_quorum(quorum: usize, endpoints: usize) -> Result<(), ProviderError> {
+    if quorum > endpoints {
+        return Err(ProviderError::CustomError(format!(
+            "A quorum of {} needs at least {} RPC endpoints, but only {} are available",
+            quorum, quorum, endpoints
+        )));
+    }
+    Ok(())
+}
+
+/// Connects to a list of websocket RPC endpoints, failing over between them.
+pub async fn connect_ws(
+    urls: &[String],
+    quorum: usize,
+) -> Result<Provider<FailoverClient<Ws>>, ProviderError> {
+    Ok(Provider::new(failover_ws(urls, quorum).await?))
+}
+
+/// Connects to a list of websocket RPC endpoints, returning the client
+/// failing over between them.
+///
+/// Endpoints that can't be reached are skipped with a warning, so one
+/// endpoint being down doesn't stop shadow from starting. Fails if none
+/// of them can be reached, or fewer than `quorum`.
+pub async fn failover_ws(
+    urls: &[String],
+    quorum: usize,
+) -> Result<FailoverClient<Ws>, ProviderError> {
+    check_quorum(quorum, urls.len())?;
+    let mut clients = Vec::new();
+    let mut last_error = None;
+    for (index, url) in urls.iter().enumerate() {
+        match Ws::connect(url).await {
+            Ok(client) => clients.push(client),
+            Err(e) => {
+                log::warn!(
+                    "Skipping RPC endpoint #{}, which can't be reached: {}",
+                    index,
+                    e
+                );
+                last_error = Some(e);
+            }
+        }
+    }
+    if clients.is_empty() {
+        return Err(match last_error {
+            Some(e) => e.into(),
+            None => ProviderError::CustomError("Please set a WS_RPC_URL".to_owned()),
+        });
+    }
+    check_quorum(quorum, clients.len())?;
+    Ok(FailoverClient::new(clients, quorum))
+}
+
+/// Builds a provider from a list of HTTP RPC endpoints, failing over between them.
+///
+/// Every endpoint shares the given HTTP client, and its connection pool.
+pub fn connect_http(
+    urls: &[String],
+    quorum: usize,
+    client: reqwest::Client,
+) -> Result<Provider<FailoverClient<Http>>, ProviderError> {
+    if urls.is_empty() {
+        return Err(ProviderError::CustomError(
+            "Please set an ETH_RPC_URL".to_owned(),
+        ));
+    }
+    check_quorum(quorum, urls.len())?;
+    let clients = urls
+        .iter()
+        .map(|url| {
+            Url::from_str(url)
+                .map(|url| Http::new_with_client(url, client.clone()))
+                .map_err(|e| ProviderError::CustomError(format!("Invalid RPC url {}: {}", url, e)))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Provider::new(FailoverClient::new(clients, quorum)))
+}
+
+/// The default timeout of an RPC call
+pub const DEFAULT_RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Awaits an RPC call, failing it if it takes longer than `timeout`,
+/// so a hung endpoint can't stall the caller indefinitely.
+pub async fn with_timeout<T, F>(timeout: Duration, call: F) -> Result<T, ProviderError>
+where
+    F: Future<Output = Result<T, ProviderError>>,
+{
+    tokio::time::timeout(timeout, call).await.map_err(|_| {
+        ProviderError::CustomError(format!("RPC call timed out after {:?}", timeout))
+    })?
+}
+
+/// Retries an RPC call, e.g. a resubscription, with exponential backoff
+/// until it succeeds.
+///
+/// Returns `None` if cancelled while waiting to retry.
+pub async fn retry_with_backoff<T, F, Fut>(
+    what: &str,
+    cancel: &CancellationToken,
+    mut call: F,
+) -> Option<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ProviderError>>,
+{
+    let mut delay = INITIAL_RETRY_DELAY;
+    loop {
+        match call().await {
+            Ok(value) => return Some(value),
+            Err(e) => {
+                log::warn!("Error {}, retrying in {:?}: {}", what, delay, e);
+                tokio::select! {
+                    _ = cancel.cancelled() => return None,
+                    _ = tokio::time::sleep(delay) => {}
+                }
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn can_time_out_rpc_calls() {
+        let result = with_timeout(Duration::from_millis(10), async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        })
+        .await;
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+
+        let result = with_timeout(Duration::from_secs(1), async { Ok(1) }).await;
+        assert_eq!(result.unwrap(), 1);
+    }
+
+    #[test]
+    fn rejects_unreachable_quorum() {
+        let client = reqwest::Client::new();
+        let urls = vec!["http://localhost:8545".to_owned()];
+        assert!(connect_http(&urls, 1, client.clone()).is_ok());
+        assert!(connect_http(&urls, 2, client.clone()).is_err());
+        assert!(connect_http(&["not a url".to_owned()], 1, client.clone()).is_err());
+        assert!(connect_http(&[], 1, client).is_err());
+    }
+
+    #[tokio::test]
+    async fn can_retry_until_cancelled() {
+        let cancel = CancellationToken::new();
+        let mut attempts = 0;
+        let result = retry_with_backoff("testing", &cancel, || {
+            attempts += 1;
+            async move { Ok(attempts) }
+        })
+        .await;
+        assert_eq!(result, Some(1));
+
+        cancel.cancel();
+        let result: Option<()> = retry_with_backoff("testing", &cancel, || async {
+            Err(ProviderError::CustomError("down".to_owned()))
+        })
+        .await;
+        assert_eq!(result, None);
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+
+use ethers::{
+    types::{Address, U256},
+    utils::{format_units, to_checksum},
+};
+use serde_json::Value;
+
+use crate::core::resources::sink::{utc_date_hour, DecodedEvent};
+
+/// The widest a value gets in a table row before it's truncated
+const TABLE_VALUE_WIDTH: usize = 24;
+
+/// How decoded events are printed to the console.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// A colored JSON object per event
+    #[default]
+    Json,
+    /// A compact row per event, for high-volume streams
+    Table,
+}
+
+/// Renders decoded events with the message templates set per event
+/// signature in config.
+#[derive(Clone, Debug, Default)]
+pub struct Renderer {
+    /// The templates, keyed by event signature, e.g. `Transfer(address,address,uint256)`
+    templates: BTreeMap<String, String>,
+}
+
+impl Renderer {
+    pub fn new(templates: BTreeMap<String, String>) -> Self {
+        Renderer { templates }
+    }
+
+    /// Returns the template set for an event signature, if any.
+    pub fn template_for(&self, signature: &str) -> Option<&str> {
+        self.templates.get(signature).map(String::as_str)
+    }
+
+    /// Renders an event with the template set for its signature,
+    /// returning `None` if there is no template.
+    pub fn render(&self, event: &DecodedEvent) -> Option<String> {
+        self.template_for(&event.signature)
+            .map(|template| render_template(template, event))
+    }
+}
+
+/// Returns the header of the table format.
+pub fn table_header() -> String {
+    format!(
+        "{:<19}  {:>10}  {:<20}  {:<16}  {}",
+        "TIME", "BLOCK", "CONTRACT", "EVENT", "FIELDS"
+    )
+}
+
+/// Renders an event as a single table row: its block time, block
+/// number, contract, event name and fields.
+///
+/// The fields are rendered with the event's template if there is one,
+/// or as `name=value` pairs with addresses shortened and long values
+/// truncated.
+pub fn render_row(event: &DecodedEvent, renderer: &Renderer) -> String {
+    let time = event
+        .block_timestamp
+        .map(format_timestamp)
+        .unwrap_or_else(|| "-".to_owned());
+    let contract = event
+        .contract
+        .rsplit_once(':')
+        .map_or(event.contract.as_str(), |(_, name)| name);
+    let fields = renderer.render(event).unwrap_or_else(|| match &event.data {
+        Value::Object(data) => data
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, table_value(value)))
+            .collect::<Vec<_>>()
+            .join(" "),
+        value => table_value(value),
+    });
+    format!(
+        "{:<19}  {:>10}  {:<20}  {:<16}  {}",
+        time,
+        event.block_number,
+        truncate(contract, 20),
+        truncate(&event.event, 16),
+        fields
+    )
+}
+
+/// Formats a unix timestamp as a UTC `YYYY-MM-DD HH:MM:SS` string.
+fn format_timestamp(timestamp: u64) -> String {
+    let (year, month, day, hour) = utc_date_hour(timestamp);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        hour,
+        (timestamp % 3_600) / 60,
+        timestamp % 60
+    )
+}
+
+/// Shortens a value to fit a table column.
+fn table_value(value: &Value) -> String {
+    let value = match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    };
+    if value.starts_with("0x") {
+        if let Some(address) = short_address(&value) {
+            return address;
+        }
+    }
+    truncate(&value, TABLE_VALUE_WIDTH)
+}
+
+/// Truncates a string to `width` characters, marking the cut with `…`.
+fn truncate(value: &str, width: usize) -> String {
+    if value.chars().count() <= width {
+        return value.to_owned();
+    }
+    let mut truncated = value.chars().take(width - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+/// Renders a message template for a decoded event.
+///
+/// Placeholders in braces are replaced with the decoded field of the
+/// same name, or with the event's `event`, `contract`, `address`,
+/// `blockNumber` or `transactionHash`. Unknown placeholders are kept
+/// as is, so typos are visible in the rendered message.
+///
+/// A placeholder can be piped through a formatting helper:
+/// - `{amountIn|amount:18}` formats an integer with 18 decimals
+/// - `{sender|short}` shortens an address to `0x1234…abcd`
+/// - `{sender|checksum}` checksums an address
+pub fn render_template(template: &str, event: &DecodedEvent) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rendered.push_str(&rest[start..]);
+            return rendered;
+        };
+        let (name, helper) = match rest[start + 1..start + end].split_once('|') {
+            Some((name, helper)) => (name.trim(), Some(helper.trim())),
+            None => (rest[start + 1..start + end].trim(), None),
+        };
+        let value = placeholder(name, event).and_then(|value| match helper {
+            Some(helper) => format_value(&value, helper),
+            None => Some(value),
+        });
+        match value {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(&rest[start..=start + end]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn placeholder(name: &str, event: &DecodedEvent) -> Option<String> {
+    if let Some(value) = event.data.get(name) {
+        return Some(match value {
+            Value::String(s) => s.clone(),
+            value => value.to_string(),
+        });
+    }
+    match name {
+        "event" => Some(event.event.clone()),
+        "contract" => Some(event.contract.clone()),
+        "address" => Some(event.address.clone()),
+        "blockNumber" => Some(event.block_number.to_string()),
+        "transactionHash" => Some(event.transaction_hash.clone()),
+        _ => None,
+    }
+}
+
+/// Applies a formatting helper to a value, returning `None` if the
+/// helper is unknown or doesn't apply to the value.
+fn format_value(value: &str, helper: &str) -> Option<String> {
+    let (helper, argument) = match helper.split_once(':') {
+        Some((helper, argument)) => (helper, Some(argument)),
+        None => (helper, None),
+    };
+    match helper {
+        "amount" => {
+            let decimals = argument.map_or(Some(18), |d| d.parse::<u32>().ok())?;
+            format_amount(value, decimals)
+        }
+        "short" => short_address(value),
+        "checksum" => value
+            .parse::<Address>()
+            .ok()
+            .map(|address| to_checksum(&address, None)),
+        _ => None,
+    }
+}
+
+/// Formats an integer token amount with the given decimals,
+/// trimming trailing zeros, e.g. `1500000000000000000` → `1.5`.
+pub fn format_amount(value: &str, decimals: u32) -> Option<String> {
+    let amount = U256::from_dec_str(value).ok()?;
+    let formatted = format_units(amount, decimals).ok()?;
+    Some(match formatted.split_once('.') {
+        Some((whole, fraction)) => {
+            let fraction = fraction.trim_end_matches('0');
+            if fraction.is_empty() {
+                whole.to_owned()
+            } else {
+                format!("{}.{}", whole, fraction)
+            }
+        }
+        None => formatted,
+    })
+}
+
+/// Shortens an address to its first and last four hex characters.
+pub fn short_address(value: &str) -> Option<String> {
+    let address = value.parse::<Address>().ok()?;
+    let hex = format!("{:x}", address);
+    Some(format!("0x{}…{}", &hex[..4], &hex[hex.len() - 4..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn event() -> DecodedEvent {
+        DecodedEvent {
+            block_number: 17_000_000,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 0,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            event: "Trade".to_owned(),
+            signature: "Trade(address,uint256)".to_owned(),
+            schema: None,
+            data: json!({
+                "amountIn": "1500000000000000000",
+                "senderAddress": "0x7a250d5630b4cf539739df2c5dacb4c659f2488d"
+            }),
+            raw: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_render_template() {
+        let event = event();
+        assert_eq!(
+            render_template("{amountIn} in {transactionHash}", &event),
+            "1500000000000000000 in 0x01"
+        );
+        assert_eq!(
+            render_template("{event}: {unknown} {", &event),
+            "Trade: {unknown} {"
+        );
+    }
+
+    #[test]
+    fn can_render_with_helpers() {
+        let event = event();
+        assert_eq!(
+            render_template(
+                "{senderAddress|short} swapped {amountIn|amount:18} ({amountIn|amount:6})",
+                &event
+            ),
+            "0x7a25…488d swapped 1.5 (1500000000000)"
+        );
+        assert_eq!(
+            render_template("{senderAddress|checksum}", &event),
+            "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D"
+        );
+        assert_eq!(render_template("{event|amount}", &event), "{event|amount}");
+    }
+
+    #[test]
+    fn can_render_by_signature() {
+        let renderer = Renderer::new(BTreeMap::from([(
+            "Trade(address,uint256)".to_owned(),
+            "{event} by {senderAddress|short}".to_owned(),
+        )]));
+        assert_eq!(
+            renderer.render(&event()),
+            Some("Trade by 0x7a25…488d".to_owned())
+        );
+        assert_eq!(Renderer::default().render(&event()), None);
+    }
+
+    #[test]
+    fn can_render_row() {
+        let mut event = event();
+        event.block_timestamp = Some(1_681_337_465);
+        assert_eq!(
+            render_row(&event, &Renderer::default()),
+            "2023-04-12 22:11:05    17000000  UniswapV2Router02     Trade             \
+             amountIn=1500000000000000000 senderAddress=0x7a25…488d"
+        );
+
+        // Templates replace the fields, and long values are truncated
+        event.block_timestamp = None;
+        event.event = "TradeExecutedWithFees".to_owned();
+        let renderer = Renderer::new(BTreeMap::from([(
+            "Trade(address,uint256)".to_owned(),
+            "{amountIn|amount:18} by {senderAddress|short}".to_owned(),
+        )]));
+        assert_eq!(
+            render_row(&event, &renderer),
+            "-                      17000000  UniswapV2Router02     TradeExecutedWi…  1.5 by 0x7a25…488d"
+        );
+        assert_eq!(
+            truncate("0123456789abcdef0123456789", 24).chars().count(),
+            24
+        );
+    }
+}
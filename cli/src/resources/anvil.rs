@@ -0,0 +1,136 @@
+use std::sync::Mutex;
+
+use anvil::{cmd::NodeArgs, eth::EthApi, NodeHandle};
+use anvil_core::eth::transaction::EthTransactionRequest;
+use async_trait::async_trait;
+use clap::Parser;
+use ethers::types::{Bytes, TransactionReceipt, H160, H256, U256};
+
+use crate::core::resources::fork_runner::{ForkRunner, ForkTransaction};
+
+/// Runs forks on an in-process anvil node, forked from an upstream
+/// HTTP RPC endpoint.
+pub struct AnvilForkRunner {
+    /// The upstream HTTP RPC URL
+    pub http_rpc_url: String,
+}
+
+/// A running anvil fork.
+pub struct AnvilFork {
+    api: EthApi,
+    handle: Mutex<NodeHandle>,
+}
+
+impl AnvilForkRunner {
+    pub fn new(http_rpc_url: String) -> Self {
+        Self { http_rpc_url }
+    }
+}
+
+#[async_trait]
+impl ForkRunner for AnvilForkRunner {
+    type Fork = AnvilFork;
+
+    async fn spawn(
+        &self,
+        block_number: Option<u64>,
+    ) -> Result<AnvilFork, Box<dyn std::error::Error>> {
+        let block_number = block_number
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| "latest".to_owned());
+        let anvil_args = anvil_args(&self.http_rpc_url, &block_number);
+        let (api, handle) = anvil::spawn(anvil_args.into_node_config()).await;
+        Ok(AnvilFork {
+            api,
+            handle: Mutex::new(handle),
+        })
+    }
+
+    async fn set_balance(
+        &self,
+        fork: &AnvilFork,
+        address: H160,
+        balance: U256,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(fork.api.anvil_set_balance(address, balance).await?)
+    }
+
+    async fn set_code(
+        &self,
+        fork: &AnvilFork,
+        address: H160,
+        code: Bytes,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(fork.api.anvil_set_code(address, code).await?)
+    }
+
+    async fn impersonate(
+        &self,
+        fork: &AnvilFork,
+        address: H160,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(fork.api.anvil_impersonate_account(address).await?)
+    }
+
+    async fn send_transaction(
+        &self,
+        fork: &AnvilFork,
+        transaction: ForkTransaction,
+    ) -> Result<H256, Box<dyn std::error::Error>> {
+        let request = EthTransactionRequest {
+            from: Some(transaction.from),
+            to: transaction.to,
+            value: Some(transaction.value),
+            gas: Some(transaction.gas),
+            data: Some(transaction.data),
+            ..Default::default()
+        };
+        Ok(fork.api.send_transaction(request).await?)
+    }
+
+    async fn mine(&self, fork: &AnvilFork) -> Result<(), Box<dyn std::error::Error>> {
+        fork.api.evm_mine(None).await?;
+        Ok(())
+    }
+
+    async fn transaction_receipt(
+        &self,
+        fork: &AnvilFork,
+        tx_hash: H256,
+    ) -> Result<Option<TransactionReceipt>, Box<dyn std::error::Error>> {
+        Ok(fork.api.transaction_receipt(tx_hash).await?)
+    }
+
+    async fn get_code(
+        &self,
+        fork: &AnvilFork,
+        address: H160,
+    ) -> Result<Bytes, Box<dyn std::error::Error>> {
+        Ok(fork.api.get_code(address, None).await?)
+    }
+
+    async fn stop(&self, fork: AnvilFork) {
+        fork.handle.into_inner().unwrap().node_service.abort();
+    }
+}
+
+fn anvil_args(http_rpc_url: &str, block_number: &str) -> NodeArgs {
+    NodeArgs::parse_from([
+        "anvil",
+        "--fork-url",
+        http_rpc_url,
+        "--fork-block-number",
+        block_number,
+        "--code-size-limit",
+        usize::MAX.to_string().as_str(),
+        "--base-fee",
+        "0",
+        "--gas-price",
+        "0",
+        "--no-mining",
+        "--silent",
+        "--disable-gas-limit",
+        "--hardfork",
+        "latest",
+    ])
+}
@@ -1,19 +1,166 @@
-use std::fs;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
-use crate::core::resources::artifacts::ArtifactsResource;
+use serde::de::DeserializeOwned;
+
+use crate::core::resources::artifacts::{ArtifactsResource, ContractBuildInfo};
+
+/// The directory in the artifacts directory holding the build-info files
+const BUILD_INFO_DIR: &str = "build-info";
 
 /// An Artifacts resource implementation that uses the local file
 /// system as the Artifacts store.
 ///
-/// The artifacts are stored in the `out/` directory.
+/// The artifacts are stored in the `out/` directory. Source paths in
+/// the compiler metadata are resolved relative to the project root,
+/// which defaults to the current directory.
 pub struct LocalArtifactStore {
-    path: String,
+    path: PathBuf,
+    root: PathBuf,
 }
 
 impl LocalArtifactStore {
-    pub fn new(path: String) -> Self {
-        LocalArtifactStore { path }
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        LocalArtifactStore {
+            path: path.into(),
+            root: PathBuf::from("."),
+        }
+    }
+
+    /// Creates an artifact store whose sources are resolved relative to `root`.
+    pub fn with_root(path: impl Into<PathBuf>, root: impl Into<PathBuf>) -> Self {
+        LocalArtifactStore {
+            path: path.into(),
+            root: root.into(),
+        }
+    }
+
+    /// Returns the path of an artifact, or an error suggesting the
+    /// closest artifacts if it doesn't exist.
+    fn artifact_path(
+        &self,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let file_path = self
+            .path
+            .join(file_name)
+            .join(format!("{}.json", contract_name));
+        if file_path.is_file() {
+            return Ok(file_path);
+        }
+
+        let not_found = format!("Artifact not found for {}:{}", file_name, contract_name);
+        if !self.path.is_dir() {
+            return Err(format!(
+                "{}. The artifacts directory {} does not exist, did you run `forge build`?",
+                not_found,
+                self.path.display()
+            )
+            .into());
+        }
+
+        // Artifacts are keyed by file name, not by source path
+        if let Some(base_name) = Path::new(file_name).file_name() {
+            let base_name = base_name.to_string_lossy();
+            if base_name != file_name
+                && self
+                    .path
+                    .join(base_name.as_ref())
+                    .join(format!("{}.json", contract_name))
+                    .is_file()
+            {
+                return Err(format!(
+                    "{}. Pass the file name without its directory, e.g. {}:{}",
+                    not_found, base_name, contract_name
+                )
+                .into());
+            }
+        }
+
+        let candidates = self.closest_artifacts(&format!("{}:{}", file_name, contract_name))?;
+        if candidates.is_empty() {
+            return Err(format!("{}. Did you run `forge build`?", not_found).into());
+        }
+        Err(format!("{}. Did you mean {}?", not_found, candidates.join(" or ")).into())
+    }
+
+    /// Lists up to three artifacts whose `File.sol:Contract` name is
+    /// close to `name`, closest first.
+    fn closest_artifacts(&self, name: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let name = name.to_lowercase();
+        let max_distance = (name.len() / 3).max(3);
+        let mut candidates = Vec::new();
+        for dir in fs::read_dir(&self.path)? {
+            let dir = dir?;
+            if !dir.file_type()?.is_dir() {
+                continue;
+            }
+            let file_name = dir.file_name().to_string_lossy().into_owned();
+            if file_name == BUILD_INFO_DIR {
+                continue;
+            }
+            for artifact in fs::read_dir(dir.path())? {
+                let artifact = artifact?.path();
+                if artifact.extension().map_or(true, |ext| ext != "json") {
+                    continue;
+                }
+                let Some(contract_name) = artifact.file_stem() else {
+                    continue;
+                };
+                let candidate = format!("{}:{}", file_name, contract_name.to_string_lossy());
+                let distance = edit_distance(&name, &candidate.to_lowercase());
+                if distance <= max_distance {
+                    candidates.push((distance, candidate));
+                }
+            }
+        }
+        candidates.sort();
+        Ok(candidates
+            .into_iter()
+            .take(3)
+            .map(|(_, candidate)| candidate)
+            .collect())
+    }
+}
+
+/// Resolves a source path from the compiler metadata, which always
+/// uses `/` separators, against the project root.
+fn source_path(root: &Path, path: &str) -> PathBuf {
+    if Path::new(path).is_absolute() {
+        return PathBuf::from(path);
+    }
+    path.split('/')
+        .filter(|component| !component.is_empty())
+        .fold(root.to_path_buf(), |path, component| path.join(component))
+}
+
+/// Deserializes an optional field of the compiler output, defaulting if missing.
+fn from_optional<T: DeserializeOwned + Default>(
+    value: &serde_json::Value,
+) -> Result<T, serde_json::Error> {
+    match value {
+        serde_json::Value::Null => Ok(T::default()),
+        value => serde_json::from_value(value.clone()),
+    }
+}
+
+/// Returns the Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
     }
+    previous[b.len()]
 }
 
 impl ArtifactsResource for LocalArtifactStore {
@@ -22,10 +169,132 @@ impl ArtifactsResource for LocalArtifactStore {
         file_name: &str,
         contract_name: &str,
     ) -> Result<alloy_json_abi::ContractObject, Box<dyn std::error::Error>> {
-        let file_path = format!("{}/{}/{}.json", self.path, file_name, contract_name);
+        let file_path = self.artifact_path(file_name, contract_name)?;
         let contents = fs::read_to_string(file_path)?;
         serde_json::from_str(&contents).map_err(|e| e.into())
     }
+
+    /// Reads the source hashes from the compiler metadata in the artifact.
+    fn get_source_hashes(
+        &self,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+        let metadata = self.get_metadata(file_name, contract_name)?;
+        let sources = metadata["sources"]
+            .as_object()
+            .ok_or("Artifact does not contain source metadata")?;
+        let hashes = sources
+            .iter()
+            .filter_map(|(path, source)| {
+                source["keccak256"]
+                    .as_str()
+                    .map(|hash| (path.to_owned(), hash.to_owned()))
+            })
+            .collect();
+        Ok(hashes)
+    }
+
+    fn get_metadata(
+        &self,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let file_path = self.artifact_path(file_name, contract_name)?;
+        let contents = fs::read_to_string(file_path)?;
+        let mut artifact: serde_json::Value = serde_json::from_str(&contents)?;
+        match artifact["metadata"].take() {
+            serde_json::Value::Null => Err("Artifact does not contain compiler metadata".into()),
+            metadata => Ok(metadata),
+        }
+    }
+
+    /// Reads the sources listed in the compiler metadata from the project root.
+    fn get_sources(
+        &self,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+        let mut sources = BTreeMap::new();
+        for path in self
+            .get_source_hashes(file_name, contract_name)?
+            .into_keys()
+        {
+            let contents = fs::read_to_string(source_path(&self.root, &path))
+                .map_err(|e| format!("Error reading source {}: {}", path, e))?;
+            sources.insert(path, contents);
+        }
+        Ok(sources)
+    }
+
+    /// Finds the contract in the build-info files written by
+    /// `forge build --build-info`.
+    fn get_build_info(
+        &self,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<Option<ContractBuildInfo>, Box<dyn std::error::Error>> {
+        let build_info_dir = self.path.join(BUILD_INFO_DIR);
+        if !build_info_dir.is_dir() {
+            return Ok(None);
+        }
+
+        // The build info is keyed by source path, which the flat
+        // artifact records as the compilation target
+        let metadata = self.get_metadata(file_name, contract_name)?;
+        let source_path = metadata["settings"]["compilationTarget"]
+            .as_object()
+            .and_then(|target| target.keys().next())
+            .ok_or("Artifact metadata does not contain a compilation target")?
+            .to_owned();
+
+        for entry in fs::read_dir(build_info_dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |ext| ext != "json") {
+                continue;
+            }
+            let contents = fs::read_to_string(&path)?;
+            let build_info: serde_json::Value = serde_json::from_str(&contents)
+                .map_err(|e| format!("Invalid build info {}: {}", path.display(), e))?;
+            let output = &build_info["output"]["contracts"][&source_path][contract_name];
+            if output.is_null() {
+                continue;
+            }
+            return Ok(Some(ContractBuildInfo {
+                source_path,
+                link_references: from_optional(&output["evm"]["bytecode"]["linkReferences"])?,
+                deployed_link_references: from_optional(
+                    &output["evm"]["deployedBytecode"]["linkReferences"],
+                )?,
+                immutable_references: from_optional(
+                    &output["evm"]["deployedBytecode"]["immutableReferences"],
+                )?,
+                storage_layout: match &output["storageLayout"] {
+                    serde_json::Value::Null => None,
+                    layout => Some(layout.clone()),
+                },
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Reads the storage layout from the artifact if it was built with
+    /// `--extra-output storageLayout`, or from the build info otherwise.
+    fn get_storage_layout(
+        &self,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<Option<serde_json::Value>, Box<dyn std::error::Error>> {
+        let file_path = self.artifact_path(file_name, contract_name)?;
+        let contents = fs::read_to_string(file_path)?;
+        let mut artifact: serde_json::Value = serde_json::from_str(&contents)?;
+        match artifact["storageLayout"].take() {
+            serde_json::Value::Null => Ok(self
+                .get_build_info(file_name, contract_name)?
+                .and_then(|build_info| build_info.storage_layout)),
+            layout => Ok(Some(layout)),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -43,4 +312,97 @@ mod tests {
             .unwrap();
         assert_eq!(hex::encode(&artifact.bytecode.unwrap()), expected);
     }
+
+    #[test]
+    fn test_get_source_hashes() {
+        let file_path = test_fixture!("resources", "");
+        let artifacts = LocalArtifactStore::new(file_path);
+        let hashes = artifacts
+            .get_source_hashes("UniswapV2Router02.sol", "UniswapV2Router02")
+            .unwrap();
+        assert_eq!(
+            hashes
+                .get("contracts/src/UniswapV2Router02/contracts/UniswapV2Router02.sol")
+                .unwrap(),
+            "0x1b7c4fabd68b3e978f91a58606716d8dd760ddb8ab20d41ad33d6c7b322fcfa3"
+        );
+    }
+
+    #[test]
+    fn test_get_sources() {
+        let file_path = test_fixture!("resources", "");
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let artifacts = LocalArtifactStore::with_root(file_path, root);
+        let sources = artifacts
+            .get_sources("UniswapV2Router02.sol", "UniswapV2Router02")
+            .unwrap();
+        assert!(sources
+            .get("contracts/src/UniswapV2Router02/contracts/UniswapV2Router02.sol")
+            .unwrap()
+            .contains("contract UniswapV2Router02"));
+    }
+
+    #[test]
+    fn can_resolve_source_paths() {
+        let root = Path::new("project");
+        assert_eq!(
+            source_path(root, "contracts/src/Token.sol"),
+            root.join("contracts").join("src").join("Token.sol")
+        );
+    }
+
+    #[test]
+    fn can_compute_edit_distance() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
+    }
+
+    #[test]
+    fn suggests_close_artifacts() {
+        let artifacts = LocalArtifactStore::new(test_fixture!("resources", ""));
+        let err = artifacts
+            .get_artifact("UniswapV2Router.sol", "UniswapV2Router")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("Did you mean UniswapV2Router02.sol:UniswapV2Router02?"));
+    }
+
+    #[test]
+    fn detects_source_paths() {
+        let artifacts = LocalArtifactStore::new(test_fixture!("resources", ""));
+        let err = artifacts
+            .get_artifact("src/UniswapV2Router02.sol", "UniswapV2Router02")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("e.g. UniswapV2Router02.sol:UniswapV2Router02"));
+    }
+
+    #[test]
+    fn test_get_build_info() {
+        let artifacts = LocalArtifactStore::new(test_fixture!("resources", ""));
+        let build_info = artifacts
+            .get_build_info("UniswapV2Router02.sol", "UniswapV2Router02")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            build_info.source_path,
+            "contracts/src/UniswapV2Router02/contracts/UniswapV2Router02.sol"
+        );
+        assert!(build_info.libraries().is_empty());
+        assert_eq!(build_info.immutable_references["2467"].len(), 2);
+        assert!(build_info.storage_layout.is_some());
+    }
+
+    #[test]
+    fn test_get_storage_layout() {
+        let artifacts = LocalArtifactStore::new(test_fixture!("resources", ""));
+        let layout = artifacts
+            .get_storage_layout("UniswapV2Router02.sol", "UniswapV2Router02")
+            .unwrap()
+            .unwrap();
+        assert!(layout["storage"].is_array());
+    }
 }
@@ -0,0 +1,119 @@
+#[cfg(feature = "archive")]
+mod object_store;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+
+use crate::core::resources::backup::BackupStore;
+
+#[cfg(feature = "archive")]
+pub use self::object_store::ObjectStoreBackup;
+
+/// Stores backups in a local directory, e.g. a mounted network drive.
+///
+/// Keys map to paths relative to the directory.
+pub struct LocalBackupStore {
+    dir: PathBuf,
+}
+
+impl LocalBackupStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl BackupStore for LocalBackupStore {
+    async fn put(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let path = self.dir.join(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Written through a temporary file, so a backup is never half written
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(fs::read(self.dir.join(key))?)
+    }
+
+    async fn list(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut keys = Vec::new();
+        if self.dir.exists() {
+            list_files(&self.dir, "", &mut keys)?;
+        }
+        Ok(keys)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let path = self.dir.join(key);
+        fs::remove_file(&path)?;
+        // Remove the backup directory once it's empty
+        if let Some(parent) = path.parent() {
+            if parent != self.dir && fs::read_dir(parent)?.next().is_none() {
+                fs::remove_dir(parent)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Adds the keys of the files under a directory, recursively.
+fn list_files(dir: &Path, prefix: &str, keys: &mut Vec<String>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let key = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            list_files(&entry.path(), &format!("{}/", key), keys)?;
+        } else {
+            keys.push(key);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn can_store_backups_in_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalBackupStore::new(dir.path().join("backups"));
+        assert!(store.list().await.unwrap().is_empty());
+
+        store
+            .put("backup-1/shadow.json.gz", b"shadow".to_vec())
+            .await
+            .unwrap();
+        store
+            .put("backup-1/manifest.json", b"{}".to_vec())
+            .await
+            .unwrap();
+        let mut keys = store.list().await.unwrap();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["backup-1/manifest.json", "backup-1/shadow.json.gz"]
+        );
+        assert_eq!(
+            store.get("backup-1/shadow.json.gz").await.unwrap(),
+            b"shadow"
+        );
+
+        store.delete("backup-1/manifest.json").await.unwrap();
+        store.delete("backup-1/shadow.json.gz").await.unwrap();
+        assert!(!dir.path().join("backups").join("backup-1").exists());
+    }
+}
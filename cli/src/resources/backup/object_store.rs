@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use object_store::{path::Path, ObjectStore};
+
+use crate::{core::resources::backup::BackupStore, resources::sinks::object_store::open_bucket};
+
+/// Stores backups in an S3 or GCS bucket, under a prefix.
+///
+/// Credentials are read from the environment, as with the AWS and GCP CLIs.
+pub struct ObjectStoreBackup {
+    store: Box<dyn ObjectStore>,
+    prefix: String,
+}
+
+impl ObjectStoreBackup {
+    /// Connects to the bucket in the URL, e.g. `s3://bucket/prefix` or
+    /// `gs://bucket/prefix`.
+    pub fn new(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (store, prefix) = open_bucket(url)?;
+        Ok(Self { store, prefix })
+    }
+
+    fn path(&self, key: &str) -> Path {
+        if self.prefix.is_empty() {
+            Path::from(key)
+        } else {
+            Path::from(format!("{}/{}", self.prefix, key))
+        }
+    }
+}
+
+#[async_trait]
+impl BackupStore for ObjectStoreBackup {
+    async fn put(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.store.put(&self.path(key), data.into()).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let bytes = self.store.get(&self.path(key)).await?.bytes().await?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn list(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let prefix = (!self.prefix.is_empty()).then(|| Path::from(self.prefix.as_str()));
+        let objects = self
+            .store
+            .list(prefix.as_ref())
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(objects
+            .into_iter()
+            .map(|object| {
+                let key = object.location.to_string();
+                match key.strip_prefix(&format!("{}/", self.prefix)) {
+                    Some(key) if !self.prefix.is_empty() => key.to_owned(),
+                    _ => key,
+                }
+            })
+            .collect())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.store.delete(&self.path(key)).await?;
+        Ok(())
+    }
+}
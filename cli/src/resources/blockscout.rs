@@ -0,0 +1,203 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::core::resources::etherscan::{
+    ContractCreationResult, EtherscanResource, GetContractCreationResponse, GetSourceCodeResponse,
+    SourceCodeResult,
+};
+
+/// An implementation of the contract metadata resource backed by
+/// Blockscout's REST API.
+///
+/// Many L2s and appchains only have a Blockscout explorer. The responses
+/// are mapped into the Etherscan response types, so the deploy action
+/// doesn't need to know which explorer it is talking to.
+///
+/// https://docs.blockscout.com/for-users/api/rest-api-endpoints
+pub struct Blockscout {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+/// The subset of Blockscout's `/api/v2/addresses/{address}` response we use
+#[derive(Deserialize)]
+struct BlockscoutAddress {
+    hash: String,
+    creator_address_hash: Option<String>,
+    creation_tx_hash: Option<String>,
+}
+
+/// The subset of Blockscout's `/api/v2/smart-contracts/{address}` response we use
+#[derive(Deserialize)]
+struct BlockscoutSmartContract {
+    constructor_args: Option<String>,
+    source_code: Option<String>,
+    name: Option<String>,
+    compiler_version: Option<String>,
+}
+
+impl Blockscout {
+    /// Creates a Blockscout resource for the explorer at `base_url`
+    /// (e.g. `https://eth.blockscout.com`).
+    pub fn new(base_url: String, client: reqwest::Client) -> Self {
+        Blockscout {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            client,
+        }
+    }
+
+    /// Fetches `url`, returning `None` when Blockscout doesn't know the
+    /// address.
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<Option<T>, reqwest::Error> {
+        let response = self.client.get(url).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.json::<T>().await?))
+    }
+}
+
+#[async_trait]
+impl EtherscanResource for Blockscout {
+    async fn get_contract_creation(
+        &self,
+        address: &str,
+    ) -> Result<GetContractCreationResponse, reqwest::Error> {
+        let url = format!("{}/api/v2/addresses/{}", self.base_url, address);
+        let response = self.get::<BlockscoutAddress>(&url).await?;
+        Ok(into_contract_creation_response(address, response))
+    }
+
+    async fn get_source_code(
+        &self,
+        address: &str,
+    ) -> Result<GetSourceCodeResponse, reqwest::Error> {
+        let url = format!("{}/api/v2/smart-contracts/{}", self.base_url, address);
+        let response = self.get::<BlockscoutSmartContract>(&url).await?;
+        Ok(into_source_code_response(address, response))
+    }
+}
+
+/// Maps a Blockscout address into an Etherscan response. `None` means
+/// Blockscout answered 404, which is reported as a failed lookup.
+fn into_contract_creation_response(
+    address: &str,
+    response: Option<BlockscoutAddress>,
+) -> GetContractCreationResponse {
+    let Some(response) = response else {
+        return GetContractCreationResponse {
+            status: "0".to_owned(),
+            message: format!("Address {} not found", address),
+            result: vec![],
+        };
+    };
+    match (response.creator_address_hash, response.creation_tx_hash) {
+        (Some(contract_creator), Some(tx_hash)) => GetContractCreationResponse {
+            status: "1".to_owned(),
+            message: "OK".to_owned(),
+            result: vec![ContractCreationResult {
+                contract_address: response.hash.to_lowercase(),
+                contract_creator: contract_creator.to_lowercase(),
+                tx_hash,
+            }],
+        },
+        _ => GetContractCreationResponse {
+            status: "0".to_owned(),
+            message: format!("No creation transaction found for {}", response.hash),
+            result: vec![],
+        },
+    }
+}
+
+/// Maps a Blockscout smart contract into an Etherscan response. Unknown
+/// and unverified contracts are reported as a failed lookup.
+fn into_source_code_response(
+    address: &str,
+    response: Option<BlockscoutSmartContract>,
+) -> GetSourceCodeResponse {
+    match response {
+        Some(BlockscoutSmartContract {
+            constructor_args,
+            source_code: Some(source_code),
+            name,
+            compiler_version,
+        }) => GetSourceCodeResponse {
+            status: "1".to_owned(),
+            message: "OK".to_owned(),
+            result: vec![SourceCodeResult {
+                constructor_arguments: constructor_args
+                    .unwrap_or_default()
+                    .trim_start_matches("0x")
+                    .to_owned(),
+                source_code,
+                contract_name: name.unwrap_or_default(),
+                compiler_version: compiler_version.unwrap_or_default(),
+            }],
+        },
+        _ => GetSourceCodeResponse {
+            status: "0".to_owned(),
+            message: format!("Contract source code not verified for {}", address),
+            result: vec![],
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUTER: &str = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
+
+    #[test]
+    fn can_map_blockscout_responses() {
+        let address: BlockscoutAddress = serde_json::from_str(
+            r#"{
+                "hash": "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D",
+                "creator_address_hash": "0x9C33eaCc2F50E39940D3AfaF2c7B8246B681A374",
+                "creation_tx_hash": "0x4fc1580e7f66c58b7c26881cce0aab9c3509afe6e507527f30566fbf8039bcd0"
+            }"#,
+        )
+        .unwrap();
+        let response = into_contract_creation_response(ROUTER, Some(address));
+        assert_eq!(response.status, "1");
+        assert_eq!(
+            response.result[0].contract_creator,
+            "0x9c33eacc2f50e39940d3afaf2c7b8246b681a374"
+        );
+
+        let contract: BlockscoutSmartContract = serde_json::from_str(
+            r#"{
+                "constructor_args": "0x0000000000000000000000005c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f",
+                "source_code": "pragma solidity =0.6.6;",
+                "name": "UniswapV2Router02",
+                "compiler_version": "v0.6.6+commit.6c089d02"
+            }"#,
+        )
+        .unwrap();
+        let response = into_source_code_response(ROUTER, Some(contract));
+        assert_eq!(response.status, "1");
+        assert_eq!(
+            response.result[0].constructor_arguments,
+            "0000000000000000000000005c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f"
+        );
+        assert_eq!(response.result[0].contract_name, "UniswapV2Router02");
+    }
+
+    #[test]
+    fn reports_failed_lookups() {
+        let response = into_contract_creation_response(ROUTER, None);
+        assert_eq!(response.status, "0");
+        assert!(response.result.is_empty());
+
+        let response = into_source_code_response(ROUTER, None);
+        assert_eq!(response.status, "0");
+        assert!(response.result.is_empty());
+
+        let unverified: BlockscoutSmartContract =
+            serde_json::from_str(r#"{ "source_code": null }"#).unwrap();
+        let response = into_source_code_response(ROUTER, Some(unverified));
+        assert_eq!(response.status, "0");
+        assert!(response.result.is_empty());
+    }
+}
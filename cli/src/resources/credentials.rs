@@ -0,0 +1,310 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{IsTerminal, Write},
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Nonce,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::core::resources::credentials::CredentialsResource;
+
+/// The environment variable holding the passphrase for the encrypted credentials file
+pub const PASSPHRASE_ENV: &str = "SHADOW_CREDENTIALS_PASSPHRASE";
+
+/// The name of the Etherscan API key credential
+pub const ETHERSCAN: &str = "etherscan";
+
+/// The name of the credential holding the private key of the signer
+/// of post-deploy transactions
+pub const SIGNER: &str = "signer";
+
+/// The prefix of config values read from an environment variable, e.g.
+/// `env:DISCORD_WEBHOOK_URL`
+pub const ENV_PREFIX: &str = "env:";
+
+/// The prefix of config values read from the credentials store, e.g.
+/// `credential:discord`
+pub const CREDENTIAL_PREFIX: &str = "credential:";
+
+/// The on-disk format of the encrypted credentials file
+#[derive(Serialize, Deserialize)]
+struct EncryptedCredentials {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// A Credentials resource implementation that stores the credentials
+/// in a file encrypted with a passphrase.
+///
+/// The encryption key is derived from the passphrase with scrypt, and
+/// the credentials are encrypted with ChaCha20-Poly1305.
+///
+/// The credentials are stored in `~/.shadow/credentials` by default.
+pub struct EncryptedFileCredentialStore {
+    path: PathBuf,
+    passphrase: String,
+}
+
+impl EncryptedFileCredentialStore {
+    pub fn new(path: PathBuf, passphrase: String) -> Self {
+        EncryptedFileCredentialStore { path, passphrase }
+    }
+
+    /// Returns the default location of the credentials file
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".shadow").join("credentials"))
+    }
+
+    fn derive_key(&self, salt: &[u8]) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+        let mut key = [0u8; 32];
+        scrypt::scrypt(
+            self.passphrase.as_bytes(),
+            salt,
+            &scrypt::Params::recommended(),
+            &mut key,
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(key)
+    }
+
+    fn read_from_file(&self) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let contents = fs::read_to_string(&self.path)?;
+        let encrypted: EncryptedCredentials = serde_json::from_str(&contents)?;
+        let key = self.derive_key(&hex::decode(encrypted.salt)?)?;
+        let cipher = ChaCha20Poly1305::new_from_slice(&key).map_err(|e| e.to_string())?;
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(&hex::decode(encrypted.nonce)?),
+                hex::decode(encrypted.ciphertext)?.as_ref(),
+            )
+            .map_err(|_| "Could not decrypt credentials, is the passphrase correct?")?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn write_to_file(
+        &self,
+        credentials: &BTreeMap<String, String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let key = self.derive_key(&salt)?;
+        let cipher = ChaCha20Poly1305::new_from_slice(&key).map_err(|e| e.to_string())?;
+        let plaintext = serde_json::to_vec(credentials)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_ref())
+            .map_err(|_| "Could not encrypt credentials")?;
+
+        let encrypted = EncryptedCredentials {
+            version: 1,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Only the owner may read the file, even though it is encrypted
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            if self.path.exists() {
+                fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))?;
+            }
+        }
+        options
+            .open(&self.path)?
+            .write_all(serde_json::to_string(&encrypted)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl CredentialsResource for EncryptedFileCredentialStore {
+    fn get(&self, name: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let credentials = self.read_from_file()?;
+        Ok(credentials.get(name).cloned())
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut credentials = self.read_from_file()?;
+        credentials.insert(name.to_owned(), value.to_owned());
+        self.write_to_file(&credentials)
+    }
+}
+
+/// A Credentials resource implementation that uses the OS keychain
+/// (macOS Keychain, Windows Credential Manager, or the Secret Service on Linux).
+#[cfg(feature = "keychain")]
+pub struct KeychainCredentialStore {
+    service: String,
+}
+
+#[cfg(feature = "keychain")]
+impl KeychainCredentialStore {
+    pub fn new() -> Self {
+        KeychainCredentialStore {
+            service: "shadow".to_owned(),
+        }
+    }
+}
+
+#[cfg(feature = "keychain")]
+impl CredentialsResource for KeychainCredentialStore {
+    fn get(&self, name: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        match keyring::Entry::new(&self.service, name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+        keyring::Entry::new(&self.service, name)?.set_password(value)?;
+        Ok(())
+    }
+}
+
+/// Resolves a credential at runtime.
+///
+/// The credential is looked up, in order, from the explicitly provided
+/// value (e.g. a flag or environment variable), the OS keychain (when
+/// built with the `keychain` feature), and the encrypted credentials
+/// file, if it exists. Its passphrase is read from [`PASSPHRASE_ENV`],
+/// or prompted for once per run when attached to a terminal.
+pub fn resolve(
+    name: &str,
+    explicit: Option<String>,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    if explicit.is_some() {
+        return Ok(explicit);
+    }
+
+    #[cfg(feature = "keychain")]
+    if let Some(value) = KeychainCredentialStore::new().get(name)? {
+        return Ok(Some(value));
+    }
+
+    match EncryptedFileCredentialStore::default_path() {
+        Some(path) if path.exists() => {
+            let passphrase = file_passphrase(&path)?;
+            EncryptedFileCredentialStore::new(path, passphrase).get(name)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Resolves a secret set in `shadow.config.json`, such as a notifier's
+/// webhook URL, so it doesn't have to be kept in the file in plain text.
+///
+/// Values starting with [`ENV_PREFIX`] are read from the environment
+/// variable they name, and values starting with [`CREDENTIAL_PREFIX`]
+/// from the credential they name, as stored with
+/// `shadow auth set secret --key <name>`. Other values are used as is.
+pub fn resolve_secret(value: &str) -> Result<String, Box<dyn std::error::Error>> {
+    if let Some(var) = value.strip_prefix(ENV_PREFIX) {
+        return std::env::var(var)
+            .map_err(|_| format!("The environment variable {} is not set", var).into());
+    }
+    if let Some(name) = value.strip_prefix(CREDENTIAL_PREFIX) {
+        return resolve(name, None)?.ok_or_else(|| {
+            format!(
+                "The credential {} is not set, store it with `shadow auth set secret --key {}`",
+                name, name
+            )
+            .into()
+        });
+    }
+    Ok(value.to_owned())
+}
+
+/// Returns the passphrase of the credentials file, from [`PASSPHRASE_ENV`]
+/// or prompted for, so several credentials only prompt once.
+fn file_passphrase(path: &std::path::Path) -> Result<String, Box<dyn std::error::Error>> {
+    static PROMPTED: OnceLock<String> = OnceLock::new();
+
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    if let Some(passphrase) = PROMPTED.get() {
+        return Ok(passphrase.clone());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(format!(
+            "The credentials file {} is encrypted, please set {} to read it",
+            path.display(),
+            PASSPHRASE_ENV
+        )
+        .into());
+    }
+    let passphrase = rpassword::prompt_password("Enter the credentials passphrase: ")?;
+    Ok(PROMPTED.get_or_init(|| passphrase).clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn can_round_trip_encrypted_credentials() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("credentials");
+
+        let store = EncryptedFileCredentialStore::new(path.clone(), "hunter2".to_owned());
+        assert_eq!(store.get(ETHERSCAN).unwrap(), None);
+        store.set(ETHERSCAN, "ABCDEFG").unwrap();
+        assert_eq!(store.get(ETHERSCAN).unwrap(), Some("ABCDEFG".to_owned()));
+
+        // The key is not stored in plain text
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("ABCDEFG"));
+
+        // Only the owner can read the file
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // A wrong passphrase cannot decrypt the file
+        let store = EncryptedFileCredentialStore::new(path, "wrong".to_owned());
+        assert!(store.get(ETHERSCAN).is_err());
+    }
+
+    #[test]
+    fn can_resolve_secrets_from_the_environment() {
+        std::env::set_var(
+            "SHADOW_TEST_WEBHOOK_URL",
+            "https://hooks.slack.com/services/T0",
+        );
+        assert_eq!(
+            resolve_secret("env:SHADOW_TEST_WEBHOOK_URL").unwrap(),
+            "https://hooks.slack.com/services/T0"
+        );
+        assert!(resolve_secret("env:SHADOW_TEST_UNSET_WEBHOOK_URL").is_err());
+        assert_eq!(
+            resolve_secret("https://discord.com/api/webhooks/1").unwrap(),
+            "https://discord.com/api/webhooks/1"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use fs2::FileExt;
+
+use crate::core::resources::dead_letter::{DeadLetter, DeadLetterResource};
+
+/// The default location of the dead-letter file, relative to the current directory
+pub const DEFAULT_DEAD_LETTERS_PATH: &str = "dead-letters.jsonl";
+
+/// A dead-letter queue stored in a file, one JSON object per line.
+///
+/// Writes take an exclusive lock on a `.lock` file next to it, so a
+/// redrive can't lose the letters a running listener appends.
+pub struct DeadLetterFile {
+    path: PathBuf,
+}
+
+impl DeadLetterFile {
+    pub fn new(path: PathBuf) -> Self {
+        DeadLetterFile { path }
+    }
+
+    /// Locks the queue until the returned file is dropped.
+    fn lock(&self) -> std::io::Result<File> {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(".lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(PathBuf::from(path))?;
+        file.lock_exclusive()?;
+        Ok(file)
+    }
+
+    fn read(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let contents = fs::read_to_string(&self.path)?;
+        let mut letters = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let letter = serde_json::from_str(line).map_err(|e| {
+                format!(
+                    "Invalid dead letter on line {} of {}: {}",
+                    i + 1,
+                    self.path.display(),
+                    e
+                )
+            })?;
+            letters.push(letter);
+        }
+        Ok(letters)
+    }
+}
+
+impl DeadLetterResource for DeadLetterFile {
+    fn push(&self, letter: &DeadLetter) -> Result<(), Box<dyn std::error::Error>> {
+        let mut line = serde_json::to_string(letter)?;
+        line.push('\n');
+        let _lock = self.lock()?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<DeadLetter>, Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
+        self.read()
+    }
+
+    fn resolve(
+        &self,
+        redriven: &[DeadLetter],
+        failed: &[DeadLetter],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Read the queue again under the lock, keeping the letters
+        // pushed since it was listed
+        let _lock = self.lock()?;
+        let mut contents = String::new();
+        for letter in self
+            .read()?
+            .iter()
+            .filter(|letter| !redriven.contains(letter))
+            .chain(failed)
+        {
+            contents.push_str(&serde_json::to_string(letter)?);
+            contents.push('\n');
+        }
+
+        // Write to a temporary file first, so a crash never loses letters
+        let tmp = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::resources::{dead_letter::FailureStage, sink::RawLog};
+
+    fn letter(log_index: u64) -> DeadLetter {
+        DeadLetter {
+            stage: FailureStage::Decode,
+            error: "Invalid data".to_owned(),
+            failed_at: 0,
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            signature: "Trade()".to_owned(),
+            transaction_hash: "0x01".to_owned(),
+            raw: RawLog {
+                address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+                topics: Vec::new(),
+                data: "0x".to_owned(),
+                block_number: 17_000_000,
+                log_index,
+            },
+            event: None,
+        }
+    }
+
+    #[test]
+    fn can_resolve_without_losing_new_letters() {
+        let dir = tempfile::tempdir().unwrap();
+        let dead_letters = DeadLetterFile::new(dir.path().join("dead-letters.jsonl"));
+        dead_letters.push(&letter(0)).unwrap();
+        dead_letters.push(&letter(1)).unwrap();
+        let listed = dead_letters.list().unwrap();
+
+        // A listener appends a letter during the redrive
+        dead_letters.push(&letter(2)).unwrap();
+        let failed = DeadLetter {
+            error: "Still invalid".to_owned(),
+            ..listed[1].clone()
+        };
+        dead_letters
+            .resolve(&listed, std::slice::from_ref(&failed))
+            .unwrap();
+
+        assert_eq!(dead_letters.list().unwrap(), vec![letter(2), failed]);
+    }
+}
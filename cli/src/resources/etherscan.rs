@@ -1,17 +1,71 @@
 use async_trait::async_trait;
+use reqwest::Url;
 
 use crate::core::resources::etherscan::{
     EtherscanResource, GetContractCreationResponse, GetSourceCodeResponse,
 };
 
 /// The implementation of the Etherscan resource.
+///
+/// Works with any Etherscan-compatible API: by default it talks to
+/// Etherscan, but [`Etherscan::with_endpoint`] points it elsewhere.
 pub struct Etherscan {
     api_key: String,
+    api_url: Url,
+    client: reqwest::Client,
 }
 
 impl Etherscan {
     pub fn new(api_key: String) -> Self {
-        Etherscan { api_key }
+        Etherscan {
+            api_key,
+            api_url: Url::parse("https://api.etherscan.io/api").unwrap(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Creates an Etherscan resource for a custom API endpoint.
+    ///
+    /// The endpoint may be Etherscan, or any Etherscan-compatible API
+    /// such as a Blockscout instance or an internal mirror. Its query
+    /// string is kept, e.g. the `chainid` of multichain endpoints.
+    pub fn with_endpoint(
+        api_key: String,
+        api_url: String,
+        client: reqwest::Client,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let api_url = Url::parse(api_url.trim_end_matches('/'))
+            .map_err(|e| format!("Invalid explorer API url {}: {}", api_url, e))?;
+        Ok(Etherscan {
+            api_key,
+            api_url,
+            client,
+        })
+    }
+
+    /// Builds the url of a query, adding the API key if one is set.
+    fn url(&self, query: &[(&str, &str)]) -> Url {
+        let mut url = self.api_url.clone();
+        {
+            let mut pairs = url.query_pairs_mut();
+            pairs.extend_pairs(query);
+            if !self.api_key.is_empty() {
+                pairs.append_pair("apikey", &self.api_key);
+            }
+        }
+        url
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        query: &[(&str, &str)],
+    ) -> Result<T, reqwest::Error> {
+        self.client
+            .get(self.url(query))
+            .send()
+            .await?
+            .json::<T>()
+            .await
     }
 }
 
@@ -22,15 +76,12 @@ impl EtherscanResource for Etherscan {
         &self,
         address: &str,
     ) -> Result<GetContractCreationResponse, reqwest::Error> {
-        let url = format!(
-            "https://api.etherscan.io/api?module=contract&action=getcontractcreation&contractaddresses={}&apikey={}",
-            address, self.api_key
-        );
-        let response = reqwest::get(&url)
-            .await?
-            .json::<GetContractCreationResponse>()
-            .await?;
-        Ok(response)
+        self.get(&[
+            ("module", "contract"),
+            ("action", "getcontractcreation"),
+            ("contractaddresses", address),
+        ])
+        .await
     }
 
     /// https://docs.etherscan.io/api-endpoints/contracts#get-contract-source-code-for-verified-contract-source-codes
@@ -38,15 +89,12 @@ impl EtherscanResource for Etherscan {
         &self,
         address: &str,
     ) -> Result<GetSourceCodeResponse, reqwest::Error> {
-        let url = format!(
-            "https://api.etherscan.io/api?module=contract&action=getsourcecode&address={}&apikey={}",
-            address, self.api_key
-        );
-        let response = reqwest::get(&url)
-            .await?
-            .json::<GetSourceCodeResponse>()
-            .await?;
-        Ok(response)
+        self.get(&[
+            ("module", "contract"),
+            ("action", "getsourcecode"),
+            ("address", address),
+        ])
+        .await
     }
 }
 
@@ -56,12 +104,45 @@ mod tests {
 
     use super::Etherscan;
 
+    #[test]
+    fn can_build_urls() {
+        let etherscan = Etherscan::with_endpoint(
+            "key".to_owned(),
+            "https://api.etherscan.io/v2/api?chainid=10".to_owned(),
+            reqwest::Client::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            etherscan
+                .url(&[("module", "contract"), ("address", "0x7a25")])
+                .as_str(),
+            "https://api.etherscan.io/v2/api?chainid=10&module=contract&address=0x7a25&apikey=key"
+        );
+
+        let etherscan = Etherscan::with_endpoint(
+            String::new(),
+            "https://explorer.example.com/api/".to_owned(),
+            reqwest::Client::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            etherscan.url(&[("module", "contract")]).as_str(),
+            "https://explorer.example.com/api?module=contract"
+        );
+
+        assert!(Etherscan::with_endpoint(
+            "key".to_owned(),
+            "not a url".to_owned(),
+            reqwest::Client::new()
+        )
+        .is_err());
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn can_get_contract_creation() {
-        let etherscan = Etherscan::new(String::from(env!(
-            "ETHERSCAN_API_KEY",
-            "Please set an ETHERSCAN_API_KEY"
-        )));
+        let etherscan = Etherscan::new(
+            std::env::var("ETHERSCAN_API_KEY").expect("Please set an ETHERSCAN_API_KEY"),
+        );
         let response = etherscan
             .get_contract_creation(&String::from("0x7a250d5630b4cf539739df2c5dacb4c659f2488d"))
             .await
@@ -86,10 +167,9 @@ mod tests {
 
     #[tokio::test(flavor = "multi_thread")]
     async fn can_get_source_code() {
-        let etherscan = Etherscan::new(String::from(env!(
-            "ETHERSCAN_API_KEY",
-            "Please set an ETHERSCAN_API_KEY"
-        )));
+        let etherscan = Etherscan::new(
+            std::env::var("ETHERSCAN_API_KEY").expect("Please set an ETHERSCAN_API_KEY"),
+        );
         let response = etherscan
             .get_source_code(&String::from("0x7a250d5630b4cf539739df2c5dacb4c659f2488d"))
             .await
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+
+use crate::{
+    config::{ExplorerConfig, ExplorerKind, HttpConfig},
+    core::resources::etherscan::{
+        EtherscanResource, GetContractCreationResponse, GetSourceCodeResponse,
+    },
+};
+
+use super::{blockscout::Blockscout, etherscan::Etherscan, http::client_with_auth};
+
+/// A contract metadata resource that is selected at runtime from config.
+pub enum Explorer {
+    Etherscan(Etherscan),
+    Blockscout(Blockscout),
+}
+
+impl Explorer {
+    /// Builds the explorer described by the config.
+    ///
+    /// The API key is only required by Etherscan.
+    pub fn from_config(
+        config: &ExplorerConfig,
+        http: &HttpConfig,
+        api_key: Option<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = client_with_auth(http, config.auth_header.as_deref())?;
+        match config.kind {
+            ExplorerKind::Etherscan => {
+                let api_key = api_key
+                    .ok_or("Please set an ETHERSCAN_API_KEY or run `shadow auth set etherscan`")?;
+                Ok(Explorer::Etherscan(Etherscan::with_endpoint(
+                    api_key,
+                    config.api_url.clone(),
+                    client,
+                )?))
+            }
+            ExplorerKind::Blockscout => Ok(Explorer::Blockscout(Blockscout::new(
+                config.api_url.clone(),
+                client,
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl EtherscanResource for Explorer {
+    async fn get_contract_creation(
+        &self,
+        address: &str,
+    ) -> Result<GetContractCreationResponse, reqwest::Error> {
+        match self {
+            Explorer::Etherscan(etherscan) => etherscan.get_contract_creation(address).await,
+            Explorer::Blockscout(blockscout) => blockscout.get_contract_creation(address).await,
+        }
+    }
+
+    async fn get_source_code(
+        &self,
+        address: &str,
+    ) -> Result<GetSourceCodeResponse, reqwest::Error> {
+        match self {
+            Explorer::Etherscan(etherscan) => etherscan.get_source_code(address).await,
+            Explorer::Blockscout(blockscout) => blockscout.get_source_code(address).await,
+        }
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(feature = "parquet")]
+mod parquet;
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::core::resources::{export::ExportWriter, sink::DecodedEvent};
+
+#[cfg(feature = "parquet")]
+pub use self::parquet::ParquetExport;
+
+/// Exports events to a file, one JSON object per line, in the format of
+/// the `events --archive` file.
+///
+/// The position of the writer is the length of the file, which it is
+/// truncated to when resumed, dropping events written after the last
+/// checkpoint.
+pub struct JsonlExport {
+    file: File,
+    buffer: Vec<u8>,
+}
+
+impl JsonlExport {
+    /// Creates the file, replacing any existing one.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        Ok(Self {
+            file: File::create(path)?,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Reopens the file to resume an export at a position.
+    pub fn resume(path: impl AsRef<Path>, position: u64) -> Result<Self, std::io::Error> {
+        let mut file = OpenOptions::new().write(true).create(true).open(path)?;
+        file.set_len(position)?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            file,
+            buffer: Vec::new(),
+        })
+    }
+}
+
+impl ExportWriter for JsonlExport {
+    fn write(
+        &mut self,
+        _from_block: u64,
+        _to_block: u64,
+        events: &[DecodedEvent],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for event in events {
+            serde_json::to_writer(&mut self.buffer, event)?;
+            self.buffer.push(b'\n');
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<u64, Box<dyn std::error::Error>> {
+        self.file.write_all(&self.buffer)?;
+        self.file.sync_data()?;
+        self.buffer.clear();
+        Ok(self.file.stream_position()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::resources::sinks::jsonl::read_events;
+
+    fn event(block_number: u64) -> DecodedEvent {
+        DecodedEvent {
+            block_number,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 0,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "Token.sol:Token".to_owned(),
+            event: "Transfer".to_owned(),
+            signature: "Transfer(address,address,uint256)".to_owned(),
+            schema: None,
+            data: json!({ "value": "1" }),
+            raw: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_resume_jsonl_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transfers.jsonl");
+
+        let mut writer = JsonlExport::create(&path).unwrap();
+        writer.write(0, 9, &[event(1), event(2)]).unwrap();
+        let position = writer.flush().unwrap();
+        // Written after the last checkpoint, then interrupted
+        writer.write(10, 19, &[event(11)]).unwrap();
+        writer.flush().unwrap();
+
+        let mut writer = JsonlExport::resume(&path, position).unwrap();
+        writer.write(10, 19, &[event(12)]).unwrap();
+        writer.flush().unwrap();
+        let blocks: Vec<u64> = read_events(&path)
+            .unwrap()
+            .iter()
+            .map(|event| event.block_number)
+            .collect();
+        assert_eq!(blocks, vec![1, 2, 12]);
+    }
+}
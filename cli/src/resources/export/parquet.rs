@@ -0,0 +1,269 @@
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use ::parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use alloy_json_abi::Event;
+use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use serde_json::Value;
+
+use crate::{
+    core::resources::{export::ExportWriter, sink::DecodedEvent},
+    decode::event::param_key,
+};
+
+/// The columns every exported event has, before its parameters
+const EVENT_COLUMNS: [&str; 5] = [
+    "block_number",
+    "block_timestamp",
+    "transaction_hash",
+    "log_index",
+    "address",
+];
+
+/// Exports events to a directory of Parquet files, with a column per
+/// event parameter.
+///
+/// Every flush writes the buffered events to a new file named after the
+/// first block it covers, e.g. `part-17000000.parquet`. Files are
+/// written under a temporary name and renamed once complete, so readers
+/// never see a partial file. A resumed export starts at the first block
+/// of the interrupted flush, so it overwrites its file, even if the
+/// flush completed but the checkpoint wasn't saved.
+pub struct ParquetExport {
+    dir: PathBuf,
+    schema: Arc<Schema>,
+    /// The keys of the parameters in the decoded events, by column
+    params: Vec<String>,
+    events: Vec<DecodedEvent>,
+    /// The first block of the buffered ranges
+    first_block: Option<u64>,
+}
+
+impl ParquetExport {
+    /// Creates the output directory, for the events of an event ABI.
+    pub fn create(dir: impl Into<PathBuf>, event: &Event) -> Result<Self, std::io::Error> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut fields = vec![
+            Field::new(EVENT_COLUMNS[0], DataType::UInt64, false),
+            Field::new(EVENT_COLUMNS[1], DataType::UInt64, true),
+            Field::new(EVENT_COLUMNS[2], DataType::Utf8, false),
+            Field::new(EVENT_COLUMNS[3], DataType::UInt64, false),
+            Field::new(EVENT_COLUMNS[4], DataType::Utf8, false),
+        ];
+        let mut params = Vec::new();
+        for (i, input) in event.inputs.iter().enumerate() {
+            // Parameters are read under the key the decoder writes them to
+            let key = param_key(input);
+            let column = match key.as_str() {
+                _ if input.name.is_empty() => format!("arg{}", i),
+                key if EVENT_COLUMNS.contains(&key) => format!("param_{}", key),
+                key => key.to_owned(),
+            };
+            fields.push(Field::new(column, DataType::Utf8, true));
+            params.push(key);
+        }
+
+        Ok(Self {
+            dir,
+            schema: Arc::new(Schema::new(fields)),
+            params,
+            events: Vec::new(),
+            first_block: None,
+        })
+    }
+
+    /// Builds the record batch of the buffered events.
+    fn record_batch(&self) -> Result<RecordBatch, Box<dyn std::error::Error>> {
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from_iter_values(
+                self.events.iter().map(|event| event.block_number),
+            )),
+            Arc::new(UInt64Array::from(
+                self.events
+                    .iter()
+                    .map(|event| event.block_timestamp)
+                    .collect::<Vec<_>>(),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                self.events.iter().map(|event| &event.transaction_hash),
+            )),
+            Arc::new(UInt64Array::from_iter_values(
+                self.events.iter().map(|event| event.log_index),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                self.events.iter().map(|event| &event.address),
+            )),
+        ];
+        for param in &self.params {
+            columns.push(Arc::new(StringArray::from(
+                self.events
+                    .iter()
+                    .map(|event| event.data.get(param).map(param_string))
+                    .collect::<Vec<_>>(),
+            )));
+        }
+        Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+}
+
+/// Returns a decoded parameter as a Parquet string: strings as is, and
+/// arrays and tuples as JSON.
+fn param_string(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+impl ExportWriter for ParquetExport {
+    fn write(
+        &mut self,
+        from_block: u64,
+        _to_block: u64,
+        events: &[DecodedEvent],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.first_block.get_or_insert(from_block);
+        self.events.extend_from_slice(events);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<u64, Box<dyn std::error::Error>> {
+        let Some(from_block) = self.first_block.take() else {
+            return Ok(0);
+        };
+        if self.events.is_empty() {
+            return Ok(0);
+        }
+
+        let path = self.dir.join(format!("part-{}.parquet", from_block));
+        let tmp = path.with_extension("parquet.tmp");
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&tmp)?, self.schema.clone(), Some(properties))?;
+        writer.write(&self.record_batch()?)?;
+        writer.close()?;
+        fs::rename(&tmp, &path)?;
+        log::debug!("Wrote {} events to {}", self.events.len(), path.display());
+        self.events.clear();
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn can_write_parquet_parts() {
+        let event: Event = serde_json::from_str(
+            r#"{
+                "type": "event",
+                "name": "Transfer",
+                "inputs": [
+                    { "name": "from", "type": "address", "internalType": "address", "indexed": true },
+                    { "name": "to", "type": "address", "internalType": "address", "indexed": true },
+                    { "name": "value", "type": "uint256", "internalType": "uint256", "indexed": false }
+                ],
+                "anonymous": false
+            }"#,
+        )
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = ParquetExport::create(dir.path(), &event).unwrap();
+        let decoded = DecodedEvent {
+            block_number: 5,
+            block_timestamp: Some(1681338455),
+            transaction_hash: "0x01".to_owned(),
+            log_index: 3,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "Token.sol:Token".to_owned(),
+            event: "Transfer".to_owned(),
+            signature: "Transfer(address,address,uint256)".to_owned(),
+            schema: None,
+            data: json!({ "from": "0xaa", "to": "0xbb", "value": "69" }),
+            raw: None,
+            ..Default::default()
+        };
+        writer.write(0, 4, &[]).unwrap();
+        writer.write(5, 9, &[decoded]).unwrap();
+        writer.flush().unwrap();
+        // Ranges without events don't create files
+        writer.write(10, 19, &[]).unwrap();
+        writer.flush().unwrap();
+
+        let path = dir.path().join("part-0.parquet");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+        assert_eq!(batches[0].num_rows(), 1);
+        let values = batches[0]
+            .column_by_name("value")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(values.value(0), "69");
+    }
+
+    #[test]
+    fn can_write_unnamed_params() {
+        let event: Event = serde_json::from_str(
+            r#"{
+                "type": "event",
+                "name": "Deposit",
+                "inputs": [
+                    { "name": "", "type": "uint256", "internalType": "uint256", "indexed": false }
+                ],
+                "anonymous": false
+            }"#,
+        )
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = ParquetExport::create(dir.path(), &event).unwrap();
+        // The decoder keys unnamed parameters by their empty name
+        let decoded = DecodedEvent {
+            block_number: 5,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 0,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "Vault.sol:Vault".to_owned(),
+            event: "Deposit".to_owned(),
+            signature: "Deposit(uint256)".to_owned(),
+            schema: None,
+            data: json!({ "": "7" }),
+            raw: None,
+            ..Default::default()
+        };
+        writer.write(5, 9, &[decoded]).unwrap();
+        writer.flush().unwrap();
+
+        let file = File::open(dir.path().join("part-5.parquet")).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+        let values = batches[0]
+            .column_by_name("arg0")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(values.value(0), "7");
+    }
+}
@@ -0,0 +1,173 @@
+use async_trait::async_trait;
+use clap::ValueEnum;
+use ethers::{
+    providers::Middleware,
+    types::{Bytes, TransactionReceipt, H160, H256, U256},
+};
+
+use crate::core::resources::fork_runner::{ForkRunner, ForkTransaction};
+
+use super::{
+    anvil::{AnvilFork, AnvilForkRunner},
+    revm::{RevmFork, RevmForkRunner},
+};
+
+/// The engine the temporary deploy fork runs on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// An in-process anvil node
+    #[default]
+    Anvil,
+    /// revm, reading upstream state on demand. Much faster to start.
+    Revm,
+}
+
+/// Runs forks on the backend chosen at runtime.
+pub enum BackendForkRunner<M> {
+    Anvil(AnvilForkRunner),
+    Revm(RevmForkRunner<M>),
+}
+
+/// A running fork of a [`BackendForkRunner`].
+pub enum BackendFork<M> {
+    Anvil(AnvilFork),
+    Revm(RevmFork<M>),
+}
+
+impl<M: Middleware> BackendForkRunner<M> {
+    /// Builds the runner for a backend. Anvil forks from the HTTP RPC
+    /// URL, and revm reads state through the provider.
+    pub fn new(backend: Backend, http_rpc_url: String, provider: M) -> Self {
+        match backend {
+            Backend::Anvil => Self::Anvil(AnvilForkRunner::new(http_rpc_url)),
+            Backend::Revm => Self::Revm(RevmForkRunner::new(provider)),
+        }
+    }
+}
+
+const MISMATCHED_FORK: &str = "Fork was started by a different backend";
+
+#[async_trait]
+impl<M: Middleware + 'static> ForkRunner for BackendForkRunner<M> {
+    type Fork = BackendFork<M>;
+
+    async fn spawn(
+        &self,
+        block_number: Option<u64>,
+    ) -> Result<BackendFork<M>, Box<dyn std::error::Error>> {
+        match self {
+            Self::Anvil(runner) => Ok(BackendFork::Anvil(runner.spawn(block_number).await?)),
+            Self::Revm(runner) => Ok(BackendFork::Revm(runner.spawn(block_number).await?)),
+        }
+    }
+
+    async fn set_balance(
+        &self,
+        fork: &BackendFork<M>,
+        address: H160,
+        balance: U256,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match (self, fork) {
+            (Self::Anvil(runner), BackendFork::Anvil(fork)) => {
+                runner.set_balance(fork, address, balance).await
+            }
+            (Self::Revm(runner), BackendFork::Revm(fork)) => {
+                runner.set_balance(fork, address, balance).await
+            }
+            _ => Err(MISMATCHED_FORK.into()),
+        }
+    }
+
+    async fn set_code(
+        &self,
+        fork: &BackendFork<M>,
+        address: H160,
+        code: Bytes,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match (self, fork) {
+            (Self::Anvil(runner), BackendFork::Anvil(fork)) => {
+                runner.set_code(fork, address, code).await
+            }
+            (Self::Revm(runner), BackendFork::Revm(fork)) => {
+                runner.set_code(fork, address, code).await
+            }
+            _ => Err(MISMATCHED_FORK.into()),
+        }
+    }
+
+    async fn impersonate(
+        &self,
+        fork: &BackendFork<M>,
+        address: H160,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match (self, fork) {
+            (Self::Anvil(runner), BackendFork::Anvil(fork)) => {
+                runner.impersonate(fork, address).await
+            }
+            (Self::Revm(runner), BackendFork::Revm(fork)) => {
+                runner.impersonate(fork, address).await
+            }
+            _ => Err(MISMATCHED_FORK.into()),
+        }
+    }
+
+    async fn send_transaction(
+        &self,
+        fork: &BackendFork<M>,
+        transaction: ForkTransaction,
+    ) -> Result<H256, Box<dyn std::error::Error>> {
+        match (self, fork) {
+            (Self::Anvil(runner), BackendFork::Anvil(fork)) => {
+                runner.send_transaction(fork, transaction).await
+            }
+            (Self::Revm(runner), BackendFork::Revm(fork)) => {
+                runner.send_transaction(fork, transaction).await
+            }
+            _ => Err(MISMATCHED_FORK.into()),
+        }
+    }
+
+    async fn mine(&self, fork: &BackendFork<M>) -> Result<(), Box<dyn std::error::Error>> {
+        match (self, fork) {
+            (Self::Anvil(runner), BackendFork::Anvil(fork)) => runner.mine(fork).await,
+            (Self::Revm(runner), BackendFork::Revm(fork)) => runner.mine(fork).await,
+            _ => Err(MISMATCHED_FORK.into()),
+        }
+    }
+
+    async fn transaction_receipt(
+        &self,
+        fork: &BackendFork<M>,
+        tx_hash: H256,
+    ) -> Result<Option<TransactionReceipt>, Box<dyn std::error::Error>> {
+        match (self, fork) {
+            (Self::Anvil(runner), BackendFork::Anvil(fork)) => {
+                runner.transaction_receipt(fork, tx_hash).await
+            }
+            (Self::Revm(runner), BackendFork::Revm(fork)) => {
+                runner.transaction_receipt(fork, tx_hash).await
+            }
+            _ => Err(MISMATCHED_FORK.into()),
+        }
+    }
+
+    async fn get_code(
+        &self,
+        fork: &BackendFork<M>,
+        address: H160,
+    ) -> Result<Bytes, Box<dyn std::error::Error>> {
+        match (self, fork) {
+            (Self::Anvil(runner), BackendFork::Anvil(fork)) => runner.get_code(fork, address).await,
+            (Self::Revm(runner), BackendFork::Revm(fork)) => runner.get_code(fork, address).await,
+            _ => Err(MISMATCHED_FORK.into()),
+        }
+    }
+
+    async fn stop(&self, fork: BackendFork<M>) {
+        match (self, fork) {
+            (Self::Anvil(runner), BackendFork::Anvil(fork)) => runner.stop(fork).await,
+            (Self::Revm(runner), BackendFork::Revm(fork)) => runner.stop(fork).await,
+            _ => {}
+        }
+    }
+}
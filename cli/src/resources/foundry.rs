@@ -0,0 +1,112 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+/// The name of the Foundry config file
+pub const FOUNDRY_TOML: &str = "foundry.toml";
+
+/// A Foundry project discovered on the local file system.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoundryProject {
+    /// The directory containing `foundry.toml`
+    pub root: PathBuf,
+    /// The sources directory
+    pub src: PathBuf,
+    /// The artifacts directory
+    pub out: PathBuf,
+}
+
+/// The subset of `foundry.toml` the CLI reads.
+#[derive(Debug, Default, Deserialize)]
+struct FoundryToml {
+    #[serde(default)]
+    profile: BTreeMap<String, Profile>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+struct Profile {
+    src: Option<String>,
+    out: Option<String>,
+}
+
+impl FoundryProject {
+    /// Walks up from `dir` to the first directory containing a
+    /// `foundry.toml`, and loads the project from it.
+    pub fn discover(dir: &Path) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        for ancestor in dir.ancestors() {
+            if ancestor.join(FOUNDRY_TOML).is_file() {
+                return Self::load(ancestor).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Loads the project whose `foundry.toml` is in `root`.
+    ///
+    /// Settings are read from the profile set in `FOUNDRY_PROFILE`,
+    /// falling back to the default profile and then Foundry's defaults.
+    pub fn load(root: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = root.join(FOUNDRY_TOML);
+        let contents = fs::read_to_string(&path)?;
+        let config: FoundryToml =
+            toml::from_str(&contents).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+
+        let default = config.profile.get("default").cloned().unwrap_or_default();
+        let profile = env::var("FOUNDRY_PROFILE")
+            .ok()
+            .and_then(|name| config.profile.get(&name).cloned())
+            .unwrap_or_default();
+
+        let src = profile
+            .src
+            .or(default.src)
+            .unwrap_or_else(|| "src".to_owned());
+        let out = profile
+            .out
+            .or(default.out)
+            .unwrap_or_else(|| "out".to_owned());
+        Ok(FoundryProject {
+            root: root.to_path_buf(),
+            src: root.join(src),
+            out: root.join(out),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn can_discover_from_subdirectory() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(FOUNDRY_TOML),
+            "[profile.default]\nsrc = \"contracts/src\"\nout = \"contracts/out\"\n",
+        )
+        .unwrap();
+        let subdir = temp_dir.path().join("contracts/src/nested");
+        fs::create_dir_all(&subdir).unwrap();
+
+        let project = FoundryProject::discover(&subdir).unwrap().unwrap();
+        assert_eq!(project.root, temp_dir.path());
+        assert_eq!(project.src, temp_dir.path().join("contracts/src"));
+        assert_eq!(project.out, temp_dir.path().join("contracts/out"));
+    }
+
+    #[test]
+    fn uses_foundry_defaults() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(FOUNDRY_TOML), "").unwrap();
+
+        let project = FoundryProject::load(temp_dir.path()).unwrap();
+        assert_eq!(project.src, temp_dir.path().join("src"));
+        assert_eq!(project.out, temp_dir.path().join("out"));
+    }
+}
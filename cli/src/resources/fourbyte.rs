@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::core::resources::signatures::SignaturesResource;
+
+/// The default 4byte.directory API
+pub const FOURBYTE_API_URL: &str = "https://www.4byte.directory/api/v1";
+
+/// A Signatures resource backed by the 4byte.directory signature database.
+///
+/// https://www.4byte.directory/event-signatures/
+pub struct FourByte {
+    api_url: String,
+    client: reqwest::Client,
+}
+
+/// The subset of the `/event-signatures/` response we use
+#[derive(Deserialize)]
+struct EventSignaturesResponse {
+    results: Vec<EventSignature>,
+}
+
+#[derive(Deserialize)]
+struct EventSignature {
+    id: u64,
+    text_signature: String,
+}
+
+impl FourByte {
+    pub fn new(api_url: String, client: reqwest::Client) -> Self {
+        FourByte { api_url, client }
+    }
+}
+
+#[async_trait]
+impl SignaturesResource for FourByte {
+    async fn lookup_event(&self, topic0: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let response: EventSignaturesResponse = self
+            .client
+            .get(format!("{}/event-signatures/", self.api_url))
+            .query(&[("hex_signature", topic0)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // The oldest submission is the most likely to be genuine
+        let mut results = response.results;
+        results.sort_by_key(|result| result.id);
+        Ok(results
+            .into_iter()
+            .map(|result| result.text_signature)
+            .collect())
+    }
+}
@@ -0,0 +1,77 @@
+use std::{sync::OnceLock, time::Duration};
+
+use crate::config::HttpConfig;
+
+/// How often idle connections are probed, so load balancers don't drop them
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The client shared by every resource without custom headers
+static SHARED_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// Returns the HTTP client shared across the CLI.
+///
+/// Cloning a client is cheap and clones share the same connection
+/// pool, so connections are reused across the RPC provider, the
+/// explorers and the sinks. The first call's config wins.
+pub fn shared_client(config: &HttpConfig) -> reqwest::Client {
+    SHARED_CLIENT
+        .get_or_init(|| {
+            client_builder(config)
+                .build()
+                .expect("Failed to build the HTTP client")
+        })
+        .clone()
+}
+
+/// Returns an HTTP client that sends the auth header, in the form
+/// `Name: value`, with every request.
+///
+/// Without an auth header, this is the shared client.
+pub fn client_with_auth(
+    config: &HttpConfig,
+    auth_header: Option<&str>,
+) -> Result<reqwest::Client, Box<dyn std::error::Error>> {
+    let Some(auth_header) = auth_header else {
+        return Ok(shared_client(config));
+    };
+    let (name, value) = auth_header
+        .split_once(':')
+        .ok_or("The auth header must be in the form `Name: value`")?;
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+        reqwest::header::HeaderName::from_bytes(name.trim().as_bytes())?,
+        reqwest::header::HeaderValue::from_str(value.trim())?,
+    );
+    Ok(client_builder(config).default_headers(headers).build()?)
+}
+
+/// Configures keep-alive, HTTP/2 and timeouts.
+///
+/// HTTP/2 is negotiated over TLS, so plain HTTP/1.1 servers keep working.
+fn client_builder(config: &HttpConfig) -> reqwest::ClientBuilder {
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout))
+        .connect_timeout(Duration::from_secs(config.connect_timeout))
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout))
+        .tcp_keepalive(KEEP_ALIVE_INTERVAL)
+        .http2_keep_alive_interval(KEEP_ALIVE_INTERVAL)
+        .http2_keep_alive_while_idle(true)
+        .http2_adaptive_window(true);
+    if let Some(max_idle) = config.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_build_clients() {
+        let config = HttpConfig::default();
+        assert!(client_with_auth(&config, None).is_ok());
+        assert!(client_with_auth(&config, Some("Authorization: Bearer token")).is_ok());
+        assert!(client_with_auth(&config, Some("Authorization")).is_err());
+    }
+}
@@ -1,3 +1,25 @@
+pub mod anvil;
 pub mod artifacts;
+pub mod backup;
+pub mod blockscout;
+pub mod credentials;
+pub mod dead_letters;
 pub mod etherscan;
+pub mod explorer;
+pub mod export;
+pub mod fork_backend;
+pub mod foundry;
+pub mod fourbyte;
+pub mod http;
+pub mod openchain;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod revm;
 pub mod shadow;
+pub mod signature_db;
+pub mod signer;
+pub mod sinks;
+pub mod stores;
+pub mod verifier;
+
+pub use shadow_decode_core::migrations;
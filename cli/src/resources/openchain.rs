@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::core::resources::signatures::SignaturesResource;
+
+/// The default openchain.xyz signature database API
+pub const OPENCHAIN_API_URL: &str = "https://api.openchain.xyz/signature-database/v1";
+
+/// A Signatures resource backed by the openchain.xyz signature database.
+///
+/// https://openchain.xyz/signatures
+pub struct Openchain {
+    api_url: String,
+    client: reqwest::Client,
+}
+
+/// The subset of the `/lookup` response we use
+#[derive(Deserialize)]
+struct LookupResponse {
+    ok: bool,
+    result: LookupResult,
+}
+
+#[derive(Deserialize)]
+struct LookupResult {
+    #[serde(default)]
+    event: BTreeMap<String, Option<Vec<SignatureEntry>>>,
+}
+
+#[derive(Deserialize)]
+struct SignatureEntry {
+    name: String,
+}
+
+impl Openchain {
+    pub fn new(api_url: String, client: reqwest::Client) -> Self {
+        Openchain { api_url, client }
+    }
+}
+
+#[async_trait]
+impl SignaturesResource for Openchain {
+    async fn lookup_event(&self, topic0: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let response: LookupResponse = self
+            .client
+            .get(format!("{}/lookup", self.api_url))
+            .query(&[("event", topic0), ("filter", "true")])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if !response.ok {
+            return Err("openchain.xyz lookup failed".into());
+        }
+        Ok(response
+            .result
+            .event
+            .into_values()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.name)
+            .collect())
+    }
+}
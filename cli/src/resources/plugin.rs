@@ -0,0 +1,236 @@
+use std::{path::Path, sync::Mutex};
+
+use wasmtime::{Engine, Linker, Memory, Module, Store, TypedFunc};
+
+type PluginError = Box<dyn std::error::Error + Send + Sync>;
+
+use crate::core::resources::{plugin::EventPlugin, sink::DecodedEvent};
+
+/// The fuel a plugin may burn on a single event, roughly the number of
+/// WASM instructions, so a stuck plugin fails the event instead of
+/// stalling the listener
+pub const DEFAULT_PLUGIN_FUEL: u64 = 1_000_000_000;
+
+/// An event plugin compiled to WebAssembly.
+///
+/// The module has no imports, so it can't reach the file system or the
+/// network, and must export:
+/// - `memory`, its linear memory
+/// - `alloc(len: i32) -> i32`, returning where the host may write an
+///   input of `len` bytes
+/// - `process(ptr: i32, len: i32) -> i64`, processing the event JSON at
+///   `ptr` and returning where the output JSON is, as `ptr << 32 | len`.
+///   A length of 0 drops the event.
+///
+/// The instance is kept between events, so a plugin may keep state,
+/// and owns its memory: the host never frees what `alloc` returned.
+/// After a trap, e.g. when the plugin runs out of fuel, its state may
+/// be half-updated, so the module is instantiated again.
+pub struct WasmPlugin {
+    name: String,
+    fuel: u64,
+    module: Module,
+    instance: Mutex<PluginInstance>,
+}
+
+struct PluginInstance {
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    process: TypedFunc<(i32, i32), i64>,
+}
+
+impl WasmPlugin {
+    /// Loads a plugin from a `.wasm` file, or a `.wat` text file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PluginError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("Error reading plugin {}: {}", path.display(), e))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        Self::new(name, &bytes, DEFAULT_PLUGIN_FUEL)
+    }
+
+    /// Compiles and instantiates a plugin.
+    pub fn new(name: String, bytes: &[u8], fuel: u64) -> Result<Self, PluginError> {
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module =
+            Module::new(&engine, bytes).map_err(|e| format!("Invalid plugin {}: {}", name, e))?;
+        let instance = PluginInstance::new(&name, &module)?;
+        Ok(Self {
+            name,
+            fuel,
+            module,
+            instance: Mutex::new(instance),
+        })
+    }
+}
+
+impl PluginInstance {
+    /// Instantiates a plugin's module in a fresh store.
+    fn new(name: &str, module: &Module) -> Result<Self, PluginError> {
+        let mut store = Store::new(module.engine(), ());
+        let instance = Linker::new(module.engine()).instantiate(&mut store, module)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| format!("Plugin {} doesn't export its memory", name))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let process = instance.get_typed_func::<(i32, i32), i64>(&mut store, "process")?;
+        Ok(Self {
+            store,
+            memory,
+            alloc,
+            process,
+        })
+    }
+
+    /// Runs the plugin over an event's JSON, returning the processed
+    /// JSON, or `None` if the event is dropped.
+    fn run(&mut self, input: &[u8], fuel: u64) -> Result<Option<Vec<u8>>, PluginError> {
+        // Top the fuel back up, so every event gets the same budget
+        let remaining = self.store.consume_fuel(0)?;
+        if remaining < fuel {
+            self.store.add_fuel(fuel - remaining)?;
+        }
+
+        let len = i32::try_from(input.len()).map_err(|_| "Event too large for the plugin")?;
+        let ptr = self.alloc.call(&mut self.store, len)?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, input)?;
+        let output = self.process.call(&mut self.store, (ptr, len))?;
+
+        let (ptr, len) = ((output >> 32) as u32 as usize, output as u32 as usize);
+        if len == 0 {
+            return Ok(None);
+        }
+        let mut output = vec![0; len];
+        self.memory.read(&self.store, ptr, &mut output)?;
+        Ok(Some(output))
+    }
+}
+
+impl EventPlugin for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn process(&self, event: &DecodedEvent) -> Result<Option<DecodedEvent>, PluginError> {
+        let input = serde_json::to_vec(event)?;
+        let mut instance = self.instance.lock().unwrap();
+        let output = match instance.run(&input, self.fuel) {
+            Ok(Some(output)) => output,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                *instance = PluginInstance::new(&self.name, &self.module)?;
+                return Err(e);
+            }
+        };
+        let event = serde_json::from_slice(&output)
+            .map_err(|e| format!("Plugin {} returned an invalid event: {}", self.name, e))?;
+        Ok(Some(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Returns every event unchanged
+    const ECHO: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "process") (param $ptr i32) (param $len i32) (result i64)
+                (i64.or
+                    (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                    (i64.extend_i32_u (local.get $len)))))
+    "#;
+
+    /// Drops every event
+    const DROP: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "process") (param i32 i32) (result i64) i64.const 0))
+    "#;
+
+    /// Never returns
+    const LOOP: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "process") (param i32 i32) (result i64)
+                (loop $forever (br $forever))
+                i64.const 0))
+    "#;
+
+    /// Echoes the first event, and gets stuck on every later one
+    const STUCK_AFTER_ONE: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (global $calls (mut i32) (i32.const 0))
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "process") (param $ptr i32) (param $len i32) (result i64)
+                (global.set $calls (i32.add (global.get $calls) (i32.const 1)))
+                (if (i32.ge_u (global.get $calls) (i32.const 2))
+                    (then (loop $forever (br $forever))))
+                (i64.or
+                    (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                    (i64.extend_i32_u (local.get $len)))))
+    "#;
+
+    fn event() -> DecodedEvent {
+        DecodedEvent {
+            block_number: 17_000_000,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 3,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            event: "Trade".to_owned(),
+            signature: "Trade(address,uint256)".to_owned(),
+            schema: None,
+            data: json!({ "amountIn": "150000000000000000" }),
+            raw: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_run_plugins() {
+        let echo = WasmPlugin::new("echo".to_owned(), ECHO.as_bytes(), 100_000).unwrap();
+        assert_eq!(echo.process(&event()).unwrap(), Some(event()));
+        // The instance is reused
+        assert_eq!(echo.process(&event()).unwrap(), Some(event()));
+
+        let drop = WasmPlugin::new("drop".to_owned(), DROP.as_bytes(), 100_000).unwrap();
+        assert_eq!(drop.process(&event()).unwrap(), None);
+
+        let stuck = WasmPlugin::new("loop".to_owned(), LOOP.as_bytes(), 100_000).unwrap();
+        assert!(stuck.process(&event()).is_err());
+    }
+
+    #[test]
+    fn starts_over_after_a_trap() {
+        let plugin =
+            WasmPlugin::new("stuck".to_owned(), STUCK_AFTER_ONE.as_bytes(), 100_000).unwrap();
+        assert_eq!(plugin.process(&event()).unwrap(), Some(event()));
+        assert!(plugin.process(&event()).is_err());
+        // The fresh instance has no calls counted
+        assert_eq!(plugin.process(&event()).unwrap(), Some(event()));
+    }
+
+    #[test]
+    fn rejects_modules_without_exports() {
+        let err = WasmPlugin::new("empty".to_owned(), b"(module)", 100_000)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("doesn't export its memory"));
+    }
+}
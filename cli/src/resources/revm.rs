@@ -0,0 +1,502 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use ethers::{
+    providers::Middleware,
+    types::{BlockId, Bytes, Log, TransactionReceipt, H160, H256, U256, U64},
+};
+use revm::{
+    db::{CacheDB, DatabaseRef},
+    primitives::{
+        keccak256, AccountInfo, Bytecode, CreateScheme, ExecutionResult, Output, SpecId,
+        TransactTo, B160, B256, KECCAK_EMPTY, U256 as RU256,
+    },
+    Database, EVM,
+};
+
+use crate::core::resources::fork_runner::{ForkRunner, ForkTransaction};
+
+/// Runs forks on an in-process revm instance, reading the upstream
+/// state lazily over RPC.
+///
+/// This skips starting a full anvil node, which makes it much faster
+/// for short-lived forks like deploys. Only the accounts and storage
+/// slots the transactions touch are fetched.
+pub struct RevmForkRunner<M> {
+    /// The upstream provider
+    pub provider: Arc<M>,
+}
+
+/// A running revm fork.
+pub struct RevmFork<M> {
+    block: ForkBlock,
+    state: Arc<Mutex<CacheDB<ForkedState<M>>>>,
+    pending: Mutex<Vec<(H256, ForkTransaction)>>,
+    receipts: Mutex<HashMap<H256, TransactionReceipt>>,
+}
+
+/// The block the fork's transactions are executed in, which follows
+/// the fork block.
+#[derive(Clone)]
+struct ForkBlock {
+    chain_id: u64,
+    number: u64,
+    timestamp: U256,
+    coinbase: H160,
+    difficulty: U256,
+    prevrandao: Option<H256>,
+}
+
+impl<M: Middleware> RevmForkRunner<M> {
+    pub fn new(provider: M) -> Self {
+        Self {
+            provider: Arc::new(provider),
+        }
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> ForkRunner for RevmForkRunner<M> {
+    type Fork = RevmFork<M>;
+
+    async fn spawn(
+        &self,
+        block_number: Option<u64>,
+    ) -> Result<RevmFork<M>, Box<dyn std::error::Error>> {
+        let block_id = match block_number {
+            Some(n) => BlockId::from(n),
+            None => BlockId::from(ethers::types::BlockNumber::Latest),
+        };
+        let block = self
+            .provider
+            .get_block(block_id)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or("Fork block not found")?;
+        let number = block.number.ok_or("Fork block is pending")?.as_u64();
+        let chain_id = self
+            .provider
+            .get_chainid()
+            .await
+            .map_err(|e| e.to_string())?
+            .as_u64();
+
+        Ok(RevmFork {
+            block: ForkBlock {
+                chain_id,
+                number: number + 1,
+                timestamp: block.timestamp,
+                coinbase: block.author.unwrap_or_default(),
+                difficulty: block.difficulty,
+                prevrandao: block.mix_hash,
+            },
+            state: Arc::new(Mutex::new(CacheDB::new(ForkedState {
+                provider: self.provider.clone(),
+                block: BlockId::from(number),
+                handle: tokio::runtime::Handle::current(),
+            }))),
+            pending: Mutex::new(Vec::new()),
+            receipts: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn set_balance(
+        &self,
+        fork: &RevmFork<M>,
+        address: H160,
+        balance: U256,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        fork.with_state(move |state| {
+            let address = B160(address.0);
+            let info = Database::basic(state, address)?.unwrap_or_default();
+            state.insert_account_info(
+                address,
+                AccountInfo {
+                    balance: to_revm_u256(balance),
+                    ..info
+                },
+            );
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_code(
+        &self,
+        fork: &RevmFork<M>,
+        address: H160,
+        code: Bytes,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        fork.with_state(move |state| {
+            let address = B160(address.0);
+            let info = Database::basic(state, address)?.unwrap_or_default();
+            state.insert_account_info(
+                address,
+                AccountInfo {
+                    code_hash: keccak256(&code),
+                    code: Some(Bytecode::new_raw(code.0)),
+                    ..info
+                },
+            );
+            Ok(())
+        })
+        .await
+    }
+
+    async fn impersonate(
+        &self,
+        _fork: &RevmFork<M>,
+        _address: H160,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // revm doesn't check signatures, so any account can send transactions
+        Ok(())
+    }
+
+    async fn send_transaction(
+        &self,
+        fork: &RevmFork<M>,
+        transaction: ForkTransaction,
+    ) -> Result<H256, Box<dyn std::error::Error>> {
+        let mut pending = fork.pending.lock().unwrap();
+        let index = fork.receipts.lock().unwrap().len() + pending.len();
+        let hash = H256(ethers::utils::keccak256(
+            [
+                transaction.from.as_bytes(),
+                &index.to_be_bytes()[..],
+                transaction.data.as_ref(),
+            ]
+            .concat(),
+        ));
+        pending.push((hash, transaction));
+        Ok(hash)
+    }
+
+    async fn mine(&self, fork: &RevmFork<M>) -> Result<(), Box<dyn std::error::Error>> {
+        let pending = std::mem::take(&mut *fork.pending.lock().unwrap());
+        let block = fork.block.clone();
+        let executed = fork
+            .with_state(move |state| {
+                let mut executed = Vec::new();
+                for (index, (hash, transaction)) in pending.into_iter().enumerate() {
+                    let result = execute(state, &block, &transaction)?;
+                    executed.push((
+                        hash,
+                        receipt(hash, index, block.number, &transaction.from, result),
+                    ));
+                }
+                Ok(executed)
+            })
+            .await?;
+        fork.receipts.lock().unwrap().extend(executed);
+        Ok(())
+    }
+
+    async fn transaction_receipt(
+        &self,
+        fork: &RevmFork<M>,
+        tx_hash: H256,
+    ) -> Result<Option<TransactionReceipt>, Box<dyn std::error::Error>> {
+        Ok(fork.receipts.lock().unwrap().get(&tx_hash).cloned())
+    }
+
+    async fn get_code(
+        &self,
+        fork: &RevmFork<M>,
+        address: H160,
+    ) -> Result<Bytes, Box<dyn std::error::Error>> {
+        fork.with_state(move |state| {
+            let info = match Database::basic(state, B160(address.0))? {
+                Some(info) => info,
+                None => return Ok(Bytes::default()),
+            };
+            let code = match info.code {
+                Some(code) => code,
+                None if info.code_hash == KECCAK_EMPTY => return Ok(Bytes::default()),
+                None => Database::code_by_hash(state, info.code_hash)?,
+            };
+            Ok(Bytes(code.original_bytes()))
+        })
+        .await
+    }
+
+    async fn stop(&self, _fork: RevmFork<M>) {}
+}
+
+impl<M: Middleware + 'static> RevmFork<M> {
+    /// Runs `f` on the fork state on a blocking thread, since revm reads
+    /// the upstream state synchronously.
+    async fn with_state<T, F>(&self, f: F) -> Result<T, Box<dyn std::error::Error>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut CacheDB<ForkedState<M>>) -> Result<T, String> + Send + 'static,
+    {
+        let state = self.state.clone();
+        let result = tokio::task::spawn_blocking(move || f(&mut state.lock().unwrap()))
+            .await
+            .map_err(|e| format!("Error executing on the fork: {}", e))?;
+        Ok(result?)
+    }
+}
+
+/// Executes a transaction in the fork block, committing its changes.
+fn execute<M: Middleware>(
+    state: &mut CacheDB<ForkedState<M>>,
+    block: &ForkBlock,
+    transaction: &ForkTransaction,
+) -> Result<ExecutionResult, String> {
+    let mut evm = EVM::new();
+    evm.env.cfg.chain_id = RU256::from(block.chain_id);
+    evm.env.cfg.spec_id = SpecId::LATEST;
+    evm.env.cfg.limit_contract_code_size = Some(usize::MAX);
+    evm.env.block.number = RU256::from(block.number);
+    evm.env.block.timestamp = to_revm_u256(block.timestamp);
+    evm.env.block.coinbase = B160(block.coinbase.0);
+    evm.env.block.difficulty = to_revm_u256(block.difficulty);
+    evm.env.block.prevrandao = block.prevrandao.map(|h| B256(h.0));
+    evm.env.block.basefee = RU256::ZERO;
+    evm.env.block.gas_limit = RU256::MAX;
+    evm.env.tx.caller = B160(transaction.from.0);
+    evm.env.tx.transact_to = match transaction.to {
+        Some(to) => TransactTo::Call(B160(to.0)),
+        None => TransactTo::Create(CreateScheme::Create),
+    };
+    evm.env.tx.value = to_revm_u256(transaction.value);
+    evm.env.tx.gas_limit = transaction.gas.min(U256::from(u64::MAX)).as_u64();
+    evm.env.tx.gas_price = RU256::ZERO;
+    evm.env.tx.data = transaction.data.0.clone();
+    evm.database(state);
+    evm.transact_commit()
+        .map_err(|e| format!("Error executing transaction: {:?}", e))
+}
+
+/// Builds the receipt of an executed transaction.
+fn receipt(
+    hash: H256,
+    index: usize,
+    block_number: u64,
+    from: &H160,
+    result: ExecutionResult,
+) -> TransactionReceipt {
+    let gas_used = result.gas_used();
+    let (status, contract_address, logs) = match result {
+        ExecutionResult::Success { output, logs, .. } => {
+            let contract_address = match output {
+                Output::Create(_, address) => address.map(|a| H160(a.0)),
+                Output::Call(_) => None,
+            };
+            let logs = logs
+                .into_iter()
+                .enumerate()
+                .map(|(log_index, log)| Log {
+                    address: H160(log.address.0),
+                    topics: log.topics.into_iter().map(|t| H256(t.0)).collect(),
+                    data: Bytes(log.data),
+                    block_number: Some(U64::from(block_number)),
+                    transaction_hash: Some(hash),
+                    transaction_index: Some(U64::from(index)),
+                    log_index: Some(U256::from(log_index)),
+                    ..Default::default()
+                })
+                .collect();
+            (1_u64, contract_address, logs)
+        }
+        ExecutionResult::Revert { .. } | ExecutionResult::Halt { .. } => (0, None, Vec::new()),
+    };
+    TransactionReceipt {
+        transaction_hash: hash,
+        transaction_index: U64::from(index),
+        block_number: Some(U64::from(block_number)),
+        from: *from,
+        gas_used: Some(U256::from(gas_used)),
+        contract_address,
+        logs,
+        status: Some(U64::from(status)),
+        ..Default::default()
+    }
+}
+
+/// Reads accounts, storage and block hashes from the upstream chain
+/// at the fork block.
+///
+/// revm reads state synchronously, so each read blocks on the upstream
+/// request with a handle to the runtime the fork was spawned on. The
+/// state is only used on blocking threads, see [`RevmFork::with_state`].
+pub struct ForkedState<M> {
+    provider: Arc<M>,
+    block: BlockId,
+    handle: tokio::runtime::Handle,
+}
+
+impl<M: Middleware> ForkedState<M> {
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.handle.block_on(future)
+    }
+}
+
+impl<M: Middleware> DatabaseRef for ForkedState<M> {
+    type Error = String;
+
+    fn basic(&self, address: B160) -> Result<Option<AccountInfo>, Self::Error> {
+        let address = H160(address.0);
+        let block = Some(self.block);
+        let (balance, nonce, code) = self
+            .block_on(async {
+                futures_util::try_join!(
+                    self.provider.get_balance(address, block),
+                    self.provider.get_transaction_count(address, block),
+                    self.provider.get_code(address, block),
+                )
+            })
+            .map_err(|e| e.to_string())?;
+        Ok(Some(AccountInfo {
+            balance: to_revm_u256(balance),
+            nonce: nonce.as_u64(),
+            code_hash: keccak256(&code),
+            code: Some(Bytecode::new_raw(code.0)),
+        }))
+    }
+
+    fn code_by_hash(&self, _code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // Code is always fetched with the account in `basic`
+        Err("Code must be loaded with its account".to_owned())
+    }
+
+    fn storage(&self, address: B160, index: RU256) -> Result<RU256, Self::Error> {
+        let slot = H256(index.to_be_bytes::<32>());
+        let value = self
+            .block_on(
+                self.provider
+                    .get_storage_at(H160(address.0), slot, Some(self.block)),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(RU256::from_be_bytes(value.0))
+    }
+
+    fn block_hash(&self, number: RU256) -> Result<B256, Self::Error> {
+        let number: u64 = number
+            .try_into()
+            .map_err(|_| "Block number out of range".to_owned())?;
+        let block = self
+            .block_on(self.provider.get_block(number))
+            .map_err(|e| e.to_string())?;
+        Ok(block
+            .and_then(|block| block.hash)
+            .map(|hash| B256(hash.0))
+            .unwrap_or_default())
+    }
+}
+
+fn to_revm_u256(value: U256) -> RU256 {
+    RU256::from_limbs(value.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::providers::{Http, Provider};
+    use revm::primitives::{Eval, Halt};
+
+    use super::*;
+
+    /// Forks a local chain on the current-thread runtime `#[tokio::test]`
+    /// uses, deploys a contract and calls it.
+    #[tokio::test]
+    async fn can_execute_transactions() {
+        let (_api, handle) = anvil::spawn(anvil::NodeConfig::test()).await;
+        let runner =
+            RevmForkRunner::new(Provider::<Http>::try_from(handle.http_endpoint()).unwrap());
+        let fork = runner.spawn(None).await.unwrap();
+        let from = H160::repeat_byte(1);
+        runner
+            .set_balance(&fork, from, U256::exp10(18))
+            .await
+            .unwrap();
+
+        // The contract emits an empty log: PUSH1 0 PUSH1 0 LOG0 STOP
+        let code = Bytes::from(hex::decode("60006000a000").unwrap());
+        let init_code = [
+            hex::decode("600680600b6000396000f3").unwrap(),
+            code.to_vec(),
+        ]
+        .concat();
+        let deploy = runner
+            .send_transaction(
+                &fork,
+                ForkTransaction {
+                    from,
+                    to: None,
+                    value: U256::zero(),
+                    gas: U256::from(1_000_000),
+                    data: init_code.into(),
+                },
+            )
+            .await
+            .unwrap();
+        runner.mine(&fork).await.unwrap();
+        let receipt = runner
+            .transaction_receipt(&fork, deploy)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(receipt.status, Some(U64::from(1)));
+        let contract = receipt.contract_address.unwrap();
+        assert_eq!(runner.get_code(&fork, contract).await.unwrap(), code);
+
+        let call = runner
+            .send_transaction(
+                &fork,
+                ForkTransaction {
+                    from,
+                    to: Some(contract),
+                    value: U256::zero(),
+                    gas: U256::from(100_000),
+                    data: Bytes::default(),
+                },
+            )
+            .await
+            .unwrap();
+        runner.mine(&fork).await.unwrap();
+        let receipt = runner
+            .transaction_receipt(&fork, call)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(receipt.status, Some(U64::from(1)));
+        assert_eq!(receipt.logs.len(), 1);
+        assert_eq!(receipt.logs[0].address, contract);
+
+        runner.stop(fork).await;
+        handle.node_service.abort();
+    }
+
+    #[test]
+    fn can_build_receipt() {
+        let hash = H256::repeat_byte(1);
+        let from = H160::repeat_byte(2);
+        let created = B160([3; 20]);
+
+        let success = ExecutionResult::Success {
+            reason: Eval::Return,
+            gas_used: 21000,
+            gas_refunded: 0,
+            logs: Vec::new(),
+            output: Output::Create(Default::default(), Some(created)),
+        };
+        let receipt = super::receipt(hash, 0, 100, &from, success);
+        assert_eq!(receipt.status, Some(U64::from(1)));
+        assert_eq!(receipt.contract_address, Some(H160::repeat_byte(3)));
+        assert_eq!(receipt.gas_used, Some(U256::from(21000)));
+        assert_eq!(receipt.block_number, Some(U64::from(100)));
+
+        let halt = ExecutionResult::Halt {
+            reason: Halt::OpcodeNotFound,
+            gas_used: 30000,
+        };
+        let receipt = super::receipt(hash, 0, 100, &from, halt);
+        assert_eq!(receipt.status, Some(U64::from(0)));
+        assert_eq!(receipt.contract_address, None);
+    }
+}
@@ -1,52 +1,100 @@
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
+use std::path::PathBuf;
 
 use crate::core::resources::shadow::{ShadowContract, ShadowResource};
+use crate::resources::migrations::{self, CURRENT_VERSION};
 
 /// The Shadow resource implementation that uses the local file
 /// system as the Shadow store.
 ///
 /// The Shadow contracts are stored in a file called `shadow.json`.
+/// Files in an older format are upgraded in place when read, after
+/// being copied to `shadow.json.v<version>.bak`.
 pub struct LocalShadowStore {
-    path: String,
+    path: PathBuf,
 }
 
+/// The name of the Shadow store file
+pub const SHADOW_FILE: &str = "shadow.json";
+
 impl LocalShadowStore {
-    pub fn new(path: String) -> Self {
-        LocalShadowStore { path }
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        LocalShadowStore { path: path.into() }
+    }
+
+    /// Returns the path of `shadow.json`.
+    pub fn file_path(&self) -> PathBuf {
+        self.path.join(SHADOW_FILE)
     }
 
     fn read_from_file(&self) -> Result<Vec<ShadowContract>, Box<dyn std::error::Error>> {
-        let file_path = format!("{}/shadow.json", self.path);
+        let file_path = self.file_path();
 
         // Create the shadow file if it doesn't exist
         if let Ok(mut file) = OpenOptions::new()
             .write(true)
             .create_new(true)
-            .open(file_path.clone())
+            .open(&file_path)
         {
-            file.write_all("[]".as_bytes())?;
+            let contents = serde_json::to_string(&ShadowFile {
+                version: CURRENT_VERSION,
+                contracts: Vec::new(),
+            })?;
+            file.write_all(contents.as_bytes())?;
         }
 
-        let contents = fs::read_to_string(file_path)?;
-        let contracts: Vec<ShadowContract> = serde_json::from_str(&contents)?;
-        Ok(contracts)
+        let contents = fs::read_to_string(&file_path)?;
+        let (contents, from) = migrations::migrate(serde_json::from_str(&contents)?)?;
+        let shadow_file: ShadowFile = serde_json::from_value(contents)?;
+
+        // Upgrade older files in place, keeping a backup
+        if from < CURRENT_VERSION {
+            let backup_path = self.path.join(format!("{}.v{}.bak", SHADOW_FILE, from));
+            fs::copy(&file_path, &backup_path)?;
+            log::info!(
+                "Upgraded shadow.json from version {} to {}, backup saved to {}",
+                from,
+                CURRENT_VERSION,
+                backup_path.display()
+            );
+            self.write_to_file(shadow_file.contracts.clone())?;
+        }
+        Ok(shadow_file.contracts)
     }
 
     fn write_to_file(
         &self,
         contracts: Vec<ShadowContract>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let file_path: String = format!("{}/shadow.json", self.path);
-        let contents = serde_json::to_string(&contracts)?;
+        let file_path = self.file_path();
+        let contents = serde_json::to_string(&ShadowFile {
+            version: CURRENT_VERSION,
+            contracts,
+        })?;
         let mut file = File::create(file_path)?;
         file.write_all(contents.as_bytes())?;
         Ok(())
     }
 }
 
+/// Parses the contents of a `shadow.json` file in the current format,
+/// so errors point at the line and column they occur at.
+pub fn parse_contracts(contents: &str) -> Result<Vec<ShadowContract>, serde_json::Error> {
+    let shadow_file: ShadowFile = serde_json::from_str(contents)?;
+    Ok(shadow_file.contracts)
+}
+
+/// The contents of `shadow.json`
+#[derive(Serialize, Deserialize)]
+struct ShadowFile {
+    version: u64,
+    contracts: Vec<ShadowContract>,
+}
+
 #[async_trait]
 impl ShadowResource for LocalShadowStore {
     async fn get_by_address(
@@ -117,6 +165,14 @@ impl ShadowResource for LocalShadowStore {
         self.write_to_file(contracts)?;
         Ok(())
     }
+
+    async fn replace(
+        &self,
+        contracts: Vec<ShadowContract>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_to_file(contracts)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -216,8 +272,7 @@ mod tests {
         fs::copy(test_fixture!("resources", "shadow.json"), file_path).unwrap();
 
         // Create a shadow store
-        let shadow_store =
-            super::LocalShadowStore::new(temp_dir.path().to_str().unwrap().to_string());
+        let shadow_store = super::LocalShadowStore::new(temp_dir.path());
 
         // Insert a new contract
         let contract = ShadowContract {
@@ -225,6 +280,9 @@ mod tests {
             contract_name: "Seaport".to_string(),
             address: "0x00000000000001ad428e4906ae43d8f9852d0dd6".to_string(),
             runtime_bytecode: "Seaport_dummyruntimebytecode".to_string(),
+            enabled: true,
+            tags: Vec::new(),
+            attestation: None,
         };
         shadow_store.upsert(contract.clone()).await.unwrap();
 
@@ -243,8 +301,7 @@ mod tests {
         fs::copy(test_fixture!("resources", "shadow.json"), file_path).unwrap();
 
         // Create a shadow store
-        let shadow_store =
-            super::LocalShadowStore::new(temp_dir.path().to_str().unwrap().to_string());
+        let shadow_store = super::LocalShadowStore::new(temp_dir.path());
 
         // Update a contract
         let contract = ShadowContract {
@@ -252,6 +309,9 @@ mod tests {
             contract_name: "UniswapV2Router02".to_string(),
             address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_string(),
             runtime_bytecode: "UniswapV2Router02_dummyruntimebytecode_new".to_string(),
+            enabled: true,
+            tags: Vec::new(),
+            attestation: None,
         };
         shadow_store.upsert(contract.clone()).await.unwrap();
 
@@ -273,4 +333,26 @@ mod tests {
             "UniswapV2Router02_dummyruntimebytecode_new"
         );
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_upgrade_v1_file() {
+        // Create a temp directory with a version 1 shadow.json file
+        let temp_dir = tempdir().unwrap();
+        let file_path_buf = temp_dir.path().join("shadow.json");
+        fs::copy(test_fixture!("resources", "shadow_v1.json"), &file_path_buf).unwrap();
+
+        // Create a shadow store
+        let shadow_store = super::LocalShadowStore::new(temp_dir.path());
+
+        // Check that the contracts are read and the file was upgraded
+        let contracts = shadow_store.list().await.unwrap();
+        assert_eq!(contracts.len(), 2);
+        let contents: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&file_path_buf).unwrap()).unwrap();
+        assert_eq!(contents["version"], super::CURRENT_VERSION);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("shadow.json.v1.bak")).unwrap(),
+            fs::read_to_string(test_fixture!("resources", "shadow_v1.json")).unwrap()
+        );
+    }
 }
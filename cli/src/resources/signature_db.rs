@@ -0,0 +1,273 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::core::resources::signatures::SignaturesResource;
+
+/// A Signatures resource that queries several signature databases in
+/// order and caches what they return on disk.
+///
+/// The first database with a match wins. Lookups that no database
+/// matches are cached too, for [`UNKNOWN_SIGNATURE_TTL`] by default, so
+/// unknown events are looked up again once the databases may have
+/// learned them. Lookups that fail aren't cached.
+///
+/// The cache is stored in `<cache dir>/shadow/signatures.json` by default.
+pub struct SignatureDatabase {
+    sources: Vec<Box<dyn SignaturesResource + Send + Sync>>,
+    cache_path: Option<PathBuf>,
+    cache: Mutex<BTreeMap<String, CacheEntry>>,
+    unknown_ttl: Duration,
+}
+
+/// How long a lookup that no database matched is cached for
+pub const UNKNOWN_SIGNATURE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A cached lookup: the signatures found, or when the databases last
+/// had none.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum CacheEntry {
+    Found(Vec<String>),
+    Unknown {
+        #[serde(rename = "unknownSince")]
+        unknown_since: u64,
+    },
+}
+
+impl SignatureDatabase {
+    /// Creates a signature database over the given sources, caching to
+    /// `cache_path` if set.
+    pub fn new(
+        sources: Vec<Box<dyn SignaturesResource + Send + Sync>>,
+        cache_path: Option<PathBuf>,
+    ) -> Self {
+        let cache = cache_path.as_deref().map(read_cache).unwrap_or_default();
+        SignatureDatabase {
+            sources,
+            cache_path,
+            cache: Mutex::new(cache),
+            unknown_ttl: UNKNOWN_SIGNATURE_TTL,
+        }
+    }
+
+    /// Sets how long lookups that no database matched are cached for.
+    pub fn with_unknown_ttl(mut self, unknown_ttl: Duration) -> Self {
+        self.unknown_ttl = unknown_ttl;
+        self
+    }
+
+    /// Returns the default location of the cache file
+    pub fn default_cache_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|cache| cache.join("shadow").join("signatures.json"))
+    }
+
+    /// Returns the cached signatures of an event, if they are still
+    /// fresh.
+    fn cached(&self, topic0: &str) -> Option<Vec<String>> {
+        match self.cache.lock().unwrap().get(topic0)? {
+            // Caches written before unknown lookups expired have no
+            // timestamp, so look them up again
+            CacheEntry::Found(signatures) if signatures.is_empty() => None,
+            CacheEntry::Found(signatures) => Some(signatures.clone()),
+            CacheEntry::Unknown { unknown_since } => {
+                let age = now().saturating_sub(*unknown_since);
+                (age < self.unknown_ttl.as_secs()).then(Vec::new)
+            }
+        }
+    }
+
+    fn insert(&self, topic0: &str, signatures: &[String]) {
+        let entry = match signatures.is_empty() {
+            true => CacheEntry::Unknown {
+                unknown_since: now(),
+            },
+            false => CacheEntry::Found(signatures.to_vec()),
+        };
+        let mut cache = self.cache.lock().unwrap();
+        cache.insert(topic0.to_owned(), entry);
+        if let Some(path) = &self.cache_path {
+            if let Err(e) = write_cache(path, &cache) {
+                log::warn!("Error writing signature cache {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SignaturesResource for SignatureDatabase {
+    async fn lookup_event(&self, topic0: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let topic0 = topic0.to_lowercase();
+        if let Some(signatures) = self.cached(&topic0) {
+            return Ok(signatures);
+        }
+
+        let mut last_error = None;
+        for source in &self.sources {
+            match source.lookup_event(&topic0).await {
+                Ok(signatures) if !signatures.is_empty() => {
+                    self.insert(&topic0, &signatures);
+                    return Ok(signatures);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log::warn!("Error looking up event {}: {}", topic0, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        if let Some(e) = last_error {
+            return Err(e);
+        }
+        self.insert(&topic0, &[]);
+        Ok(vec![])
+    }
+}
+
+/// Returns the current time in seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Reads the cache file, starting afresh if it's missing or unreadable.
+fn read_cache(path: &Path) -> BTreeMap<String, CacheEntry> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return BTreeMap::new();
+    };
+    serde_json::from_str(&contents).unwrap_or_else(|e| {
+        log::warn!("Ignoring invalid signature cache {}: {}", path.display(), e);
+        BTreeMap::new()
+    })
+}
+
+/// Writes the cache file through a temporary file, so an interrupted
+/// write doesn't leave it truncated.
+fn write_cache(
+    path: &Path,
+    cache: &BTreeMap<String, CacheEntry>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(cache)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const TRANSFER: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+    /// A signature database that returns fixed results and counts lookups
+    struct MockSignatures {
+        result: Option<Vec<String>>,
+        lookups: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl SignaturesResource for MockSignatures {
+        async fn lookup_event(
+            &self,
+            _topic0: &str,
+        ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            self.result.clone().ok_or_else(|| "unavailable".into())
+        }
+    }
+
+    fn mock(result: Option<Vec<String>>) -> (Box<MockSignatures>, Arc<AtomicUsize>) {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let source = Box::new(MockSignatures {
+            result,
+            lookups: lookups.clone(),
+        });
+        (source, lookups)
+    }
+
+    #[tokio::test]
+    async fn falls_back_and_caches_lookups() {
+        let temp_dir = tempdir().unwrap();
+        let cache_path = temp_dir.path().join("shadow").join("signatures.json");
+        let (failing, failing_lookups) = mock(None);
+        let (empty, _) = mock(Some(vec![]));
+        let (found, found_lookups) =
+            mock(Some(vec!["Transfer(address,address,uint256)".to_owned()]));
+        let database =
+            SignatureDatabase::new(vec![failing, empty, found], Some(cache_path.clone()));
+
+        let signatures = database.lookup_event(TRANSFER).await.unwrap();
+        assert_eq!(signatures, vec!["Transfer(address,address,uint256)"]);
+        database.lookup_event(TRANSFER).await.unwrap();
+        assert_eq!(failing_lookups.load(Ordering::SeqCst), 1);
+        assert_eq!(found_lookups.load(Ordering::SeqCst), 1);
+
+        // The cache survives a restart
+        let (source, lookups) = mock(Some(vec![]));
+        let database = SignatureDatabase::new(vec![source], Some(cache_path));
+        let signatures = database.lookup_event(TRANSFER).await.unwrap();
+        assert_eq!(signatures, vec!["Transfer(address,address,uint256)"]);
+        assert_eq!(lookups.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_failed_lookups() {
+        let (failing, lookups) = mock(None);
+        let database = SignatureDatabase::new(vec![failing], None);
+        assert!(database.lookup_event(TRANSFER).await.is_err());
+        assert!(database.lookup_event(TRANSFER).await.is_err());
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+        let (empty, lookups) = mock(Some(vec![]));
+        let database = SignatureDatabase::new(vec![empty], None);
+        assert!(database.lookup_event(TRANSFER).await.unwrap().is_empty());
+        assert!(database.lookup_event(TRANSFER).await.unwrap().is_empty());
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn looks_up_unknown_events_again_once_expired() {
+        let temp_dir = tempdir().unwrap();
+        let cache_path = temp_dir.path().join("signatures.json");
+        let (empty, lookups) = mock(Some(vec![]));
+        let database = SignatureDatabase::new(vec![empty], Some(cache_path.clone()))
+            .with_unknown_ttl(Duration::ZERO);
+        assert!(database.lookup_event(TRANSFER).await.unwrap().is_empty());
+        assert!(database.lookup_event(TRANSFER).await.unwrap().is_empty());
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+        assert!(!cache_path.with_extension("json.tmp").exists());
+
+        // The unknown lookup is kept on disk, until it expires
+        let (empty, lookups) = mock(Some(vec![]));
+        let database = SignatureDatabase::new(vec![empty], Some(cache_path.clone()));
+        assert!(database.lookup_event(TRANSFER).await.unwrap().is_empty());
+        assert_eq!(lookups.load(Ordering::SeqCst), 0);
+
+        // Caches from before unknown lookups expired are looked up again
+        fs::write(&cache_path, format!(r#"{{ "{}": [] }}"#, TRANSFER)).unwrap();
+        let (found, lookups) = mock(Some(vec!["Transfer(address,address,uint256)".to_owned()]));
+        let database = SignatureDatabase::new(vec![found], Some(cache_path));
+        let signatures = database.lookup_event(TRANSFER).await.unwrap();
+        assert_eq!(signatures, vec!["Transfer(address,address,uint256)"]);
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    }
+}
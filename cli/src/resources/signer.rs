@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use ethers::signers::LocalWallet;
+
+use crate::{config::SignerConfig, resources::credentials};
+
+/// The environment variable holding the private key of the signer
+pub const PRIVATE_KEY_ENV: &str = "SHADOW_PRIVATE_KEY";
+
+/// The environment variable holding the password of the signer's keystore
+pub const KEYSTORE_PASSWORD_ENV: &str = "SHADOW_KEYSTORE_PASSWORD";
+
+/// Loads the local signer of the post-deploy transactions.
+///
+/// The private key is read, in order, from the explicitly provided value
+/// (e.g. [`PRIVATE_KEY_ENV`]), the keystore set in the config, and the
+/// `signer` credential. Returns `None` if no signer is set up.
+pub fn load(
+    explicit: Option<String>,
+    config: &SignerConfig,
+) -> Result<Option<LocalWallet>, Box<dyn std::error::Error>> {
+    if explicit.is_none() {
+        if let Some(keystore) = &config.keystore {
+            return decrypt_keystore(Path::new(keystore)).map(Some);
+        }
+    }
+    let Some(private_key) = credentials::resolve(credentials::SIGNER, explicit)? else {
+        return Ok(None);
+    };
+    let wallet = private_key
+        .trim()
+        .parse::<LocalWallet>()
+        .map_err(|e| format!("Invalid signer private key: {}", e))?;
+    Ok(Some(wallet))
+}
+
+/// Decrypts a JSON keystore, with the password in
+/// [`KEYSTORE_PASSWORD_ENV`] or prompted for.
+fn decrypt_keystore(path: &Path) -> Result<LocalWallet, Box<dyn std::error::Error>> {
+    let password = match std::env::var(KEYSTORE_PASSWORD_ENV) {
+        Ok(password) => password,
+        Err(_) => rpassword::prompt_password(format!(
+            "Enter the password of the keystore {}: ",
+            path.display()
+        ))?,
+    };
+    LocalWallet::decrypt_keystore(path, password)
+        .map_err(|e| format!("Error decrypting keystore {}: {}", path.display(), e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::signers::Signer;
+
+    use super::*;
+
+    #[test]
+    fn can_load_private_key() {
+        // The first anvil dev account
+        let private_key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let wallet = load(Some(private_key.to_owned()), &SignerConfig::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            format!("{:?}", wallet.address()),
+            "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266"
+        );
+
+        assert!(load(Some("0x1234".to_owned()), &SignerConfig::default()).is_err());
+    }
+}
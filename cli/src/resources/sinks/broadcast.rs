@@ -0,0 +1,35 @@
+use async_trait::async_trait;
+use tokio::sync::broadcast;
+
+use crate::core::resources::sink::{DecodedEvent, EventSink};
+
+/// A sink that fans decoded events out to in-process subscribers,
+/// such as the clients of `shadow serve`.
+///
+/// Slow subscribers that fall more than `capacity` events behind
+/// miss the oldest events rather than blocking the sink.
+#[derive(Clone)]
+pub struct BroadcastSink {
+    sender: broadcast::Sender<DecodedEvent>,
+}
+
+impl BroadcastSink {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        BroadcastSink { sender }
+    }
+
+    /// Returns a receiver for all the events written from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<DecodedEvent> {
+        self.sender.subscribe()
+    }
+}
+
+#[async_trait]
+impl EventSink for BroadcastSink {
+    async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        // Sending only fails when there are no subscribers, which is fine
+        let _ = self.sender.send(event.clone());
+        Ok(())
+    }
+}
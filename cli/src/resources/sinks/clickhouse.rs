@@ -0,0 +1,321 @@
+use std::{sync::Arc, time::Duration};
+
+use alloy_json_abi::Event;
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+use tokio::sync::Mutex;
+
+use crate::{
+    core::resources::sink::{DecodedEvent, EventSink},
+    decode::event::{is_hashed_topic, topic_hash_key, ERRORS_KEY},
+};
+
+/// A sink that inserts decoded events into ClickHouse over its
+/// HTTP interface.
+///
+/// Every event gets its own table, named `<contract>_<event>`, with
+/// the log metadata followed by one typed column per event parameter.
+/// Rows are buffered and inserted in batches, once the batch is full,
+/// the flush interval elapses, or the listener stops.
+pub struct ClickHouseSink {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    client: reqwest::Client,
+    url: String,
+    table: String,
+    batch_size: usize,
+    rows: Mutex<Vec<Value>>,
+}
+
+impl ClickHouseSink {
+    /// Creates the event's table if it doesn't exist, and starts
+    /// flushing buffered rows every `flush_interval`.
+    pub async fn new(
+        url: String,
+        database: &str,
+        contract_name: &str,
+        event: &Event,
+        batch_size: usize,
+        flush_interval: Duration,
+        client: reqwest::Client,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let table = format!(
+            "{}.{}",
+            identifier(database),
+            identifier(&format!("{}_{}", contract_name, event.name))
+        );
+        let inner = Arc::new(Inner {
+            client,
+            url,
+            table,
+            batch_size,
+            rows: Mutex::new(Vec::new()),
+        });
+        inner
+            .query(format!(
+                "CREATE DATABASE IF NOT EXISTS {}",
+                identifier(database)
+            ))
+            .await?;
+        inner.query(create_table(&inner.table, event)).await?;
+
+        // Flush partial batches in the background
+        let weak = Arc::downgrade(&inner);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(flush_interval);
+            loop {
+                interval.tick().await;
+                let Some(inner) = weak.upgrade() else {
+                    return;
+                };
+                if let Err(e) = inner.flush().await {
+                    log::warn!("Error flushing events to ClickHouse: {}", e);
+                }
+            }
+        });
+
+        Ok(ClickHouseSink { inner })
+    }
+
+    /// Adds the raw log columns to the event's table, for events
+    /// that include the raw log.
+    pub async fn with_raw_columns(self) -> Result<Self, Box<dyn std::error::Error>> {
+        self.inner
+            .query(format!(
+                "ALTER TABLE {} ADD COLUMN IF NOT EXISTS raw_topics Array(String), \
+                 ADD COLUMN IF NOT EXISTS raw_data String",
+                self.inner.table
+            ))
+            .await?;
+        Ok(self)
+    }
+}
+
+impl Inner {
+    async fn query(&self, query: String) -> Result<(), Box<dyn std::error::Error>> {
+        let response = self.client.post(&self.url).body(query).send().await?;
+        if !response.status().is_success() {
+            return Err(format!("ClickHouse error: {}", response.text().await?).into());
+        }
+        Ok(())
+    }
+
+    /// Inserts the buffered rows.
+    ///
+    /// The rows are taken out of the buffer before they are sent, so
+    /// events keep being buffered during the insert. If it fails, the
+    /// rows are put back and retried on the next flush.
+    async fn flush(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let batch = std::mem::take(&mut *self.rows.lock().await);
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let mut body = format!("INSERT INTO {} FORMAT JSONEachRow\n", self.table);
+        for row in &batch {
+            body.push_str(&row.to_string());
+            body.push('\n');
+        }
+        let error = match self.query(body).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e.to_string(),
+        };
+        let mut rows = self.rows.lock().await;
+        let newer = std::mem::replace(&mut *rows, batch);
+        rows.extend(newer);
+        Err(error.into())
+    }
+}
+
+#[async_trait]
+impl EventSink for ClickHouseSink {
+    async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let full = {
+            let mut rows = self.inner.rows.lock().await;
+            rows.push(row(event));
+            rows.len() >= self.inner.batch_size
+        };
+        if full {
+            self.inner.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.inner.flush().await
+    }
+}
+
+/// Builds the `CREATE TABLE` statement for an event.
+fn create_table(table: &str, event: &Event) -> String {
+    let mut columns = vec![
+        "block_number UInt64".to_owned(),
+        "block_timestamp Nullable(DateTime)".to_owned(),
+        "transaction_hash String".to_owned(),
+        "log_index UInt32".to_owned(),
+        "address String".to_owned(),
+    ];
+    for input in event.inputs.iter().filter(|input| !input.name.is_empty()) {
+        if input.indexed && is_hashed_topic(input) {
+            columns.push(format!(
+                "{} String",
+                identifier(&topic_hash_key(&input.name))
+            ));
+            continue;
+        }
+        columns.push(format!(
+            "{} {}",
+            identifier(&input.name),
+            column_type(&input.ty)
+        ));
+    }
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} ({}) ENGINE = MergeTree ORDER BY (block_number, log_index)",
+        table,
+        columns.join(", ")
+    )
+}
+
+/// Maps a Solidity type to a ClickHouse column type.
+///
+/// Arrays and tuples are stored as JSON strings.
+fn column_type(ty: &str) -> String {
+    if ty.ends_with(']') || ty.starts_with("tuple") {
+        return "String".to_owned();
+    }
+    let sized = |prefix: &str, signed: &str| {
+        let bits: u32 = ty[prefix.len()..].parse().unwrap_or(256);
+        let bits = [8, 16, 32, 64, 128, 256]
+            .into_iter()
+            .find(|size| *size >= bits)
+            .unwrap_or(256);
+        format!("{}{}", signed, bits)
+    };
+    match ty {
+        "bool" => "Bool".to_owned(),
+        _ if ty.starts_with("uint") => sized("uint", "UInt"),
+        _ if ty.starts_with("int") => sized("int", "Int"),
+        _ => "String".to_owned(),
+    }
+}
+
+/// Builds the JSONEachRow row for an event.
+fn row(event: &DecodedEvent) -> Value {
+    let mut row = Map::new();
+    row.insert("block_number".to_owned(), json!(event.block_number));
+    row.insert("block_timestamp".to_owned(), json!(event.block_timestamp));
+    row.insert("transaction_hash".to_owned(), json!(event.transaction_hash));
+    row.insert("log_index".to_owned(), json!(event.log_index));
+    row.insert("address".to_owned(), json!(event.address));
+    if let Value::Object(data) = &event.data {
+        for (name, value) in data {
+            // The table has no column for the errors of partially decoded
+            // events, which are logged by the events action instead
+            if name == ERRORS_KEY {
+                continue;
+            }
+            let value = match value {
+                Value::Array(_) | Value::Object(_) => Value::String(value.to_string()),
+                value => value.clone(),
+            };
+            row.insert(name.clone(), value);
+        }
+    }
+    if let Some(raw) = &event.raw {
+        row.insert("raw_topics".to_owned(), json!(raw.topics));
+        row.insert("raw_data".to_owned(), json!(raw.data));
+    }
+    Value::Object(row)
+}
+
+/// Quotes an identifier with backticks.
+fn identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "\\`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ethers::types::{Bytes, Log, H160, H256, U256, U64};
+
+    use crate::core::resources::sink::RawLog;
+
+    use super::*;
+
+    #[test]
+    fn can_map_column_types() {
+        assert_eq!(column_type("uint256"), "UInt256");
+        assert_eq!(column_type("uint40"), "UInt64");
+        assert_eq!(column_type("int24"), "Int32");
+        assert_eq!(column_type("uint"), "UInt256");
+        assert_eq!(column_type("bool"), "Bool");
+        assert_eq!(column_type("address"), "String");
+        assert_eq!(column_type("uint256[]"), "String");
+        assert_eq!(column_type("tuple"), "String");
+    }
+
+    #[test]
+    fn can_build_create_table() {
+        let event: Event = serde_json::from_value(json!({
+            "type": "event",
+            "name": "Transfer",
+            "anonymous": false,
+            "inputs": [
+                { "name": "from", "type": "address", "indexed": true },
+                { "name": "to", "type": "address", "indexed": true },
+                { "name": "value", "type": "uint256", "indexed": false }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            create_table("`shadow`.`WETH9_Transfer`", &event),
+            "CREATE TABLE IF NOT EXISTS `shadow`.`WETH9_Transfer` (block_number UInt64, \
+             block_timestamp Nullable(DateTime), transaction_hash String, log_index UInt32, \
+             address String, `from` String, `to` String, `value` UInt256) \
+             ENGINE = MergeTree ORDER BY (block_number, log_index)"
+        );
+    }
+
+    #[test]
+    fn can_build_row_with_raw_log() {
+        let log = Log {
+            address: H160::from_str("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2").unwrap(),
+            topics: vec![H256::from_str(
+                "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+            )
+            .unwrap()],
+            data: Bytes::from_str("0x2a").unwrap(),
+            block_number: Some(U64::from(17_000_000)),
+            log_index: Some(U256::from(3)),
+            ..Default::default()
+        };
+        let mut event = DecodedEvent {
+            block_number: 17_000_000,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 3,
+            address: "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".to_owned(),
+            contract: "WETH9.sol:WETH9".to_owned(),
+            event: "Transfer".to_owned(),
+            signature: "Transfer(address,address,uint256)".to_owned(),
+            schema: None,
+            data: json!({ "value": "42" }),
+            raw: None,
+            ..Default::default()
+        };
+        assert!(row(&event).get("raw_data").is_none());
+
+        event.raw = Some(RawLog::from(&log));
+        assert_eq!(event.raw.as_ref().unwrap().block_number, 17_000_000);
+        assert_eq!(event.raw.as_ref().unwrap().log_index, 3);
+        let row = row(&event);
+        assert_eq!(
+            row["raw_topics"],
+            json!(["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"])
+        );
+        assert_eq!(row["raw_data"], json!("0x2a"));
+        assert_eq!(row["value"], json!("42"));
+    }
+}
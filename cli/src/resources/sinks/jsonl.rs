@@ -0,0 +1,394 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::core::resources::{
+    event_store::{EventQuery, EventStore},
+    sink::{DecodedEvent, EventSink},
+};
+
+/// When and how the archive file of a [`JsonlSink`] is rotated.
+///
+/// Rotated files are named `<path>.<unix millis>-<counter>`, with a
+/// `.gz` suffix when compressed, and live next to the archive file. The
+/// counter tells apart files rotated within the same millisecond.
+#[derive(Clone, Debug, Default)]
+pub struct RotationPolicy {
+    /// Rotate once the file reaches this many bytes
+    pub max_bytes: Option<u64>,
+    /// Rotate once the file has been open for this long
+    pub max_age: Option<Duration>,
+    /// Gzip rotated files
+    pub compress: bool,
+    /// Keep at most this many rotated files, deleting the oldest
+    pub retain: Option<usize>,
+}
+
+impl RotationPolicy {
+    fn is_enabled(&self) -> bool {
+        self.max_bytes.is_some() || self.max_age.is_some()
+    }
+}
+
+/// A sink that appends decoded events to a file, one JSON object per line.
+///
+/// The file doubles as the local event archive read by `shadow stats`.
+/// Writing and rotating are blocking file IO, so they run on tokio's
+/// blocking threads.
+pub struct JsonlSink {
+    path: PathBuf,
+    rotation: RotationPolicy,
+    state: Arc<Mutex<FileState>>,
+}
+
+/// The currently open archive file.
+struct FileState {
+    file: File,
+    size: u64,
+    opened_at: Instant,
+}
+
+impl FileState {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(FileState {
+            file,
+            size,
+            opened_at: Instant::now(),
+        })
+    }
+}
+
+impl JsonlSink {
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.into();
+        let state = FileState::open(&path)?;
+        Ok(JsonlSink {
+            path,
+            rotation: RotationPolicy::default(),
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    /// Sets the rotation policy of the sink.
+    pub fn with_rotation(mut self, rotation: RotationPolicy) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl RotationPolicy {
+    fn should_rotate(&self, state: &FileState) -> bool {
+        if state.size == 0 {
+            return false;
+        }
+        let too_big = matches!(self.max_bytes, Some(max) if state.size >= max);
+        let too_old = matches!(self.max_age, Some(max) if state.opened_at.elapsed() >= max);
+        too_big || too_old
+    }
+
+    /// Moves the current file at `path` aside, compresses it if
+    /// configured, applies the retention policy, and opens a fresh file.
+    fn rotate(&self, path: &Path, state: &mut FileState) -> io::Result<()> {
+        state.file.flush()?;
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let mut counter = 0;
+        let rotated = loop {
+            let rotated = with_suffix(path, &format!("{}-{}", millis, counter));
+            if !rotated.exists() && !with_suffix(&rotated, "gz").exists() {
+                break rotated;
+            }
+            counter += 1;
+        };
+        fs::rename(path, &rotated)?;
+        if self.compress {
+            compress_file(&rotated)?;
+        }
+        *state = FileState::open(path)?;
+
+        if let Some(retain) = self.retain {
+            let rotated = rotated_files(path)?;
+            let excess = rotated.len().saturating_sub(retain);
+            for path in &rotated[..excess] {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for JsonlSink {
+    async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        let path = self.path.clone();
+        let rotation = self.rotation.clone();
+        let state = self.state.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut state = state.lock().unwrap();
+            if rotation.is_enabled() && rotation.should_rotate(&state) {
+                rotation.rotate(&path, &mut state)?;
+            }
+            state.file.write_all(line.as_bytes())?;
+            state.size += line.len() as u64;
+            Ok::<_, io::Error>(())
+        })
+        .await??;
+        Ok(())
+    }
+}
+
+/// The archive can be queried as an event store, reading the rotated
+/// files along with the current one.
+#[async_trait]
+impl EventStore for JsonlSink {
+    async fn insert(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        self.write(event).await
+    }
+
+    async fn query(
+        &self,
+        query: &EventQuery,
+    ) -> Result<Vec<DecodedEvent>, Box<dyn std::error::Error>> {
+        // Reading the archive is blocking file IO
+        let path = self.path.clone();
+        let events =
+            tokio::task::spawn_blocking(move || read_events(&path).map_err(|e| e.to_string()))
+                .await??;
+        let mut events: Vec<DecodedEvent> =
+            events.into_iter().filter(|e| query.matches(e)).collect();
+        events.sort_by_key(|e| (e.block_number, e.log_index));
+        Ok(events)
+    }
+}
+
+/// Appends `.<suffix>` to the file name of a path, e.g. `events.jsonl`
+/// to `events.jsonl.gz`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Gzips a file in place, replacing it with `<path>.gz`.
+fn compress_file(path: &Path) -> io::Result<()> {
+    let mut input = File::open(path)?;
+    let output = File::create(with_suffix(path, "gz"))?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)
+}
+
+/// Lists the rotated files of an archive, oldest first.
+///
+/// Files rotated before the counter was added to their name, named
+/// `<path>.<unix millis>`, are listed too.
+pub fn rotated_files(path: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let archive = path.as_ref();
+    let dir = match archive.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let prefix = match archive.file_name() {
+        Some(name) => format!("{}.", name.to_string_lossy()),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut rotated: Vec<((u128, u64), PathBuf)> = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(suffix) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        if let Some(order) = rotation_order(suffix.trim_end_matches(".gz")) {
+            rotated.push((order, entry.path()));
+        }
+    }
+    rotated.sort();
+    Ok(rotated.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Parses the `<unix millis>-<counter>` suffix of a rotated file, or
+/// the `<unix millis>` of older ones.
+fn rotation_order(suffix: &str) -> Option<(u128, u64)> {
+    match suffix.split_once('-') {
+        Some((millis, counter)) => Some((millis.parse().ok()?, counter.parse().ok()?)),
+        None => Some((suffix.parse().ok()?, 0)),
+    }
+}
+
+/// Reads all the decoded events from a JSONL archive.
+///
+/// Rotated files are read first, oldest first, followed by the
+/// current file. Gzipped rotated files are decompressed on the fly.
+pub fn read_events(
+    path: impl AsRef<Path>,
+) -> Result<Vec<DecodedEvent>, Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let mut events = Vec::new();
+    for rotated in rotated_files(path)? {
+        read_file(&rotated, &mut events)?;
+    }
+    read_file(path, &mut events)?;
+    Ok(events)
+}
+
+fn read_file(
+    path: &Path,
+    events: &mut Vec<DecodedEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = if path.extension().map_or(false, |ext| ext == "gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = serde_json::from_str(&line).map_err(|e| {
+            format!(
+                "Invalid event on line {} of {}: {}",
+                i + 1,
+                path.display(),
+                e
+            )
+        })?;
+        events.push(event);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::core::resources::sink::Correlation;
+
+    fn event(block_number: u64) -> DecodedEvent {
+        DecodedEvent {
+            block_number,
+            block_timestamp: Some(1_681_338_455),
+            transaction_hash: "0x01".to_owned(),
+            log_index: 3,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            event: "Trade".to_owned(),
+            signature: "Trade(address,uint256)".to_owned(),
+            schema: None,
+            data: json!({ "amount": "42" }),
+            raw: None,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_write_and_read_events() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("events.jsonl");
+        let sink = JsonlSink::new(&path).unwrap();
+
+        let mut event = event(17_000_000);
+        event.correlation = Some(Correlation {
+            transaction_hash: "0x01".to_owned(),
+            transaction_index: 12,
+            ordinal: 1,
+        });
+        sink.write(&event).await.unwrap();
+        sink.write(&event).await.unwrap();
+
+        let events = read_events(sink.path()).unwrap();
+        assert_eq!(events, vec![event.clone(), event]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_query_archive() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("events.jsonl");
+        let sink = JsonlSink::new(&path).unwrap();
+        for block_number in [3, 1, 2] {
+            sink.insert(&event(block_number)).await.unwrap();
+        }
+
+        let query = EventQuery {
+            from_block: Some(2),
+            ..Default::default()
+        };
+        let blocks: Vec<u64> = sink
+            .query(&query)
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.block_number)
+            .collect();
+        assert_eq!(blocks, vec![2, 3]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn can_rotate_compress_and_retain() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("events.jsonl");
+        let sink = JsonlSink::new(&path)
+            .unwrap()
+            .with_rotation(RotationPolicy {
+                max_bytes: Some(1),
+                max_age: None,
+                compress: true,
+                retain: Some(2),
+            });
+
+        // Every write after the first rotates the previous file, several
+        // times within the same millisecond
+        for block_number in 0..4 {
+            sink.write(&event(block_number)).await.unwrap();
+        }
+
+        let rotated = rotated_files(sink.path()).unwrap();
+        assert_eq!(rotated.len(), 2);
+        assert!(rotated.iter().all(|path| path.extension().unwrap() == "gz"));
+
+        let blocks: Vec<u64> = read_events(sink.path())
+            .unwrap()
+            .iter()
+            .map(|event| event.block_number)
+            .collect();
+        assert_eq!(blocks, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn can_order_rotated_files() {
+        assert_eq!(
+            rotation_order("1700000000000-2"),
+            Some((1_700_000_000_000, 2))
+        );
+        assert_eq!(
+            rotation_order("1700000000000"),
+            Some((1_700_000_000_000, 0))
+        );
+        assert_eq!(rotation_order("backup"), None);
+    }
+}
@@ -0,0 +1,23 @@
+pub mod broadcast;
+pub mod clickhouse;
+pub mod jsonl;
+#[cfg(feature = "nats")]
+pub mod nats;
+pub mod notifier;
+#[cfg(feature = "archive")]
+pub mod object_store;
+#[cfg(feature = "redis")]
+pub mod redis;
+pub mod sampled;
+
+#[cfg(feature = "archive")]
+pub use self::object_store::ObjectStoreSink;
+#[cfg(feature = "redis")]
+pub use self::redis::RedisSink;
+pub use broadcast::BroadcastSink;
+pub use clickhouse::ClickHouseSink;
+pub use jsonl::{JsonlSink, RotationPolicy};
+#[cfg(feature = "nats")]
+pub use nats::{NatsSink, NatsStreamConfig};
+pub use notifier::NotifierSink;
+pub use sampled::SampledSink;
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use async_nats::jetstream::{self, stream::StorageType};
+use async_trait::async_trait;
+
+use crate::core::resources::sink::{DecodedEvent, EventSink};
+
+/// A sink that publishes decoded events to a NATS JetStream stream.
+///
+/// Each event is published on the subject `<prefix>.<contract>.<event>`,
+/// e.g. `shadow.UniswapV2Router02.Trade`, and the stream captures
+/// every subject under the prefix.
+pub struct NatsSink {
+    context: jetstream::Context,
+    subject_prefix: String,
+}
+
+/// The durable stream configuration of a [`NatsSink`].
+#[derive(Clone, Debug)]
+pub struct NatsStreamConfig {
+    /// The name of the JetStream stream
+    pub name: String,
+    /// The subject prefix events are published under
+    pub subject_prefix: String,
+    /// Discard events older than this
+    pub max_age: Option<Duration>,
+    /// Keep the stream in memory instead of on disk
+    pub memory: bool,
+}
+
+impl NatsSink {
+    /// Connects to NATS and creates the stream if it doesn't exist.
+    pub async fn connect(
+        url: &str,
+        config: NatsStreamConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = async_nats::connect(url).await?;
+        let context = jetstream::new(client);
+        context
+            .get_or_create_stream(jetstream::stream::Config {
+                name: config.name,
+                subjects: vec![format!("{}.>", config.subject_prefix)],
+                max_age: config.max_age.unwrap_or_default(),
+                storage: if config.memory {
+                    StorageType::Memory
+                } else {
+                    StorageType::File
+                },
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| format!("Error creating NATS stream: {}", e))?;
+        Ok(NatsSink {
+            context,
+            subject_prefix: config.subject_prefix,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for NatsSink {
+    async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let subject = subject(&self.subject_prefix, event);
+        let payload = serde_json::to_vec(event)?;
+        // Wait for the stream to acknowledge the event
+        self.context
+            .publish(subject, payload.into())
+            .await
+            .map_err(|e| format!("Error publishing to NATS: {}", e))?
+            .await
+            .map_err(|e| format!("NATS did not acknowledge event: {}", e))?;
+        Ok(())
+    }
+}
+
+/// Builds the subject an event is published on.
+///
+/// The contract name is used without its file name, and characters
+/// that are reserved in NATS subjects are replaced with underscores.
+fn subject(prefix: &str, event: &DecodedEvent) -> String {
+    let contract = event.contract.rsplit(':').next().unwrap_or(&event.contract);
+    format!("{}.{}.{}", prefix, token(contract), token(&event.event))
+}
+
+fn token(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '.' | '*' | '>' | ' ' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn can_build_subject() {
+        let event = DecodedEvent {
+            block_number: 17_000_000,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 0,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            event: "Trade".to_owned(),
+            signature: "Trade(address,uint256)".to_owned(),
+            schema: None,
+            data: json!({}),
+            raw: None,
+            ..Default::default()
+        };
+        assert_eq!(subject("shadow", &event), "shadow.UniswapV2Router02.Trade");
+    }
+}
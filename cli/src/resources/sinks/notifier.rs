@@ -0,0 +1,110 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::{
+    config::{AlertRule, NotifierConfig, NotifierKind},
+    core::resources::sink::{DecodedEvent, EventSink},
+    render::{render_template, Renderer},
+    resources::credentials::resolve_secret,
+};
+
+/// The default Telegram Bot API URL
+const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+
+/// The template used when neither the notifier nor the event's
+/// signature has one
+const DEFAULT_TEMPLATE: &str = "{event} on {contract} in {transactionHash}";
+
+/// A sink that posts a rendered message to a chat for every
+/// event matching its alert rule.
+pub struct NotifierSink {
+    target: Target,
+    template: Option<String>,
+    renderer: Renderer,
+    rule: AlertRule,
+    client: reqwest::Client,
+}
+
+/// Where notifications are posted
+enum Target {
+    Discord { webhook_url: String },
+    Slack { webhook_url: String },
+    Telegram { bot_token: String, chat_id: String },
+}
+
+impl NotifierSink {
+    /// Builds the notifier, resolving its webhook URL or bot token from
+    /// the environment or the credentials store when they reference one.
+    pub fn from_config(
+        config: &NotifierConfig,
+        renderer: Renderer,
+        client: reqwest::Client,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let webhook_url = || {
+            resolve_secret(
+                config
+                    .webhook_url
+                    .as_deref()
+                    .ok_or("Discord and Slack notifiers require a webhookUrl")?,
+            )
+        };
+        let target = match config.kind {
+            NotifierKind::Discord => Target::Discord {
+                webhook_url: webhook_url()?,
+            },
+            NotifierKind::Slack => Target::Slack {
+                webhook_url: webhook_url()?,
+            },
+            NotifierKind::Telegram => Target::Telegram {
+                bot_token: resolve_secret(
+                    config
+                        .bot_token
+                        .as_deref()
+                        .ok_or("Telegram notifiers require a botToken")?,
+                )?,
+                chat_id: config
+                    .chat_id
+                    .clone()
+                    .ok_or("Telegram notifiers require a chatId")?,
+            },
+        };
+        Ok(NotifierSink {
+            target,
+            template: config.template.clone(),
+            renderer,
+            rule: config.rule.clone(),
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for NotifierSink {
+    async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.rule.matches(event) {
+            return Ok(());
+        }
+        let template = self
+            .template
+            .as_deref()
+            .or_else(|| self.renderer.template_for(&event.signature))
+            .unwrap_or(DEFAULT_TEMPLATE);
+        let message = render_template(template, event);
+        let request = match &self.target {
+            Target::Discord { webhook_url } => self
+                .client
+                .post(webhook_url)
+                .json(&json!({ "content": message })),
+            Target::Slack { webhook_url } => self
+                .client
+                .post(webhook_url)
+                .json(&json!({ "text": message })),
+            Target::Telegram { bot_token, chat_id } => self
+                .client
+                .post(format!("{}/bot{}/sendMessage", TELEGRAM_API_URL, bot_token))
+                .json(&json!({ "chat_id": chat_id, "text": message })),
+        };
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
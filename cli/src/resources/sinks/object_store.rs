@@ -0,0 +1,177 @@
+use std::{
+    io::Write,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use flate2::{write::GzEncoder, Compression};
+use object_store::{aws::AmazonS3Builder, gcp::GoogleCloudStorageBuilder, path::Path, ObjectStore};
+use tokio::sync::Mutex;
+
+use crate::core::resources::sink::{utc_date_hour, DecodedEvent, EventSink};
+
+/// A sink that periodically uploads gzipped JSONL batches of decoded
+/// events to S3 or GCS.
+///
+/// Batches are uploaded to date-partitioned keys, e.g.
+/// `<prefix>/date=2023-04-12/hour=22/17034841-17034899-1681338455000.jsonl.gz`,
+/// using the UTC hour of the first event in the batch. Credentials are
+/// read from the environment, as with the AWS and GCP CLIs.
+pub struct ObjectStoreSink {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    store: Box<dyn ObjectStore>,
+    prefix: String,
+    events: Mutex<Vec<DecodedEvent>>,
+}
+
+impl ObjectStoreSink {
+    /// Connects to the bucket in the URL, e.g. `s3://bucket/prefix` or
+    /// `gs://bucket/prefix`, and starts uploading every `interval`.
+    pub fn new(url: &str, interval: Duration) -> Result<Self, Box<dyn std::error::Error>> {
+        let (store, prefix) = open_bucket(url)?;
+        let inner = Arc::new(Inner {
+            store,
+            prefix,
+            events: Mutex::new(Vec::new()),
+        });
+
+        // Upload batches in the background
+        let weak = Arc::downgrade(&inner);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            // The first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(inner) = weak.upgrade() else {
+                    return;
+                };
+                if let Err(e) = inner.upload().await {
+                    log::warn!("Error uploading events to the archive: {}", e);
+                }
+            }
+        });
+
+        Ok(ObjectStoreSink { inner })
+    }
+}
+
+impl Inner {
+    /// Uploads the buffered events as one batch.
+    ///
+    /// If the upload fails, the events are kept and retried with the next batch.
+    async fn upload(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut events = self.events.lock().await;
+        let (Some(first), Some(last)) = (events.first(), events.last()) else {
+            return Ok(());
+        };
+        let uploaded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let key = batch_key(
+            &self.prefix,
+            first.block_timestamp.unwrap_or(uploaded_at.as_secs()),
+            first.block_number,
+            last.block_number,
+            uploaded_at.as_millis(),
+        );
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        for event in events.iter() {
+            serde_json::to_writer(&mut encoder, event)?;
+            encoder.write_all(b"\n")?;
+        }
+        let body = encoder.finish()?;
+
+        self.store
+            .put(&Path::from(key.as_str()), body.into())
+            .await?;
+        log::info!("Archived {} events to {}", events.len(), key);
+        events.clear();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for ObjectStoreSink {
+    async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        self.inner.events.lock().await.push(event.clone());
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.inner.upload().await.map_err(|e| e.to_string().into())
+    }
+}
+
+/// Connects to the bucket in a URL, e.g. `s3://bucket/prefix` or
+/// `gs://bucket/prefix`, and returns it with the prefix.
+pub fn open_bucket(
+    url: &str,
+) -> Result<(Box<dyn ObjectStore>, String), Box<dyn std::error::Error>> {
+    let (scheme, rest) = url
+        .split_once("://")
+        .ok_or("The URL must be in the form s3://bucket/prefix or gs://bucket/prefix")?;
+    let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+    let store: Box<dyn ObjectStore> = match scheme {
+        "s3" => Box::new(
+            AmazonS3Builder::from_env()
+                .with_bucket_name(bucket)
+                .build()?,
+        ),
+        "gs" => Box::new(
+            GoogleCloudStorageBuilder::from_env()
+                .with_bucket_name(bucket)
+                .build()?,
+        ),
+        _ => return Err(format!("Unsupported bucket URL scheme: {}", scheme).into()),
+    };
+    Ok((store, prefix.trim_end_matches('/').to_owned()))
+}
+
+/// Builds the date-partitioned key of a batch.
+fn batch_key(
+    prefix: &str,
+    timestamp: u64,
+    first_block: u64,
+    last_block: u64,
+    uploaded_at: u128,
+) -> String {
+    let (year, month, day, hour) = utc_date_hour(timestamp);
+    let key = format!(
+        "date={:04}-{:02}-{:02}/hour={:02}/{}-{}-{}.jsonl.gz",
+        year, month, day, hour, first_block, last_block, uploaded_at
+    );
+    if prefix.is_empty() {
+        key
+    } else {
+        format!("{}/{}", prefix, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_build_batch_key() {
+        assert_eq!(
+            batch_key(
+                "shadow/events",
+                1_681_338_455,
+                17_034_841,
+                17_034_899,
+                1_681_338_455_000
+            ),
+            "shadow/events/date=2023-04-12/hour=22/17034841-17034899-1681338455000.jsonl.gz"
+        );
+        assert_eq!(
+            batch_key("", 0, 1, 2, 3),
+            "date=1970-01-01/hour=00/1-2-3.jsonl.gz"
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, streams::StreamMaxlen, AsyncCommands};
+
+use crate::core::resources::sink::{DecodedEvent, EventSink};
+
+/// A sink that appends decoded events to a Redis stream with `XADD`.
+///
+/// Each entry has an `event` field with the event name, and a `payload`
+/// field with the event as JSON. The stream is trimmed to roughly
+/// `maxlen` entries, so it never grows unbounded.
+pub struct RedisSink {
+    connection: ConnectionManager,
+    key: String,
+    maxlen: Option<usize>,
+}
+
+impl RedisSink {
+    pub async fn connect(
+        url: &str,
+        key: String,
+        maxlen: Option<usize>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = redis::Client::open(url)?;
+        let connection = client.get_tokio_connection_manager().await?;
+        Ok(RedisSink {
+            connection,
+            key,
+            maxlen,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for RedisSink {
+    async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let payload = serde_json::to_string(event)?;
+        let items = [
+            ("event", event.event.as_str()),
+            ("payload", payload.as_str()),
+        ];
+        // The connection manager is cheap to clone and multiplexes a single connection
+        let mut connection = self.connection.clone();
+        let _: String = match self.maxlen {
+            Some(maxlen) => {
+                connection
+                    .xadd_maxlen(&self.key, StreamMaxlen::Approx(maxlen), "*", &items)
+                    .await?
+            }
+            None => connection.xadd(&self.key, "*", &items).await?,
+        };
+        Ok(())
+    }
+}
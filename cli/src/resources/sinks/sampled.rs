@@ -0,0 +1,173 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    config::SamplingRule,
+    core::resources::sink::{DecodedEvent, EventSink},
+};
+
+/// The window `maxPerMinute` is counted over
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// A sink that only forwards a sample of noisy events to another sink.
+///
+/// Events whose signature has no sampling rule are always forwarded.
+pub struct SampledSink<S: EventSink> {
+    inner: S,
+    sampler: Sampler,
+}
+
+impl<S: EventSink> SampledSink<S> {
+    pub fn new(inner: S, rules: Vec<SamplingRule>) -> Self {
+        SampledSink {
+            inner,
+            sampler: Sampler::new(rules),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: EventSink> EventSink for SampledSink<S> {
+    async fn write(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.sampler.admit(event, Instant::now()) {
+            log::debug!("Sampled out {} in {}", event.event, event.transaction_hash);
+            return Ok(());
+        }
+        self.inner.write(event).await
+    }
+
+    async fn flush(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.inner.flush().await
+    }
+}
+
+/// Decides which events pass the sampling rules, keyed by signature.
+///
+/// If several rules have the same signature, the first one is used.
+struct Sampler {
+    rules: HashMap<String, (SamplingRule, Mutex<SampleState>)>,
+}
+
+#[derive(Default)]
+struct SampleState {
+    /// The number of events seen
+    seen: u64,
+    /// When the current rate window started
+    window_start: Option<Instant>,
+    /// The number of events kept in the current rate window
+    kept_in_window: u64,
+}
+
+impl Sampler {
+    fn new(rules: Vec<SamplingRule>) -> Self {
+        let mut by_signature = HashMap::new();
+        for rule in rules {
+            by_signature
+                .entry(rule.signature.clone())
+                .or_insert_with(|| (rule, Mutex::new(SampleState::default())));
+        }
+        Sampler {
+            rules: by_signature,
+        }
+    }
+
+    /// Returns whether an event seen at `now` is kept.
+    fn admit(&self, event: &DecodedEvent, now: Instant) -> bool {
+        let Some((rule, state)) = self.rules.get(&event.signature) else {
+            return true;
+        };
+        let mut state = state.lock().unwrap();
+        state.seen += 1;
+        if let Some(every) = rule.every {
+            if every > 1 && (state.seen - 1) % every != 0 {
+                return false;
+            }
+        }
+        if let Some(max_per_minute) = rule.max_per_minute {
+            let expired = state
+                .window_start
+                .map_or(true, |start| now.duration_since(start) >= RATE_WINDOW);
+            if expired {
+                state.window_start = Some(now);
+                state.kept_in_window = 0;
+            }
+            if state.kept_in_window >= max_per_minute {
+                return false;
+            }
+            state.kept_in_window += 1;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(signature: &str) -> DecodedEvent {
+        DecodedEvent {
+            block_number: 17_000_000,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 0,
+            address: "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".to_owned(),
+            contract: "WETH9.sol:WETH9".to_owned(),
+            event: signature.split('(').next().unwrap().to_owned(),
+            signature: signature.to_owned(),
+            schema: None,
+            data: json!({}),
+            raw: None,
+            ..Default::default()
+        }
+    }
+
+    fn rule(every: Option<u64>, max_per_minute: Option<u64>) -> SamplingRule {
+        SamplingRule {
+            signature: "Transfer(address,address,uint256)".to_owned(),
+            every,
+            max_per_minute,
+        }
+    }
+
+    #[test]
+    fn can_sample_one_in_n() {
+        let sampler = Sampler::new(vec![rule(Some(3), None)]);
+        let transfer = event("Transfer(address,address,uint256)");
+        let now = Instant::now();
+        let kept = (0..7)
+            .map(|_| sampler.admit(&transfer, now))
+            .collect::<Vec<_>>();
+        assert_eq!(kept, vec![true, false, false, true, false, false, true]);
+
+        // Other signatures aren't sampled
+        let approval = event("Approval(address,address,uint256)");
+        assert!((0..3).all(|_| sampler.admit(&approval, now)));
+    }
+
+    #[test]
+    fn can_rate_limit_per_minute() {
+        let sampler = Sampler::new(vec![rule(None, Some(2))]);
+        let transfer = event("Transfer(address,address,uint256)");
+        let start = Instant::now();
+        assert!(sampler.admit(&transfer, start));
+        assert!(sampler.admit(&transfer, start + Duration::from_secs(10)));
+        assert!(!sampler.admit(&transfer, start + Duration::from_secs(59)));
+        assert!(sampler.admit(&transfer, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn first_rule_for_a_signature_wins() {
+        let sampler = Sampler::new(vec![rule(None, Some(1)), rule(None, None)]);
+        let transfer = event("Transfer(address,address,uint256)");
+        let now = Instant::now();
+        assert!(sampler.admit(&transfer, now));
+        assert!(!sampler.admit(&transfer, now));
+    }
+}
@@ -0,0 +1,142 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use async_trait::async_trait;
+
+use crate::core::resources::{
+    event_store::{EventQuery, EventStore},
+    sink::DecodedEvent,
+};
+
+/// The most events a memory store keeps by default
+pub const DEFAULT_MEMORY_STORE_CAPACITY: usize = 100_000;
+
+/// An event store that keeps events in memory, for the lifetime of
+/// the process.
+///
+/// Events are keyed by their position, so inserting an event twice
+/// replaces it rather than duplicating it. The composite records of
+/// join rules, which take the position of the joined log, are kept
+/// apart from events. Once the store holds `capacity` events, the
+/// oldest ones are dropped to make room for new ones.
+pub struct MemoryEventStore {
+    events: Mutex<BTreeMap<(u64, u64, String, bool), DecodedEvent>>,
+    capacity: usize,
+}
+
+impl Default for MemoryEventStore {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_MEMORY_STORE_CAPACITY)
+    }
+}
+
+impl MemoryEventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a store that keeps at most `capacity` events.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            events: Mutex::new(BTreeMap::new()),
+            capacity: capacity.max(1),
+        }
+    }
+}
+
+#[async_trait]
+impl EventStore for MemoryEventStore {
+    async fn insert(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let key = (
+            event.block_number,
+            event.log_index,
+            event.transaction_hash.clone(),
+            event.is_join(),
+        );
+        let mut events = self.events.lock().unwrap();
+        events.insert(key, event.clone());
+        while events.len() > self.capacity {
+            events.pop_first();
+        }
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        query: &EventQuery,
+    ) -> Result<Vec<DecodedEvent>, Box<dyn std::error::Error>> {
+        Ok(self
+            .events
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|event| query.matches(event))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(block_number: u64, event: &str) -> DecodedEvent {
+        DecodedEvent {
+            block_number,
+            block_timestamp: None,
+            transaction_hash: format!("0x{:02x}", block_number),
+            log_index: 0,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            event: event.to_owned(),
+            signature: format!("{}(address,uint256)", event),
+            schema: None,
+            data: json!({}),
+            raw: None,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn can_insert_and_query_events() {
+        let store = MemoryEventStore::new();
+        for e in [event(3, "Trade"), event(1, "Trade"), event(2, "Swap")] {
+            store.insert(&e).await.unwrap();
+        }
+        store.insert(&event(1, "Trade")).await.unwrap();
+
+        let blocks: Vec<u64> = store
+            .list()
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.block_number)
+            .collect();
+        assert_eq!(blocks, vec![1, 2, 3]);
+
+        let query = EventQuery {
+            event: Some("Trade".to_owned()),
+            to_block: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(store.query(&query).await.unwrap(), vec![event(1, "Trade")]);
+    }
+
+    #[tokio::test]
+    async fn drops_the_oldest_events_when_full() {
+        let store = MemoryEventStore::with_capacity(2);
+        for e in [event(2, "Trade"), event(1, "Trade"), event(3, "Trade")] {
+            store.insert(&e).await.unwrap();
+        }
+
+        let blocks: Vec<u64> = store
+            .list()
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.block_number)
+            .collect();
+        assert_eq!(blocks, vec![2, 3]);
+    }
+}
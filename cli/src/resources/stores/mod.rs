@@ -0,0 +1,48 @@
+pub mod memory;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use std::sync::Arc;
+
+pub use memory::MemoryEventStore;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresEventStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteEventStore;
+
+use crate::core::resources::event_store::EventStore;
+
+use super::sinks::JsonlSink;
+
+/// Opens the event store at the given location.
+///
+/// - `sqlite://<path>` opens a SQLite database file, with the `sqlite` feature
+/// - `postgres://...` connects to a Postgres database, with the `postgres` feature
+/// - `memory://` keeps events in memory, for the lifetime of the process
+/// - anything else is the path of a JSONL archive
+pub async fn open(location: &str) -> Result<Arc<dyn EventStore>, Box<dyn std::error::Error>> {
+    if let Some(path) = location.strip_prefix("sqlite://") {
+        #[cfg(feature = "sqlite")]
+        return Ok(Arc::new(SqliteEventStore::open(path)?));
+        #[cfg(not(feature = "sqlite"))]
+        return Err(format!(
+            "Cannot open {}: shadow was built without the `sqlite` feature",
+            path
+        )
+        .into());
+    }
+    if location.starts_with("postgres://") || location.starts_with("postgresql://") {
+        #[cfg(feature = "postgres")]
+        return Ok(Arc::new(PostgresEventStore::connect(location).await?));
+        #[cfg(not(feature = "postgres"))]
+        return Err(
+            "Cannot connect to Postgres: shadow was built without the `postgres` feature".into(),
+        );
+    }
+    if location == "memory://" {
+        return Ok(Arc::new(MemoryEventStore::new()));
+    }
+    Ok(Arc::new(JsonlSink::new(location.to_owned())?))
+}
@@ -0,0 +1,165 @@
+use async_trait::async_trait;
+use tokio_postgres::{types::Json, Client, NoTls, Row};
+
+use crate::core::resources::{
+    event_store::{EventQuery, EventStore},
+    sink::{BalanceDiff, DecodedEvent, RawLog},
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS shadow_events (
+    transaction_hash TEXT NOT NULL,
+    log_index BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_timestamp BIGINT,
+    address TEXT NOT NULL,
+    contract TEXT NOT NULL,
+    event TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    schema TEXT,
+    data JSONB NOT NULL,
+    raw JSONB,
+    balance_diffs JSONB,
+    PRIMARY KEY (transaction_hash, log_index)
+);
+CREATE INDEX IF NOT EXISTS shadow_events_block ON shadow_events (block_number, log_index);
+CREATE TABLE IF NOT EXISTS shadow_joined_events (
+    transaction_hash TEXT NOT NULL,
+    log_index BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_timestamp BIGINT,
+    address TEXT NOT NULL,
+    contract TEXT NOT NULL,
+    event TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    schema TEXT,
+    data JSONB NOT NULL,
+    raw JSONB,
+    balance_diffs JSONB,
+    PRIMARY KEY (transaction_hash, log_index)
+);
+CREATE INDEX IF NOT EXISTS shadow_joined_events_block
+    ON shadow_joined_events (block_number, log_index);
+ALTER TABLE shadow_events ADD COLUMN IF NOT EXISTS balance_diffs JSONB;
+ALTER TABLE shadow_joined_events ADD COLUMN IF NOT EXISTS balance_diffs JSONB;
+";
+
+/// An event store backed by a Postgres table.
+///
+/// Events are keyed by their transaction hash and log index, so
+/// inserting an event twice replaces it rather than duplicating it. The
+/// composite records of join rules take the log index of the joined
+/// log, so they are kept in their own `shadow_joined_events` table. The
+/// decoded parameters, raw log and balance diffs are stored as `JSONB`,
+/// so they can be queried with Postgres' JSON operators.
+pub struct PostgresEventStore {
+    client: Client,
+}
+
+impl PostgresEventStore {
+    /// Connects to the database, creating the `shadow_events` table if needed.
+    pub async fn connect(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (client, connection) = tokio_postgres::connect(url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                log::error!("Postgres connection closed: {}", e);
+            }
+        });
+        client.batch_execute(SCHEMA).await?;
+        Ok(PostgresEventStore { client })
+    }
+}
+
+#[async_trait]
+impl EventStore for PostgresEventStore {
+    async fn insert(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let table = if event.is_join() {
+            "shadow_joined_events"
+        } else {
+            "shadow_events"
+        };
+        self.client
+            .execute(
+                &format!(
+                    "INSERT INTO {} (transaction_hash, log_index, block_number, \
+                     block_timestamp, address, contract, event, signature, schema, data, raw, \
+                     balance_diffs) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) \
+                     ON CONFLICT (transaction_hash, log_index) DO UPDATE SET \
+                     block_number = EXCLUDED.block_number, \
+                     block_timestamp = EXCLUDED.block_timestamp, address = EXCLUDED.address, \
+                     contract = EXCLUDED.contract, event = EXCLUDED.event, \
+                     signature = EXCLUDED.signature, schema = EXCLUDED.schema, \
+                     data = EXCLUDED.data, raw = EXCLUDED.raw, \
+                     balance_diffs = EXCLUDED.balance_diffs",
+                    table
+                ),
+                &[
+                    &event.transaction_hash,
+                    &(event.log_index as i64),
+                    &(event.block_number as i64),
+                    &event.block_timestamp.map(|t| t as i64),
+                    &event.address,
+                    &event.contract,
+                    &event.event,
+                    &event.signature,
+                    &event.schema,
+                    &Json(&event.data),
+                    &event.raw.as_ref().map(Json),
+                    &(!event.balance_diffs.is_empty()).then_some(Json(&event.balance_diffs)),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        query: &EventQuery,
+    ) -> Result<Vec<DecodedEvent>, Box<dyn std::error::Error>> {
+        let rows = self
+            .client
+            .query(
+                // Composite records come after the events at the same position
+                "SELECT transaction_hash, log_index, block_number, block_timestamp, address, \
+                 contract, event, signature, schema, data, raw, balance_diffs, 0 AS joined \
+                 FROM shadow_events \
+                 WHERE block_number >= $1 AND block_number <= $2 \
+                 AND ($3::TEXT IS NULL OR event = $3) \
+                 UNION ALL \
+                 SELECT transaction_hash, log_index, block_number, block_timestamp, address, \
+                 contract, event, signature, schema, data, raw, balance_diffs, 1 AS joined \
+                 FROM shadow_joined_events \
+                 WHERE block_number >= $1 AND block_number <= $2 \
+                 AND ($3::TEXT IS NULL OR event = $3) \
+                 ORDER BY block_number, log_index, joined",
+                &[
+                    &query.from_block.map_or(0, |b| b as i64),
+                    &query.to_block.map_or(i64::MAX, |b| b as i64),
+                    &query.event,
+                ],
+            )
+            .await?;
+        Ok(rows.iter().map(read_row).collect())
+    }
+}
+
+fn read_row(row: &Row) -> DecodedEvent {
+    DecodedEvent {
+        transaction_hash: row.get(0),
+        log_index: row.get::<_, i64>(1) as u64,
+        block_number: row.get::<_, i64>(2) as u64,
+        block_timestamp: row.get::<_, Option<i64>>(3).map(|t| t as u64),
+        address: row.get(4),
+        contract: row.get(5),
+        event: row.get(6),
+        signature: row.get(7),
+        schema: row.get(8),
+        data: row.get::<_, Json<serde_json::Value>>(9).0,
+        raw: row.get::<_, Option<Json<RawLog>>>(10).map(|raw| raw.0),
+        balance_diffs: row
+            .get::<_, Option<Json<Vec<BalanceDiff>>>>(11)
+            .map(|diffs| diffs.0)
+            .unwrap_or_default(),
+        ..Default::default()
+    }
+}
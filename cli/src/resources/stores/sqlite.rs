@@ -0,0 +1,299 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection, Row};
+
+use crate::core::resources::{
+    event_store::{EventQuery, EventStore},
+    sink::DecodedEvent,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    transaction_hash TEXT NOT NULL,
+    log_index INTEGER NOT NULL,
+    block_number INTEGER NOT NULL,
+    block_timestamp INTEGER,
+    address TEXT NOT NULL,
+    contract TEXT NOT NULL,
+    event TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    schema TEXT,
+    data TEXT NOT NULL,
+    raw TEXT,
+    balance_diffs TEXT,
+    PRIMARY KEY (transaction_hash, log_index)
+);
+CREATE INDEX IF NOT EXISTS events_block ON events (block_number, log_index);
+CREATE TABLE IF NOT EXISTS joined_events (
+    transaction_hash TEXT NOT NULL,
+    log_index INTEGER NOT NULL,
+    block_number INTEGER NOT NULL,
+    block_timestamp INTEGER,
+    address TEXT NOT NULL,
+    contract TEXT NOT NULL,
+    event TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    schema TEXT,
+    data TEXT NOT NULL,
+    raw TEXT,
+    balance_diffs TEXT,
+    PRIMARY KEY (transaction_hash, log_index)
+);
+CREATE INDEX IF NOT EXISTS joined_events_block ON joined_events (block_number, log_index);
+";
+
+/// An event store backed by a SQLite database file.
+///
+/// Events are keyed by their transaction hash and log index, so
+/// inserting an event twice replaces it rather than duplicating it. The
+/// composite records of join rules take the log index of the joined
+/// log, so they are kept in their own `joined_events` table. The decoded
+/// parameters, raw log and balance diffs are stored as JSON text.
+///
+/// SQLite calls block, so they run on tokio's blocking threads.
+pub struct SqliteEventStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteEventStore {
+    /// Opens the database at the given path, creating it if needed.
+    pub fn open(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        migrate(&connection)?;
+        Ok(SqliteEventStore {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+}
+
+#[async_trait]
+impl EventStore for SqliteEventStore {
+    async fn insert(&self, event: &DecodedEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let raw = event.raw.as_ref().map(serde_json::to_string).transpose()?;
+        let balance_diffs = match event.balance_diffs.is_empty() {
+            true => None,
+            false => Some(serde_json::to_string(&event.balance_diffs)?),
+        };
+        let table = if event.is_join() {
+            "joined_events"
+        } else {
+            "events"
+        };
+        let connection = self.connection.clone();
+        let event = event.clone();
+        tokio::task::spawn_blocking(move || {
+            connection.lock().unwrap().execute(
+                &format!(
+                    "INSERT OR REPLACE INTO {} (transaction_hash, log_index, block_number, \
+                     block_timestamp, address, contract, event, signature, schema, data, raw, \
+                     balance_diffs) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                    table
+                ),
+                params![
+                    event.transaction_hash,
+                    event.log_index as i64,
+                    event.block_number as i64,
+                    event.block_timestamp.map(|t| t as i64),
+                    event.address,
+                    event.contract,
+                    event.event,
+                    event.signature,
+                    event.schema,
+                    event.data.to_string(),
+                    raw,
+                    balance_diffs,
+                ],
+            )
+        })
+        .await??;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        query: &EventQuery,
+    ) -> Result<Vec<DecodedEvent>, Box<dyn std::error::Error>> {
+        let connection = self.connection.clone();
+        let query = query.clone();
+        let rows = tokio::task::spawn_blocking(move || {
+            let connection = connection.lock().unwrap();
+            // Composite records come after the events at the same position
+            let mut statement = connection.prepare(
+                "SELECT transaction_hash, log_index, block_number, block_timestamp, address, \
+                 contract, event, signature, schema, data, raw, balance_diffs, 0 AS joined FROM events \
+                 WHERE block_number >= ?1 AND block_number <= ?2 AND (?3 IS NULL OR event = ?3) \
+                 UNION ALL \
+                 SELECT transaction_hash, log_index, block_number, block_timestamp, address, \
+                 contract, event, signature, schema, data, raw, balance_diffs, 1 AS joined \
+                 FROM joined_events \
+                 WHERE block_number >= ?1 AND block_number <= ?2 AND (?3 IS NULL OR event = ?3) \
+                 ORDER BY block_number, log_index, joined",
+            )?;
+            let rows = statement.query_map(
+                params![
+                    query.from_block.map_or(0, |b| b as i64),
+                    query.to_block.map_or(i64::MAX, |b| b as i64),
+                    query.event,
+                ],
+                read_row,
+            )?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .await??;
+
+        let mut events = Vec::new();
+        for (mut event, data, raw, balance_diffs) in rows {
+            event.data = serde_json::from_str(&data)?;
+            event.raw = raw.as_deref().map(serde_json::from_str).transpose()?;
+            if let Some(balance_diffs) = balance_diffs {
+                event.balance_diffs = serde_json::from_str(&balance_diffs)?;
+            }
+            events.push(event);
+        }
+        Ok(events)
+    }
+}
+
+/// The columns of an event row, with the JSON columns to be parsed
+/// separately.
+type EventRow = (DecodedEvent, String, Option<String>, Option<String>);
+
+/// Adds the columns newer versions store to tables created before them.
+fn migrate(connection: &Connection) -> rusqlite::Result<()> {
+    for table in ["events", "joined_events"] {
+        let query = format!("SELECT balance_diffs FROM {} LIMIT 0", table);
+        if connection.prepare(&query).is_err() {
+            connection.execute_batch(&format!(
+                "ALTER TABLE {} ADD COLUMN balance_diffs TEXT",
+                table
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads an event row, returning the JSON data, raw log and balance
+/// diff columns to be parsed separately.
+fn read_row(row: &Row) -> rusqlite::Result<EventRow> {
+    let event = DecodedEvent {
+        transaction_hash: row.get(0)?,
+        log_index: row.get::<_, i64>(1)? as u64,
+        block_number: row.get::<_, i64>(2)? as u64,
+        block_timestamp: row.get::<_, Option<i64>>(3)?.map(|t| t as u64),
+        address: row.get(4)?,
+        contract: row.get(5)?,
+        event: row.get(6)?,
+        signature: row.get(7)?,
+        schema: row.get(8)?,
+        data: serde_json::Value::Null,
+        raw: None,
+        ..Default::default()
+    };
+    Ok((event, row.get(9)?, row.get(10)?, row.get(11)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::resources::sink::{BalanceDiff, RawLog};
+
+    #[tokio::test]
+    async fn can_insert_and_query_events() {
+        let store = SqliteEventStore::open(":memory:").unwrap();
+        let event = DecodedEvent {
+            block_number: 17_000_000,
+            block_timestamp: Some(1_681_338_455),
+            transaction_hash: "0x01".to_owned(),
+            log_index: 3,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            event: "Trade".to_owned(),
+            signature: "Trade(address,uint256)".to_owned(),
+            schema: Some("0x01020304".to_owned()),
+            data: json!({ "amount": "42" }),
+            raw: Some(RawLog {
+                address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+                topics: vec![],
+                data: "0x".to_owned(),
+                block_number: 17_000_000,
+                log_index: 3,
+            }),
+            balance_diffs: vec![BalanceDiff {
+                address: "0x0101010101010101010101010101010101010101".to_owned(),
+                token: "ETH".to_owned(),
+                before: "100".to_owned(),
+                after: "40".to_owned(),
+                change: "-60".to_owned(),
+            }],
+            ..Default::default()
+        };
+        store.insert(&event).await.unwrap();
+        store.insert(&event).await.unwrap();
+        assert_eq!(store.list().await.unwrap(), vec![event.clone()]);
+
+        // Inserting an event again replaces it, e.g. when redriven
+        let event = DecodedEvent {
+            data: json!({ "amount": "43" }),
+            ..event
+        };
+        store.insert(&event).await.unwrap();
+        assert_eq!(store.list().await.unwrap(), vec![event.clone()]);
+        let query = EventQuery {
+            from_block: Some(17_000_001),
+            ..Default::default()
+        };
+        assert!(store.query(&query).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn keeps_joined_records_apart_from_events() {
+        let store = SqliteEventStore::open(":memory:").unwrap();
+        let event = DecodedEvent {
+            block_number: 17_000_000,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 3,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV3Pool.sol:UniswapV3Pool".to_owned(),
+            event: "Swap".to_owned(),
+            signature: "Swap(address,uint256)".to_owned(),
+            schema: None,
+            data: json!({ "amount": "42" }),
+            raw: None,
+            ..Default::default()
+        };
+        // The composite record takes the log index of the joined event
+        let joined = DecodedEvent {
+            event: "ObservedSwap".to_owned(),
+            signature: "PriceObserved(address)+Swap(address,uint256)".to_owned(),
+            data: json!({ "PriceObserved": {}, "Swap": { "amount": "42" } }),
+            ..event.clone()
+        };
+        store.insert(&event).await.unwrap();
+        store.insert(&joined).await.unwrap();
+
+        assert_eq!(store.list().await.unwrap(), vec![event, joined]);
+    }
+
+    #[test]
+    fn adds_balance_diffs_to_existing_tables() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE events (transaction_hash TEXT NOT NULL, log_index INTEGER NOT NULL);
+                 CREATE TABLE joined_events (transaction_hash TEXT NOT NULL, log_index INTEGER NOT NULL);",
+            )
+            .unwrap();
+        migrate(&connection).unwrap();
+        // Migrating again leaves the tables alone
+        migrate(&connection).unwrap();
+        for table in ["events", "joined_events"] {
+            let query = format!("SELECT balance_diffs FROM {} LIMIT 0", table);
+            assert!(connection.prepare(&query).is_ok());
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::core::resources::verifier::{VerificationRequest, VerifierResource};
+
+/// The response of an Etherscan-compatible verification API
+#[derive(Deserialize)]
+struct EtherscanVerifyResponse {
+    status: String,
+    result: String,
+}
+
+/// A verifier that submits sources to an Etherscan-compatible
+/// verification API (Etherscan, Blockscout, or a local mirror).
+///
+/// https://docs.etherscan.io/api-endpoints/contracts#verify-source-code
+pub struct EtherscanVerifier {
+    api_url: String,
+    api_key: String,
+    client: reqwest::Client,
+}
+
+impl EtherscanVerifier {
+    pub fn new(api_url: String, api_key: String, client: reqwest::Client) -> Self {
+        EtherscanVerifier {
+            api_url,
+            api_key,
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl VerifierResource for EtherscanVerifier {
+    async fn verify(
+        &self,
+        request: &VerificationRequest,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let source_code = request.standard_json_input().to_string();
+        let contract_name = format!("{}:{}", request.source_path, request.contract_name);
+        let compiler_version = request.compiler_version();
+        let form = [
+            ("apikey", self.api_key.as_str()),
+            ("module", "contract"),
+            ("action", "verifysourcecode"),
+            ("contractaddress", request.address.as_str()),
+            ("sourceCode", source_code.as_str()),
+            ("codeformat", "solidity-standard-json-input"),
+            ("contractname", contract_name.as_str()),
+            ("compilerversion", compiler_version.as_str()),
+            // Etherscan's API misspells this parameter
+            (
+                "constructorArguements",
+                request.constructor_arguments.as_str(),
+            ),
+        ];
+        let response = self
+            .client
+            .post(&self.api_url)
+            .form(&form)
+            .send()
+            .await?
+            .json::<EtherscanVerifyResponse>()
+            .await?;
+        if response.status != "1" {
+            return Err(response.result.into());
+        }
+        Ok(response.result)
+    }
+}
+
+/// A verifier that submits the compiler metadata and sources to a
+/// Sourcify server, which is what Otterscan uses to display
+/// verified sources.
+///
+/// https://docs.sourcify.dev/docs/api/server/v1/verify/
+pub struct SourcifyVerifier {
+    url: String,
+    chain_id: u64,
+    client: reqwest::Client,
+}
+
+impl SourcifyVerifier {
+    pub fn new(url: String, chain_id: u64, client: reqwest::Client) -> Self {
+        SourcifyVerifier {
+            url: url.trim_end_matches('/').to_owned(),
+            chain_id,
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl VerifierResource for SourcifyVerifier {
+    async fn verify(
+        &self,
+        request: &VerificationRequest,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut files = serde_json::Map::new();
+        files.insert(
+            "metadata.json".to_owned(),
+            request.metadata.to_string().into(),
+        );
+        for (path, content) in &request.sources {
+            files.insert(path.clone(), content.clone().into());
+        }
+        let body = json!({
+            "address": request.address,
+            "chain": self.chain_id.to_string(),
+            "files": files,
+        });
+        let response = self
+            .client
+            .post(format!("{}/verify", self.url))
+            .json(&body)
+            .send()
+            .await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(format!("Sourcify returned {}: {}", status, text).into());
+        }
+        Ok(text)
+    }
+}
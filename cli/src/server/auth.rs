@@ -0,0 +1,171 @@
+use std::{fmt, net::SocketAddr, sync::Arc};
+
+/// What a token may do. Each scope includes the ones before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Scope {
+    /// Subscribe to and query events, list the shadow contracts and read
+    /// the replay status
+    Read,
+    /// Everything, including reloading contracts and replaying
+    /// transactions on the fork
+    Admin,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Scope::Read => write!(f, "read"),
+            Scope::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// Why a request was rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthFailure {
+    /// The request has no token, or an unknown one (HTTP 401)
+    Unauthenticated,
+    /// The token's scope is too narrow for the request (HTTP 403)
+    Forbidden(Scope),
+}
+
+impl AuthFailure {
+    /// Returns the HTTP status code of the failure.
+    pub fn status(&self) -> u16 {
+        match self {
+            AuthFailure::Unauthenticated => 401,
+            AuthFailure::Forbidden(_) => 403,
+        }
+    }
+}
+
+impl fmt::Display for AuthFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthFailure::Unauthenticated => write!(f, "Missing or invalid API token"),
+            AuthFailure::Forbidden(scope) => write!(f, "This request needs a {} token", scope),
+        }
+    }
+}
+
+/// The bearer tokens accepted by the API servers, with their scopes.
+///
+/// Without any token, authentication is disabled and every request is
+/// allowed, as before tokens existed.
+#[derive(Clone, Debug, Default)]
+pub struct ApiAuth {
+    tokens: Arc<Vec<(String, Scope)>>,
+}
+
+impl ApiAuth {
+    pub fn new(read_tokens: &[String], admin_tokens: &[String]) -> Self {
+        let tokens = read_tokens
+            .iter()
+            .map(|token| (token.clone(), Scope::Read))
+            .chain(
+                admin_tokens
+                    .iter()
+                    .map(|token| (token.clone(), Scope::Admin)),
+            )
+            .filter(|(token, _)| !token.is_empty())
+            .collect();
+        ApiAuth {
+            tokens: Arc::new(tokens),
+        }
+    }
+
+    /// Returns whether requests must carry a token.
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// Checks that a token has at least the required scope.
+    pub fn authorize(&self, token: Option<&str>, required: Scope) -> Result<(), AuthFailure> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let scope = token
+            .and_then(|token| {
+                // Every token is compared, so the timing doesn't reveal which matched
+                self.tokens
+                    .iter()
+                    .filter(|(known, _)| constant_time_eq(known.as_bytes(), token.as_bytes()))
+                    .map(|(_, scope)| *scope)
+                    .max()
+            })
+            .ok_or(AuthFailure::Unauthenticated)?;
+        if scope < required {
+            return Err(AuthFailure::Forbidden(required));
+        }
+        Ok(())
+    }
+
+    /// Checks the `Authorization: Bearer <token>` header of a request.
+    pub fn authorize_header(
+        &self,
+        header: Option<&str>,
+        required: Scope,
+    ) -> Result<(), AuthFailure> {
+        self.authorize(header.and_then(bearer_token), required)
+    }
+
+    /// Warns when a server is reachable from other hosts without
+    /// authentication.
+    pub fn warn_if_open(&self, server: &str, addr: SocketAddr) {
+        if !self.is_enabled() && !addr.ip().is_loopback() {
+            log::warn!(
+                "The {} server on {} accepts requests from anyone, set --api-read-token or \
+                 --api-admin-token to require a token",
+                server,
+                addr
+            );
+        }
+    }
+}
+
+/// Returns the token of an `Authorization: Bearer <token>` header.
+pub fn bearer_token(header: &str) -> Option<&str> {
+    let (scheme, token) = header.trim().split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then_some(token.trim())
+}
+
+/// Compares two byte strings in a time that only depends on their lengths.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_authorize_scopes() {
+        let auth = ApiAuth::new(&["reader".to_owned()], &["admin".to_owned()]);
+        assert_eq!(auth.authorize(Some("reader"), Scope::Read), Ok(()));
+        assert_eq!(auth.authorize(Some("admin"), Scope::Read), Ok(()));
+        assert_eq!(auth.authorize(Some("admin"), Scope::Admin), Ok(()));
+        assert_eq!(
+            auth.authorize(Some("reader"), Scope::Admin),
+            Err(AuthFailure::Forbidden(Scope::Admin))
+        );
+        assert_eq!(
+            auth.authorize(Some("other"), Scope::Read),
+            Err(AuthFailure::Unauthenticated)
+        );
+        assert_eq!(
+            auth.authorize_header(None, Scope::Read),
+            Err(AuthFailure::Unauthenticated)
+        );
+        assert_eq!(
+            auth.authorize_header(Some("Bearer admin"), Scope::Admin),
+            Ok(())
+        );
+
+        // Without tokens, everything is allowed
+        let open = ApiAuth::new(&[], &["".to_owned()]);
+        assert!(!open.is_enabled());
+        assert_eq!(open.authorize(None, Scope::Admin), Ok(()));
+    }
+}
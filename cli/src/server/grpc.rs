@@ -0,0 +1,139 @@
+use std::{net::SocketAddr, pin::Pin, sync::Arc};
+
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tonic::{transport::Server, Request, Response, Status};
+
+use super::auth::{ApiAuth, AuthFailure, Scope};
+use crate::{
+    core::resources::{event_store::EventStore, sink::DecodedEvent},
+    resources::sinks::BroadcastSink,
+};
+
+pub mod proto {
+    tonic::include_proto!("shadow.events.v1");
+}
+
+use proto::shadow_events_server::{ShadowEvents, ShadowEventsServer};
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<proto::Event, Status>> + Send>>;
+
+/// Serves live and archived events over gRPC.
+///
+/// With authentication enabled, clients send a read token in the
+/// `authorization: Bearer <token>` metadata of each call.
+pub struct EventsService {
+    /// The live events
+    broadcast: BroadcastSink,
+    /// The event store range queries are answered from
+    store: Option<Arc<dyn EventStore>>,
+    /// The tokens calls are checked against
+    auth: ApiAuth,
+}
+
+impl EventsService {
+    pub fn new(
+        broadcast: BroadcastSink,
+        store: Option<Arc<dyn EventStore>>,
+        auth: ApiAuth,
+    ) -> Self {
+        EventsService {
+            broadcast,
+            store,
+            auth,
+        }
+    }
+
+    /// Serves the gRPC API on the given address until it fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+        log::info!("Serving gRPC on {}", addr);
+        self.auth.warn_if_open("gRPC", addr);
+        let auth = self.auth.clone();
+        let interceptor = move |request: Request<()>| {
+            let header = request
+                .metadata()
+                .get("authorization")
+                .and_then(|value| value.to_str().ok());
+            match auth.authorize_header(header, Scope::Read) {
+                Ok(()) => Ok(request),
+                Err(failure @ AuthFailure::Unauthenticated) => {
+                    Err(Status::unauthenticated(failure.to_string()))
+                }
+                Err(failure @ AuthFailure::Forbidden(_)) => {
+                    Err(Status::permission_denied(failure.to_string()))
+                }
+            }
+        };
+        Server::builder()
+            .add_service(ShadowEventsServer::with_interceptor(self, interceptor))
+            .serve(addr)
+            .await
+    }
+}
+
+#[tonic::async_trait]
+impl ShadowEvents for EventsService {
+    type SubscribeStream = EventStream;
+    type QueryRangeStream = EventStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<proto::SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let name = request.into_inner().event;
+        let stream =
+            BroadcastStream::new(self.broadcast.subscribe()).filter_map(move |event| match event {
+                Ok(event) if name.is_empty() || event.event == name => Some(Ok(event.into())),
+                Ok(_) => None,
+                Err(e) => {
+                    log::warn!("gRPC subscriber fell behind: {}", e);
+                    None
+                }
+            });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn query_range(
+        &self,
+        request: Request<proto::RangeRequest>,
+    ) -> Result<Response<Self::QueryRangeStream>, Status> {
+        let request = request.into_inner();
+        let store = self
+            .store
+            .as_ref()
+            .ok_or_else(|| Status::failed_precondition("No event archive configured"))?;
+
+        let name = (!request.event.is_empty()).then_some(request.event.as_str());
+        let query = super::range_query(request.from_block, request.to_block, name);
+        let events = store
+            .query(&query)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let events: Vec<Result<proto::Event, Status>> =
+            events.into_iter().map(|event| Ok(event.into())).collect();
+        Ok(Response::new(Box::pin(tokio_stream::iter(events))))
+    }
+}
+
+impl From<DecodedEvent> for proto::Event {
+    fn from(event: DecodedEvent) -> Self {
+        proto::Event {
+            block_number: event.block_number,
+            block_timestamp: event.block_timestamp,
+            transaction_hash: event.transaction_hash,
+            log_index: event.log_index,
+            address: event.address,
+            contract: event.contract,
+            event: event.event,
+            signature: event.signature,
+            schema: event.schema,
+            data_json: event.data.to_string(),
+            raw: event.raw.map(|raw| proto::RawLog {
+                address: raw.address,
+                topics: raw.topics,
+                data: raw.data,
+                block_number: raw.block_number,
+                log_index: raw.log_index,
+            }),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server,
+};
+use serde_json::json;
+
+use crate::core::actions::fork::ReplayMetrics;
+
+/// How long a daemon may go without processing a block before it is
+/// reported as not live, by default
+pub const DEFAULT_MAX_STALL: Duration = Duration::from_secs(120);
+
+/// The health of a long-running daemon, reported to container
+/// orchestrators.
+///
+/// A daemon is ready once it is processing blocks, and live as long as
+/// it keeps processing them. It is live while it starts, as deploying
+/// the shadow contracts can take a while. Clones share the same state.
+#[derive(Clone, Debug)]
+pub struct Health {
+    state: Arc<Mutex<HealthState>>,
+    max_stall: Duration,
+}
+
+#[derive(Debug)]
+struct HealthState {
+    ready: bool,
+    last_block: Option<u64>,
+    last_progress: Instant,
+    metrics: Option<Arc<Mutex<ReplayMetrics>>>,
+}
+
+/// The health of a daemon at a point in time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HealthStatus {
+    /// Whether the daemon is processing blocks
+    pub ready: bool,
+    /// Whether the daemon processed a block recently enough
+    pub live: bool,
+    /// The last block processed
+    pub last_block: Option<u64>,
+    /// The seconds since the daemon became ready or last processed a block
+    pub stalled_for: u64,
+}
+
+impl Health {
+    pub fn new(max_stall: Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(HealthState {
+                ready: false,
+                last_block: None,
+                last_progress: Instant::now(),
+                metrics: None,
+            })),
+            max_stall,
+        }
+    }
+
+    /// Marks the daemon as ready, once it starts processing blocks.
+    pub fn set_ready(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.ready = true;
+        state.last_progress = Instant::now();
+    }
+
+    /// Records that a block was processed.
+    pub fn record_block(&self, block_number: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.last_block = Some(block_number);
+        state.last_progress = Instant::now();
+    }
+
+    /// Serves the replay metrics of a fork on `GET /metrics`.
+    pub fn report_metrics(&self, metrics: Arc<Mutex<ReplayMetrics>>) {
+        self.state.lock().unwrap().metrics = Some(metrics);
+    }
+
+    /// Returns the replay metrics in the Prometheus text format, if a
+    /// fork reports them.
+    pub fn metrics(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        let metrics = state.metrics.as_ref()?;
+        let metrics = metrics.lock().unwrap().to_prometheus();
+        Some(metrics)
+    }
+
+    /// Returns the current health.
+    pub fn status(&self) -> HealthStatus {
+        let state = self.state.lock().unwrap();
+        let stalled_for = state.last_progress.elapsed();
+        HealthStatus {
+            ready: state.ready,
+            live: !state.ready || stalled_for <= self.max_stall,
+            last_block: state.last_block,
+            stalled_for: stalled_for.as_secs(),
+        }
+    }
+}
+
+/// Serves the health of a daemon over HTTP: `GET /healthz` is the
+/// liveness probe and `GET /readyz` the readiness probe. Both answer
+/// 200 when healthy and 503 otherwise, with the status as JSON.
+///
+/// `GET /metrics` serves the replay metrics of `shadow fork` to
+/// Prometheus.
+///
+/// The port is bound before returning, so a port that is taken fails
+/// the daemon's startup, and the server then runs in the background.
+pub fn serve(addr: SocketAddr, health: Health) -> Result<(), hyper::Error> {
+    let make_service = make_service_fn(move |_| {
+        let health = health.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let health = health.clone();
+                async move { Ok::<_, Infallible>(handle(request, &health)) }
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    log::info!("Serving health checks on {}", addr);
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            log::error!("Health check server stopped: {}", e);
+        }
+    });
+    Ok(())
+}
+
+fn handle(request: Request<Body>, health: &Health) -> Response<Body> {
+    if request.method() != Method::GET {
+        return Response::builder().status(405).body(Body::empty()).unwrap();
+    }
+    if request.uri().path() == "/metrics" {
+        return match health.metrics() {
+            Some(metrics) => Response::builder()
+                .header("content-type", "text/plain; version=0.0.4")
+                .body(Body::from(metrics))
+                .unwrap(),
+            None => Response::builder().status(404).body(Body::empty()).unwrap(),
+        };
+    }
+    let status = health.status();
+    let healthy = match request.uri().path() {
+        "/healthz" => status.live,
+        "/readyz" => status.ready && status.live,
+        _ => return Response::builder().status(404).body(Body::empty()).unwrap(),
+    };
+    let body = json!({
+        "ready": status.ready,
+        "live": status.live,
+        "lastBlock": status.last_block,
+        "stalledFor": status.stalled_for,
+    });
+    Response::builder()
+        .status(if healthy { 200 } else { 503 })
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(path: &str, health: &Health) -> u16 {
+        let request = Request::get(path).body(Body::empty()).unwrap();
+        handle(request, health).status().as_u16()
+    }
+
+    #[test]
+    fn reports_readiness_and_liveness() {
+        let health = Health::new(Duration::from_secs(60));
+        assert_eq!(get("/healthz", &health), 200);
+        assert_eq!(get("/readyz", &health), 503);
+
+        health.set_ready();
+        health.record_block(17_000_000);
+        assert_eq!(get("/readyz", &health), 200);
+        assert_eq!(health.status().last_block, Some(17_000_000));
+        // Only forks report metrics
+        assert_eq!(get("/metrics", &health), 404);
+        let metrics = Arc::new(Mutex::new(ReplayMetrics::default()));
+        metrics.lock().unwrap().record_block_failure();
+        health.report_metrics(metrics);
+        assert_eq!(get("/metrics", &health), 200);
+        assert!(health
+            .metrics()
+            .unwrap()
+            .contains("\nshadow_replay_blocks_failed_total 1\n"));
+
+        let stalled = Health::new(Duration::ZERO);
+        stalled.set_ready();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(!stalled.status().live);
+        assert_eq!(get("/healthz", &stalled), 503);
+        assert_eq!(get("/readyz", &stalled), 503);
+    }
+
+    #[tokio::test]
+    async fn fails_to_serve_on_a_taken_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let health = Health::new(Duration::from_secs(60));
+        assert!(serve(addr, health).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+pub mod auth;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod health;
+pub mod ws;
+
+use crate::core::resources::event_store::EventQuery;
+
+/// Builds the query for archived events in a block range, with an
+/// optional event name.
+pub fn range_query(from_block: u64, to_block: Option<u64>, name: Option<&str>) -> EventQuery {
+    EventQuery {
+        from_block: Some(from_block),
+        to_block,
+        event: name.map(str::to_owned),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::resources::sink::DecodedEvent;
+
+    #[test]
+    fn can_filter_by_range() {
+        let event = DecodedEvent {
+            block_number: 10,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 0,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            event: "Trade".to_owned(),
+            signature: "Trade(address,uint256)".to_owned(),
+            schema: None,
+            data: json!({}),
+            raw: None,
+            ..Default::default()
+        };
+        assert!(range_query(0, None, None).matches(&event));
+        assert!(range_query(10, Some(10), Some("Trade")).matches(&event));
+        assert!(!range_query(11, None, None).matches(&event));
+        assert!(!range_query(0, Some(9), None).matches(&event));
+        assert!(!range_query(0, None, Some("Swap")).matches(&event));
+    }
+}
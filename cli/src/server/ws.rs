@@ -0,0 +1,196 @@
+use std::net::SocketAddr;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast::error::RecvError,
+};
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http, Message,
+};
+
+use super::auth::{ApiAuth, Scope};
+use crate::{core::resources::sink::DecodedEvent, resources::sinks::BroadcastSink};
+
+/// A subscription filter sent by a websocket client.
+///
+/// Clients send a filter as a JSON text message, e.g.
+/// `{"contract": "UniswapV2Router02", "event": "Trade"}`, and can
+/// replace it at any time by sending another one. Unset fields match
+/// all events.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Subscription {
+    /// The contract name, `File.sol:Contract` or address to match
+    pub contract: Option<String>,
+    /// The event name to match
+    pub event: Option<String>,
+}
+
+impl Subscription {
+    pub fn matches(&self, event: &DecodedEvent) -> bool {
+        let contract_matches = match &self.contract {
+            Some(contract) => {
+                event.contract == *contract
+                    || event.contract.rsplit(':').next() == Some(contract.as_str())
+                    || event.address.eq_ignore_ascii_case(contract)
+            }
+            None => true,
+        };
+        let event_matches = match &self.event {
+            Some(name) => event.event == *name,
+            None => true,
+        };
+        contract_matches && event_matches
+    }
+}
+
+/// Pushes live events to websocket clients as JSON.
+///
+/// With authentication enabled, clients send a read token in the
+/// `Authorization: Bearer <token>` header of the handshake, or in the
+/// `token` query parameter, since browsers can't set headers.
+pub struct WsServer {
+    broadcast: BroadcastSink,
+    auth: ApiAuth,
+}
+
+impl WsServer {
+    pub fn new(broadcast: BroadcastSink, auth: ApiAuth) -> Self {
+        WsServer { broadcast, auth }
+    }
+
+    /// Accepts websocket clients on the given address until it fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), std::io::Error> {
+        let listener = TcpListener::bind(addr).await?;
+        log::info!("Serving websocket on {}", addr);
+        self.auth.warn_if_open("websocket", addr);
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let broadcast = self.broadcast.clone();
+            let auth = self.auth.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_client(stream, broadcast, auth).await {
+                    log::debug!("Websocket client {} disconnected: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+/// Rejects the handshakes of clients without a read token.
+fn authorize(auth: &ApiAuth, request: &Request) -> Result<(), ErrorResponse> {
+    let header = request
+        .headers()
+        .get(http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    let query = request.uri().query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "token")
+            .map(|(_, token)| token.into_owned())
+    });
+    let result = match query {
+        Some(token) => auth.authorize(Some(&token), Scope::Read),
+        None => auth.authorize_header(header, Scope::Read),
+    };
+    result.map_err(|failure| {
+        http::Response::builder()
+            .status(failure.status())
+            .body(Some(failure.to_string()))
+            .unwrap()
+    })
+}
+
+async fn handle_client(
+    stream: TcpStream,
+    broadcast: BroadcastSink,
+    auth: ApiAuth,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let socket =
+        tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+            authorize(&auth, request).map(|_| response)
+        })
+        .await?;
+    let (mut outgoing, mut incoming) = socket.split();
+    let mut events = broadcast.subscribe();
+    let mut subscription = Subscription::default();
+
+    loop {
+        tokio::select! {
+            message = incoming.next() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                    Ok(filter) => subscription = filter,
+                    Err(e) => {
+                        let error = serde_json::json!({ "error": format!("Invalid subscription: {}", e) });
+                        outgoing.send(Message::Text(error.to_string())).await?;
+                    }
+                },
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+            },
+            event = events.recv() => match event {
+                Ok(event) if subscription.matches(&event) => {
+                    outgoing.send(Message::Text(serde_json::to_string(&event)?)).await?;
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Websocket client fell behind, skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn can_match_subscription() {
+        let event = DecodedEvent {
+            block_number: 17_000_000,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 0,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "UniswapV2Router02.sol:UniswapV2Router02".to_owned(),
+            event: "Trade".to_owned(),
+            signature: "Trade(address,uint256)".to_owned(),
+            schema: None,
+            data: json!({}),
+            raw: None,
+            ..Default::default()
+        };
+        let subscription = |value| serde_json::from_value::<Subscription>(value).unwrap();
+
+        assert!(subscription(json!({})).matches(&event));
+        assert!(subscription(json!({ "contract": "UniswapV2Router02" })).matches(&event));
+        assert!(subscription(
+            json!({ "contract": "UniswapV2Router02.sol:UniswapV2Router02", "event": "Trade" })
+        )
+        .matches(&event));
+        assert!(
+            subscription(json!({ "contract": "0x7A250D5630B4CF539739DF2C5DACB4C659F2488D" }))
+                .matches(&event)
+        );
+        assert!(!subscription(json!({ "event": "Swap" })).matches(&event));
+        assert!(!subscription(json!({ "contract": "Other" })).matches(&event));
+    }
+
+    #[test]
+    fn can_authorize_with_encoded_query_token() {
+        let auth = ApiAuth::new(&["read+token/=".to_owned()], &[]);
+        let request = |uri: &str| Request::builder().uri(uri).body(()).unwrap();
+
+        assert!(authorize(&auth, &request("/?token=read%2Btoken%2F%3D")).is_ok());
+        assert!(authorize(&auth, &request("/?other=1&token=read%2Btoken%2F%3D")).is_ok());
+        assert!(authorize(&auth, &request("/?token=read+token/=")).is_err());
+        assert!(authorize(&auth, &request("/")).is_err());
+    }
+}
@@ -0,0 +1,651 @@
+use std::collections::BTreeMap;
+
+use ethers::{
+    abi::{
+        param_type::Reader,
+        token::{LenientTokenizer, Tokenizer},
+        ParamType, Token,
+    },
+    types::{I256, U256},
+    utils::keccak256,
+};
+use serde::Deserialize;
+
+use crate::core::resources::artifacts::ArtifactsResource;
+
+mod path;
+
+use path::Segment;
+
+/// The longest string or bytes value read from storage, in bytes
+const MAX_BYTES_LENGTH: usize = 1 << 20;
+
+/// The storage layout solc outputs for a contract, with
+/// `forge build --extra-output storageLayout` or in the build info.
+#[derive(Clone, Debug, Deserialize)]
+pub struct StorageLayout {
+    /// The state variables, in declaration order
+    pub storage: Vec<StorageEntry>,
+    /// The types of the state variables, keyed by type id
+    #[serde(default)]
+    pub types: BTreeMap<String, StorageType>,
+}
+
+/// A state variable, or a member of a struct.
+#[derive(Clone, Debug, Deserialize)]
+pub struct StorageEntry {
+    /// The name of the variable
+    pub label: String,
+    /// The slot the variable starts at, relative to the struct for members
+    pub slot: String,
+    /// The byte offset in the slot, counted from the right
+    pub offset: usize,
+    /// The type id, e.g. `t_mapping(t_address,t_uint256)`
+    #[serde(rename = "type")]
+    pub type_id: String,
+}
+
+/// A type in the storage layout.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageType {
+    /// How the value is stored: `inplace`, `mapping`, `dynamic_array` or `bytes`
+    pub encoding: String,
+    /// The Solidity type, e.g. `mapping(address => uint256)`
+    pub label: String,
+    /// The number of bytes the value takes up in place
+    pub number_of_bytes: String,
+    /// The key type of a mapping
+    pub key: Option<String>,
+    /// The value type of a mapping
+    pub value: Option<String>,
+    /// The element type of an array
+    pub base: Option<String>,
+    /// The members of a struct
+    pub members: Option<Vec<StorageEntry>>,
+}
+
+/// Where a value is stored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    /// The slot the value starts at
+    pub slot: U256,
+    /// The byte offset in the slot, counted from the right
+    pub offset: usize,
+    /// The type id of the value
+    pub type_id: String,
+}
+
+/// Bytes to write into a slot, at a byte offset counted from the right.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotWrite {
+    pub slot: U256,
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+}
+
+impl SlotWrite {
+    /// Writes the bytes into the current slot value.
+    pub fn apply(&self, mut word: [u8; 32]) -> [u8; 32] {
+        let end = 32 - self.offset;
+        word[end - self.bytes.len()..end].copy_from_slice(&self.bytes);
+        word
+    }
+}
+
+impl StorageLayout {
+    /// Reads the storage layout of a contract from the artifacts.
+    pub fn from_artifacts<A: ArtifactsResource>(
+        artifacts_resource: &A,
+        file_name: &str,
+        contract_name: &str,
+    ) -> Result<Self, String> {
+        let layout = artifacts_resource
+            .get_storage_layout(file_name, contract_name)
+            .map_err(|e| format!("Error getting artifact: {}", e))?
+            .ok_or_else(|| {
+                format!(
+                    "No storage layout for {}:{}, please build with `forge build --extra-output storageLayout`",
+                    file_name, contract_name
+                )
+            })?;
+        serde_json::from_value(layout).map_err(|e| format!("Invalid storage layout: {}", e))
+    }
+
+    /// Finds where the value at a variable path is stored.
+    ///
+    /// The path starts with a state variable, followed by struct members
+    /// and mapping keys or array indexes, e.g. `balances[0xab...]` or
+    /// `pools[3].config.fee`.
+    pub fn locate(&self, path: &str) -> Result<Location, String> {
+        let (name, segments) = path::parse(path)?;
+        let entry = self
+            .storage
+            .iter()
+            .find(|entry| entry.label == name)
+            .ok_or_else(|| format!("No state variable named {}", name))?;
+        let mut location = Location {
+            slot: parse_u256(&entry.slot)?,
+            offset: entry.offset,
+            type_id: entry.type_id.clone(),
+        };
+
+        for segment in segments {
+            let ty = self.get_type(&location.type_id)?;
+            location = match (segment, ty.encoding.as_str()) {
+                (Segment::Member(member), "inplace") if ty.members.is_some() => {
+                    let member = ty
+                        .members
+                        .iter()
+                        .flatten()
+                        .find(|m| m.label == member)
+                        .ok_or_else(|| format!("{} has no member {}", ty.label, member))?;
+                    Location {
+                        slot: location.slot + parse_u256(&member.slot)?,
+                        offset: member.offset,
+                        type_id: member.type_id.clone(),
+                    }
+                }
+                (Segment::Index(key), "mapping") => {
+                    let key_type = self.get_type(ty.key.as_deref().unwrap_or_default())?;
+                    let mut preimage = encode_key(&key_type.label, &key)?;
+                    preimage.extend_from_slice(&word(location.slot));
+                    Location {
+                        slot: U256::from_big_endian(&keccak256(preimage)),
+                        offset: 0,
+                        type_id: ty.value.clone().unwrap_or_default(),
+                    }
+                }
+                (Segment::Index(index), "dynamic_array") => {
+                    let start = U256::from_big_endian(&keccak256(word(location.slot)));
+                    self.element(start, ty, &index, None)?
+                }
+                (Segment::Index(index), "inplace") if ty.base.is_some() => {
+                    let length = static_array_length(&ty.label);
+                    self.element(location.slot, ty, &index, length)?
+                }
+                (Segment::Member(member), _) => {
+                    return Err(format!(
+                        "{} is not a struct, can't get .{}",
+                        ty.label, member
+                    ))
+                }
+                (Segment::Index(key), _) => {
+                    return Err(format!(
+                        "{} is not a mapping or array, can't get [{}]",
+                        ty.label, key
+                    ))
+                }
+            };
+        }
+        Ok(location)
+    }
+
+    /// Encodes a value to store at a location, into the slots to write.
+    ///
+    /// Value types are written in place. Strings and bytes are written
+    /// like solc does, in place if they're shorter than 32 bytes, or to
+    /// the slots starting at the hash of the slot otherwise.
+    pub fn encode(&self, location: &Location, value: &str) -> Result<Vec<SlotWrite>, String> {
+        let ty = self.get_type(&location.type_id)?;
+        match ty.encoding.as_str() {
+            "bytes" => {
+                let data = if ty.label == "string" {
+                    unquote(value).as_bytes().to_vec()
+                } else {
+                    decode_hex(value)?
+                };
+                Ok(encode_bytes(location.slot, &data))
+            }
+            "inplace" if ty.members.is_none() && ty.base.is_none() => {
+                let size: usize = ty
+                    .number_of_bytes
+                    .parse()
+                    .map_err(|_| format!("Invalid size of {}", ty.label))?;
+                let word = encode_word(&ty.label, value)?;
+                let bytes = if is_fixed_bytes(&ty.label) {
+                    word[..size].to_vec()
+                } else {
+                    word[32 - size..].to_vec()
+                };
+                Ok(vec![SlotWrite {
+                    slot: location.slot,
+                    offset: location.offset,
+                    bytes,
+                }])
+            }
+            _ => Err(format!(
+                "{} is not a value type, a string or bytes, so it can't be set directly",
+                ty.label
+            )),
+        }
+    }
+
+    /// Returns the slots the data of a long string or bytes value is
+    /// stored in, given the value of its own slot. Other values are
+    /// stored in place.
+    pub fn data_slots(&self, location: &Location, first: [u8; 32]) -> Result<Vec<U256>, String> {
+        let ty = self.get_type(&location.type_id)?;
+        if ty.encoding != "bytes" || first[31] & 1 == 0 {
+            return Ok(Vec::new());
+        }
+        let length = long_bytes_length(first)?;
+        let start = U256::from_big_endian(&keccak256(word(location.slot)));
+        Ok((0..(length + 31) / 32)
+            .map(|i| start + U256::from(i))
+            .collect())
+    }
+
+    /// Decodes the value at a location, given the value of its slot,
+    /// and of its data slots for long strings and bytes.
+    ///
+    /// Dynamic arrays decode to their length. Mappings, structs and
+    /// static arrays must be narrowed down to a value first.
+    pub fn decode(
+        &self,
+        location: &Location,
+        first: [u8; 32],
+        data: &[[u8; 32]],
+    ) -> Result<String, String> {
+        let ty = self.get_type(&location.type_id)?;
+        match ty.encoding.as_str() {
+            "bytes" => {
+                let bytes = if first[31] & 1 == 0 {
+                    // Short values keep their length in the last byte
+                    let length = (first[31] / 2) as usize;
+                    if length > 31 {
+                        return Err(format!("Invalid length {} of short {}", length, ty.label));
+                    }
+                    first[..length].to_vec()
+                } else {
+                    let length = long_bytes_length(first)?;
+                    let data = data.concat();
+                    if data.len() < length {
+                        return Err(format!("Missing data slots for {}", ty.label));
+                    }
+                    data[..length].to_vec()
+                };
+                if ty.label == "string" {
+                    Ok(format!("{:?}", String::from_utf8_lossy(&bytes)))
+                } else {
+                    Ok(format!("0x{}", hex::encode(bytes)))
+                }
+            }
+            "dynamic_array" => Ok(format!("length {}", U256::from_big_endian(&first))),
+            "mapping" => Err(format!("{} is a mapping, add a key with [key]", ty.label)),
+            "inplace" if ty.members.is_some() => Err(format!(
+                "{} is a struct, select a member: {}",
+                ty.label,
+                ty.members
+                    .iter()
+                    .flatten()
+                    .map(|m| m.label.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            "inplace" if ty.base.is_some() => Err(format!(
+                "{} is an array, add an index with [index]",
+                ty.label
+            )),
+            "inplace" => {
+                let size: usize = ty
+                    .number_of_bytes
+                    .parse()
+                    .map_err(|_| format!("Invalid size of {}", ty.label))?;
+                decode_word(
+                    &ty.label,
+                    &first[32 - location.offset - size..32 - location.offset],
+                )
+            }
+            _ => Err(format!("Unsupported encoding {}", ty.encoding)),
+        }
+    }
+
+    pub fn get_type(&self, type_id: &str) -> Result<&StorageType, String> {
+        self.types
+            .get(type_id)
+            .ok_or_else(|| format!("Type {} is missing from the storage layout", type_id))
+    }
+
+    /// Locates an array element, given where the elements start.
+    fn element(
+        &self,
+        start: U256,
+        array: &StorageType,
+        index: &str,
+        length: Option<u64>,
+    ) -> Result<Location, String> {
+        let index: u64 = index
+            .parse()
+            .map_err(|_| format!("Invalid index {} into {}", index, array.label))?;
+        if let Some(length) = length {
+            if index >= length {
+                return Err(format!(
+                    "Index {} is out of bounds for {}",
+                    index, array.label
+                ));
+            }
+        }
+        let base_id = array.base.clone().unwrap_or_default();
+        let size: u64 = self
+            .get_type(&base_id)?
+            .number_of_bytes
+            .parse()
+            .map_err(|_| format!("Invalid size of {}", base_id))?;
+
+        // Small elements are packed into a slot, larger ones take up
+        // whole slots
+        let (slot, offset) = if size < 32 {
+            let per_slot = 32 / size;
+            (
+                start + U256::from(index / per_slot),
+                ((index % per_slot) * size) as usize,
+            )
+        } else {
+            (start + U256::from(index * ((size + 31) / 32)), 0)
+        };
+        Ok(Location {
+            slot,
+            offset,
+            type_id: base_id,
+        })
+    }
+}
+
+/// Returns the ABI type a storage value type is encoded as.
+pub fn abi_type(label: &str) -> Result<ParamType, String> {
+    let label = if label.starts_with("contract ") || label == "address payable" {
+        "address"
+    } else if label.starts_with("enum ") {
+        "uint8"
+    } else {
+        label
+    };
+    Reader::read(label).map_err(|_| format!("Unsupported type {}", label))
+}
+
+/// Whether a value type is left-aligned in its slot, like `bytes4`.
+pub fn is_fixed_bytes(label: &str) -> bool {
+    matches!(abi_type(label), Ok(ParamType::FixedBytes(_)))
+}
+
+/// ABI-encodes a value type into a 32 byte word.
+fn encode_word(label: &str, value: &str) -> Result<Vec<u8>, String> {
+    let param = abi_type(label)?;
+    let value = match param {
+        ParamType::Address | ParamType::FixedBytes(_) => value.trim_start_matches("0x"),
+        _ => unquote(value),
+    };
+    let token = LenientTokenizer::tokenize(&param, value)
+        .map_err(|e| format!("Invalid {} {}: {}", label, value, e))?;
+    Ok(ethers::abi::encode(&[token]))
+}
+
+/// Decodes a value type from its bytes in the slot.
+fn decode_word(label: &str, bytes: &[u8]) -> Result<String, String> {
+    let param = abi_type(label)?;
+    let size = bytes.len();
+    let mut word = [0u8; 32];
+    match param {
+        ParamType::FixedBytes(_) => word[..size].copy_from_slice(bytes),
+        ParamType::Int(_) if bytes[0] & 0x80 != 0 => {
+            word = [0xff; 32];
+            word[32 - size..].copy_from_slice(bytes);
+        }
+        _ => word[32 - size..].copy_from_slice(bytes),
+    }
+    let token = ethers::abi::decode(&[param], &word)
+        .map_err(|e| format!("Invalid {}: {}", label, e))?
+        .remove(0);
+    Ok(match token {
+        Token::Address(address) => format!("{:?}", address),
+        Token::Uint(value) => value.to_string(),
+        Token::Int(value) => I256::from_raw(value).to_string(),
+        Token::FixedBytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        token => token.to_string(),
+    })
+}
+
+/// Returns the length of a string or bytes value of 32 bytes or more,
+/// whose slot holds `length * 2 + 1`.
+fn long_bytes_length(first: [u8; 32]) -> Result<usize, String> {
+    let length = (U256::from_big_endian(&first) - 1) / 2;
+    if length > U256::from(MAX_BYTES_LENGTH) {
+        return Err(format!("Value is too long to read: {} bytes", length));
+    }
+    Ok(length.as_usize())
+}
+
+/// Encodes a mapping key for hashing. Value types are padded to 32 bytes,
+/// while strings and bytes are hashed as is.
+fn encode_key(label: &str, key: &str) -> Result<Vec<u8>, String> {
+    match label {
+        "string" => Ok(unquote(key).as_bytes().to_vec()),
+        "bytes" => decode_hex(key),
+        _ => encode_word(label, key),
+    }
+}
+
+/// Encodes a string or bytes value into the slots it's stored in.
+fn encode_bytes(slot: U256, data: &[u8]) -> Vec<SlotWrite> {
+    if data.len() < 32 {
+        let mut word = [0u8; 32];
+        word[..data.len()].copy_from_slice(data);
+        word[31] = (data.len() * 2) as u8;
+        return vec![SlotWrite {
+            slot,
+            offset: 0,
+            bytes: word.to_vec(),
+        }];
+    }
+
+    let start = U256::from_big_endian(&keccak256(word(slot)));
+    let mut writes = vec![SlotWrite {
+        slot,
+        offset: 0,
+        bytes: word(U256::from(data.len() * 2 + 1)).to_vec(),
+    }];
+    writes.extend(data.chunks(32).enumerate().map(|(i, chunk)| {
+        let mut word = [0u8; 32];
+        word[..chunk.len()].copy_from_slice(chunk);
+        SlotWrite {
+            slot: start + U256::from(i),
+            offset: 0,
+            bytes: word.to_vec(),
+        }
+    }));
+    writes
+}
+
+/// Returns the length of a static array, e.g. 3 for `uint256[3]`.
+fn static_array_length(label: &str) -> Option<u64> {
+    label
+        .strip_suffix(']')
+        .and_then(|label| label.rsplit_once('['))
+        .and_then(|(_, length)| length.parse().ok())
+}
+
+/// Parses a slot, which the layout stores as a decimal string.
+fn parse_u256(value: &str) -> Result<U256, String> {
+    U256::from_dec_str(value).map_err(|_| format!("Invalid slot {}", value))
+}
+
+pub fn word(value: U256) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    word
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value.trim_start_matches("0x")).map_err(|e| format!("Invalid hex {}: {}", value, e))
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> StorageLayout {
+        serde_json::from_str(
+            r#"{
+                "storage": [
+                    {"label": "owner", "offset": 0, "slot": "0", "type": "t_address"},
+                    {"label": "paused", "offset": 20, "slot": "0", "type": "t_bool"},
+                    {"label": "balances", "offset": 0, "slot": "1", "type": "t_mapping(t_address,t_uint256)"},
+                    {"label": "fees", "offset": 0, "slot": "2", "type": "t_array(t_uint16)dyn_storage"},
+                    {"label": "config", "offset": 0, "slot": "3", "type": "t_struct(Config)1_storage"},
+                    {"label": "name", "offset": 0, "slot": "5", "type": "t_string_storage"},
+                    {"label": "limits", "offset": 0, "slot": "6", "type": "t_array(t_uint256)3_storage"}
+                ],
+                "types": {
+                    "t_address": {"encoding": "inplace", "label": "address", "numberOfBytes": "20"},
+                    "t_bool": {"encoding": "inplace", "label": "bool", "numberOfBytes": "1"},
+                    "t_uint16": {"encoding": "inplace", "label": "uint16", "numberOfBytes": "2"},
+                    "t_uint256": {"encoding": "inplace", "label": "uint256", "numberOfBytes": "32"},
+                    "t_string_storage": {"encoding": "bytes", "label": "string", "numberOfBytes": "32"},
+                    "t_mapping(t_address,t_uint256)": {"encoding": "mapping", "key": "t_address", "label": "mapping(address => uint256)", "numberOfBytes": "32", "value": "t_uint256"},
+                    "t_array(t_uint16)dyn_storage": {"base": "t_uint16", "encoding": "dynamic_array", "label": "uint16[]", "numberOfBytes": "32"},
+                    "t_array(t_uint256)3_storage": {"base": "t_uint256", "encoding": "inplace", "label": "uint256[3]", "numberOfBytes": "96"},
+                    "t_struct(Config)1_storage": {"encoding": "inplace", "label": "struct Token.Config", "numberOfBytes": "64", "members": [
+                        {"label": "fee", "offset": 0, "slot": "0", "type": "t_uint256"},
+                        {"label": "admin", "offset": 0, "slot": "1", "type": "t_address"}
+                    ]}
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn can_locate_values() {
+        let layout = layout();
+        assert_eq!(
+            layout.locate("paused").unwrap(),
+            Location {
+                slot: U256::zero(),
+                offset: 20,
+                type_id: "t_bool".to_owned()
+            }
+        );
+        assert_eq!(layout.locate("config.admin").unwrap().slot, U256::from(4));
+        assert_eq!(layout.locate("limits[2]").unwrap().slot, U256::from(8));
+        assert!(layout.locate("limits[3]").is_err());
+
+        // keccak256(abi.encode(address(1), uint256(1)))
+        let key = "0x0000000000000000000000000000000000000001";
+        let mut preimage = [0u8; 64];
+        preimage[31] = 1;
+        preimage[63] = 1;
+        assert_eq!(
+            layout.locate(&format!("balances[{}]", key)).unwrap().slot,
+            U256::from_big_endian(&keccak256(preimage))
+        );
+
+        // uint16 elements are packed 16 to a slot
+        let start = U256::from_big_endian(&keccak256(word(U256::from(2))));
+        let fee = layout.locate("fees[17]").unwrap();
+        assert_eq!(fee.slot, start + U256::one());
+        assert_eq!(fee.offset, 2);
+
+        assert!(layout.locate("missing").is_err());
+        assert!(layout.locate("owner.fee").is_err());
+        assert!(layout.locate("config.missing").is_err());
+    }
+
+    #[test]
+    fn can_encode_values() {
+        let layout = layout();
+        let paused = layout.locate("paused").unwrap();
+        let writes = layout.encode(&paused, "true").unwrap();
+        assert_eq!(
+            writes,
+            vec![SlotWrite {
+                slot: U256::zero(),
+                offset: 20,
+                bytes: vec![1]
+            }]
+        );
+
+        // Packed values keep their neighbours
+        let mut word = [0xaa; 32];
+        word = writes[0].apply(word);
+        assert_eq!(word[11], 1);
+        assert_eq!(word[10], 0xaa);
+        assert_eq!(word[12], 0xaa);
+
+        let name = layout.locate("name").unwrap();
+        let writes = layout.encode(&name, "\"shadow\"").unwrap();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(&writes[0].bytes[..6], b"shadow");
+        assert_eq!(writes[0].bytes[31], 12);
+
+        let long = "a".repeat(40);
+        let writes = layout.encode(&name, &long).unwrap();
+        assert_eq!(writes.len(), 3);
+        assert_eq!(writes[0].bytes, super::word(U256::from(81)).to_vec());
+
+        let config = layout.locate("config").unwrap();
+        assert!(layout.encode(&config, "1").is_err());
+        assert!(layout.decode(&config, [0; 32], &[]).is_err());
+        let admin = layout.locate("config.admin").unwrap();
+        assert!(layout.encode(&admin, "not an address").is_err());
+    }
+
+    #[test]
+    fn can_decode_values() {
+        let layout = layout();
+        let mut slot = [0u8; 32];
+        for (path, value) in [
+            ("owner", "0xe09b0e8b5370528ab4f84c278b229c670ad91f4f"),
+            ("paused", "true"),
+        ] {
+            let location = layout.locate(path).unwrap();
+            slot = layout.encode(&location, value).unwrap()[0].apply(slot);
+        }
+        let owner = layout.locate("owner").unwrap();
+        assert_eq!(
+            layout.decode(&owner, slot, &[]).unwrap(),
+            "0xe09b0e8b5370528ab4f84c278b229c670ad91f4f"
+        );
+        let paused = layout.locate("paused").unwrap();
+        assert_eq!(layout.decode(&paused, slot, &[]).unwrap(), "true");
+
+        let name = layout.locate("name").unwrap();
+        let long = "a".repeat(40);
+        let writes = layout.encode(&name, &long).unwrap();
+        let first: [u8; 32] = writes[0].bytes.clone().try_into().unwrap();
+        assert_eq!(
+            layout.data_slots(&name, first).unwrap(),
+            writes[1..].iter().map(|w| w.slot).collect::<Vec<_>>()
+        );
+        let data: Vec<[u8; 32]> = writes[1..]
+            .iter()
+            .map(|w| w.bytes.clone().try_into().unwrap())
+            .collect();
+        assert_eq!(
+            layout.decode(&name, first, &data).unwrap(),
+            format!("{:?}", long)
+        );
+
+        // A short value can't be longer than its slot
+        let mut corrupted = [0u8; 32];
+        corrupted[31] = 0xfe;
+        assert!(layout.decode(&name, corrupted, &[]).is_err());
+
+        assert_eq!(decode_word("int8", &[0xff]).unwrap(), "-1");
+        assert_eq!(decode_word("bytes2", &[0xab, 0xcd]).unwrap(), "0xabcd");
+        assert_eq!(decode_word("enum Status", &[2]).unwrap(), "2");
+
+        let fees = layout.locate("fees").unwrap();
+        assert_eq!(
+            layout.decode(&fees, word(U256::from(3)), &[]).unwrap(),
+            "length 3"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+/// A step into a value in a variable path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+    /// A struct member, e.g. `.fee`
+    Member(String),
+    /// A mapping key or array index, e.g. `[0xab...]`
+    Index(String),
+}
+
+/// Parses a variable path like `pools[3].config.fee` into the state
+/// variable name and the steps into it.
+///
+/// Keys are taken verbatim up to the closing bracket, unless they're
+/// quoted, so string keys may contain brackets.
+pub fn parse(path: &str) -> Result<(String, Vec<Segment>), String> {
+    let path = path.trim();
+    let name_end = path.find(['.', '[']).unwrap_or(path.len());
+    let name = &path[..name_end];
+    if name.is_empty() {
+        return Err(format!("Invalid variable path {}", path));
+    }
+
+    let mut segments = Vec::new();
+    let mut rest = &path[name_end..];
+    while !rest.is_empty() {
+        if let Some(member) = rest.strip_prefix('.') {
+            let end = member.find(['.', '[']).unwrap_or(member.len());
+            if end == 0 {
+                return Err(format!("Missing member name in {}", path));
+            }
+            segments.push(Segment::Member(member[..end].to_owned()));
+            rest = &member[end..];
+        } else if let Some(key) = rest.strip_prefix('[') {
+            let end = closing_bracket(key)
+                .ok_or_else(|| format!("Missing closing bracket in {}", path))?;
+            segments.push(Segment::Index(key[..end].trim().to_owned()));
+            rest = &key[end + 1..];
+        } else {
+            return Err(format!("Invalid variable path {}", path));
+        }
+    }
+    Ok((name.to_owned(), segments))
+}
+
+/// Finds the bracket closing a key, skipping over quoted strings.
+fn closing_bracket(key: &str) -> Option<usize> {
+    let mut quoted = false;
+    for (i, c) in key.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ']' if !quoted => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_paths() {
+        assert_eq!(
+            parse("pools[3].config.fee").unwrap(),
+            (
+                "pools".to_owned(),
+                vec![
+                    Segment::Index("3".to_owned()),
+                    Segment::Member("config".to_owned()),
+                    Segment::Member("fee".to_owned()),
+                ]
+            )
+        );
+        assert_eq!(
+            parse(r#"names["a]b"]"#).unwrap().1,
+            vec![Segment::Index(r#""a]b""#.to_owned())]
+        );
+        assert_eq!(parse("owner").unwrap(), ("owner".to_owned(), vec![]));
+        assert!(parse("balances[0xab").is_err());
+        assert!(parse("config..fee").is_err());
+        assert!(parse("[1]").is_err());
+    }
+}
@@ -0,0 +1,366 @@
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use ethers::types::H160;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    config::{AlertRule, Config, NotifierKind},
+    core::{
+        actions::{
+            deploy::InitManifest,
+            fork::{
+                PinnedContract, PolledView, PostDeployTransaction, Scenario, TransactionRewrite,
+            },
+        },
+        resources::shadow::ShadowContract,
+    },
+    resources::{
+        migrations::{self, CURRENT_VERSION},
+        shadow::parse_contracts,
+    },
+};
+
+/// The kinds of files `shadow validate` checks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    /// The config file, `shadow.config.json`
+    Config,
+    /// The init calls of `shadow deploy --init-calls`
+    InitCalls,
+    /// A scenario of `shadow fork --scenario`
+    Scenario,
+    /// The shadow store, `shadow.json`
+    ShadowStore,
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileKind::Config => write!(f, "config"),
+            FileKind::InitCalls => write!(f, "init calls"),
+            FileKind::Scenario => write!(f, "scenario"),
+            FileKind::ShadowStore => write!(f, "shadow store"),
+        }
+    }
+}
+
+/// A problem found in a file, with its position when it's a syntax or
+/// schema error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The file
+    pub file: PathBuf,
+    /// The line and column, starting at 1
+    pub position: Option<(usize, usize)>,
+    /// What is wrong
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(file: &Path, message: impl Into<String>) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            position: None,
+            message: message.into(),
+        }
+    }
+
+    /// Turns a serde error into a diagnostic, moving its position out of
+    /// the message.
+    fn from_json(file: &Path, error: &serde_json::Error) -> Self {
+        let message = error.to_string();
+        if error.line() == 0 {
+            return Self::new(file, message);
+        }
+        let suffix = format!(" at line {} column {}", error.line(), error.column());
+        Self {
+            file: file.to_path_buf(),
+            position: Some((error.line(), error.column())),
+            message: message.strip_suffix(&suffix).unwrap_or(&message).to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.file.display(),
+                line,
+                column,
+                self.message
+            ),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+/// Checks a file, returning every problem found.
+///
+/// Syntax errors, unknown fields and invalid values are reported with
+/// their line and column. The file is then checked the way the commands
+/// reading it would, e.g. that addresses parse and that notifiers have
+/// the settings their kind needs.
+pub fn validate_file(kind: FileKind, path: &Path) -> Vec<Diagnostic> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return vec![Diagnostic::new(path, format!("Error reading file: {}", e))],
+    };
+    let problems = match kind {
+        FileKind::Config => parse::<Config>(path, &contents).map(|config| config_problems(&config)),
+        FileKind::InitCalls => {
+            parse::<InitManifest>(path, &contents).map(|manifest| init_call_problems(&manifest))
+        }
+        FileKind::Scenario => parse::<Scenario>(path, &contents)
+            .map(|_| Scenario::from_json(&contents).err().into_iter().collect()),
+        FileKind::ShadowStore => return validate_shadow_store(path, &contents),
+    };
+    match problems {
+        Ok(problems) => problems
+            .into_iter()
+            .map(|problem| Diagnostic::new(path, problem))
+            .collect(),
+        Err(diagnostic) => vec![diagnostic],
+    }
+}
+
+fn parse<T: DeserializeOwned>(path: &Path, contents: &str) -> Result<T, Diagnostic> {
+    serde_json::from_str(contents).map_err(|e| Diagnostic::from_json(path, &e))
+}
+
+/// Checks the settings that parse, but that the commands would reject.
+pub fn config_problems(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    if config.timeouts.rpc == 0 {
+        problems.push("timeouts.rpc must be at least 1 second".to_owned());
+    }
+    if let Some(verifier) = &config.verifier {
+        if verifier.url.trim().is_empty() {
+            problems.push("verifier.url is empty".to_owned());
+        }
+    }
+    for (index, notifier) in config.notifiers.iter().enumerate() {
+        let required = match notifier.kind {
+            NotifierKind::Discord | NotifierKind::Slack => {
+                vec![("webhookUrl", &notifier.webhook_url)]
+            }
+            NotifierKind::Telegram => vec![
+                ("botToken", &notifier.bot_token),
+                ("chatId", &notifier.chat_id),
+            ],
+        };
+        for (field, _) in required.into_iter().filter(|(_, value)| value.is_none()) {
+            problems.push(format!(
+                "notifiers[{}]: {:?} notifiers require a {}",
+                index, notifier.kind, field
+            ));
+        }
+        rule_problems(
+            &notifier.rule,
+            &format!("notifiers[{}].rule", index),
+            &mut problems,
+        );
+        for (rule, sampling) in notifier.sampling.iter().enumerate() {
+            if sampling.every == Some(0) {
+                problems.push(format!(
+                    "notifiers[{}].sampling[{}].every must be at least 1",
+                    index, rule
+                ));
+            }
+        }
+    }
+    for (index, sampling) in config.sampling.iter().enumerate() {
+        if sampling.every == Some(0) {
+            problems.push(format!("sampling[{}].every must be at least 1", index));
+        }
+    }
+    for (index, join) in config.joins.iter().enumerate() {
+        if join.name.trim().is_empty() {
+            problems.push(format!("joins[{}].name is empty", index));
+        }
+        rule_problems(
+            &join.event,
+            &format!("joins[{}].event", index),
+            &mut problems,
+        );
+        rule_problems(&join.with, &format!("joins[{}].with", index), &mut problems);
+    }
+    for (index, rewrite) in config.replay_rewrites.iter().enumerate() {
+        if let Err(e) = TransactionRewrite::try_from(rewrite) {
+            problems.push(format!("replayRewrites[{}]: {}", index, e));
+        }
+    }
+    for (index, call) in config.post_deploy.iter().enumerate() {
+        if let Err(e) = PostDeployTransaction::try_from(call) {
+            problems.push(format!("postDeploy[{}]: {}", index, e));
+        }
+    }
+    for (index, pin) in config.pins.iter().enumerate() {
+        if let Err(e) = PinnedContract::try_from(pin) {
+            problems.push(format!("pins[{}]: {}", index, e));
+        }
+    }
+    for (index, call) in config.view_calls.iter().enumerate() {
+        if let Err(e) = PolledView::try_from(call) {
+            problems.push(format!("viewCalls[{}]: {}", index, e));
+        }
+    }
+    problems
+}
+
+fn rule_problems(rule: &AlertRule, prefix: &str, problems: &mut Vec<String>) {
+    for (index, condition) in rule.conditions.iter().enumerate() {
+        if condition.field.trim().is_empty() {
+            problems.push(format!("{}.conditions[{}].field is empty", prefix, index));
+        }
+    }
+}
+
+fn init_call_problems(manifest: &InitManifest) -> Vec<String> {
+    manifest
+        .calls
+        .iter()
+        .enumerate()
+        .filter_map(|(index, call)| {
+            call.calldata()
+                .err()
+                .map(|e| format!("calls[{}] ({}): {}", index, call.label(index), e))
+        })
+        .collect()
+}
+
+/// Checks `shadow.json`. Files in an older format are checked after
+/// being upgraded, so their errors have no position.
+fn validate_shadow_store(path: &Path, contents: &str) -> Vec<Diagnostic> {
+    let value: Value = match parse(path, contents) {
+        Ok(value) => value,
+        Err(diagnostic) => return vec![diagnostic],
+    };
+    let contracts = match migrations::version(&value) {
+        Ok(CURRENT_VERSION) => {
+            parse_contracts(contents).map_err(|e| Diagnostic::from_json(path, &e))
+        }
+        Ok(_) => migrations::migrate(value)
+            .map_err(|e| Diagnostic::new(path, e))
+            .and_then(|(value, _)| {
+                serde_json::from_value::<Vec<ShadowContract>>(value["contracts"].clone())
+                    .map_err(|e| Diagnostic::from_json(path, &e))
+            }),
+        Err(e) => Err(Diagnostic::new(path, e)),
+    };
+    let contracts = match contracts {
+        Ok(contracts) => contracts,
+        Err(diagnostic) => return vec![diagnostic],
+    };
+
+    let mut seen = HashSet::new();
+    let mut diagnostics = Vec::new();
+    for (index, contract) in contracts.iter().enumerate() {
+        let label = format!(
+            "contracts[{}] ({}:{})",
+            index, contract.file_name, contract.contract_name
+        );
+        if contract.address.parse::<H160>().is_err() {
+            diagnostics.push(Diagnostic::new(
+                path,
+                format!("{}: invalid address {}", label, contract.address),
+            ));
+        } else if contract.address != contract.address.to_lowercase() {
+            diagnostics.push(Diagnostic::new(
+                path,
+                format!("{}: address {} must be lowercase", label, contract.address),
+            ));
+        }
+        if !seen.insert(contract.address.to_lowercase()) {
+            diagnostics.push(Diagnostic::new(
+                path,
+                format!("{}: {} is shadowed twice", label, contract.address),
+            ));
+        }
+        if contract.runtime_bytecode.is_empty() {
+            diagnostics.push(Diagnostic::new(
+                path,
+                format!("{}: runtimeBytecode is empty", label),
+            ));
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn validate(kind: FileKind, contents: &str) -> Vec<Diagnostic> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        validate_file(kind, file.path())
+    }
+
+    #[test]
+    fn reports_unknown_fields_with_position() {
+        let diagnostics = validate(
+            FileKind::Config,
+            "{\n  \"explorer\": {\n    \"apiUrl\": \"https://api.etherscan.io/api\",\n    \"authHeadr\": \"X-API-Key: secret\"\n  }\n}",
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].position.map(|(line, _)| line), Some(4));
+        assert!(diagnostics[0]
+            .message
+            .starts_with("unknown field `authHeadr`"));
+
+        let diagnostics = validate(
+            FileKind::Config,
+            r#"{ "notifiers": [{ "kind": "teams", "webhookUrl": "https://example.com" }] }"#,
+        );
+        assert!(diagnostics[0].message.contains("unknown variant `teams`"));
+    }
+
+    #[test]
+    fn reports_invalid_settings() {
+        let diagnostics = validate(
+            FileKind::Config,
+            r#"{
+                "notifiers": [{ "kind": "telegram", "botToken": "token" }],
+                "pins": [{ "address": "oracle" }]
+            }"#,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            messages[0],
+            "notifiers[0]: Telegram notifiers require a chatId"
+        );
+        assert!(messages[1].starts_with("pins[0]: "));
+
+        assert!(validate(FileKind::Config, "{}").is_empty());
+    }
+
+    #[test]
+    fn can_validate_shadow_store() {
+        let diagnostics = validate(
+            FileKind::ShadowStore,
+            r#"{ "version": 2, "contracts": [
+                { "fileName": "A.sol", "contractName": "A", "address": "0x00000000000000000000000000000000000000aa", "runtimeBytecode": "0x00" },
+                { "fileName": "B.sol", "contractName": "B", "address": "0x00000000000000000000000000000000000000aa", "runtimeBytecode": "0x00" }
+            ] }"#,
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.ends_with("is shadowed twice"));
+
+        let diagnostics = validate(
+            FileKind::ShadowStore,
+            r#"{ "version": 2, "contracts": [{}] }"#,
+        );
+        assert!(diagnostics[0].position.is_some());
+    }
+}
@@ -0,0 +1,38 @@
+//! Runs the CLI with and without `--profile`, which install different
+//! global loggers.
+
+use std::{path::Path, process::Command};
+
+fn list(dir: &Path, profile: Option<&Path>) -> std::process::Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_shadow"));
+    command
+        .current_dir(dir)
+        .arg("list")
+        .arg("--store-path")
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/resources/fixtures"
+        ));
+    if let Some(profile) = profile {
+        command.arg("--profile").arg(profile);
+    }
+    command.output().unwrap()
+}
+
+#[test]
+fn can_log_without_profile() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = list(dir.path(), None);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("UniswapV2Router02"));
+}
+
+#[test]
+fn can_log_with_profile() {
+    let dir = tempfile::tempdir().unwrap();
+    let trace = dir.path().join("trace.json");
+    let output = list(dir.path(), Some(&trace));
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("UniswapV2Router02"));
+    assert!(trace.exists());
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Represents a decoded shadow event, as delivered to sinks
+/// and stored in event archives.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedEvent {
+    /// The number of the block the event was emitted in
+    pub block_number: u64,
+    /// The timestamp of the block the event was emitted in
+    #[serde(default)]
+    pub block_timestamp: Option<u64>,
+    /// The hash of the transaction that emitted the event
+    pub transaction_hash: String,
+    /// The index of the log in the block
+    pub log_index: u64,
+    /// The address of the contract that emitted the event
+    pub address: String,
+    /// The contract that emitted the event, in the form `File.sol:Contract`
+    pub contract: String,
+    /// The name of the event
+    pub event: String,
+    /// The signature of the event, e.g. `Transfer(address,address,uint256)`
+    pub signature: String,
+    /// The id of the event ABI the event was decoded with, which changes
+    /// when the shape of the decoded parameters does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// The decoded event parameters
+    pub data: Value,
+    /// The raw log, if requested, so the event can be re-decoded later
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<RawLog>,
+    /// The balance changes around the event's transaction, if tracked
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub balance_diffs: Vec<BalanceDiff>,
+    /// Ties together the events decoded from the same transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation: Option<Correlation>,
+}
+
+impl DecodedEvent {
+    /// Whether the event is the composite record of a join rule, whose
+    /// signature joins the signatures of both events with `+`.
+    pub fn is_join(&self) -> bool {
+        self.signature.contains('+')
+    }
+}
+
+/// Identifies the transaction a [`DecodedEvent`] was emitted in, and its
+/// place among the transaction's decoded events.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Correlation {
+    /// The hash of the transaction, shared by its events
+    pub transaction_hash: String,
+    /// The index of the transaction in its block
+    pub transaction_index: u64,
+    /// The position of the event among the transaction's decoded
+    /// events, starting at 0
+    pub ordinal: u64,
+}
+
+/// The change of an ETH or ERC-20 balance of an account around the
+/// transaction that emitted a [`DecodedEvent`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceDiff {
+    /// The account whose balance changed
+    pub address: String,
+    /// `ETH`, or the address of the ERC-20 token
+    pub token: String,
+    /// The balance before, as a decimal string
+    pub before: String,
+    /// The balance after, as a decimal string
+    pub after: String,
+    /// The signed change, as a decimal string, e.g. `-1000`
+    pub change: String,
+}
+
+/// The raw log a [`DecodedEvent`] was decoded from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawLog {
+    /// The address of the contract that emitted the log
+    pub address: String,
+    /// The log topics, topic0 first
+    pub topics: Vec<String>,
+    /// The log data
+    pub data: String,
+    /// The number of the block the log was emitted in
+    pub block_number: u64,
+    /// The index of the log in the block
+    pub log_index: u64,
+}
+
+impl From<&ethers::types::Log> for RawLog {
+    fn from(log: &ethers::types::Log) -> Self {
+        RawLog {
+            address: format!("{:?}", log.address),
+            topics: log
+                .topics
+                .iter()
+                .map(|topic| format!("{:?}", topic))
+                .collect(),
+            data: format!("0x{}", hex::encode(&log.data)),
+            block_number: log.block_number.map(|n| n.as_u64()).unwrap_or_default(),
+            log_index: log.log_index.map(|i| i.as_u64()).unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<&RawLog> for ethers::types::Log {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(raw: &RawLog) -> Result<Self, Self::Error> {
+        let mut topics = Vec::with_capacity(raw.topics.len());
+        for topic in &raw.topics {
+            topics.push(topic.parse()?);
+        }
+        Ok(ethers::types::Log {
+            address: raw.address.parse()?,
+            topics,
+            data: raw.data.parse::<ethers::types::Bytes>()?,
+            block_number: Some(raw.block_number.into()),
+            log_index: Some(raw.log_index.into()),
+            ..Default::default()
+        })
+    }
+}
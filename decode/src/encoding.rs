@@ -0,0 +1,368 @@
+use std::borrow::Cow;
+
+use alloy_json_abi::{Event, Param};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use ethers::types::{Sign, I256, U256};
+use serde_json::Value;
+
+use super::event::component_key;
+use crate::DecodedEvent;
+
+/// The largest integer a float represents exactly, 2^53 - 1
+pub const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// How the integer values of decoded events are encoded in JSON.
+///
+/// The decoder renders integers as decimal strings, since uint256
+/// values don't fit in JSON numbers; this re-encodes them for sinks
+/// that need another representation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum NumericEncoding {
+    /// Decimal strings, e.g. `"255"`. Lossless, and castable to
+    /// Postgres `numeric` or BigQuery `BIGNUMERIC`.
+    #[default]
+    Decimal,
+    /// Hex strings, e.g. `"0xff"`, as read by `BigInt` in JavaScript.
+    /// Negative integers are prefixed with a minus sign.
+    Hex,
+    /// JSON numbers. Lossy: integers above 2^53 lose precision.
+    Float,
+}
+
+/// How the `bytes` and `bytesN` values of decoded events are encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum BytesEncoding {
+    /// Hex without a 0x prefix, e.g. `"0f99"`, as the decoder renders
+    /// them, and as events were written before the 0x prefix became
+    /// the default
+    Unprefixed,
+    /// 0x-prefixed hex, e.g. `"0x0f99"`, matching transaction hashes
+    /// and addresses
+    #[default]
+    Hex,
+    /// The UTF-8 text when it is printable, ignoring the zero padding
+    /// of `bytesN`, and 0x-prefixed hex otherwise
+    Utf8,
+    /// Standard base64, e.g. `"D5k="`
+    Base64,
+}
+
+/// Re-encodes the integer and bytes values of decoded events.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Encoder {
+    /// How integers are encoded
+    pub numbers: NumericEncoding,
+    /// How bytes are encoded
+    pub bytes: BytesEncoding,
+    /// Warn when a float is encoded from an integer larger than this
+    pub float_warn_above: f64,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Encoder {
+            numbers: NumericEncoding::Decimal,
+            bytes: BytesEncoding::Hex,
+            float_warn_above: MAX_SAFE_INTEGER,
+        }
+    }
+}
+
+impl Encoder {
+    /// Returns whether the encoder leaves values as the decoder renders them.
+    pub fn is_identity(&self) -> bool {
+        self.numbers == NumericEncoding::Decimal && self.bytes == BytesEncoding::Unprefixed
+    }
+
+    /// Returns a decoded event with its values re-encoded.
+    ///
+    /// Events decoded with another ABI than `abi`, like joined records,
+    /// are returned as is.
+    pub fn encode_event<'a>(&self, abi: &Event, event: &'a DecodedEvent) -> Cow<'a, DecodedEvent> {
+        if self.is_identity() || event.signature != abi.signature() {
+            return Cow::Borrowed(event);
+        }
+        let mut encoded = event.clone();
+        encoded.data = self.encode(abi, &event.data);
+        Cow::Owned(encoded)
+    }
+
+    /// Returns the decoded fields of an event with its integers and
+    /// bytes, found by the event's parameter types, re-encoded.
+    pub fn encode(&self, event: &Event, data: &Value) -> Value {
+        let mut data = data.clone();
+        if self.is_identity() {
+            return data;
+        }
+        if let Value::Object(fields) = &mut data {
+            for input in &event.inputs {
+                if let Some(value) = fields.get_mut(&input.name) {
+                    self.encode_param(&input.ty, &input.components, value);
+                }
+            }
+        }
+        data
+    }
+
+    fn encode_param(&self, ty: &str, components: &[Param], value: &mut Value) {
+        // The element type of e.g. `tuple[2][]` is `tuple[2]`
+        if let Some((element, _)) = ty.strip_suffix(']').and_then(|ty| ty.rsplit_once('[')) {
+            if let Value::Array(items) = value {
+                for item in items {
+                    self.encode_param(element, components, item);
+                }
+            }
+            return;
+        }
+        if !components.is_empty() {
+            if let Value::Object(fields) = value {
+                for (i, component) in components.iter().enumerate() {
+                    if let Some(value) = fields.get_mut(&component_key(i, component)) {
+                        self.encode_param(&component.ty, &component.components, value);
+                    }
+                }
+            }
+            return;
+        }
+
+        if ty.starts_with("uint") {
+            self.encode_integer(false, value);
+        } else if ty.starts_with("int") {
+            self.encode_integer(true, value);
+        } else if ty.starts_with("bytes") {
+            self.encode_bytes(ty != "bytes", value);
+        }
+    }
+
+    /// Re-encodes an integer rendered as a decimal string.
+    ///
+    /// Signed integers are rendered as their 256-bit two's complement.
+    fn encode_integer(&self, signed: bool, value: &mut Value) {
+        let Some(raw) = value.as_str().and_then(|s| U256::from_dec_str(s).ok()) else {
+            return;
+        };
+        let (sign, abs) = if signed {
+            I256::from_raw(raw).into_sign_and_abs()
+        } else {
+            (Sign::Positive, raw)
+        };
+        let minus = if sign == Sign::Negative { "-" } else { "" };
+        *value = match self.numbers {
+            NumericEncoding::Decimal => return,
+            NumericEncoding::Hex => Value::String(format!("{}{:#x}", minus, abs)),
+            NumericEncoding::Float => {
+                let Ok(float) = format!("{}{}", minus, abs).parse::<f64>() else {
+                    return;
+                };
+                if float.abs() > self.float_warn_above {
+                    log::warn!(
+                        "Encoding {}{} as a float loses precision: {}",
+                        minus,
+                        abs,
+                        float
+                    );
+                }
+                match serde_json::Number::from_f64(float) {
+                    Some(number) => Value::Number(number),
+                    None => return,
+                }
+            }
+        };
+    }
+
+    /// Re-encodes bytes rendered as unprefixed hex.
+    fn encode_bytes(&self, fixed: bool, value: &mut Value) {
+        let Some(bytes) = value.as_str().and_then(|s| hex::decode(s).ok()) else {
+            return;
+        };
+        let encoded = match self.bytes {
+            BytesEncoding::Unprefixed => return,
+            BytesEncoding::Hex => format!("0x{}", hex::encode(&bytes)),
+            BytesEncoding::Utf8 => {
+                // bytesN values are padded with zeros on the right
+                let text = if fixed {
+                    let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+                    &bytes[..len]
+                } else {
+                    &bytes[..]
+                };
+                match std::str::from_utf8(text) {
+                    Ok(text) if !text.is_empty() && !text.chars().any(char::is_control) => {
+                        text.to_owned()
+                    }
+                    _ => format!("0x{}", hex::encode(&bytes)),
+                }
+            }
+            BytesEncoding::Base64 => STANDARD.encode(&bytes),
+        };
+        *value = Value::String(encoded);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn swap_event() -> Event {
+        serde_json::from_value(json!({
+            "name": "Swap",
+            "type": "event",
+            "inputs": [
+                { "name": "sender", "type": "address", "indexed": true },
+                { "name": "amount", "type": "int256", "indexed": false },
+                { "name": "ticks", "type": "uint24[]", "indexed": false },
+                { "name": "fee", "type": "tuple", "indexed": false, "components": [
+                    { "name": "token", "type": "address" },
+                    { "name": "value", "type": "uint256" }
+                ]}
+            ],
+            "anonymous": false
+        }))
+        .unwrap()
+    }
+
+    fn swap() -> Value {
+        json!({
+            "sender": "0x8c3c",
+            // -1 as a 256-bit two's complement
+            "amount": U256::MAX.to_string(),
+            "ticks": ["255", "16"],
+            "fee": { "token": "0x8c3c", "value": "9007199254740993" }
+        })
+    }
+
+    fn numbers(numbers: NumericEncoding) -> Encoder {
+        Encoder {
+            numbers,
+            bytes: BytesEncoding::Unprefixed,
+            ..Default::default()
+        }
+    }
+
+    fn bytes(bytes: BytesEncoding) -> Encoder {
+        Encoder {
+            bytes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_encode_integers() {
+        let event = swap_event();
+        assert_eq!(
+            numbers(NumericEncoding::Decimal).encode(&event, &swap()),
+            swap()
+        );
+        assert_eq!(
+            numbers(NumericEncoding::Hex).encode(&event, &swap()),
+            json!({
+                "sender": "0x8c3c",
+                "amount": "-0x1",
+                "ticks": ["0xff", "0x10"],
+                "fee": { "token": "0x8c3c", "value": "0x20000000000001" }
+            })
+        );
+        assert_eq!(
+            numbers(NumericEncoding::Float).encode(&event, &swap()),
+            json!({
+                "sender": "0x8c3c",
+                "amount": -1.0,
+                "ticks": [255.0, 16.0],
+                "fee": { "token": "0x8c3c", "value": 9007199254740992.0 }
+            })
+        );
+    }
+
+    #[test]
+    fn can_encode_bytes() {
+        let event: Event = serde_json::from_value(json!({
+            "name": "Named",
+            "type": "event",
+            "inputs": [
+                { "name": "orderHash", "type": "bytes32", "indexed": true },
+                { "name": "symbol", "type": "bytes32", "indexed": false },
+                { "name": "data", "type": "bytes", "indexed": false }
+            ],
+            "anonymous": false
+        }))
+        .unwrap();
+        let data = json!({
+            "orderHash": "0f99",
+            "symbol": format!("{:0<64}", hex::encode("USDC")),
+            "data": hex::encode("hi\n")
+        });
+
+        assert_eq!(
+            bytes(BytesEncoding::Hex).encode(&event, &data),
+            json!({
+                "orderHash": "0x0f99",
+                "symbol": format!("0x{:0<64}", hex::encode("USDC")),
+                "data": "0x68690a"
+            })
+        );
+        assert_eq!(
+            bytes(BytesEncoding::Utf8).encode(&event, &data),
+            json!({ "orderHash": "0x0f99", "symbol": "USDC", "data": "0x68690a" })
+        );
+        assert_eq!(
+            bytes(BytesEncoding::Base64).encode(&event, &data)["data"],
+            json!("aGkK")
+        );
+
+        // Bytes are prefixed by default
+        assert_eq!(
+            Encoder::default().encode(&event, &data),
+            bytes(BytesEncoding::Hex).encode(&event, &data)
+        );
+        assert_eq!(bytes(BytesEncoding::Unprefixed).encode(&event, &data), data);
+    }
+
+    #[test]
+    fn can_encode_nested_bytes() {
+        // Seaport's OrderFulfilled-like parameters: bytes in a tuple, and
+        // in the tuples of an array within it
+        let event: Event = serde_json::from_value(json!({
+            "name": "OrderValidated",
+            "type": "event",
+            "inputs": [
+                { "name": "orderHash", "type": "bytes32", "indexed": false },
+                { "name": "parameters", "type": "tuple", "indexed": false, "components": [
+                    { "name": "offerer", "type": "address" },
+                    { "name": "zoneHash", "type": "bytes32" },
+                    { "name": "offer", "type": "tuple[]", "components": [
+                        { "name": "token", "type": "address" },
+                        { "name": "extraData", "type": "bytes" }
+                    ]},
+                    { "name": "", "type": "bytes32" }
+                ]}
+            ],
+            "anonymous": false
+        }))
+        .unwrap();
+        let data = json!({
+            "orderHash": "0f99",
+            "parameters": {
+                "offerer": "0x8c3c",
+                "zoneHash": "00ff",
+                "offer": [{ "token": "0x8c3c", "extraData": "abcd" }],
+                "3": "1234"
+            }
+        });
+
+        assert_eq!(
+            Encoder::default().encode(&event, &data),
+            json!({
+                "orderHash": "0x0f99",
+                "parameters": {
+                    "offerer": "0x8c3c",
+                    "zoneHash": "0x00ff",
+                    "offer": [{ "token": "0x8c3c", "extraData": "0xabcd" }],
+                    "3": "0x1234"
+                }
+            })
+        );
+    }
+}
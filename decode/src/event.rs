@@ -1,21 +1,28 @@
-use alloy_json_abi::{Event, Param};
+use alloy_json_abi::{Event, EventParam, Param};
 use serde::{Serialize, Serializer};
 use serde_json::Value;
 
 use super::param::ToEthAbiParamType;
 use super::token::Token;
 
+/// The key of the errors of the parameters that failed to decode
+pub const ERRORS_KEY: &str = "_errors";
+
 /// Decodes a log using the given event ABI.
 ///
 /// Returns a JSON object with the parameter names as
 /// keys and the decoded topics and data as values.
 ///
+/// Parameters that fail to decode are left out, with their error
+/// under `_errors`, so one malformed field doesn't lose the others.
+///
 /// Example:
 /// {
 ///     "from": "0x73ede13ab9c28bc4302e94c1d1e7f755988a9158",
 ///     "to": "0x91364516d3cad16e1666261dbdbb39c881dbe9ee",
 ///     "value": "69000000000000000000"
 /// }
+#[tracing::instrument(skip_all, fields(event = %event.name))]
 pub fn decode_log(
     log: &ethers::types::Log,
     event: &Event,
@@ -32,55 +39,64 @@ pub fn decode_log(
     Ok(topics)
 }
 
+/// Returns the errors of the parameters of a decoded log that failed
+/// to decode, by parameter name.
+pub fn decode_errors(decoded: &Value) -> Option<&serde_json::Map<String, Value>> {
+    decoded.get(ERRORS_KEY).and_then(Value::as_object)
+}
+
 /// Decodes the log topics using the given event ABI.
 ///
 /// Returns a JSON object with the parameter names as
 /// keys and the decoded topics as values.
+///
+/// Indexed strings, bytes, arrays and structs are stored as the
+/// keccak256 hash of their encoding, whose preimage can't be recovered
+/// from the log. Their topic is returned as is, under `<name>_hash`.
 fn decode_topics(
     log: &ethers::types::Log,
     event: &Event,
 ) -> Result<Value, Box<dyn std::error::Error>> {
-    // Get the indexed parameters
-    let indexed_params = event
-        .inputs
-        .iter()
-        .filter(|input| input.indexed)
-        .map(|p| p.to_owned())
-        .collect::<Vec<_>>();
-
-    // Build the ethabi types
-    let mut ethabi_types = Vec::new();
-    for param in indexed_params.iter() {
-        ethabi_types.push(param.to_eth_abi_param_type()?);
-    }
-
-    // Combine the topic bytes
-    let topics = log
-        .topics
-        .iter()
-        .skip(1)
-        .flat_map(|t| t.as_bytes())
-        .map(|b| b.to_owned())
-        .collect::<Vec<_>>();
-
-    // Decode the topics
-    let tokens = ethabi::decode_whole(&ethabi_types, &topics)?;
+    let indexed_params = event.inputs.iter().filter(|input| input.indexed);
 
-    // Build the map
+    // Build the map, with a topic per indexed parameter after topic0
     let mut map = serde_json::Map::new();
-    for (i, event_param) in indexed_params.iter().enumerate() {
-        let param = Param {
-            name: event_param.name.clone(),
-            ty: event_param.ty.clone(),
-            internal_type: event_param.internal_type.clone(),
-            components: event_param.components.clone(),
-        };
-        let token = Token::new(tokens[i].clone());
-        let param_and_token = ParamAndValue {
-            param,
-            value: token,
+    let mut errors = serde_json::Map::new();
+    for (i, event_param) in indexed_params.enumerate() {
+        let Some(topic) = log.topics.get(i + 1) else {
+            errors.insert(
+                event_param.name.clone(),
+                Value::String("Missing topic".to_owned()),
+            );
+            continue;
         };
-        map.insert(event_param.name.clone(), param_and_token.to_value());
+        if is_hashed_topic(event_param) {
+            map.insert(
+                topic_hash_key(&event_param.name),
+                Value::String(format!("{:?}", topic)),
+            );
+            continue;
+        }
+
+        let token = event_param
+            .to_eth_abi_param_type()
+            .map_err(|e| e.to_string())
+            .and_then(|ty| {
+                ethabi::decode_whole(&[ty], topic.as_bytes())
+                    .map(|mut tokens| tokens.remove(0))
+                    .map_err(|e| e.to_string())
+            });
+        match token {
+            Ok(token) => {
+                map.insert(event_param.name.clone(), param_value(event_param, token));
+            }
+            Err(e) => {
+                errors.insert(event_param.name.clone(), Value::String(e));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        map.insert(ERRORS_KEY.to_owned(), Value::Object(errors));
     }
 
     // Create the value
@@ -89,6 +105,31 @@ fn decode_topics(
     Ok(value)
 }
 
+/// Returns whether an indexed parameter is stored in its topic as the
+/// keccak256 hash of its value, as strings, bytes, arrays and structs are.
+pub fn is_hashed_topic(param: &EventParam) -> bool {
+    param.ty == "string"
+        || param.ty == "bytes"
+        || param.ty.ends_with(']')
+        || !param.components.is_empty()
+}
+
+/// Returns the key the topic of a hashed indexed parameter is decoded
+/// under, e.g. `tags_hash` for `string[] indexed tags`.
+pub fn topic_hash_key(name: &str) -> String {
+    format!("{}_hash", name)
+}
+
+/// Returns the key a parameter is decoded under: its name, or
+/// `<name>_hash` for the topic of a hashed indexed parameter.
+pub fn param_key(param: &EventParam) -> String {
+    if param.indexed && is_hashed_topic(param) {
+        topic_hash_key(&param.name)
+    } else {
+        param.name.clone()
+    }
+}
+
 /// Decodes log data using the given event ABI.
 ///
 /// Returns a JSON object with the parameter names as
@@ -102,33 +143,36 @@ fn decode_data(
         .inputs
         .iter()
         .filter(|input| !input.indexed)
-        .map(|p| p.to_owned())
         .collect::<Vec<_>>();
 
     // Build the ethabi types
-    let mut eth_abi_types = Vec::new();
-    for param in non_indexed_params.iter() {
-        eth_abi_types.push(param.to_eth_abi_param_type()?);
-    }
+    let eth_abi_types = non_indexed_params
+        .iter()
+        .map(|param| param.to_eth_abi_param_type().map_err(|e| e.to_string()))
+        .collect::<Vec<_>>();
 
-    // Decode the data
-    let tokens = ethabi::decode(&eth_abi_types, &log.data)?;
+    // Decode the data, one parameter at a time if it fails as a whole
+    let all_types = eth_abi_types.iter().cloned().collect::<Result<Vec<_>, _>>();
+    let tokens = match all_types.map(|types| ethabi::decode(&types, &log.data)) {
+        Ok(Ok(tokens)) => tokens.into_iter().map(Ok).collect(),
+        _ => decode_each(&eth_abi_types, &log.data),
+    };
 
     // Build the token map
     let mut map = serde_json::Map::new();
-    for (i, event_param) in non_indexed_params.iter().enumerate() {
-        let param = Param {
-            name: event_param.name.clone(),
-            ty: event_param.ty.clone(),
-            internal_type: event_param.internal_type.clone(),
-            components: event_param.components.clone(),
-        };
-        let token = Token::new(tokens[i].clone());
-        let param_and_token = ParamAndValue {
-            param,
-            value: token,
-        };
-        map.insert(event_param.name.clone(), param_and_token.to_value());
+    let mut errors = serde_json::Map::new();
+    for (event_param, token) in non_indexed_params.iter().zip(tokens) {
+        match token {
+            Ok(token) => {
+                map.insert(event_param.name.clone(), param_value(event_param, token));
+            }
+            Err(e) => {
+                errors.insert(event_param.name.clone(), Value::String(e));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        map.insert(ERRORS_KEY.to_owned(), Value::Object(errors));
     }
 
     // Create the value
@@ -137,6 +181,83 @@ fn decode_data(
     Ok(value)
 }
 
+/// Decodes each parameter of ABI-encoded data on its own, from its
+/// head, so a malformed parameter doesn't fail the others.
+///
+/// The parameters after one of unknown type can't be located.
+fn decode_each(
+    types: &[Result<ethabi::ParamType, String>],
+    data: &[u8],
+) -> Vec<Result<ethabi::Token, String>> {
+    let mut head = Some(0);
+    types
+        .iter()
+        .map(|ty| {
+            let (Some(offset), Ok(ty)) = (head, ty) else {
+                head = None;
+                return Err(match ty {
+                    Err(e) => e.clone(),
+                    Ok(_) => "Follows a parameter of unknown type".to_owned(),
+                });
+            };
+            head = Some(offset + head_size(ty));
+            decode_at(ty, data, offset)
+        })
+        .collect()
+}
+
+/// Decodes a parameter whose head is at `offset` in the data.
+fn decode_at(ty: &ethabi::ParamType, data: &[u8], offset: usize) -> Result<ethabi::Token, String> {
+    let encoded = if ty.is_dynamic() {
+        // The head is the offset of the value, rebased to follow a new head
+        let word = data
+            .get(offset..offset + 32)
+            .ok_or("Data too short for the parameter")?;
+        let start = ethers::types::U256::from_big_endian(word);
+        let tail = usize::try_from(start)
+            .ok()
+            .and_then(|start| data.get(start..))
+            .ok_or("Parameter offset out of bounds")?;
+        let mut encoded = ethabi::encode(&[ethabi::Token::Uint(32.into())]);
+        encoded.extend_from_slice(tail);
+        encoded
+    } else {
+        data.get(offset..)
+            .ok_or("Data too short for the parameter")?
+            .to_vec()
+    };
+    ethabi::decode(&[ty.clone()], &encoded)
+        .map(|mut tokens| tokens.remove(0))
+        .map_err(|e| e.to_string())
+}
+
+/// Returns the size of a parameter's head in ABI-encoded data.
+fn head_size(ty: &ethabi::ParamType) -> usize {
+    if ty.is_dynamic() {
+        return 32;
+    }
+    match ty {
+        ethabi::ParamType::FixedArray(inner, len) => head_size(inner) * len,
+        ethabi::ParamType::Tuple(types) => types.iter().map(head_size).sum(),
+        _ => 32,
+    }
+}
+
+/// Converts the decoded value of an event parameter to JSON.
+fn param_value(event_param: &EventParam, token: ethabi::Token) -> Value {
+    let param = Param {
+        name: event_param.name.clone(),
+        ty: event_param.ty.clone(),
+        internal_type: event_param.internal_type.clone(),
+        components: event_param.components.clone(),
+    };
+    let param_and_token = ParamAndValue {
+        param,
+        value: Token::new(token),
+    };
+    param_and_token.to_value()
+}
+
 fn merge(a: &mut Value, b: Value) {
     match (a, b) {
         (a @ &mut Value::Object(_), Value::Object(b)) => {
@@ -156,83 +277,61 @@ fn merge(a: &mut Value, b: Value) {
 ///
 /// The value can be a simple value (e.g. 1)
 /// or a complex value (e.g. (string, address, uint256)).
-struct ParamAndValue {
+pub(super) struct ParamAndValue {
     pub param: Param,
     pub value: Token,
 }
 
 impl ParamAndValue {
     pub fn to_value(&self) -> serde_json::Value {
-        if self.param.is_complex_type() {
-            // Get the components of the complex type
-            let param_components = self.param.components.clone();
-
-            // We have an array of complex values (e.g. Swap[])
-            //
-            // To handle an array of complex values, we need to
-            // iterate over the array and decode each value.
-            //
-            // In the case of an array, the underlying value is an
-            // array of complex values (e.g. [(string, address, uint256), (string, address, uint256)]).
-            // We need to iterate over each of those complex values
-            // and map the parameter names with the decoded fields.
-            //
-            // We do this by creating a new [`ParamAndValue`] for each
-            // item in the array (which all share the same complex param type).
-            // Then we call `to_value()` on each of those [`ParamAndValue`]s.
-            //
-            // Example:
-            //  param_components = Array(Tuple(string, address, uint256))
-            //  nested_values = Token(Array([("abc", "0x0000", 1), ("def", "0x0000", 2)]))
-            if let ethabi::Token::Array(values) = self.value.underlying() {
-                let array_values = values
-                    .iter()
-                    .map(|t| {
-                        let param_and_value = ParamAndValue {
-                            param: self.param.clone(),
-                            value: Token::new(t.clone()),
-                        };
-                        param_and_value.to_value()
-                    })
-                    .collect::<Vec<_>>();
-                return serde_json::to_value(&array_values).unwrap();
-            }
+        token_to_value(&self.param.components, &self.value)
+    }
+}
+
+/// Returns the key of a tuple component in its decoded object: its
+/// name, or its position if it is unnamed.
+pub fn component_key(index: usize, component: &Param) -> String {
+    if component.name.is_empty() {
+        index.to_string()
+    } else {
+        component.name.clone()
+    }
+}
 
-            // We have a complex type (e.g. Swap)
-            //
-            // To handle a complex type, we need to map the parameter names
-            // with the decoded values.
-            //
-            // Example:
-            //  param_components = Tuple(string, address, uint256)
-            //  nested_values = Token("abc", "0x0000", 1)
-            let nested_values = self.value.clone().into_tokens();
-            let param_and_values = param_components
+/// Converts a decoded value to JSON, recursing into arrays and tuples.
+///
+/// Arrays, fixed or dynamic and nested to any depth, become arrays.
+/// Tuples become objects keyed by component name, or by position for
+/// unnamed components, with `components` naming the fields of every
+/// tuple in the value (e.g. each `Swap` of a `Swap[][]`). Other values
+/// are rendered as strings.
+///
+/// Example:
+///  components = (string name, address token, uint256 amount)
+///  token = Array([("abc", 0x0000, 1), ("def", 0x0000, 2)])
+///  value = [{"name": "abc", "token": "0x0000", "amount": "1"}, ...]
+fn token_to_value(components: &[Param], token: &ethabi::Token) -> Value {
+    match token {
+        ethabi::Token::Array(tokens) | ethabi::Token::FixedArray(tokens) => Value::Array(
+            tokens
+                .iter()
+                .map(|token| token_to_value(components, token))
+                .collect(),
+        ),
+        ethabi::Token::Tuple(tokens) => Value::Object(
+            components
                 .iter()
-                .zip(nested_values.iter())
-                .map(|(param, token)| ParamAndValue {
-                    param: param.clone(),
-                    value: Token::new(token.clone()),
+                .zip(tokens)
+                .enumerate()
+                .map(|(i, (component, token))| {
+                    (
+                        component_key(i, component),
+                        token_to_value(&component.components, token),
+                    )
                 })
-                .fold(serde_json::Map::new(), |mut acc, param_and_token| {
-                    acc.insert(
-                        param_and_token.param.name.clone(),
-                        param_and_token.to_value(),
-                    );
-                    acc
-                });
-            serde_json::to_value(&param_and_values).unwrap()
-        } else {
-            // If we have an array of simple values (e.g. uint256[]),
-            // convert the array of values to an array of strings.
-            if let ethabi::Token::Array(tokens) = self.value.underlying() {
-                let array_values = tokens.iter().map(|t| t.to_string()).collect::<Vec<_>>();
-                return serde_json::to_value(array_values).unwrap();
-            }
-
-            // Otherwise, just convert the value to a string.
-            serde_json::to_value(self.value.to_string()).unwrap()
-        }
+                .collect(),
+        ),
+        token => Value::String(Token::new(token.clone()).to_string()),
     }
 }
 
@@ -413,6 +512,185 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn can_decode_hashed_topics() {
+        let event: Event = serde_json::from_value(json!({
+            "name": "Tagged",
+            "type": "event",
+            "inputs": [
+                { "name": "owner", "type": "address", "indexed": true },
+                { "name": "tags", "type": "string[]", "indexed": true },
+                { "name": "order", "type": "tuple", "indexed": true, "components": [
+                    { "name": "amount", "type": "uint256" }
+                ]},
+                { "name": "count", "type": "uint256", "indexed": false }
+            ],
+            "anonymous": false
+        }))
+        .unwrap();
+        let log = Log {
+            topics: vec![
+                ethers::types::H256::repeat_byte(0x00),
+                ethers::types::H256::from(ethers::types::H160::repeat_byte(0xab)),
+                ethers::types::H256::repeat_byte(0x11),
+                ethers::types::H256::repeat_byte(0x22),
+            ],
+            data: ethabi::encode(&[ethabi::Token::Uint(3.into())]).into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            decode_log(&log, &event).unwrap(),
+            json!({
+                "owner": "0xabababababababababababababababababababab",
+                "tags_hash": format!("0x{}", "11".repeat(32)),
+                "order_hash": format!("0x{}", "22".repeat(32)),
+                "count": "3"
+            })
+        );
+    }
+
+    #[test]
+    fn can_decode_malformed_logs() {
+        let event: Event = serde_json::from_value(json!({
+            "name": "Deposit",
+            "type": "event",
+            "inputs": [
+                { "name": "owner", "type": "address", "indexed": true },
+                { "name": "flag", "type": "bool", "indexed": false },
+                { "name": "memo", "type": "string", "indexed": false },
+                { "name": "amount", "type": "uint256", "indexed": false }
+            ],
+            "anonymous": false
+        }))
+        .unwrap();
+        let mut data = ethabi::encode(&[
+            ethabi::Token::Bool(true),
+            ethabi::Token::String("gm".to_owned()),
+            ethabi::Token::Uint(3.into()),
+        ]);
+        // Not a bool, and no topic for the owner
+        data[31] = 2;
+        let log = Log {
+            topics: vec![ethers::types::H256::repeat_byte(0x00)],
+            data: data.into(),
+            ..Default::default()
+        };
+
+        let decoded = decode_log(&log, &event).unwrap();
+        assert_eq!(decoded["memo"], json!("gm"));
+        assert_eq!(decoded["amount"], json!("3"));
+        assert!(decoded.get("flag").is_none());
+        let errors = decode_errors(&decoded).unwrap();
+        assert_eq!(errors.keys().collect::<Vec<_>>(), vec!["flag", "owner"]);
+        assert_eq!(errors["owner"], json!("Missing topic"));
+    }
+
+    fn param(value: serde_json::Value) -> Param {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn to_value(param: &Param, token: ethabi::Token) -> Value {
+        ParamAndValue {
+            param: param.clone(),
+            value: Token::new(token),
+        }
+        .to_value()
+    }
+
+    fn item(item_type: u8, amount: u64) -> ethabi::Token {
+        ethabi::Token::Tuple(vec![
+            ethabi::Token::Uint(item_type.into()),
+            ethabi::Token::Uint(amount.into()),
+        ])
+    }
+
+    #[test]
+    fn can_convert_nested_values() {
+        let item_components = json!([
+            { "name": "itemType", "type": "uint8" },
+            { "name": "amount", "type": "uint256" }
+        ]);
+
+        // Seaport's OrderComponents: a tuple with arrays of tuples
+        let order = param(json!({
+            "name": "order",
+            "type": "tuple",
+            "components": [
+                { "name": "offerer", "type": "address" },
+                { "name": "offer", "type": "tuple[]", "components": item_components },
+                { "name": "zoneHash", "type": "bytes32" },
+                { "name": "counters", "type": "uint256[2]" }
+            ]
+        }));
+        let token = ethabi::Token::Tuple(vec![
+            ethabi::Token::Address(ethers::types::H160::repeat_byte(0xab)),
+            ethabi::Token::Array(vec![item(2, 1), item(0, 45)]),
+            ethabi::Token::FixedBytes(vec![0x0f; 32]),
+            ethabi::Token::FixedArray(vec![
+                ethabi::Token::Uint(255.into()),
+                ethabi::Token::Uint(1.into()),
+            ]),
+        ]);
+        assert_eq!(
+            to_value(&order, token),
+            json!({
+                "offerer": "0xabababababababababababababababababababab",
+                "offer": [
+                    { "itemType": "2", "amount": "1" },
+                    { "itemType": "0", "amount": "45" }
+                ],
+                "zoneHash": "0f".repeat(32),
+                "counters": ["255", "1"]
+            })
+        );
+
+        // Arrays of arrays of tuples, fixed and dynamic
+        let batches = param(json!({
+            "name": "batches",
+            "type": "tuple[2][]",
+            "components": item_components
+        }));
+        let token = ethabi::Token::Array(vec![ethabi::Token::FixedArray(vec![
+            item(1, 10),
+            item(3, 30),
+        ])]);
+        assert_eq!(
+            to_value(&batches, token),
+            json!([[
+                { "itemType": "1", "amount": "10" },
+                { "itemType": "3", "amount": "30" }
+            ]])
+        );
+
+        // Nested arrays of values, and unnamed tuple components
+        let matrix = param(json!({ "name": "matrix", "type": "uint256[][]" }));
+        let token = ethabi::Token::Array(vec![
+            ethabi::Token::Array(vec![ethabi::Token::Uint(16.into())]),
+            ethabi::Token::Array(vec![]),
+        ]);
+        assert_eq!(to_value(&matrix, token), json!([["16"], []]));
+
+        let pair = param(json!({
+            "name": "pair",
+            "type": "tuple",
+            "components": [
+                { "name": "", "type": "bool" },
+                { "name": "", "type": "address[]" }
+            ]
+        }));
+        let token = ethabi::Token::Tuple(vec![
+            ethabi::Token::Bool(true),
+            ethabi::Token::Array(vec![ethabi::Token::Address(
+                ethers::types::H160::repeat_byte(0x01),
+            )]),
+        ]);
+        assert_eq!(
+            to_value(&pair, token),
+            json!({ "0": "true", "1": ["0x0101010101010101010101010101010101010101"] })
+        );
+    }
+
     async fn erc20_transfer_log() -> Result<Log, Box<dyn std::error::Error>> {
         // Build the provider
         let http_rpc_url = env!("ETH_RPC_URL", "Please set an ETH_RPC_URL").to_owned();
@@ -0,0 +1,231 @@
+use alloy_json_abi::{Function, JsonAbi, Param};
+use ethers::{
+    abi::{
+        token::{LenientTokenizer, Tokenizer},
+        AbiParser,
+    },
+    types::Bytes,
+};
+use serde_json::Value;
+
+use super::event::ParamAndValue;
+use super::param::ToDynSolType;
+use super::param::ToEthAbiParamType;
+use super::token::Token;
+
+/// Decodes function calldata using the given contract ABI.
+///
+/// Returns the matching function and a JSON object with the
+/// parameter names as keys and the decoded arguments as values,
+/// or `None` if no function in the ABI matches the selector.
+///
+/// Example:
+/// {
+///     "amountIn": "1000000000000000000",
+///     "path": ["0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", "0x6b175474e89094c44da98b954eedeac495271d0f"]
+/// }
+pub fn decode_function_input(
+    input: &[u8],
+    abi: &JsonAbi,
+) -> Result<Option<(Function, Value)>, Box<dyn std::error::Error>> {
+    if input.len() < 4 {
+        return Ok(None);
+    }
+
+    let function = abi
+        .functions
+        .iter()
+        .flat_map(|(_, functions)| functions)
+        .find(|f| f.selector().as_slice() == &input[..4]);
+
+    match function {
+        Some(function) => {
+            let value = decode_params(&function.inputs, &input[4..])?;
+            Ok(Some((function.clone(), value)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Decodes function return data using the given function ABI.
+///
+/// Returns a JSON object with the output names as keys
+/// and the decoded return values as values.
+pub fn decode_function_output(
+    output: &[u8],
+    function: &Function,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    decode_params(&function.outputs, output)
+}
+
+/// Decodes ABI-encoded data into a JSON object keyed by parameter name.
+///
+/// Unnamed parameters are keyed by their position.
+fn decode_params(params: &[Param], data: &[u8]) -> Result<Value, Box<dyn std::error::Error>> {
+    // Build the ethabi types
+    let mut eth_abi_types = Vec::new();
+    for param in params.iter() {
+        eth_abi_types.push(param.to_dyn_sol_type()?.to_eth_abi_param_type()?);
+    }
+
+    // Decode the data
+    let tokens = ethabi::decode(&eth_abi_types, data)?;
+
+    // Build the map
+    let mut map = serde_json::Map::new();
+    for (i, (param, token)) in params.iter().zip(tokens).enumerate() {
+        let name = if param.name.is_empty() {
+            i.to_string()
+        } else {
+            param.name.clone()
+        };
+        let param_and_value = ParamAndValue {
+            param: param.clone(),
+            value: Token::new(token),
+        };
+        map.insert(name, param_and_value.to_value());
+    }
+
+    Ok(Value::Object(map))
+}
+
+/// Encodes a call to a function from its signature and arguments,
+/// e.g. `setFee(uint24)` with `["500"]`.
+///
+/// Arguments are parsed leniently, so numbers can be decimal.
+pub fn encode_function_call(signature: &str, args: &[String]) -> Result<Bytes, String> {
+    let function = AbiParser::default()
+        .parse_function(signature)
+        .map_err(|e| format!("Invalid function {}: {}", signature, e))?;
+    if function.inputs.len() != args.len() {
+        return Err(format!(
+            "{} takes {} arguments, got {}",
+            signature,
+            function.inputs.len(),
+            args.len()
+        ));
+    }
+    let tokens = function
+        .inputs
+        .iter()
+        .zip(args)
+        .map(|(input, arg)| {
+            LenientTokenizer::tokenize(&input.kind, arg)
+                .map_err(|e| format!("Invalid argument {} of {}: {}", arg, signature, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    function
+        .encode_input(&tokens)
+        .map(Into::into)
+        .map_err(|e| format!("Error encoding {}: {}", signature, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn erc20_abi() -> JsonAbi {
+        serde_json::from_str(
+            r#"[{
+                "type": "function",
+                "name": "transfer",
+                "inputs": [
+                    { "name": "to", "type": "address", "internalType": "address" },
+                    { "name": "value", "type": "uint256", "internalType": "uint256" }
+                ],
+                "outputs": [{ "name": "", "type": "bool", "internalType": "bool" }],
+                "stateMutability": "nonpayable"
+            }]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn can_decode_function_input_and_output() {
+        let abi = erc20_abi();
+        let input = hex::decode("a9059cbb00000000000000000000000091364516d3cad16e1666261dbdbb39c881dbe9ee000000000000000000000000000000000000000000000003bd913e6c1df40000").unwrap();
+
+        let (function, args) = decode_function_input(&input, &abi).unwrap().unwrap();
+        assert_eq!(function.name, "transfer");
+        assert_eq!(
+            args,
+            json!({
+                "to": "0x91364516d3cad16e1666261dbdbb39c881dbe9ee",
+                "value": "69000000000000000000"
+            })
+        );
+
+        let output =
+            hex::decode("0000000000000000000000000000000000000000000000000000000000000001")
+                .unwrap();
+        let result = decode_function_output(&output, &function).unwrap();
+        assert_eq!(result, json!({ "0": "true" }));
+
+        // Unknown selectors are not decoded
+        assert!(decode_function_input(&[0, 0, 0, 0], &abi)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn can_decode_nested_function_output() {
+        let abi: JsonAbi = serde_json::from_str(
+            r#"[{
+                "type": "function",
+                "name": "multicall",
+                "inputs": [{ "name": "data", "type": "bytes[]" }],
+                "outputs": [{ "name": "results", "type": "bytes[]" }],
+                "stateMutability": "payable"
+            }, {
+                "type": "function",
+                "name": "aggregate3",
+                "inputs": [],
+                "outputs": [{
+                    "name": "returnData",
+                    "type": "tuple[]",
+                    "components": [
+                        { "name": "success", "type": "bool" },
+                        { "name": "returnData", "type": "bytes" }
+                    ]
+                }],
+                "stateMutability": "payable"
+            }]"#,
+        )
+        .unwrap();
+
+        // UniswapV3's multicall returns the results of each call
+        let output = ethabi::encode(&[ethabi::Token::Array(vec![
+            ethabi::Token::Bytes(vec![0x01, 0x02]),
+            ethabi::Token::Bytes(vec![]),
+        ])]);
+        let multicall = abi.function("multicall").unwrap()[0].clone();
+        assert_eq!(
+            decode_function_output(&output, &multicall).unwrap(),
+            json!({ "results": ["0102", ""] })
+        );
+
+        // Multicall3's aggregate3 returns a struct per call
+        let output = ethabi::encode(&[ethabi::Token::Array(vec![
+            ethabi::Token::Tuple(vec![
+                ethabi::Token::Bool(true),
+                ethabi::Token::Bytes(vec![0xff]),
+            ]),
+            ethabi::Token::Tuple(vec![
+                ethabi::Token::Bool(false),
+                ethabi::Token::Bytes(vec![]),
+            ]),
+        ])]);
+        let aggregate3 = abi.function("aggregate3").unwrap()[0].clone();
+        assert_eq!(
+            decode_function_output(&output, &aggregate3).unwrap(),
+            json!({
+                "returnData": [
+                    { "success": "true", "returnData": "ff" },
+                    { "success": "false", "returnData": "" }
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! Decodes shadow events and calls with their ABIs, and re-encodes the
+//! decoded values for sinks.
+//!
+//! This is shared by the CLI, its Python bindings and its C ABI, so
+//! every one of them decodes a log to the same values. The `shadow.json`
+//! [`migrations`] live here too, so the reth integration reads the same
+//! versions as the CLI.
+
+pub mod decoded;
+pub mod encoding;
+pub mod event;
+pub mod function;
+pub mod migrations;
+mod param;
+pub mod registry;
+pub mod schema;
+mod token;
+pub mod unnamed;
+
+pub use decoded::{BalanceDiff, Correlation, DecodedEvent, RawLog};
+pub use event::{decode_errors, decode_log};
+pub use function::{decode_function_input, decode_function_output, encode_function_call};
+pub use registry::{DecodedLog, EventRegistry};
+pub use schema::{schema_id, SchemaDrift, SchemaViolation};
+pub use unnamed::decode_unnamed;
@@ -0,0 +1,87 @@
+use serde_json::{json, Value};
+
+/// The current version of the `shadow.json` format.
+pub const CURRENT_VERSION: u64 = 2;
+
+/// A migration upgrades the `shadow.json` contents from one version
+/// to the next.
+type Migration = fn(Value) -> Result<Value, String>;
+
+/// The migrations, indexed by the version they upgrade from, minus one.
+///
+/// To change the format, bump [`CURRENT_VERSION`] and append a
+/// migration. New optional fields with a serde default don't need one.
+const MIGRATIONS: &[Migration] = &[v1_to_v2];
+
+/// Returns the version of the `shadow.json` contents.
+///
+/// Version 1 files are a bare array of shadow contracts and have no
+/// `version` field.
+pub fn version(contents: &Value) -> Result<u64, String> {
+    match contents {
+        Value::Array(_) => Ok(1),
+        Value::Object(object) => object
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| "Missing version in shadow.json".to_owned()),
+        _ => Err("Invalid shadow.json".to_owned()),
+    }
+}
+
+/// Upgrades the `shadow.json` contents to the current version.
+///
+/// Returns the upgraded contents and the version they were upgraded from.
+pub fn migrate(mut contents: Value) -> Result<(Value, u64), String> {
+    let from = version(&contents)?;
+    if from == 0 {
+        return Err("shadow.json has version 0, which doesn't exist".to_owned());
+    }
+    if from > CURRENT_VERSION {
+        return Err(format!(
+            "shadow.json has version {}, but this version of shadow only supports up to version {}. Please upgrade shadow.",
+            from, CURRENT_VERSION
+        ));
+    }
+    for migration in &MIGRATIONS[from as usize - 1..] {
+        contents = migration(contents)?;
+    }
+    Ok((contents, from))
+}
+
+/// Wraps the bare array of shadow contracts in a versioned object.
+fn v1_to_v2(contents: Value) -> Result<Value, String> {
+    Ok(json!({ "version": 2, "contracts": contents }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_migrate_v1() {
+        let contracts = json!([{ "address": "0x01" }]);
+        let (contents, from) = migrate(contracts.clone()).unwrap();
+        assert_eq!(from, 1);
+        assert_eq!(contents, json!({ "version": 2, "contracts": contracts }));
+    }
+
+    #[test]
+    fn can_skip_current_version() {
+        let contents = json!({ "version": CURRENT_VERSION, "contracts": [] });
+        assert_eq!(
+            migrate(contents.clone()).unwrap(),
+            (contents, CURRENT_VERSION)
+        );
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        assert!(migrate(json!({ "version": CURRENT_VERSION + 1, "contracts": [] })).is_err());
+        assert!(migrate(json!({ "contracts": [] })).is_err());
+    }
+
+    #[test]
+    fn rejects_version_zero() {
+        assert!(migrate(json!({ "version": 0, "contracts": [] })).is_err());
+    }
+}
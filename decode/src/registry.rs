@@ -0,0 +1,189 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use alloy_json_abi::{Event, JsonAbi};
+use ethers::types::H256;
+use serde_json::Value;
+
+use super::decode_log;
+
+/// Known event ABIs keyed by their topic0, used to decode logs from
+/// any contract.
+#[derive(Clone, Debug, Default)]
+pub struct EventRegistry {
+    events: HashMap<H256, Vec<(String, Event)>>,
+}
+
+/// A log decoded with an ABI from the [`EventRegistry`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedLog {
+    /// The name of the contract the ABI was read from
+    pub contract: String,
+    /// The event name
+    pub event: String,
+    /// The event signature, e.g. `Transfer(address,address,uint256)`
+    pub signature: String,
+    /// The decoded parameters, keyed by name
+    pub data: Value,
+}
+
+impl EventRegistry {
+    /// Adds an event ABI, labelled with the contract it comes from.
+    ///
+    /// Anonymous events have no topic0 and are ignored.
+    pub fn add(&mut self, contract: &str, event: Event) {
+        if event.anonymous {
+            return;
+        }
+        let topic0 = H256::from_slice(event.selector().as_slice());
+        let candidates = self.events.entry(topic0).or_default();
+        if !candidates.iter().any(|(_, known)| known == &event) {
+            candidates.push((contract.to_owned(), event));
+        }
+    }
+
+    /// Returns the number of distinct events.
+    pub fn len(&self) -> usize {
+        self.events.values().map(Vec::len).sum()
+    }
+
+    /// Returns whether the registry has no events.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Loads the events from every JSON file under `dir`.
+    ///
+    /// Files may be Foundry or Hardhat artifacts with an `abi` field, or
+    /// plain ABI arrays. The contract is named after the file. Files
+    /// that aren't ABIs are skipped.
+    pub fn load_dir(&mut self, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.load_dir(&path)?;
+                continue;
+            }
+            if path.extension().map_or(true, |ext| ext != "json") {
+                continue;
+            }
+            let contents = fs::read_to_string(&path)?;
+            let Ok(value) = serde_json::from_str::<Value>(&contents) else {
+                continue;
+            };
+            let abi = match value {
+                Value::Object(mut object) => object.remove("abi").unwrap_or(Value::Null),
+                value => value,
+            };
+            let Ok(abi) = serde_json::from_value::<JsonAbi>(abi) else {
+                continue;
+            };
+            let contract = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            for event in abi.events.into_values().flatten() {
+                self.add(&contract, event);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes a log with the first known ABI that fits it.
+    ///
+    /// Events sharing a topic0 can differ in which parameters are
+    /// indexed, so every candidate is tried.
+    pub fn decode(&self, log: &ethers::types::Log) -> Option<DecodedLog> {
+        let topic0 = log.topics.first()?;
+        self.events
+            .get(topic0)?
+            .iter()
+            .filter(|(_, event)| {
+                event.inputs.iter().filter(|input| input.indexed).count() + 1 == log.topics.len()
+            })
+            .find_map(|(contract, event)| {
+                let data = decode_log(log, event).ok()?;
+                Some(DecodedLog {
+                    contract: contract.clone(),
+                    event: event.name.clone(),
+                    signature: event.signature(),
+                    data,
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Bytes, Log, H160};
+    use serde_json::json;
+
+    use super::*;
+
+    fn transfer(indexed_value: bool) -> Event {
+        serde_json::from_value(json!({
+            "type": "event",
+            "name": "Transfer",
+            "anonymous": false,
+            "inputs": [
+                { "name": "from", "type": "address", "indexed": true },
+                { "name": "to", "type": "address", "indexed": true },
+                { "name": "value", "type": "uint256", "indexed": indexed_value }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn can_decode_with_matching_candidate() {
+        let mut registry = EventRegistry::default();
+        // ERC721 indexes the token id, ERC20 doesn't
+        registry.add("ERC721", transfer(true));
+        registry.add("ERC20", transfer(false));
+        assert_eq!(registry.len(), 2);
+
+        let topic0 = H256::from_slice(transfer(false).selector().as_slice());
+        let log = Log {
+            address: H160::zero(),
+            topics: vec![topic0, H256::from_low_u64_be(1), H256::from_low_u64_be(2)],
+            data: Bytes::from(H256::from_low_u64_be(42).as_bytes().to_vec()),
+            ..Default::default()
+        };
+        let decoded = registry.decode(&log).unwrap();
+        assert_eq!(decoded.contract, "ERC20");
+        assert_eq!(decoded.signature, "Transfer(address,address,uint256)");
+        assert_eq!(decoded.data["value"], "42");
+    }
+
+    #[test]
+    fn can_load_artifacts_and_abis() {
+        let mut registry = EventRegistry::default();
+        registry
+            .load_dir(Path::new(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/fixtures"
+            )))
+            .unwrap();
+        assert_eq!(registry.len(), 2);
+
+        // A bare ABI, and a Foundry artifact in a subdirectory
+        let topic0 = H256::from_slice(transfer(false).selector().as_slice());
+        let log = Log {
+            topics: vec![topic0, H256::from_low_u64_be(1), H256::from_low_u64_be(2)],
+            data: Bytes::from(H256::from_low_u64_be(42).as_bytes().to_vec()),
+            ..Default::default()
+        };
+        assert_eq!(registry.decode(&log).unwrap().contract, "ERC20");
+        let log = Log {
+            topics: vec![
+                topic0,
+                H256::from_low_u64_be(1),
+                H256::from_low_u64_be(2),
+                H256::from_low_u64_be(7),
+            ],
+            ..Default::default()
+        };
+        let decoded = registry.decode(&log).unwrap();
+        assert_eq!(decoded.contract, "ERC721");
+        assert_eq!(decoded.data["tokenId"], "7");
+    }
+}
@@ -0,0 +1,385 @@
+use std::{collections::BTreeMap, fmt};
+
+use alloy_json_abi::{Event, Param};
+use serde_json::Value;
+
+use super::event::{component_key, decode_errors, is_hashed_topic, topic_hash_key, ERRORS_KEY};
+use crate::DecodedEvent;
+
+/// The schema of events decoded before schemas were recorded
+pub const UNVERSIONED: &str = "unversioned";
+
+/// The version of the shape the decoder renders values in. It is part
+/// of every schema id, so events decoded in an older shape never share
+/// an id with newer ones.
+///
+/// - 1: the original shape
+/// - 2: nested and fixed arrays are JSON arrays, and unnamed tuple
+///   components are keyed by their position
+/// - 3: `bytes` values are prefixed with `0x` by default
+pub const DECODER_VERSION: u8 = 3;
+
+/// Returns a short id of the shape of an event's decoded fields.
+///
+/// The id covers the event name and the name, type, indexing and
+/// components of every parameter, so renaming a parameter changes
+/// it even though the event signature stays the same. It also covers
+/// the [`DECODER_VERSION`].
+pub fn schema_id(event: &Event) -> String {
+    let mut abi = vec![DECODER_VERSION];
+    abi.extend(serde_json::to_vec(event).unwrap_or_default());
+    let hash = ethers::utils::keccak256(abi);
+    format!("0x{}", hex::encode(&hash[..4]))
+}
+
+/// Describes an event with its parameter names, e.g.
+/// `Transfer(address indexed from,address indexed to,uint256 value)`.
+pub fn describe_event(event: &Event) -> String {
+    let params: Vec<String> = event
+        .inputs
+        .iter()
+        .map(|param| {
+            let indexed = if param.indexed { " indexed" } else { "" };
+            format!("{}{} {}", param.ty, indexed, param.name)
+        })
+        .collect();
+    format!("{}({})", event.name, params.join(","))
+}
+
+/// A field of a decoded event whose shape doesn't match the event's
+/// schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// The path of the field, e.g. `offer[0].amount`
+    pub path: String,
+    /// The shape the schema expects, e.g. `string`
+    pub expected: String,
+    /// The shape of the decoded field, e.g. `number` or `missing`
+    pub actual: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.path, self.expected, self.actual
+        )
+    }
+}
+
+/// Checks that a decoded event has the fields of its schema, in the
+/// shape the decoder renders them: values as strings, arrays as arrays
+/// and structs as objects.
+///
+/// Returns the fields that don't match, empty if the event is valid.
+///
+/// Parameters that failed to decode, listed under `_errors`, are
+/// expected to be missing.
+pub fn validate(event: &Event, data: &Value) -> Vec<SchemaViolation> {
+    let failed = decode_errors(data);
+    let mut data = data.clone();
+    if let Value::Object(fields) = &mut data {
+        fields.remove(ERRORS_KEY);
+    }
+    let params = event
+        .inputs
+        .iter()
+        .filter(|input| failed.map_or(true, |failed| !failed.contains_key(&input.name)))
+        .map(|input| {
+            if input.indexed && is_hashed_topic(input) {
+                // Only the topic hash of the value is known
+                Param {
+                    name: topic_hash_key(&input.name),
+                    ty: "bytes32".to_owned(),
+                    internal_type: None,
+                    components: Vec::new(),
+                }
+            } else {
+                Param {
+                    name: input.name.clone(),
+                    ty: input.ty.clone(),
+                    internal_type: input.internal_type.clone(),
+                    components: input.components.clone(),
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut violations = Vec::new();
+    validate_object("", &params, &data, &mut violations);
+    violations
+}
+
+/// Checks the fields of an event or struct.
+fn validate_object(path: &str, params: &[Param], value: &Value, out: &mut Vec<SchemaViolation>) {
+    let Value::Object(fields) = value else {
+        out.push(violation(path, "object", value));
+        return;
+    };
+    for param in params {
+        let field_path = if path.is_empty() {
+            param.name.clone()
+        } else {
+            format!("{}.{}", path, param.name)
+        };
+        match fields.get(&param.name) {
+            Some(field) => validate_param(&field_path, &param.ty, &param.components, field, out),
+            None => out.push(SchemaViolation {
+                path: field_path,
+                expected: expected_shape(&param.ty, &param.components).to_owned(),
+                actual: "missing".to_owned(),
+            }),
+        }
+    }
+    for (name, field) in fields {
+        if !params.iter().any(|param| &param.name == name) {
+            let field_path = if path.is_empty() {
+                name.clone()
+            } else {
+                format!("{}.{}", path, name)
+            };
+            out.push(SchemaViolation {
+                path: field_path,
+                expected: "no field".to_owned(),
+                actual: shape(field).to_owned(),
+            });
+        }
+    }
+}
+
+/// Checks a single field against its parameter type.
+fn validate_param(
+    path: &str,
+    ty: &str,
+    components: &[Param],
+    value: &Value,
+    out: &mut Vec<SchemaViolation>,
+) {
+    // The element type of e.g. `tuple[2][]` is `tuple[2]`
+    if let Some((element, _)) = ty.strip_suffix(']').and_then(|ty| ty.rsplit_once('[')) {
+        match value {
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    validate_param(&format!("{}[{}]", path, i), element, components, item, out);
+                }
+            }
+            _ => out.push(violation(path, "array", value)),
+        }
+    } else if !components.is_empty() {
+        let components = components
+            .iter()
+            .enumerate()
+            .map(|(i, component)| Param {
+                name: component_key(i, component),
+                ..component.clone()
+            })
+            .collect::<Vec<_>>();
+        validate_object(path, &components, value, out);
+    } else if !value.is_string() {
+        out.push(violation(path, "string", value));
+    }
+}
+
+fn expected_shape(ty: &str, components: &[Param]) -> &'static str {
+    if ty.ends_with(']') {
+        "array"
+    } else if !components.is_empty() {
+        "object"
+    } else {
+        "string"
+    }
+}
+
+fn violation(path: &str, expected: &str, value: &Value) -> SchemaViolation {
+    SchemaViolation {
+        path: path.to_owned(),
+        expected: expected.to_owned(),
+        actual: shape(value).to_owned(),
+    }
+}
+
+/// Returns the JSON type of a value.
+fn shape(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Archived events of a contract that were decoded with another
+/// schema than the current one.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SchemaDrift {
+    /// The current schema of the event
+    pub current: String,
+    /// The number of archived events per older schema
+    pub older: BTreeMap<String, usize>,
+}
+
+impl SchemaDrift {
+    /// Finds the archived events of a contract's event that were
+    /// decoded with another schema than the current one.
+    pub fn find<'a>(
+        events: impl IntoIterator<Item = &'a DecodedEvent>,
+        contract: &str,
+        event: &str,
+        current: &str,
+    ) -> Option<Self> {
+        let mut older = BTreeMap::new();
+        for archived in events {
+            if archived.contract != contract || archived.event != event {
+                continue;
+            }
+            let schema = archived.schema.as_deref().unwrap_or(UNVERSIONED);
+            if schema != current {
+                *older.entry(schema.to_owned()).or_insert(0) += 1;
+            }
+        }
+        (!older.is_empty()).then(|| SchemaDrift {
+            current: current.to_owned(),
+            older,
+        })
+    }
+}
+
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let older: Vec<String> = self
+            .older
+            .iter()
+            .map(|(schema, count)| format!("{} events with schema {}", count, schema))
+            .collect();
+        write!(
+            f,
+            "the archive has {} decoded with an older ABI than the current schema {}, their fields may differ",
+            older.join(", "),
+            self.current
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn transfer_event(value_name: &str) -> Event {
+        serde_json::from_value(json!({
+            "name": "Transfer",
+            "type": "event",
+            "inputs": [
+                { "name": "from", "type": "address", "indexed": true, "internalType": "address" },
+                { "name": value_name, "type": "uint256", "indexed": false, "internalType": "uint256" }
+            ],
+            "anonymous": false
+        }))
+        .unwrap()
+    }
+
+    fn archived(schema: Option<&str>) -> DecodedEvent {
+        DecodedEvent {
+            block_number: 17_000_000,
+            block_timestamp: None,
+            transaction_hash: "0x01".to_owned(),
+            log_index: 0,
+            address: "0x7a250d5630b4cf539739df2c5dacb4c659f2488d".to_owned(),
+            contract: "Token.sol:Token".to_owned(),
+            event: "Transfer".to_owned(),
+            signature: "Transfer(address,uint256)".to_owned(),
+            schema: schema.map(str::to_owned),
+            data: json!({}),
+            raw: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_version_event_schemas() {
+        let event = transfer_event("value");
+        assert_eq!(schema_id(&event), schema_id(&transfer_event("value")));
+        assert_ne!(schema_id(&event), schema_id(&transfer_event("amount")));
+        assert_eq!(
+            describe_event(&event),
+            "Transfer(address indexed from,uint256 value)"
+        );
+    }
+
+    #[test]
+    fn can_validate_decoded_events() {
+        let event: Event = serde_json::from_value(json!({
+            "name": "OrderFulfilled",
+            "type": "event",
+            "inputs": [
+                { "name": "orderHash", "type": "bytes32", "indexed": false },
+                { "name": "ids", "type": "uint256[]", "indexed": false },
+                { "name": "offer", "type": "tuple[]", "indexed": false, "components": [
+                    { "name": "token", "type": "address" },
+                    { "name": "amount", "type": "uint256" }
+                ]}
+            ],
+            "anonymous": false
+        }))
+        .unwrap();
+        let valid = json!({
+            "orderHash": "0f99",
+            "ids": ["1", "2"],
+            "offer": [{ "token": "0x8c3c", "amount": "1" }]
+        });
+        assert!(validate(&event, &valid).is_empty());
+
+        // Partially decoded events are missing their undecodable fields
+        let partial = json!({
+            "orderHash": "0f99",
+            "offer": [],
+            "_errors": { "ids": "Invalid data" }
+        });
+        assert!(validate(&event, &partial).is_empty());
+
+        let invalid = json!({
+            "ids": ["1", 2],
+            "offer": [{ "token": "0x8c3c", "amount": 1 }],
+            "extra": true
+        });
+        let violations = validate(&event, &invalid)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            violations,
+            vec![
+                "orderHash: expected string, got missing",
+                "ids[1]: expected string, got number",
+                "offer[0].amount: expected string, got number",
+                "extra: expected no field, got boolean",
+            ]
+        );
+    }
+
+    #[test]
+    fn can_find_schema_drift() {
+        let events = [
+            archived(Some("0x01")),
+            archived(Some("0x02")),
+            archived(None),
+        ];
+        assert_eq!(
+            SchemaDrift::find(&events[..1], "Token.sol:Token", "Transfer", "0x01"),
+            None
+        );
+
+        let drift = SchemaDrift::find(&events, "Token.sol:Token", "Transfer", "0x01").unwrap();
+        assert_eq!(
+            drift.older,
+            BTreeMap::from([("0x02".to_owned(), 1), (UNVERSIONED.to_owned(), 1)])
+        );
+        assert_eq!(
+            drift.to_string(),
+            "the archive has 1 events with schema 0x02, 1 events with schema unversioned decoded with an older ABI than the current schema 0x01, their fields may differ"
+        );
+    }
+}
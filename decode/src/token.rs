@@ -1,8 +1,6 @@
 use std::fmt;
 use std::ops::{Deref, DerefMut};
 
-use ethers::abi::Tokenize;
-
 /// Wrapper around [`ethabi::Token`] to implement
 /// a custom [`fmt::Display`].
 ///
@@ -16,14 +14,6 @@ impl Token {
     pub fn new(token: ethabi::Token) -> Self {
         Self(token)
     }
-
-    pub fn into_tokens(self) -> Vec<ethabi::Token> {
-        self.0.into_tokens()
-    }
-
-    pub fn underlying(&self) -> &ethabi::Token {
-        &self.0
-    }
 }
 
 impl Deref for Token {
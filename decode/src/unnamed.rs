@@ -0,0 +1,125 @@
+use ethabi::{param_type::Reader, ParamType};
+use serde_json::Value;
+
+use super::token::Token;
+
+/// Decodes a log with a bare text signature, e.g.
+/// `Transfer(address,address,uint256)`, such as one from a signature
+/// database.
+///
+/// Text signatures don't name their parameters or say which are
+/// indexed, so this is best effort: the first parameters are assumed
+/// to be the indexed ones, one per topic after topic0, and the
+/// parameters are keyed by position (`arg0`, `arg1`, ...).
+///
+/// Indexed dynamic values (strings, bytes, arrays and tuples) are
+/// stored as their hash and are returned as the raw topic.
+///
+/// Returns `None` if the log doesn't fit the signature.
+pub fn decode_unnamed(log: &ethers::types::Log, signature: &str) -> Option<Value> {
+    let types = parse_types(signature)?;
+    let indexed = log.topics.len().checked_sub(1)?;
+    if indexed > types.len() {
+        return None;
+    }
+
+    let mut values = Vec::with_capacity(types.len());
+    for (ty, topic) in types.iter().zip(log.topics.iter().skip(1)) {
+        if ty.is_dynamic() || matches!(ty, ParamType::FixedArray(..) | ParamType::Tuple(_)) {
+            values.push(Value::String(format!("{:?}", topic)));
+        } else {
+            let token = ethabi::decode(&[ty.clone()], topic.as_bytes()).ok()?;
+            values.push(to_value(&token[0]));
+        }
+    }
+    let tokens = ethabi::decode_whole(&types[indexed..], &log.data).ok()?;
+    values.extend(tokens.iter().map(to_value));
+
+    let map = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| (format!("arg{}", i), value))
+        .collect::<serde_json::Map<_, _>>();
+    Some(Value::Object(map))
+}
+
+/// Parses the parameter types of a text signature.
+fn parse_types(signature: &str) -> Option<Vec<ParamType>> {
+    let start = signature.find('(')?;
+    let params = signature[start..].trim();
+    match Reader::read(params).ok()? {
+        ParamType::Tuple(types) => Some(types),
+        _ => None,
+    }
+}
+
+/// Converts a token to JSON the way named parameters are, with tuples
+/// as arrays since their fields have no names.
+fn to_value(token: &ethabi::Token) -> Value {
+    match token {
+        ethabi::Token::Array(tokens)
+        | ethabi::Token::FixedArray(tokens)
+        | ethabi::Token::Tuple(tokens) => Value::Array(tokens.iter().map(to_value).collect()),
+        token => Value::String(Token::new(token.clone()).to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Bytes, Log, H256};
+    use serde_json::json;
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn transfer_log(indexed: usize) -> Log {
+        let topics = [
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+            "0x000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+            "0x000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+        ];
+        let amount = "00000000000000000000000000000000000000000000000000000000000003e8";
+        let mut data = topics[1..]
+            .iter()
+            .skip(indexed)
+            .map(|topic| topic.trim_start_matches("0x").to_owned())
+            .collect::<String>();
+        data.push_str(amount);
+        Log {
+            topics: topics[..=indexed]
+                .iter()
+                .map(|topic| H256::from_str(topic).unwrap())
+                .collect(),
+            data: Bytes::from_str(&data).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_decode_unnamed() {
+        let signature = "Transfer(address,address,uint256)";
+        let expected = json!({
+            "arg0": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+            "arg1": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+            "arg2": "1000",
+        });
+        assert_eq!(
+            decode_unnamed(&transfer_log(2), signature).unwrap(),
+            expected
+        );
+
+        // The same event where nothing is indexed, e.g. from another token
+        assert_eq!(
+            decode_unnamed(&transfer_log(0), signature).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn rejects_logs_that_do_not_fit() {
+        let log = transfer_log(2);
+        assert!(decode_unnamed(&log, "Approval(address,uint256)").is_none());
+        assert!(decode_unnamed(&log, "Transfer(address,address)").is_none());
+        assert!(decode_unnamed(&log, "not a signature").is_none());
+    }
+}
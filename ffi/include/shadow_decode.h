@@ -0,0 +1,51 @@
+/*
+ * A C ABI over the shadow decoder.
+ *
+ * Every function takes a NUL-terminated JSON request and returns a
+ * NUL-terminated JSON response, {"ok": <result>} or {"error": "..."},
+ * which must be freed with shadow_string_free. The functions are
+ * thread-safe.
+ */
+#ifndef SHADOW_DECODE_H
+#define SHADOW_DECODE_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The version of the ABI this header describes */
+#define SHADOW_DECODE_ABI_VERSION 1
+
+/* Returns the version of the ABI the library implements */
+uint32_t shadow_decode_abi_version(void);
+
+/*
+ * Decodes a log.
+ *
+ * Request: {"event": <event ABI>, "topics": ["0x..."], "data": "0x...",
+ *           "numbers": "decimal" | "hex" | "float",
+ *           "bytes": "hex" | "unprefixed" | "utf8" | "base64"}
+ *          The encodings default to "decimal" and "hex".
+ * Result:  an object with the parameter names as keys
+ */
+char *shadow_decode_log(const char *request);
+
+/*
+ * Decodes calldata.
+ *
+ * Request: {"abi": [...], "calldata": "0x..."}
+ * Result:  {"signature": "transfer(address,uint256)", "args": {...}}, or
+ *          null if no function of the ABI matches the selector
+ */
+char *shadow_decode_function_input(const char *request);
+
+/* Frees a response */
+void shadow_string_free(char *response);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SHADOW_DECODE_H */
@@ -0,0 +1,162 @@
+//! A C ABI over the shadow decoder.
+//!
+//! Every function takes a NUL-terminated JSON request and returns a
+//! NUL-terminated JSON response, `{"ok": <result>}` or `{"error": "..."}`,
+//! which the caller frees with [`shadow_string_free`]. Requests and
+//! responses only gain optional fields within an ABI version, so
+//! callers can check [`shadow_decode_abi_version`] once at load time.
+//!
+//! The decoder is the CLI's own, so values decode exactly as in the CLI's
+//! archives and sinks, including tuples, arrays of tuples, and
+//! parameters that fail to decode under `_errors`.
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use alloy_json_abi::{Event, JsonAbi};
+use clap::ValueEnum;
+use ethers::types::{Bytes, Log, H256};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use shadow_decode_core::{
+    self as decode,
+    encoding::{BytesEncoding, Encoder, NumericEncoding},
+};
+
+/// The version of the C ABI, bumped when a function or a request or
+/// response field changes incompatibly
+pub const ABI_VERSION: u32 = 1;
+
+/// A `shadow_decode_log` request
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct DecodeLogRequest {
+    /// The event ABI
+    event: Event,
+    /// The log topics, topic0 first
+    topics: Vec<H256>,
+    /// The log data
+    data: Bytes,
+    /// How integers are encoded, as in `shadow events --numbers`
+    #[serde(default)]
+    numbers: Option<String>,
+    /// How bytes are encoded, as in `shadow events --bytes`
+    #[serde(default)]
+    bytes: Option<String>,
+}
+
+/// A `shadow_decode_function_input` request
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct DecodeFunctionInputRequest {
+    /// The contract ABI
+    abi: JsonAbi,
+    /// The calldata, selector first
+    calldata: Bytes,
+}
+
+/// Returns the version of the C ABI.
+#[no_mangle]
+pub extern "C" fn shadow_decode_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+/// Decodes a log.
+///
+/// The request is `{"event": <event ABI>, "topics": [...], "data": "0x..."}`,
+/// with optional `numbers` and `bytes` encodings. The result is an object
+/// with the parameter names as keys.
+///
+/// # Safety
+///
+/// `request` must be a valid NUL-terminated string, or null.
+#[no_mangle]
+pub unsafe extern "C" fn shadow_decode_log(request: *const c_char) -> *mut c_char {
+    respond(request, |request| {
+        let request: DecodeLogRequest =
+            serde_json::from_str(request).map_err(|e| format!("Invalid request: {}", e))?;
+        let encoder = encoder(request.numbers.as_deref(), request.bytes.as_deref())?;
+        let log = Log {
+            topics: request.topics,
+            data: request.data,
+            ..Default::default()
+        };
+        let decoded = decode::decode_log(&log, &request.event).map_err(|e| e.to_string())?;
+        Ok(encoder.encode(&request.event, &decoded))
+    })
+}
+
+/// Decodes calldata.
+///
+/// The request is `{"abi": [...], "calldata": "0x..."}`. The result is
+/// `{"signature": "transfer(address,uint256)", "args": {...}}`, or null
+/// if no function of the ABI matches the selector.
+///
+/// # Safety
+///
+/// `request` must be a valid NUL-terminated string, or null.
+#[no_mangle]
+pub unsafe extern "C" fn shadow_decode_function_input(request: *const c_char) -> *mut c_char {
+    respond(request, |request| {
+        let request: DecodeFunctionInputRequest =
+            serde_json::from_str(request).map_err(|e| format!("Invalid request: {}", e))?;
+        let decoded = decode::decode_function_input(&request.calldata, &request.abi)
+            .map_err(|e| e.to_string())?;
+        Ok(match decoded {
+            Some((function, args)) => json!({ "signature": function.signature(), "args": args }),
+            None => Value::Null,
+        })
+    })
+}
+
+/// Frees a response.
+///
+/// # Safety
+///
+/// `response` must have been returned by this library and not freed
+/// yet, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn shadow_string_free(response: *mut c_char) {
+    if !response.is_null() {
+        drop(CString::from_raw(response));
+    }
+}
+
+/// Reads a request, runs a handler over it, and wraps its result or error
+/// in a response. Panics are reported as errors, since they must not
+/// unwind into the caller.
+unsafe fn respond(
+    request: *const c_char,
+    handler: impl FnOnce(&str) -> Result<Value, String>,
+) -> *mut c_char {
+    let response = if request.is_null() {
+        Err("The request is null".to_owned())
+    } else {
+        match CStr::from_ptr(request).to_str() {
+            Ok(request) => catch_unwind(AssertUnwindSafe(|| handler(request)))
+                .unwrap_or_else(|_| Err("The decoder panicked".to_owned())),
+            Err(e) => Err(format!("The request isn't UTF-8: {}", e)),
+        }
+    };
+    let response = match response {
+        Ok(result) => json!({ "ok": result }),
+        Err(error) => json!({ "error": error }),
+    };
+    // JSON escapes control characters, so the response has no NUL byte
+    CString::new(response.to_string())
+        .expect("JSON has no NUL bytes")
+        .into_raw()
+}
+
+fn encoder(numbers: Option<&str>, bytes: Option<&str>) -> Result<Encoder, String> {
+    let mut encoder = Encoder::default();
+    if let Some(numbers) = numbers {
+        encoder.numbers = NumericEncoding::from_str(numbers, true)?;
+    }
+    if let Some(bytes) = bytes {
+        encoder.bytes = BytesEncoding::from_str(bytes, true)?;
+    }
+    Ok(encoder)
+}
@@ -0,0 +1,97 @@
+use std::ffi::{c_char, CStr, CString};
+
+use serde_json::{json, Value};
+use shadow_decode_ffi::{
+    shadow_decode_abi_version, shadow_decode_function_input, shadow_decode_log, shadow_string_free,
+    ABI_VERSION,
+};
+
+/// Sends a request through the C ABI and returns the parsed response.
+fn call(function: unsafe extern "C" fn(*const c_char) -> *mut c_char, request: &Value) -> Value {
+    let request = CString::new(request.to_string()).unwrap();
+    unsafe {
+        let response = function(request.as_ptr());
+        let parsed = serde_json::from_str(CStr::from_ptr(response).to_str().unwrap()).unwrap();
+        shadow_string_free(response);
+        parsed
+    }
+}
+
+#[test]
+fn can_decode_logs() {
+    assert_eq!(shadow_decode_abi_version(), ABI_VERSION);
+
+    let request = json!({
+        "event": {
+            "type": "event",
+            "name": "Swap",
+            "anonymous": false,
+            "inputs": [
+                { "name": "sender", "type": "address", "indexed": true },
+                {
+                    "name": "order",
+                    "type": "tuple",
+                    "indexed": false,
+                    "components": [
+                        { "name": "amount", "type": "uint256" },
+                        { "name": "data", "type": "bytes2" }
+                    ]
+                }
+            ]
+        },
+        "topics": [
+            "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "0x00000000000000000000000073ede13ab9c28bc4302e94c1d1e7f755988a9158"
+        ],
+        "data": "0x00000000000000000000000000000000000000000000000000000000000003e80f99000000000000000000000000000000000000000000000000000000000000",
+        "numbers": "hex"
+    });
+    let response = call(shadow_decode_log, &request);
+    let decoded = &response["ok"];
+    assert_eq!(
+        decoded["sender"],
+        "0x73ede13ab9c28bc4302e94c1d1e7f755988a9158"
+    );
+    assert_eq!(decoded["order"]["amount"], "0x3e8");
+    assert_eq!(decoded["order"]["data"], "0x0f99");
+
+    let response = call(shadow_decode_log, &json!({ "event": {} }));
+    assert!(response["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("Invalid request"));
+}
+
+#[test]
+fn can_decode_function_input() {
+    let abi = json!([{
+        "type": "function",
+        "name": "transfer",
+        "stateMutability": "nonpayable",
+        "inputs": [
+            { "name": "to", "type": "address" },
+            { "name": "amount", "type": "uint256" }
+        ],
+        "outputs": [{ "name": "", "type": "bool" }]
+    }]);
+    let response = call(
+        shadow_decode_function_input,
+        &json!({
+            "abi": abi,
+            "calldata": "0xa9059cbb00000000000000000000000091364516d3cad16e1666261dbdbb39c881dbe9ee00000000000000000000000000000000000000000000000000000000000003e8"
+        }),
+    );
+    assert_eq!(
+        response["ok"],
+        json!({
+            "signature": "transfer(address,uint256)",
+            "args": { "to": "0x91364516d3cad16e1666261dbdbb39c881dbe9ee", "amount": "1000" }
+        })
+    );
+
+    let response = call(
+        shadow_decode_function_input,
+        &json!({ "abi": abi, "calldata": "0xdeadbeef" }),
+    );
+    assert_eq!(response, json!({ "ok": null }));
+}
@@ -0,0 +1,109 @@
+//! Python bindings for the shadow decoder.
+//!
+//! The decoder is the CLI's own, so logs and calldata decode to exactly
+//! the values the CLI writes to its archives and sinks: integers as
+//! decimal strings, addresses in lowercase, bytes as 0x-prefixed hex, and
+//! parameters that fail to decode under `_errors`. The `--numbers` and
+//! `--bytes` encodings of `shadow events` are available as the `numbers`
+//! and `bytes` arguments.
+
+use std::fmt::Display;
+
+use alloy_json_abi::{Event, JsonAbi};
+use clap::ValueEnum;
+use ethers::types::{Bytes, Log, H256};
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde_json::Value;
+use shadow_decode_core::{
+    self as decode,
+    encoding::{BytesEncoding, Encoder, NumericEncoding},
+};
+
+/// Decodes a log with an event ABI, given as JSON.
+///
+/// Returns a dict with the parameter names as keys, e.g.
+/// `{"from": "0x73ed...", "to": "0x9136...", "value": "69000000000000000000"}`.
+#[pyfunction]
+#[pyo3(signature = (event, topics, data, numbers = "decimal", bytes = "hex"))]
+fn decode_log(
+    py: Python<'_>,
+    event: &str,
+    topics: Vec<&str>,
+    data: &str,
+    numbers: &str,
+    bytes: &str,
+) -> PyResult<PyObject> {
+    let event: Event = serde_json::from_str(event).map_err(value_error)?;
+    let log = Log {
+        topics: topics
+            .iter()
+            .map(|topic| topic.parse::<H256>())
+            .collect::<Result<_, _>>()
+            .map_err(value_error)?,
+        data: parse_bytes(data)?,
+        ..Default::default()
+    };
+    let decoded = decode::decode_log(&log, &event).map_err(value_error)?;
+    let encoded = encoder(numbers, bytes)?.encode(&event, &decoded);
+    to_python(py, &encoded)
+}
+
+/// Decodes calldata with a contract ABI, given as JSON.
+///
+/// Returns the signature of the called function and a dict with its
+/// argument names as keys, or `None` if no function of the ABI matches
+/// the selector.
+#[pyfunction]
+fn decode_function_input(
+    py: Python<'_>,
+    abi: &str,
+    calldata: &str,
+) -> PyResult<Option<(String, PyObject)>> {
+    let abi: JsonAbi = serde_json::from_str(abi).map_err(value_error)?;
+    let calldata = parse_bytes(calldata)?;
+    match decode::decode_function_input(&calldata, &abi).map_err(value_error)? {
+        Some((function, args)) => Ok(Some((function.signature(), to_python(py, &args)?))),
+        None => Ok(None),
+    }
+}
+
+/// Returns the id of an event ABI, which changes when the shape of its
+/// decoded parameters does, as stored with every archived event.
+#[pyfunction]
+fn schema_id(event: &str) -> PyResult<String> {
+    let event: Event = serde_json::from_str(event).map_err(value_error)?;
+    Ok(decode::schema_id(&event))
+}
+
+fn encoder(numbers: &str, bytes: &str) -> PyResult<Encoder> {
+    Ok(Encoder {
+        numbers: NumericEncoding::from_str(numbers, true).map_err(value_error)?,
+        bytes: BytesEncoding::from_str(bytes, true).map_err(value_error)?,
+        ..Default::default()
+    })
+}
+
+fn parse_bytes(hex: &str) -> PyResult<Bytes> {
+    hex.parse::<Bytes>().map_err(value_error)
+}
+
+/// Converts a decoded value to Python objects through the `json` module,
+/// so numbers and strings come out as `json.loads` would read an archive.
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    let loaded = py
+        .import("json")?
+        .call_method1("loads", (value.to_string(),))?;
+    Ok(loaded.into())
+}
+
+fn value_error(e: impl Display) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+#[pymodule]
+fn shadow_decode(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(decode_log, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_function_input, m)?)?;
+    m.add_function(wrap_pyfunction!(schema_id, m)?)?;
+    Ok(())
+}
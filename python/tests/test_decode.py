@@ -0,0 +1,63 @@
+import json
+
+import shadow_decode
+
+TRANSFER = json.dumps(
+    {
+        "type": "event",
+        "name": "Transfer",
+        "anonymous": False,
+        "inputs": [
+            {"name": "from", "type": "address", "indexed": True},
+            {"name": "to", "type": "address", "indexed": True},
+            {"name": "value", "type": "uint256", "indexed": False},
+        ],
+    }
+)
+
+TOPICS = [
+    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+    "0x00000000000000000000000073ede13ab9c28bc4302e94c1d1e7f755988a9158",
+    "0x00000000000000000000000091364516d3cad16e1666261dbdbb39c881dbe9ee",
+]
+
+DATA = "0x000000000000000000000000000000000000000000000003bd913e6c1df40000"
+
+
+def test_decode_log():
+    assert shadow_decode.decode_log(TRANSFER, TOPICS, DATA) == {
+        "from": "0x73ede13ab9c28bc4302e94c1d1e7f755988a9158",
+        "to": "0x91364516d3cad16e1666261dbdbb39c881dbe9ee",
+        "value": "69000000000000000000",
+    }
+
+
+def test_decode_log_with_encoding():
+    decoded = shadow_decode.decode_log(TRANSFER, TOPICS, DATA, numbers="hex")
+    assert decoded["value"] == "0x3bd913e6c1df40000"
+
+
+def test_decode_function_input():
+    abi = json.dumps(
+        [
+            {
+                "type": "function",
+                "name": "transfer",
+                "stateMutability": "nonpayable",
+                "inputs": [
+                    {"name": "to", "type": "address"},
+                    {"name": "amount", "type": "uint256"},
+                ],
+                "outputs": [{"name": "", "type": "bool"}],
+            }
+        ]
+    )
+    calldata = (
+        "0xa9059cbb"
+        "00000000000000000000000091364516d3cad16e1666261dbdbb39c881dbe9ee"
+        "00000000000000000000000000000000000000000000000000000000000003e8"
+    )
+    signature, args = shadow_decode.decode_function_input(abi, calldata)
+    assert signature == "transfer(address,uint256)"
+    assert args == {"to": "0x91364516d3cad16e1666261dbdbb39c881dbe9ee", "amount": "1000"}
+    assert shadow_decode.decode_function_input(abi, "0xdeadbeef") is None
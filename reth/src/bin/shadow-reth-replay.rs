@@ -0,0 +1,67 @@
+use std::{path::PathBuf, sync::Arc};
+
+use clap::Parser;
+use eyre::eyre;
+use reth::args::utils::chain_value_parser;
+use reth_chainspec::ChainSpec;
+use reth_db::{mdbx::DatabaseArguments, open_db_read_only};
+use reth_provider::{
+    providers::StaticFileProvider, BlockReader, ProviderFactory, TransactionVariant,
+};
+use shadow_reth::{replay::replay_block, sink::JsonlSink, store};
+
+/// Replays historical blocks with the shadow contracts' code, reading
+/// the state from a reth data directory instead of over RPC.
+///
+/// The node can keep running, the database is opened read-only.
+#[derive(Parser)]
+struct Args {
+    /// The reth data directory, e.g. ~/.local/share/reth/mainnet
+    #[arg(long)]
+    datadir: PathBuf,
+
+    /// The chain the data directory is for
+    #[arg(long, default_value = "mainnet", value_parser = chain_value_parser)]
+    chain: Arc<ChainSpec>,
+
+    /// The first block to replay
+    #[arg(long)]
+    from_block: u64,
+
+    /// The last block to replay, inclusive
+    #[arg(long)]
+    to_block: u64,
+
+    /// The directory containing shadow.json
+    #[arg(long, default_value = ".")]
+    store_path: PathBuf,
+
+    /// The file the shadow logs are appended to. Defaults to stdout.
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+fn main() -> eyre::Result<()> {
+    let args = Args::parse();
+    let contracts = store::load(&args.store_path)?;
+    let mut sink = JsonlSink::open(args.out.as_deref())?;
+
+    let db = open_db_read_only(
+        &args.datadir.join("db"),
+        DatabaseArguments::new(Default::default()),
+    )?;
+    let factory = ProviderFactory::new(
+        db,
+        args.chain.clone(),
+        StaticFileProvider::read_only(args.datadir.join("static_files"))?,
+    );
+
+    for number in args.from_block..=args.to_block {
+        let block = factory
+            .sealed_block_with_senders(number.into(), TransactionVariant::WithHash)?
+            .ok_or_else(|| eyre!("Block {} not found", number))?;
+        let logs = replay_block(&factory, &args.chain, &block, &contracts)?;
+        sink.write_logs(&logs)?;
+    }
+    Ok(())
+}
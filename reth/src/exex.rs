@@ -0,0 +1,50 @@
+use eyre::Result;
+use reth_exex::{ExExContext, ExExEvent};
+use reth_node_api::FullNodeComponents;
+
+use crate::{replay::replay_block, sink::JsonlSink, store::ShadowContract};
+
+/// Replays every block the node commits with the shadow contracts' code,
+/// and writes the shadow logs to the sink.
+///
+/// When blocks are reorged out, a `reverted` record with their numbers is
+/// written first, so consumers can drop the logs they emitted.
+///
+/// A block that fails to replay is logged and recorded with a `failed`
+/// record instead of stopping the extension, which would take the node
+/// down with it.
+pub async fn run<Node: FullNodeComponents>(
+    mut ctx: ExExContext<Node>,
+    contracts: Vec<ShadowContract>,
+    mut sink: JsonlSink,
+) -> Result<()> {
+    while let Some(notification) = ctx.notifications.recv().await {
+        if let Some(reverted) = notification.reverted_chain() {
+            let blocks: Vec<u64> = reverted.blocks().keys().copied().collect();
+            if let Err(e) = sink.write_reverted(&blocks) {
+                tracing::error!("Error writing reverted blocks {:?}: {}", blocks, e);
+            }
+        }
+
+        if let Some(committed) = notification.committed_chain() {
+            for block in committed.blocks_iter() {
+                let written =
+                    match replay_block(ctx.provider(), &ctx.config.chain, block, &contracts) {
+                        Ok(logs) => sink.write_logs(&logs),
+                        Err(e) => {
+                            tracing::error!("Error replaying block {}: {}", block.number, e);
+                            sink.write_failed(block.number, &e.to_string())
+                        }
+                    };
+                if let Err(e) = written {
+                    tracing::error!("Error writing the logs of block {}: {}", block.number, e);
+                }
+            }
+
+            // Let the node prune the blocks we're done with
+            ctx.events
+                .send(ExExEvent::FinishedHeight(committed.tip().number))?;
+        }
+    }
+    Ok(())
+}
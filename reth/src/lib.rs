@@ -0,0 +1,12 @@
+//! Runs the shadow replay against a local reth node.
+//!
+//! The shadow fork normally replays blocks over RPC, which is too slow to
+//! replay every transaction. A node operator already has the state locally,
+//! so this crate re-executes blocks with the shadow contracts' code straight
+//! from reth's database, either as an execution extension (ExEx) that follows
+//! the chain head, or over a range of historical blocks.
+
+pub mod exex;
+pub mod replay;
+pub mod sink;
+pub mod store;
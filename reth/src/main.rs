@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser};
+use reth::cli::Cli;
+use reth_node_ethereum::EthereumNode;
+use shadow_reth::{exex, sink::JsonlSink, store};
+
+/// The shadow options, added to `reth node`.
+#[derive(Debug, Clone, Args)]
+struct ShadowArgs {
+    /// The directory containing shadow.json
+    #[arg(long = "shadow.store-path", default_value = ".")]
+    store_path: PathBuf,
+
+    /// The file the shadow logs are appended to, as JSON lines
+    #[arg(long = "shadow.out", default_value = "shadow-logs.jsonl")]
+    out: PathBuf,
+}
+
+/// Runs a reth node with the shadow replay installed as an execution
+/// extension, e.g. `shadow-reth node --shadow.store-path contracts`.
+fn main() -> eyre::Result<()> {
+    Cli::<ShadowArgs>::parse().run(|builder, args| async move {
+        let contracts = store::load(&args.store_path)?;
+        let sink = JsonlSink::open(Some(&args.out))?;
+        println!(
+            "Shadowing {} contracts, writing logs to {}",
+            contracts.len(),
+            args.out.display()
+        );
+
+        let handle = builder
+            .node(EthereumNode::default())
+            .install_exex("shadow", move |ctx| async move {
+                Ok(exex::run(ctx, contracts, sink))
+            })
+            .launch()
+            .await?;
+        handle.wait_for_node_exit().await
+    })
+}
@@ -0,0 +1,114 @@
+use eyre::{eyre, Result};
+use reth_chainspec::ChainSpec;
+use reth_evm::ConfigureEvmEnv;
+use reth_evm_ethereum::EthEvmConfig;
+use reth_primitives::{keccak256, SealedBlockWithSenders, U256};
+use reth_provider::{HeaderProvider, StateProviderFactory};
+use reth_revm::{
+    database::StateProviderDatabase,
+    db::CacheDB,
+    primitives::{
+        AccountInfo, BlockEnv, Bytecode, CfgEnv, CfgEnvWithHandlerCfg, EnvWithHandlerCfg,
+        ResultAndState, SpecId, TxEnv,
+    },
+    Database, DatabaseCommit, Evm,
+};
+
+use crate::{sink::ShadowLog, store::ShadowContract};
+
+/// Re-executes a block with the shadow contracts' code, and returns the
+/// logs the shadow contracts emitted.
+///
+/// Every transaction in the block is replayed on top of the canonical
+/// state of the parent block, read from the node's database. Unlike the
+/// RPC shadow fork, state changes made by the shadow code don't carry
+/// over to the next block, so the shadow state never drifts from mainnet.
+pub fn replay_block<P>(
+    provider: &P,
+    chain_spec: &ChainSpec,
+    block: &SealedBlockWithSenders,
+    contracts: &[ShadowContract],
+) -> Result<Vec<ShadowLog>>
+where
+    P: StateProviderFactory + HeaderProvider,
+{
+    if contracts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Start from the parent state, with the shadow code in place
+    let parent = block
+        .number
+        .checked_sub(1)
+        .ok_or_else(|| eyre!("Cannot replay the genesis block"))?;
+    let state = provider.history_by_block_number(parent)?;
+    let mut db = CacheDB::new(StateProviderDatabase::new(state));
+    for contract in contracts {
+        let info = Database::basic(&mut db, contract.address)?.unwrap_or_default();
+        db.insert_account_info(
+            contract.address,
+            AccountInfo {
+                code_hash: keccak256(&contract.code),
+                code: Some(Bytecode::new_raw(contract.code.clone())),
+                ..info
+            },
+        );
+    }
+
+    // Set up the block environment like the node does, without the
+    // contract size limit the shadow code may exceed
+    let total_difficulty = provider
+        .header_td_by_number(block.number)?
+        .unwrap_or(U256::ZERO);
+    let mut cfg = CfgEnvWithHandlerCfg::new_with_spec_id(CfgEnv::default(), SpecId::LATEST);
+    let mut block_env = BlockEnv::default();
+    EthEvmConfig::fill_cfg_and_block_env(
+        &mut cfg,
+        &mut block_env,
+        chain_spec,
+        &block.header,
+        total_difficulty,
+    );
+    cfg.limit_contract_code_size = Some(usize::MAX);
+    let mut evm = Evm::builder()
+        .with_db(&mut db)
+        .with_env_with_handler_cfg(EnvWithHandlerCfg::new_with_cfg_env(
+            cfg,
+            block_env,
+            TxEnv::default(),
+        ))
+        .build();
+
+    // Replay the transactions, collecting the shadow logs
+    let mut logs = Vec::new();
+    let mut log_index = 0;
+    for (transaction_index, (sender, transaction)) in block.transactions_with_sender().enumerate() {
+        EthEvmConfig::fill_tx_env(evm.tx_mut(), transaction, *sender);
+        let ResultAndState { result, state } = evm.transact().map_err(|e| {
+            eyre!(
+                "Error replaying transaction {}: {:?}",
+                transaction.hash(),
+                e
+            )
+        })?;
+        evm.db_mut().commit(state);
+
+        for log in result.logs() {
+            if let Some(contract) = contracts.iter().find(|c| c.address == log.address) {
+                logs.push(ShadowLog {
+                    contract: contract.name.clone(),
+                    address: log.address,
+                    topics: log.topics().to_vec(),
+                    data: log.data.data.clone(),
+                    block_number: block.number,
+                    block_hash: block.hash(),
+                    transaction_hash: transaction.hash(),
+                    transaction_index: transaction_index as u64,
+                    log_index,
+                });
+            }
+            log_index += 1;
+        }
+    }
+    Ok(logs)
+}
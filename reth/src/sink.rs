@@ -0,0 +1,82 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use eyre::Result;
+use reth_primitives::{Address, Bytes, B256};
+use serde::Serialize;
+
+/// A log emitted by a shadow contract during the replay.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowLog {
+    /// The name of the shadow contract that emitted the log
+    pub contract: String,
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub transaction_hash: B256,
+    pub transaction_index: u64,
+    /// The index of the log in the block
+    pub log_index: u64,
+}
+
+/// A record written to the output, one JSON object per line.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Record<'a> {
+    /// A shadow log
+    Log(&'a ShadowLog),
+    /// The logs of these blocks are no longer canonical and should be dropped
+    Reverted { blocks: &'a [u64] },
+    /// The block couldn't be replayed, so its shadow logs are missing
+    Failed { block: u64, error: &'a str },
+}
+
+/// Writes shadow logs as JSON lines to stdout or a file.
+pub struct JsonlSink {
+    out: Box<dyn Write + Send>,
+}
+
+impl JsonlSink {
+    /// Appends to the file at the given path, or writes to stdout.
+    pub fn open(path: Option<&Path>) -> Result<Self> {
+        let out: Box<dyn Write + Send> = match path {
+            Some(path) => Box::new(BufWriter::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => Box::new(io::stdout()),
+        };
+        Ok(Self { out })
+    }
+
+    pub fn write_logs(&mut self, logs: &[ShadowLog]) -> Result<()> {
+        for log in logs {
+            self.write(&Record::Log(log))?;
+        }
+        Ok(self.out.flush()?)
+    }
+
+    /// Tells consumers to drop the logs of reverted blocks.
+    pub fn write_reverted(&mut self, blocks: &[u64]) -> Result<()> {
+        self.write(&Record::Reverted { blocks })?;
+        Ok(self.out.flush()?)
+    }
+
+    /// Records a block that couldn't be replayed, so it can be replayed
+    /// again with `shadow-reth-replay` once the cause is fixed.
+    pub fn write_failed(&mut self, block: u64, error: &str) -> Result<()> {
+        self.write(&Record::Failed { block, error })?;
+        Ok(self.out.flush()?)
+    }
+
+    fn write(&mut self, record: &Record) -> Result<()> {
+        serde_json::to_writer(&mut self.out, record)?;
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+}
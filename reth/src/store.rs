@@ -0,0 +1,109 @@
+use std::{fs, path::Path};
+
+use eyre::{eyre, Result};
+use reth_primitives::{Address, Bytes};
+use serde::Deserialize;
+use shadow_decode_core::migrations;
+
+/// A shadow contract, as stored in `shadow.json` by `shadow deploy`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShadowContract {
+    /// The name of the shadow contract, e.g. `UniswapV2Pair.sol:UniswapV2Pair`
+    pub name: String,
+    /// The address of the shadow contract
+    pub address: Address,
+    /// The runtime bytecode of the shadow contract
+    pub code: Bytes,
+}
+
+#[derive(Deserialize)]
+struct ShadowFile {
+    contracts: Vec<StoredContract>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredContract {
+    file_name: String,
+    contract_name: String,
+    address: String,
+    runtime_bytecode: String,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Reads the enabled shadow contracts from the `shadow.json` in a directory.
+///
+/// Older files are upgraded in memory with the CLI's migrations. Files
+/// written by a newer `shadow` are rejected.
+pub fn load(store_path: &Path) -> Result<Vec<ShadowContract>> {
+    let file_path = store_path.join("shadow.json");
+    let contents = fs::read_to_string(&file_path)
+        .map_err(|e| eyre!("Error reading {}: {}", file_path.display(), e))?;
+    parse(&contents)
+}
+
+fn parse(contents: &str) -> Result<Vec<ShadowContract>> {
+    let (contents, _) =
+        migrations::migrate(serde_json::from_str(contents)?).map_err(|e| eyre!(e))?;
+    let file: ShadowFile = serde_json::from_value(contents)?;
+    file.contracts
+        .into_iter()
+        .filter(|c| c.enabled)
+        .map(|c| {
+            Ok(ShadowContract {
+                name: format!("{}:{}", c.file_name, c.contract_name),
+                address: c.address.parse()?,
+                code: Bytes::from(hex::decode(c.runtime_bytecode.trim_start_matches("0x"))?),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_shadow_file() {
+        let contents = r#"{
+            "version": 2,
+            "contracts": [
+                {
+                    "fileName": "WETH9.sol",
+                    "contractName": "WETH9",
+                    "address": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                    "runtimeBytecode": "6080"
+                },
+                {
+                    "fileName": "Pair.sol",
+                    "contractName": "Pair",
+                    "address": "0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc",
+                    "runtimeBytecode": "60ff",
+                    "enabled": false
+                }
+            ]
+        }"#;
+        let contracts = parse(contents).unwrap();
+        assert_eq!(contracts.len(), 1);
+        assert_eq!(contracts[0].name, "WETH9.sol:WETH9");
+        assert_eq!(
+            contracts[0].address,
+            "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"
+                .parse::<Address>()
+                .unwrap()
+        );
+        assert_eq!(contracts[0].code, Bytes::from(vec![0x60, 0x80]));
+
+        assert!(parse("[]").unwrap().is_empty());
+        let newer = format!(
+            r#"{{"version": {}, "contracts": []}}"#,
+            migrations::CURRENT_VERSION + 1
+        );
+        assert!(parse(&newer).is_err());
+    }
+}